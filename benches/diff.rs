@@ -0,0 +1,62 @@
+//! Benchmarks for content comparison on large pages
+//!
+//! Run with `cargo bench --bench diff`. The page sizes mirror what the daemon
+//! sees in practice: a typical product page, and multi-megabyte HTML/JSON dumps.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+use std::hint::black_box;
+use web_watcher_alert::diff::{get_diff, has_changed, DiffOptions};
+
+/// Build a page of roughly `bytes` bytes made of indented HTML-ish lines
+fn page(bytes: usize) -> String {
+    let mut content = String::with_capacity(bytes + 128);
+    let mut i = 0;
+    while content.len() < bytes {
+        content.push_str(&format!("    <li class=\"item\">Item {} costs ${}.99</li>\n", i, i % 500));
+        i += 1;
+    }
+    content
+}
+
+/// Same page with one line changed in the middle
+fn changed_page(original: &str) -> String {
+    let middle = original.len() / 2;
+    let line_start = original[..middle].rfind('\n').map_or(0, |i| i + 1);
+    let mut content = original.to_string();
+    content.insert_str(line_start, "    <li class=\"item\">New item, just restocked</li>\n");
+    content
+}
+
+fn bench_has_changed(c: &mut Criterion) {
+    let mut group = c.benchmark_group("has_changed");
+    for size in [100 * 1024, 1024 * 1024, 8 * 1024 * 1024] {
+        let old = page(size);
+        let same_but_reindented = old.replace("    <li", "\t<li");
+        let changed = changed_page(&old);
+
+        group.bench_with_input(BenchmarkId::new("whitespace_only", size), &size, |b, _| {
+            b.iter(|| has_changed(black_box(&old), black_box(&same_but_reindented)))
+        });
+        group.bench_with_input(BenchmarkId::new("one_line_changed", size), &size, |b, _| {
+            b.iter(|| has_changed(black_box(&old), black_box(&changed)))
+        });
+    }
+    group.finish();
+}
+
+fn bench_get_diff(c: &mut Criterion) {
+    let mut group = c.benchmark_group("get_diff");
+    group.sample_size(20);
+    for size in [100 * 1024, 1024 * 1024, 8 * 1024 * 1024] {
+        let old = page(size);
+        let changed = changed_page(&old);
+
+        group.bench_with_input(BenchmarkId::new("one_line_changed", size), &size, |b, _| {
+            b.iter(|| get_diff(black_box(&old), black_box(&changed), DiffOptions::default()))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_has_changed, bench_get_diff);
+criterion_main!(benches);
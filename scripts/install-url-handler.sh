@@ -0,0 +1,95 @@
+#!/bin/bash
+
+# Web Watcher Alert - Link Handler Installation Script (macOS)
+# Installs a small AppleScript app that opens webwatcher:// links, so Safari
+# share-sheet shortcuts and bookmarklets can add watchers
+
+set -e  # Exit on error
+
+# Colors for output
+RED='\033[0;31m'
+GREEN='\033[0;32m'
+YELLOW='\033[1;33m'
+BLUE='\033[0;34m'
+NC='\033[0m' # No Color
+
+echo -e "${BLUE}╔════════════════════════════════════════════╗${NC}"
+echo -e "${BLUE}║ Web Watcher Alert - Link Handler Installer ║${NC}"
+echo -e "${BLUE}╚════════════════════════════════════════════╝${NC}"
+echo ""
+
+# Get absolute paths
+SCRIPT_DIR="$( cd "$( dirname "${BASH_SOURCE[0]}" )" && pwd )"
+PROJECT_DIR="$(dirname "$SCRIPT_DIR")"
+BINARY_PATH="$PROJECT_DIR/target/release/web-watcher-alert"
+APP_PATH="$HOME/Applications/Web Watcher Links.app"
+PLIST="$APP_PATH/Contents/Info.plist"
+LSREGISTER="/System/Library/Frameworks/CoreServices.framework/Frameworks/LaunchServices.framework/Support/lsregister"
+
+echo -e "${YELLOW}[1/3]${NC} Checking prerequisites..."
+
+if [ "$(uname)" != "Darwin" ]; then
+    echo -e "${RED}✗ Error: The link handler is for macOS${NC}"
+    exit 1
+fi
+
+if [ ! -f "$BINARY_PATH" ]; then
+    echo -e "${RED}✗ Error: Binary not found at $BINARY_PATH${NC}"
+    echo -e "${YELLOW}  Please build the project first:${NC}"
+    echo -e "  cd $PROJECT_DIR"
+    echo -e "  cargo build --release"
+    exit 1
+fi
+echo -e "${GREEN}✓${NC} Binary found"
+
+echo ""
+echo -e "${YELLOW}[2/3]${NC} Building the handler app..."
+
+# Links with keywords run in the background (the result is shown as a
+# notification); links without keywords open the add form in Terminal
+mkdir -p "$HOME/Applications"
+osacompile -o "$APP_PATH" <<APPLESCRIPT
+on open location theURL
+    set command to quoted form of "$BINARY_PATH" & " " & quoted form of theURL
+    if theURL contains "keywords=" or theURL does not start with "webwatcher://add" then
+        try
+            set output to do shell script command
+            display notification output with title "Web Watcher Alert"
+        on error message
+            display notification message with title "Web Watcher Alert"
+        end try
+    else
+        tell application "Terminal"
+            activate
+            do script command
+        end tell
+    end if
+end open location
+APPLESCRIPT
+
+/usr/libexec/PlistBuddy -c "Add :CFBundleIdentifier string com.webwatcheralert.links" "$PLIST" 2>/dev/null || \
+    /usr/libexec/PlistBuddy -c "Set :CFBundleIdentifier com.webwatcheralert.links" "$PLIST"
+/usr/libexec/PlistBuddy -c "Delete :CFBundleURLTypes" "$PLIST" 2>/dev/null || true
+/usr/libexec/PlistBuddy \
+    -c "Add :CFBundleURLTypes array" \
+    -c "Add :CFBundleURLTypes:0 dict" \
+    -c "Add :CFBundleURLTypes:0:CFBundleURLName string Web Watcher Alert link" \
+    -c "Add :CFBundleURLTypes:0:CFBundleURLSchemes array" \
+    -c "Add :CFBundleURLTypes:0:CFBundleURLSchemes:0 string webwatcher" \
+    "$PLIST"
+echo -e "${GREEN}✓${NC} Handler app: $APP_PATH"
+
+echo ""
+echo -e "${YELLOW}[3/3]${NC} Registering the webwatcher:// scheme..."
+"$LSREGISTER" -f "$APP_PATH"
+echo -e "${GREEN}✓${NC} Scheme registered"
+
+echo ""
+echo -e "${GREEN}╔════════════════════════════════════════════╗${NC}"
+echo -e "${GREEN}║          Installation Complete! ✓          ║${NC}"
+echo -e "${GREEN}╚════════════════════════════════════════════╝${NC}"
+echo ""
+echo -e "${BLUE}Try it:${NC}"
+echo -e "  open 'webwatcher://add?url=https://example.com'"
+echo -e "  open 'webwatcher://add?url=https://example.com&keywords=sale'"
+echo ""
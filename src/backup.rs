@@ -0,0 +1,232 @@
+//! Backup and restore module
+//!
+//! Packs the config, cache and data directories into a single .tar.gz
+//! archive so users can migrate machines or recover from accidental
+//! deletions, and unpacks such an archive back into place.
+
+use anyhow::{Context, Result};
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+use std::fs::{self, File};
+use std::path::{Component, Path, PathBuf};
+
+use crate::config::Config;
+
+/// Subdirectories that are never included in a backup
+const EXCLUDED_DIRS: &[&str] = &["logs", "backups"];
+
+/// The directories stored in an archive, keyed by their name inside it
+fn archive_roots() -> Result<Vec<(&'static str, PathBuf)>> {
+    Ok(vec![
+        ("config", Config::config_dir()?),
+        ("cache", Config::cache_dir()?),
+        ("data", Config::data_dir()?),
+    ])
+}
+
+/// Default archive file name, e.g. web-watcher-alert-backup-20250101-120000.tar.gz
+pub fn default_backup_name() -> String {
+    format!(
+        "web-watcher-alert-backup-{}.tar.gz",
+        chrono::Local::now().format("%Y%m%d-%H%M%S")
+    )
+}
+
+/// Write a backup archive to `dest`. Returns the number of files stored.
+pub fn create_backup(dest: &Path) -> Result<usize> {
+    let file = File::create(dest)
+        .with_context(|| format!("Failed to create backup file: {}", dest.display()))?;
+    let mut builder = tar::Builder::new(GzEncoder::new(file, Compression::default()));
+
+    let mut count = 0;
+    for (name, dir) in archive_roots()? {
+        if dir.exists() {
+            count += append_dir(&mut builder, &dir, Path::new(name), true)?;
+        }
+    }
+
+    builder
+        .into_inner()
+        .and_then(|encoder| encoder.finish())
+        .context("Failed to finish backup archive")?;
+
+    Ok(count)
+}
+
+/// Recursively add a directory's files to the archive
+fn append_dir(
+    builder: &mut tar::Builder<GzEncoder<File>>,
+    dir: &Path,
+    archive_dir: &Path,
+    top_level: bool,
+) -> Result<usize> {
+    let mut count = 0;
+
+    let entries = fs::read_dir(dir)
+        .with_context(|| format!("Failed to read directory: {}", dir.display()))?;
+
+    for entry in entries {
+        let entry = entry?;
+        let path = entry.path();
+        let name = entry.file_name();
+        let archive_path = archive_dir.join(&name);
+
+        // Links aren't followed: one could loop back to a parent directory
+        // (and restoring refuses them anyway)
+        let file_type = entry
+            .file_type()
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        if file_type.is_dir() {
+            // Skip logs and old backups at the top of each root
+            if top_level && EXCLUDED_DIRS.iter().any(|d| name == *d) {
+                continue;
+            }
+            count += append_dir(builder, &path, &archive_path, false)?;
+        } else if file_type.is_file() {
+            builder
+                .append_path_with_name(&path, &archive_path)
+                .with_context(|| format!("Failed to add {} to backup", path.display()))?;
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
+
+/// Extract a backup archive over the current config, cache and data.
+/// The whole archive is checked first, so a bad entry restores nothing.
+/// Returns the number of files restored.
+pub fn restore_backup(archive: &Path) -> Result<usize> {
+    let roots = archive_roots()?;
+
+    let mut tar = open_archive(archive)?;
+    for entry in tar.entries().context("Failed to read backup archive")? {
+        let mut entry = entry.context("Corrupt entry in backup archive")?;
+        target(&entry, &roots)?;
+        // Read the contents too, so a truncated archive is caught here
+        std::io::copy(&mut entry, &mut std::io::sink()).context("Corrupt entry in backup archive")?;
+    }
+
+    let mut tar = open_archive(archive)?;
+    let mut count = 0;
+    for entry in tar.entries().context("Failed to read backup archive")? {
+        let mut entry = entry.context("Corrupt entry in backup archive")?;
+        let target = target(&entry, &roots)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory: {}", parent.display()))?;
+        }
+
+        entry
+            .unpack(&target)
+            .with_context(|| format!("Failed to restore {}", target.display()))?;
+        count += 1;
+    }
+
+    Ok(count)
+}
+
+fn open_archive(archive: &Path) -> Result<tar::Archive<GzDecoder<File>>> {
+    let file = File::open(archive)
+        .with_context(|| format!("Failed to open backup file: {}", archive.display()))?;
+    Ok(tar::Archive::new(GzDecoder::new(file)))
+}
+
+/// Where an archive entry is restored to, or an error if it must not be
+fn target(entry: &tar::Entry<GzDecoder<File>>, roots: &[(&str, PathBuf)]) -> Result<PathBuf> {
+    let path = entry.path()?.into_owned();
+
+    // Only files and directories: a link could point later entries (or
+    // the restored files) outside the target directories
+    let kind = entry.header().entry_type();
+    if !matches!(kind, tar::EntryType::Regular | tar::EntryType::Directory) {
+        anyhow::bail!("Unsupported entry in backup archive ({:?}): {}", kind, path.display());
+    }
+
+    // Refuse anything that could escape the target directories
+    if path
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        anyhow::bail!("Unsafe path in backup archive: {}", path.display());
+    }
+
+    let mut components = path.components();
+    let root_name = components
+        .next()
+        .and_then(|c| c.as_os_str().to_str())
+        .unwrap_or_default()
+        .to_string();
+    let relative = components.as_path();
+
+    let Some((_, root_dir)) = roots.iter().find(|(name, _)| *name == root_name) else {
+        anyhow::bail!("Unexpected path in backup archive: {}", path.display());
+    };
+    Ok(root_dir.join(relative))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Write an archive with the entries `add` appends
+    fn archive(name: &str, add: impl FnOnce(&mut tar::Builder<GzEncoder<File>>)) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("web-watcher-alert-{}-{}.tar.gz", name, std::process::id()));
+        let mut builder = tar::Builder::new(GzEncoder::new(File::create(&path).unwrap(), Compression::default()));
+        add(&mut builder);
+        builder.into_inner().unwrap().finish().unwrap();
+        path
+    }
+
+    #[test]
+    fn restore_refuses_links() {
+        let path = archive("link", |builder| {
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder.append_link(&mut header, "data/history", "/etc").unwrap();
+        });
+        let error = restore_backup(&path).unwrap_err();
+        assert!(error.to_string().contains("Unsupported entry"), "{}", error);
+    }
+
+    #[test]
+    fn restore_refuses_paths_outside_the_archive_roots() {
+        let path = archive("outside", |builder| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(2);
+            builder.append_data(&mut header, "elsewhere/file", &b"hi"[..]).unwrap();
+        });
+        let error = restore_backup(&path).unwrap_err();
+        assert!(error.to_string().contains("Unexpected path"), "{}", error);
+    }
+
+    #[test]
+    fn restore_checks_the_whole_archive_first() {
+        let name = format!("restored-before-a-bad-entry-{}", std::process::id());
+        let path = archive("partial", |builder| {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(2);
+            builder.append_data(&mut header, format!("data/{}", name), &b"hi"[..]).unwrap();
+            let mut header = tar::Header::new_gnu();
+            header.set_entry_type(tar::EntryType::Symlink);
+            header.set_size(0);
+            builder.append_link(&mut header, "data/history", "/etc").unwrap();
+        });
+        assert!(restore_backup(&path).is_err());
+        assert!(!Config::data_dir().unwrap().join(&name).exists());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn backups_skip_links() {
+        let dir = std::env::temp_dir().join(format!("web-watcher-alert-loop-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        fs::write(dir.join("sub/file"), "hi").unwrap();
+        std::os::unix::fs::symlink(&dir, dir.join("sub/loop")).unwrap();
+
+        let dest = dir.with_extension("tar.gz");
+        let mut builder = tar::Builder::new(GzEncoder::new(File::create(&dest).unwrap(), Compression::default()));
+        assert_eq!(append_dir(&mut builder, &dir, Path::new("data"), true).unwrap(), 1);
+    }
+}
@@ -0,0 +1,169 @@
+//! Timing a watcher's check, stage by stage
+//!
+//! `bench <url>` runs the stages of a check several times and reports how
+//! long each took, to tell whether a slow check is the network (fetch), HTML
+//! parsing (the CSS selector, main-content extraction), comparing with the
+//! cached copy (diff) or the keyword search (match). Nothing is notified and
+//! neither the cache nor the history is written.
+
+use anyhow::{Context, Result};
+use serde::Serialize;
+use std::time::{Duration, Instant};
+
+use crate::cache;
+use crate::diff;
+use crate::domains::{self, SiteSettings};
+use crate::fetcher::{self, Fetched, Page};
+use crate::matcher::{Keyword, KeywordMatcher};
+use crate::watcher::Watcher;
+
+/// Most runs a benchmark does
+pub const MAX_RUNS: usize = 50;
+
+/// The stages of a check, in order
+pub const STAGES: &[&str] = &["fetch", "select", "diff", "match"];
+
+/// How long each stage of one run took
+#[derive(Debug, Clone, Default)]
+pub struct Run {
+    pub fetch: Duration,
+    pub select: Duration,
+    pub diff: Duration,
+    pub find: Duration,
+    /// Size of the page as downloaded
+    pub bytes: u64,
+}
+
+impl Run {
+    fn stage(&self, name: &str) -> Duration {
+        match name {
+            "fetch" => self.fetch,
+            "select" => self.select,
+            "diff" => self.diff,
+            _ => self.find,
+        }
+    }
+
+    pub fn total(&self) -> Duration {
+        self.fetch + self.select + self.diff + self.find
+    }
+}
+
+/// Timings of one stage over all runs
+#[derive(Debug, Clone, Serialize)]
+pub struct StageSummary {
+    pub stage: String,
+    pub min_ms: f64,
+    pub median_ms: f64,
+    pub max_ms: f64,
+}
+
+/// Run the stages of a check of `watcher` `runs` times. A run that fails
+/// (or gets a page over the size cap, which is never parsed) stops the
+/// benchmark with the error.
+pub async fn run(
+    watcher: &Watcher,
+    keywords: &[Keyword],
+    site: &SiteSettings,
+    max_page_size: Option<u64>,
+    runs: usize,
+) -> Result<Vec<Run>> {
+    let matcher = KeywordMatcher::new(keywords);
+    let cached = cache::read_cache(&watcher.full_cache_path()?)?;
+
+    let mut results = Vec::new();
+    for _ in 0..runs {
+        let mut run = Run::default();
+
+        let started = Instant::now();
+        let Fetched { page, meta } = fetcher::fetch_page(&watcher.url, &site.request, max_page_size, keywords)
+            .await
+            .context("Failed to fetch URL")?;
+        run.fetch = started.elapsed();
+        run.bytes = meta.content_length.unwrap_or_default();
+        let content = match page {
+            Page::Text(content) => content,
+            Page::Oversized(digest) => anyhow::bail!(
+                "The page is over the size limit ({} bytes): it's only hashed, there's nothing to parse or match",
+                digest.size
+            ),
+        };
+
+        let started = Instant::now();
+        let content = domains::watched_part(site, content)?;
+        run.select = started.elapsed();
+
+        // Without a cached copy (never checked) the page is compared with
+        // itself, which still normalizes it
+        let started = Instant::now();
+        let old = cached.as_deref().unwrap_or(&content);
+        if diff::has_changed(old, &content) {
+            let _ = diff::get_diff(old, &content, watcher.diff);
+        }
+        run.diff = started.elapsed();
+
+        let started = Instant::now();
+        let _ = matcher.find(&content);
+        run.find = started.elapsed();
+
+        results.push(run);
+    }
+    Ok(results)
+}
+
+/// Min, median and max of each stage, and of whole runs ("total")
+pub fn summarize(runs: &[Run]) -> Vec<StageSummary> {
+    let mut names: Vec<&str> = STAGES.to_vec();
+    names.push("total");
+    names
+        .into_iter()
+        .map(|name| {
+            let mut times: Vec<Duration> = runs
+                .iter()
+                .map(|run| if name == "total" { run.total() } else { run.stage(name) })
+                .collect();
+            times.sort();
+            // Milliseconds, to the microsecond
+            let ms = |duration: Option<&Duration>| {
+                duration.map_or(0.0, |d| (d.as_secs_f64() * 1_000_000.0).round() / 1000.0)
+            };
+            StageSummary {
+                stage: name.to_string(),
+                min_ms: ms(times.first()),
+                median_ms: ms(times.get(times.len() / 2)),
+                max_ms: ms(times.last()),
+            }
+        })
+        .collect()
+}
+
+/// Which stage takes most of the time, and what that suggests
+pub fn verdict(summary: &[StageSummary]) -> Option<String> {
+    let total: f64 = summary.iter().filter(|s| s.stage != "total").map(|s| s.median_ms).sum();
+    let slowest = summary
+        .iter()
+        .filter(|s| s.stage != "total")
+        .max_by(|a, b| a.median_ms.total_cmp(&b.median_ms))?;
+    if total <= 0.0 {
+        return None;
+    }
+    let share = slowest.median_ms / total * 100.0;
+    let hint = match slowest.stage.as_str() {
+        "fetch" => "the network or the server (--lightweight skips downloading unchanged pages)",
+        "select" => "parsing the HTML for the CSS selector",
+        "diff" => "comparing with the cached copy (a large page: a selector can narrow it down)",
+        _ => "the keyword search (many keywords or expensive regexes)",
+    };
+    Some(format!("{:.0}% of a check is {}: {}", share, slowest.stage, hint))
+}
+
+/// A duration for the report: 1.23s, 45.6ms or 789µs
+pub fn format_ms(ms: f64) -> String {
+    if ms >= 1000.0 {
+        format!("{:.2}s", ms / 1000.0)
+    } else if ms >= 1.0 {
+        format!("{:.1}ms", ms)
+    } else {
+        format!("{:.0}µs", ms * 1000.0)
+    }
+}
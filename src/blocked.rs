@@ -0,0 +1,59 @@
+//! Recognizing block pages
+//!
+//! Sites behind bot protection sometimes answer with a challenge or CAPTCHA
+//! page instead of the real one. Treated as content, that page would
+//! replace the cached copy (and its disappearance would look like a change
+//! later on), so a check that gets one fails as "blocked" instead: the user
+//! is told once, and watchers with `pause_when_blocked` are disabled until
+//! they're re-enabled by hand.
+
+use reqwest::header::HeaderMap;
+
+/// Markers that only appear on block pages, whatever their size
+const INTERSTITIAL_MARKERS: &[(&str, &str)] = &[
+    ("/cdn-cgi/challenge-platform/", "Cloudflare challenge"),
+    ("window._cf_chl_opt", "Cloudflare challenge"),
+    ("Attention Required! | Cloudflare", "Cloudflare block"),
+    ("captcha-delivery.com", "DataDome CAPTCHA"),
+    ("px-captcha", "PerimeterX CAPTCHA"),
+    ("errors.edgesuite.net", "Akamai access denied"),
+    ("_Incapsula_Resource", "Imperva challenge"),
+];
+
+/// CAPTCHA widgets, which also show up in forms of regular pages: they only
+/// count on an error response or a page with little else on it
+const CAPTCHA_MARKERS: &[(&str, &str)] = &[
+    ("g-recaptcha", "reCAPTCHA"),
+    ("google.com/recaptcha", "reCAPTCHA"),
+    ("h-captcha", "hCaptcha"),
+    ("hcaptcha.com/1/api.js", "hCaptcha"),
+    ("cf-turnstile", "Cloudflare Turnstile"),
+];
+
+/// Largest page that can be "just a CAPTCHA"
+const MAX_CAPTCHA_PAGE_BYTES: usize = 16 * 1024;
+
+/// What blocked the request, if the response is a block page. `body` may
+/// be the start of the page only.
+pub fn detect(status: u16, headers: &HeaderMap, body: &str) -> Option<&'static str> {
+    // Cloudflare labels its challenge responses
+    let mitigated = headers
+        .get("cf-mitigated")
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("challenge"));
+    if mitigated {
+        return Some("Cloudflare challenge");
+    }
+
+    if let Some((_, kind)) = INTERSTITIAL_MARKERS.iter().find(|(marker, _)| body.contains(marker)) {
+        return Some(kind);
+    }
+
+    let error = matches!(status, 403 | 429 | 503);
+    if error || body.len() <= MAX_CAPTCHA_PAGE_BYTES {
+        if let Some((_, kind)) = CAPTCHA_MARKERS.iter().find(|(marker, _)| body.contains(marker)) {
+            return Some(kind);
+        }
+    }
+    None
+}
@@ -0,0 +1,198 @@
+//! Browser bookmark import
+//!
+//! Reads exported bookmarks so watchers can be seeded from pages the user
+//! already keeps an eye on: the Netscape bookmarks HTML that every browser
+//! exports, Chrome's `Bookmarks` JSON file and Firefox's JSON backups.
+
+use anyhow::{Context, Result};
+use scraper::{ElementRef, Html, Selector};
+use serde_json::Value;
+use std::fs;
+use std::path::Path;
+
+/// Separator between folder names in a folder path
+pub const FOLDER_SEPARATOR: &str = "/";
+
+/// A bookmarked page
+#[derive(Debug, Clone)]
+pub struct Bookmark {
+    pub title: String,
+    pub url: String,
+    /// Folders from the top level down (empty at the top level)
+    pub folders: Vec<String>,
+}
+
+impl Bookmark {
+    /// Folder path like "Bookmarks bar/Shopping"
+    pub fn folder_path(&self) -> String {
+        self.folders.join(FOLDER_SEPARATOR)
+    }
+
+    /// Whether the bookmark is in `folder` or one of its subfolders.
+    /// `folder` is a folder name or a path, matched case-insensitively.
+    pub fn is_in(&self, folder: &str) -> bool {
+        let wanted: Vec<String> = folder
+            .split(FOLDER_SEPARATOR)
+            .map(|name| name.trim().to_lowercase())
+            .filter(|name| !name.is_empty())
+            .collect();
+        if wanted.is_empty() {
+            return true;
+        }
+
+        let folders: Vec<String> = self.folders.iter().map(|name| name.to_lowercase()).collect();
+        folders.windows(wanted.len()).any(|window| window == wanted.as_slice())
+    }
+}
+
+/// Load the web bookmarks (http/https only) from an exported file
+pub fn load(path: &Path) -> Result<Vec<Bookmark>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read bookmarks file: {}", path.display()))?;
+
+    let mut bookmarks = Vec::new();
+    if content.trim_start().starts_with('{') {
+        let json: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse bookmarks JSON: {}", path.display()))?;
+        if let Some(roots) = json.get("roots").and_then(Value::as_object) {
+            // Chrome: bookmark_bar, other and synced roots
+            for root in roots.values() {
+                collect_json(root, &mut Vec::new(), &mut bookmarks);
+            }
+        } else {
+            // Firefox: a single root folder
+            collect_json(&json, &mut Vec::new(), &mut bookmarks);
+        }
+    } else {
+        parse_html(&content, &mut bookmarks);
+    }
+
+    bookmarks.retain(|b| b.url.starts_with("http://") || b.url.starts_with("https://"));
+    Ok(bookmarks)
+}
+
+/// All folder paths that contain bookmarks, with how many each holds
+/// directly, in file order
+pub fn folders(bookmarks: &[Bookmark]) -> Vec<(String, usize)> {
+    let mut folders: Vec<(String, usize)> = Vec::new();
+    for bookmark in bookmarks {
+        let path = bookmark.folder_path();
+        match folders.iter_mut().find(|(p, _)| *p == path) {
+            Some((_, count)) => *count += 1,
+            None => folders.push((path, 1)),
+        }
+    }
+    folders
+}
+
+/// Walk a Chrome or Firefox JSON bookmark node
+fn collect_json(node: &Value, folders: &mut Vec<String>, bookmarks: &mut Vec<Bookmark>) {
+    let field = |name: &str| node.get(name).and_then(Value::as_str).unwrap_or_default();
+    // Chrome names pages "url" and folders "folder"; Firefox uses
+    // "text/x-moz-place" and "text/x-moz-place-container"
+    let title = match field("name") {
+        "" => field("title"),
+        name => name,
+    };
+
+    match field("type") {
+        "url" | "text/x-moz-place" => {
+            let url = match field("url") {
+                "" => field("uri"),
+                url => url,
+            };
+            bookmarks.push(Bookmark {
+                title: title.to_string(),
+                url: url.to_string(),
+                folders: folders.clone(),
+            });
+        }
+        _ => {
+            let Some(children) = node.get("children").and_then(Value::as_array) else {
+                return;
+            };
+            // Firefox's unnamed root isn't a folder the user knows about
+            let named = !title.is_empty();
+            if named {
+                folders.push(title.to_string());
+            }
+            for child in children {
+                collect_json(child, folders, bookmarks);
+            }
+            if named {
+                folders.pop();
+            }
+        }
+    }
+}
+
+/// Parse a Netscape bookmarks file: folders are `<H3>` headings followed by
+/// a `<DL>` list of their entries, pages are `<A HREF>` links
+fn parse_html(content: &str, bookmarks: &mut Vec<Bookmark>) {
+    let document = Html::parse_document(content);
+    let links = Selector::parse("a[href]").expect("valid selector");
+
+    for link in document.select(&links) {
+        // Each <DL> a link is in is the list of the folder named by the
+        // heading just before it
+        let mut folders: Vec<String> = link
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .filter(|element| element.value().name() == "dl")
+            .filter_map(|list| {
+                list.prev_siblings()
+                    .filter_map(ElementRef::wrap)
+                    .next()
+                    .filter(|heading| heading.value().name() == "h3")
+            })
+            .map(|heading| text(heading))
+            .collect();
+        folders.reverse();
+
+        bookmarks.push(Bookmark {
+            title: text(link),
+            url: link.value().attr("href").unwrap_or_default().trim().to_string(),
+            folders,
+        });
+    }
+}
+
+fn text(element: ElementRef) -> String {
+    element.text().collect::<String>().trim().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn netscape_files_keep_their_folders() {
+        let mut bookmarks = Vec::new();
+        parse_html(
+            r#"<!DOCTYPE NETSCAPE-Bookmark-file-1>
+<TITLE>Bookmarks</TITLE>
+<H1>Bookmarks</H1>
+<DL><p>
+    <DT><H3 ADD_DATE="1">Bookmarks bar</H3>
+    <DL><p>
+        <DT><H3>Shopping &amp; deals</H3>
+        <DL><p>
+            <DT><A HREF="https://shop.example.com/gpu?a=1&amp;b=2" DATA-HREF="https://wrong.example.com/">GPU &lt;restock&gt;</A>
+        </DL><p>
+        <DT><A HREF="https://news.example.com/">News</A>
+    </DL><p>
+    <DT><A DATA-HREF="https://wrong.example.com/" HREF="https://top.example.com/">Top</A>
+</DL><p>"#,
+            &mut bookmarks,
+        );
+
+        assert_eq!(bookmarks.len(), 3);
+        assert_eq!(bookmarks[0].url, "https://shop.example.com/gpu?a=1&b=2");
+        assert_eq!(bookmarks[0].title, "GPU <restock>");
+        assert_eq!(bookmarks[0].folder_path(), "Bookmarks bar/Shopping & deals");
+        assert_eq!(bookmarks[1].folder_path(), "Bookmarks bar");
+        assert_eq!(bookmarks[2].url, "https://top.example.com/");
+        assert!(bookmarks[2].folders.is_empty());
+        assert!(bookmarks[0].is_in("shopping & DEALS"));
+    }
+}
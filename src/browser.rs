@@ -0,0 +1,103 @@
+//! Opening pages in the default browser, and rendering them headless
+//!
+//! Uses the platform's opener (`open` on macOS, `xdg-open` on Linux,
+//! `start` on Windows), detached from the terminal so its output can't
+//! garble the TUI. Watchers in browser render mode run a headless
+//! Chrome/Chromium to get the page as it looks once its scripts have run.
+
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+use std::time::Duration;
+
+/// Chrome/Chromium executables, tried in order
+#[cfg(target_os = "macos")]
+const HEADLESS_COMMANDS: &[&str] = &[
+    "/Applications/Google Chrome.app/Contents/MacOS/Google Chrome",
+    "/Applications/Chromium.app/Contents/MacOS/Chromium",
+    "chromium",
+];
+#[cfg(not(target_os = "macos"))]
+const HEADLESS_COMMANDS: &[&str] = &["chromium", "chromium-browser", "google-chrome", "google-chrome-stable"];
+
+/// How long scripts may run before the DOM is taken (in browser time)
+const RENDER_BUDGET_MS: u32 = 5000;
+
+/// Open a URL in the default browser (returns once the opener started)
+pub fn open(url: &str) -> Result<()> {
+    if crate::unix_socket::is_unix_url(url) {
+        anyhow::bail!("Browsers can't open unix socket URLs: {}", url);
+    }
+
+    let mut command = if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else if cfg!(target_os = "windows") {
+        // `start` is a cmd builtin; its first quoted argument is a window title
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else {
+        Command::new("xdg-open")
+    };
+
+    let program = command.get_program().to_string_lossy().into_owned();
+    let mut child = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to open the browser ({} not found)", program))?;
+
+    // Reap the opener when it exits instead of leaving a zombie behind
+    std::thread::spawn(move || child.wait());
+    Ok(())
+}
+
+/// The first headless-capable browser that is installed
+pub fn headless_command() -> Option<&'static str> {
+    HEADLESS_COMMANDS.iter().copied().find(|command| {
+        Command::new(command)
+            .arg("--version")
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .status()
+            .is_ok_and(|status| status.success())
+    })
+}
+
+/// Load a page in a headless browser and return its DOM as HTML
+pub async fn render(url: &str, user_agent: &str, timeout: Duration) -> Result<String> {
+    let Some(program) = headless_command() else {
+        anyhow::bail!(
+            "Browser rendering needs Chrome or Chromium (none of: {})",
+            HEADLESS_COMMANDS.join(", ")
+        );
+    };
+
+    let child = tokio::process::Command::new(program)
+        .args([
+            "--headless",
+            "--disable-gpu",
+            "--no-first-run",
+            "--dump-dom",
+            &format!("--virtual-time-budget={}", RENDER_BUDGET_MS),
+            &format!("--user-agent={}", user_agent),
+            url,
+        ])
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start {}", program))?;
+
+    let output = tokio::time::timeout(timeout, child.wait_with_output())
+        .await
+        .with_context(|| format!("Rendering timed out: {}", url))?
+        .context("Failed to render page")?;
+    if !output.status.success() || output.stdout.is_empty() {
+        anyhow::bail!("The browser failed to render {}", url);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
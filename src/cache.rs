@@ -1,10 +1,19 @@
-/// Cache management module
-///
-/// Handles reading and writing cached webpage content to disk
+//! Cache management module
+//!
+//! Handles reading and writing cached webpage content to disk
+//!
+//! The daemon `verify`s the caches of the enabled watchers when it starts:
+//! a cache that a disk cleanup removed, emptied or mangled would otherwise
+//! make the next check behave like a first one (or fail on every check).
 
 use anyhow::{Context, Result};
-use std::fs;
-use std::path::Path;
+use sha2::{Digest, Sha256};
+use std::fs::{self, File};
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+
+use crate::config::EncryptionSettings;
+use crate::crypto::{self, Role};
 
 /// Read cached content from file
 pub fn read_cache(path: &Path) -> Result<Option<String>> {
@@ -13,24 +22,216 @@ pub fn read_cache(path: &Path) -> Result<Option<String>> {
         return Ok(None);
     }
 
-    // Read and return the content
-    let content = fs::read_to_string(path)
-        .with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    // Read and return the content (decrypted if it was written encrypted)
+    Ok(Some(read_text(path)?))
+}
+
+fn read_text(path: &Path) -> Result<String> {
+    let data = fs::read(path).with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    let data = open(path, &data)?;
+    String::from_utf8(data).with_context(|| format!("Cache file isn't text: {}", path.display()))
+}
+
+/// The file's name, which its encrypted content is bound to
+fn file_name(path: &Path) -> String {
+    path.file_name().map(|name| name.to_string_lossy().into_owned()).unwrap_or_default()
+}
+
+fn open(path: &Path, data: &[u8]) -> Result<Vec<u8>> {
+    crypto::open(data, Role::Cache(&file_name(path)))
+        .with_context(|| format!("Failed to read cache file: {}", path.display()))
+}
 
-    Ok(Some(content))
+fn seal(path: &Path, data: &[u8], encryption: &EncryptionSettings) -> Result<Vec<u8>> {
+    crypto::seal(data, Role::Cache(&file_name(path)), encryption)
 }
 
 /// Write content to cache file
-pub fn write_cache(path: &Path, content: &str) -> Result<()> {
+pub fn write_cache(path: &Path, content: &str, encryption: &EncryptionSettings) -> Result<()> {
+    // Full content supersedes a stored hash (the page shrank below the cap)
+    let digest_path = digest_path(path);
+    if path != digest_path && digest_path.exists() {
+        let _ = fs::remove_file(&digest_path);
+    }
+
     // Create parent directories if they don't exist
     if let Some(parent) = path.parent() {
         fs::create_dir_all(parent)
             .with_context(|| format!("Failed to create cache directory: {}", parent.display()))?;
     }
 
-    // Write content to file
-    fs::write(path, content)
+    // Write content to file (encrypted if encryption is on)
+    fs::write(path, seal(path, content.as_bytes(), encryption)?)
         .with_context(|| format!("Failed to write cache file: {}", path.display()))?;
 
     Ok(())
 }
+
+/// SHA-256 of some content, hex encoded
+pub fn content_hash(content: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// Path of the hash file stored next to a cache file (for oversized pages)
+fn digest_path(path: &Path) -> PathBuf {
+    path.with_extension("sha256")
+}
+
+/// Hash of the cached page, if any: the stored hash for an oversized page,
+/// or the SHA-256 of the cached content (streamed from disk)
+pub fn read_digest(path: &Path) -> Result<Option<String>> {
+    let digest_path = digest_path(path);
+    if digest_path.exists() {
+        let hash = read_text(&digest_path)?;
+        return Ok(Some(hash.trim().to_string()));
+    }
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut file = File::open(path)
+        .with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    let mut hasher = Sha256::new();
+    let mut head = Vec::new();
+    (&mut file)
+        .take(crypto::MAGIC_LEN)
+        .read_to_end(&mut head)
+        .with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    if crypto::is_encrypted(&head) {
+        // Encrypted content has to be decrypted whole
+        hasher.update(read_text(path)?);
+    } else {
+        hasher.update(&head);
+        io::copy(&mut file, &mut hasher)
+            .with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+    }
+
+    Ok(Some(format!("{:x}", hasher.finalize())))
+}
+
+/// Store only the hash of an oversized page, replacing any cached content
+pub fn write_digest(path: &Path, hash: &str, encryption: &EncryptionSettings) -> Result<()> {
+    if path.exists() {
+        fs::remove_file(path)
+            .with_context(|| format!("Failed to remove cache file: {}", path.display()))?;
+    }
+
+    write_cache(&digest_path(path), hash, encryption)
+}
+
+/// Something wrong with a watcher's cache, found (and repaired) by `verify`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Problem {
+    /// The watcher was checked, but nothing is cached
+    Missing,
+    /// The cache is an empty file
+    Empty,
+    /// The cache isn't valid UTF-8
+    NotUtf8,
+    /// The cache can't be read
+    Unreadable(String),
+    /// Both content and the hash of an oversized page are stored
+    StaleDigest,
+    /// The stored hash of an oversized page isn't a SHA-256
+    InvalidDigest,
+    /// The cache is encrypted and there's no key to read it
+    NoKey,
+}
+
+impl Problem {
+    /// What was wrong, and what was done about it
+    pub fn describe(&self) -> String {
+        match self {
+            Problem::Missing => String::from("cache missing: the next check starts over, without a diff"),
+            Problem::Empty => String::from("cache empty (cut short?): removed, the next check starts over"),
+            Problem::NotUtf8 => String::from("cache not UTF-8: re-encoded, replacing the invalid bytes"),
+            Problem::Unreadable(error) => format!("cache unreadable ({}): removed, the next check starts over", error),
+            Problem::StaleDigest => String::from("stale hash of an oversized page next to the cache: removed"),
+            Problem::InvalidDigest => String::from("stored page hash invalid: removed, the next check starts over"),
+            Problem::NoKey => String::from("cache encrypted, but there's no key: checks fail until it's back"),
+        }
+    }
+}
+
+/// Check a watcher's cache and repair what can be: empty or unreadable
+/// files are removed (the next check starts over), content that isn't
+/// UTF-8 is re-encoded, and stray or invalid hashes are removed. A missing
+/// cache is only a problem if the watcher was checked (`checked`).
+pub fn verify(path: &Path, checked: bool, encryption: &EncryptionSettings) -> Result<Vec<Problem>> {
+    let mut problems = Vec::new();
+    let digest_path = digest_path(path);
+    if digest_path.exists() {
+        if path.exists() {
+            problems.push(Problem::StaleDigest);
+        } else {
+            let digest = read_text(&digest_path).unwrap_or_default();
+            let digest = digest.trim();
+            if digest.len() == 64 && digest.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Ok(problems);
+            }
+            problems.push(Problem::InvalidDigest);
+        }
+        fs::remove_file(&digest_path)
+            .with_context(|| format!("Failed to remove cache file: {}", digest_path.display()))?;
+        if !path.exists() {
+            return Ok(problems);
+        }
+    }
+
+    if !path.exists() {
+        if checked {
+            problems.push(Problem::Missing);
+        }
+        return Ok(problems);
+    }
+
+    let problem = match fs::read(path) {
+        Ok(bytes) if bytes.is_empty() => Problem::Empty,
+        // Without the key it can't be told apart from a damaged cache
+        Ok(bytes) if crypto::is_encrypted(&bytes) && !crypto::has_key() => {
+            problems.push(Problem::NoKey);
+            return Ok(problems);
+        }
+        Ok(bytes) => {
+            let bytes = match open(path, &bytes) {
+                Ok(bytes) => bytes,
+                Err(e) => {
+                    fs::remove_file(path)
+                        .with_context(|| format!("Failed to remove cache file: {}", path.display()))?;
+                    problems.push(Problem::Unreadable(format!("{:#}", e)));
+                    return Ok(problems);
+                }
+            };
+            if let Err(e) = String::from_utf8(bytes) {
+                write_cache(path, &String::from_utf8_lossy(e.as_bytes()), encryption)?;
+                problems.push(Problem::NotUtf8);
+            }
+            return Ok(problems);
+        }
+        Err(e) => Problem::Unreadable(e.to_string()),
+    };
+    fs::remove_file(path).with_context(|| format!("Failed to remove cache file: {}", path.display()))?;
+    problems.push(problem);
+    Ok(problems)
+}
+
+/// Rewrite a watcher's cache (and stored page hash) encrypted, or not, as
+/// encryption is now set. Returns the number of files rewritten.
+pub fn reseal(path: &Path, encryption: &EncryptionSettings) -> Result<usize> {
+    let mut rewritten = 0;
+    for path in [path.to_path_buf(), digest_path(path)] {
+        if !path.exists() {
+            continue;
+        }
+        let data = fs::read(&path).with_context(|| format!("Failed to read cache file: {}", path.display()))?;
+        if crypto::is_encrypted(&data) == encryption.enabled {
+            continue;
+        }
+        let data = open(&path, &data)?;
+        fs::write(&path, seal(&path, &data, encryption)?)
+            .with_context(|| format!("Failed to write cache file: {}", path.display()))?;
+        rewritten += 1;
+    }
+    Ok(rewritten)
+}
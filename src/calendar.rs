@@ -0,0 +1,686 @@
+//! Quiet and paused periods from calendars
+//!
+//! Static windows (active hours) don't cover a holiday or a week of
+//! meetings. A calendar in the ICS format (an `https://` or `webcal://`
+//! subscription URL, or a file) can mark such periods: while one of its
+//! events is on, keyword alerts are held (`quiet`: checks go on, matches
+//! are logged and kept in the history) or watchers aren't checked at all
+//! (`pause`). Critical watchers ignore both. Only the events whose summary
+//! contains `summary` count, when it's set.
+//!
+//! The monitor reads the calendars when it starts and every 15 minutes;
+//! when one can't be read, its last events are kept. Recurring events are
+//! supported for daily, weekly (with `BYDAY`), monthly and yearly rules;
+//! an event with other rule parts only counts at its first occurrence.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration as ChronoDuration, Months, NaiveDate, NaiveDateTime, Utc, Weekday};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::{oneshot, RwLock};
+
+use crate::config::Config;
+use crate::events::EventLog;
+use crate::timezone::Zone;
+
+/// How often the calendars are read again
+pub const REFRESH: Duration = Duration::from_secs(15 * 60);
+
+/// How long the monitor waits for the first read before starting the
+/// watchers without the calendars
+pub const FIRST_READ: Duration = Duration::from_secs(60);
+
+/// How far ahead occurrences of recurring events are kept
+const HORIZON_DAYS: i64 = 14;
+
+/// Most periods of a recurring event looked at, from the one before `from`
+/// (a daily event over the horizon needs 16)
+const MAX_PERIODS: u32 = 1_000;
+
+/// What a calendar's events do
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CalendarAction {
+    /// Keyword alerts aren't sent
+    #[default]
+    Quiet,
+    /// Watchers aren't checked
+    Pause,
+}
+
+impl CalendarAction {
+    pub fn name(self) -> &'static str {
+        match self {
+            CalendarAction::Quiet => "quiet",
+            CalendarAction::Pause => "pause",
+        }
+    }
+}
+
+/// A calendar whose events are quiet or paused periods
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CalendarSettings {
+    /// ICS subscription URL (https://, webcal://) or file path
+    pub url: String,
+    #[serde(default)]
+    pub action: CalendarAction,
+    /// Only events whose summary contains this (case-insensitive) count
+    #[serde(default)]
+    pub summary: Option<String>,
+}
+
+/// An occurrence of a calendar event
+#[derive(Debug, Clone, PartialEq)]
+pub struct Event {
+    pub summary: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+impl Event {
+    fn is_on(&self, now: DateTime<Utc>) -> bool {
+        self.start <= now && now < self.end
+    }
+}
+
+/// A calendar's events as last read
+struct Loaded {
+    url: String,
+    action: CalendarAction,
+    events: Vec<Event>,
+}
+
+/// The events of each calendar
+static CALENDARS: Mutex<Vec<Loaded>> = Mutex::new(Vec::new());
+
+/// The event making this a quiet (or paused) period, if one is on (of
+/// several, the one lasting longest)
+pub fn current(action: CalendarAction) -> Option<Event> {
+    let now = Utc::now();
+    let calendars = CALENDARS.lock().unwrap_or_else(PoisonError::into_inner);
+    calendars
+        .iter()
+        .filter(|calendar| calendar.action == action)
+        .flat_map(|calendar| &calendar.events)
+        .filter(|event| event.is_on(now))
+        .max_by_key(|event| event.end)
+        .cloned()
+}
+
+/// Read the calendars again, keeping the last events of those that can't
+/// be read
+pub async fn refresh(calendars: &[CalendarSettings], zone: Zone, log: &EventLog) {
+    let mut loaded = Vec::new();
+    for calendar in calendars {
+        match load(calendar, zone).await {
+            Ok(events) => loaded.push(Loaded {
+                url: calendar.url.clone(),
+                action: calendar.action,
+                events,
+            }),
+            Err(e) => log.warning(format!("⚠ Failed to read the calendar {}: {:#}", calendar.url, e)),
+        }
+    }
+
+    let mut kept = CALENDARS.lock().unwrap_or_else(PoisonError::into_inner);
+    kept.retain(|old| {
+        calendars.iter().any(|c| c.url == old.url && c.action == old.action)
+            && !loaded.iter().any(|new| new.url == old.url && new.action == old.action)
+    });
+    kept.extend(loaded);
+}
+
+/// Read the calendars of the config now and every `REFRESH` after, until
+/// aborted. `read` is told when the first read is done.
+pub async fn watch(config: Arc<RwLock<Config>>, log: EventLog, read: oneshot::Sender<()>) {
+    let mut read = Some(read);
+    loop {
+        let (calendars, zone) = {
+            let config = config.read().await;
+            (config.calendars.clone(), config.timezone)
+        };
+        refresh(&calendars, zone, &log).await;
+        if let Some(read) = read.take() {
+            let _ = read.send(());
+        }
+        tokio::time::sleep(REFRESH).await;
+    }
+}
+
+/// The events of a calendar from now on (within `HORIZON_DAYS`), oldest
+/// first, with times without a zone in `zone`
+pub async fn load(calendar: &CalendarSettings, zone: Zone) -> Result<Vec<Event>> {
+    let ics = fetch(&calendar.url).await?;
+    if !ics.trim_start().starts_with("BEGIN:VCALENDAR") {
+        anyhow::bail!("Not an ICS calendar");
+    }
+    let now = Utc::now();
+    let mut events = parse(&ics, zone, now, now + ChronoDuration::days(HORIZON_DAYS));
+    if let Some(filter) = calendar.summary.as_deref().map(str::to_lowercase).filter(|f| !f.is_empty()) {
+        events.retain(|event| event.summary.to_lowercase().contains(&filter));
+    }
+    Ok(events)
+}
+
+async fn fetch(url: &str) -> Result<String> {
+    let url = match url.strip_prefix("webcal://") {
+        Some(rest) => format!("https://{}", rest),
+        None => url.to_string(),
+    };
+    if !url.starts_with("http://") && !url.starts_with("https://") {
+        if let Some(user) = crate::system::monitored_user() {
+            anyhow::bail!(
+                "Calendar files are off for the users of the system daemon ({}): they would be read as the service's account",
+                user
+            );
+        }
+        let path = url.strip_prefix("file://").unwrap_or(&url);
+        return std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path));
+    }
+
+    let response = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .build()?
+        .get(&url)
+        .send()
+        .await
+        .with_context(|| format!("Failed to fetch {}", url))?
+        .error_for_status()?;
+    response.text().await.context("Failed to read the calendar")
+}
+
+/// A time of an event, as written in the calendar
+#[derive(Debug, Clone, Copy)]
+struct Moment {
+    wall: NaiveDateTime,
+    zone: Zone,
+    all_day: bool,
+}
+
+impl Moment {
+    fn utc(&self) -> Option<DateTime<Utc>> {
+        self.zone.utc_from_wall_clock(self.wall)
+    }
+}
+
+/// A VEVENT's properties
+#[derive(Default)]
+struct RawEvent {
+    uid: Option<String>,
+    summary: String,
+    start: Option<Moment>,
+    end: Option<Moment>,
+    duration: Option<ChronoDuration>,
+    rule: Option<String>,
+    exceptions: Vec<NaiveDateTime>,
+    recurrence_id: Option<NaiveDateTime>,
+    cancelled: bool,
+}
+
+/// The occurrences of the events in `ics` overlapping `from..to`, oldest
+/// first
+pub fn parse(ics: &str, zone: Zone, from: DateTime<Utc>, to: DateTime<Utc>) -> Vec<Event> {
+    let mut raw = Vec::new();
+    let mut current: Option<RawEvent> = None;
+    // Components nested in an event (VALARM) have properties of their own
+    let mut nested = 0;
+
+    for line in unfold(ics) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        let mut params = name.split(';');
+        let name = params.next().unwrap_or_default().to_uppercase();
+        let params: Vec<(String, String)> = params
+            .filter_map(|param| param.split_once('='))
+            .map(|(key, value)| (key.to_uppercase(), value.trim_matches('"').to_string()))
+            .collect();
+
+        match (name.as_str(), value.trim()) {
+            ("BEGIN", "VEVENT") => current = Some(RawEvent::default()),
+            ("END", "VEVENT") => raw.extend(current.take()),
+            ("BEGIN", _) if current.is_some() => nested += 1,
+            ("END", _) if current.is_some() && nested > 0 => nested -= 1,
+            _ if nested > 0 => {}
+            (name, value) => {
+                let Some(event) = current.as_mut() else {
+                    continue;
+                };
+                match name {
+                    "UID" => event.uid = Some(value.to_string()),
+                    "SUMMARY" => event.summary = unescape(value),
+                    "DTSTART" => event.start = parse_moment(value, &params, zone),
+                    "DTEND" => event.end = parse_moment(value, &params, zone),
+                    "DURATION" => event.duration = parse_duration(value),
+                    "RRULE" => event.rule = Some(value.to_string()),
+                    "EXDATE" => event.exceptions.extend(
+                        value.split(',').filter_map(|value| parse_moment(value, &params, zone)).map(|m| m.wall),
+                    ),
+                    "RECURRENCE-ID" => event.recurrence_id = parse_moment(value, &params, zone).map(|m| m.wall),
+                    "STATUS" => event.cancelled = value.eq_ignore_ascii_case("CANCELLED"),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    // Occurrences moved or cancelled on their own replace those of the rule
+    let replaced: HashSet<(String, NaiveDateTime)> = raw
+        .iter()
+        .filter_map(|event| Some((event.uid.clone()?, event.recurrence_id?)))
+        .collect();
+
+    let mut events: Vec<Event> = raw
+        .iter()
+        .filter(|event| !event.cancelled)
+        .flat_map(|event| occurrences(event, &replaced, from, to))
+        .collect();
+    events.sort_by_key(|event| event.start);
+    events
+}
+
+/// Lines with their continuations joined
+fn unfold(ics: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    for line in ics.lines() {
+        let line = line.trim_end_matches('\r');
+        match (line.strip_prefix([' ', '\t']), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(line.to_string()),
+        }
+    }
+    lines
+}
+
+fn unescape(value: &str) -> String {
+    let mut text = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => text.push(' '),
+            Some(other) => text.push(other),
+            None => {}
+        }
+    }
+    text
+}
+
+/// A date ("20261020"), or a date and time ("20261020T090000", in UTC with
+/// a "Z", else in its TZID or `zone`)
+fn parse_moment(value: &str, params: &[(String, String)], zone: Zone) -> Option<Moment> {
+    let value = value.trim();
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y%m%d") {
+        return Some(Moment {
+            wall: date.and_hms_opt(0, 0, 0)?,
+            zone,
+            all_day: true,
+        });
+    }
+    let (value, zone) = match value.strip_suffix('Z') {
+        Some(value) => (value, Zone::Named(chrono_tz::UTC)),
+        None => {
+            let tzid = params.iter().find(|(key, _)| key == "TZID").map(|(_, tzid)| tzid.as_str());
+            (value, tzid.and_then(|tzid| Zone::parse(tzid).ok()).unwrap_or(zone))
+        }
+    };
+    Some(Moment {
+        wall: NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S").ok()?,
+        zone,
+        all_day: false,
+    })
+}
+
+/// An ISO 8601 duration as calendars write them ("PT1H30M", "P2D", "P1W")
+fn parse_duration(value: &str) -> Option<ChronoDuration> {
+    let value = value.trim().trim_start_matches('+');
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, value),
+    };
+    let mut seconds: i64 = 0;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.strip_prefix('P')?.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            unit => {
+                let n: i64 = number.parse().ok()?;
+                number.clear();
+                seconds += n * match (unit, in_time) {
+                    ('W', false) => 7 * 86_400,
+                    ('D', false) => 86_400,
+                    ('H', true) => 3_600,
+                    ('M', true) => 60,
+                    ('S', true) => 1,
+                    _ => return None,
+                };
+            }
+        }
+    }
+    Some(ChronoDuration::seconds(if negative { -seconds } else { seconds }))
+}
+
+/// A recurrence rule, as far as it's supported
+struct Rule {
+    frequency: Frequency,
+    interval: u32,
+    count: Option<usize>,
+    until: Option<DateTime<Utc>>,
+    days: Vec<Weekday>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+/// None for rules with parts other than FREQ, INTERVAL, COUNT, UNTIL (and
+/// BYDAY, for weekly rules)
+fn parse_rule(rule: &str, zone: Zone) -> Option<Rule> {
+    let mut parsed = Rule {
+        frequency: Frequency::Daily,
+        interval: 1,
+        count: None,
+        until: None,
+        days: Vec::new(),
+    };
+    let mut frequency = None;
+    for part in rule.split(';') {
+        let (key, value) = part.split_once('=')?;
+        match key.to_uppercase().as_str() {
+            "FREQ" => {
+                frequency = Some(match value.to_uppercase().as_str() {
+                    "DAILY" => Frequency::Daily,
+                    "WEEKLY" => Frequency::Weekly,
+                    "MONTHLY" => Frequency::Monthly,
+                    "YEARLY" => Frequency::Yearly,
+                    _ => return None,
+                })
+            }
+            "INTERVAL" => parsed.interval = value.parse().ok().filter(|interval| *interval > 0)?,
+            "COUNT" => parsed.count = Some(value.parse().ok()?),
+            "UNTIL" => parsed.until = parse_moment(value, &[], zone).and_then(|until| until.utc()),
+            "BYDAY" => {
+                parsed.days = value
+                    .split(',')
+                    .map(|day| match day.to_uppercase().as_str() {
+                        "MO" => Some(Weekday::Mon),
+                        "TU" => Some(Weekday::Tue),
+                        "WE" => Some(Weekday::Wed),
+                        "TH" => Some(Weekday::Thu),
+                        "FR" => Some(Weekday::Fri),
+                        "SA" => Some(Weekday::Sat),
+                        "SU" => Some(Weekday::Sun),
+                        _ => None,
+                    })
+                    .collect::<Option<_>>()?
+            }
+            "WKST" => {}
+            _ => return None,
+        }
+    }
+    parsed.frequency = frequency?;
+    if !parsed.days.is_empty() && parsed.frequency != Frequency::Weekly {
+        return None;
+    }
+    parsed.days.sort_by_key(|day| day.num_days_from_monday());
+    Some(parsed)
+}
+
+/// The occurrences of an event overlapping `from..to`
+fn occurrences(
+    event: &RawEvent,
+    replaced: &HashSet<(String, NaiveDateTime)>,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<Event> {
+    let Some(start) = event.start else {
+        return Vec::new();
+    };
+    let length = match (event.end.and_then(|end| end.utc()), start.utc(), event.duration) {
+        (Some(end), Some(start), _) => end - start,
+        (None, _, Some(duration)) => duration,
+        _ if start.all_day => ChronoDuration::days(1),
+        _ => ChronoDuration::zero(),
+    };
+    if length <= ChronoDuration::zero() {
+        return Vec::new();
+    }
+
+    // A moved occurrence is an event of its own; the rule's is dropped
+    let is_replaced = |wall: NaiveDateTime| match (&event.uid, event.recurrence_id) {
+        (Some(uid), None) => replaced.contains(&(uid.clone(), wall)),
+        _ => false,
+    };
+    let rule = match (&event.rule, event.recurrence_id) {
+        (Some(rule), None) => parse_rule(rule, start.zone),
+        _ => None,
+    };
+
+    // Periods that ended before `from` are skipped, though those of a rule
+    // with a COUNT still count
+    let first_period = match &rule {
+        Some(rule) => from
+            .checked_sub_signed(length)
+            .map_or(0, |since| period_before(start.wall, rule, since.naive_utc())),
+        None => 0,
+    };
+    let mut seen = match &rule {
+        Some(rule) if rule.count.is_some() => count_before(start.wall, rule, first_period),
+        _ => 0,
+    };
+
+    let mut found = Vec::new();
+    'periods: for period in first_period..first_period.saturating_add(MAX_PERIODS) {
+        let starts = match &rule {
+            // Past the dates there are: nothing more
+            Some(rule) => match period_starts(start.wall, rule, period) {
+                Some(starts) => starts,
+                None => break,
+            },
+            None if period == 0 => vec![start.wall],
+            None => break,
+        };
+        for wall in starts {
+            if wall < start.wall {
+                continue;
+            }
+            seen += 1;
+            if rule.as_ref().and_then(|rule| rule.count).is_some_and(|count| seen > count) {
+                break 'periods;
+            }
+            let Some(begin) = start.zone.utc_from_wall_clock(wall) else {
+                continue;
+            };
+            if begin >= to || rule.as_ref().and_then(|rule| rule.until).is_some_and(|until| begin > until) {
+                break 'periods;
+            }
+            if begin + length > from && !event.exceptions.contains(&wall) && !is_replaced(wall) {
+                found.push(Event {
+                    summary: event.summary.clone(),
+                    start: begin,
+                    end: begin + length,
+                });
+            }
+        }
+    }
+    found
+}
+
+/// The period of a rule before the one `since` is in (one before, as
+/// `since` is in UTC and the starts are wall-clock times)
+fn period_before(first: NaiveDateTime, rule: &Rule, since: NaiveDateTime) -> u32 {
+    let elapsed = match rule.frequency {
+        Frequency::Daily => (since - first).num_days(),
+        Frequency::Weekly => (since - first).num_weeks(),
+        Frequency::Monthly => {
+            i64::from(since.year() - first.year()) * 12 + i64::from(since.month()) - i64::from(first.month())
+        }
+        Frequency::Yearly => i64::from(since.year() - first.year()),
+    };
+    let period = elapsed / i64::from(rule.interval) - 1;
+    u32::try_from(period.max(0)).unwrap_or(u32::MAX)
+}
+
+/// How many occurrences of a rule the periods before `period` have
+fn count_before(first: NaiveDateTime, rule: &Rule, period: u32) -> usize {
+    match rule.frequency {
+        Frequency::Daily => period as usize,
+        Frequency::Weekly if rule.days.is_empty() => period as usize,
+        Frequency::Weekly if period > 0 => {
+            let weekday = first.weekday().num_days_from_monday();
+            let before_first = rule.days.iter().filter(|day| day.num_days_from_monday() < weekday).count();
+            period as usize * rule.days.len() - before_first
+        }
+        Frequency::Weekly => 0,
+        // Some months and years don't have the day: counted one by one
+        Frequency::Monthly | Frequency::Yearly => {
+            (0..period).map_while(|period| period_starts(first, rule, period)).map(|starts| starts.len()).sum()
+        }
+    }
+}
+
+/// The starts of the `period`th period of a rule (days, weeks, months or
+/// years after the first start), as wall-clock times. None once they're
+/// past the dates there are.
+fn period_starts(first: NaiveDateTime, rule: &Rule, period: u32) -> Option<Vec<NaiveDateTime>> {
+    let step = period.checked_mul(rule.interval)?;
+    let time = first.time();
+    Some(match rule.frequency {
+        Frequency::Daily => vec![first.checked_add_signed(ChronoDuration::days(step.into()))?],
+        Frequency::Weekly if rule.days.is_empty() => vec![first.checked_add_signed(ChronoDuration::weeks(step.into()))?],
+        Frequency::Weekly => {
+            let monday = first
+                .date()
+                .checked_sub_signed(ChronoDuration::days(first.weekday().num_days_from_monday().into()))?
+                .checked_add_signed(ChronoDuration::weeks(step.into()))?;
+            rule.days
+                .iter()
+                .filter_map(|day| monday.checked_add_signed(ChronoDuration::days(day.num_days_from_monday().into())))
+                .map(|date| date.and_time(time))
+                .collect()
+        }
+        // Months without the day (the 31st, February 29th) are skipped
+        Frequency::Monthly => first
+            .date()
+            .with_day(1)?
+            .checked_add_months(Months::new(step))?
+            .with_day(first.day())
+            .map(|date| date.and_time(time))
+            .into_iter()
+            .collect(),
+        Frequency::Yearly => {
+            let year = i32::try_from(step).ok().and_then(|step| first.year().checked_add(step))?;
+            NaiveDate::from_ymd_opt(year, 1, 1)?;
+            NaiveDate::from_ymd_opt(year, first.month(), first.day())
+                .map(|date| date.and_time(time))
+                .into_iter()
+                .collect()
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn time(text: &str) -> DateTime<Utc> {
+        text.parse().unwrap()
+    }
+
+    /// The starts of an event's occurrences in `from..to` (properties one
+    /// per line)
+    fn starts(properties: &str, from: &str, to: &str) -> Vec<DateTime<Utc>> {
+        let ics = format!(
+            "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nSUMMARY:Event\r\nDURATION:PT1H\r\n{}\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+            properties.lines().map(str::trim).collect::<Vec<_>>().join("\r\n")
+        );
+        let zone = Zone::parse("UTC").unwrap();
+        parse(&ics, zone, time(from), time(to)).iter().map(|event| event.start).collect()
+    }
+
+    #[test]
+    fn weekly_rules_repeat_on_their_days() {
+        let found = starts(
+            "DTSTART:20261012T090000Z
+             RRULE:FREQ=WEEKLY;BYDAY=TH,MO",
+            "2026-10-12T00:00:00Z",
+            "2026-10-26T00:00:00Z",
+        );
+        let expected = ["2026-10-12T09:00:00Z", "2026-10-15T09:00:00Z", "2026-10-19T09:00:00Z", "2026-10-22T09:00:00Z"];
+        assert_eq!(found, expected.map(time));
+    }
+
+    #[test]
+    fn exdates_drop_occurrences() {
+        let found = starts(
+            "DTSTART:20261012T090000Z
+             RRULE:FREQ=DAILY
+             EXDATE:20261013T090000Z,20261015T090000Z",
+            "2026-10-12T00:00:00Z",
+            "2026-10-16T00:00:00Z",
+        );
+        assert_eq!(found, ["2026-10-12T09:00:00Z", "2026-10-14T09:00:00Z"].map(time));
+    }
+
+    #[test]
+    fn count_and_until_end_rules() {
+        let week = |rule: &str| starts(rule, "2026-10-12T00:00:00Z", "2026-10-19T00:00:00Z");
+        let three = ["2026-10-12T09:00:00Z", "2026-10-13T09:00:00Z", "2026-10-14T09:00:00Z"].map(time);
+        assert_eq!(week("DTSTART:20261012T090000Z\nRRULE:FREQ=DAILY;COUNT=3"), three);
+        assert_eq!(week("DTSTART:20261012T090000Z\nRRULE:FREQ=DAILY;UNTIL=20261014T090000Z"), three);
+
+        // Occurrences before the range count too: the 12th to 14th of 14
+        assert_eq!(week("DTSTART:20261001T090000Z\nRRULE:FREQ=DAILY;COUNT=14"), three);
+        // Thursday January 1st, then two a week: October 12th is the 82nd
+        let weekly = "DTSTART:20260101T090000Z\nRRULE:FREQ=WEEKLY;BYDAY=MO,TH;COUNT=";
+        assert_eq!(week(&format!("{}82", weekly)), [time("2026-10-12T09:00:00Z")]);
+        assert_eq!(week(&format!("{}83", weekly)), ["2026-10-12T09:00:00Z", "2026-10-15T09:00:00Z"].map(time));
+        // Months without a 31st don't count: October's is the 6th
+        let october = |count: u32| {
+            let rule = format!("DTSTART:20260131T090000Z\nRRULE:FREQ=MONTHLY;COUNT={}", count);
+            starts(&rule, "2026-10-01T00:00:00Z", "2026-11-05T00:00:00Z")
+        };
+        assert_eq!(october(6), [time("2026-10-31T09:00:00Z")]);
+        assert!(october(5).is_empty());
+    }
+
+    #[test]
+    fn events_recurring_for_long_are_found() {
+        let found = starts(
+            "DTSTART:19000101T090000Z
+             RRULE:FREQ=DAILY",
+            "2026-10-12T00:00:00Z",
+            "2026-10-14T00:00:00Z",
+        );
+        assert_eq!(found, ["2026-10-12T09:00:00Z", "2026-10-13T09:00:00Z"].map(time));
+    }
+
+    #[tokio::test]
+    async fn calendar_files_are_refused_for_users_of_the_system_daemon() {
+        let _user = crate::system::monitor_as("alice");
+        for url in ["/etc/shadow", "file:///etc/shadow"] {
+            let error = fetch(url).await.unwrap_err();
+            assert!(error.to_string().contains("system daemon (alice)"), "{}", error);
+        }
+    }
+
+    #[test]
+    fn oversized_intervals_end_the_rule() {
+        for frequency in ["DAILY", "WEEKLY", "WEEKLY;BYDAY=MO", "MONTHLY", "YEARLY"] {
+            let rule = format!("DTSTART:20261012T090000Z\nRRULE:FREQ={};INTERVAL=4294967295", frequency);
+            let found = starts(&rule, "2026-10-12T00:00:00Z", "9999-01-01T00:00:00Z");
+            assert_eq!(found, [time("2026-10-12T09:00:00Z")], "{}", frequency);
+        }
+    }
+}
@@ -0,0 +1,300 @@
+//! HTTP archive (HAR) captures for debugging a watcher
+//!
+//! When the daemon sees different content than the browser, `capture <url>`
+//! fetches the page the way a check does (same headers, User-Agent and TLS
+//! settings) and writes every request and response, redirects included, to
+//! a HAR file: headers, timing and body. Browsers' developer tools export
+//! the same format ("Save all as HAR"), so both can be opened and compared
+//! in the same viewer.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use chrono::{DateTime, Utc};
+use serde_json::{json, Value};
+use std::fs;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use crate::domains;
+use crate::fetcher::{self, RequestOptions, MAX_REDIRECTS};
+
+/// Request headers that aren't sent on to another host when redirected
+/// (reqwest drops the same ones)
+const SENSITIVE_HEADERS: &[&str] = &["authorization", "cookie", "proxy-authorization", "www-authenticate"];
+
+/// One request and its response
+pub struct Exchange {
+    pub started: DateTime<Utc>,
+    pub url: String,
+    /// Headers as sent, the User-Agent included
+    pub request_headers: Vec<(String, String)>,
+    pub status: u16,
+    pub status_text: String,
+    pub http_version: String,
+    pub response_headers: Vec<(String, String)>,
+    /// The body, up to the size cap
+    pub body: Vec<u8>,
+    /// Size of the whole body as received
+    pub body_size: u64,
+    /// From sending the request until the response headers arrived
+    pub wait: Duration,
+    /// From the headers until the end of the body
+    pub receive: Duration,
+    /// Where the response redirects to
+    pub redirect_to: Option<String>,
+}
+
+impl Exchange {
+    /// The body was cut off at the size cap
+    pub fn truncated(&self) -> bool {
+        (self.body.len() as u64) < self.body_size
+    }
+}
+
+/// The requests of one fetch, in order
+pub struct Capture {
+    pub exchanges: Vec<Exchange>,
+    /// Why the fetch stopped early (the exchanges up to there are kept)
+    pub error: Option<String>,
+}
+
+impl Capture {
+    /// The last response, unless the fetch ended in a redirect
+    pub fn final_response(&self) -> Option<&Exchange> {
+        self.exchanges.last().filter(|exchange| exchange.redirect_to.is_none())
+    }
+
+    /// The capture in HAR 1.2 format
+    pub fn to_har(&self) -> Value {
+        let entries: Vec<Value> = self.exchanges.iter().map(har_entry).collect();
+        let mut log = json!({
+            "version": "1.2",
+            "creator": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+            },
+            "entries": entries,
+        });
+        if let Some(error) = &self.error {
+            log["comment"] = json!(error);
+        }
+        json!({ "log": log })
+    }
+
+    /// Write the capture to a HAR file
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let contents = serde_json::to_string_pretty(&self.to_har())?;
+        fs::write(path, contents).with_context(|| format!("Failed to write capture: {}", path.display()))
+    }
+}
+
+/// HAR file name for a capture of `url` made now
+pub fn default_file_name(url: &str) -> String {
+    let host = domains::host(url).unwrap_or_else(|| String::from("page"));
+    format!("capture-{}-{}.har", host, chrono::Local::now().format("%Y%m%d-%H%M%S"))
+}
+
+/// Fetch `url` with a watcher's request options, following redirects one by
+/// one so each is recorded. Bodies are kept up to `max_body` bytes.
+pub async fn capture(url: &str, options: &RequestOptions, max_body: Option<u64>) -> Capture {
+    let mut capture = Capture {
+        exchanges: Vec::new(),
+        error: None,
+    };
+    let mut headers: Vec<(String, String)> = options
+        .request_headers()
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.to_string()))
+        .collect();
+
+    let client = match fetcher::http_client(options, reqwest::redirect::Policy::none()) {
+        Ok(client) => client,
+        Err(e) => {
+            capture.error = Some(format!("{:#}", e));
+            return capture;
+        }
+    };
+
+    let mut url = url.to_string();
+    loop {
+        let exchange = match send(&client, &url, &headers, options.user_agent(), max_body).await {
+            Ok(exchange) => exchange,
+            Err(e) => {
+                capture.error = Some(format!("{:#}", e));
+                break;
+            }
+        };
+        let next = exchange.redirect_to.clone();
+        capture.exchanges.push(exchange);
+
+        let Some(next) = next else {
+            break;
+        };
+        if capture.exchanges.len() > MAX_REDIRECTS {
+            capture.error = Some(String::from("Too many redirects"));
+            break;
+        }
+
+        // Credentials stay with the host they were meant for
+        if domains::host(&next) != domains::host(&url) {
+            headers.retain(|(name, _)| !SENSITIVE_HEADERS.contains(&name.to_lowercase().as_str()));
+        }
+        url = next;
+    }
+    capture
+}
+
+/// Send one GET request and read its response
+async fn send(
+    client: &reqwest::Client,
+    url: &str,
+    headers: &[(String, String)],
+    user_agent: &str,
+    max_body: Option<u64>,
+) -> Result<Exchange> {
+    let mut request = client.get(url);
+    for (name, value) in headers {
+        request = request.header(name.as_str(), value.as_str());
+    }
+    let request = request.build().with_context(|| format!("Invalid request for {}", url))?;
+
+    // The client adds the User-Agent, and Accept when none is given
+    let mut request_headers = header_list(request.headers());
+    request_headers.push((String::from("user-agent"), user_agent.to_string()));
+    if !request.headers().contains_key(reqwest::header::ACCEPT) {
+        request_headers.push((String::from("accept"), String::from("*/*")));
+    }
+
+    let started = Utc::now();
+    let sent = Instant::now();
+    let mut response = client
+        .execute(request)
+        .await
+        .with_context(|| format!("Failed to fetch URL: {}", url))?;
+    let wait = sent.elapsed();
+
+    let redirect_to = if response.status().is_redirection() {
+        response
+            .headers()
+            .get(reqwest::header::LOCATION)
+            .and_then(|location| location.to_str().ok())
+            .and_then(|location| response.url().join(location).ok())
+            .map(|next| next.to_string())
+    } else {
+        None
+    };
+    let status = response.status();
+    let http_version = format!("{:?}", response.version());
+    let response_headers = header_list(response.headers());
+
+    let mut body = Vec::new();
+    let mut body_size: u64 = 0;
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .with_context(|| format!("Failed to read the response of {}", url))?
+    {
+        body_size += chunk.len() as u64;
+        let room = max_body.map_or(chunk.len(), |max| (max as usize).saturating_sub(body.len()));
+        body.extend_from_slice(&chunk[..room.min(chunk.len())]);
+    }
+
+    Ok(Exchange {
+        started,
+        url: url.to_string(),
+        request_headers,
+        status: status.as_u16(),
+        status_text: status.canonical_reason().unwrap_or_default().to_string(),
+        http_version,
+        response_headers,
+        body,
+        body_size,
+        wait,
+        receive: sent.elapsed() - wait,
+        redirect_to,
+    })
+}
+
+fn header_list(headers: &reqwest::header::HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .map(|(name, value)| (name.to_string(), String::from_utf8_lossy(value.as_bytes()).into_owned()))
+        .collect()
+}
+
+fn har_headers(headers: &[(String, String)]) -> Value {
+    headers
+        .iter()
+        .map(|(name, value)| json!({ "name": name, "value": value }))
+        .collect()
+}
+
+/// An exchange as a HAR entry. Text bodies are stored as text, others as
+/// base64.
+fn har_entry(exchange: &Exchange) -> Value {
+    let query: Vec<Value> = url::Url::parse(&exchange.url)
+        .map(|parsed| {
+            parsed
+                .query_pairs()
+                .map(|(name, value)| json!({ "name": name, "value": value }))
+                .collect()
+        })
+        .unwrap_or_default();
+    let mime_type = exchange
+        .response_headers
+        .iter()
+        .find(|(name, _)| name == "content-type")
+        .map_or("", |(_, value)| value.as_str());
+
+    let mut content = json!({
+        "size": exchange.body_size,
+        "mimeType": mime_type,
+    });
+    match std::str::from_utf8(&exchange.body) {
+        Ok(text) => content["text"] = json!(text),
+        Err(_) => {
+            content["text"] = json!(base64::engine::general_purpose::STANDARD.encode(&exchange.body));
+            content["encoding"] = json!("base64");
+        }
+    }
+    if exchange.truncated() {
+        content["comment"] = json!(format!("Cut off after {} bytes (the size limit)", exchange.body.len()));
+    }
+
+    let wait = exchange.wait.as_secs_f64() * 1000.0;
+    let receive = exchange.receive.as_secs_f64() * 1000.0;
+    json!({
+        "startedDateTime": exchange.started.to_rfc3339(),
+        "time": wait + receive,
+        "request": {
+            "method": "GET",
+            "url": exchange.url,
+            "httpVersion": exchange.http_version,
+            "cookies": [],
+            "headers": har_headers(&exchange.request_headers),
+            "queryString": query,
+            "headersSize": -1,
+            "bodySize": 0,
+        },
+        "response": {
+            "status": exchange.status,
+            "statusText": exchange.status_text,
+            "httpVersion": exchange.http_version,
+            "cookies": [],
+            "headers": har_headers(&exchange.response_headers),
+            "content": content,
+            "redirectURL": exchange.redirect_to.clone().unwrap_or_default(),
+            "headersSize": -1,
+            "bodySize": exchange.body_size,
+        },
+        "cache": {},
+        "timings": {
+            "blocked": -1,
+            "dns": -1,
+            "connect": -1,
+            "send": 0,
+            "wait": wait,
+            "receive": receive,
+        },
+    })
+}
@@ -0,0 +1,2141 @@
+//! Command-line interface module
+//!
+//! Handles non-interactive subcommands such as `add` and `templates`,
+//! so watchers can be managed from scripts without opening the TUI.
+
+use anyhow::{Context, Result};
+use std::path::Path;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use crate::{
+    backup, bench, bookmarks, cache, calendar, capture, clipboard, cluster, companion,
+    config::{Config, EncryptionSettings},
+    config_history, crypto,
+    diff::{Algorithm, DiffOptions, Granularity},
+    doctor, domains,
+    events::{EventLog, LogFormat},
+    extractor::ContentMode,
+    fetcher::{self, Page, RenderMode},
+    history,
+    hook::HookSettings,
+    impersonate::RequestProfile,
+    latency,
+    match_test,
+    matcher::{Keyword, KeywordMatcher, Priority},
+    matrix::{self, Matrix},
+    monitor::Monitor,
+    notify::{self, Channel},
+    opml, remote, review,
+    schedule::ActiveWindow,
+    share, sync,
+    system,
+    templates, trash, ui, unix_socket,
+    values::{self, ExportFormat},
+    watcher::{
+        format_expiry, format_interval, normalize_url, parse_expiry, parse_interval, Watcher,
+        MAX_CONSENSUS_FETCHES,
+    },
+};
+
+/// Exit status of `check` when a check failed (and nothing matched).
+/// Other failures, like an invalid config or arguments, exit with 1.
+pub const EXIT_FETCH_ERRORS: u8 = 2;
+
+/// Exit status of `check` when keywords matched (even if other checks failed)
+pub const EXIT_MATCHES: u8 = 3;
+
+/// How much `check` and the daemon print (`-q`/`-v`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verbosity {
+    Quiet,
+    Normal,
+    Verbose,
+}
+
+/// Print usage information
+pub fn print_usage() {
+    println!("Usage: web-watcher-alert [--profile <name>] [--user <name>] [-q|-v] [COMMAND]");
+    println!();
+    println!("  --profile <name>             Use a separate set of watchers, settings and history");
+    println!("                               (also WEB_WATCHER_ALERT_PROFILE; default: \"default\")");
+    println!("  --user <name>                Use a user's directories of the system installation");
+    println!("  --read-only                  Never write the config (also WEB_WATCHER_ALERT_READ_ONLY=1)");
+    println!("  -q, --quiet                  check: print nothing; daemon: only log matches, warnings and errors");
+    println!("  -v, --verbose                check: also print the log of each check");
+    println!();
+    println!("Commands:");
+    println!("  (none)                       Start the interactive TUI (the daemon without a terminal)");
+    println!("  --daemon                     Run the monitor in the background");
+    println!("      --foreground             Container mode: stay attached and log JSON lines to stdout");
+    println!("      --log-format <text|json> Log format (default text, json with --foreground)");
+    println!("      --log-level <level>      Least important events logged: info, changes (leaves out the");
+    println!("                               checks, counted every 15 min), matches, warnings or errors");
+    println!("      --system                 Serve every user of the system installation");
+    println!("                               (WEB_WATCHER_ALERT_SYSTEM_DIR, default {})", system::DEFAULT_SYSTEM_DIR);
+    println!("  add <url> [options]          Add a watcher");
+    println!("      --keywords <a,b,c>       Comma-separated keywords (@name uses a keyword set)");
+    println!("      --interval <duration>    Check interval, e.g. 90s, 15m, 2h, 1d (default 30m)");
+    println!("      --template <name>        Pre-fill settings from a template");
+    println!("      --from-clipboard         Take the URL from the clipboard");
+    println!("      --merge                  Merge keywords into an existing watcher for the URL");
+    println!("      --regex                  The keywords are regular expressions");
+    println!("      --whole-word             Only match the keywords as whole words");
+    println!("      --priority <level>       Alert priority of the keywords: low, normal or high");
+    println!("      --channels <a,b>         Alert the keywords only through these channels (desktop, webhook, tasks)");
+    println!("      --force-interval         Allow an interval below the minimum for public sites");
+    println!("      --active <window>        Only check during these hours, e.g. \"08:00-20:00 mon-fri\"");
+    println!("      --expires <date>         Disable the watcher after this date (YYYY-MM-DD [HH:MM])");
+    println!("      --once                   Disable the watcher after its first match");
+    println!("      --consensus <2|3>        Fetch N times per check, ignoring content that varies");
+    println!("      --lightweight            Check with HEAD first; download only when it changed");
+    println!("      --critical               Keep the normal interval in battery saving mode");
+    println!("      --feed                   Mark the URL as an RSS/Atom feed (for OPML export)");
+    println!("      --stream                 Read the URL as server-sent events, alerting on each message");
+    println!("                               (ws:// and wss:// URLs are always streams)");
+    println!("      --notify-cleared         Also notify when matched keywords disappear again");
+    println!("      --pause-when-blocked     Disable the watcher when it gets a CAPTCHA/block page");
+    println!("      --translate              Match the keywords' translations on foreign-language pages");
+    println!("      --main-content           Only watch the page's main content (no menus, footers, banners)");
+    println!("      --content <mode>         raw_html (the HTML, default) or text (the visible text, so");
+    println!("                               script hashes and form tokens don't count as changes)");
+    println!("      --hook <command>         Run a command when keywords match (gets WATCHER_URL, MATCHED_KEYWORDS)");
+    println!("                               and translate the alerts (needs \"translation\" in the config)");
+    println!("      --request-profile <name> Send a browser's headers: chrome-like or firefox-like");
+    println!("      --alert-title <text>     Title of the alerts; {{{{name}}}} is a regex keyword's named group,");
+    println!("                               e.g. \"{{{{price}}}} at Shop\" with (?P<price>\\$\\d+)");
+    println!("      --track-value <regex>    Read a number from the page on every check (its first group,");
+    println!("                               or the whole match) and chart it; alerts show the trend");
+    println!("      --slow-after <time>      Alarm when a response takes longer, e.g. 1500ms or 2s");
+    println!("      --slow-increase <pct>    Alarm when a response takes this much longer than the");
+    println!("                               average of the recent checks, e.g. 50%");
+    println!("      --min-recheck-gap <time> Don't fetch the page again within this time of a check");
+    println!("                               (`check` reports the last result instead), e.g. 5m");
+    println!("      --diff <line|word|char>  What the diffs in alerts are made of (word suits prose)");
+    println!("      --diff-algorithm <name>  myers (default), patience (reordered rows) or lcs");
+    println!("      --ca-cert <file>         Also trust this CA certificate (PEM) for the watcher");
+    println!("      --insecure               Skip TLS certificate verification (dangerous)");
+    println!("  add-url <url>                Open the TUI's add form with the URL filled in (the URL");
+    println!("                               alone works too, e.g. `web-watcher-alert https://...`)");
+    println!("  archive [url...]             Archive watchers: kept with their history but never checked");
+    println!("                               (lists the archived watchers without URLs)");
+    println!("  unarchive <url...>           Restore archived watchers");
+    println!("  trash [restore <url>|empty]  List deleted watchers, restore one, or delete them for good");
+    println!("  prune [url...]               Thin out the check histories as the \"history\" settings say");
+    println!("      --dry-run                Only show what would be removed");
+    println!("  encryption [on|off]          Encrypt the caches and check histories at rest (the key is");
+    println!("                               kept in the keychain), stop, or show whether they are");
+    println!("  matrix                       List the matrix watchers");
+    println!("  matrix set <name>            Create or change a matrix: the keywords on every URL template");
+    println!("      --urls <a,b>             (with {{value}} in them) for every value, as child watchers");
+    println!("      --values <x,y>           (also --keywords, --interval and the keyword settings)");
+    println!("  matrix remove <name>         Remove a matrix and its watchers");
+    println!("  matrix apply [name]          Update the watchers of matrices edited in the config file");
+    println!("  remote [push|pull]           Show the remote sync's state, or push or pull the settings");
+    println!("      --force                  Settle a conflict: push over the remote's, or take them");
+    println!("  cluster                      Show this daemon's part in a cluster (and the agents)");
+    println!("  cluster coordinator          Deliver the alerts of agents, dropping duplicates");
+    println!("      --listen <addr>          Address to listen on (default 0.0.0.0:7392)");
+    println!("  cluster agent <url>          Report alerts to the coordinator at <url>");
+    println!("      --token <token>          The coordinator's token (--name: this machine's name)");
+    println!("  cluster off                  Deliver alerts directly again");
+    println!("  notify test                  Send a sample alert through each enabled channel and report");
+    println!("      --channel <name>         which ones delivered it (--channel: only that one)");
+    println!("  calendar                     List the calendars of quiet or paused periods, and their events");
+    println!("  calendar add <url>           No alerts during the events of an ICS calendar (URL or file)");
+    println!("      --pause                  Don't check watchers at all during its events");
+    println!("      --summary <text>         Only events whose summary contains <text>");
+    println!("  calendar remove <url>        Stop following a calendar");
+    println!("  config history [--limit N]   Show who changed which settings when");
+    println!("  config show <revision>       Print the settings of a revision");
+    println!("  config rollback <revision>   Go back to the settings of a revision");
+    println!("  check [url...]               Check the enabled (or the given) watchers once, now");
+    println!("                               (streaming watchers are only watched by the daemon)");
+    println!("                               Exit status: 0 nothing found, 1 config error,");
+    println!("                               2 a check failed, 3 keywords matched");
+    println!("  matches [url...]             List the keyword matches of the last day, newest first");
+    println!("      --since <duration>       How far back to look, e.g. 12h or 7d (default 1d)");
+    println!("      --json                   Print JSON, for Shortcuts and scripts");
+    println!("  webwatcher://<action>?...    Run a link: add?url=..&keywords=.., check?url=.., matches");
+    println!("  companion                    Enable the local endpoint that adds watchers from a browser");
+    println!("                               extension or bookmarklet, and show its token and bookmarklet");
+    println!("      --port <port>            Port to listen on (127.0.0.1 only, default 7391)");
+    println!("      --new-token              Replace the token (extensions need the new one)");
+    println!("      --disable                Turn the endpoint off");
+    println!("  triggers                     Enable the endpoint that checks a watcher when another system");
+    println!("                               (CI, IFTTT) calls it, and show its token and URLs");
+    println!("      --listen <address>       Address to listen on (default 127.0.0.1:7393)");
+    println!("      --new-token              Replace the token (callers need the new one)");
+    println!("      --disable                Turn the endpoint off");
+    println!("  import-bookmarks <file>      Create disabled watchers from exported bookmarks");
+    println!("      --folder <name>          Folder to import (lists the folders if omitted)");
+    println!("      --keywords <a,b,c>       Keywords for the imported watchers (add later if omitted)");
+    println!("      --interval <duration>    Check interval for the imported watchers (default 30m)");
+    println!("  import-opml <file>           Create disabled feed watchers from an OPML subscription list");
+    println!("      --keywords <a,b,c>       Keywords for the imported watchers (add later if omitted)");
+    println!("      --interval <duration>    Check interval for the imported watchers (default 30m)");
+    println!("  export-opml [file]           Write feed watchers as OPML (to stdout if no file)");
+    println!("  share <url>                  Print a webwatcher://import link others can import the watcher with");
+    println!("      --json                   Print its settings as a JSON snippet instead");
+    println!("  import-watcher <snippet>     Add a shared watcher: a JSON snippet, an import link, a file");
+    println!("                               with one, or - for stdin");
+    println!("      --force-interval         Allow an interval below the guardrails");
+    println!("  export-values [url...]       Write the tracked values of the watchers (all that track one,");
+    println!("                               or the given ones) as CSV, one url,checked_at,value row each");
+    println!("      --format <csv|json>      Output format (default: from the file name, else csv)");
+    println!("      --output <file>          Write to a file instead of stdout");
+    println!("  sync <file.yaml> [--apply]   Make the watchers match a declarative YAML list (plan only");
+    println!("                               without --apply; unlisted watchers are removed)");
+    println!("  templates                    List available watcher templates");
+    println!("  keyword-sets                 List shared keyword sets and the watchers using them");
+    println!("  users                        List the users of a system installation and their watchers");
+    println!("  doctor [--offline]           Check the config for problems (alias: validate)");
+    println!("  capture <url> [--output <f>] Fetch a page as its watcher does and save every request and");
+    println!("                               response (redirects, headers, timing, body) as a HAR file");
+    println!("  bench <url> [--runs N]       Time each stage of a check (fetch, select, diff, match)");
+    println!("                               over N runs (default 5, --json for scripts)");
+    println!("  match-test [url]             Try keywords, regexes and selectors on a page (fetched,");
+    println!("      --file <path>            from a file, or pasted), seeing the matches as you type");
+    println!("  backup [file]                Save config, cache and history to a .tar.gz archive");
+    println!("  restore <file> [--force]     Restore from a backup archive");
+    println!("  help                         Show this message");
+}
+
+/// `add` command: create a new watcher from command-line arguments
+pub fn add(args: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    config.ensure_writable()?;
+
+    // Start from a template if one was requested
+    let template = match flag_value(args, "--template") {
+        Some(name) => Some(
+            templates::find_template(&config, &name)
+                .with_context(|| format!("Unknown template: {} (see `templates`)", name))?,
+        ),
+        None => None,
+    };
+
+    // URL is the first positional argument (or the clipboard), falling back
+    // to the template's URL
+    let from_clipboard = if has_flag(args, "--from-clipboard") {
+        Some(clipboard::paste_url()?)
+    } else {
+        None
+    };
+    let url = positional(args)
+        .or(from_clipboard)
+        .or_else(|| template.as_ref().map(|t| t.url.clone()))
+        .filter(|u| !u.is_empty())
+        .context("Missing URL. Usage: add <url> [--keywords a,b] [--interval 15m]")?;
+    let url = crate::watcher::normalize_url(&url)?;
+
+    let keywords: Vec<String> = match flag_value(args, "--keywords") {
+        Some(list) => split_keywords(&list),
+        None => template.as_ref().map(|t| t.keywords.clone()).unwrap_or_default(),
+    };
+    let (keywords, keyword_sets) = split_keyword_sets(keywords);
+    if keywords.is_empty() && keyword_sets.is_empty() {
+        anyhow::bail!("At least one keyword is required (--keywords a,b)");
+    }
+    config.check_keyword_sets(&keyword_sets)?;
+    let keywords = keyword_settings(args, keywords)?;
+
+    let interval = match flag_value(args, "--interval") {
+        Some(input) => parse_interval(&input)?,
+        None => template
+            .as_ref()
+            .map(|t| t.check_interval)
+            .unwrap_or(Duration::from_secs(30 * 60)),
+    };
+
+    // Don't create a second watcher for the same URL
+    if let Some(index) = config.find_watcher_by_url(&url) {
+        if !has_flag(args, "--merge") {
+            anyhow::bail!(
+                "A watcher for {} already exists (#{}). \
+                Re-run with --merge to add the keywords to it.",
+                url,
+                index + 1
+            );
+        }
+
+        let existing = &mut config.watchers[index];
+        let added = existing.merge_keywords(&keywords) + existing.merge_keyword_sets(&keyword_sets);
+        println!(
+            "Merged {} new keyword(s) into watcher #{}: {}",
+            added,
+            index + 1,
+            existing.keyword_summary()
+        );
+        config.save()?;
+        return Ok(());
+    }
+
+    // Refuse aggressive intervals on public sites unless explicitly overridden
+    let force_interval = has_flag(args, "--force-interval");
+    if let Some(warning) = config.guardrails.check_interval(&url, interval) {
+        if !force_interval {
+            anyhow::bail!("{} Re-run with --force-interval to override.", warning);
+        }
+        eprintln!("Warning: {}", warning);
+    }
+
+    let mut watcher = Watcher::new(url, keywords, interval);
+    watcher.keyword_sets = keyword_sets;
+    watcher.allow_short_interval = force_interval;
+    if let Some(template) = &template {
+        template.apply(&mut watcher);
+    }
+    if let Some(window) = flag_value(args, "--active") {
+        watcher.active_window = ActiveWindow::parse(&window)?;
+    }
+    if let Some(date) = flag_value(args, "--expires") {
+        watcher.expires_at = parse_expiry(&date, &config.timezone)?;
+    }
+    watcher.disable_after_match = has_flag(args, "--once");
+    watcher.lightweight = has_flag(args, "--lightweight");
+    watcher.critical = has_flag(args, "--critical");
+    watcher.feed = has_flag(args, "--feed");
+    watcher.stream = has_flag(args, "--stream");
+    watcher.notify_cleared = has_flag(args, "--notify-cleared");
+    watcher.pause_when_blocked = has_flag(args, "--pause-when-blocked");
+    watcher.translate = has_flag(args, "--translate");
+    if has_flag(args, "--main-content") {
+        watcher.main_content = Some(true);
+    }
+    if let Some(mode) = flag_value(args, "--content") {
+        watcher.content = Some(ContentMode::parse(&mode)?);
+    }
+    watcher.hook = flag_value(args, "--hook").map(HookSettings::new);
+    if let Some(name) = flag_value(args, "--request-profile") {
+        watcher.request_profile = Some(RequestProfile::parse(&name)?);
+    }
+    watcher.alert_title = flag_value(args, "--alert-title");
+    if let Some(pattern) = flag_value(args, "--track-value") {
+        values::compile(&pattern).with_context(|| format!("Invalid --track-value pattern: {}", pattern))?;
+        watcher.track_value = Some(pattern);
+    }
+    if let Some(threshold) = flag_value(args, "--slow-after") {
+        watcher.latency_threshold_ms = Some(latency::parse_threshold(&threshold)?);
+    }
+    if let Some(percent) = flag_value(args, "--slow-increase") {
+        watcher.latency_increase_percent = Some(latency::parse_percent(&percent)?);
+    }
+    if let Some(gap) = flag_value(args, "--min-recheck-gap") {
+        watcher.min_recheck_gap = Some(parse_interval(&gap)?.as_secs());
+    }
+    if let Some(granularity) = flag_value(args, "--diff") {
+        watcher.diff.granularity = Granularity::parse(&granularity)?;
+    }
+    if let Some(algorithm) = flag_value(args, "--diff-algorithm") {
+        watcher.diff.algorithm = Algorithm::parse(&algorithm)?;
+    }
+    if let Some(count) = flag_value(args, "--consensus") {
+        watcher.consensus_fetches = match count.parse::<u8>() {
+            Ok(n) if (1..=MAX_CONSENSUS_FETCHES).contains(&n) => n,
+            _ => anyhow::bail!("--consensus must be between 1 and {}", MAX_CONSENSUS_FETCHES),
+        };
+    }
+    if let Some(path) = flag_value(args, "--ca-cert") {
+        // Store an absolute path so the daemon finds it from any directory
+        let path = std::fs::canonicalize(&path)
+            .with_context(|| format!("CA certificate not found: {}", path))?;
+        watcher.tls.ca_cert = Some(path);
+        watcher.tls.load_ca_cert()?;
+    }
+    watcher.tls.insecure = has_flag(args, "--insecure");
+    println!(
+        "Added watcher: {} | Keywords: {} | Interval: {}",
+        watcher.url,
+        watcher.keyword_summary(),
+        format_interval(watcher.check_interval)
+    );
+    for keyword in watcher.keywords.iter().filter(|k| !k.is_plain()) {
+        println!("Keyword: {}", keyword.describe());
+    }
+    if let Some(window) = &watcher.active_window {
+        println!("Active hours: {}", window);
+    }
+    if let Some(expires_at) = watcher.expires_at {
+        println!("Expires: {}", format_expiry(expires_at, &config.timezone));
+    }
+    if watcher.disable_after_match {
+        println!("One-shot: disabled after the first match");
+    }
+    if watcher.consensus_fetches > 1 {
+        println!("Consensus: {} fetches per check", watcher.consensus_fetches);
+    }
+    if watcher.lightweight {
+        println!("Lightweight: HEAD request first, full download only on change");
+    }
+    if watcher.critical {
+        println!("Critical: not slowed down in battery saving mode");
+    }
+    if watcher.feed {
+        println!("Feed: included in OPML exports");
+    }
+    if watcher.is_stream() {
+        println!("Stream: each message is matched as it arrives (reconnects after {} of silence)",
+            format_interval(watcher.check_interval));
+    }
+    if watcher.notify_cleared {
+        println!("Cleared alerts: notified when matched keywords disappear again");
+    }
+    if watcher.pause_when_blocked {
+        println!("Blocked: paused when the site answers with a CAPTCHA or block page");
+    }
+    if watcher.translate {
+        println!("Translated: keywords are also matched in the page's language");
+    }
+    if let Some(selector) = &watcher.selector {
+        println!("Selector: only {} is watched", selector);
+    }
+    if !watcher.ignore.is_empty() {
+        println!("Ignored: {}", watcher.ignore.join(", "));
+    }
+    if watcher.main_content == Some(true) {
+        println!("Main content: navigation, footers and banners are ignored");
+    }
+    if watcher.content == Some(ContentMode::Text) {
+        println!("Content: the visible text (scripts, styles and markup are ignored)");
+    }
+    if let Some(hook) = &watcher.hook {
+        println!("Hook: {} (killed after {}s)", hook.command, hook.timeout);
+    }
+    if let Some(profile) = watcher.request_profile {
+        println!("Request profile: {} (sends that browser's headers)", profile);
+    }
+    if let Some(title) = &watcher.alert_title {
+        println!("Alert title: {}", title);
+    }
+    if let Some(threshold) = watcher.latency_threshold_ms {
+        println!("Slow: alarm when a response takes over {}ms", threshold);
+    }
+    if let Some(percent) = watcher.latency_increase_percent {
+        println!("Slow: alarm when a response takes {}% longer than the recent average", percent);
+    }
+    if let Some(pattern) = &watcher.track_value {
+        println!("Tracked value: {} (shown as a chart in the detail view)", pattern);
+    }
+    if let Some(gap) = watcher.min_recheck_gap {
+        println!("Not fetched again within {} of a check", format_interval(Duration::from_secs(gap)));
+    }
+    if watcher.diff != DiffOptions::default() {
+        println!(
+            "Diffs: by {}, {} algorithm",
+            watcher.diff.granularity.name(),
+            watcher.diff.algorithm.name()
+        );
+    }
+    if let Some(path) = &watcher.tls.ca_cert {
+        println!("CA certificate: {}", path.display());
+    }
+    if watcher.tls.insecure {
+        eprintln!("WARNING: TLS certificate verification is DISABLED for this watcher.");
+        eprintln!("         Anyone on the network path can impersonate {}.", watcher.url);
+        eprintln!("         Prefer --ca-cert with the service's CA certificate if you can.");
+    }
+
+    config.watchers.push(watcher);
+    config.save()?;
+
+    Ok(())
+}
+
+/// `import-bookmarks` command: create disabled watchers for the bookmarks in
+/// a folder, to be reviewed (keywords, interval) and enabled in the TUI
+pub fn import_bookmarks(args: &[String]) -> Result<()> {
+    let file = positional(args)
+        .context("Usage: import-bookmarks <file> [--folder <name>] [--keywords a,b] [--interval 1h]")?;
+    let bookmarks = bookmarks::load(Path::new(&file))?;
+    if bookmarks.is_empty() {
+        anyhow::bail!("No web bookmarks found in {}", file);
+    }
+
+    // Without a folder, show what there is to choose from
+    let Some(folder) = flag_value(args, "--folder") else {
+        println!("Folders in {} (bookmarks directly inside):", file);
+        for (path, count) in bookmarks::folders(&bookmarks) {
+            let path = if path.is_empty() { String::from("(top level)") } else { path };
+            println!("  {:>4}  {}", count, path);
+        }
+        println!();
+        println!("Re-run with --folder <name> to import a folder (including its subfolders).");
+        return Ok(());
+    };
+
+    let selected: Vec<_> = bookmarks.iter().filter(|b| b.is_in(&folder)).collect();
+    if selected.is_empty() {
+        anyhow::bail!("No bookmarks in folder '{}' (run without --folder to list folders)", folder);
+    }
+
+    let (keywords, keyword_sets) = split_keyword_sets(
+        flag_value(args, "--keywords")
+            .map(|list| split_keywords(&list))
+            .unwrap_or_default(),
+    );
+    let keywords: Vec<Keyword> = keywords.into_iter().map(Keyword::new).collect();
+    let interval = match flag_value(args, "--interval") {
+        Some(input) => parse_interval(&input)?,
+        None => Duration::from_secs(30 * 60),
+    };
+
+    let mut config = Config::load()?;
+    config.ensure_writable()?;
+    config.check_keyword_sets(&keyword_sets)?;
+    let (mut added, mut skipped) = (0, 0);
+    for bookmark in selected {
+        let url = match crate::watcher::normalize_url(&bookmark.url) {
+            Ok(url) => url,
+            Err(e) => {
+                eprintln!("Skipped {}: {}", bookmark.url, e);
+                skipped += 1;
+                continue;
+            }
+        };
+        if config.find_watcher_by_url(&url).is_some() {
+            println!("Skipped {} (already watched)", url);
+            skipped += 1;
+            continue;
+        }
+
+        // Imported watchers start disabled so nothing is fetched before
+        // the user has reviewed them
+        let mut watcher = Watcher::new(url, keywords.clone(), interval);
+        watcher.keyword_sets = keyword_sets.clone();
+        watcher.enabled = false;
+        watcher.disabled_reason = Some(String::from("Imported from bookmarks"));
+        println!("Imported {} ({})", watcher.url, bookmark.title);
+        config.watchers.push(watcher);
+        added += 1;
+    }
+
+    config.save()?;
+    println!();
+    println!("Imported {} watcher(s), skipped {}.", added, skipped);
+    if keywords.is_empty() && keyword_sets.is_empty() {
+        println!("They start disabled: add keywords and enable them in the TUI (List Watchers: e to edit, t to enable).");
+    } else {
+        println!("They start disabled: review and enable them in the TUI (List Watchers: t to enable).");
+    }
+    Ok(())
+}
+
+/// `import-opml` command: create disabled feed watchers from a feed
+/// reader's subscription list
+pub fn import_opml(args: &[String]) -> Result<()> {
+    let file = positional(args).context("Usage: import-opml <file> [--keywords a,b] [--interval 1h]")?;
+    let feeds = opml::load(Path::new(&file))?;
+    if feeds.is_empty() {
+        anyhow::bail!("No feeds found in {}", file);
+    }
+
+    let (keywords, keyword_sets) = split_keyword_sets(
+        flag_value(args, "--keywords")
+            .map(|list| split_keywords(&list))
+            .unwrap_or_default(),
+    );
+    let keywords: Vec<Keyword> = keywords.into_iter().map(Keyword::new).collect();
+    let interval = match flag_value(args, "--interval") {
+        Some(input) => parse_interval(&input)?,
+        None => Duration::from_secs(30 * 60),
+    };
+
+    let mut config = Config::load()?;
+    config.ensure_writable()?;
+    config.check_keyword_sets(&keyword_sets)?;
+    let (mut added, mut skipped) = (0, 0);
+    for feed in feeds {
+        let url = match crate::watcher::normalize_url(&feed.url) {
+            Ok(url) => url,
+            Err(e) => {
+                eprintln!("Skipped {}: {}", feed.url, e);
+                skipped += 1;
+                continue;
+            }
+        };
+        if config.find_watcher_by_url(&url).is_some() {
+            println!("Skipped {} (already watched)", url);
+            skipped += 1;
+            continue;
+        }
+
+        // Like bookmark imports, start disabled until reviewed
+        let mut watcher = Watcher::new(url, keywords.clone(), interval);
+        watcher.keyword_sets = keyword_sets.clone();
+        watcher.feed = true;
+        watcher.name = Some(feed.title.clone()).filter(|title| *title != feed.url);
+        watcher.enabled = false;
+        watcher.disabled_reason = Some(String::from("Imported from OPML"));
+        println!("Imported {} ({})", watcher.url, feed.title);
+        config.watchers.push(watcher);
+        added += 1;
+    }
+
+    config.save()?;
+    println!();
+    println!("Imported {} feed watcher(s), skipped {}.", added, skipped);
+    if keywords.is_empty() && keyword_sets.is_empty() {
+        println!("They start disabled: add keywords and enable them in the TUI (List Watchers: e to edit, t to enable).");
+    } else {
+        println!("They start disabled: review and enable them in the TUI (List Watchers: t to enable).");
+    }
+    Ok(())
+}
+
+/// `export-opml` command: write the feed watchers as an OPML subscription list
+pub fn export_opml(args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    let feeds: Vec<&Watcher> = config.watchers.iter().filter(|w| w.feed).collect();
+    let document = opml::export(&feeds);
+
+    match positional(args) {
+        Some(file) => {
+            std::fs::write(&file, document)
+                .with_context(|| format!("Failed to write OPML file: {}", file))?;
+            println!("Exported {} feed watcher(s) to {}", feeds.len(), file);
+        }
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+/// `share` command: print a watcher as a `webwatcher://import` link, or
+/// with `--json` as a JSON snippet
+pub fn share(args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    let url = positional(args).context("Missing URL. Usage: share <url> [--json]")?;
+    let index = config
+        .find_watcher_by_url(&url)
+        .with_context(|| format!("No watcher for {}", url))?;
+    let snippet = share::snippet(&config, &config.watchers[index])?;
+
+    if has_flag(args, "--json") {
+        println!("{}", serde_json::to_string_pretty(&snippet)?);
+    } else {
+        println!("{}", share::link(&snippet)?);
+    }
+    Ok(())
+}
+
+/// `import-watcher` command: add a watcher shared with `share`
+pub fn import_watcher(args: &[String]) -> Result<()> {
+    let input = positional(args).context("Missing snippet. Usage: import-watcher <json|link|file|->")?;
+    let input = if input == "-" {
+        let mut input = String::new();
+        std::io::Read::read_to_string(&mut std::io::stdin(), &mut input).context("Failed to read stdin")?;
+        input
+    } else if Path::new(&input).is_file() {
+        std::fs::read_to_string(&input).with_context(|| format!("Failed to read {}", input))?
+    } else {
+        input
+    };
+    let mut watcher = share::to_watcher(&share::parse(&input)?)?;
+
+    let mut config = Config::load()?;
+    config.ensure_writable()?;
+    if let Some(index) = config.find_watcher_by_url(&watcher.url) {
+        anyhow::bail!("A watcher for {} already exists (#{})", watcher.url, index + 1);
+    }
+    if let Some(warning) = config.guardrails.check_interval(&watcher.url, watcher.check_interval) {
+        if !has_flag(args, "--force-interval") {
+            anyhow::bail!("{} Re-run with --force-interval to override.", warning);
+        }
+        eprintln!("Warning: {}", warning);
+        watcher.allow_short_interval = true;
+    }
+
+    println!(
+        "Imported watcher: {} | Keywords: {} | Interval: {}",
+        watcher.url,
+        watcher.keyword_summary(),
+        format_interval(watcher.check_interval)
+    );
+    config.watchers.push(watcher);
+    config.save()
+}
+
+/// `export-values` command: write the time series of tracked values for
+/// analysis elsewhere
+pub fn export_values(args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    let output = flag_value(args, "--output");
+    let format = match flag_value(args, "--format") {
+        Some(name) => ExportFormat::parse(&name)?,
+        None => output
+            .as_deref()
+            .and_then(ExportFormat::from_file_name)
+            .unwrap_or(ExportFormat::Csv),
+    };
+
+    // The named watchers (whatever they track now), or all tracking a value
+    let urls = positionals(args);
+    let watchers: Vec<&Watcher> = if urls.is_empty() {
+        config.watchers.iter().filter(|w| w.track_value.is_some()).collect()
+    } else {
+        urls.iter()
+            .map(|url| {
+                let index = config
+                    .find_watcher_by_url(url)
+                    .with_context(|| format!("No watcher for {}", url))?;
+                Ok(&config.watchers[index])
+            })
+            .collect::<Result<_>>()?
+    };
+    if watchers.is_empty() {
+        anyhow::bail!("No watchers track a value (add one with --track-value)");
+    }
+
+    let series = watchers
+        .into_iter()
+        .map(values::series)
+        .collect::<Result<Vec<_>>>()?;
+    let document = format.write(&series)?;
+
+    match output {
+        Some(file) => {
+            std::fs::write(&file, document).with_context(|| format!("Failed to write export file: {}", file))?;
+            let count: usize = series.iter().map(|one| one.points.len()).sum();
+            println!("Exported {} value(s) of {} watcher(s) to {}", count, series.len(), file);
+        }
+        None => print!("{}", document),
+    }
+    Ok(())
+}
+
+/// `matches` command: list the keyword matches of the given watchers (or
+/// all of them) in the last day, or since `--since`, newest first
+pub fn list_matches(args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    let period = match flag_value(args, "--since") {
+        Some(input) => parse_interval(&input)?,
+        None => Duration::from_secs(24 * 60 * 60),
+    };
+    let since = chrono::Utc::now() - chrono::Duration::from_std(period)?;
+
+    let urls = positionals(args);
+    let watchers: Vec<&Watcher> = if urls.is_empty() {
+        config.watchers.iter().collect()
+    } else {
+        urls.iter()
+            .map(|url| {
+                let index = config
+                    .find_watcher_by_url(url)
+                    .with_context(|| format!("No watcher for {}", url))?;
+                Ok(&config.watchers[index])
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let mut found = Vec::new();
+    for watcher in watchers {
+        for record in history::load(&watcher.id, usize::MAX)? {
+            if let history::CheckOutcome::Matched { keywords } = record.outcome {
+                if record.checked_at >= since {
+                    found.push((record.checked_at, watcher.url.as_str(), keywords));
+                }
+            }
+        }
+    }
+    found.sort_by_key(|(at, _, _)| std::cmp::Reverse(*at));
+
+    if has_flag(args, "--json") {
+        let entries: Vec<serde_json::Value> = found
+            .iter()
+            .map(|(at, url, keywords)| {
+                let checked_at = at.to_rfc3339_opts(chrono::SecondsFormat::Secs, true);
+                serde_json::json!({ "url": url, "checked_at": checked_at, "keywords": keywords })
+            })
+            .collect();
+        println!("{}", serde_json::to_string_pretty(&entries)?);
+        return Ok(());
+    }
+
+    if found.is_empty() {
+        println!("No matches since {}", config.timezone.format(since, "%Y-%m-%d %H:%M"));
+    }
+    for (at, url, keywords) in &found {
+        println!("{}  {}: {}", config.timezone.format(*at, "%Y-%m-%d %H:%M"), url, keywords.join(", "));
+    }
+    Ok(())
+}
+
+/// `archive` and `unarchive` commands: put watchers away (kept with their
+/// history, never checked) or bring them back. Without URLs, `archive`
+/// lists the archived watchers.
+pub fn archive(args: &[String], archived: bool) -> Result<()> {
+    let mut config = Config::load()?;
+    let urls = positionals(args);
+    if urls.is_empty() {
+        if !archived {
+            anyhow::bail!("Missing URL. Usage: unarchive <url...>");
+        }
+        let listed: Vec<&Watcher> = config.watchers.iter().filter(|w| w.archived).collect();
+        if listed.is_empty() {
+            println!("No archived watchers");
+        }
+        for watcher in listed {
+            println!("{} | Keywords: {}", watcher.url, watcher.keyword_summary());
+        }
+        return Ok(());
+    }
+
+    config.ensure_writable()?;
+    for url in &urls {
+        let index = config
+            .find_watcher_by_url(url)
+            .with_context(|| format!("No watcher for {}", url))?;
+        let watcher = &mut config.watchers[index];
+        if watcher.archived == archived {
+            let state = if archived { "already archived" } else { "not archived" };
+            println!("{} is {}", watcher.url, state);
+            continue;
+        }
+        watcher.archived = archived;
+        println!("{} {}", if archived { "Archived" } else { "Restored" }, watcher.url);
+    }
+    config.save()
+}
+
+/// `prune` command: thin out the check histories as the `history`
+/// settings say (the daemon does this too), or with `--dry-run` show what
+/// would go
+pub fn prune(args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    let dry_run = has_flag(args, "--dry-run");
+    let urls = positionals(args);
+    let watchers: Vec<&Watcher> = if urls.is_empty() {
+        config.watchers.iter().collect()
+    } else {
+        urls.iter()
+            .map(|url| {
+                config
+                    .find_watcher_by_url(url)
+                    .map(|index| &config.watchers[index])
+                    .with_context(|| format!("No watcher for {}", url))
+            })
+            .collect::<Result<_>>()?
+    };
+
+    let mut total = history::Pruned::default();
+    for watcher in watchers {
+        let pruned = history::prune(&watcher.id, &config.history, dry_run)?;
+        if pruned.removed > 0 {
+            println!(
+                "{}: {} of {} checks, {} → {}",
+                watcher.url,
+                pruned.removed,
+                pruned.kept + pruned.removed,
+                ui::format_bytes(pruned.bytes_before),
+                ui::format_bytes(pruned.bytes_after)
+            );
+        }
+        total.kept += pruned.kept;
+        total.removed += pruned.removed;
+        total.bytes_before += pruned.bytes_before;
+        total.bytes_after += pruned.bytes_after;
+    }
+
+    let verb = if dry_run { "Would remove" } else { "Removed" };
+    println!(
+        "{} {} check record(s), keeping {} ({} → {})",
+        verb,
+        total.removed,
+        total.kept,
+        ui::format_bytes(total.bytes_before),
+        ui::format_bytes(total.bytes_after)
+    );
+    Ok(())
+}
+
+/// `encryption` command: show whether the caches and histories are
+/// encrypted, or turn encryption on (creating a key in the keychain) or off,
+/// rewriting the existing files either way
+pub fn encryption(args: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    let enable = match positional(args).as_deref() {
+        None => {
+            println!(
+                "Encryption at rest: {}",
+                if config.encryption.enabled { "on" } else { "off" }
+            );
+            if config.encryption.enabled && !crypto::has_key() {
+                println!(
+                    "⚠ No key: run `web-watcher-alert encryption on` where it was created, or set {}",
+                    crypto::KEY_ENV
+                );
+            }
+            return Ok(());
+        }
+        Some("on") => true,
+        Some("off") => false,
+        Some(other) => anyhow::bail!("Unknown encryption command: {} (use on or off)", other),
+    };
+    config.ensure_writable()?;
+
+    // Turning it off needs the key too (to decrypt what's there), but
+    // doesn't create one
+    let key = if enable { crypto::ensure_key()? } else { "" };
+    let encryption = EncryptionSettings { enabled: enable };
+    let mut rewritten = 0;
+    for watcher in &config.watchers {
+        rewritten += cache::reseal(&watcher.full_cache_path()?, &encryption)?;
+        rewritten += usize::from(history::reseal(&watcher.id, &encryption)?);
+    }
+    rewritten += usize::from(review::reseal(&encryption)?);
+    if config.encryption.enabled != enable {
+        config.encryption.enabled = enable;
+        config.save()?;
+    }
+
+    if enable {
+        println!("Encryption at rest is on (key in {}); {} file(s) encrypted", key, rewritten);
+    } else {
+        println!("Encryption at rest is off; {} file(s) decrypted", rewritten);
+    }
+    println!("Restart the daemon to apply");
+    Ok(())
+}
+
+/// `trash` command: list the deleted watchers, `trash restore <url|id>` one
+/// back, or `trash empty` to delete them for good
+pub fn trash(args: &[String]) -> Result<()> {
+    let words = positionals(args);
+    match words.first().map(String::as_str) {
+        None => {
+            let entries = trash::list()?;
+            if entries.is_empty() {
+                println!("The trash is empty");
+            }
+            let days = Config::load()?.trash.days;
+            for entry in entries {
+                let purged = entry.deleted_at + chrono::Duration::days(i64::from(days));
+                println!(
+                    "{} | Keywords: {} | Deleted {} (until {})",
+                    entry.watcher.url,
+                    entry.watcher.keyword_summary(),
+                    entry.deleted_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                    purged.with_timezone(&chrono::Local).format("%Y-%m-%d")
+                );
+            }
+            Ok(())
+        }
+        Some("restore") => {
+            let wanted = words.get(1).context("Missing URL. Usage: trash restore <url|id>")?;
+            let entries = trash::list()?;
+            let url = normalize_url(wanted).ok();
+            let entry = entries
+                .iter()
+                .find(|entry| &entry.watcher.id == wanted || Some(&entry.watcher.url) == url.as_ref())
+                .with_context(|| format!("No deleted watcher for {}", wanted))?;
+
+            let mut config = Config::load()?;
+            config.ensure_writable()?;
+            if config.find_watcher_by_url(&entry.watcher.url).is_some() {
+                anyhow::bail!("There is already a watcher for {}", entry.watcher.url);
+            }
+            let watcher = trash::restore(&entry.watcher.id)?;
+            println!("Restored {}", watcher.url);
+            config.watchers.push(watcher);
+            config.save()
+        }
+        Some("empty") => {
+            let purged = trash::purge(0)?;
+            println!("Deleted {} watcher(s) for good", purged);
+            Ok(())
+        }
+        Some(other) => anyhow::bail!("Unknown trash command: {} (use restore or empty)", other),
+    }
+}
+
+/// `matrix` command: list the matrices, `matrix set <name>` one (and expand
+/// it into its watchers), `matrix remove <name>` or `matrix apply [name]`
+/// after editing them in the config file
+pub fn matrix(args: &[String]) -> Result<()> {
+    let words = positionals(args);
+    let mut config = Config::load()?;
+    let name = words.get(1).cloned();
+
+    let names: Vec<String> = match words.first().map(String::as_str) {
+        None => {
+            if config.matrices.is_empty() {
+                println!("No matrices. Create one with `matrix set <name> --urls <a,b> --values <x,y> --keywords <k>`");
+            }
+            for matrix in &config.matrices {
+                let children: Vec<&Watcher> =
+                    config.watchers.iter().filter(|w| w.matrix.as_ref() == Some(&matrix.name)).collect();
+                let enabled = children.iter().filter(|w| w.is_active()).count();
+                println!(
+                    "{} | {} URL(s) × {} value(s) = {} watchers ({} enabled) | Keywords: {} | Every {}",
+                    matrix.name,
+                    matrix.urls.len(),
+                    matrix.values.len(),
+                    children.len(),
+                    enabled,
+                    matrix
+                        .keywords
+                        .iter()
+                        .map(|k| k.text.clone())
+                        .chain(matrix.keyword_sets.iter().map(|set| format!("@{}", set)))
+                        .collect::<Vec<_>>()
+                        .join(", "),
+                    format_interval(matrix.check_interval)
+                );
+            }
+            return Ok(());
+        }
+        Some("set") => {
+            let name = name.context("Missing name. Usage: matrix set <name> --urls <a,b> --values <x,y> --keywords <k>")?;
+            let existing = config.matrices.iter().position(|m| m.name == name);
+            let mut matrix = match existing {
+                Some(index) => config.matrices[index].clone(),
+                None => Matrix {
+                    name: name.clone(),
+                    urls: Vec::new(),
+                    values: Vec::new(),
+                    keywords: Vec::new(),
+                    keyword_sets: Vec::new(),
+                    check_interval: Duration::from_secs(30 * 60),
+                },
+            };
+            if let Some(list) = flag_value(args, "--urls") {
+                matrix.urls = split_keywords(&list);
+            }
+            if let Some(list) = flag_value(args, "--values") {
+                matrix.values = split_keywords(&list);
+            }
+            if let Some(list) = flag_value(args, "--keywords") {
+                let (keywords, keyword_sets) = split_keyword_sets(split_keywords(&list));
+                config.check_keyword_sets(&keyword_sets)?;
+                matrix.keywords = keyword_settings(args, keywords)?;
+                matrix.keyword_sets = keyword_sets;
+            }
+            if let Some(input) = flag_value(args, "--interval") {
+                matrix.check_interval = parse_interval(&input)?;
+            }
+            if matrix.urls.is_empty() {
+                anyhow::bail!("The matrix needs URL templates: --urls https://example.com/p/{{value}},...");
+            }
+            match existing {
+                Some(index) => config.matrices[index] = matrix,
+                None => config.matrices.push(matrix),
+            }
+            vec![name]
+        }
+        Some("remove") => {
+            let name = name.context("Missing name. Usage: matrix remove <name>")?;
+            config.ensure_writable()?;
+            let children = matrix::remove(&mut config, &name)?;
+            config.save()?;
+            for child in &children {
+                trash::discard(&config.trash, child)?;
+            }
+            println!("Removed the matrix {} and its {} watcher(s)", name, children.len());
+            return Ok(());
+        }
+        Some("apply") => match name {
+            Some(name) => vec![name],
+            None => config.matrices.iter().map(|m| m.name.clone()).collect(),
+        },
+        Some(other) => anyhow::bail!("Unknown matrix command: {} (use set, remove or apply)", other),
+    };
+
+    config.ensure_writable()?;
+    let mut removed = Vec::new();
+    let mut lines = Vec::new();
+    for name in &names {
+        let expanded = matrix::expand(&mut config, name)?;
+        let count = config.watchers.iter().filter(|w| w.matrix.as_ref() == Some(name)).count();
+        lines.push(format!(
+            "Matrix {}: {} watchers ({} added, {} updated, {} removed)",
+            name,
+            count,
+            expanded.added,
+            expanded.updated,
+            expanded.removed.len()
+        ));
+        removed.extend(expanded.removed);
+    }
+    config.save()?;
+    for watcher in &removed {
+        trash::discard(&config.trash, watcher)?;
+    }
+    for line in lines {
+        println!("{}", line);
+    }
+    Ok(())
+}
+
+/// `config` command: `config history` lists the recorded revisions of the
+/// config, newest first; `config show <n>` prints one, `config rollback <n>`
+/// goes back to it (as a new revision)
+pub fn config(args: &[String]) -> Result<()> {
+    let words = positionals(args);
+    let revision = |words: &[String]| -> Result<config_history::Revision> {
+        let number = words.get(1).context("Missing revision number")?;
+        let number: u64 = number
+            .trim_start_matches('#')
+            .parse()
+            .with_context(|| format!("Invalid revision number: {}", number))?;
+        config_history::load()?
+            .into_iter()
+            .find(|revision| revision.number == number)
+            .with_context(|| format!("No revision {} in the config history", number))
+    };
+
+    match words.first().map(String::as_str) {
+        Some("history") => {
+            let limit = match flag_value(args, "--limit") {
+                Some(limit) => limit.parse().with_context(|| format!("Invalid --limit: {}", limit))?,
+                None => 20,
+            };
+            let revisions = config_history::load()?;
+            if has_flag(args, "--json") {
+                let newest: Vec<_> = revisions.iter().rev().take(limit).collect();
+                println!("{}", serde_json::to_string_pretty(&newest)?);
+                return Ok(());
+            }
+            if revisions.is_empty() {
+                println!("No config changes recorded yet");
+            }
+            for revision in revisions.iter().rev().take(limit) {
+                println!(
+                    "#{} {} by {} ({})",
+                    revision.number,
+                    revision.saved_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M:%S"),
+                    revision.user,
+                    revision.command
+                );
+                for change in &revision.changes {
+                    println!("    {}", change);
+                }
+            }
+            Ok(())
+        }
+        Some("show") => {
+            let revision = revision(&words)?;
+            println!("{}", serde_json::to_string_pretty(&revision.settings)?);
+            Ok(())
+        }
+        Some("rollback") => {
+            let revision = revision(&words)?;
+            let config = Config::load()?;
+            config.ensure_writable()?;
+            let rolled_back = config_history::rolled_back(&revision, &config)?;
+            rolled_back.save()?;
+            println!("Rolled back to revision {} (restart the daemon to apply)", revision.number);
+            Ok(())
+        }
+        Some(other) => anyhow::bail!("Unknown config command: {} (use history, show or rollback)", other),
+        None => anyhow::bail!("Missing command. Usage: config history | show <revision> | rollback <revision>"),
+    }
+}
+
+/// `remote` command: show where the settings are synced to and when they
+/// last were, or `remote push` / `remote pull` them now
+pub fn remote(args: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    let force = has_flag(args, "--force");
+    match positional(args).as_deref() {
+        None => {
+            let Some(backend) = config.remote.backend else {
+                println!("No remote: set \"remote\" in the config to sync the settings (see the README)");
+                return Ok(());
+            };
+            println!("Remote: {} {}", backend.name(), config.remote.location);
+            if config.remote.history {
+                println!("Check histories are synced too");
+            }
+            match remote::SyncState::load()?.synced_at {
+                Some(at) => println!("Last synced: {}", config.timezone.format(at, "%Y-%m-%d %H:%M")),
+                None => println!("Not synced yet: run `web-watcher-alert remote push` (or pull)"),
+            }
+            Ok(())
+        }
+        Some("push") => {
+            match remote::push(&config, force)? {
+                remote::Pushed::UpToDate => println!("The remote is up to date"),
+                remote::Pushed::Uploaded { histories } if config.remote.history => {
+                    println!("Pushed the settings and {} check history(ies)", histories)
+                }
+                remote::Pushed::Uploaded { .. } => println!("Pushed the settings"),
+            }
+            Ok(())
+        }
+        Some("pull") => {
+            config.ensure_writable()?;
+            let pulled = remote::pull(&mut config, force)?;
+            if pulled.applied {
+                config.save()?;
+                println!("Pulled the settings: {} watcher(s) (restart the daemon to apply)", config.watchers.len());
+            } else if pulled.ahead {
+                println!("Nothing to pull: the settings here are newer (run `web-watcher-alert remote push`)");
+            } else {
+                println!("The settings are up to date");
+            }
+            if pulled.records > 0 {
+                println!("Merged {} check record(s) from other machines", pulled.records);
+            }
+            Ok(())
+        }
+        Some(other) => anyhow::bail!("Unknown remote command: {} (use push or pull)", other),
+    }
+}
+
+/// `cluster` command: show this daemon's part in a cluster, or make it the
+/// coordinator, an agent or neither
+pub fn cluster(args: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    let before = config.notifications.cluster.clone();
+    let settings = &mut config.notifications.cluster;
+    match positional(args).as_deref() {
+        None => {
+            match settings.role {
+                None => println!("Not in a cluster: alerts are delivered directly"),
+                Some(cluster::Role::Coordinator) => {
+                    println!("Coordinator, listening on {}", settings.listen);
+                    println!("Alerts from other machines within {}s of each other are dropped", settings.dedupe_window);
+                }
+                Some(cluster::Role::Agent) => {
+                    let url = settings.coordinator.clone().unwrap_or_default();
+                    println!("Agent \"{}\", reporting to {}", settings.agent_name(), url);
+                    let runtime = tokio::runtime::Runtime::new()?;
+                    match runtime.block_on(cluster::fetch_status(settings)) {
+                        Ok(agents) => {
+                            println!("The coordinator is reachable; it heard from {} machine(s):", agents.len());
+                            for agent in agents {
+                                println!(
+                                    "  {} (last seen {}, {} alert(s), {} duplicate(s))",
+                                    agent["name"].as_str().unwrap_or_default(),
+                                    agent["last_seen"].as_str().unwrap_or_default(),
+                                    agent["alerts"],
+                                    agent["duplicates"]
+                                );
+                            }
+                        }
+                        Err(e) => println!("✗ {:#} (alerts are delivered here meanwhile)", e),
+                    }
+                }
+            }
+            return Ok(());
+        }
+        Some("coordinator") => {
+            settings.role = Some(cluster::Role::Coordinator);
+            settings.coordinator = None;
+            if let Some(listen) = flag_value(args, "--listen") {
+                listen
+                    .parse::<std::net::SocketAddr>()
+                    .with_context(|| format!("Invalid address: {} (like 0.0.0.0:7392)", listen))?;
+                settings.listen = listen;
+            }
+            if settings.token.is_none() {
+                settings.token = Some(companion::new_token());
+            }
+        }
+        Some("agent") => {
+            let url = positionals(args)
+                .get(1)
+                .cloned()
+                .context("Usage: web-watcher-alert cluster agent <coordinator url> --token <token>")?;
+            url::Url::parse(&url).with_context(|| format!("Invalid coordinator URL: {}", url))?;
+            let token = flag_value(args, "--token")
+                .or_else(|| settings.token.clone())
+                .context("Give the coordinator's token with --token (`cluster` on the coordinator shows it)")?;
+            settings.role = Some(cluster::Role::Agent);
+            settings.coordinator = Some(url);
+            settings.token = Some(token);
+            if let Some(name) = flag_value(args, "--name") {
+                settings.name = Some(name);
+            }
+        }
+        Some("off") => settings.role = None,
+        Some(other) => anyhow::bail!("Unknown cluster command: {} (use coordinator, agent or off)", other),
+    }
+
+    let settings = config.notifications.cluster.clone();
+    if settings != before {
+        config.ensure_writable()?;
+        config.save()?;
+    }
+    match settings.role {
+        None => println!("Alerts are delivered directly (restart the daemon to apply)"),
+        Some(cluster::Role::Coordinator) => {
+            let token = settings.token.unwrap_or_default();
+            println!("This daemon is the coordinator (restart it to apply)");
+            println!();
+            println!("On each of the other machines, run:");
+            println!(
+                "  web-watcher-alert cluster agent http://{}:{} --token {}",
+                remote::hostname(),
+                settings.listen.rsplit(':').next().unwrap_or_default(),
+                token
+            );
+        }
+        Some(cluster::Role::Agent) => println!(
+            "Alerts are reported to {} as \"{}\" (restart the daemon to apply)",
+            settings.coordinator.as_deref().unwrap_or_default(),
+            settings.agent_name()
+        ),
+    }
+    Ok(())
+}
+
+/// `calendar` command: list, add or remove the calendars of quiet and
+/// paused periods
+pub fn calendar(args: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    let positionals = positionals(args);
+    match positionals.first().map(String::as_str) {
+        None => {
+            if config.calendars.is_empty() {
+                println!("No calendars: add one with `calendar add <url>`");
+                return Ok(());
+            }
+            let runtime = tokio::runtime::Runtime::new()?;
+            let now = chrono::Utc::now();
+            for settings in &config.calendars {
+                let filter = match &settings.summary {
+                    Some(summary) => format!(", events with \"{}\"", summary),
+                    None => String::new(),
+                };
+                println!("{} ({}{})", settings.url, settings.action.name(), filter);
+                match runtime.block_on(calendar::load(settings, config.timezone)) {
+                    Ok(events) if events.is_empty() => println!("  No events in the next two weeks"),
+                    Ok(events) => {
+                        for event in events.iter().take(10) {
+                            println!(
+                                "  {}{} - {}  {}",
+                                if event.start <= now { "now: " } else { "" },
+                                config.timezone.format(event.start, "%a %d %b %H:%M"),
+                                config.timezone.format(event.end, "%a %d %b %H:%M"),
+                                event.summary
+                            );
+                        }
+                    }
+                    Err(e) => println!("  ✗ {:#}", e),
+                }
+            }
+            return Ok(());
+        }
+        Some("add") => {
+            let url = positionals.get(1).context("Usage: calendar add <url> [--pause] [--summary <text>]")?;
+            let settings = calendar::CalendarSettings {
+                url: url.clone(),
+                action: if has_flag(args, "--pause") { calendar::CalendarAction::Pause } else { calendar::CalendarAction::Quiet },
+                summary: flag_value(args, "--summary"),
+            };
+            let runtime = tokio::runtime::Runtime::new()?;
+            let events = runtime
+                .block_on(calendar::load(&settings, config.timezone))
+                .with_context(|| format!("Failed to read the calendar {}", url))?;
+            config.calendars.retain(|c| c.url != *url);
+            config.calendars.push(settings);
+            config.ensure_writable()?;
+            config.save()?;
+            println!("Added the calendar {} ({} event(s) in the next two weeks)", url, events.len());
+        }
+        Some("remove") => {
+            let url = positionals.get(1).context("Usage: calendar remove <url>")?;
+            let before = config.calendars.len();
+            config.calendars.retain(|c| c.url != *url);
+            if config.calendars.len() == before {
+                anyhow::bail!("No calendar with the URL {}", url);
+            }
+            config.ensure_writable()?;
+            config.save()?;
+            println!("Removed the calendar {}", url);
+        }
+        Some(other) => anyhow::bail!("Unknown calendar command: {} (use add or remove)", other),
+    }
+    println!("Restart the daemon to apply");
+    Ok(())
+}
+
+/// `companion` command: turn on the endpoint for browser extensions (with
+/// a new token if there's none) and show how to call it
+pub fn companion(args: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    if has_flag(args, "--disable") {
+        config.ensure_writable()?;
+        config.companion.enabled = false;
+        config.save()?;
+        println!("Companion endpoint disabled (restart the daemon to apply)");
+        return Ok(());
+    }
+
+    let before = config.companion.clone();
+    let settings = &mut config.companion;
+    settings.enabled = true;
+    if let Some(port) = flag_value(args, "--port") {
+        settings.port = port.parse().with_context(|| format!("Invalid port: {}", port))?;
+    }
+    if settings.token.is_none() || has_flag(args, "--new-token") {
+        settings.token = Some(companion::new_token());
+    }
+    let (port, token) = (settings.port, settings.token.clone().unwrap_or_default());
+    if config.companion != before {
+        config.ensure_writable()?;
+        config.save()?;
+        println!("Companion endpoint enabled (restart the daemon to apply)");
+        println!();
+    }
+
+    println!("Endpoint: POST http://127.0.0.1:{}/watch", port);
+    println!("Token:    {}", token);
+    println!();
+    println!("Send the page's URL and the selected text, which becomes the keywords:");
+    println!("  Authorization: Bearer {}", token);
+    println!("  {{\"url\": \"https://example.com/item\", \"selection\": \"Back in stock\"}}");
+    println!();
+    println!("Bookmarklet (select text on a page, then click it):");
+    println!("{}", companion::bookmarklet(port, &token));
+    Ok(())
+}
+
+/// `triggers` command: turn on the endpoint for checks triggered from
+/// outside (with a new token if there's none) and show each watcher's URL
+pub fn triggers(args: &[String]) -> Result<()> {
+    let mut config = Config::load()?;
+    if has_flag(args, "--disable") {
+        config.ensure_writable()?;
+        config.triggers.enabled = false;
+        config.save()?;
+        println!("Trigger endpoint disabled (restart the daemon to apply)");
+        return Ok(());
+    }
+
+    let before = config.triggers.clone();
+    let settings = &mut config.triggers;
+    settings.enabled = true;
+    if let Some(listen) = flag_value(args, "--listen") {
+        listen
+            .parse::<std::net::SocketAddr>()
+            .with_context(|| format!("Invalid listen address: {} (use e.g. 0.0.0.0:7393)", listen))?;
+        settings.listen = listen;
+    }
+    if settings.token.is_none() || has_flag(args, "--new-token") {
+        settings.token = Some(companion::new_token());
+    }
+    let (listen, token) = (settings.listen.clone(), settings.token.clone().unwrap_or_default());
+    if config.triggers != before {
+        config.ensure_writable()?;
+        config.save()?;
+        println!("Trigger endpoint enabled (restart the daemon to apply)");
+        println!();
+    }
+
+    println!("Endpoint: POST http://{}/trigger/<watcher id>", listen);
+    println!("Token:    {}", token);
+    println!();
+    println!("Send the token as `Authorization: Bearer <token>`:");
+    println!("  curl -X POST -H \"Authorization: Bearer {}\" http://{}/trigger/<watcher id>", token, listen);
+    let watchers: Vec<_> = config.watchers.iter().filter(|w| w.is_active() && !w.is_stream()).collect();
+    if !watchers.is_empty() {
+        println!();
+        for watcher in watchers {
+            println!("  {}  http://{}/trigger/{}", watcher.url, listen, watcher.id);
+        }
+    }
+    Ok(())
+}
+
+/// `notify test` command: send a sample alert through each enabled channel
+/// (or the one given with `--channel`), to try the settings without waiting
+/// for a match
+pub fn notify(args: &[String]) -> Result<()> {
+    match positional(args).as_deref() {
+        Some("test") => {}
+        Some(other) => anyhow::bail!("Unknown notify command: {} (use test)", other),
+        None => anyhow::bail!("Usage: web-watcher-alert notify test [--channel <name>]"),
+    }
+    let config = Config::load()?;
+    let settings = &config.notifications;
+    let notifiers = notify::Notifiers::local(settings);
+
+    let only = flag_value(args, "--channel").map(|name| name.trim().to_lowercase());
+    if let Some(name) = &only {
+        if notifiers.get(name).is_none() {
+            match Channel::from_name(name) {
+                Some(Channel::Desktop) if settings.desktop => {
+                    anyhow::bail!("The desktop channel is enabled, but there's no desktop session here")
+                }
+                Some(channel) => anyhow::bail!("The {} channel isn't enabled", channel.name()),
+                None => {
+                    Channel::parse(name)?;
+                }
+            }
+        }
+    }
+    if notifiers.is_empty() {
+        anyhow::bail!("No channel is enabled (or works here): matches are only logged");
+    }
+    if settings.cluster.role == Some(cluster::Role::Agent) {
+        println!("This machine reports its alerts to the coordinator (see `cluster`); testing its own channels");
+    }
+    if only.is_none() && settings.desktop && notifiers.get(Channel::Desktop.name()).is_none() {
+        println!("- desktop: skipped (no desktop session here)");
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let results = runtime.block_on(notifiers.send_test(only.as_deref()));
+    let mut failed = 0;
+    for (name, result) in &results {
+        match result {
+            Ok(()) => println!("✓ {}: sent", name),
+            Err(e) => {
+                failed += 1;
+                println!("✗ {}: {:#}", name, e);
+            }
+        }
+    }
+    if failed > 0 {
+        anyhow::bail!("{} of {} channel(s) failed", failed, results.len());
+    }
+    Ok(())
+}
+
+/// `templates` command: list built-in and user-defined templates
+pub fn list_templates() -> Result<()> {
+    let config = Config::load()?;
+
+    for template in templates::all_templates(&config) {
+        println!("{:<22} {}", template.name, template.description);
+        println!(
+            "{:<22} Keywords: {} | Interval: {}",
+            "",
+            template.keywords.join(", "),
+            format_interval(template.check_interval)
+        );
+        let mut watched = Vec::new();
+        if let Some(selector) = &template.selector {
+            watched.push(format!("Selector: {}", selector));
+        }
+        if !template.ignore.is_empty() {
+            watched.push(format!("Ignored: {}", template.ignore.join(", ")));
+        }
+        if template.main_content == Some(true) {
+            watched.push(String::from("Main content"));
+        }
+        if let Some(content) = template.content {
+            watched.push(format!("Content: {}", content.name()));
+        }
+        if !watched.is_empty() {
+            println!("{:<22} {}", "", watched.join(" | "));
+        }
+    }
+
+    Ok(())
+}
+
+/// `keyword-sets` command: list the shared keyword sets and their users
+pub fn list_keyword_sets() -> Result<()> {
+    let config = Config::load()?;
+    if config.keyword_sets.is_empty() {
+        println!("No keyword sets. Define them under \"keyword_sets\" in the config, e.g.");
+        println!("  \"keyword_sets\": {{ \"gpu-models\": [\"RTX 5090\", \"RX 9070\"] }}");
+        println!("and use them in a watcher's keywords as @gpu-models.");
+        return Ok(());
+    }
+
+    for (name, keywords) in &config.keyword_sets {
+        let users = config
+            .watchers
+            .iter()
+            .filter(|w| w.keyword_sets.contains(name))
+            .count();
+        println!("@{:<21} {} keyword(s), used by {} watcher(s)", name, keywords.len(), users);
+        let list = keywords.iter().map(|k| k.describe()).collect::<Vec<_>>().join(", ");
+        println!("{:<22} {}", "", if list.is_empty() { "(empty)" } else { &list });
+    }
+
+    Ok(())
+}
+
+/// `users` command: the users of the system installation and all their
+/// watchers in one list
+pub fn list_users() -> Result<()> {
+    let users = system::users()?;
+    if users.is_empty() {
+        println!("No users in {}.", system::system_dir().display());
+        println!("Add one with `web-watcher-alert --user <name> add <url> --keywords <a,b,c>`.");
+        return Ok(());
+    }
+
+    let mut watchers = Vec::new();
+    for name in &users {
+        let config = match system::load_user(name) {
+            Ok(config) => config,
+            Err(e) => {
+                println!("{:<16} ✗ {:#}", name, e);
+                continue;
+            }
+        };
+
+        let settings = &config.notifications;
+        let mut channels = Vec::new();
+        if settings.desktop {
+            channels.push("desktop");
+        }
+        if settings.webhook_url.as_deref().is_some_and(|url| !url.trim().is_empty()) {
+            channels.push("webhook");
+        }
+        if let Some(target) = settings.tasks.target {
+            channels.push(target.name());
+        }
+        let enabled = config.watchers.iter().filter(|w| w.is_active()).count();
+        println!(
+            "{:<16} {} watcher(s), {} enabled | Alerts: {}",
+            name,
+            config.watchers.len(),
+            enabled,
+            if channels.is_empty() { "none (only logged)".to_string() } else { channels.join(", ") }
+        );
+
+        watchers.extend(config.watchers.into_iter().map(|w| (name.clone(), w)));
+    }
+
+    if !watchers.is_empty() {
+        println!();
+        for (user, watcher) in &watchers {
+            println!(
+                "{:<16} {} {} (every {})",
+                user,
+                if watcher.is_active() { "✓" } else { "-" },
+                watcher.url,
+                format_interval(watcher.check_interval)
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// `check` command: check watchers once, like the monitor does (alerts are
+/// sent and results saved), and exit with a status scripts can branch on
+pub fn check(args: &[String], verbosity: Verbosity) -> Result<ExitCode> {
+    let config = Config::load()?;
+
+    // The named watchers (enabled or not), or all enabled ones. Streaming
+    // watchers have no page to check.
+    let urls = positionals(args);
+    let ids: Vec<String> = if urls.is_empty() {
+        config
+            .watchers
+            .iter()
+            .filter(|w| w.is_active() && !w.is_stream())
+            .map(|w| w.id.clone())
+            .collect()
+    } else {
+        urls.iter()
+            .map(|url| {
+                let index = config
+                    .find_watcher_by_url(url)
+                    .with_context(|| format!("No watcher for {}", url))?;
+                let watcher = &config.watchers[index];
+                if watcher.is_stream() {
+                    anyhow::bail!("{} is a stream: it's only watched by the daemon", watcher.url);
+                }
+                Ok(watcher.id.clone())
+            })
+            .collect::<Result<_>>()?
+    };
+    if ids.is_empty() {
+        anyhow::bail!("No enabled watchers to check");
+    }
+
+    // Verbose output is the monitor's log; otherwise the summary below is
+    // all there is (events sent to a dropped channel are discarded)
+    let log = match verbosity {
+        Verbosity::Verbose => EventLog::stdout(config.timezone, LogFormat::Text),
+        _ => EventLog::channel(config.timezone).0,
+    };
+    let zone = config.timezone;
+    let monitor = Monitor::with_event_log(config, log);
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let reports = runtime.block_on(monitor.check_once(&ids));
+
+    if verbosity != Verbosity::Quiet {
+        for report in &reports {
+            let line = if let Some(error) = &report.error {
+                format!("✗ {}: {}", report.url, error)
+            } else if !report.matched_keywords.is_empty() {
+                format!("✓ {}: matched {}", report.url, report.matched_keywords.join(", "))
+            } else if report.changed {
+                format!("~ {}: changed", report.url)
+            } else {
+                format!("- {}: no changes", report.url)
+            };
+            let disabled = if report.disabled { " (watcher disabled)" } else { "" };
+            let cached = match report.cached_at {
+                Some(at) => format!(" (checked at {})", zone.format(at, "%H:%M:%S")),
+                None => String::new(),
+            };
+            println!("{}{}{}", line, disabled, cached);
+        }
+    }
+
+    let matched = reports.iter().filter(|r| !r.matched_keywords.is_empty()).count();
+    let failed = reports.iter().filter(|r| r.error.is_some()).count();
+    if verbosity != Verbosity::Quiet {
+        println!("Checked {} watchers: {} with matches, {} failed", reports.len(), matched, failed);
+    }
+
+    Ok(if matched > 0 {
+        ExitCode::from(EXIT_MATCHES)
+    } else if failed > 0 {
+        ExitCode::from(EXIT_FETCH_ERRORS)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// `doctor` command: check the config and environment for problems
+pub fn doctor(args: &[String]) -> Result<()> {
+    let config = Config::load()?;
+    let offline = has_flag(args, "--offline");
+
+    if !offline {
+        println!("Checking {} watchers (use --offline to skip network checks)...", config.watchers.len());
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let issues = runtime.block_on(doctor::run_checks(&config, offline));
+
+    doctor::print_report(&config, &issues)
+}
+
+/// `bench` command: run the stages of a watcher's check several times and
+/// show how long each takes
+pub fn bench(args: &[String]) -> Result<()> {
+    let url = positional(args).context("Usage: bench <url> [--runs N] [--json]")?;
+    let url = normalize_url(&url)?;
+    let runs = match flag_value(args, "--runs") {
+        Some(runs) => match runs.parse::<usize>() {
+            Ok(n) if (1..=bench::MAX_RUNS).contains(&n) => n,
+            _ => anyhow::bail!("--runs must be between 1 and {}", bench::MAX_RUNS),
+        },
+        None => 5,
+    };
+    let json = has_flag(args, "--json");
+    let config = Config::load()?;
+
+    // Pages without a watcher are fetched with their domain's defaults
+    let watcher = match config.find_watcher_by_url(&url) {
+        Some(index) => config.watchers[index].clone(),
+        None => {
+            if !json {
+                println!("No watcher for {}: using the domain defaults and no keywords", url);
+            }
+            Watcher::new(url, Vec::new(), Duration::from_secs(30 * 60))
+        }
+    };
+    if watcher.is_stream() {
+        anyhow::bail!("{} is a stream: there's no check to time", watcher.url);
+    }
+    let site = config.site_settings(&watcher);
+    let keywords = config.keywords_for(&watcher);
+
+    if !json {
+        println!("Timing {} runs of a check of {}...", runs, watcher.url);
+    }
+    let runtime = tokio::runtime::Runtime::new()?;
+    let results = runtime.block_on(bench::run(
+        &watcher,
+        &keywords,
+        &site,
+        config.limits.max_page_size_for(&watcher),
+        runs,
+    ))?;
+    let summary = bench::summarize(&results);
+    let bytes = results.last().map_or(0, |run| run.bytes);
+
+    if json {
+        let report = serde_json::json!({
+            "url": watcher.url,
+            "runs": runs,
+            "bytes": bytes,
+            "keywords": keywords.len(),
+            "stages": summary,
+        });
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    println!();
+    println!("{:<8} {:>10} {:>10} {:>10}", "Stage", "min", "median", "max");
+    for stage in &summary {
+        println!(
+            "{:<8} {:>10} {:>10} {:>10}",
+            stage.stage,
+            bench::format_ms(stage.min_ms),
+            bench::format_ms(stage.median_ms),
+            bench::format_ms(stage.max_ms)
+        );
+    }
+    println!();
+    let selector = site.selector.as_deref().unwrap_or("none");
+    println!("Page: {} bytes | Keywords: {} | Selector: {}", bytes, keywords.len(), selector);
+    if site.main_content {
+        println!("Note: select includes extracting the main content");
+    }
+    if site.content == ContentMode::Text {
+        println!("Note: select includes extracting the visible text");
+    }
+    match site.request.render {
+        RenderMode::Http => {}
+        RenderMode::Browser => println!("Note: fetch includes rendering the page in a headless browser"),
+        RenderMode::Impersonate => println!("Note: fetch runs curl-impersonate"),
+    }
+    if let Some(verdict) = bench::verdict(&summary) {
+        println!("{}", verdict);
+    }
+    Ok(())
+}
+
+/// `match-test` command: load a page (fetched, from a file or pasted) and
+/// try keywords, regexes and selectors on it line by line
+pub fn match_test(args: &[String]) -> Result<()> {
+    let stdin = std::io::stdin();
+    let mut keywords = Vec::new();
+    let (page, site) = if let Some(path) = flag_value(args, "--file") {
+        let page = std::fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path))?;
+        (page, domains::SiteSettings::default())
+    } else if let Some(url) = positional(args) {
+        let url = normalize_url(&url)?;
+        let config = Config::load()?;
+
+        // Pages without a watcher are fetched with their domain's defaults
+        let watcher = match config.find_watcher_by_url(&url) {
+            Some(index) => config.watchers[index].clone(),
+            None => {
+                println!("No watcher for {}: using the domain defaults", url);
+                Watcher::new(url, Vec::new(), Duration::from_secs(30 * 60))
+            }
+        };
+        if watcher.is_stream() {
+            anyhow::bail!("{} is a stream: there's no page to try keywords on", watcher.url);
+        }
+        let site = config.site_settings(&watcher);
+        keywords = config.keywords_for(&watcher);
+
+        println!("Fetching {}...", watcher.url);
+        let runtime = tokio::runtime::Runtime::new()?;
+        let fetched = runtime
+            .block_on(fetcher::fetch_page(
+                &watcher.url,
+                &site.request,
+                config.limits.max_page_size_for(&watcher),
+                &keywords,
+            ))
+            .context("Failed to fetch URL")?;
+        let page = match fetched.page {
+            Page::Text(page) => page,
+            Page::Oversized(digest) => anyhow::bail!(
+                "The page is over the size limit ({} bytes): it's only hashed, there's nothing to search",
+                digest.size
+            ),
+        };
+        (page, site)
+    } else {
+        println!("Paste the content, then a line with only a period (.) to end it:");
+        let mut page = String::new();
+        let mut line = String::new();
+        while stdin.read_line(&mut line).context("Failed to read stdin")? > 0 && line.trim_end() != "." {
+            page.push_str(&line);
+            line.clear();
+        }
+        (page, domains::SiteSettings::default())
+    };
+
+    let mut session = match_test::Session::new(page, site)?;
+    println!("Searching {} characters. :help lists the commands.", session.content().chars().count());
+    if !keywords.is_empty() {
+        println!("The watcher's keywords:");
+        println!("{}", session.report(&keywords)?);
+    }
+
+    loop {
+        print!("> ");
+        std::io::Write::flush(&mut std::io::stdout())?;
+        let mut line = String::new();
+        if stdin.read_line(&mut line).context("Failed to read stdin")? == 0 {
+            println!();
+            return Ok(());
+        }
+        match session.handle(&line) {
+            Ok(match_test::Step::Print(text)) if text.is_empty() => {}
+            Ok(match_test::Step::Print(text)) => println!("{}", text),
+            Ok(match_test::Step::Quit) => return Ok(()),
+            Err(e) => println!("✗ {:#}", e),
+        }
+    }
+}
+
+/// `capture` command: fetch a page with its watcher's settings and save the
+/// requests and responses as a HAR file
+pub fn capture(args: &[String]) -> Result<()> {
+    let url = positional(args).context("Usage: capture <url> [--output <file>]")?;
+    let url = normalize_url(&url)?;
+    let config = Config::load()?;
+
+    // Pages without a watcher are fetched with their domain's defaults
+    let watcher = match config.find_watcher_by_url(&url) {
+        Some(index) => config.watchers[index].clone(),
+        None => {
+            println!("No watcher for {}: using the domain defaults", url);
+            Watcher::new(url, Vec::new(), Duration::from_secs(30 * 60))
+        }
+    };
+    if watcher.is_stream() {
+        anyhow::bail!("{} is a stream: there's no page to capture", watcher.url);
+    }
+    if unix_socket::is_unix_url(&watcher.url) {
+        anyhow::bail!("Only http:// and https:// pages can be captured");
+    }
+
+    let site = config.site_settings(&watcher);
+    match site.request.render {
+        RenderMode::Http => {}
+        RenderMode::Browser => {
+            println!("Note: checks render this page in a headless browser; the capture shows the plain HTTP requests")
+        }
+        RenderMode::Impersonate => {
+            println!("Note: checks fetch this page with curl-impersonate; the capture shows the plain HTTP requests")
+        }
+    }
+
+    let runtime = tokio::runtime::Runtime::new()?;
+    let capture = runtime.block_on(capture::capture(
+        &watcher.url,
+        &site.request,
+        config.limits.max_page_size_for(&watcher),
+    ));
+
+    for exchange in &capture.exchanges {
+        let total = exchange.wait + exchange.receive;
+        println!(
+            "GET {} → {} {} ({} ms, {} bytes)",
+            exchange.url,
+            exchange.status,
+            exchange.status_text,
+            total.as_millis(),
+            exchange.body_size
+        );
+        if exchange.truncated() {
+            println!("  Body cut off after {} bytes (the size limit)", exchange.body.len());
+        }
+    }
+    if let Some(error) = &capture.error {
+        println!("✗ {}", error);
+    }
+
+    // What a check would make of the page
+    let keywords = config.keywords_for(&watcher);
+    if let (Some(response), false) = (capture.final_response(), keywords.is_empty()) {
+        let page = String::from_utf8_lossy(&response.body).into_owned();
+        let watched = domains::watched_part(&site, page);
+        match watched {
+            Ok(content) => {
+                let mut found: Vec<String> = KeywordMatcher::new(&keywords)
+                    .find(&content)
+                    .into_iter()
+                    .map(|m| m.keyword)
+                    .collect();
+                found.dedup();
+                if found.is_empty() {
+                    println!("None of the keywords are on the page");
+                } else {
+                    println!("Keywords on the page: {}", found.join(", "));
+                }
+            }
+            Err(e) => println!("✗ {:#}", e),
+        }
+    }
+
+    let output = flag_value(args, "--output").unwrap_or_else(|| capture::default_file_name(&watcher.url));
+    capture.save(Path::new(&output))?;
+    println!("Saved to {}", output);
+
+    let sends_credentials = site
+        .request
+        .headers
+        .keys()
+        .any(|name| name.eq_ignore_ascii_case("authorization") || name.eq_ignore_ascii_case("cookie"));
+    if sends_credentials {
+        eprintln!("Warning: the capture contains the watcher's Authorization/Cookie headers; remove them before sharing it.");
+    }
+    Ok(())
+}
+
+/// `backup` command: archive config, cache and data into one file
+pub fn backup(args: &[String]) -> Result<()> {
+    let dest = positional(args).unwrap_or_else(backup::default_backup_name);
+    let count = backup::create_backup(Path::new(&dest))?;
+
+    println!("Backed up {} files to {}", count, dest);
+    Ok(())
+}
+
+/// `restore` command: unpack a backup archive over the current state
+pub fn restore(args: &[String]) -> Result<()> {
+    let archive = positional(args).context("Usage: restore <file> [--force]")?;
+
+    // A managed config must not be replaced either
+    Config::load()?.ensure_writable()?;
+
+    // Never silently overwrite an existing setup
+    if Config::config_path()?.exists() {
+        if !has_flag(args, "--force") {
+            anyhow::bail!(
+                "A config already exists. Re-run with --force to replace it \
+                (a safety backup of the current state is made first)."
+            );
+        }
+
+        let safety = backup::default_backup_name().replace("-backup-", "-pre-restore-");
+        let count = backup::create_backup(Path::new(&safety))?;
+        println!("Saved current state ({} files) to {}", count, safety);
+    }
+
+    let count = backup::restore_backup(Path::new(&archive))?;
+    println!("Restored {} files from {}", count, archive);
+    Ok(())
+}
+
+/// `sync` command: print the changes that make the watchers match a YAML
+/// file, and make them with --apply
+pub fn sync(args: &[String]) -> Result<()> {
+    let file = positional(args).context("Usage: sync <file.yaml> [--apply]")?;
+    let declared = sync::load(Path::new(&file))?;
+
+    let mut config = Config::load()?;
+    let plan = sync::plan(&config, &declared)?;
+    if plan.is_empty() {
+        println!("Watchers already match {}", file);
+        return Ok(());
+    }
+
+    for line in plan.describe(&config) {
+        println!("{}", line);
+    }
+    let (added, updated, removed) = plan.counts();
+    println!();
+    println!("Plan: {} to add, {} to update, {} to remove.", added, updated, removed);
+
+    if !has_flag(args, "--apply") {
+        println!("Re-run with --apply to make these changes.");
+        return Ok(());
+    }
+
+    config.ensure_writable()?;
+    let removed = plan.apply(&mut config);
+    config.save()?;
+    for watcher in &removed {
+        trash::discard(&config.trash, watcher)?;
+    }
+    println!("Applied.");
+    Ok(())
+}
+
+/// Keywords with the settings given to `add`: --regex, --whole-word,
+/// --priority and --channels apply to every keyword in --keywords
+fn keyword_settings(args: &[String], texts: Vec<String>) -> Result<Vec<Keyword>> {
+    let priority = match flag_value(args, "--priority") {
+        Some(input) => Priority::parse(&input)?,
+        None => Priority::Normal,
+    };
+    let channels = match flag_value(args, "--channels") {
+        Some(list) => Some(
+            split_keywords(&list)
+                .iter()
+                .map(|name| Channel::parse(name))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        None => None,
+    };
+
+    texts
+        .into_iter()
+        .map(|text| {
+            let keyword = Keyword {
+                text,
+                is_regex: has_flag(args, "--regex"),
+                whole_word: has_flag(args, "--whole-word"),
+                priority,
+                channels: channels.clone(),
+            };
+            if keyword.is_regex {
+                keyword
+                    .compile()
+                    .with_context(|| format!("Invalid regex keyword: {}", keyword.text))?;
+            }
+            Ok(keyword)
+        })
+        .collect()
+}
+
+/// Separate keyword set references (`@name`) from plain keywords
+pub fn split_keyword_sets(items: Vec<String>) -> (Vec<String>, Vec<String>) {
+    let mut keywords = Vec::new();
+    let mut sets = Vec::new();
+    for item in items {
+        match item.strip_prefix('@') {
+            Some(name) if !name.trim().is_empty() => {
+                let name = name.trim().to_string();
+                if !sets.contains(&name) {
+                    sets.push(name);
+                }
+            }
+            _ => keywords.push(item),
+        }
+    }
+    (keywords, sets)
+}
+
+/// Split a comma-separated keyword list, dropping empty entries
+pub fn split_keywords(input: &str) -> Vec<String> {
+    input
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+/// Flags that don't take a value
+const SWITCHES: &[&str] = &["--merge", "--force-interval", "--offline", "--force", "--once", "--lightweight", "--insecure", "--critical", "--feed", "--stream", "--from-clipboard", "--apply", "--notify-cleared", "--pause-when-blocked", "--translate", "--regex", "--whole-word", "--json", "--disable", "--new-token", "--dry-run", "--pause", "--main-content"];
+
+/// Check whether a switch like `--merge` was given
+fn has_flag(args: &[String], flag: &str) -> bool {
+    args.iter().any(|arg| arg == flag)
+}
+
+/// Get the value following a `--flag` argument
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|arg| arg == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// Get the first positional argument (one that isn't a flag or a flag's value)
+fn positional(args: &[String]) -> Option<String> {
+    positionals(args).into_iter().next()
+}
+
+/// Get all positional arguments, in order
+fn positionals(args: &[String]) -> Vec<String> {
+    let mut found = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        if arg.starts_with("--") {
+            // Skip the flag's value (switches don't have one)
+            if !SWITCHES.contains(&arg.as_str()) {
+                iter.next();
+            }
+        } else {
+            found.push(arg.clone());
+        }
+    }
+    found
+}
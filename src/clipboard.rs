@@ -0,0 +1,92 @@
+//! System clipboard access
+//!
+//! Goes through the platform's clipboard tools (pbcopy/pbpaste on macOS;
+//! wl-copy/wl-paste, xclip or xsel on Linux) instead of talking to the
+//! window system, so it works the same from the TUI and the command line.
+
+use anyhow::{Context, Result};
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+/// Commands that print the clipboard, tried in order
+#[cfg(target_os = "macos")]
+const PASTE_COMMANDS: &[&[&str]] = &[&["pbpaste"]];
+#[cfg(not(target_os = "macos"))]
+const PASTE_COMMANDS: &[&[&str]] = &[
+    &["wl-paste", "--no-newline"],
+    &["xclip", "-selection", "clipboard", "-out"],
+    &["xsel", "--clipboard", "--output"],
+];
+
+/// Commands that set the clipboard from stdin, tried in order
+#[cfg(target_os = "macos")]
+const COPY_COMMANDS: &[&[&str]] = &[&["pbcopy"]];
+#[cfg(not(target_os = "macos"))]
+const COPY_COMMANDS: &[&[&str]] = &[
+    &["wl-copy"],
+    &["xclip", "-selection", "clipboard", "-in"],
+    &["xsel", "--clipboard", "--input"],
+];
+
+/// Put text on the clipboard
+pub fn copy(text: &str) -> Result<()> {
+    for command in COPY_COMMANDS {
+        // The X11/Wayland tools keep running in the background to serve the
+        // clipboard, so their output must not be captured
+        let Ok(mut child) = Command::new(command[0])
+            .args(&command[1..])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            // Not installed, try the next one
+            continue;
+        };
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(text.as_bytes())
+                .with_context(|| format!("Failed to write to {}", command[0]))?;
+        }
+        if child.wait().is_ok_and(|status| status.success()) {
+            return Ok(());
+        }
+    }
+
+    let tools: Vec<&str> = COPY_COMMANDS.iter().map(|command| command[0]).collect();
+    anyhow::bail!("Can't write the clipboard (install one of: {})", tools.join(", "))
+}
+
+/// Read the clipboard as text
+pub fn paste() -> Result<String> {
+    for command in PASTE_COMMANDS {
+        let Ok(output) = Command::new(command[0]).args(&command[1..]).output() else {
+            // Not installed, try the next one
+            continue;
+        };
+        if output.status.success() {
+            return Ok(String::from_utf8_lossy(&output.stdout).into_owned());
+        }
+    }
+
+    let tools: Vec<&str> = PASTE_COMMANDS.iter().map(|command| command[0]).collect();
+    anyhow::bail!("Can't read the clipboard (install one of: {})", tools.join(", "))
+}
+
+/// Read a URL from the clipboard (the first non-empty line, normalized)
+pub fn paste_url() -> Result<String> {
+    let text = paste()?;
+    let line = text
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .context("The clipboard is empty")?;
+
+    // normalize_url would turn any word into https://word
+    if line.contains(char::is_whitespace) || !(line.contains("://") || line.contains('.')) {
+        anyhow::bail!("The clipboard doesn't contain a URL: {}", line);
+    }
+    crate::watcher::normalize_url(line)
+        .with_context(|| format!("The clipboard doesn't contain a URL: {}", line))
+}
@@ -0,0 +1,460 @@
+//! Agents reporting to a coordinator
+//!
+//! The same watchers can run on several machines for redundancy (a home
+//! server and an office machine), without every alert arriving twice: one
+//! daemon is the coordinator, the others are agents.
+//!
+//! - An agent sends its alerts to the coordinator (`POST /report`) instead
+//!   of delivering them. If the coordinator can't be reached, it delivers
+//!   them through its own channels, so nothing is lost while it's down.
+//! - The coordinator delivers what the agents report, and its own alerts,
+//!   through its channels, dropping an alert another machine already
+//!   reported within `dedupe_window` (same page, title and keywords).
+//!
+//! Requests carry the shared token as `Authorization: Bearer <token>`.
+//! `GET /status` lists the agents the coordinator has heard from, and
+//! `POST /lease` grants leases on watchers (see `lease`).
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use futures_util::future::BoxFuture;
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::companion;
+use crate::config::NotificationSettings;
+use crate::events::EventLog;
+use crate::lease::{self, Claim};
+use crate::notify::{Message, Notifier, Notifiers};
+
+/// Name of the notifier reporting to (or delivering for) the coordinator
+pub const NOTIFIER_NAME: &str = "coordinator";
+
+/// Largest report accepted
+const MAX_BODY_BYTES: usize = 256 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    Coordinator,
+    Agent,
+}
+
+impl Role {
+    pub fn name(self) -> &'static str {
+        match self {
+            Role::Coordinator => "coordinator",
+            Role::Agent => "agent",
+        }
+    }
+}
+
+/// This daemon's part in a group of machines running the same watchers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ClusterSettings {
+    /// None: alerts are delivered directly
+    #[serde(default)]
+    pub role: Option<Role>,
+    /// Coordinator: the address to listen on for agents
+    #[serde(default = "default_listen")]
+    pub listen: String,
+    /// Agent: the coordinator's URL, like http://home-server:7392
+    #[serde(default)]
+    pub coordinator: Option<String>,
+    /// Secret the agents and the coordinator share (`cluster coordinator`
+    /// creates one)
+    #[serde(default)]
+    pub token: Option<String>,
+    /// Agent: its name in the coordinator's log (default: the host name)
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Coordinator: seconds within which the same alert from another
+    /// machine is dropped
+    #[serde(default = "default_dedupe_window")]
+    pub dedupe_window: u64,
+}
+
+impl Default for ClusterSettings {
+    fn default() -> Self {
+        Self {
+            role: None,
+            listen: default_listen(),
+            coordinator: None,
+            token: None,
+            name: None,
+            dedupe_window: default_dedupe_window(),
+        }
+    }
+}
+
+fn default_listen() -> String {
+    String::from("0.0.0.0:7392")
+}
+
+fn default_dedupe_window() -> u64 {
+    3600
+}
+
+impl ClusterSettings {
+    /// This machine's name in reports
+    pub fn agent_name(&self) -> String {
+        self.name
+            .clone()
+            .filter(|name| !name.trim().is_empty())
+            .unwrap_or_else(crate::remote::hostname)
+    }
+}
+
+/// Body of a `/report` request
+#[derive(Debug, Serialize, Deserialize)]
+struct Report {
+    agent: String,
+    message: Message,
+}
+
+/// Body of a `/lease` request
+#[derive(Debug, Serialize, Deserialize)]
+struct LeaseRequest {
+    watcher: String,
+    holder: String,
+    seconds: u64,
+}
+
+/// What the coordinator knows about a machine
+#[derive(Debug, Clone, Serialize)]
+pub struct AgentStatus {
+    pub name: String,
+    pub last_seen: DateTime<Utc>,
+    /// Alerts delivered for it
+    pub alerts: u64,
+    /// Alerts dropped as another machine's duplicates
+    pub duplicates: u64,
+}
+
+/// Alerts delivered lately (by key), and the machines heard from
+#[derive(Default)]
+struct Coordinator {
+    delivered: HashMap<String, (Instant, String)>,
+    agents: HashMap<String, AgentStatus>,
+}
+
+static COORDINATOR: Mutex<Option<Coordinator>> = Mutex::new(None);
+
+impl Coordinator {
+    /// Note an alert an agent reported. Returns whether it's a duplicate:
+    /// another machine reported it within the window.
+    fn record(&mut self, settings: &ClusterSettings, agent: &str, message: &Message) -> bool {
+        let key = format!("{}\n{}\n{}", message.url, message.title, message.keywords.join(","));
+        let window = Duration::from_secs(settings.dedupe_window);
+        self.delivered.retain(|_, (at, _)| at.elapsed() < window);
+        let duplicate = self.delivered.get(&key).is_some_and(|(_, first)| first != agent);
+        if !duplicate {
+            self.delivered.insert(key, (Instant::now(), agent.to_string()));
+        }
+
+        let status = self.agents.entry(agent.to_string()).or_insert_with(|| AgentStatus {
+            name: agent.to_string(),
+            last_seen: Utc::now(),
+            alerts: 0,
+            duplicates: 0,
+        });
+        status.last_seen = Utc::now();
+        if duplicate {
+            status.duplicates += 1;
+        } else {
+            status.alerts += 1;
+        }
+        duplicate
+    }
+}
+
+/// Delivers alerts through the coordinator: reports them to it (agents),
+/// or deduplicates and delivers them (the coordinator's own alerts)
+pub struct ClusterNotifier {
+    pub settings: ClusterSettings,
+    /// This machine's own channels
+    pub local: NotificationSettings,
+}
+
+impl Notifier for ClusterNotifier {
+    fn name(&self) -> &str {
+        NOTIFIER_NAME
+    }
+
+    fn relays(&self) -> bool {
+        true
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<()>> {
+        Box::pin(async move {
+            let name = self.settings.agent_name();
+            if self.settings.role == Some(Role::Coordinator) {
+                deliver(&self.local, &self.settings, &name, message).await?;
+                return Ok(());
+            }
+            match report(&self.settings, &name, message).await {
+                Ok(()) => Ok(()),
+                // Deliver it here rather than not at all
+                Err(e) => Notifiers::local(&self.local)
+                    .send(message)
+                    .await
+                    .with_context(|| format!("{:#}, and delivering it here failed too", e)),
+            }
+        })
+    }
+}
+
+/// Send an alert to the coordinator
+async fn report(settings: &ClusterSettings, agent: &str, message: &Message) -> Result<()> {
+    let url = settings.coordinator.as_deref().context("No coordinator URL")?;
+    let response = reqwest::Client::new()
+        .post(format!("{}/report", url.trim_end_matches('/')))
+        .bearer_auth(settings.token.as_deref().unwrap_or_default())
+        .timeout(Duration::from_secs(15))
+        .json(&Report {
+            agent: agent.to_string(),
+            message: message.clone(),
+        })
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach the coordinator: {}", url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("The coordinator answered HTTP {}", response.status());
+    }
+    Ok(())
+}
+
+/// Deliver an alert through this machine's channels unless another machine
+/// delivered it within the window. Returns whether it was delivered.
+async fn deliver(
+    local: &NotificationSettings,
+    settings: &ClusterSettings,
+    agent: &str,
+    message: &Message,
+) -> Result<bool> {
+    let duplicate = {
+        let mut coordinator = COORDINATOR.lock().unwrap_or_else(PoisonError::into_inner);
+        coordinator.get_or_insert_with(Coordinator::default).record(settings, agent, message)
+    };
+
+    if duplicate {
+        return Ok(false);
+    }
+    Notifiers::local(local).send(message).await?;
+    Ok(true)
+}
+
+/// The machines the coordinator has heard from
+pub fn agents() -> Vec<AgentStatus> {
+    let coordinator = COORDINATOR.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut agents: Vec<AgentStatus> = coordinator
+        .as_ref()
+        .map(|c| c.agents.values().cloned().collect())
+        .unwrap_or_default();
+    agents.sort_by(|a, b| a.name.cmp(&b.name));
+    agents
+}
+
+/// Serve the agents until the task is aborted
+pub async fn serve(settings: NotificationSettings, log: EventLog) {
+    let cluster = settings.cluster.clone();
+    let Some(token) = cluster.token.clone().filter(|token| !token.is_empty()) else {
+        log.warning("⚠ The coordinator needs a token: run `web-watcher-alert cluster coordinator` to create one");
+        return;
+    };
+    let addr: SocketAddr = match cluster.listen.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            log.error(format!("✗ Coordinator: invalid listen address {}: {}", cluster.listen, e));
+            return;
+        }
+    };
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder,
+        Err(e) => {
+            log.error(format!("✗ Coordinator: failed to listen on {}: {}", addr, e));
+            return;
+        }
+    };
+
+    let shared = Arc::new((settings, token, log.clone()));
+    let make_service = make_service_fn(move |_| {
+        let shared = Arc::clone(&shared);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let shared = Arc::clone(&shared);
+                async move {
+                    let (settings, token, log) = &*shared;
+                    Ok::<_, Infallible>(respond(settings, token, log, request).await)
+                }
+            }))
+        }
+    });
+
+    log.info(format!("Coordinator listening for agents on http://{}", addr));
+    if let Err(e) = server.serve(make_service).await {
+        log.error(format!("✗ Coordinator stopped: {}", e));
+    }
+}
+
+async fn respond(settings: &NotificationSettings, token: &str, log: &EventLog, request: Request<Body>) -> Response<Body> {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|sent| companion::token_matches(sent.trim(), token));
+
+    let (status, body) = match (request.method(), request.uri().path()) {
+        _ if !authorized => (StatusCode::UNAUTHORIZED, json!({ "ok": false, "error": "Missing or wrong token" })),
+        (&Method::GET, "/status") => (StatusCode::OK, json!({ "ok": true, "agents": agents() })),
+        (&Method::POST, "/lease") => match read_json::<LeaseRequest>(request.into_body()).await {
+            Ok(lease) => match lease::grant(&lease.watcher, &lease.holder, Duration::from_secs(lease.seconds)) {
+                Claim::Granted => (StatusCode::OK, json!({ "ok": true, "granted": true })),
+                Claim::Held { holder } => (StatusCode::OK, json!({ "ok": true, "granted": false, "holder": holder })),
+            },
+            Err(e) => (StatusCode::BAD_REQUEST, json!({ "ok": false, "error": format!("{:#}", e) })),
+        },
+        (&Method::POST, "/report") => match receive(settings, log, request.into_body()).await {
+            Ok(delivered) => (StatusCode::OK, json!({ "ok": true, "delivered": delivered })),
+            Err(e) => (StatusCode::BAD_REQUEST, json!({ "ok": false, "error": format!("{:#}", e) })),
+        },
+        _ => (StatusCode::NOT_FOUND, json!({ "ok": false, "error": "Not found" })),
+    };
+
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+    response
+}
+
+/// Deliver a reported alert (unless it's a duplicate). Returns whether it
+/// was delivered.
+async fn receive(settings: &NotificationSettings, log: &EventLog, body: Body) -> Result<bool> {
+    let report: Report = read_json(body).await?;
+
+    // Delivery failures are queued in the outbox: the report was received
+    let delivered = deliver(settings, &settings.cluster, &report.agent, &report.message)
+        .await
+        .unwrap_or(true);
+    let log = log.for_watcher(&report.message.url);
+    if delivered {
+        log.info(format!("Alert from {}: {}", report.agent, report.message.title));
+    } else {
+        log.info(format!("Alert from {} dropped, another machine sent it: {}", report.agent, report.message.title));
+    }
+    Ok(delivered)
+}
+
+/// A request's JSON body
+async fn read_json<T: serde::de::DeserializeOwned>(mut body: Body) -> Result<T> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.context("Failed to read the request")?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            anyhow::bail!("The request is too large");
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    serde_json::from_slice(&bytes).context("Invalid request")
+}
+
+/// Ask the coordinator for the lease on a watcher
+pub async fn request_lease(settings: &ClusterSettings, watcher_id: &str, holder: &str, ttl: Duration) -> Result<Claim> {
+    let url = settings.coordinator.as_deref().context("No coordinator URL")?;
+    let response = reqwest::Client::new()
+        .post(format!("{}/lease", url.trim_end_matches('/')))
+        .bearer_auth(settings.token.as_deref().unwrap_or_default())
+        .timeout(Duration::from_secs(15))
+        .json(&LeaseRequest {
+            watcher: watcher_id.to_string(),
+            holder: holder.to_string(),
+            seconds: ttl.as_secs(),
+        })
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach the coordinator: {}", url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("The coordinator answered HTTP {}", response.status());
+    }
+    let answer: serde_json::Value = response.json().await.context("Invalid answer from the coordinator")?;
+    if answer["granted"].as_bool().unwrap_or(true) {
+        Ok(Claim::Granted)
+    } else {
+        Ok(Claim::Held {
+            holder: answer["holder"].as_str().unwrap_or("another machine").to_string(),
+        })
+    }
+}
+
+/// The coordinator's agents, as it reports them
+pub async fn fetch_status(settings: &ClusterSettings) -> Result<Vec<serde_json::Value>> {
+    let url = settings.coordinator.as_deref().context("No coordinator URL")?;
+    let response = reqwest::Client::new()
+        .get(format!("{}/status", url.trim_end_matches('/')))
+        .bearer_auth(settings.token.as_deref().unwrap_or_default())
+        .timeout(Duration::from_secs(15))
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach the coordinator: {}", url))?;
+    if !response.status().is_success() {
+        anyhow::bail!("The coordinator answered HTTP {}", response.status());
+    }
+    let status: serde_json::Value = response.json().await.context("Invalid answer from the coordinator")?;
+    Ok(status["agents"].as_array().cloned().unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    fn alert(url: &str) -> Message {
+        Message {
+            title: String::from("Web Watcher Alert: restock found!"),
+            body: String::from("Found on the page"),
+            url: url.to_string(),
+            keywords: vec![String::from("restock")],
+            priority: None,
+            fields: BTreeMap::new(),
+        }
+    }
+
+    #[test]
+    fn alerts_other_agents_already_reported_are_dropped() {
+        // A coordinator of its own, not the process's
+        let mut coordinator = Coordinator::default();
+        let settings = ClusterSettings::default();
+        let message = alert("https://dedupe.example.com/gpu");
+
+        assert!(!coordinator.record(&settings, "laptop", &message));
+        assert!(coordinator.record(&settings, "server", &message));
+        // The agent that reported it first alerts again (a new match)
+        assert!(!coordinator.record(&settings, "laptop", &message));
+        let other = alert("https://dedupe.example.com/cpu");
+        assert!(!coordinator.record(&settings, "server", &other));
+
+        // Outside the window, nothing is a duplicate
+        let no_window = ClusterSettings {
+            dedupe_window: 0,
+            ..ClusterSettings::default()
+        };
+        let late = alert("https://dedupe.example.com/ram");
+        assert!(!coordinator.record(&no_window, "laptop", &late));
+        assert!(!coordinator.record(&no_window, "server", &late));
+
+        let server = &coordinator.agents["server"];
+        assert_eq!((server.alerts, server.duplicates), (2, 1));
+        assert_eq!(coordinator.agents["laptop"].alerts, 3);
+    }
+}
@@ -0,0 +1,271 @@
+//! Companion endpoint for browser extensions and bookmarklets
+//!
+//! With `companion.enabled`, the monitor listens on 127.0.0.1 (port
+//! `companion.port`) for the page a browser is showing: a POST to `/watch`
+//! with the page's URL and the text selected on it creates a watcher for the
+//! URL with the selection as its keywords (or adds them to the URL's
+//! existing watcher). Requests must carry the token from the config, so
+//! other pages can't add watchers. `GET /status` tells an extension the
+//! endpoint is there.
+//!
+//! ```text
+//! POST /watch
+//! Authorization: Bearer <token>
+//! {"url": "https://example.com/item", "selection": "Back in stock"}
+//! ```
+//!
+//! The selection is one keyword per line; `keywords` (a list) and `interval`
+//! ("15m") can be sent instead or as well.
+
+use hyper::body::HttpBody;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::Deserialize;
+use serde_json::json;
+use subtle::ConstantTimeEq;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{Notify, RwLock};
+
+use crate::config::Config;
+use crate::events::EventLog;
+use crate::matcher::Keyword;
+use crate::update::CURRENT_VERSION;
+use crate::watcher::{normalize_url, parse_interval, Watcher};
+
+/// Largest request body accepted
+const MAX_BODY_BYTES: usize = 64 * 1024;
+
+/// Longest keyword taken from a selection (a longer selection is more
+/// likely a paragraph selected by accident than text to wait for)
+const MAX_KEYWORD_CHARS: usize = 200;
+
+/// Interval of watchers created without one
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30 * 60);
+
+/// What the endpoint works with
+struct Shared {
+    config: Arc<RwLock<Config>>,
+    changed: Arc<Notify>,
+    token: String,
+    log: EventLog,
+}
+
+/// Body of a `/watch` request
+#[derive(Debug, Deserialize)]
+struct WatchRequest {
+    url: String,
+    #[serde(default)]
+    selection: Option<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    interval: Option<String>,
+}
+
+/// A request that can't be carried out, and the status to answer with
+struct Refusal(StatusCode, String);
+
+impl Refusal {
+    fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self(status, message.into())
+    }
+}
+
+/// Serve the endpoint until the task is aborted. Watchers it adds are saved
+/// and `changed` is notified, so the monitor starts them.
+pub async fn serve(config: Arc<RwLock<Config>>, changed: Arc<Notify>, log: EventLog) {
+    let settings = config.read().await.companion.clone();
+    let Some(token) = settings.token.filter(|token| !token.is_empty()) else {
+        log.warning("⚠ The companion endpoint needs a token: run `web-watcher-alert companion` to create one");
+        return;
+    };
+
+    let addr = SocketAddr::from(([127, 0, 0, 1], settings.port));
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder,
+        Err(e) => {
+            log.error(format!("✗ Companion endpoint: failed to listen on {}: {}", addr, e));
+            return;
+        }
+    };
+
+    let shared = Arc::new(Shared {
+        config,
+        changed,
+        token,
+        log: log.clone(),
+    });
+    let make_service = make_service_fn(move |_| {
+        let shared = Arc::clone(&shared);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let shared = Arc::clone(&shared);
+                async move { Ok::<_, Infallible>(respond(&shared, request).await) }
+            }))
+        }
+    });
+
+    log.info(format!("Companion endpoint listening on http://{}", addr));
+    if let Err(e) = server.serve(make_service).await {
+        log.error(format!("✗ Companion endpoint stopped: {}", e));
+    }
+}
+
+async fn respond(shared: &Shared, request: Request<Body>) -> Response<Body> {
+    let (status, body) = match (request.method(), request.uri().path()) {
+        // Browsers ask before sending the token from another origin
+        (&Method::OPTIONS, _) => {
+            let mut response = Response::new(Body::empty());
+            *response.status_mut() = StatusCode::NO_CONTENT;
+            return with_cors(response);
+        }
+        (&Method::GET, "/status") => (StatusCode::OK, json!({ "ok": true, "version": CURRENT_VERSION })),
+        (&Method::POST, "/watch") => match watch(shared, request).await {
+            Ok((status, message)) => (status, json!({ "ok": true, "message": message })),
+            Err(Refusal(status, error)) => (status, json!({ "ok": false, "error": error })),
+        },
+        (_, "/watch") => (StatusCode::METHOD_NOT_ALLOWED, json!({ "ok": false, "error": "Use POST" })),
+        _ => (StatusCode::NOT_FOUND, json!({ "ok": false, "error": "Not found" })),
+    };
+
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+    with_cors(response)
+}
+
+/// Allow requests from pages (the token is what keeps them out)
+fn with_cors(mut response: Response<Body>) -> Response<Body> {
+    let headers = response.headers_mut();
+    headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, header::HeaderValue::from_static("*"));
+    headers.insert(header::ACCESS_CONTROL_ALLOW_METHODS, header::HeaderValue::from_static("GET, POST, OPTIONS"));
+    headers.insert(
+        header::ACCESS_CONTROL_ALLOW_HEADERS,
+        header::HeaderValue::from_static("Authorization, Content-Type"),
+    );
+    // Chrome's Private Network Access: public pages may call localhost
+    headers.insert("Access-Control-Allow-Private-Network", header::HeaderValue::from_static("true"));
+    response
+}
+
+/// Add (or extend) the watcher a `/watch` request asks for
+async fn watch(shared: &Shared, request: Request<Body>) -> Result<(StatusCode, String), Refusal> {
+    let authorized = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|token| token_matches(token.trim(), &shared.token));
+    if !authorized {
+        return Err(Refusal::new(StatusCode::UNAUTHORIZED, "Missing or wrong token"));
+    }
+
+    let body = read_body(request.into_body()).await?;
+    let request: WatchRequest = serde_json::from_slice(&body)
+        .map_err(|e| Refusal::new(StatusCode::BAD_REQUEST, format!("Invalid request: {}", e)))?;
+    let url = normalize_url(&request.url).map_err(|e| Refusal::new(StatusCode::BAD_REQUEST, format!("{:#}", e)))?;
+    let keywords = request_keywords(&request)?;
+    let interval = match &request.interval {
+        Some(input) => parse_interval(input).map_err(|e| Refusal::new(StatusCode::BAD_REQUEST, format!("{:#}", e)))?,
+        None => DEFAULT_INTERVAL,
+    };
+
+    let ((status, message), saved) = {
+        let mut config = shared.config.write().await;
+        config
+            .ensure_writable()
+            .map_err(|e| Refusal::new(StatusCode::FORBIDDEN, format!("{:#}", e)))?;
+
+        let outcome = match config.find_watcher_by_url(&url) {
+            Some(index) => {
+                let existing = &mut config.watchers[index];
+                let added = existing.merge_keywords(&keywords);
+                (StatusCode::OK, format!("Added {} keyword(s) to the watcher of {}: {}",
+                    added, url, existing.keyword_summary()))
+            }
+            None => {
+                if let Some(warning) = config.guardrails.check_interval(&url, interval) {
+                    return Err(Refusal::new(StatusCode::BAD_REQUEST, warning));
+                }
+                let watcher = Watcher::new(url.clone(), keywords, interval);
+                let message = format!("Watching {} for {}", url, watcher.keyword_summary());
+                config.watchers.push(watcher);
+                (StatusCode::CREATED, message)
+            }
+        };
+        (outcome, config.clone())
+    };
+    // Saved (and pushed) without holding the config, off the async workers
+    tokio::task::spawn_blocking(move || saved.save())
+        .await
+        .unwrap_or_else(|e| Err(e.into()))
+        .map_err(|e| Refusal::new(StatusCode::INTERNAL_SERVER_ERROR, format!("{:#}", e)))?;
+
+    shared.changed.notify_one();
+    shared.log.for_watcher(&url).info(format!("Companion: {}", message));
+    Ok((status, message))
+}
+
+async fn read_body(mut body: Body) -> Result<Vec<u8>, Refusal> {
+    let mut bytes = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk.map_err(|e| Refusal::new(StatusCode::BAD_REQUEST, format!("Failed to read the request: {}", e)))?;
+        if bytes.len() + chunk.len() > MAX_BODY_BYTES {
+            return Err(Refusal::new(StatusCode::PAYLOAD_TOO_LARGE, "The request is too large"));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+    Ok(bytes)
+}
+
+/// The keywords of a request: each line of the selection, and `keywords`
+fn request_keywords(request: &WatchRequest) -> Result<Vec<Keyword>, Refusal> {
+    let selection = request.selection.as_deref().unwrap_or_default();
+    let mut texts: Vec<String> = Vec::new();
+    for line in selection.lines().chain(request.keywords.iter().map(String::as_str)) {
+        let text = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !text.is_empty() && !texts.iter().any(|known| known.eq_ignore_ascii_case(&text)) {
+            texts.push(text);
+        }
+    }
+
+    if texts.is_empty() {
+        return Err(Refusal::new(StatusCode::BAD_REQUEST, "Select the text to watch for on the page first"));
+    }
+    if let Some(long) = texts.iter().find(|text| text.chars().count() > MAX_KEYWORD_CHARS) {
+        return Err(Refusal::new(
+            StatusCode::BAD_REQUEST,
+            format!("Select less text: keywords can be up to {} characters, not {}",
+                MAX_KEYWORD_CHARS, long.chars().count()),
+        ));
+    }
+    Ok(texts.into_iter().map(Keyword::new).collect())
+}
+
+/// A token for `companion.token` (or the other endpoints)
+pub fn new_token() -> String {
+    uuid::Uuid::new_v4().simple().to_string()
+}
+
+/// Whether a token sent to one of the endpoints is the configured one,
+/// compared in constant time so response times don't give it away
+pub fn token_matches(sent: &str, token: &str) -> bool {
+    sent.as_bytes().ct_eq(token.as_bytes()).into()
+}
+
+/// A bookmarklet sending the current page and selection to the endpoint
+pub fn bookmarklet(port: u16, token: &str) -> String {
+    format!(
+        "javascript:(()=>{{fetch('http://127.0.0.1:{}/watch',{{method:'POST',\
+         headers:{{'Authorization':'Bearer {}','Content-Type':'application/json'}},\
+         body:JSON.stringify({{url:location.href,selection:String(getSelection())}})}})\
+         .then(r=>r.json()).then(r=>alert(r.message||r.error))\
+         .catch(()=>alert('Web Watcher Alert is not running'))}})()",
+        port, token
+    )
+}
@@ -1,16 +1,801 @@
-/// Configuration management module
-///
-/// Handles loading and saving the application configuration,
-/// including all watchers, to ~/.config/web-watcher-alert/config.json
+//! Configuration management module
+//!
+//! Handles loading and saving the application configuration,
+//! including all watchers, to ~/.config/web-watcher-alert/config.json
+//!
+//! In containers the locations can be moved with environment variables
+//! (`WEB_WATCHER_ALERT_CONFIG`, `WEB_WATCHER_ALERT_DATA_DIR` and
+//! `WEB_WATCHER_ALERT_CACHE_DIR`), e.g. onto a mounted volume. A named
+//! profile (see `profile`) uses a subdirectory of each location.
 
 use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+use crate::markup::Format;
+use crate::matcher::{Keyword, Priority};
+
+/// Environment variable overriding the config file path
+pub const CONFIG_ENV: &str = "WEB_WATCHER_ALERT_CONFIG";
+
+/// Environment variable overriding the data directory (history, state)
+pub const DATA_DIR_ENV: &str = "WEB_WATCHER_ALERT_DATA_DIR";
+
+/// Environment variable overriding the cache directory
+pub const CACHE_DIR_ENV: &str = "WEB_WATCHER_ALERT_CACHE_DIR";
+
+/// Environment variable supplying a webhook URL when the config has none
+/// (so a container can be configured without editing the config file)
+pub const WEBHOOK_ENV: &str = "WEB_WATCHER_ALERT_WEBHOOK_URL";
+
+/// Environment variable making the config read-only (like `--read-only`)
+pub const READ_ONLY_ENV: &str = "WEB_WATCHER_ALERT_READ_ONLY";
+
+/// Set by `--read-only`: never write the config in this process
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Config {
     pub watchers: Vec<crate::watcher::Watcher>,
+
+    /// User-defined watcher templates (in addition to the built-in ones)
+    #[serde(default)]
+    pub templates: Vec<crate::templates::Template>,
+
+    /// Defaults for the watchers of a domain (headers, request spacing,
+    /// selector, render mode); a domain also covers its subdomains
+    #[serde(default)]
+    pub domains: BTreeMap<String, crate::domains::DomainSettings>,
+
+    /// Named keyword lists that watchers share (e.g. "gpu-models" for every
+    /// retailer), referenced as `@name` in a watcher's keywords
+    #[serde(default)]
+    pub keyword_sets: BTreeMap<String, Vec<Keyword>>,
+
+    /// Keywords watched on every combination of URL templates and values,
+    /// expanded into child watchers (see `matrix`)
+    #[serde(default)]
+    pub matrices: Vec<crate::matrix::Matrix>,
+
+    /// Notification channel settings
+    #[serde(default)]
+    pub notifications: NotificationSettings,
+
+    /// Minimum interval limits that protect public sites from hammering
+    #[serde(default)]
+    pub guardrails: Guardrails,
+
+    /// How long deleted watchers can be restored
+    #[serde(default)]
+    pub trash: TrashSettings,
+
+    /// How much check history is kept per watcher
+    #[serde(default)]
+    pub history: HistoryRetention,
+
+    /// Encryption of cached pages and the check history (see `crypto`)
+    #[serde(default)]
+    pub encryption: EncryptionSettings,
+
+    /// Keep only fingerprints of the watched pages (and the contexts of
+    /// matches) instead of copies: alerts come without a diff
+    #[serde(default)]
+    pub minimize_data: bool,
+
+    /// Syncing the settings with other machines through git, WebDAV or S3
+    #[serde(default)]
+    pub remote: crate::remote::RemoteSettings,
+
+    /// Leases letting one machine at a time check each watcher
+    #[serde(default)]
+    pub lease: crate::lease::LeaseSettings,
+
+    /// Number of previous config versions to keep in the backups folder
+    /// (0 disables automatic backups before each save)
+    #[serde(default)]
+    pub config_backups: usize,
+
+    /// Time zone for log timestamps, displayed times and active hours
+    /// ("local" or an IANA name like "Europe/Berlin")
+    #[serde(default)]
+    pub timezone: crate::timezone::Zone,
+
+    /// Memory limits for fetched pages
+    #[serde(default)]
+    pub limits: Limits,
+
+    /// Restarting of watchers whose checks hang or crash
+    #[serde(default)]
+    pub watchdog: WatchdogSettings,
+
+    /// Pausing checks while offline or on a metered connection
+    #[serde(default)]
+    pub network: NetworkSettings,
+
+    /// Slowing down non-critical watchers on a low battery
+    #[serde(default)]
+    pub battery: BatterySettings,
+
+    /// Pings to an external uptime service while the daemon is healthy
+    #[serde(default)]
+    pub uptime_ping: UptimePing,
+
+    /// Disabling of watchers whose page is permanently gone
+    #[serde(default)]
+    pub auto_disable: AutoDisable,
+
+    /// Opt-in check for new releases of this app
+    #[serde(default)]
+    pub update_check: UpdateCheck,
+
+    /// Translation of foreign-language pages for watchers with `translate`
+    #[serde(default)]
+    pub translation: TranslationSettings,
+
+    /// Local endpoint for adding watchers from a browser extension or
+    /// bookmarklet
+    #[serde(default)]
+    pub companion: CompanionSettings,
+
+    /// Calendars whose events are quiet or paused periods
+    #[serde(default)]
+    pub calendars: Vec<crate::calendar::CalendarSettings>,
+
+    /// Endpoint letting other systems (CI, IFTTT) trigger checks
+    #[serde(default)]
+    pub triggers: crate::trigger::TriggerSettings,
+
+    /// Log files written besides the daemon's output
+    #[serde(default)]
+    pub logging: crate::logfiles::LogSettings,
+
+    /// The file is managed by another tool (Ansible, chezmoi, ...): never
+    /// write it. The TUI only shows watchers, and changing them fails.
+    #[serde(default)]
+    pub read_only: bool,
+}
+
+/// Which channels alerts are delivered through
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationSettings {
+    /// Show native desktop notifications
+    #[serde(default = "default_true")]
+    pub desktop: bool,
+
+    /// Optional webhook URL that receives a JSON POST for each alert
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+
+    /// Format of the alert bodies posted to the webhook: plain, markdown
+    /// (Slack, Discord, Telegram) or html
+    #[serde(default)]
+    pub webhook_format: Format,
+
+    /// Sound and urgency of desktop notifications per keyword priority
+    #[serde(default)]
+    pub desktop_styles: DesktopStyles,
+
+    /// Show a desktop notification when the daemon starts, summarizing the
+    /// active watchers and their next checks
+    #[serde(default)]
+    pub startup_summary: bool,
+
+    /// Keep keyword alerts as tasks: in a Markdown or todo.txt file, or in
+    /// macOS Reminders
+    #[serde(default)]
+    pub tasks: crate::tasks::TaskSettings,
+
+    /// Report alerts to a coordinator, or be one (see `cluster`)
+    #[serde(default)]
+    pub cluster: crate::cluster::ClusterSettings,
+}
+
+impl Default for NotificationSettings {
+    fn default() -> Self {
+        Self {
+            desktop: true,
+            webhook_url: None,
+            webhook_format: Format::Plain,
+            desktop_styles: DesktopStyles::default(),
+            startup_summary: false,
+            tasks: crate::tasks::TaskSettings::default(),
+            cluster: crate::cluster::ClusterSettings::default(),
+        }
+    }
+}
+
+impl NotificationSettings {
+    /// The webhook alerts are posted to: the configured one, or the one
+    /// from the environment
+    pub fn webhook(&self) -> Option<String> {
+        self.webhook_url
+            .clone()
+            .or_else(|| std::env::var(WEBHOOK_ENV).ok().filter(|url| !url.trim().is_empty()))
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// How desktop notifications for each keyword priority are shown (alerts
+/// that aren't about keywords are shown like normal ones)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DesktopStyles {
+    #[serde(default = "DesktopStyle::silent")]
+    pub low: DesktopStyle,
+    #[serde(default)]
+    pub normal: DesktopStyle,
+    #[serde(default)]
+    pub high: DesktopStyle,
+}
+
+impl Default for DesktopStyles {
+    fn default() -> Self {
+        Self {
+            low: DesktopStyle::silent(),
+            normal: DesktopStyle::default(),
+            high: DesktopStyle::default(),
+        }
+    }
+}
+
+impl DesktopStyles {
+    pub fn for_priority(&self, priority: Priority) -> &DesktopStyle {
+        match priority {
+            Priority::Low => &self.low,
+            Priority::Normal => &self.normal,
+            Priority::High => &self.high,
+        }
+    }
+}
+
+/// Sound, urgency and grouping of a desktop notification
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DesktopStyle {
+    /// Sound to play ("default", or a system sound like "Glass" on macOS),
+    /// none for a silent notification
+    #[serde(default = "default_sound")]
+    pub sound: Option<String>,
+
+    /// Show the notification even in Do Not Disturb (a critical
+    /// notification on Linux, a reminder on Windows, time-sensitive on
+    /// macOS)
+    #[serde(default)]
+    pub urgent: bool,
+
+    /// Keep one notification per watched page: a new alert replaces the
+    /// page's previous one instead of piling up
+    #[serde(default)]
+    pub group: bool,
+}
+
+impl Default for DesktopStyle {
+    fn default() -> Self {
+        Self {
+            sound: default_sound(),
+            urgent: false,
+            group: false,
+        }
+    }
+}
+
+impl DesktopStyle {
+    /// No sound
+    pub fn silent() -> Self {
+        Self {
+            sound: None,
+            urgent: false,
+            group: false,
+        }
+    }
+}
+
+fn default_sound() -> Option<String> {
+    Some(String::from("default"))
+}
+
+/// Minimum check intervals for public websites
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Guardrails {
+    /// Shortest interval allowed for public sites (in seconds)
+    #[serde(default = "default_min_interval", with = "crate::watcher::duration_serde")]
+    pub min_interval: Duration,
+
+    /// Per-domain minimums in seconds, overriding `min_interval`
+    /// (a domain also covers its subdomains)
+    #[serde(default)]
+    pub domain_min_intervals: BTreeMap<String, u64>,
+}
+
+impl Default for Guardrails {
+    fn default() -> Self {
+        Self {
+            min_interval: default_min_interval(),
+            domain_min_intervals: BTreeMap::new(),
+        }
+    }
+}
+
+fn default_min_interval() -> Duration {
+    Duration::from_secs(60)
+}
+
+impl Guardrails {
+    /// Minimum interval for a URL, or None if the host isn't a public site
+    /// (localhost, private network addresses and unix sockets are never limited)
+    pub fn min_interval_for(&self, url: &str) -> Option<Duration> {
+        let parsed = url::Url::parse(url).ok()?;
+        if parsed.scheme() == crate::unix_socket::SCHEME {
+            return None;
+        }
+
+        let host = match parsed.host()? {
+            url::Host::Domain(domain) => {
+                let domain = domain.to_lowercase();
+                if domain == "localhost" || domain.ends_with(".local") || domain.ends_with(".localhost") {
+                    return None;
+                }
+                domain
+            }
+            url::Host::Ipv4(ip) => {
+                if ip.is_private() || ip.is_loopback() || ip.is_link_local() {
+                    return None;
+                }
+                ip.to_string()
+            }
+            url::Host::Ipv6(ip) => {
+                if ip.is_loopback() {
+                    return None;
+                }
+                ip.to_string()
+            }
+        };
+
+        // Most specific matching domain wins
+        let domain_limit = self
+            .domain_min_intervals
+            .iter()
+            .filter(|(domain, _)| crate::domains::covers(domain, &host))
+            .max_by_key(|(domain, _)| domain.len())
+            .map(|(_, secs)| Duration::from_secs(*secs));
+
+        Some(domain_limit.unwrap_or(self.min_interval))
+    }
+
+    /// Describe why an interval is too aggressive, or None if it's fine
+    pub fn check_interval(&self, url: &str, interval: Duration) -> Option<String> {
+        let minimum = self.min_interval_for(url)?;
+        if interval >= minimum {
+            return None;
+        }
+
+        Some(format!(
+            "Interval {} is below the {} minimum for public sites like this one. \
+            Checking too often can get your IP banned.",
+            crate::watcher::format_interval(interval),
+            crate::watcher::format_interval(minimum)
+        ))
+    }
+
+    /// The interval a watcher actually runs at, after applying the minimum
+    pub fn effective_interval(&self, watcher: &crate::watcher::Watcher) -> Duration {
+        if watcher.allow_short_interval {
+            return watcher.check_interval;
+        }
+
+        match self.min_interval_for(&watcher.url) {
+            Some(minimum) => watcher.check_interval.max(minimum),
+            None => watcher.check_interval,
+        }
+    }
+}
+
+/// Memory limits that keep the daemon small on low-memory machines
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Limits {
+    /// Largest page (in bytes) kept in memory for diffing and keyword
+    /// context. Bigger pages are compared by hash only. 0 means no limit.
+    #[serde(default = "default_max_page_size")]
+    pub max_page_size: u64,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Self {
+            max_page_size: default_max_page_size(),
+        }
+    }
+}
+
+fn default_max_page_size() -> u64 {
+    5 * 1024 * 1024
+}
+
+impl Limits {
+    /// The page size cap for a watcher (its own override, or the global one)
+    pub fn max_page_size_for(&self, watcher: &crate::watcher::Watcher) -> Option<u64> {
+        match watcher.max_page_size.unwrap_or(self.max_page_size) {
+            0 => None,
+            bytes => Some(bytes),
+        }
+    }
+}
+
+/// Shortest time a watcher may go without completing a check before the
+/// watchdog restarts it. A check can legitimately take a while: each fetch
+/// may run into its 30s timeout, and consensus/HEAD checks fetch repeatedly.
+const WATCHDOG_MIN_ALLOWANCE: Duration = Duration::from_secs(5 * 60);
+
+/// Watchdog for watcher tasks that stop completing checks (a hung request,
+/// a deadlock)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WatchdogSettings {
+    /// Restart a watcher that hasn't completed a check within this many
+    /// check intervals (0 disables the watchdog)
+    #[serde(default = "default_stuck_after_intervals")]
+    pub stuck_after_intervals: u32,
+
+    /// Send a notification when a watcher is restarted (after hanging or
+    /// crashing)
+    #[serde(default = "default_true")]
+    pub notify: bool,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            stuck_after_intervals: default_stuck_after_intervals(),
+            notify: true,
+        }
+    }
+}
+
+fn default_stuck_after_intervals() -> u32 {
+    3
+}
+
+impl WatchdogSettings {
+    /// How long a watcher with this interval may go without completing a
+    /// check, or None if the watchdog is disabled
+    pub fn allowance(&self, interval: Duration) -> Option<Duration> {
+        match self.stuck_after_intervals {
+            0 => None,
+            n => Some(interval.saturating_mul(n).max(WATCHDOG_MIN_ALLOWANCE)),
+        }
+    }
+}
+
+/// When the monitor pauses checks because of the network
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NetworkSettings {
+    /// Pause checks while the machine is offline
+    #[serde(default = "default_true")]
+    pub pause_when_offline: bool,
+
+    /// Pause checks while the default route goes through one of these
+    /// interfaces (e.g. "usb0" or "en5" for a phone hotspot)
+    #[serde(default)]
+    pub metered_interfaces: Vec<String>,
+}
+
+impl Default for NetworkSettings {
+    fn default() -> Self {
+        Self {
+            pause_when_offline: true,
+            metered_interfaces: Vec::new(),
+        }
+    }
+}
+
+impl NetworkSettings {
+    /// Whether the monitor needs to watch the network at all
+    pub fn enabled(&self) -> bool {
+        self.pause_when_offline || !self.metered_interfaces.is_empty()
+    }
+}
+
+/// Battery saving: while running on battery below `below_percent`,
+/// non-critical watchers are slowed down (or paused)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BatterySettings {
+    /// Turn battery saving on
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Battery saving starts below this charge (in percent)
+    #[serde(default = "default_battery_threshold")]
+    pub below_percent: u8,
+
+    /// Multiply the interval of non-critical watchers by this
+    #[serde(default = "default_battery_interval_multiplier")]
+    pub interval_multiplier: u32,
+
+    /// Pause non-critical watchers entirely instead of slowing them down
+    #[serde(default)]
+    pub pause_non_critical: bool,
+}
+
+impl Default for BatterySettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            below_percent: default_battery_threshold(),
+            interval_multiplier: default_battery_interval_multiplier(),
+            pause_non_critical: false,
+        }
+    }
+}
+
+fn default_battery_threshold() -> u8 {
+    30
+}
+
+fn default_battery_interval_multiplier() -> u32 {
+    4
+}
+
+impl BatterySettings {
+    /// Whether battery saving applies to the given power status
+    pub fn is_saving(&self, status: Option<&crate::power::PowerStatus>) -> bool {
+        self.enabled
+            && status.is_some_and(|status| {
+                // Unknown charge on battery: assume it's low
+                status.on_battery && status.charge_percent.is_none_or(|c| c < self.below_percent)
+            })
+    }
+}
+
+/// Push-style uptime monitoring (healthchecks.io, UptimeRobot, ...): the
+/// daemon requests `url` regularly while all watchers are healthy, so the
+/// service alerts when the pings stop (e.g. the whole daemon died)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UptimePing {
+    /// URL to request (None disables pings)
+    #[serde(default)]
+    pub url: Option<String>,
+
+    /// Time between pings (in seconds)
+    #[serde(default = "default_uptime_ping_interval", with = "crate::watcher::duration_serde")]
+    pub interval: Duration,
+}
+
+impl Default for UptimePing {
+    fn default() -> Self {
+        Self {
+            url: None,
+            interval: default_uptime_ping_interval(),
+        }
+    }
+}
+
+fn default_uptime_ping_interval() -> Duration {
+    Duration::from_secs(5 * 60)
+}
+
+/// Looking for new releases in the project's release feed (see `update`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UpdateCheck {
+    /// Off by default: no requests to the project unless asked for
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Atom feed listing the releases
+    #[serde(default = "default_release_feed")]
+    pub feed_url: String,
+
+    /// Time between fetches of the feed (in seconds, at least an hour)
+    #[serde(default = "default_update_check_interval", with = "crate::watcher::duration_serde")]
+    pub interval: Duration,
+}
+
+impl Default for UpdateCheck {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            feed_url: default_release_feed(),
+            interval: default_update_check_interval(),
+        }
+    }
+}
+
+fn default_release_feed() -> String {
+    String::from("https://github.com/unremarkablegarden/webwatcheralert/releases.atom")
+}
+
+fn default_update_check_interval() -> Duration {
+    Duration::from_secs(24 * 60 * 60)
+}
+
+/// Where translated watchers get their translations (see `translate`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TranslationSettings {
+    /// None turns translation off
+    #[serde(default)]
+    pub provider: Option<TranslationProvider>,
+
+    /// LibreTranslate server (required), or a DeepL API other than the
+    /// free one
+    #[serde(default)]
+    pub url: Option<String>,
+
+    #[serde(default)]
+    pub api_key: Option<String>,
+
+    /// Shell command of the `command` provider: it reads the text on stdin
+    /// and writes the translation to stdout, with the languages in
+    /// `SOURCE_LANGUAGE` and `TARGET_LANGUAGE`
+    #[serde(default)]
+    pub command: Option<String>,
+
+    /// Language of the keywords and the translated alerts (ISO 639-1)
+    #[serde(default = "default_translation_language")]
+    pub language: String,
+}
+
+impl Default for TranslationSettings {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            url: None,
+            api_key: None,
+            command: None,
+            language: default_translation_language(),
+        }
+    }
+}
+
+fn default_translation_language() -> String {
+    String::from("en")
+}
+
+/// A translation service
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TranslationProvider {
+    #[serde(rename = "libretranslate")]
+    LibreTranslate,
+    #[serde(rename = "deepl")]
+    DeepL,
+    /// A program of the user's (see `TranslationSettings::command`)
+    Command,
+}
+
+/// The monitor's endpoint for browser extensions and bookmarklets (see
+/// `companion`), listening on 127.0.0.1 only
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompanionSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    #[serde(default = "default_companion_port")]
+    pub port: u16,
+
+    /// Secret that requests must send as `Authorization: Bearer <token>`
+    /// (the `companion` command creates one)
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl Default for CompanionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            port: default_companion_port(),
+            token: None,
+        }
+    }
+}
+
+fn default_companion_port() -> u16 {
+    7391
+}
+
+/// Keeping deleted watchers in the trash (see `trash`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TrashSettings {
+    /// Days a deleted watcher stays restorable (0 deletes it right away)
+    #[serde(default = "default_trash_days")]
+    pub days: u32,
+}
+
+impl Default for TrashSettings {
+    fn default() -> Self {
+        Self {
+            days: default_trash_days(),
+        }
+    }
+}
+
+fn default_trash_days() -> u32 {
+    30
+}
+
+/// Encryption at rest (see `crypto`)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct EncryptionSettings {
+    /// Write cached pages and history records encrypted
+    #[serde(default)]
+    pub enabled: bool,
+}
+
+/// How much check history is kept per watcher (see `history`)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryRetention {
+    /// Newest checks always kept
+    #[serde(default = "default_keep_last")]
+    pub keep_last: usize,
+    /// Before those, the last check of each day (and every match) for this
+    /// many days
+    #[serde(default)]
+    pub daily_days: u32,
+    /// Before the daily ones, the last check of each week
+    #[serde(default)]
+    pub weekly: bool,
+    /// Most bytes of history per watcher: the oldest checks go first
+    #[serde(default)]
+    pub max_bytes: Option<u64>,
+}
+
+impl Default for HistoryRetention {
+    fn default() -> Self {
+        Self {
+            keep_last: default_keep_last(),
+            daily_days: 0,
+            weekly: false,
+            max_bytes: None,
+        }
+    }
+}
+
+fn default_keep_last() -> usize {
+    crate::history::MAX_RECORDS
+}
+
+/// Automatic disabling of watchers whose checks keep failing permanently
+/// (the domain doesn't exist, the page returns 404/410). Both limits must be
+/// reached, so neither a burst of quick checks nor one failure a week ago
+/// disables a watcher.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoDisable {
+    /// Permanent failures in a row before disabling (0 never disables)
+    #[serde(default = "default_auto_disable_failures")]
+    pub after_failures: u32,
+
+    /// How long the failures must have lasted (in seconds)
+    #[serde(default = "default_auto_disable_duration", with = "crate::watcher::duration_serde")]
+    pub min_duration: Duration,
+}
+
+impl Default for AutoDisable {
+    fn default() -> Self {
+        Self {
+            after_failures: default_auto_disable_failures(),
+            min_duration: default_auto_disable_duration(),
+        }
+    }
+}
+
+fn default_auto_disable_failures() -> u32 {
+    10
+}
+
+fn default_auto_disable_duration() -> Duration {
+    Duration::from_secs(7 * 24 * 60 * 60)
+}
+
+impl AutoDisable {
+    /// Whether a watcher with this many permanent failures in a row, the
+    /// first at `since`, should be disabled
+    pub fn applies(&self, failures: u32, since: DateTime<Utc>) -> bool {
+        let failing_for = (Utc::now() - since).to_std().unwrap_or_default();
+        self.after_failures > 0 && failures >= self.after_failures && failing_for >= self.min_duration
+    }
 }
 
 impl Config {
@@ -20,9 +805,7 @@ impl Config {
 
         // If config doesn't exist, return empty config
         if !config_path.exists() {
-            return Ok(Self {
-                watchers: Vec::new(),
-            });
+            return Ok(Self::default());
         }
 
         // Read and parse the config file
@@ -35,8 +818,20 @@ impl Config {
         Ok(config)
     }
 
-    /// Save configuration to disk
+    /// Save configuration to disk, and push changed settings to the remote
+    /// (fails for a read-only config)
     pub fn save(&self) -> Result<()> {
+        if self.save_unpushed()? {
+            self.push_saved()?;
+        }
+        Ok(())
+    }
+
+    /// Save configuration to disk without pushing it. Returns whether there
+    /// are changed settings for `push_saved`, which runs git, curl or aws:
+    /// call it once a lock on the config is released.
+    pub fn save_unpushed(&self) -> Result<bool> {
+        self.ensure_writable()?;
         let config_path = Self::config_path()?;
 
         // Create config directory if it doesn't exist
@@ -45,18 +840,177 @@ impl Config {
                 .context("Failed to create config directory")?;
         }
 
+        let previous = fs::read_to_string(&config_path).ok();
+
         // Serialize and write config
         let contents = serde_json::to_string_pretty(self)
             .context("Failed to serialize config")?;
 
-        fs::write(&config_path, contents)
+        // Keep a copy of the previous version before overwriting it, if the
+        // settings change (not for the status saved after every check)
+        let settings_changed = |previous: &str| {
+            let old = crate::config_history::settings(previous).ok();
+            old.is_none() || old != crate::config_history::settings(&contents).ok()
+        };
+        if self.config_backups > 0 && previous.as_deref().is_some_and(settings_changed) {
+            Self::rotate_backups(&config_path, self.config_backups)?;
+        }
+
+        fs::write(&config_path, &contents)
             .context("Failed to write config file")?;
 
+        // Record what changed (for `config history`)
+        let changed = crate::config_history::record(previous.as_deref(), &contents)
+            .context("Failed to record config history")?;
+
+        Ok(changed && self.remote.enabled())
+    }
+
+    /// Share saved settings with the other machines
+    pub fn push_saved(&self) -> Result<()> {
+        crate::remote::push_saved(self).context("Saved, but failed to push the settings to the remote")
+    }
+
+    /// Make every config read-only for the rest of the process
+    /// (`--read-only` or the environment variable)
+    pub fn set_read_only() {
+        READ_ONLY.store(true, Ordering::Relaxed);
+    }
+
+    /// Whether the config must not be written
+    pub fn is_read_only(&self) -> bool {
+        self.read_only || READ_ONLY.load(Ordering::Relaxed)
+    }
+
+    /// Fail with an explanation if the config must not be written
+    pub fn ensure_writable(&self) -> Result<()> {
+        if !self.is_read_only() {
+            return Ok(());
+        }
+
+        let reason = if self.read_only {
+            "\"read_only\" is set in the file"
+        } else {
+            "--read-only or WEB_WATCHER_ALERT_READ_ONLY"
+        };
+        let path = Self::config_path()?;
+        anyhow::bail!(
+            "The config is read-only ({}): make the change in the source of {} instead",
+            reason,
+            path.display()
+        )
+    }
+
+    /// Find the index of a watcher monitoring the same URL (after normalization)
+    pub fn find_watcher_by_url(&self, url: &str) -> Option<usize> {
+        let normalize = |u: &str| crate::watcher::normalize_url(u).unwrap_or_else(|_| u.to_string());
+        let target = normalize(url);
+
+        self.watchers.iter().position(|w| normalize(&w.url) == target)
+    }
+
+    /// The keywords a watcher searches for: its own, then those of the
+    /// keyword sets it uses (without repeating a keyword). Sets that don't
+    /// exist are skipped; `doctor` reports them.
+    pub fn keywords_for(&self, watcher: &crate::watcher::Watcher) -> Vec<Keyword> {
+        let mut keywords = watcher.keywords.clone();
+        for name in &watcher.keyword_sets {
+            for keyword in self.keyword_sets.get(name).into_iter().flatten() {
+                if !keywords.iter().any(|k| k.text.eq_ignore_ascii_case(&keyword.text)) {
+                    keywords.push(keyword.clone());
+                }
+            }
+        }
+        keywords
+    }
+
+    /// How a watcher's page is requested and which part of it is watched,
+    /// with its domain's defaults applied
+    pub fn site_settings(&self, watcher: &crate::watcher::Watcher) -> crate::domains::SiteSettings {
+        let mut settings = crate::domains::resolve(&self.domains, watcher);
+        if watcher.translate && self.translation.provider.is_some() {
+            settings.translation = Some(self.translation.clone());
+        }
+        settings
+    }
+
+    /// Fail if any of the named keyword sets isn't defined
+    pub fn check_keyword_sets(&self, names: &[String]) -> Result<()> {
+        for name in names {
+            if !self.keyword_sets.contains_key(name) {
+                anyhow::bail!("Unknown keyword set: @{} (define it under \"keyword_sets\" in the config)", name);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rotate config.json.1 .. config.json.N in the backups folder and
+    /// copy the current config to config.json.1
+    fn rotate_backups(config_path: &Path, keep: usize) -> Result<()> {
+        let backup_dir = Self::config_dir()?.join("backups");
+        fs::create_dir_all(&backup_dir)
+            .context("Failed to create config backup directory")?;
+
+        let backup = |n: usize| backup_dir.join(format!("config.json.{}", n));
+
+        // Drop the oldest, then shift the rest up by one
+        let _ = fs::remove_file(backup(keep));
+        for n in (1..keep).rev() {
+            if backup(n).exists() {
+                fs::rename(backup(n), backup(n + 1))
+                    .context("Failed to rotate config backups")?;
+            }
+        }
+
+        fs::copy(config_path, backup(1))
+            .context("Failed to back up config file")?;
+
         Ok(())
     }
 
-    /// Get the config file path
+    /// Get the config directory (~/.config/web-watcher-alert)
+    pub fn config_dir() -> Result<PathBuf> {
+        let config_path = Self::config_path()?;
+        let dir = config_path
+            .parent()
+            .context("Config path has no parent directory")?;
+        Ok(dir.to_path_buf())
+    }
+
+    /// Get the data directory (~/.local/share/web-watcher-alert)
+    /// Holds logs and persistent data such as check history
+    pub fn data_dir() -> Result<PathBuf> {
+        let data_dir = match env_path(DATA_DIR_ENV) {
+            Some(dir) => dir,
+            None => dirs::home_dir()
+                .context("Could not find home directory")?
+                .join(".local")
+                .join("share")
+                .join("web-watcher-alert"),
+        };
+
+        Ok(crate::profile::scoped(data_dir))
+    }
+
+    /// Get the config file path of the active profile
     pub fn config_path() -> Result<PathBuf> {
+        let path = Self::default_config_path()?;
+        if crate::profile::active().is_none() {
+            return Ok(path);
+        }
+
+        // ~/.config/web-watcher-alert/profiles/<name>/config.json
+        let dir = path.parent().context("Config path has no parent directory")?;
+        let file_name = path.file_name().context("Config path has no file name")?;
+        Ok(crate::profile::scoped(dir.to_path_buf()).join(file_name))
+    }
+
+    /// Get the config file path of the default profile
+    pub fn default_config_path() -> Result<PathBuf> {
+        if let Some(path) = env_path(CONFIG_ENV) {
+            return Ok(path);
+        }
+
         // Get home directory
         let home = dirs::home_dir()
             .context("Could not find home directory")?;
@@ -72,12 +1026,14 @@ impl Config {
 
     /// Get the cache directory path
     pub fn cache_dir() -> Result<PathBuf> {
-        let home = dirs::home_dir()
-            .context("Could not find home directory")?;
-
-        let cache_dir = home
-            .join(".cache")
-            .join("web-watcher-alert");
+        let cache_dir = match env_path(CACHE_DIR_ENV) {
+            Some(dir) => dir,
+            None => dirs::home_dir()
+                .context("Could not find home directory")?
+                .join(".cache")
+                .join("web-watcher-alert"),
+        };
+        let cache_dir = crate::profile::scoped(cache_dir);
 
         // Create cache directory if it doesn't exist
         fs::create_dir_all(&cache_dir)
@@ -86,3 +1042,75 @@ impl Config {
         Ok(cache_dir)
     }
 }
+
+/// A path from an environment variable (None if unset or empty)
+fn env_path(name: &str) -> Option<PathBuf> {
+    std::env::var_os(name)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::watcher::Watcher;
+
+    /// Point the config and data at a directory of this test run
+    fn use_test_dirs() -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("web-watcher-alert-config-{}", std::process::id()));
+        std::env::set_var(CONFIG_ENV, dir.join("config.json"));
+        std::env::set_var(DATA_DIR_ENV, dir.join("data"));
+        dir
+    }
+
+    #[test]
+    fn guardrails_limit_public_sites() {
+        let guardrails = Guardrails {
+            min_interval: Duration::from_secs(60),
+            domain_min_intervals: BTreeMap::from([
+                (String::from("example.com"), 600),
+                (String::from("shop.example.com"), 300),
+            ]),
+        };
+        let minute = Some(Duration::from_secs(60));
+        assert_eq!(guardrails.min_interval_for("https://other.org/"), minute);
+        assert_eq!(guardrails.min_interval_for("https://www.example.com/"), Some(Duration::from_secs(600)));
+        // The most specific domain wins
+        assert_eq!(guardrails.min_interval_for("https://shop.example.com/gpu"), Some(Duration::from_secs(300)));
+        assert_eq!(guardrails.min_interval_for("http://localhost:8080/"), None);
+        assert_eq!(guardrails.min_interval_for("http://192.168.1.20/status"), None);
+        assert_eq!(guardrails.min_interval_for("http://printer.local/"), None);
+
+        assert!(guardrails.check_interval("https://www.example.com/", Duration::from_secs(120)).is_some());
+        assert!(guardrails.check_interval("https://www.example.com/", Duration::from_secs(600)).is_none());
+
+        let mut watcher = Watcher::new("https://other.org/".into(), Vec::new(), Duration::from_secs(10));
+        assert_eq!(guardrails.effective_interval(&watcher), Duration::from_secs(60));
+        watcher.allow_short_interval = true;
+        assert_eq!(guardrails.effective_interval(&watcher), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn backups_rotate_only_when_settings_change() {
+        let dir = use_test_dirs();
+        let backup = |n: usize| dir.join("backups").join(format!("config.json.{}", n));
+        let mut config = Config {
+            config_backups: 2,
+            watchers: vec![Watcher::new("https://example.com/".into(), Vec::new(), Duration::from_secs(600))],
+            ..Config::default()
+        };
+        config.save().unwrap();
+        assert!(!backup(1).exists());
+
+        // Check results aren't settings
+        config.watchers[0].last_checked = Some(Utc::now());
+        config.save().unwrap();
+        assert!(!backup(1).exists());
+
+        let before = fs::read_to_string(Config::config_path().unwrap()).unwrap();
+        config.watchers[0].check_interval = Duration::from_secs(1200);
+        config.save().unwrap();
+        assert_eq!(fs::read_to_string(backup(1)).unwrap(), before);
+        assert!(!backup(2).exists());
+    }
+}
@@ -0,0 +1,213 @@
+//! Config history
+//!
+//! Every save that changes a setting records a revision in
+//! config-history.jsonl next to the config: who saved it (the OS user and
+//! the command, e.g. `add` or `daemon`), when, what changed (a JSON diff of
+//! the settings) and the settings themselves, so `config rollback` can go
+//! back to them. Check results the monitor writes after every check
+//! (`last_checked`, error counts, ...) aren't settings: saves that only
+//! change those aren't recorded.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use crate::config::Config;
+use crate::diff;
+
+/// Revisions kept; older ones are dropped
+const MAX_REVISIONS: usize = 100;
+
+/// Watcher fields holding check results rather than settings
+const CHECK_RESULT_FIELDS: &[&str] = &[
+    "last_checked",
+    "consecutive_errors",
+    "last_error",
+    "blocked",
+    "slow",
+    "permanent_failures",
+    "permanent_failure_since",
+    "moved_to",
+];
+
+/// The command this process runs, recorded with its saves
+static COMMAND: OnceLock<String> = OnceLock::new();
+
+/// A recorded version of the config
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Revision {
+    pub number: u64,
+    pub saved_at: DateTime<Utc>,
+    /// OS user who saved it
+    pub user: String,
+    /// Command that saved it (`tui`, `daemon`, `add`, ...)
+    pub command: String,
+    /// Changed settings as JSON paths, like `watchers[2].keywords[0].text: "sale" → "deal"`
+    pub changes: Vec<String>,
+    /// The settings after the save
+    pub settings: Value,
+}
+
+/// Set the command recorded with this process's saves
+pub fn set_command(command: &str) {
+    let _ = COMMAND.set(command.to_string());
+}
+
+/// Record a revision if a save changed the settings. `old_content` is the
+/// config file before the save (None for a new config). Returns whether
+/// the settings changed.
+pub fn record(old_content: Option<&str>, new_content: &str) -> Result<bool> {
+    let new = settings(new_content)?;
+    // A file that didn't parse counts as none
+    let old = old_content.and_then(|content| settings(content).ok());
+    if old.as_ref() == Some(&new) {
+        return Ok(false);
+    }
+
+    let mut revisions = load()?;
+    let mut number = revisions.last().map_or(0, |last| last.number);
+
+    // The file was edited by hand since the last revision: record that
+    // first, so the next revision only shows what this save changed
+    if let (Some(last), Some(old)) = (revisions.last(), &old) {
+        if &last.settings != old {
+            number += 1;
+            let changes = changes(&last.settings, old);
+            revisions.push(Revision {
+                number,
+                saved_at: Utc::now(),
+                user: String::from("unknown"),
+                command: String::from("edited by hand"),
+                changes,
+                settings: old.clone(),
+            });
+        }
+    }
+
+    number += 1;
+    let changes = match &old {
+        Some(old) => changes(old, &new),
+        None => vec![String::from("(new config)")],
+    };
+    revisions.push(Revision {
+        number,
+        saved_at: Utc::now(),
+        user: current_user(),
+        command: COMMAND.get().cloned().unwrap_or_else(|| String::from("unknown")),
+        changes,
+        settings: new,
+    });
+
+    let skip = revisions.len().saturating_sub(MAX_REVISIONS);
+    save(&revisions[skip..])?;
+    Ok(true)
+}
+
+/// The recorded revisions, oldest first
+pub fn load() -> Result<Vec<Revision>> {
+    let path = history_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config history: {}", path.display()))?;
+    // Skip lines that don't parse (a revision cut short by a crash)
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// The config as of a revision. Check results are taken from `current`,
+/// for the watchers that still exist.
+pub fn rolled_back(revision: &Revision, current: &Config) -> Result<Config> {
+    with_check_results(revision.settings.clone(), current)
+        .with_context(|| format!("Revision {} doesn't fit this version's config", revision.number))
+}
+
+/// A config with these settings, and the check results of `current`'s
+/// watchers that are among them
+pub fn with_check_results(mut settings: Value, current: &Config) -> Result<Config> {
+    let current_value = serde_json::to_value(current).context("Failed to serialize config")?;
+    let current_watchers = current_value["watchers"].as_array().cloned().unwrap_or_default();
+
+    if let Some(watchers) = settings.get_mut("watchers").and_then(Value::as_array_mut) {
+        for watcher in watchers.iter_mut().filter_map(Value::as_object_mut) {
+            let Some(now) = current_watchers
+                .iter()
+                .find(|w| w.get("id").is_some() && w.get("id") == watcher.get("id"))
+            else {
+                continue;
+            };
+            for field in CHECK_RESULT_FIELDS {
+                if let Some(value) = now.get(*field) {
+                    watcher.insert(field.to_string(), value.clone());
+                }
+            }
+        }
+    }
+
+    serde_json::from_value(settings).context("The settings don't fit this version's config")
+}
+
+/// The settings of a config file's content (without check results)
+pub fn settings(content: &str) -> Result<Value> {
+    let mut value: Value = serde_json::from_str(content).context("Failed to parse config for its history")?;
+    if let Some(watchers) = value.get_mut("watchers").and_then(Value::as_array_mut) {
+        for watcher in watchers.iter_mut().filter_map(Value::as_object_mut) {
+            for field in CHECK_RESULT_FIELDS {
+                watcher.remove(*field);
+            }
+        }
+    }
+    Ok(value)
+}
+
+/// What changed between two versions of the settings, with the URL of the
+/// watcher each watcher change is about
+fn changes(old: &Value, new: &Value) -> Vec<String> {
+    let lines = diff::json_changes(&old.to_string(), &new.to_string()).unwrap_or_default();
+    lines
+        .into_iter()
+        .map(|line| {
+            let path = line.trim_start_matches(['+', '-', ' ']);
+            let Some(index) = path
+                .strip_prefix("watchers[")
+                .and_then(|rest| rest.split(']').next())
+                .and_then(|index| index.parse::<usize>().ok())
+            else {
+                return line;
+            };
+            // Removed watchers are only in the old version
+            let source = if line.starts_with('-') { old } else { new };
+            match source["watchers"][index]["url"].as_str() {
+                Some(url) => format!("{} ({})", line, url),
+                None => line,
+            }
+        })
+        .collect()
+}
+
+fn current_user() -> String {
+    std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_else(|_| String::from("unknown"))
+}
+
+fn save(revisions: &[Revision]) -> Result<()> {
+    let path = history_path()?;
+    let mut content = String::new();
+    for revision in revisions {
+        content.push_str(&serde_json::to_string(revision).context("Failed to serialize config revision")?);
+        content.push('\n');
+    }
+    fs::write(&path, content).with_context(|| format!("Failed to write config history: {}", path.display()))
+}
+
+fn history_path() -> Result<PathBuf> {
+    Ok(Config::config_dir()?.join("config-history.jsonl"))
+}
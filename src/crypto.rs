@@ -0,0 +1,307 @@
+//! Encryption of the cache and history at rest
+//!
+//! With `"encryption": { "enabled": true }`, cached pages and check history
+//! records are written encrypted (ChaCha20-Poly1305), so pages of internal
+//! tools don't sit in plaintext under ~/.cache. The key is kept in the OS
+//! keychain: the macOS Keychain (through `security`) or the Secret Service
+//! on Linux (through `secret-tool`). Without one, it's given in
+//! WEB_WATCHER_ALERT_KEY.
+//!
+//! Encrypted data is marked, so files written before encryption was turned
+//! on (or after it was turned off) are read either way. `encryption on` and
+//! `encryption off` rewrite the existing files.
+//!
+//! Each file's role and name (the watcher's cache or history, the review
+//! queue) is bound to its ciphertext as associated data, so one encrypted
+//! file can't be swapped in for another.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{ChaCha20Poly1305, Key, Nonce};
+use chacha20poly1305::aead::Payload;
+use std::io::Write;
+use std::process::{Command, Stdio};
+use std::sync::OnceLock;
+
+use crate::config::EncryptionSettings;
+
+/// Environment variable with the key (base64), for systems without a
+/// keychain
+pub const KEY_ENV: &str = "WEB_WATCHER_ALERT_KEY";
+
+/// Start of an encrypted file, followed by the nonce and the ciphertext
+const MAGIC: &[u8] = b"WWAENC1\0";
+
+/// Bytes to read to tell whether a file is encrypted
+pub const MAGIC_LEN: u64 = MAGIC.len() as u64;
+
+/// Start of an encrypted history line, followed by the file format in base64
+const LINE_PREFIX: &str = "enc1:";
+
+/// Where the key is kept in the keychain
+const KEYCHAIN_SERVICE: &str = "web-watcher-alert";
+const KEYCHAIN_ACCOUNT: &str = "storage-key";
+
+const NONCE_LEN: usize = 12;
+
+/// The key, once read from the environment or the keychain
+static KEY: OnceLock<Key> = OnceLock::new();
+
+/// What encrypted data is: bound to the ciphertext, so decrypting it as
+/// anything else fails
+#[derive(Debug, Clone, Copy)]
+pub enum Role<'a> {
+    /// A cache file, by its file name
+    Cache(&'a str),
+    /// A line of a watcher's history, by the watcher's ID
+    History(&'a str),
+    /// The queue of changes waiting for review
+    ReviewQueue,
+}
+
+impl Role<'_> {
+    fn associated_data(self) -> String {
+        match self {
+            Role::Cache(name) => format!("cache/{}", name),
+            Role::History(id) => format!("history/{}", id),
+            Role::ReviewQueue => String::from("review"),
+        }
+    }
+}
+
+/// Whether data was written encrypted
+pub fn is_encrypted(data: &[u8]) -> bool {
+    data.starts_with(MAGIC)
+}
+
+/// Whether a history line was written encrypted
+pub fn is_encrypted_line(line: &str) -> bool {
+    line.starts_with(LINE_PREFIX)
+}
+
+/// Data to write: encrypted if the settings turn encryption on
+pub fn seal(plain: &[u8], role: Role, settings: &EncryptionSettings) -> Result<Vec<u8>> {
+    if settings.enabled {
+        encrypt(plain, role)
+    } else {
+        Ok(plain.to_vec())
+    }
+}
+
+/// Data as read: decrypted if it was written encrypted
+pub fn open(data: &[u8], role: Role) -> Result<Vec<u8>> {
+    if is_encrypted(data) {
+        decrypt(data, role)
+    } else {
+        Ok(data.to_vec())
+    }
+}
+
+/// A history line to write: encrypted if the settings turn encryption on
+pub fn seal_line(line: &str, role: Role, settings: &EncryptionSettings) -> Result<String> {
+    if !settings.enabled {
+        return Ok(line.to_string());
+    }
+    let encrypted = encrypt(line.as_bytes(), role)?;
+    Ok(format!("{}{}", LINE_PREFIX, base64::engine::general_purpose::STANDARD.encode(encrypted)))
+}
+
+/// A history line as read: decrypted if it was written encrypted
+pub fn open_line(line: &str, role: Role) -> Result<String> {
+    let Some(encoded) = line.strip_prefix(LINE_PREFIX) else {
+        return Ok(line.to_string());
+    };
+    let data = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .context("Invalid encrypted history line")?;
+    String::from_utf8(decrypt(&data, role)?).context("Decrypted history line isn't text")
+}
+
+fn encrypt(plain: &[u8], role: Role) -> Result<Vec<u8>> {
+    let cipher = ChaCha20Poly1305::new(key()?);
+    let nonce = ChaCha20Poly1305::generate_nonce(&mut OsRng);
+    let aad = role.associated_data();
+    let ciphertext = cipher
+        .encrypt(&nonce, Payload { msg: plain, aad: aad.as_bytes() })
+        .map_err(|_| anyhow::anyhow!("Failed to encrypt"))?;
+
+    let mut data = Vec::with_capacity(MAGIC.len() + NONCE_LEN + ciphertext.len());
+    data.extend_from_slice(MAGIC);
+    data.extend_from_slice(&nonce);
+    data.extend_from_slice(&ciphertext);
+    Ok(data)
+}
+
+fn decrypt(data: &[u8], role: Role) -> Result<Vec<u8>> {
+    let rest = data.strip_prefix(MAGIC).context("Not encrypted data")?;
+    if rest.len() < NONCE_LEN {
+        anyhow::bail!("Encrypted data is cut short");
+    }
+    let (nonce, ciphertext) = rest.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(key()?)
+        .decrypt(Nonce::from_slice(nonce), Payload { msg: ciphertext, aad: role.associated_data().as_bytes() })
+        .map_err(|_| anyhow::anyhow!("Failed to decrypt (a different key, another file's data, or damaged data)"))
+}
+
+/// Whether a key can be had (without creating one)
+pub fn has_key() -> bool {
+    key().is_ok()
+}
+
+/// The key, from WEB_WATCHER_ALERT_KEY or the keychain
+fn key() -> Result<&'static Key> {
+    if let Some(key) = KEY.get() {
+        return Ok(key);
+    }
+    let encoded = match std::env::var(KEY_ENV) {
+        Ok(encoded) if !encoded.trim().is_empty() => encoded,
+        _ => keychain_lookup()?.with_context(|| {
+            format!("No encryption key: run `web-watcher-alert encryption on`, or set {}", KEY_ENV)
+        })?,
+    };
+    let key = parse_key(&encoded)?;
+    Ok(KEY.get_or_init(|| key))
+}
+
+fn parse_key(encoded: &str) -> Result<Key> {
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(encoded.trim())
+        .context("Invalid encryption key (expected base64)")?;
+    if bytes.len() != 32 {
+        anyhow::bail!("Invalid encryption key: expected 32 bytes, got {}", bytes.len());
+    }
+    Ok(*Key::from_slice(&bytes))
+}
+
+/// Make sure there is a key, creating one in the keychain if there's none
+/// yet. Returns where it is.
+pub fn ensure_key() -> Result<&'static str> {
+    if std::env::var(KEY_ENV).is_ok_and(|encoded| !encoded.trim().is_empty()) {
+        key()?;
+        return Ok(KEY_ENV);
+    }
+    if keychain_lookup()?.is_none() {
+        let encoded = base64::engine::general_purpose::STANDARD.encode(ChaCha20Poly1305::generate_key(&mut OsRng));
+        keychain_store(&encoded).with_context(|| {
+            format!(
+                "Failed to store a new key in the keychain. Set {}={} for every run instead \
+                 (and keep it safe), then run this again",
+                KEY_ENV, encoded
+            )
+        })?;
+    }
+    key()?;
+    Ok("the keychain")
+}
+
+/// The key stored in the keychain, if any
+#[cfg(target_os = "macos")]
+fn keychain_lookup() -> Result<Option<String>> {
+    let output = Command::new("security")
+        .args(["find-generic-password", "-s", KEYCHAIN_SERVICE, "-a", KEYCHAIN_ACCOUNT, "-w"])
+        .stderr(Stdio::null())
+        .output()
+        .context("Failed to run security")?;
+    Ok(output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).trim().to_string()))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn keychain_lookup() -> Result<Option<String>> {
+    let Ok(output) = Command::new("secret-tool")
+        .args(["lookup", "service", KEYCHAIN_SERVICE, "account", KEYCHAIN_ACCOUNT])
+        .stderr(Stdio::null())
+        .output()
+    else {
+        // No Secret Service tools: the key can only come from the environment
+        return Ok(None);
+    };
+    let key = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok((output.status.success() && !key.is_empty()).then_some(key))
+}
+
+/// The key goes to `security` on stdin (`-w` last, without a value, makes
+/// it prompt for the password and its confirmation), so it never shows in
+/// the process list
+#[cfg(target_os = "macos")]
+fn keychain_store(encoded: &str) -> Result<()> {
+    let mut child = Command::new("security")
+        .args(["add-generic-password", "-U", "-s", KEYCHAIN_SERVICE, "-a", KEYCHAIN_ACCOUNT, "-w"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("Failed to run security")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin
+            .write_all(format!("{}\n{}\n", encoded, encoded).as_bytes())
+            .context("Failed to write to security")?;
+    }
+    if !child.wait().is_ok_and(|status| status.success()) {
+        anyhow::bail!("security add-generic-password failed");
+    }
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn keychain_store(encoded: &str) -> Result<()> {
+    let mut child = Command::new("secret-tool")
+        .args([
+            "store",
+            "--label=Web Watcher Alert storage key",
+            "service",
+            KEYCHAIN_SERVICE,
+            "account",
+            KEYCHAIN_ACCOUNT,
+        ])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .context("secret-tool isn't installed (libsecret-tools)")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(encoded.as_bytes()).context("Failed to write to secret-tool")?;
+    }
+    if !child.wait().is_ok_and(|status| status.success()) {
+        anyhow::bail!("secret-tool store failed");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn on() -> EncryptionSettings {
+        KEY.get_or_init(|| *Key::from_slice(&[7; 32]));
+        EncryptionSettings { enabled: true }
+    }
+
+    #[test]
+    fn sealed_data_opens_as_what_it_was_sealed_as() {
+        let sealed = seal(b"page", Role::Cache("a.html"), &on()).unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_eq!(open(&sealed, Role::Cache("a.html")).unwrap(), b"page");
+
+        // Another watcher's cache, or the same bytes as something else
+        assert!(open(&sealed, Role::Cache("b.html")).is_err());
+        assert!(open(&sealed, Role::History("a.html")).is_err());
+    }
+
+    #[test]
+    fn history_lines_are_bound_to_their_watcher() {
+        let line = seal_line("{}", Role::History("one"), &on()).unwrap();
+        assert!(line.starts_with(LINE_PREFIX));
+        assert_eq!(open_line(&line, Role::History("one")).unwrap(), "{}");
+        assert!(open_line(&line, Role::History("two")).is_err());
+    }
+
+    #[test]
+    fn nothing_is_encrypted_with_encryption_off() {
+        let off = EncryptionSettings::default();
+        assert_eq!(seal(b"page", Role::Cache("a.html"), &off).unwrap(), b"page");
+        assert_eq!(seal_line("{}", Role::History("one"), &off).unwrap(), "{}");
+        assert!(!is_encrypted(b"page"));
+    }
+}
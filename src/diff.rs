@@ -1,8 +1,134 @@
-/// Content diffing module
-///
-/// Compares new content with cached version to detect meaningful changes
+//! Content diffing module
+//!
+//! Compares new content with cached version to detect meaningful changes
+//!
+//! Pages can be several megabytes (HTML dumps, large JSON endpoints), so the
+//! comparison streams over normalized lines instead of building normalized
+//! copies, and the line diff only runs on the region that actually changed.
+//!
+//! JSON endpoints are compared structurally instead (key order and
+//! formatting don't matter), with a path-based change list for alerts.
+//!
+//! HTML alerts (email) show the changed region side by side instead, with
+//! the changed words highlighted (`side_by_side`).
+//!
+//! Watchers choose what a change is made of (`DiffOptions`): whole lines
+//! suit tables and code, words prose, where a line is a whole paragraph.
+//! The algorithm is one of the `similar` crate's.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use similar::{ChangeTag, DiffOp, TextDiff, TextDiffConfig};
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Number of normalized lines compared at a time when narrowing a diff
+const CHUNK_LINES: usize = 256;
+
+/// Upper bound on time spent computing a line diff; past this the diff is
+/// approximate (still correct, just not minimal)
+const DIFF_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// Unchanged lines shown around the changed region of a side-by-side diff
+const CONTEXT_LINES: usize = 2;
+
+/// Most rows of a side-by-side diff
+const MAX_SIDE_BY_SIDE_ROWS: usize = 40;
+
+/// What a change is made of
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Granularity {
+    #[default]
+    Line,
+    Word,
+    Char,
+}
+
+impl Granularity {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "line" | "lines" => Ok(Granularity::Line),
+            "word" | "words" => Ok(Granularity::Word),
+            "char" | "chars" | "character" | "characters" => Ok(Granularity::Char),
+            other => anyhow::bail!("Unknown diff granularity: {} (use line, word or char)", other),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Granularity::Line => "line",
+            Granularity::Word => "word",
+            Granularity::Char => "char",
+        }
+    }
+
+    /// What the changes are counted in
+    fn unit(self) -> &'static str {
+        match self {
+            Granularity::Line => "lines",
+            Granularity::Word => "words",
+            Granularity::Char => "characters",
+        }
+    }
+}
 
-use similar::{ChangeTag, TextDiff};
+/// How the changes are found
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Algorithm {
+    /// Fast, and minimal
+    #[default]
+    Myers,
+    /// Lines unique to both versions anchor the diff, which keeps moved
+    /// blocks (reordered table rows) from turning into a jumble
+    Patience,
+    /// Longest common subsequence (slower on large changes)
+    Lcs,
+}
+
+impl Algorithm {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "myers" => Ok(Algorithm::Myers),
+            "patience" => Ok(Algorithm::Patience),
+            "lcs" => Ok(Algorithm::Lcs),
+            other => anyhow::bail!("Unknown diff algorithm: {} (use myers, patience or lcs)", other),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Myers => "myers",
+            Algorithm::Patience => "patience",
+            Algorithm::Lcs => "lcs",
+        }
+    }
+}
+
+/// How a watcher's changes are diffed (for alerts and the TUI)
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffOptions {
+    #[serde(default)]
+    pub granularity: Granularity,
+    #[serde(default)]
+    pub algorithm: Algorithm,
+}
+
+impl DiffOptions {
+    fn config(self) -> TextDiffConfig {
+        let mut config = TextDiff::configure();
+        let algorithm = match self.algorithm {
+            Algorithm::Myers => similar::Algorithm::Myers,
+            Algorithm::Patience => similar::Algorithm::Patience,
+            Algorithm::Lcs => similar::Algorithm::Lcs,
+        };
+        config.algorithm(algorithm).timeout(DIFF_TIMEOUT);
+        config
+    }
+}
 
 /// Check if content has meaningfully changed
 /// Returns true if there are actual content differences (ignoring minor whitespace)
@@ -12,65 +138,430 @@ pub fn has_changed(old_content: &str, new_content: &str) -> bool {
         return false;
     }
 
-    // Normalize whitespace for comparison
-    let old_normalized = normalize_whitespace(old_content);
-    let new_normalized = normalize_whitespace(new_content);
+    // JSON documents: compare values, ignoring formatting and key order
+    if let (Some(old), Some(new)) = (parse_json(old_content), parse_json(new_content)) {
+        return old != new;
+    }
 
-    // Check if normalized versions differ
-    old_normalized != new_normalized
+    // Compare normalized lines one by one, without allocating normalized copies
+    !normalized_lines(old_content).eq(normalized_lines(new_content))
 }
 
-/// Get a human-readable diff summary
-#[allow(dead_code)]
-pub fn get_diff(old_content: &str, new_content: &str) -> String {
-    let diff = TextDiff::from_lines(old_content, new_content);
-
-    let mut changes = Vec::new();
-    let mut added_lines = 0;
-    let mut removed_lines = 0;
-
-    for change in diff.iter_all_changes() {
-        match change.tag() {
-            ChangeTag::Delete => {
-                removed_lines += 1;
-                if removed_lines <= 3 {
-                    changes.push(format!("- {}", change.value().trim()));
-                }
-            }
-            ChangeTag::Insert => {
-                added_lines += 1;
-                if added_lines <= 3 {
-                    changes.push(format!("+ {}", change.value().trim()));
-                }
-            }
-            ChangeTag::Equal => {}
-        }
+/// Hash of the content as `has_changed` compares it (the JSON value, or the
+/// normalized lines), for watching a page without keeping a copy: two
+/// contents have the same fingerprint unless they differ meaningfully
+pub fn fingerprint(content: &str) -> String {
+    if let Some(json) = parse_json(content) {
+        return format!("{:x}", Sha256::digest(json.to_string()));
     }
+    let mut hasher = Sha256::new();
+    for line in normalized_lines(content) {
+        hasher.update(line.as_bytes());
+        hasher.update(b"\n");
+    }
+    format!("{:x}", hasher.finalize())
+}
 
+/// Get a human-readable diff summary, in the lines, words or characters
+/// that changed
+pub fn get_diff(old_content: &str, new_content: &str, options: DiffOptions) -> String {
+    let (changes, added, removed) = collect_changes(old_content, new_content, options);
     if changes.is_empty() {
         return String::from("Content changed (whitespace only)");
     }
 
+    // The first 3 removals and additions, in order
+    let mut shown = Vec::new();
+    let (mut removals, mut additions) = (0, 0);
+    for (removal, text) in &changes {
+        let count = if *removal { &mut removals } else { &mut additions };
+        *count += 1;
+        if *count <= 3 {
+            shown.push(format!("{} {}", if *removal { "-" } else { "+" }, text));
+        }
+    }
+
+    let unit = options.granularity.unit();
     let summary = format!(
-        "{} lines added, {} lines removed\n{}",
-        added_lines,
-        removed_lines,
-        changes.join("\n")
+        "{} {} added, {} {} removed\n{}",
+        added,
+        unit,
+        removed,
+        unit,
+        shown.join("\n")
     );
 
-    if added_lines > 3 || removed_lines > 3 {
+    if removals > 3 || additions > 3 {
         format!("{}\n... (showing first 3 changes)", summary)
     } else {
         summary
     }
 }
 
-/// Normalize whitespace for comparison
-fn normalize_whitespace(content: &str) -> String {
+/// The removed and the added lines (or runs of words or characters), in
+/// order
+pub fn changed_text(old_content: &str, new_content: &str, options: DiffOptions) -> (Vec<String>, Vec<String>) {
+    let (changes, _, _) = collect_changes(old_content, new_content, options);
+    let (removed, added): (Vec<_>, Vec<_>) = changes.into_iter().partition(|(removal, _)| *removal);
+    (
+        removed.into_iter().map(|(_, text)| text).collect(),
+        added.into_iter().map(|(_, text)| text).collect(),
+    )
+}
+
+/// (removed?, text) of each change, and how many units were added and
+/// removed
+fn collect_changes(old_content: &str, new_content: &str, options: DiffOptions) -> (Vec<(bool, String)>, usize, usize) {
+    let old_lines: Vec<&str> = normalized_lines(old_content).collect();
+    let new_lines: Vec<&str> = normalized_lines(new_content).collect();
+
+    // Skip unchanged chunks at both ends so only the changed region is diffed
+    let (old_changed, new_changed) = changed_region(&old_lines, &new_lines);
+
+    let mut changes: Vec<(bool, String)> = Vec::new();
+    let mut added = 0;
+    let mut removed = 0;
+
+    match options.granularity {
+        Granularity::Line => {
+            let diff = options.config().diff_slices(old_changed, new_changed);
+            for change in diff.iter_all_changes() {
+                match change.tag() {
+                    ChangeTag::Delete => {
+                        removed += 1;
+                        changes.push((true, change.value().to_string()));
+                    }
+                    ChangeTag::Insert => {
+                        added += 1;
+                        changes.push((false, change.value().to_string()));
+                    }
+                    ChangeTag::Equal => {}
+                }
+            }
+        }
+        granularity => {
+            let old_text = old_changed.join("\n");
+            let new_text = new_changed.join("\n");
+            let config = options.config();
+            let diff = match granularity {
+                Granularity::Word => config.diff_words(&old_text, &new_text),
+                _ => config.diff_chars(&old_text, &new_text),
+            };
+            // Runs of removed or added words make one change
+            let mut in_run = false;
+            for change in diff.iter_all_changes() {
+                let removal = match change.tag() {
+                    ChangeTag::Equal => {
+                        in_run = false;
+                        continue;
+                    }
+                    ChangeTag::Delete => true,
+                    ChangeTag::Insert => false,
+                };
+                let value = change.value();
+                if !value.trim().is_empty() {
+                    if removal {
+                        removed += 1;
+                    } else {
+                        added += 1;
+                    }
+                }
+                match changes.last_mut() {
+                    Some((last, text)) if in_run && *last == removal => text.push_str(value),
+                    _ => changes.push((removal, value.to_string())),
+                }
+                in_run = true;
+            }
+            for (_, text) in &mut changes {
+                *text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            }
+            changes.retain(|(_, text)| !text.is_empty());
+        }
+    }
+
+    (changes, added, removed)
+}
+
+/// Lines with surrounding whitespace trimmed and blank lines dropped
+fn normalized_lines(content: &str) -> impl Iterator<Item = &str> {
     content
         .lines()
         .map(|line| line.trim())
         .filter(|line| !line.is_empty())
+}
+
+/// A part of a line in a side-by-side diff
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segment {
+    pub text: String,
+    /// Removed (on the old side) or added (on the new side)
+    pub changed: bool,
+}
+
+/// A row of a side-by-side diff: a line of the old version next to the
+/// line of the new version it became. A line that was removed or added has
+/// nothing on the other side.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Row {
+    pub old: Option<Vec<Segment>>,
+    pub new: Option<Vec<Segment>>,
+}
+
+impl Row {
+    /// An unchanged line
+    fn equal(line: &str) -> Self {
+        let segment = vec![Segment { text: line.to_string(), changed: false }];
+        Self { old: Some(segment.clone()), new: Some(segment) }
+    }
+
+    pub fn is_equal(&self) -> bool {
+        self.old == self.new
+    }
+}
+
+/// The changed region of a page, line by line, with a few unchanged lines
+/// around it
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SideBySide {
+    pub rows: Vec<Row>,
+    /// Rows left out past `MAX_SIDE_BY_SIDE_ROWS`
+    pub more: usize,
+}
+
+/// Side-by-side diff of the changed region (whitespace is ignored as in
+/// `has_changed`). Lines replaced by others are paired up, with the words
+/// that differ marked as changed (the characters, with char granularity).
+/// None if only whitespace changed.
+pub fn side_by_side(old_content: &str, new_content: &str, options: DiffOptions) -> Option<SideBySide> {
+    let old_lines: Vec<&str> = normalized_lines(old_content).collect();
+    let new_lines: Vec<&str> = normalized_lines(new_content).collect();
+
+    let (prefix, suffix) = common_ends(&old_lines, &new_lines);
+    if prefix == old_lines.len() && prefix == new_lines.len() {
+        return None;
+    }
+    let start = prefix.saturating_sub(CONTEXT_LINES);
+    let context_after = suffix.min(CONTEXT_LINES);
+    let old_region = &old_lines[start..old_lines.len() - suffix + context_after];
+    let new_region = &new_lines[start..new_lines.len() - suffix + context_after];
+
+    let diff = options.config().diff_slices(old_region, new_region);
+
+    let mut rows = Vec::new();
+    for op in diff.ops() {
+        match *op {
+            DiffOp::Equal { old_index, len, .. } => {
+                rows.extend(old_region[old_index..old_index + len].iter().map(|line| Row::equal(line)));
+            }
+            DiffOp::Delete { old_index, old_len, .. } => {
+                rows.extend(old_region[old_index..old_index + old_len].iter().map(|line| Row {
+                    old: Some(vec![Segment { text: line.to_string(), changed: true }]),
+                    new: None,
+                }));
+            }
+            DiffOp::Insert { new_index, new_len, .. } => {
+                rows.extend(new_region[new_index..new_index + new_len].iter().map(|line| Row {
+                    old: None,
+                    new: Some(vec![Segment { text: line.to_string(), changed: true }]),
+                }));
+            }
+            DiffOp::Replace { old_index, old_len, new_index, new_len } => {
+                for i in 0..old_len.max(new_len) {
+                    let old = (i < old_len).then(|| old_region[old_index + i]);
+                    let new = (i < new_len).then(|| new_region[new_index + i]);
+                    rows.push(match (old, new) {
+                        (Some(old), Some(new)) => changed_parts(old, new, options),
+                        (old, new) => Row {
+                            old: old.map(|line| vec![Segment { text: line.to_string(), changed: true }]),
+                            new: new.map(|line| vec![Segment { text: line.to_string(), changed: true }]),
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    let more = rows.len().saturating_sub(MAX_SIDE_BY_SIDE_ROWS);
+    rows.truncate(MAX_SIDE_BY_SIDE_ROWS);
+    Some(SideBySide { rows, more })
+}
+
+/// A line and the line it became, with the words or characters that differ
+/// marked
+fn changed_parts(old: &str, new: &str, options: DiffOptions) -> Row {
+    let config = options.config();
+    let diff = match options.granularity {
+        Granularity::Char => config.diff_chars(old, new),
+        _ => config.diff_words(old, new),
+    };
+    let mut old_segments: Vec<Segment> = Vec::new();
+    let mut new_segments: Vec<Segment> = Vec::new();
+    for change in diff.iter_all_changes() {
+        let tag = change.tag();
+        if tag != ChangeTag::Insert {
+            push_segment(&mut old_segments, change.value(), tag == ChangeTag::Delete);
+        }
+        if tag != ChangeTag::Delete {
+            push_segment(&mut new_segments, change.value(), tag == ChangeTag::Insert);
+        }
+    }
+    Row { old: Some(old_segments), new: Some(new_segments) }
+}
+
+/// Add text to a line's segments (runs with the same mark make one segment)
+fn push_segment(segments: &mut Vec<Segment>, text: &str, changed: bool) {
+    match segments.last_mut() {
+        Some(last) if last.changed == changed => last.text.push_str(text),
+        _ => segments.push(Segment { text: text.to_string(), changed }),
+    }
+}
+
+/// Narrow two line lists down to the region between their longest common
+/// prefix and suffix, comparing whole chunks first and then single lines
+fn changed_region<'a>(old: &'a [&'a str], new: &'a [&'a str]) -> (&'a [&'a str], &'a [&'a str]) {
+    let (prefix, suffix) = common_ends(old, new);
+    (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix])
+}
+
+/// Lengths of the longest common prefix and suffix of two line lists (not
+/// overlapping)
+fn common_ends(old: &[&str], new: &[&str]) -> (usize, usize) {
+    let max_common = old.len().min(new.len());
+
+    // Common prefix: skip equal chunks, then equal lines
+    let mut prefix = 0;
+    while prefix + CHUNK_LINES <= max_common
+        && old[prefix..prefix + CHUNK_LINES] == new[prefix..prefix + CHUNK_LINES]
+    {
+        prefix += CHUNK_LINES;
+    }
+    while prefix < max_common && old[prefix] == new[prefix] {
+        prefix += 1;
+    }
+
+    // Common suffix (not overlapping the prefix)
+    let max_suffix = max_common - prefix;
+    let mut suffix = 0;
+    while suffix + CHUNK_LINES <= max_suffix
+        && old[old.len() - suffix - CHUNK_LINES..old.len() - suffix]
+            == new[new.len() - suffix - CHUNK_LINES..new.len() - suffix]
+    {
+        suffix += CHUNK_LINES;
+    }
+    while suffix < max_suffix && old[old.len() - suffix - 1] == new[new.len() - suffix - 1] {
+        suffix += 1;
+    }
+
+    (prefix, suffix)
+}
+
+/// Keep only the lines of the first fetch that also appear in every other
+/// fetch of the same page
+///
+/// Content that differs between back-to-back fetches (A/B-test variants,
+/// CSRF tokens, request IDs) is dropped, so it can't trigger a change.
+/// Lines are compared after trimming whitespace.
+pub fn consensus(fetches: &[String]) -> String {
+    let Some((first, others)) = fetches.split_first() else {
+        return String::new();
+    };
+
+    let other_lines: Vec<HashSet<&str>> = others
+        .iter()
+        .map(|page| normalized_lines(page).collect())
+        .collect();
+
+    normalized_lines(first)
+        .filter(|line| other_lines.iter().all(|lines| lines.contains(line)))
         .collect::<Vec<_>>()
         .join("\n")
 }
+
+/// Maximum length of a value shown in a JSON change line
+const MAX_JSON_VALUE_CHARS: usize = 40;
+
+/// Parse content as JSON if it looks like a JSON object or array
+fn parse_json(content: &str) -> Option<Value> {
+    let trimmed = content.trim_start();
+    if !trimmed.starts_with('{') && !trimmed.starts_with('[') {
+        return None;
+    }
+    serde_json::from_str(trimmed).ok()
+}
+
+/// Structural diff of two JSON documents as compact, path-based lines such
+/// as `items[3].price: 499 → 449`, `+ items[4]: {...}` or `- meta.etag`.
+/// Returns None unless both versions are JSON.
+pub fn json_changes(old_content: &str, new_content: &str) -> Option<Vec<String>> {
+    let old = parse_json(old_content)?;
+    let new = parse_json(new_content)?;
+
+    let mut changes = Vec::new();
+    diff_values(&old, &new, String::new(), &mut changes);
+    Some(changes)
+}
+
+fn diff_values(old: &Value, new: &Value, path: String, changes: &mut Vec<String>) {
+    match (old, new) {
+        (Value::Object(old_map), Value::Object(new_map)) => {
+            for (key, old_value) in old_map {
+                let child = object_path(&path, key);
+                match new_map.get(key) {
+                    Some(new_value) => diff_values(old_value, new_value, child, changes),
+                    None => changes.push(format!("- {}", child)),
+                }
+            }
+            for (key, new_value) in new_map {
+                if !old_map.contains_key(key) {
+                    changes.push(format!("+ {}: {}", object_path(&path, key), compact(new_value)));
+                }
+            }
+        }
+        (Value::Array(old_items), Value::Array(new_items)) => {
+            for (i, (old_item, new_item)) in old_items.iter().zip(new_items).enumerate() {
+                diff_values(old_item, new_item, format!("{}[{}]", path, i), changes);
+            }
+            for i in new_items.len()..old_items.len() {
+                changes.push(format!("- {}[{}]", path, i));
+            }
+            for (i, added) in new_items.iter().enumerate().skip(old_items.len()) {
+                changes.push(format!("+ {}[{}]: {}", path, i, compact(added)));
+            }
+        }
+        _ if old != new => {
+            let path = if path.is_empty() { String::from("(root)") } else { path };
+            changes.push(format!("{}: {} → {}", path, compact(old), compact(new)));
+        }
+        _ => {}
+    }
+}
+
+/// Path of an object member: `a.b` for simple keys, `a["odd key"]` otherwise
+fn object_path(parent: &str, key: &str) -> String {
+    let simple = !key.is_empty()
+        && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+
+    if !simple {
+        format!("{}[{}]", parent, Value::from(key))
+    } else if parent.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", parent, key)
+    }
+}
+
+/// Short single-line rendering of a value
+fn compact(value: &Value) -> String {
+    let text = match value {
+        Value::Object(map) if !map.is_empty() => String::from("{...}"),
+        Value::Array(items) if !items.is_empty() => format!("[{} items]", items.len()),
+        other => other.to_string(),
+    };
+
+    if text.chars().count() > MAX_JSON_VALUE_CHARS {
+        let cut: String = text.chars().take(MAX_JSON_VALUE_CHARS - 3).collect();
+        format!("{}...", cut)
+    } else {
+        text
+    }
+}
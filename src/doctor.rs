@@ -0,0 +1,580 @@
+//! Config integrity checks
+//!
+//! Backs the `doctor` command: inspects the configuration and environment
+//! for problems that would otherwise make the daemon misbehave silently
+//! (invalid or unreachable URLs, watchers that can never alert, unwritable
+//! cache directory, missing encryption key, broken notification, uptime
+//! ping or domain settings).
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::fs;
+
+use crate::{
+    config::{Config, TranslationProvider},
+    crypto,
+    fetcher::{self, RenderMode},
+    impersonate::{self, RequestProfile},
+    lease::LeaseBackend,
+    matcher::Keyword,
+    notify,
+    tasks::TaskTarget,
+    values, watchdog,
+    watcher::normalize_url,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Warning,
+    Error,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    /// What the issue is about (a watcher, or a global setting)
+    pub subject: String,
+    pub message: String,
+}
+
+impl Issue {
+    fn error(subject: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Error,
+            subject: subject.to_string(),
+            message: message.into(),
+        }
+    }
+
+    fn warning(subject: &str, message: impl Into<String>) -> Self {
+        Self {
+            severity: Severity::Warning,
+            subject: subject.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+/// Keyword settings that make keywords match or alert nothing
+fn check_keywords(subject: &str, keywords: &[Keyword], issues: &mut Vec<Issue>) {
+    for keyword in keywords.iter().filter(|k| k.is_regex) {
+        if let Err(e) = keyword.compile() {
+            issues.push(Issue::error(
+                subject,
+                format!("Invalid regex keyword {}: it will never match ({})", keyword.text, e),
+            ));
+        }
+    }
+    for keyword in keywords {
+        if keyword.channels.as_ref().is_some_and(|channels| channels.is_empty()) {
+            issues.push(Issue::warning(
+                subject,
+                format!("Keyword {} has no channels: its matches are never alerted", keyword.text),
+            ));
+        }
+    }
+}
+
+/// Every `{{name}}` of an alert title needs a regex keyword with that
+/// named group (or, for `value` and `change`, a tracked value)
+fn check_alert_title(subject: &str, title: &str, keywords: &[Keyword], tracking: bool, issues: &mut Vec<Issue>) {
+    let groups: Vec<String> = keywords
+        .iter()
+        .filter(|keyword| keyword.is_regex)
+        .filter_map(|keyword| keyword.compile().ok())
+        .flat_map(|regex| regex.capture_names().flatten().map(String::from).collect::<Vec<_>>())
+        .collect();
+    for name in notify::template_fields(title) {
+        let tracked = tracking && matches!(name, "value" | "change");
+        if !tracked && !groups.iter().any(|group| group == name) {
+            issues.push(Issue::warning(
+                subject,
+                format!("Alert title uses {{{{{}}}}}, but no regex keyword has a group named {}", name, name),
+            ));
+        }
+    }
+}
+
+/// Shared keyword sets: empty, unused or with broken keywords
+fn check_keyword_sets(config: &Config, issues: &mut Vec<Issue>) {
+    for (name, keywords) in &config.keyword_sets {
+        let subject = format!("Keyword set @{}", name);
+        if keywords.iter().all(|k| k.text.trim().is_empty()) {
+            issues.push(Issue::warning(&subject, "No keywords"));
+        }
+        if !config.watchers.iter().any(|w| w.keyword_sets.contains(name)) {
+            issues.push(Issue::warning(&subject, "Not used by any watcher"));
+        }
+        check_keywords(&subject, keywords, issues);
+    }
+}
+
+/// Matrices: invalid URL templates, and watchers out of step with their
+/// matrix (edited in the config file without `matrix apply`)
+fn check_matrices(config: &Config, issues: &mut Vec<Issue>) {
+    for matrix in &config.matrices {
+        let subject = format!("Matrix {}", matrix.name);
+        let urls = match matrix.child_urls() {
+            Ok(urls) => urls,
+            Err(e) => {
+                issues.push(Issue::error(&subject, format!("{:#}", e)));
+                continue;
+            }
+        };
+        let children: Vec<&str> = config
+            .watchers
+            .iter()
+            .filter(|w| w.matrix.as_ref() == Some(&matrix.name))
+            .map(|w| w.url.as_str())
+            .collect();
+        let stale = urls.iter().any(|url| !children.contains(&url.as_str()))
+            || children.iter().any(|url| !urls.iter().any(|u| u == url));
+        if stale {
+            issues.push(Issue::warning(
+                &subject,
+                format!("Out of step with its watchers: run `web-watcher-alert matrix apply {}`", matrix.name),
+            ));
+        }
+        check_keywords(&subject, &matrix.keywords, issues);
+    }
+    for watcher in &config.watchers {
+        if let Some(name) = &watcher.matrix {
+            if !config.matrices.iter().any(|m| &m.name == name) {
+                issues.push(Issue::warning(&watcher.url, format!("Belongs to matrix {}, which doesn't exist", name)));
+            }
+        }
+    }
+}
+
+/// Domain defaults: domain names, headers, selectors and render mode
+fn check_domains(config: &Config, issues: &mut Vec<Issue>) {
+    for (domain, settings) in &config.domains {
+        let subject = format!("Domain {}", domain);
+        if domain.contains("://") || domain.contains('/') {
+            issues.push(Issue::error(
+                &subject,
+                "Use a bare domain like example.com (no scheme or path): it matches no watcher",
+            ));
+        } else if !config.watchers.iter().any(|w| {
+            crate::domains::host(&w.url).is_some_and(|host| crate::domains::covers(&domain.to_lowercase(), &host))
+        }) {
+            issues.push(Issue::warning(&subject, "No watcher is on this domain"));
+        }
+        check_site_settings(&subject, &settings.headers, settings.selector.as_deref(), issues);
+        if let Some(render) = settings.render {
+            check_render(&subject, render, settings.request_profile, issues);
+        }
+    }
+}
+
+/// Headers that can't be sent and selectors that don't parse
+fn check_site_settings(
+    subject: &str,
+    headers: &BTreeMap<String, String>,
+    selector: Option<&str>,
+    issues: &mut Vec<Issue>,
+) {
+    for (name, value) in headers {
+        let valid = reqwest::header::HeaderName::from_bytes(name.as_bytes()).is_ok()
+            && reqwest::header::HeaderValue::from_str(value).is_ok();
+        if !valid {
+            issues.push(Issue::error(subject, format!("Invalid request header: {}: {}", name, value)));
+        }
+    }
+    if let Some(selector) = selector {
+        if let Err(e) = crate::domains::validate_selector(selector) {
+            issues.push(Issue::error(subject, format!("{:#}", e)));
+        }
+    }
+}
+
+/// Browser rendering needs Chrome or Chromium, impersonation the
+/// curl-impersonate command of the browser
+fn check_render(subject: &str, render: RenderMode, profile: Option<RequestProfile>, issues: &mut Vec<Issue>) {
+    match render {
+        RenderMode::Http => {}
+        RenderMode::Browser => {
+            if crate::browser::headless_command().is_none() {
+                issues.push(Issue::error(
+                    subject,
+                    "Render mode is browser, but Chrome/Chromium isn't installed: checks will fail",
+                ));
+            }
+        }
+        RenderMode::Impersonate => {
+            let profile = profile.unwrap_or(RequestProfile::ChromeLike);
+            if impersonate::command(profile).is_none() {
+                issues.push(Issue::error(
+                    subject,
+                    format!("Render mode is impersonate, but curl-impersonate for {} isn't installed: checks will fail", profile),
+                ));
+            }
+        }
+    }
+}
+
+/// Run all checks. Network checks (reachability) are skipped when `offline` is set.
+pub async fn run_checks(config: &Config, offline: bool) -> Vec<Issue> {
+    let mut issues = Vec::new();
+
+    check_cache_dir(&mut issues);
+    check_encryption(config, &mut issues);
+    check_lease(config, &mut issues);
+    check_notifications(config, &mut issues);
+    check_tasks(config, &mut issues);
+    check_uptime_ping(config, &mut issues);
+    check_companion(config, &mut issues);
+    check_translation(config, &mut issues);
+    check_keyword_sets(config, &mut issues);
+    check_matrices(config, &mut issues);
+    check_domains(config, &mut issues);
+    if !offline {
+        check_calendars(config, &mut issues).await;
+    }
+
+    for (i, watcher) in config.watchers.iter().enumerate() {
+        let subject = format!("Watcher #{} ({})", i + 1, watcher.url);
+
+        // URL must be valid and normalized
+        match normalize_url(&watcher.url) {
+            Ok(normalized) => {
+                if normalized != watcher.url {
+                    issues.push(Issue::warning(
+                        &subject,
+                        format!("URL is not normalized (expected {})", normalized),
+                    ));
+                }
+
+                if let Some(other) = config.find_watcher_by_url(&watcher.url) {
+                    if other != i {
+                        issues.push(Issue::warning(
+                            &subject,
+                            format!("Duplicate of watcher #{}", other + 1),
+                        ));
+                    }
+                }
+            }
+            Err(e) => issues.push(Issue::error(&subject, e.to_string())),
+        }
+
+        for name in &watcher.keyword_sets {
+            if !config.keyword_sets.contains_key(name) {
+                issues.push(Issue::error(&subject, format!("Uses undefined keyword set @{}", name)));
+            }
+        }
+
+        // A watcher without keywords can never alert
+        if config.keywords_for(watcher).iter().all(|k| k.text.trim().is_empty()) {
+            issues.push(Issue::error(&subject, "No keywords: this watcher will never alert"));
+        }
+        check_keywords(&subject, &watcher.keywords, &mut issues);
+        if let Some(title) = &watcher.alert_title {
+            let tracking = watcher.track_value.is_some();
+            check_alert_title(&subject, title, &config.keywords_for(watcher), tracking, &mut issues);
+        }
+        // Slower requests time out and fail instead
+        if let Some(threshold) = watcher.latency_threshold_ms {
+            if u128::from(threshold) >= fetcher::TIMEOUT.as_millis() {
+                issues.push(Issue::warning(
+                    &subject,
+                    format!(
+                        "Response time threshold {}ms is never reached: requests time out after {}s",
+                        threshold,
+                        fetcher::TIMEOUT.as_secs()
+                    ),
+                ));
+            }
+        }
+        if let Some(pattern) = &watcher.track_value {
+            if let Err(e) = values::compile(pattern) {
+                issues.push(Issue::error(&subject, format!("Invalid track_value pattern: {}", e)));
+            }
+        }
+        check_site_settings(&subject, &watcher.headers, watcher.selector.as_deref(), &mut issues);
+        for selector in &watcher.ignore {
+            if let Err(e) = crate::domains::validate_selector(selector) {
+                issues.push(Issue::error(&subject, format!("Ignored part: {:#}", e)));
+            }
+        }
+        if let Some(render) = watcher.render {
+            check_render(&subject, render, config.site_settings(watcher).request.profile, &mut issues);
+        }
+
+        if watcher.check_interval.as_secs() == 0 {
+            issues.push(Issue::error(&subject, "Check interval is zero"));
+        } else if !watcher.allow_short_interval {
+            if let Some(warning) = config.guardrails.check_interval(&watcher.url, watcher.check_interval) {
+                issues.push(Issue::warning(
+                    &subject,
+                    format!("{} The monitor will use the minimum instead.", warning),
+                ));
+            }
+        }
+
+        if watcher.is_active() && watcher.is_expired() {
+            issues.push(Issue::warning(
+                &subject,
+                "Expired: the monitor will disable it on its next run",
+            ));
+        }
+
+        if watcher.tls.insecure {
+            issues.push(Issue::warning(
+                &subject,
+                "TLS certificate verification is disabled (the site can be impersonated)",
+            ));
+        }
+        if let Err(e) = watcher.tls.load_ca_cert() {
+            issues.push(Issue::error(&subject, format!("{:#}", e)));
+        }
+
+        if let Some(hook) = &watcher.hook {
+            if hook.command.trim().is_empty() {
+                issues.push(Issue::error(&subject, "The hook has no command"));
+            }
+            if let Some(dir) = hook.working_dir.as_ref().filter(|dir| !dir.is_dir()) {
+                issues.push(Issue::error(
+                    &subject,
+                    format!("The hook's working directory {} doesn't exist: it won't run", dir.display()),
+                ));
+            }
+            if hook.timeout > crate::hook::MAX_TIMEOUT {
+                issues.push(Issue::warning(
+                    &subject,
+                    format!("The hook's timeout is over {}s: it's killed after {}s", crate::hook::MAX_TIMEOUT, crate::hook::MAX_TIMEOUT),
+                ));
+            }
+        }
+
+        // Reachability (only for enabled watchers, to keep the report quick)
+        if !offline && watcher.is_active() {
+            if let Err(e) = fetcher::fetch_url(&watcher.url, &config.site_settings(watcher).request).await {
+                issues.push(Issue::error(&subject, format!("Unreachable: {:#}", e)));
+            }
+        }
+    }
+
+    issues.sort_by_key(|issue| std::cmp::Reverse(issue.severity));
+    issues
+}
+
+/// The cache directory must exist and be writable
+fn check_cache_dir(issues: &mut Vec<Issue>) {
+    let cache_dir = match Config::cache_dir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            issues.push(Issue::error("Cache directory", format!("{:#}", e)));
+            return;
+        }
+    };
+
+    let probe = cache_dir.join(".doctor-write-test");
+    match fs::write(&probe, b"ok") {
+        Ok(()) => {
+            let _ = fs::remove_file(&probe);
+        }
+        Err(e) => issues.push(Issue::error(
+            "Cache directory",
+            format!("{} is not writable: {}", cache_dir.display(), e),
+        )),
+    }
+}
+
+/// Encrypted caches and histories need the key
+fn check_encryption(config: &Config, issues: &mut Vec<Issue>) {
+    if config.encryption.enabled && !crypto::has_key() {
+        issues.push(Issue::error(
+            "Encryption",
+            format!(
+                "No key in the keychain or {}: cached pages and history can't be read or written",
+                crypto::KEY_ENV
+            ),
+        ));
+    }
+}
+
+/// Tasks need a file to go to, or Reminders
+fn check_tasks(config: &Config, issues: &mut Vec<Issue>) {
+    let settings = &config.notifications.tasks;
+    match settings.target {
+        None => {}
+        Some(TaskTarget::Reminders) if !cfg!(target_os = "macos") => issues.push(Issue::error(
+            "Tasks",
+            "Reminders are only on macOS: use the markdown or todotxt task target",
+        )),
+        Some(TaskTarget::Reminders) => {}
+        Some(_) => {
+            if let Err(e) = settings.file() {
+                issues.push(Issue::error("Tasks", format!("{:#}", e)));
+            }
+        }
+    }
+}
+
+/// Calendars of quiet and paused periods must be readable
+async fn check_calendars(config: &Config, issues: &mut Vec<Issue>) {
+    for settings in &config.calendars {
+        if let Err(e) = crate::calendar::load(settings, config.timezone).await {
+            issues.push(Issue::error(
+                "Calendars",
+                format!("Failed to read {}: {:#} (its periods won't apply)", settings.url, e),
+            ));
+        }
+    }
+}
+
+/// Leases must be kept somewhere every machine reaches
+fn check_lease(config: &Config, issues: &mut Vec<Issue>) {
+    match config.lease.backend {
+        None => {}
+        Some(LeaseBackend::File) => match &config.lease.dir {
+            None => issues.push(Issue::error("Leases", "No lease folder: set \"lease\": { \"dir\": ... }")),
+            Some(dir) if !dir.is_dir() => issues.push(Issue::error(
+                "Leases",
+                format!("The lease folder {} doesn't exist (is the share mounted?)", dir.display()),
+            )),
+            Some(_) => {}
+        },
+        Some(LeaseBackend::Coordinator) if config.notifications.cluster.role.is_none() => issues.push(Issue::error(
+            "Leases",
+            "Leases from the coordinator need `cluster agent` or `cluster coordinator`: every watcher is checked here",
+        )),
+        Some(LeaseBackend::Coordinator) => {}
+    }
+}
+
+/// Notification channels must be usable
+fn check_notifications(config: &Config, issues: &mut Vec<Issue>) {
+    let settings = &config.notifications;
+
+    if !settings.desktop && settings.webhook().is_none() && settings.tasks.target.is_none() {
+        issues.push(Issue::warning(
+            "Notifications",
+            "All channels are disabled: matches will only be logged",
+        ));
+    } else if !notify::has_channel(settings) {
+        issues.push(Issue::warning(
+            "Notifications",
+            format!(
+                "No desktop session for notifications and no webhook: matches will only be logged (set a webhook URL or {})",
+                crate::config::WEBHOOK_ENV
+            ),
+        ));
+    }
+
+    if let Some(webhook) = &settings.webhook() {
+        match url::Url::parse(webhook) {
+            Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+            Ok(parsed) => issues.push(Issue::error(
+                "Notifications",
+                format!("Webhook URL uses unsupported scheme '{}'", parsed.scheme()),
+            )),
+            Err(e) => issues.push(Issue::error(
+                "Notifications",
+                format!("Webhook URL is invalid: {}", e),
+            )),
+        }
+    }
+}
+
+/// The uptime ping URL must be usable
+fn check_uptime_ping(config: &Config, issues: &mut Vec<Issue>) {
+    let Some(ping_url) = &config.uptime_ping.url else {
+        return;
+    };
+
+    match url::Url::parse(ping_url) {
+        Ok(parsed) if parsed.scheme() == "http" || parsed.scheme() == "https" => {}
+        Ok(parsed) => issues.push(Issue::error(
+            "Uptime ping",
+            format!("URL uses unsupported scheme '{}'", parsed.scheme()),
+        )),
+        Err(e) => issues.push(Issue::error("Uptime ping", format!("URL is invalid: {}", e))),
+    }
+
+    if config.uptime_ping.interval < watchdog::POLL_INTERVAL {
+        issues.push(Issue::warning(
+            "Uptime ping",
+            format!(
+                "Interval is shorter than {}s: pings are sent at most that often",
+                watchdog::POLL_INTERVAL.as_secs()
+            ),
+        ));
+    }
+}
+
+/// An enabled companion endpoint needs a token, and a config it can add to
+fn check_companion(config: &Config, issues: &mut Vec<Issue>) {
+    let settings = &config.companion;
+    if !settings.enabled {
+        return;
+    }
+    if settings.token.as_deref().is_none_or(str::is_empty) {
+        issues.push(Issue::error(
+            "Companion endpoint",
+            "No token, so it won't start: run `companion` to create one",
+        ));
+    }
+    if config.is_read_only() {
+        issues.push(Issue::warning(
+            "Companion endpoint",
+            "The config is read-only, so watchers can't be added through it",
+        ));
+    }
+}
+
+/// Translated watchers need a provider with what it requires
+fn check_translation(config: &Config, issues: &mut Vec<Issue>) {
+    let settings = &config.translation;
+    let translated = config.watchers.iter().filter(|w| w.translate).count();
+    let missing = match settings.provider {
+        None if translated > 0 => {
+            issues.push(Issue::error(
+                "Translation",
+                format!("{} watchers are translated, but no provider is configured", translated),
+            ));
+            return;
+        }
+        None => return,
+        Some(TranslationProvider::LibreTranslate) => settings.url.is_none().then_some("the server's address (url)"),
+        Some(TranslationProvider::DeepL) => settings.api_key.is_none().then_some("an API key (api_key)"),
+        Some(TranslationProvider::Command) => settings.command.is_none().then_some("a command (command)"),
+    };
+    if let Some(missing) = missing {
+        issues.push(Issue::error("Translation", format!("The provider needs {}", missing)));
+    }
+    if isolang::Language::from_639_1(&settings.language.to_lowercase()).is_none() {
+        issues.push(Issue::error(
+            "Translation",
+            format!("Unknown language '{}' (use an ISO 639-1 code like \"en\")", settings.language),
+        ));
+    }
+}
+
+/// Print the report. Returns an error if any error-level issue was found.
+pub fn print_report(config: &Config, issues: &[Issue]) -> Result<()> {
+    println!("Checked {} watchers.", config.watchers.len());
+
+    if issues.is_empty() {
+        println!("✓ No problems found.");
+        return Ok(());
+    }
+
+    for issue in issues {
+        let marker = match issue.severity {
+            Severity::Error => "✗ error",
+            Severity::Warning => "! warning",
+        };
+        println!("{}: {}: {}", marker, issue.subject, issue.message);
+    }
+
+    let errors = issues.iter().filter(|i| i.severity == Severity::Error).count();
+    let warnings = issues.len() - errors;
+    println!();
+    println!("{} error(s), {} warning(s).", errors, warnings);
+
+    if errors > 0 {
+        anyhow::bail!("Config check failed with {} error(s)", errors);
+    }
+    Ok(())
+}
@@ -0,0 +1,250 @@
+//! Per-domain default settings
+//!
+//! Watching many pages of one site (every product page of a shop) tends to
+//! repeat the same settings. The config's `domains` map holds them once per
+//! domain, covering its subdomains: request headers (or a browser's, see
+//! `impersonate`), a minimum spacing between requests to the site, the part
+//! of each page to watch (a CSS selector, and/or only its main content, as
+//! HTML or as text) and how pages are fetched. A more specific domain overrides a
+//! less specific one, and a watcher's own settings override both.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use crate::{
+    extractor::{self, ContentMode},
+    fetcher::{RenderMode, RequestOptions},
+    impersonate::RequestProfile,
+    watcher::Watcher,
+};
+
+/// Settings shared by the watchers of one domain
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DomainSettings {
+    /// Extra request headers, e.g. a cookie or Accept-Language
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Send the headers of a browser ("chrome-like" or "firefox-like")
+    #[serde(default)]
+    pub request_profile: Option<RequestProfile>,
+
+    /// Shortest time between two requests to the site (in seconds), across
+    /// all of its watchers
+    #[serde(default)]
+    pub request_spacing: Option<u64>,
+
+    /// Only watch the part of each page matching this CSS selector
+    #[serde(default)]
+    pub selector: Option<String>,
+
+    /// Only watch the main content of each page, without navigation,
+    /// footers and banners (see `readability`)
+    #[serde(default)]
+    pub main_content: Option<bool>,
+
+    /// Watch the HTML of each page ("raw_html") or its visible text
+    /// ("text"; see `extractor`)
+    #[serde(default)]
+    pub content: Option<ContentMode>,
+
+    /// How pages are fetched ("http", "browser" for pages built by
+    /// JavaScript, or "impersonate" for sites that fingerprint the client)
+    #[serde(default)]
+    pub render: Option<RenderMode>,
+}
+
+/// A watcher's settings after applying its domains' defaults
+#[derive(Debug, Clone, Default)]
+pub struct SiteSettings {
+    pub request: RequestOptions,
+    pub selector: Option<String>,
+    pub ignore: Vec<String>,
+    pub main_content: bool,
+    pub content: ContentMode,
+    /// Set for translated watchers when a provider is configured
+    pub translation: Option<crate::config::TranslationSettings>,
+}
+
+/// When each rate-limited domain may be requested next
+static NEXT_REQUEST: Mutex<BTreeMap<String, Instant>> = Mutex::new(BTreeMap::new());
+
+/// Whether `domain` covers `host` (the domain itself or a subdomain)
+pub fn covers(domain: &str, host: &str) -> bool {
+    host == domain || host.ends_with(&format!(".{}", domain))
+}
+
+/// The lowercased host of a URL
+pub fn host(url: &str) -> Option<String> {
+    let parsed = url::Url::parse(url).ok()?;
+    parsed.host_str().map(str::to_lowercase)
+}
+
+/// The settings a watcher runs with: its domains' defaults from the least
+/// to the most specific domain, then its own settings
+pub fn resolve(domains: &BTreeMap<String, DomainSettings>, watcher: &Watcher) -> SiteSettings {
+    let mut matching: Vec<(&String, &DomainSettings)> = match host(&watcher.url) {
+        Some(host) => domains
+            .iter()
+            .filter(|(domain, _)| covers(&domain.to_lowercase(), &host))
+            .collect(),
+        None => Vec::new(),
+    };
+    matching.sort_by_key(|(domain, _)| domain.len());
+
+    let mut site = SiteSettings {
+        request: RequestOptions {
+            tls: watcher.tls.clone(),
+            ..RequestOptions::default()
+        },
+        selector: None,
+        ignore: Vec::new(),
+        main_content: false,
+        content: ContentMode::RawHtml,
+        translation: None,
+    };
+    for (domain, settings) in matching {
+        site.request.headers.extend(settings.headers.clone());
+        if settings.request_profile.is_some() {
+            site.request.profile = settings.request_profile;
+        }
+        if let Some(secs) = settings.request_spacing.filter(|secs| *secs > 0) {
+            site.request.rate_limit = Some((domain.to_lowercase(), Duration::from_secs(secs)));
+        }
+        if settings.selector.is_some() {
+            site.selector = settings.selector.clone();
+        }
+        if let Some(main_content) = settings.main_content {
+            site.main_content = main_content;
+        }
+        if let Some(content) = settings.content {
+            site.content = content;
+        }
+        if let Some(render) = settings.render {
+            site.request.render = render;
+        }
+    }
+
+    site.request.headers.extend(watcher.headers.clone());
+    if watcher.request_profile.is_some() {
+        site.request.profile = watcher.request_profile;
+    }
+    if watcher.selector.is_some() {
+        site.selector = watcher.selector.clone();
+    }
+    site.ignore = watcher.ignore.clone();
+    if let Some(main_content) = watcher.main_content {
+        site.main_content = main_content;
+    }
+    if let Some(content) = watcher.content {
+        site.content = content;
+    }
+    if let Some(render) = watcher.render {
+        site.request.render = render;
+    }
+    site
+}
+
+/// Wait until `domain` may be requested again, and take the next slot.
+/// Slots are handed out in order, so watchers due at the same time are
+/// spread out instead of hitting the site together.
+pub async fn wait_turn(domain: &str, spacing: Duration) {
+    let wait = {
+        let mut next = NEXT_REQUEST.lock().unwrap_or_else(PoisonError::into_inner);
+        let now = Instant::now();
+        let slot = next.get(domain).copied().filter(|at| *at > now).unwrap_or(now);
+        next.insert(domain.to_string(), slot + spacing);
+        slot - now
+    };
+    if !wait.is_zero() {
+        tokio::time::sleep(wait).await;
+    }
+}
+
+/// The parts of an HTML page matching a CSS selector, in document order
+pub fn select(html: &str, selector: &str) -> Result<String> {
+    let parsed = scraper::Selector::parse(selector)
+        .map_err(|e| anyhow::anyhow!("Invalid CSS selector {}: {}", selector, e))?;
+    let document = scraper::Html::parse_document(html);
+
+    let parts: Vec<String> = document.select(&parsed).map(|element| element.html()).collect();
+    if parts.is_empty() {
+        // Treating this as an empty page would report the whole page as
+        // gone (and everything as new once the selector matches again)
+        anyhow::bail!("Nothing on the page matches the selector {}", selector);
+    }
+    Ok(parts.join("\n"))
+}
+
+/// An HTML page without the elements matching any of the CSS selectors
+pub fn without(html: &str, selectors: &[String]) -> Result<String> {
+    let mut document = scraper::Html::parse_document(html);
+    for selector in selectors {
+        let parsed = scraper::Selector::parse(selector)
+            .map_err(|e| anyhow::anyhow!("Invalid CSS selector {}: {}", selector, e))?;
+        let ids: Vec<_> = document.select(&parsed).map(|element| element.id()).collect();
+        for id in ids {
+            if let Some(mut node) = document.tree.get_mut(id) {
+                node.detach();
+            }
+        }
+    }
+    Ok(document.html())
+}
+
+/// The part of a fetched page a watcher looks at: the page without its
+/// ignored parts, what the selector picks of that, of that the main content
+/// if the watcher only watches that, and as text if it watches the text
+pub fn watched_part(site: &SiteSettings, content: String) -> Result<String> {
+    let content = match site.ignore.is_empty() {
+        true => content,
+        false => without(&content, &site.ignore)?,
+    };
+    let content = match &site.selector {
+        Some(selector) => select(&content, selector)?,
+        None => content,
+    };
+    let content = match site.main_content {
+        true => crate::readability::extract(&content)?,
+        false => content,
+    };
+    match site.content {
+        ContentMode::Text => Ok(extractor::to_text(&content)),
+        ContentMode::RawHtml => Ok(content),
+    }
+}
+
+/// Check that a CSS selector parses
+pub fn validate_selector(selector: &str) -> Result<()> {
+    scraper::Selector::parse(selector)
+        .map(|_| ())
+        .map_err(|e| anyhow::anyhow!("Invalid CSS selector {}: {}", selector, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ignored_parts_are_left_out() {
+        let site = SiteSettings {
+            ignore: vec![String::from("time"), String::from(".ad")],
+            content: ContentMode::Text,
+            ..SiteSettings::default()
+        };
+        let page = "<html><body><p>In stock</p><time>3 minutes ago</time><div class=\"ad\">Sale!</div></body></html>";
+        let watched = watched_part(&site, page.to_string()).unwrap();
+        assert!(watched.contains("In stock"));
+        assert!(!watched.contains("minutes ago"));
+        assert!(!watched.contains("Sale"));
+
+        let bad = SiteSettings {
+            ignore: vec![String::from("[")],
+            ..SiteSettings::default()
+        };
+        assert!(watched_part(&bad, page.to_string()).is_err());
+    }
+}
@@ -0,0 +1,368 @@
+//! Monitor event log
+//!
+//! Everything the monitor reports (checks, matches, errors, pauses) goes
+//! through an `EventLog`. The daemon prints events to stdout/stderr, which
+//! the service scripts redirect to log files (or, as JSON lines, to a
+//! container's log collector); the TUI's live monitoring view receives them
+//! over a channel instead. Each watcher's events can also go to a file of
+//! its own (see `logfiles`).
+//!
+//! Besides the log lines, the monitor publishes the outcome of each check
+//! as a `MonitorEvent` on an `EventBus`, for consumers that follow the
+//! watchers rather than read the log (the TUI's dashboard). Every
+//! subscriber gets every event, and so does the events file (see
+//! `logfiles`).
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+
+use crate::logfiles::{self, WatcherFiles};
+use crate::timezone::{Zone, LOG_FORMAT};
+
+/// Severity of an event (ordered from least to most important)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Level {
+    Info,
+    /// A page changed, without keywords matching
+    #[serde(alias = "changes")]
+    Change,
+    /// Keywords matched and an alert was sent
+    #[serde(alias = "matches")]
+    Match,
+    #[serde(alias = "warnings")]
+    Warning,
+    #[serde(alias = "errors")]
+    Error,
+}
+
+impl Level {
+    /// Lowercase name, as written in JSON logs
+    pub fn name(self) -> &'static str {
+        match self {
+            Level::Info => "info",
+            Level::Change => "change",
+            Level::Match => "match",
+            Level::Warning => "warning",
+            Level::Error => "error",
+        }
+    }
+
+    /// Parse a level name (singular or plural, like "changes")
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "info" => Ok(Level::Info),
+            "change" | "changes" => Ok(Level::Change),
+            "match" | "matches" => Ok(Level::Match),
+            "warning" | "warnings" => Ok(Level::Warning),
+            "error" | "errors" => Ok(Level::Error),
+            other => anyhow::bail!("Unknown log level '{}' (expected info, changes, matches, warnings or errors)", other),
+        }
+    }
+}
+
+/// How printed events are formatted
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// `[timestamp] message`, with warnings and errors on stderr
+    Text,
+    /// One JSON object per line, all on stdout (for `docker logs` and
+    /// log collectors)
+    Json,
+}
+
+impl LogFormat {
+    /// Parse "text" or "json"
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => anyhow::bail!("Unknown log format '{}' (expected text or json)", other),
+        }
+    }
+}
+
+/// One line of monitor output
+#[derive(Debug, Clone)]
+pub struct Event {
+    pub at: DateTime<Utc>,
+    pub level: Level,
+    /// URL of the watcher the event is about, if any
+    pub watcher: Option<String>,
+    pub message: String,
+}
+
+/// Where the monitor sends its events
+#[derive(Debug, Clone)]
+pub struct EventLog {
+    /// Zone for the timestamps of printed lines
+    zone: Zone,
+    /// Format of printed lines
+    format: LogFormat,
+    /// Channel to the TUI (None prints to stdout/stderr)
+    sink: Option<UnboundedSender<Event>>,
+    /// Watcher that events logged through this handle are about
+    watcher: Option<String>,
+    /// ID of that watcher, for its log file
+    watcher_id: Option<String>,
+    /// The watchers' log files, if they're written
+    files: Option<Arc<WatcherFiles>>,
+    /// Less important events are dropped (`-q` keeps matches and up)
+    min_level: Level,
+}
+
+impl EventLog {
+    /// Print events to stdout (in text format, errors and warnings to stderr)
+    pub fn stdout(zone: Zone, format: LogFormat) -> Self {
+        Self {
+            zone,
+            format,
+            sink: None,
+            watcher: None,
+            watcher_id: None,
+            files: None,
+            min_level: Level::Info,
+        }
+    }
+
+    /// Send events over a channel
+    pub fn channel(zone: Zone) -> (Self, UnboundedReceiver<Event>) {
+        let (sink, receiver) = mpsc::unbounded_channel();
+        let log = Self {
+            zone,
+            format: LogFormat::Text,
+            sink: Some(sink),
+            watcher: None,
+            watcher_id: None,
+            files: None,
+            min_level: Level::Info,
+        };
+        (log, receiver)
+    }
+
+    /// A handle that drops events less important than `level`
+    pub fn min_level(self, level: Level) -> Self {
+        Self {
+            min_level: level,
+            ..self
+        }
+    }
+
+    /// The least important events that aren't dropped
+    pub fn level(&self) -> Level {
+        self.min_level
+    }
+
+    /// A handle that also writes the events of watchers to their log
+    /// files (see `with_file`)
+    pub fn watcher_files(self, files: WatcherFiles) -> Self {
+        Self {
+            files: Some(Arc::new(files)),
+            ..self
+        }
+    }
+
+    /// A handle whose events are about the given watcher
+    pub fn for_watcher(&self, url: &str) -> Self {
+        Self {
+            watcher: Some(url.to_string()),
+            ..self.clone()
+        }
+    }
+
+    /// A handle that also writes to the log file of the watcher with this
+    /// ID, if watcher files are written
+    pub fn with_file(self, watcher_id: &str) -> Self {
+        Self {
+            watcher_id: Some(watcher_id.to_string()),
+            ..self
+        }
+    }
+
+    pub fn info(&self, message: impl Into<String>) {
+        self.log(Level::Info, message.into());
+    }
+
+    pub fn changed(&self, message: impl Into<String>) {
+        self.log(Level::Change, message.into());
+    }
+
+    pub fn matched(&self, message: impl Into<String>) {
+        self.log(Level::Match, message.into());
+    }
+
+    pub fn warning(&self, message: impl Into<String>) {
+        self.log(Level::Warning, message.into());
+    }
+
+    pub fn error(&self, message: impl Into<String>) {
+        self.log(Level::Error, message.into());
+    }
+
+    /// A summary of what the dropped events were about: logged (as info)
+    /// whatever the level
+    pub fn summary(&self, message: impl Into<String>) {
+        self.emit(Level::Info, message.into());
+    }
+
+    fn log(&self, level: Level, message: String) {
+        // The files get everything: they're for looking into one watcher
+        if let (Some(files), Some(id)) = (&self.files, &self.watcher_id) {
+            files.write(id, &format!("[{}] {}", self.zone.format(Utc::now(), LOG_FORMAT), message));
+        }
+        if level < self.min_level {
+            return;
+        }
+        self.emit(level, message);
+    }
+
+    fn emit(&self, level: Level, message: String) {
+        let event = Event {
+            at: Utc::now(),
+            level,
+            watcher: self.watcher.clone(),
+            message,
+        };
+
+        match &self.sink {
+            // The receiver is gone once the view closes; nothing to do then
+            Some(sink) => {
+                let _ = sink.send(event);
+            }
+            None if self.format == LogFormat::Json => {
+                // JSON logs are read by machines: UTC timestamps, not the display zone
+                let line = serde_json::json!({
+                    "time": event.at.to_rfc3339_opts(SecondsFormat::Millis, true),
+                    "level": level.name(),
+                    "watcher": event.watcher,
+                    "message": event.message,
+                });
+                println!("{}", line);
+            }
+            None => {
+                // Without the info lines, a line has to name its watcher
+                let message = match &event.watcher {
+                    Some(url) if self.min_level > Level::Info && !event.message.contains(url.as_str()) => {
+                        format!("{}: {}", url, event.message.trim_start())
+                    }
+                    _ => event.message,
+                };
+                let line = format!("[{}] {}", self.zone.format(event.at, LOG_FORMAT), message);
+                if level >= Level::Warning {
+                    eprintln!("{}", line);
+                } else {
+                    println!("{}", line);
+                }
+            }
+        }
+    }
+}
+
+/// The outcome of a check of a watcher
+#[derive(Debug, Clone)]
+pub enum MonitorEvent {
+    /// A check succeeded (`changed`: the page differs from the cached copy)
+    CheckCompleted {
+        watcher_id: String,
+        url: String,
+        at: DateTime<Utc>,
+        changed: bool,
+    },
+    /// A check found new keyword matches and notified them (published
+    /// before its `CheckCompleted`)
+    MatchFound {
+        watcher_id: String,
+        url: String,
+        at: DateTime<Utc>,
+        keywords: Vec<String>,
+    },
+    /// A check failed
+    Error {
+        watcher_id: String,
+        url: String,
+        at: DateTime<Utc>,
+        message: String,
+    },
+    /// A check got a block page (a bot challenge or CAPTCHA) instead of the
+    /// page
+    Blocked {
+        watcher_id: String,
+        url: String,
+        at: DateTime<Utc>,
+        reason: String,
+    },
+}
+
+impl MonitorEvent {
+    /// ID of the watcher the event is about
+    pub fn watcher_id(&self) -> &str {
+        match self {
+            MonitorEvent::CheckCompleted { watcher_id, .. }
+            | MonitorEvent::MatchFound { watcher_id, .. }
+            | MonitorEvent::Error { watcher_id, .. }
+            | MonitorEvent::Blocked { watcher_id, .. } => watcher_id,
+        }
+    }
+
+    /// When it happened
+    pub fn at(&self) -> DateTime<Utc> {
+        match self {
+            MonitorEvent::CheckCompleted { at, .. }
+            | MonitorEvent::MatchFound { at, .. }
+            | MonitorEvent::Error { at, .. }
+            | MonitorEvent::Blocked { at, .. } => *at,
+        }
+    }
+
+    /// The event as a line of the events file
+    pub fn to_json(&self) -> serde_json::Value {
+        let (event, mut line) = match self {
+            MonitorEvent::CheckCompleted { changed, .. } => ("check", serde_json::json!({ "changed": changed })),
+            MonitorEvent::MatchFound { keywords, .. } => ("match", serde_json::json!({ "keywords": keywords })),
+            MonitorEvent::Error { message, .. } => ("error", serde_json::json!({ "message": message })),
+            MonitorEvent::Blocked { reason, .. } => ("blocked", serde_json::json!({ "reason": reason })),
+        };
+        let url = match self {
+            MonitorEvent::CheckCompleted { url, .. }
+            | MonitorEvent::MatchFound { url, .. }
+            | MonitorEvent::Error { url, .. }
+            | MonitorEvent::Blocked { url, .. } => url,
+        };
+        line["time"] = self.at().to_rfc3339_opts(SecondsFormat::Millis, true).into();
+        line["event"] = event.into();
+        line["watcher_id"] = self.watcher_id().into();
+        line["url"] = url.as_str().into();
+        line
+    }
+}
+
+/// Hands the monitor's events to every subscriber
+#[derive(Debug, Clone, Default)]
+pub struct EventBus {
+    subscribers: Arc<Mutex<Vec<UnboundedSender<MonitorEvent>>>>,
+}
+
+impl EventBus {
+    /// Receive every event published from now on
+    pub fn subscribe(&self) -> UnboundedReceiver<MonitorEvent> {
+        let (sender, receiver) = mpsc::unbounded_channel();
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(sender);
+        receiver
+    }
+
+    /// Send an event to all subscribers (and the events file), dropping
+    /// the subscribers that went away
+    pub fn publish(&self, event: MonitorEvent) {
+        logfiles::record(event.to_json());
+        self.subscribers
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .retain(|subscriber| subscriber.send(event.clone()).is_ok());
+    }
+}
@@ -0,0 +1,116 @@
+//! Visible-text extraction
+//!
+//! The HTML of a page changes without anything on it changing: script
+//! bundles get new hashes, forms new CSRF tokens, ads new IDs. With a
+//! watcher's `content` set to `text` (or its domain's), the page is turned
+//! into the text a reader sees before it's compared with the cached copy
+//! and searched for keywords: scripts, styles, comments, attributes and
+//! hidden elements are dropped, every block (paragraph, heading, list item,
+//! table row) is a line of its own and whitespace is collapsed. Keywords
+//! then only match what's on the page, not a class name or a script.
+//!
+//! The default, `raw_html`, watches the HTML as it's served. JSON and feeds
+//! are always watched as they are.
+
+use anyhow::Result;
+use scraper::{ElementRef, Html, Node};
+use serde::{Deserialize, Serialize};
+
+/// What of a page is watched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentMode {
+    /// The HTML as served
+    #[default]
+    RawHtml,
+    /// The visible text
+    Text,
+}
+
+impl ContentMode {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().replace('-', "_").as_str() {
+            "raw_html" | "html" => Ok(ContentMode::RawHtml),
+            "text" => Ok(ContentMode::Text),
+            other => anyhow::bail!("Unknown content mode: {} (use raw_html or text)", other),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ContentMode::RawHtml => "raw_html",
+            ContentMode::Text => "text",
+        }
+    }
+}
+
+/// Elements whose content is never visible text
+const INVISIBLE: &[&str] = &["head", "script", "style", "noscript", "template", "svg", "iframe", "object"];
+
+/// Elements that start a line of their own
+const BLOCKS: &[&str] = &[
+    "address", "article", "aside", "blockquote", "dd", "details", "dialog", "div", "dl", "dt", "fieldset",
+    "figcaption", "figure", "footer", "form", "h1", "h2", "h3", "h4", "h5", "h6", "header", "hr", "li", "main",
+    "nav", "ol", "option", "p", "pre", "section", "summary", "table", "tr", "ul",
+];
+
+/// The visible text of an HTML page (other content, like JSON or a feed,
+/// is returned as it is)
+pub fn to_text(content: &str) -> String {
+    if !is_html(content) {
+        return content.to_string();
+    }
+    let document = Html::parse_document(content);
+    let mut text = String::new();
+    write_text(document.root_element(), &mut text);
+
+    let mut lines = Vec::new();
+    for line in text.lines() {
+        let line = line.split_whitespace().collect::<Vec<_>>().join(" ");
+        if !line.is_empty() {
+            lines.push(line);
+        }
+    }
+    lines.join("\n")
+}
+
+/// Whether the content is HTML (a page, or the part a selector picked)
+fn is_html(content: &str) -> bool {
+    let start = content.trim_start_matches('\u{feff}').trim_start();
+    let lower = start.get(..5).unwrap_or(start).to_lowercase();
+    start.starts_with('<') && !matches!(lower.as_str(), "<?xml" | "<rss " | "<rss>" | "<feed")
+}
+
+fn write_text(element: ElementRef, out: &mut String) {
+    let value = element.value();
+    let name = value.name();
+    if INVISIBLE.contains(&name) || value.attr("hidden").is_some() || value.attr("aria-hidden") == Some("true") {
+        return;
+    }
+    let block = BLOCKS.contains(&name);
+    if block {
+        out.push('\n');
+    }
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(text),
+            Node::Element(child_element) if child_element.name() == "br" => out.push('\n'),
+            // A cell ends with a tab, so the cells of a row stay apart
+            Node::Element(child_element) if matches!(child_element.name(), "td" | "th") => {
+                if let Some(cell) = ElementRef::wrap(child) {
+                    write_text(cell, out);
+                    out.push('\t');
+                }
+            }
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child) {
+                    write_text(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+    if block {
+        out.push('\n');
+    }
+}
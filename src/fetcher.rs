@@ -1,30 +1,667 @@
-/// HTTP content fetching module
-///
-/// Fetches webpage content with error handling
+//! HTTP content fetching module
+//!
+//! Fetches webpage content with error handling. Pages over the configured
+//! size cap are streamed through a hash (and a keyword scan) instead of
+//! being held in memory. `http+unix://` URLs are fetched over a unix socket
+//! (see `unix_socket`). Pages of sites that build their content with
+//! JavaScript can be rendered in a headless browser instead (see `browser`),
+//! and requests can imitate a browser's (see `impersonate`). Block pages
+//! (bot challenges, CAPTCHAs) fail the fetch with a `BlockedError` (see
+//! `blocked`).
 
 use anyhow::{Context, Result};
-use std::time::Duration;
+use hyper::body::{Bytes, HttpBody};
+use reqwest::header::HeaderMap;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
-/// Fetch content from a URL
-pub async fn fetch_url(url: &str) -> Result<String> {
-    // Create HTTP client with timeout
-    let client = reqwest::Client::builder()
-        .timeout(Duration::from_secs(30))
-        .user_agent("Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36")
-        .build()
-        .context("Failed to create HTTP client")?;
-
-    // Fetch the URL
-    let response = client
-        .get(url)
+use crate::matcher::{Keyword, StreamSearch};
+use crate::impersonate::{self, RequestProfile};
+use crate::{blocked, browser, domains, unix_socket};
+
+/// Request timeout
+pub const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Redirects followed per request (the same limit as reqwest's default)
+pub const MAX_REDIRECTS: usize = 10;
+
+const USER_AGENT: &str = "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36";
+
+/// Most of an error response read to look for a block page
+const MAX_ERROR_BODY_BYTES: usize = 64 * 1024;
+
+/// Response headers recorded with each check (lowercase names)
+const RECORDED_HEADERS: &[&str] = &[
+    "content-type",
+    "etag",
+    "last-modified",
+    "cache-control",
+    "age",
+    "server",
+    "x-cache",
+    "cf-cache-status",
+    "cf-mitigated",
+];
+
+/// Per-watcher TLS settings, for internal services with self-signed or
+/// privately issued certificates
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TlsOptions {
+    /// Extra CA certificate (PEM file) to trust in addition to the system's
+    #[serde(default)]
+    pub ca_cert: Option<PathBuf>,
+
+    /// Skip certificate and hostname verification entirely. Anyone on the
+    /// network path can impersonate the site, so only use this for
+    /// services you control.
+    #[serde(default)]
+    pub insecure: bool,
+}
+
+impl TlsOptions {
+    /// The custom CA certificate file, if one is configured
+    pub fn ca_cert_path(&self) -> Result<Option<&Path>> {
+        let Some(path) = &self.ca_cert else {
+            return Ok(None);
+        };
+        if let Some(user) = crate::system::monitored_user() {
+            anyhow::bail!(
+                "Custom CA files are off for the users of the system daemon ({}): they would be read as the service's account",
+                user
+            );
+        }
+        Ok(Some(path))
+    }
+
+    /// Load the custom CA certificate, if one is configured
+    pub fn load_ca_cert(&self) -> Result<Option<reqwest::Certificate>> {
+        let Some(path) = self.ca_cert_path()? else {
+            return Ok(None);
+        };
+
+        let pem = fs::read(path)
+            .with_context(|| format!("Failed to read CA certificate: {}", path.display()))?;
+        let cert = reqwest::Certificate::from_pem(&pem)
+            .with_context(|| format!("Invalid CA certificate (expected PEM): {}", path.display()))?;
+        Ok(Some(cert))
+    }
+}
+
+/// How a page is fetched
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RenderMode {
+    /// A plain HTTP request
+    #[default]
+    Http,
+    /// Load the page in a headless browser and take the DOM once scripts
+    /// have run
+    Browser,
+    /// Fetch with curl-impersonate, whose TLS handshake matches the browser
+    /// of the request profile
+    Impersonate,
+}
+
+/// Everything needed to request a watcher's page (see `domains` for how it
+/// is put together)
+#[derive(Debug, Clone, Default)]
+pub struct RequestOptions {
+    pub tls: TlsOptions,
+    /// Extra request headers
+    pub headers: BTreeMap<String, String>,
+    pub render: RenderMode,
+    /// Browser whose headers are sent (under `headers`)
+    pub profile: Option<RequestProfile>,
+    /// Domain and minimum time between requests to it
+    pub rate_limit: Option<(String, Duration)>,
+}
+
+impl RequestOptions {
+    /// The User-Agent header to send
+    pub fn user_agent(&self) -> &str {
+        self.headers
+            .iter()
+            .find(|(name, _)| name.eq_ignore_ascii_case("user-agent"))
+            .map(|(_, value)| value.as_str())
+            .or(self.profile.map(|profile| profile.user_agent()))
+            .unwrap_or(USER_AGENT)
+    }
+
+    /// The headers to send besides the User-Agent: the request profile's,
+    /// then the configured ones (replacing any of the profile's)
+    pub fn request_headers(&self) -> Vec<(&str, &str)> {
+        let configured = self
+            .headers
+            .iter()
+            .filter(|(name, _)| !name.eq_ignore_ascii_case("user-agent"))
+            .map(|(name, value)| (name.as_str(), value.as_str()));
+        let from_profile = self
+            .profile
+            .map_or(&[][..], |profile| profile.headers())
+            .iter()
+            .copied()
+            .filter(|(name, _)| !self.headers.keys().any(|configured| configured.eq_ignore_ascii_case(name)));
+        from_profile.chain(configured).collect()
+    }
+}
+
+/// HTTP metadata of a response, kept in the check history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponseMeta {
+    /// HTTP status code
+    pub status: u16,
+    /// Time from sending the request until the body was read (or the
+    /// headers arrived, for error responses)
+    pub response_time_ms: u64,
+    /// URL after following redirects
+    pub final_url: String,
+    /// Body size in bytes (as received, or the Content-Length header)
+    #[serde(default)]
+    pub content_length: Option<u64>,
+    /// Selected response headers
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+    /// Where the page has moved, when it was only reached through permanent
+    /// redirects (301/308)
+    #[serde(default)]
+    pub moved_to: Option<String>,
+}
+
+impl ResponseMeta {
+    fn from_response(response: &Response, started: Instant) -> Self {
+        let headers = RECORDED_HEADERS
+            .iter()
+            .filter_map(|name| {
+                let value = response.headers().get(*name)?.to_str().ok()?;
+                Some((name.to_string(), value.to_string()))
+            })
+            .collect();
+
+        Self {
+            status: response.status().as_u16(),
+            response_time_ms: started.elapsed().as_millis() as u64,
+            final_url: response.url(),
+            content_length: response.content_length(),
+            headers,
+            moved_to: response.moved_to(),
+        }
+    }
+}
+
+/// Non-success HTTP status, carrying the response metadata for the history
+#[derive(Debug, thiserror::Error)]
+#[error("HTTP error {}: {}", .meta.status, .url)]
+pub struct HttpStatusError {
+    pub url: String,
+    pub meta: ResponseMeta,
+}
+
+/// The site answered with a block page (a bot challenge or CAPTCHA) instead
+/// of the page, carrying the response metadata for the history
+#[derive(Debug, thiserror::Error)]
+#[error("Blocked ({}): {}", .reason, .url)]
+pub struct BlockedError {
+    pub url: String,
+    /// What blocked the request, like "Cloudflare challenge"
+    pub reason: String,
+    pub meta: ResponseMeta,
+}
+
+/// If a failed fetch means the page is gone for good rather than a
+/// temporary problem, a short description of why: the domain doesn't exist,
+/// or the server says the page isn't there (404 Not Found, 410 Gone)
+pub fn permanent_failure(error: &anyhow::Error) -> Option<String> {
+    if let Some(err) = error.downcast_ref::<HttpStatusError>() {
+        return matches!(err.meta.status, 404 | 410).then(|| format!("HTTP {}", err.meta.status));
+    }
+
+    // Resolver errors only carry a message. "Temporary failure in name
+    // resolution" (e.g. no network) is deliberately not matched.
+    error
+        .chain()
+        .any(|cause| {
+            let message = cause.to_string();
+            message.contains("Name or service not known") // glibc
+                || message.contains("nodename nor servname provided") // macOS
+        })
+        .then(|| String::from("domain not found"))
+}
+
+/// Cache validators of a response, used to tell whether a page changed
+/// without downloading it
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Validators {
+    #[serde(default)]
+    pub etag: Option<String>,
+    #[serde(default)]
+    pub last_modified: Option<String>,
+    #[serde(default)]
+    pub content_length: Option<u64>,
+}
+
+impl Validators {
+    fn from_meta(meta: &ResponseMeta) -> Self {
+        Self {
+            etag: meta.headers.get("etag").cloned(),
+            last_modified: meta.headers.get("last-modified").cloned(),
+            content_length: meta.content_length,
+        }
+    }
+
+    /// The server sent nothing to compare (every check must be a full one)
+    pub fn is_empty(&self) -> bool {
+        self.etag.is_none() && self.last_modified.is_none() && self.content_length.is_none()
+    }
+}
+
+/// A fetched page with its response metadata
+pub struct Fetched {
+    pub page: Page,
+    pub meta: ResponseMeta,
+}
+
+/// A fetched page
+pub enum Page {
+    /// The full page text (within the size cap)
+    Text(String),
+    /// A page over the size cap: only its hash and which keywords appear
+    Oversized(PageDigest),
+}
+
+/// Summary of a page too large to keep in memory
+pub struct PageDigest {
+    /// SHA-256 of the raw body, hex encoded
+    pub hash: String,
+    /// Body size in bytes
+    pub size: u64,
+    /// Keywords found anywhere in the body
+    pub keywords_found: Vec<String>,
+}
+
+/// A response from either transport
+enum Response {
+    Http {
+        response: reqwest::Response,
+        /// Redirected only through permanent redirects
+        moved: bool,
+    },
+    Unix {
+        response: hyper::Response<hyper::Body>,
+        url: String,
+    },
+    /// A page fetched whole by another program: the DOM of a page rendered
+    /// in a headless browser, or a page fetched with curl-impersonate
+    Buffered {
+        status: reqwest::StatusCode,
+        /// Taken by the first `chunk`
+        body: Option<Bytes>,
+        size: u64,
+        url: String,
+        /// Browsers don't report the response headers
+        headers: HeaderMap,
+    },
+}
+
+impl Response {
+    fn status(&self) -> reqwest::StatusCode {
+        match self {
+            Response::Http { response, .. } => response.status(),
+            Response::Unix { response, .. } => response.status(),
+            Response::Buffered { status, .. } => *status,
+        }
+    }
+
+    fn headers(&self) -> &HeaderMap {
+        match self {
+            Response::Http { response, .. } => response.headers(),
+            Response::Unix { response, .. } => response.headers(),
+            Response::Buffered { headers, .. } => headers,
+        }
+    }
+
+    /// URL after following redirects
+    fn url(&self) -> String {
+        match self {
+            Response::Http { response, .. } => response.url().to_string(),
+            Response::Unix { url, .. } | Response::Buffered { url, .. } => url.clone(),
+        }
+    }
+
+    /// The final URL if the page permanently moved there
+    fn moved_to(&self) -> Option<String> {
+        match self {
+            Response::Http { moved: true, .. } => Some(self.url()),
+            _ => None,
+        }
+    }
+
+    fn content_length(&self) -> Option<u64> {
+        match self {
+            Response::Http { response, .. } => response.content_length(),
+            Response::Unix { response, .. } => response.body().size_hint().exact(),
+            Response::Buffered { size, .. } => Some(*size),
+        }
+    }
+
+    /// Next chunk of the body, or None at the end
+    async fn chunk(&mut self) -> Result<Option<Bytes>> {
+        let chunk = match self {
+            Response::Http { response, .. } => response.chunk().await?,
+            Response::Unix { response, .. } => response.body_mut().data().await.transpose()?,
+            Response::Buffered { body, .. } => body.take(),
+        };
+        Ok(chunk)
+    }
+
+    async fn text(self) -> Result<String> {
+        let headers = self.headers().clone();
+        let body = match self {
+            Response::Http { response, .. } => response.bytes().await?,
+            Response::Unix { response, .. } => hyper::body::to_bytes(response.into_body()).await?,
+            Response::Buffered { body, .. } => body.unwrap_or_default(),
+        };
+        Ok(decode(&body, &headers))
+    }
+}
+
+/// Send a GET request (or render the page) and check the status
+async fn get(url: &str, options: &RequestOptions) -> Result<(Response, Instant)> {
+    match options.render {
+        RenderMode::Http => send(url, options, reqwest::Method::GET).await,
+        RenderMode::Browser => render(url, options).await,
+        RenderMode::Impersonate => impersonate(url, options).await,
+    }
+}
+
+/// Send a HEAD request and return the response metadata and validators
+pub async fn head(url: &str, options: &RequestOptions) -> Result<(ResponseMeta, Validators)> {
+    let (response, started) = send(url, options, reqwest::Method::HEAD).await?;
+    let mut meta = ResponseMeta::from_response(&response, started);
+
+    // A HEAD response has no body, so take the size from the header itself
+    meta.content_length = response
+        .headers()
+        .get(reqwest::header::CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse().ok());
+
+    let validators = Validators::from_meta(&meta);
+    Ok((meta, validators))
+}
+
+/// Load the page in a headless browser
+async fn render(url: &str, options: &RequestOptions) -> Result<(Response, Instant)> {
+    if unix_socket::is_unix_url(url) {
+        anyhow::bail!("Unix socket URLs can't be rendered in a browser: {}", url);
+    }
+    if let Some((domain, spacing)) = &options.rate_limit {
+        domains::wait_turn(domain, *spacing).await;
+    }
+
+    let started = Instant::now();
+    let dom = browser::render(url, options.user_agent(), TIMEOUT).await?;
+    let response = Response::Buffered {
+        status: reqwest::StatusCode::OK,
+        size: dom.len() as u64,
+        body: Some(Bytes::from(dom)),
+        url: url.to_string(),
+        headers: HeaderMap::new(),
+    };
+    Ok((response, started))
+}
+
+/// Fetch the page with curl-impersonate and check the status
+async fn impersonate(url: &str, options: &RequestOptions) -> Result<(Response, Instant)> {
+    if unix_socket::is_unix_url(url) {
+        anyhow::bail!("Unix socket URLs can't be fetched with curl-impersonate: {}", url);
+    }
+    if let Some((domain, spacing)) = &options.rate_limit {
+        domains::wait_turn(domain, *spacing).await;
+    }
+
+    let started = Instant::now();
+    let page = impersonate::fetch(url, options, TIMEOUT).await?;
+    let status = reqwest::StatusCode::from_u16(page.status)
+        .with_context(|| format!("Invalid HTTP status {} from {}", page.status, url))?;
+    let response = Response::Buffered {
+        status,
+        size: page.body.len() as u64,
+        body: Some(Bytes::from(page.body)),
+        url: page.url,
+        headers: page.headers,
+    };
+    check_status(response, url, started).await
+}
+
+/// Send a request and check the status
+async fn send(url: &str, options: &RequestOptions, method: reqwest::Method) -> Result<(Response, Instant)> {
+    // Rate-limited sites: wait for this domain's next slot
+    if let Some((domain, spacing)) = &options.rate_limit {
+        domains::wait_turn(domain, *spacing).await;
+    }
+
+    let started = Instant::now();
+    let response = if unix_socket::is_unix_url(url) {
+        Response::Unix {
+            response: unix_socket::request(url, method, options.user_agent(), TIMEOUT).await?,
+            url: url.to_string(),
+        }
+    } else {
+        let (response, moved) = send_http(url, options, method).await?;
+        Response::Http { response, moved }
+    };
+
+    check_status(response, url, started).await
+}
+
+/// Fail with the response metadata unless the response was successful.
+/// Error responses that are block pages fail with a `BlockedError`.
+async fn check_status(mut response: Response, url: &str, started: Instant) -> Result<(Response, Instant)> {
+    if response.status().is_success() {
+        return Ok((response, started));
+    }
+
+    let meta = ResponseMeta::from_response(&response, started);
+    let mut body = Vec::new();
+    if matches!(meta.status, 403 | 429 | 503) {
+        while body.len() < MAX_ERROR_BODY_BYTES {
+            match response.chunk().await {
+                Ok(Some(chunk)) => body.extend_from_slice(&chunk),
+                _ => break,
+            }
+        }
+    }
+    let body = String::from_utf8_lossy(&body);
+    if let Some(reason) = blocked::detect(meta.status, response.headers(), &body) {
+        return Err(BlockedError {
+            url: url.to_string(),
+            reason: reason.to_string(),
+            meta,
+        }
+        .into());
+    }
+
+    Err(HttpStatusError {
+        url: url.to_string(),
+        meta,
+    }
+    .into())
+}
+
+/// HTTP client with the request timeout, User-Agent and TLS settings of
+/// `options`, following redirects as `redirect` says
+pub fn http_client(options: &RequestOptions, redirect: reqwest::redirect::Policy) -> Result<reqwest::Client> {
+    let mut builder = reqwest::Client::builder()
+        .timeout(TIMEOUT)
+        .user_agent(options.user_agent())
+        .redirect(redirect);
+
+    if let Some(cert) = options.tls.load_ca_cert()? {
+        builder = builder.add_root_certificate(cert);
+    }
+    if options.tls.insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+
+    builder.build().context("Failed to create HTTP client")
+}
+
+/// Send a request over HTTP(S). Also returns whether the page was reached
+/// through permanent redirects only (it moved).
+async fn send_http(
+    url: &str,
+    options: &RequestOptions,
+    method: reqwest::Method,
+) -> Result<(reqwest::Response, bool)> {
+    // Follow redirects as usual, noting any that aren't permanent
+    let temporary_redirect = Arc::new(AtomicBool::new(false));
+    let policy = {
+        let temporary_redirect = Arc::clone(&temporary_redirect);
+        reqwest::redirect::Policy::custom(move |attempt| {
+            let status = attempt.status();
+            if status != reqwest::StatusCode::MOVED_PERMANENTLY
+                && status != reqwest::StatusCode::PERMANENT_REDIRECT
+            {
+                temporary_redirect.store(true, Ordering::Relaxed);
+            }
+            if attempt.previous().len() > MAX_REDIRECTS {
+                attempt.error("too many redirects")
+            } else {
+                attempt.follow()
+            }
+        })
+    };
+
+    let client = http_client(options, policy)?;
+
+    // Fetch the URL (the User-Agent was set on the client)
+    let mut request = client.request(method, url);
+    for (name, value) in options.request_headers() {
+        request = request.header(name, value);
+    }
+    let response = request
         .send()
         .await
         .with_context(|| format!("Failed to fetch URL: {}", url))?;
 
-    // Check if response was successful
-    if !response.status().is_success() {
-        anyhow::bail!("HTTP error {}: {}", response.status(), url);
+    let redirected = reqwest::Url::parse(url).is_ok_and(|requested| requested != *response.url());
+    let moved = redirected && !temporary_redirect.load(Ordering::Relaxed);
+    Ok((response, moved))
+}
+
+/// Fetch a page, switching to hash-only mode once it grows past `max_bytes`
+pub async fn fetch_page(
+    url: &str,
+    options: &RequestOptions,
+    max_bytes: Option<u64>,
+    keywords: &[Keyword],
+) -> Result<Fetched> {
+    let (mut response, started) = get(url, options).await?;
+    let mut meta = ResponseMeta::from_response(&response, started);
+    let headers = response.headers().clone();
+
+    let Some(max_bytes) = max_bytes else {
+        let content = response.text().await.context("Failed to read response body")?;
+        meta.response_time_ms = started.elapsed().as_millis() as u64;
+        meta.content_length = Some(content.len() as u64);
+        return unless_blocked(url, &headers, Page::Text(content), meta);
+    };
+
+    let mut body: Vec<u8> = Vec::new();
+    let mut streaming: Option<(Sha256, StreamSearch)> = None;
+    let mut size: u64 = 0;
+
+    // Skip buffering entirely when the server says the page is too big
+    if response.content_length().is_some_and(|len| len > max_bytes) {
+        streaming = Some((Sha256::new(), StreamSearch::new(keywords)));
+    }
+
+    while let Some(chunk) = response.chunk().await.context("Failed to read response body")? {
+        size += chunk.len() as u64;
+
+        if streaming.is_none() && size > max_bytes {
+            // Over the cap: hand what we have so far to the hasher and drop it
+            let mut hasher = Sha256::new();
+            let mut search = StreamSearch::new(keywords);
+            hasher.update(&body);
+            search.feed(&body);
+            body = Vec::new();
+            streaming = Some((hasher, search));
+        }
+
+        match &mut streaming {
+            Some((hasher, search)) => {
+                hasher.update(&chunk);
+                search.feed(&chunk);
+            }
+            None => body.extend_from_slice(&chunk),
+        }
+    }
+
+    meta.response_time_ms = started.elapsed().as_millis() as u64;
+    meta.content_length = Some(size);
+
+    let page = match streaming {
+        Some((hasher, search)) => Page::Oversized(PageDigest {
+            hash: format!("{:x}", hasher.finalize()),
+            size,
+            keywords_found: search.found_keywords(),
+        }),
+        None => Page::Text(decode(&body, &headers)),
+    };
+
+    unless_blocked(url, &headers, page, meta)
+}
+
+/// A body as text, in the charset its Content-Type names (UTF-8 without
+/// one, or with one that isn't known; a byte order mark wins over both)
+fn decode(body: &[u8], headers: &HeaderMap) -> String {
+    let encoding = headers
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|content_type| {
+            content_type.split(';').skip(1).find_map(|param| {
+                let (name, value) = param.split_once('=')?;
+                name.trim().eq_ignore_ascii_case("charset").then(|| value.trim().trim_matches('"'))
+            })
+        })
+        .and_then(|label| encoding_rs::Encoding::for_label(label.as_bytes()))
+        .unwrap_or(encoding_rs::UTF_8);
+    let (text, _, _) = encoding.decode(body);
+    text.into_owned()
+}
+
+/// The fetched page, or a `BlockedError` if it's a block page (pages over
+/// the size cap never are)
+fn unless_blocked(url: &str, headers: &HeaderMap, page: Page, meta: ResponseMeta) -> Result<Fetched> {
+    if let Page::Text(content) = &page {
+        if let Some(reason) = blocked::detect(meta.status, headers, content) {
+            return Err(BlockedError {
+                url: url.to_string(),
+                reason: reason.to_string(),
+                meta,
+            }
+            .into());
+        }
     }
+    Ok(Fetched { page, meta })
+}
+
+/// Request a URL and discard the response (uptime pings)
+pub async fn ping(url: &str) -> Result<()> {
+    let (_, _) = get(url, &RequestOptions::default()).await?;
+    Ok(())
+}
+
+/// Fetch content from a URL
+pub async fn fetch_url(url: &str, options: &RequestOptions) -> Result<String> {
+    let (response, _) = get(url, options).await?;
 
     // Get the response text
     let content = response
@@ -34,3 +671,34 @@ pub async fn fetch_url(url: &str) -> Result<String> {
 
     Ok(content)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn content_type(value: &'static str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(reqwest::header::CONTENT_TYPE, reqwest::header::HeaderValue::from_static(value));
+        headers
+    }
+
+    #[test]
+    fn monitored_users_cant_name_ca_files() {
+        let tls = TlsOptions {
+            ca_cert: Some(PathBuf::from("/etc/shadow")),
+            insecure: false,
+        };
+        let _user = crate::system::monitor_as("alice");
+        let error = tls.load_ca_cert().unwrap_err();
+        assert!(error.to_string().contains("system daemon (alice)"), "{}", error);
+    }
+
+    #[test]
+    fn bodies_are_decoded_in_their_charset() {
+        assert_eq!(decode(b"Gr\xf6\xdfe 42", &content_type("text/html; charset=ISO-8859-1")), "Größe 42");
+        assert_eq!(decode(b"\x8d\xdd\x8c\xc9", &content_type("text/html;Charset=\"Shift_JIS\"")), "在庫");
+        assert_eq!(decode("Größe".as_bytes(), &content_type("text/html")), "Größe");
+        assert_eq!(decode("Größe".as_bytes(), &content_type("text/html; charset=bogus")), "Größe");
+        assert_eq!(decode("Größe".as_bytes(), &HeaderMap::new()), "Größe");
+    }
+}
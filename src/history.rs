@@ -0,0 +1,321 @@
+//! Check history store
+//!
+//! Every check appends one record (outcome plus HTTP metadata) to a JSONL
+//! file per watcher under ~/.local/share/web-watcher-alert/history/, so the
+//! detail screen can show why an alert did or didn't fire.
+//!
+//! The `"history"` settings say how much is kept (see `HistoryRetention`):
+//! the newest checks in full, then one a day, then one a week, within a size
+//! cap. Files are pruned as they grow, when the daemon starts, and by the
+//! `prune` command.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Datelike, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+
+use crate::{
+    config::{Config, EncryptionSettings, HistoryRetention},
+    crypto::{self, Role},
+    fetcher::ResponseMeta,
+};
+
+/// Records kept per watcher unless the retention settings say otherwise
+pub const MAX_RECORDS: usize = 1000;
+
+/// Only consider compacting files larger than this (cheap size check
+/// before reading the file)
+const COMPACT_MIN_BYTES: u64 = 256 * 1024;
+
+/// One check of one watcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CheckRecord {
+    pub checked_at: DateTime<Utc>,
+    pub outcome: CheckOutcome,
+    /// HTTP metadata (missing when the request itself failed)
+    #[serde(default)]
+    pub http: Option<ResponseMeta>,
+    /// The value read from the page (watchers tracking a value)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub value: Option<f64>,
+    /// The run of the watcher's hook the check triggered
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hook: Option<crate::hook::HookRun>,
+}
+
+/// What a check concluded
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "result", rename_all = "snake_case")]
+pub enum CheckOutcome {
+    /// Content is the same as last time
+    Unchanged,
+    /// Content changed but no (new) keyword matched
+    Changed,
+    /// Keywords matched and a notification was sent
+    Matched { keywords: Vec<String> },
+    /// The check failed
+    Error { message: String },
+    /// The site answered with a block page (see `blocked`)
+    Blocked { reason: String },
+}
+
+impl CheckOutcome {
+    /// Short label for lists
+    pub fn label(&self) -> String {
+        match self {
+            CheckOutcome::Unchanged => String::from("unchanged"),
+            CheckOutcome::Changed => String::from("changed"),
+            CheckOutcome::Matched { keywords } => format!("matched: {}", keywords.join(", ")),
+            CheckOutcome::Error { message } => format!("error: {}", message),
+            CheckOutcome::Blocked { reason } => format!("blocked: {}", reason),
+        }
+    }
+}
+
+/// What pruning a watcher's history did (or would do)
+#[derive(Debug, Clone, Default)]
+pub struct Pruned {
+    pub kept: usize,
+    pub removed: usize,
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+/// Append a record to a watcher's history (pruning it if it grew too long)
+pub fn append(
+    watcher_id: &str,
+    record: &CheckRecord,
+    retention: &HistoryRetention,
+    encryption: &EncryptionSettings,
+) -> Result<()> {
+    let path = history_path(watcher_id)?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create history directory: {}", parent.display()))?;
+    }
+
+    let line = serde_json::to_string(record).context("Failed to serialize check record")?;
+    let line = crypto::seal_line(&line, Role::History(watcher_id), encryption)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open history file: {}", path.display()))?;
+    writeln!(file, "{}", line)
+        .with_context(|| format!("Failed to write history file: {}", path.display()))?;
+
+    compact_if_needed(watcher_id, retention)
+}
+
+/// Load the most recent records (oldest first), skipping unreadable lines
+pub fn load(watcher_id: &str, limit: usize) -> Result<Vec<CheckRecord>> {
+    let path = history_path(watcher_id)?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+
+    let records: Vec<CheckRecord> = content.lines().filter_map(|line| parse(watcher_id, line)).collect();
+
+    let skip = records.len().saturating_sub(limit);
+    Ok(records.into_iter().skip(skip).collect())
+}
+
+/// Number of checks that matched keywords since `since`
+pub fn matches_since(watcher_id: &str, since: DateTime<Utc>) -> Result<usize> {
+    Ok(match_times(watcher_id, since)?.len())
+}
+
+/// When the checks that matched keywords since `since` ran, oldest first
+pub fn match_times(watcher_id: &str, since: DateTime<Utc>) -> Result<Vec<DateTime<Utc>>> {
+    let records = load(watcher_id, MAX_RECORDS)?;
+    Ok(records
+        .iter()
+        .filter(|r| r.checked_at >= since && matches!(r.outcome, CheckOutcome::Matched { .. }))
+        .map(|r| r.checked_at)
+        .collect())
+}
+
+/// Delete a watcher's history (when the watcher is removed)
+pub fn remove(watcher_id: &str) -> Result<()> {
+    let path = history_path(watcher_id)?;
+    if path.exists() {
+        fs::remove_file(&path)
+            .with_context(|| format!("Failed to remove history file: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Delete a watcher's history, returning it so `restore` can undo that
+pub fn clear(watcher_id: &str) -> Result<String> {
+    let path = history_path(watcher_id)?;
+    if !path.exists() {
+        return Ok(String::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+    remove(watcher_id)?;
+    Ok(content)
+}
+
+/// Put back a history taken by `clear`, before the checks recorded since
+pub fn restore(watcher_id: &str, content: &str, retention: &HistoryRetention) -> Result<()> {
+    let path = history_path(watcher_id)?;
+    let newer = if path.exists() {
+        fs::read_to_string(&path).with_context(|| format!("Failed to read history file: {}", path.display()))?
+    } else {
+        String::new()
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create history directory: {}", parent.display()))?;
+    }
+    fs::write(&path, format!("{}{}", content, newer))
+        .with_context(|| format!("Failed to write history file: {}", path.display()))?;
+    compact_if_needed(watcher_id, retention)
+}
+
+/// Drop the records the retention settings don't keep (with `dry_run`,
+/// only count them)
+pub fn prune(watcher_id: &str, retention: &HistoryRetention, dry_run: bool) -> Result<Pruned> {
+    let path = history_path(watcher_id)?;
+    if !path.exists() {
+        return Ok(Pruned::default());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+
+    let kept = retained(watcher_id, &content, retention, Utc::now());
+    let mut pruned = Pruned {
+        kept: kept.len(),
+        removed: content.lines().count() - kept.len(),
+        bytes_before: content.len() as u64,
+        bytes_after: kept.iter().map(|line| line.len() as u64 + 1).sum(),
+    };
+    if pruned.removed == 0 {
+        pruned.bytes_after = pruned.bytes_before;
+    } else if !dry_run {
+        let mut rewritten = kept.join("\n");
+        rewritten.push('\n');
+        fs::write(&path, rewritten).with_context(|| format!("Failed to write history file: {}", path.display()))?;
+    }
+    Ok(pruned)
+}
+
+/// Prune the file once it grows well past what the retention keeps
+fn compact_if_needed(watcher_id: &str, retention: &HistoryRetention) -> Result<()> {
+    let path = history_path(watcher_id)?;
+    let size = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let over_cap = retention.max_bytes.is_some_and(|max| size > max);
+    if size < COMPACT_MIN_BYTES && !over_cap {
+        return Ok(());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read history file: {}", path.display()))?;
+
+    // Allow some slack so we don't rewrite the file on every check
+    let lines = content.lines().count();
+    let kept = retained(watcher_id, &content, retention, Utc::now());
+    if !over_cap && lines - kept.len() <= retention.keep_last / 5 {
+        return Ok(());
+    }
+
+    let mut rewritten = kept.join("\n");
+    rewritten.push('\n');
+    fs::write(&path, rewritten)
+        .with_context(|| format!("Failed to write history file: {}", path.display()))
+}
+
+/// The lines of a history file the retention settings keep, oldest first:
+/// the newest `keep_last` records; before them the last record of each day
+/// (and every match) for `daily_days` days, then the last record of each
+/// week with `weekly`. The oldest kept records are dropped until the rest
+/// fit in `max_bytes`. Lines that don't parse are dropped.
+fn retained<'a>(
+    watcher_id: &str,
+    content: &'a str,
+    retention: &HistoryRetention,
+    now: DateTime<Utc>,
+) -> Vec<&'a str> {
+    let records: Vec<(&str, CheckRecord)> = content
+        .lines()
+        .filter_map(|line| Some((line, parse(watcher_id, line)?)))
+        .collect();
+    let older = records.len().saturating_sub(retention.keep_last);
+    let daily_since = now - Duration::days(i64::from(retention.daily_days));
+
+    // Newest first, so the first record seen of a day or week is its last
+    let mut days = HashSet::new();
+    let mut weeks = HashSet::new();
+    let mut keep = vec![true; records.len()];
+    for i in (0..older).rev() {
+        let record = &records[i].1;
+        let at = record.checked_at;
+        keep[i] = if at >= daily_since {
+            days.insert(at.date_naive()) || matches!(record.outcome, CheckOutcome::Matched { .. })
+        } else {
+            retention.weekly && weeks.insert(at.iso_week())
+        };
+    }
+
+    let mut kept: Vec<&str> = records
+        .iter()
+        .zip(keep)
+        .filter_map(|((line, _), keep)| keep.then_some(*line))
+        .collect();
+    if let Some(max) = retention.max_bytes {
+        let mut size: u64 = kept.iter().map(|line| line.len() as u64 + 1).sum();
+        let mut drop = 0;
+        while size > max && drop < kept.len() {
+            size -= kept[drop].len() as u64 + 1;
+            drop += 1;
+        }
+        kept.drain(..drop);
+    }
+    kept
+}
+
+/// A line of a history file, decrypted if it was written encrypted (None
+/// if it doesn't parse, or can't be decrypted)
+pub fn parse(watcher_id: &str, line: &str) -> Option<CheckRecord> {
+    serde_json::from_str(&crypto::open_line(line, Role::History(watcher_id)).ok()?).ok()
+}
+
+/// Rewrite a watcher's history so every line is encrypted, or none is, as
+/// encryption is now set. Returns whether anything changed.
+pub fn reseal(watcher_id: &str, encryption: &EncryptionSettings) -> Result<bool> {
+    let path = history_path(watcher_id)?;
+    if !path.exists() {
+        return Ok(false);
+    }
+    let content =
+        fs::read_to_string(&path).with_context(|| format!("Failed to read history file: {}", path.display()))?;
+    let mut rewritten = String::with_capacity(content.len());
+    let mut changed = false;
+    for line in content.lines() {
+        if crypto::is_encrypted_line(line) == encryption.enabled {
+            rewritten.push_str(line);
+        } else {
+            let role = Role::History(watcher_id);
+            rewritten.push_str(&crypto::seal_line(&crypto::open_line(line, role)?, role, encryption)?);
+            changed = true;
+        }
+        rewritten.push('\n');
+    }
+    if !changed {
+        return Ok(false);
+    }
+    fs::write(&path, rewritten).with_context(|| format!("Failed to write history file: {}", path.display()))?;
+    Ok(true)
+}
+
+/// Where a watcher's history is kept
+pub fn history_path(watcher_id: &str) -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join("history").join(format!("{}.jsonl", watcher_id)))
+}
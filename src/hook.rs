@@ -0,0 +1,274 @@
+//! External command hooks
+//!
+//! A watcher can run a command of its own when keywords match (`hook` in
+//! its config entry, or `add --hook`), to restart a service, open a ticket
+//! or anything a notification can't do. The command gets the alert as
+//! `WATCHER_ID`, `WATCHER_URL` and `MATCHED_KEYWORDS` (and as JSON on its
+//! standard input).
+//!
+//! A hook runs boxed in, so a misbehaving one can't hang the monitor or
+//! fill the disk:
+//! - it's killed (with whatever it started) after `timeout` seconds
+//! - it starts in `working_dir` (by default the `hooks` folder of the data
+//!   directory)
+//! - its environment is only `PATH`, the alert and the variables listed in
+//!   `env`
+//! - of its output, only the first `max_output` bytes (of stdout, and of
+//!   stderr) are kept
+//!
+//! Each run (exit code, duration and output) is kept in the history with
+//! the check that triggered it.
+//!
+//! Monitors the system daemon starts for its users don't run hooks: they
+//! would run as the service's account (see `system`).
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+
+use crate::config::Config;
+use crate::system;
+use crate::watcher::Watcher;
+
+/// Longest a hook may be allowed to run (in seconds)
+pub const MAX_TIMEOUT: u64 = 600;
+
+/// Most output a hook may be allowed to keep (in bytes, per stream)
+pub const MAX_OUTPUT: usize = 1024 * 1024;
+
+/// How long the output of a finished hook is still read (a process it
+/// left behind may hold on to it)
+const OUTPUT_GRACE: Duration = Duration::from_secs(2);
+
+/// A command run when the watcher's keywords match
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookSettings {
+    /// Shell command (`sh -c`, or `cmd /C` on Windows)
+    pub command: String,
+
+    /// Seconds before the command is killed
+    #[serde(default = "default_timeout")]
+    pub timeout: u64,
+
+    /// Directory the command runs in (default: the data directory's `hooks`
+    /// folder)
+    #[serde(default)]
+    pub working_dir: Option<PathBuf>,
+
+    /// Variables of the monitor's environment the command gets (besides
+    /// `PATH`)
+    #[serde(default)]
+    pub env: Vec<String>,
+
+    /// Bytes kept of the command's stdout, and of its stderr
+    #[serde(default = "default_max_output")]
+    pub max_output: usize,
+}
+
+impl HookSettings {
+    pub fn new(command: String) -> Self {
+        Self {
+            command,
+            timeout: default_timeout(),
+            working_dir: None,
+            env: Vec::new(),
+            max_output: default_max_output(),
+        }
+    }
+}
+
+fn default_timeout() -> u64 {
+    30
+}
+
+fn default_max_output() -> usize {
+    4096
+}
+
+/// What a run of a hook did, as kept in the history
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookRun {
+    /// None when it was killed or didn't start
+    #[serde(default)]
+    pub exit_code: Option<i32>,
+    #[serde(default)]
+    pub timed_out: bool,
+    /// Why it couldn't be started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    #[serde(default)]
+    pub duration_ms: u64,
+    #[serde(default)]
+    pub stdout: String,
+    #[serde(default)]
+    pub stderr: String,
+    /// Output past `max_output` was dropped
+    #[serde(default)]
+    pub truncated: bool,
+}
+
+impl HookRun {
+    pub fn succeeded(&self) -> bool {
+        self.exit_code == Some(0)
+    }
+
+    /// How it ended, for the log and the history
+    pub fn summary(&self) -> String {
+        match (&self.error, self.timed_out, self.exit_code) {
+            (Some(error), _, _) => format!("failed to start: {}", error),
+            (None, true, _) => format!("killed after {}ms (timed out)", self.duration_ms),
+            (None, false, Some(code)) => format!("exited with {} after {}ms", code, self.duration_ms),
+            (None, false, None) => format!("killed after {}ms", self.duration_ms),
+        }
+    }
+}
+
+/// Run the watcher's hook for an alert about `keywords`
+pub async fn run(settings: &HookSettings, watcher: &Watcher, keywords: &[String]) -> HookRun {
+    let started = Instant::now();
+    let mut run = match execute(settings, watcher, keywords).await {
+        Ok(run) => run,
+        Err(e) => HookRun {
+            error: Some(format!("{:#}", e)),
+            ..HookRun::default()
+        },
+    };
+    run.duration_ms = started.elapsed().as_millis() as u64;
+    run
+}
+
+async fn execute(settings: &HookSettings, watcher: &Watcher, keywords: &[String]) -> Result<HookRun> {
+    if let Some(user) = system::monitored_user() {
+        anyhow::bail!("Hooks are off for the users of the system daemon ({}): they would run as the service's account", user);
+    }
+    let working_dir = match &settings.working_dir {
+        Some(dir) => dir.clone(),
+        None => {
+            let dir = Config::data_dir()?.join("hooks");
+            std::fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+            dir
+        }
+    };
+    if !working_dir.is_dir() {
+        anyhow::bail!("The working directory {} doesn't exist", working_dir.display());
+    }
+
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = tokio::process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    command.arg(&settings.command).current_dir(&working_dir).env_clear();
+    let inherited = ["PATH", "SYSTEMROOT"].into_iter().chain(settings.env.iter().map(String::as_str));
+    for name in inherited {
+        if let Some(value) = std::env::var_os(name) {
+            command.env(name, value);
+        }
+    }
+    command
+        .env("WATCHER_ID", &watcher.id)
+        .env("WATCHER_URL", &watcher.url)
+        .env("MATCHED_KEYWORDS", keywords.join(", "))
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true);
+    // Its own process group, so what it starts is killed with it
+    #[cfg(unix)]
+    command.process_group(0);
+
+    let mut child = command.spawn().context("Failed to run the command")?;
+    if let Some(mut stdin) = child.stdin.take() {
+        let alert = json!({ "watcher_id": watcher.id, "url": watcher.url, "keywords": keywords });
+        // A hook that doesn't read its input is fine
+        let _ = stdin.write_all(alert.to_string().as_bytes()).await;
+    }
+    let max_output = settings.max_output.min(MAX_OUTPUT);
+    let stdout = child.stdout.take().map(|pipe| tokio::spawn(read_capped(pipe, max_output)));
+    let stderr = child.stderr.take().map(|pipe| tokio::spawn(read_capped(pipe, max_output)));
+
+    let timeout = Duration::from_secs(settings.timeout.clamp(1, MAX_TIMEOUT));
+    let (exit_code, timed_out) = match tokio::time::timeout(timeout, child.wait()).await {
+        Ok(status) => (status.context("Failed to wait for the command")?.code(), false),
+        Err(_) => {
+            kill(&mut child).await;
+            (None, true)
+        }
+    };
+
+    let mut run = HookRun {
+        exit_code,
+        timed_out,
+        ..HookRun::default()
+    };
+    for (reader, output) in [(stdout, &mut run.stdout), (stderr, &mut run.stderr)] {
+        let Some(mut reader) = reader else {
+            continue;
+        };
+        match tokio::time::timeout(OUTPUT_GRACE, &mut reader).await {
+            Ok(Ok((bytes, truncated))) => {
+                *output = String::from_utf8_lossy(&bytes).into_owned();
+                run.truncated |= truncated;
+            }
+            _ => reader.abort(),
+        }
+    }
+    Ok(run)
+}
+
+/// Kill the command and the processes it started
+async fn kill(child: &mut tokio::process::Child) {
+    #[cfg(unix)]
+    if let Some(pid) = child.id() {
+        let _ = tokio::process::Command::new("kill")
+            .args(["-KILL", "--", &format!("-{}", pid)])
+            .status()
+            .await;
+    }
+    let _ = child.kill().await;
+}
+
+/// Up to `max` bytes of a pipe (the rest is read and dropped, so the
+/// command doesn't block writing it), and whether there was more
+async fn read_capped(mut pipe: impl AsyncRead + Unpin, max: usize) -> (Vec<u8>, bool) {
+    let mut kept = Vec::new();
+    let mut truncated = false;
+    let mut buffer = [0u8; 8192];
+    while let Ok(read) = pipe.read(&mut buffer).await {
+        if read == 0 {
+            break;
+        }
+        let room = max.saturating_sub(kept.len());
+        kept.extend_from_slice(&buffer[..read.min(room)]);
+        truncated |= read > room;
+    }
+    (kept, truncated)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn hooks_are_refused_for_users_of_the_system_daemon() {
+        let marker = std::env::temp_dir().join(format!("web-watcher-alert-hook-{}", std::process::id()));
+        let settings = HookSettings::new(format!("touch '{}'", marker.display()));
+        let watcher = Watcher::new("https://example.com/".into(), Vec::new(), Duration::from_secs(600));
+
+        let _user = system::monitor_as("alice");
+        let run = run(&settings, &watcher, &[String::from("sale")]).await;
+
+        assert!(!run.succeeded());
+        let error = run.error.unwrap_or_default();
+        assert!(error.contains("system daemon (alice)"), "{}", error);
+        assert!(!marker.exists());
+    }
+}
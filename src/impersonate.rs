@@ -0,0 +1,246 @@
+//! Browser-like requests
+//!
+//! Some sites serve different content (or a block page) to clients that
+//! don't look like a browser. A request profile makes a watcher's requests
+//! carry the coherent set of headers a real browser sends: its User-Agent,
+//! Accept, Accept-Language and the `Sec-Fetch-*`/client hint headers that
+//! go with them. Headers set on the watcher or its domain still win.
+//!
+//! Headers alone don't hide the HTTP client's TLS handshake, which some bot
+//! protections fingerprint. For those sites the "impersonate" render mode
+//! fetches pages with curl-impersonate (`curl_chrome*`, `curl_ff*` on the
+//! PATH), whose handshake and header order match the browser's exactly.
+
+use anyhow::{Context, Result};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::fetcher::{RequestOptions, MAX_REDIRECTS};
+
+/// A browser whose requests a watcher's requests imitate
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RequestProfile {
+    /// Chrome on macOS
+    ChromeLike,
+    /// Firefox on macOS
+    FirefoxLike,
+}
+
+impl RequestProfile {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "chrome-like" | "chrome" => Ok(RequestProfile::ChromeLike),
+            "firefox-like" | "firefox" => Ok(RequestProfile::FirefoxLike),
+            other => anyhow::bail!("Unknown request profile: {} (use chrome-like or firefox-like)", other),
+        }
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            RequestProfile::ChromeLike => "chrome-like",
+            RequestProfile::FirefoxLike => "firefox-like",
+        }
+    }
+
+    pub fn user_agent(&self) -> &'static str {
+        match self {
+            RequestProfile::ChromeLike => {
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10_15_7) AppleWebKit/537.36 (KHTML, like Gecko) Chrome/131.0.0.0 Safari/537.36"
+            }
+            RequestProfile::FirefoxLike => {
+                "Mozilla/5.0 (Macintosh; Intel Mac OS X 10.15; rv:133.0) Gecko/20100101 Firefox/133.0"
+            }
+        }
+    }
+
+    /// The headers the browser sends when navigating to a page, besides the
+    /// User-Agent. Accept-Encoding is left out: the HTTP client only reads
+    /// uncompressed bodies.
+    pub fn headers(&self) -> &'static [(&'static str, &'static str)] {
+        match self {
+            RequestProfile::ChromeLike => &[
+                ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,image/avif,image/webp,image/apng,*/*;q=0.8,application/signed-exchange;v=b3;q=0.7"),
+                ("Accept-Language", "en-US,en;q=0.9"),
+                ("Sec-CH-UA", "\"Google Chrome\";v=\"131\", \"Chromium\";v=\"131\", \"Not_A Brand\";v=\"24\""),
+                ("Sec-CH-UA-Mobile", "?0"),
+                ("Sec-CH-UA-Platform", "\"macOS\""),
+                ("Sec-Fetch-Dest", "document"),
+                ("Sec-Fetch-Mode", "navigate"),
+                ("Sec-Fetch-Site", "none"),
+                ("Sec-Fetch-User", "?1"),
+                ("Upgrade-Insecure-Requests", "1"),
+            ],
+            RequestProfile::FirefoxLike => &[
+                ("Accept", "text/html,application/xhtml+xml,application/xml;q=0.9,*/*;q=0.8"),
+                ("Accept-Language", "en-US,en;q=0.5"),
+                ("Sec-Fetch-Dest", "document"),
+                ("Sec-Fetch-Mode", "navigate"),
+                ("Sec-Fetch-Site", "none"),
+                ("Sec-Fetch-User", "?1"),
+                ("Upgrade-Insecure-Requests", "1"),
+            ],
+        }
+    }
+
+    /// Name prefixes of the curl-impersonate commands for this browser
+    /// (followed by the browser version, like `curl_chrome131`)
+    fn command_prefixes(&self) -> &'static [&'static str] {
+        match self {
+            RequestProfile::ChromeLike => &["curl_chrome"],
+            RequestProfile::FirefoxLike => &["curl_firefox", "curl_ff"],
+        }
+    }
+}
+
+impl std::fmt::Display for RequestProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// The curl-impersonate command for the newest version of the browser on
+/// the PATH
+pub fn command(profile: RequestProfile) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    let mut best: Option<(u32, PathBuf)> = None;
+    for dir in std::env::split_paths(&path) {
+        let Ok(entries) = std::fs::read_dir(&dir) else {
+            continue;
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let version = profile.command_prefixes().iter().find_map(|prefix| {
+                let version = name.strip_prefix(prefix)?;
+                version.replace('_', "").parse::<u32>().ok()
+            });
+            if let Some(version) = version {
+                if best.as_ref().is_none_or(|(newest, _)| version > *newest) {
+                    best = Some((version, entry.path()));
+                }
+            }
+        }
+    }
+    best.map(|(_, path)| path)
+}
+
+/// A page fetched with curl-impersonate
+pub struct Impersonated {
+    pub status: u16,
+    /// URL after following redirects
+    pub url: String,
+    pub headers: HeaderMap,
+    pub body: Vec<u8>,
+}
+
+/// Fetch a page with curl-impersonate, as the browser of the request's
+/// profile (Chrome if it has none). The command sends the browser's own
+/// headers; only headers set on the watcher or its domain are added.
+pub async fn fetch(url: &str, options: &RequestOptions, timeout: Duration) -> Result<Impersonated> {
+    let ca_cert = options.tls.ca_cert_path()?;
+    let profile = options.profile.unwrap_or(RequestProfile::ChromeLike);
+    let Some(program) = command(profile) else {
+        anyhow::bail!(
+            "Impersonating {} needs curl-impersonate ({}* on the PATH)",
+            profile,
+            profile.command_prefixes()[0]
+        );
+    };
+
+    // The response headers go to a file, the body to stdout, and the final
+    // status and URL to stderr after any error message
+    let header_file = std::env::temp_dir().join(format!("web-watcher-alert-{}.headers", uuid::Uuid::new_v4()));
+    let mut command = tokio::process::Command::new(&program);
+    command
+        .args(["--silent", "--show-error", "--location", "--compressed"])
+        .args(["--max-redirs", &MAX_REDIRECTS.to_string()])
+        .args(["--max-time", &timeout.as_secs().to_string()])
+        .arg("--dump-header")
+        .arg(&header_file)
+        .args(["--write-out", "%{stderr}\n%{http_code} %{url_effective}"]);
+    for (name, value) in &options.headers {
+        command.args(["--header", &format!("{}: {}", name, value)]);
+    }
+    if let Some(path) = ca_cert {
+        command.arg("--cacert").arg(path);
+    }
+    if options.tls.insecure {
+        command.arg("--insecure");
+    }
+
+    let output = command
+        .arg(url)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output()
+        .await
+        .with_context(|| format!("Failed to start {}", program.display()));
+    let headers = std::fs::read_to_string(&header_file).unwrap_or_default();
+    let _ = std::fs::remove_file(&header_file);
+    let output = output?;
+
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let (error, summary) = stderr.trim_end().rsplit_once('\n').unwrap_or(("", stderr.trim_end()));
+    if !output.status.success() {
+        let error = error.trim().trim_start_matches("curl: ");
+        anyhow::bail!("Failed to fetch URL: {}: {}", url, if error.is_empty() { "curl failed" } else { error });
+    }
+    let (status, final_url) = summary
+        .split_once(' ')
+        .with_context(|| format!("Unexpected output from {}", program.display()))?;
+
+    Ok(Impersonated {
+        status: status.parse().with_context(|| format!("Unexpected status from {}", program.display()))?,
+        url: final_url.to_string(),
+        headers: last_headers(&headers),
+        body: output.stdout,
+    })
+}
+
+/// The headers of the last response in a header dump (one block per
+/// response when redirects were followed)
+fn last_headers(dump: &str) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let Some(block) = dump.split("\r\n\r\n").filter(|block| !block.trim().is_empty()).last() else {
+        return headers;
+    };
+
+    // Skip the status line
+    for line in block.lines().skip(1) {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.trim().as_bytes()),
+            HeaderValue::from_str(value.trim()),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    headers
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fetcher::TlsOptions;
+
+    #[tokio::test]
+    async fn monitored_users_cant_name_ca_files() {
+        let options = RequestOptions {
+            tls: TlsOptions {
+                ca_cert: Some(std::path::PathBuf::from("/etc/shadow")),
+                insecure: false,
+            },
+            ..RequestOptions::default()
+        };
+        let _user = crate::system::monitor_as("alice");
+        let error = fetch("https://example.com/", &options, Duration::from_secs(5)).await.err().unwrap();
+        assert!(error.to_string().contains("system daemon (alice)"), "{}", error);
+    }
+}
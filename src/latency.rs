@@ -0,0 +1,80 @@
+//! Response time alarms
+//!
+//! A watcher can double as a lightweight performance monitor: with
+//! `latency_threshold_ms` it raises an alarm when a check's response takes
+//! longer than that, and with `latency_increase_percent` when it takes that
+//! much longer than the average of the recent checks in its history. The
+//! user is told when a site becomes slow (not on every slow check), and the
+//! watcher is marked as slow in the TUI until a check is fast again.
+
+use anyhow::Result;
+
+use crate::history::{self, CheckOutcome};
+use crate::watcher::Watcher;
+
+/// Checks the rolling average is taken over
+pub const BASELINE_CHECKS: usize = 20;
+
+/// Fewer checks than this make no average to compare with
+const MIN_BASELINE_CHECKS: usize = 5;
+
+/// Average response time (ms) of the watcher's recent successful checks,
+/// if there are enough of them
+pub fn rolling_average(watcher_id: &str) -> Result<Option<u64>> {
+    let times: Vec<u64> = history::load(watcher_id, BASELINE_CHECKS)?
+        .into_iter()
+        .filter(|record| !matches!(record.outcome, CheckOutcome::Error { .. } | CheckOutcome::Blocked { .. }))
+        .filter_map(|record| Some(record.http?.response_time_ms))
+        .collect();
+    if times.len() < MIN_BASELINE_CHECKS {
+        return Ok(None);
+    }
+    Ok(Some(times.iter().sum::<u64>() / times.len() as u64))
+}
+
+/// Why a response that took `response_ms` is too slow for the watcher, if it
+/// is (`average` is the rolling average before the check)
+pub fn slow_reason(watcher: &Watcher, response_ms: u64, average: Option<u64>) -> Option<String> {
+    if let Some(threshold) = watcher.latency_threshold_ms {
+        if response_ms > threshold {
+            return Some(format!("{}ms, over the {}ms threshold", response_ms, threshold));
+        }
+    }
+    if let (Some(percent), Some(average)) = (watcher.latency_increase_percent, average) {
+        let limit = average + average * u64::from(percent) / 100;
+        if average > 0 && response_ms > limit {
+            let increase = (response_ms - average) * 100 / average;
+            return Some(format!("{}ms, {}% above the {}ms average", response_ms, increase, average));
+        }
+    }
+    None
+}
+
+/// A threshold like "1500", "1500ms", "2s" or "1.5s", in milliseconds
+pub fn parse_threshold(input: &str) -> Result<u64> {
+    let input = input.trim().to_lowercase();
+    let parsed = if let Some(ms) = input.strip_suffix("ms") {
+        ms.trim().parse::<u64>().ok()
+    } else if let Some(seconds) = input.strip_suffix('s') {
+        seconds
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|seconds| seconds.is_finite() && *seconds >= 0.0)
+            .map(|seconds| (seconds * 1000.0).round() as u64)
+    } else {
+        input.parse::<u64>().ok()
+    };
+    match parsed {
+        Some(ms) if ms > 0 => Ok(ms),
+        _ => anyhow::bail!("Invalid response time threshold: {} (e.g. 1500ms or 2s)", input),
+    }
+}
+
+/// A percentage like "50" or "50%"
+pub fn parse_percent(input: &str) -> Result<u32> {
+    match input.trim().trim_end_matches('%').trim().parse::<u32>() {
+        Ok(percent) if percent > 0 => Ok(percent),
+        _ => anyhow::bail!("Invalid percentage: {} (e.g. 50%)", input),
+    }
+}
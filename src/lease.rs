@@ -0,0 +1,211 @@
+//! Leases on watchers run by several machines
+//!
+//! When the same settings run on several machines (see `remote`), each would
+//! check every watcher and send the same alerts. With a lease backend, a
+//! machine takes a lease on a watcher before checking it, renewed with each
+//! check; the others skip it while the lease holds. A lease lasts two check
+//! intervals, so if its holder stops (or goes offline) another machine takes
+//! over within two intervals.
+//!
+//! Leases are kept as files in a folder all the machines share (an NFS or
+//! SMB mount, a synced folder), or by the coordinator (see `cluster`).
+//! When neither can be reached, the watcher is checked anyway: a duplicate
+//! alert beats a missed one.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::cluster::{ClusterSettings, Role};
+
+/// Shortest lease, for watchers checked every few seconds
+const MIN_LEASE: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LeaseBackend {
+    /// Files in a shared folder
+    File,
+    /// The coordinator of `notifications.cluster`
+    Coordinator,
+}
+
+impl LeaseBackend {
+    pub fn name(self) -> &'static str {
+        match self {
+            LeaseBackend::File => "file",
+            LeaseBackend::Coordinator => "coordinator",
+        }
+    }
+}
+
+/// Where leases on watchers are kept
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LeaseSettings {
+    /// None: every machine checks every watcher
+    #[serde(default)]
+    pub backend: Option<LeaseBackend>,
+    /// The shared folder (file backend)
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+}
+
+/// A machine's lease on a watcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Lease {
+    pub holder: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl Lease {
+    fn holds(&self) -> bool {
+        self.expires_at > Utc::now()
+    }
+}
+
+/// The answer to a request for a lease
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Claim {
+    /// Taken or renewed: check the watcher
+    Granted,
+    /// Another machine checks it
+    Held { holder: String },
+}
+
+/// Leases granted by this process, as the coordinator
+static LEASES: Mutex<Option<HashMap<String, Lease>>> = Mutex::new(None);
+
+/// How long a lease on a watcher checked every `interval` lasts
+pub fn duration(interval: Duration) -> Duration {
+    interval.saturating_mul(2).max(MIN_LEASE)
+}
+
+/// Take (or renew) the lease on a watcher, unless another machine holds it
+pub async fn acquire(
+    settings: &LeaseSettings,
+    cluster: &ClusterSettings,
+    watcher_id: &str,
+    ttl: Duration,
+) -> Result<Claim> {
+    let holder = cluster.agent_name();
+    match settings.backend {
+        None => Ok(Claim::Granted),
+        Some(LeaseBackend::File) => {
+            let dir = settings.dir.as_deref().context("No lease folder: set \"lease\": { \"dir\": ... }")?;
+            acquire_file(dir, watcher_id, &holder, ttl)
+        }
+        Some(LeaseBackend::Coordinator) => match cluster.role {
+            Some(Role::Coordinator) => Ok(grant(watcher_id, &holder, ttl)),
+            Some(Role::Agent) => crate::cluster::request_lease(cluster, watcher_id, &holder, ttl).await,
+            None => anyhow::bail!("Leases from the coordinator need `cluster agent` or `cluster coordinator`"),
+        },
+    }
+}
+
+/// Grant a lease as the coordinator
+pub fn grant(watcher_id: &str, holder: &str, ttl: Duration) -> Claim {
+    let mut leases = LEASES.lock().unwrap_or_else(PoisonError::into_inner);
+    let leases = leases.get_or_insert_with(HashMap::new);
+    if let Some(lease) = leases.get(watcher_id) {
+        if lease.holder != holder && lease.holds() {
+            return Claim::Held {
+                holder: lease.holder.clone(),
+            };
+        }
+    }
+    leases.insert(watcher_id.to_string(), new_lease(holder, ttl));
+    Claim::Granted
+}
+
+/// Take the lease file of a watcher in the shared folder. The file is
+/// replaced atomically and read back, so of two machines renaming theirs
+/// at once, the one whose file stayed wins.
+fn acquire_file(dir: &Path, watcher_id: &str, holder: &str, ttl: Duration) -> Result<Claim> {
+    let path = dir.join(format!("{}.lease", watcher_id));
+    if let Some(lease) = read_lease(&path)? {
+        if lease.holder != holder && lease.holds() {
+            return Ok(Claim::Held { holder: lease.holder });
+        }
+    }
+
+    let temp = dir.join(format!("{}.{}.tmp", watcher_id, std::process::id()));
+    let content = serde_json::to_string(&new_lease(holder, ttl)).context("Failed to serialize lease")?;
+    std::fs::write(&temp, content).with_context(|| format!("Failed to write {}", temp.display()))?;
+    std::fs::rename(&temp, &path).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    match read_lease(&path)? {
+        Some(lease) if lease.holder != holder => Ok(Claim::Held { holder: lease.holder }),
+        _ => Ok(Claim::Granted),
+    }
+}
+
+fn read_lease(path: &Path) -> Result<Option<Lease>> {
+    match std::fs::read_to_string(path) {
+        // A damaged lease holds nothing
+        Ok(content) => Ok(serde_json::from_str(&content).ok()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    }
+}
+
+fn new_lease(holder: &str, ttl: Duration) -> Lease {
+    Lease {
+        holder: holder.to_string(),
+        expires_at: chrono::Duration::from_std(ttl)
+            .ok()
+            .and_then(|ttl| Utc::now().checked_add_signed(ttl))
+            .unwrap_or(DateTime::<Utc>::MAX_UTC),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An empty lease folder of this test
+    fn lease_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("web-watcher-alert-leases-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn lease_files_hold_until_they_expire() {
+        let dir = lease_dir("expiry");
+        let hour = Duration::from_secs(3600);
+        assert_eq!(acquire_file(&dir, "w1", "laptop", hour).unwrap(), Claim::Granted);
+        // Renewed by its holder, refused to the others
+        assert_eq!(acquire_file(&dir, "w1", "laptop", hour).unwrap(), Claim::Granted);
+        assert_eq!(
+            acquire_file(&dir, "w1", "server", hour).unwrap(),
+            Claim::Held { holder: String::from("laptop") }
+        );
+        assert_eq!(acquire_file(&dir, "w2", "server", hour).unwrap(), Claim::Granted);
+
+        // An expired (or damaged) lease is taken over
+        assert_eq!(acquire_file(&dir, "w3", "laptop", Duration::ZERO).unwrap(), Claim::Granted);
+        assert_eq!(acquire_file(&dir, "w3", "server", hour).unwrap(), Claim::Granted);
+        std::fs::write(dir.join("w4.lease"), "{ not json").unwrap();
+        assert_eq!(acquire_file(&dir, "w4", "server", hour).unwrap(), Claim::Granted);
+    }
+
+    #[test]
+    fn coordinator_grants_one_holder_at_a_time() {
+        let hour = Duration::from_secs(3600);
+        assert_eq!(grant("granted-w1", "laptop", hour), Claim::Granted);
+        assert_eq!(grant("granted-w1", "server", hour), Claim::Held { holder: String::from("laptop") });
+        assert_eq!(grant("granted-w2", "laptop", Duration::ZERO), Claim::Granted);
+        assert_eq!(grant("granted-w2", "server", hour), Claim::Granted);
+    }
+
+    #[test]
+    fn leases_last_two_intervals() {
+        assert_eq!(duration(Duration::from_secs(600)), Duration::from_secs(1200));
+        assert_eq!(duration(Duration::from_secs(5)), MIN_LEASE);
+    }
+}
@@ -0,0 +1,65 @@
+//! Web Watcher Alert
+//!
+//! Library crate behind the `web-watcher-alert` binary. Splitting the modules
+//! out of main.rs lets benchmarks (and other targets) use them directly.
+
+pub mod backup;
+pub mod bench;
+pub mod blocked;
+pub mod bookmarks;
+pub mod browser;
+pub mod cache;
+pub mod calendar;
+pub mod capture;
+pub mod cli;
+pub mod clipboard;
+pub mod cluster;
+pub mod companion;
+pub mod config;
+pub mod config_history;
+pub mod crypto;
+pub mod diff;
+pub mod doctor;
+pub mod domains;
+pub mod events;
+pub mod extractor;
+pub mod fetcher;
+pub mod history;
+pub mod hook;
+pub mod impersonate;
+pub mod latency;
+pub mod lease;
+pub mod links;
+pub mod logfiles;
+pub mod markup;
+pub mod match_test;
+pub mod matcher;
+pub mod matrix;
+pub mod monitor;
+pub mod network;
+pub mod notify;
+pub mod opml;
+pub mod outbox;
+pub mod power;
+pub mod profile;
+pub mod readability;
+pub mod remote;
+pub mod review;
+pub mod schedule;
+pub mod share;
+pub mod state;
+pub mod stream;
+pub mod sync;
+pub mod system;
+pub mod tasks;
+pub mod templates;
+pub mod timezone;
+pub mod translate;
+pub mod trash;
+pub mod trigger;
+pub mod ui;
+pub mod unix_socket;
+pub mod update;
+pub mod values;
+pub mod watchdog;
+pub mod watcher;
@@ -0,0 +1,67 @@
+//! `webwatcher://` links
+//!
+//! Links like `webwatcher://add?url=https://example.com&keywords=sale` let
+//! other tools (a Safari share-sheet shortcut, a bookmarklet, an AppleScript
+//! handler registered for the scheme) drive the CLI: a link is turned into
+//! the arguments of the command it stands for, and run like those.
+//!
+//! - `add?url=…&keywords=a,b` adds a watcher (also `interval`, `template`,
+//!   `priority`); without keywords it opens the TUI's add form instead
+//! - `check?url=…` checks one watcher (all enabled ones without `url`)
+//! - `matches?url=…&since=1d` lists recent matches
+//! - `import?watcher=…` imports a watcher shared with `share`
+
+use anyhow::{Context, Result};
+
+/// The URL scheme of links
+pub const SCHEME: &str = "webwatcher";
+
+/// Query parameters passed on as `--flag value`, by command
+const ADD_FLAGS: &[&str] = &["keywords", "interval", "template", "priority"];
+const MATCHES_FLAGS: &[&str] = &["since"];
+
+/// Whether a command-line argument is a `webwatcher://` link
+pub fn is_link(arg: &str) -> bool {
+    arg.to_lowercase().starts_with(&format!("{}:", SCHEME))
+}
+
+/// The command-line arguments a link stands for (without the program name)
+pub fn to_args(link: &str) -> Result<Vec<String>> {
+    let parsed = url::Url::parse(link).with_context(|| format!("Invalid link: {}", link))?;
+    // `webwatcher://add?…` has the action as its host, `webwatcher:add?…` as its path
+    let action = parsed
+        .host_str()
+        .filter(|host| !host.is_empty())
+        .unwrap_or_else(|| parsed.path().trim_matches('/'))
+        .to_lowercase();
+    let params: Vec<(String, String)> = parsed.query_pairs().map(|(k, v)| (k.into_owned(), v.into_owned())).collect();
+    let param = |name: &str| params.iter().find(|(key, _)| key == name).map(|(_, value)| value.clone());
+
+    let (mut args, flags) = match action.as_str() {
+        "add" => {
+            let url = param("url").context("The add link needs a url parameter")?;
+            match param("keywords").filter(|keywords| !keywords.trim().is_empty()) {
+                Some(_) => (vec![String::from("add"), url], ADD_FLAGS),
+                None => return Ok(vec![String::from("add-url"), url]),
+            }
+        }
+        "check" => (vec![String::from("check")], &[][..]),
+        "matches" => (vec![String::from("matches")], MATCHES_FLAGS),
+        "import" => {
+            let watcher = param("watcher").context("The import link needs a watcher parameter")?;
+            return Ok(vec![String::from("import-watcher"), watcher]);
+        }
+        other => anyhow::bail!("Unknown link action: {} (use add, check, matches or import)", other),
+    };
+
+    if action != "add" {
+        args.extend(param("url"));
+    }
+    for flag in flags {
+        if let Some(value) = param(flag) {
+            args.push(format!("--{}", flag));
+            args.push(value);
+        }
+    }
+    Ok(args)
+}
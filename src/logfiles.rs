@@ -0,0 +1,212 @@
+//! Log files of the monitor
+//!
+//! With `logging.events_file` on, the outcome of every check (and each
+//! match, error and block page) and every notification (per channel,
+//! delivered or not) is appended to `logs/events.jsonl` in the data
+//! directory as a JSON object of its own line, for log pipelines like Vector
+//! or Loki to pick up:
+//!
+//! ```text
+//! {"event":"match","keywords":["sale"],"time":"2025-03-01T09:30:00.120Z","url":"https://...","watcher_id":"3f0c..."}
+//! {"channel":"webhook","delivered":true,"event":"notification","keywords":["sale"],"retry":false,...}
+//! ```
+//!
+//! With `logging.watcher_files` on, each watcher's part of the log (its
+//! checks, matches, errors and pauses) is also written to a file of its own,
+//! `logs/watcher-<id>.log` in the data directory, so one noisy watcher can
+//! be followed with `tail -f` instead of grepping the daemon's log. Events
+//! dropped from the daemon's output (by `-q` or `logging.level`) still go
+//! to the files.
+//!
+//! Files roll over: one that would grow past `max_file_size` is renamed to
+//! `watcher-<id>.log.1` or `events.jsonl.1` (the older ones to `.2` and so
+//! on, up to `keep_files`) and a new one is started.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::events::Level;
+
+/// How much the daemon prints, which log files are written, and how big
+/// they get
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LogSettings {
+    /// Least important events the daemon prints (`--log-level` and `-q`
+    /// take precedence); "changes" leaves out the line of every check
+    #[serde(default)]
+    pub level: Option<Level>,
+
+    /// While the daemon leaves out the checks, print a line counting them
+    /// this often (in seconds, 0 never)
+    #[serde(default = "default_summary_interval", with = "crate::watcher::duration_serde")]
+    pub summary_interval: Duration,
+
+    /// Write each watcher's events to its own file too
+    #[serde(default)]
+    pub watcher_files: bool,
+
+    /// Append checks, matches and notifications to `events.jsonl`
+    #[serde(default)]
+    pub events_file: bool,
+
+    /// Size (in bytes) at which a file is rolled over
+    #[serde(default = "default_max_file_size")]
+    pub max_file_size: u64,
+
+    /// Rolled-over files kept of each log (0 keeps none)
+    #[serde(default = "default_keep_files")]
+    pub keep_files: usize,
+}
+
+impl Default for LogSettings {
+    fn default() -> Self {
+        Self {
+            level: None,
+            summary_interval: default_summary_interval(),
+            watcher_files: false,
+            events_file: false,
+            max_file_size: default_max_file_size(),
+            keep_files: default_keep_files(),
+        }
+    }
+}
+
+fn default_summary_interval() -> Duration {
+    Duration::from_secs(15 * 60)
+}
+
+fn default_max_file_size() -> u64 {
+    1024 * 1024
+}
+
+fn default_keep_files() -> usize {
+    3
+}
+
+/// The directory log files are written to
+pub fn dir() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join("logs"))
+}
+
+/// The events file, once a monitor opened it
+static EVENTS: Mutex<Option<EventsFile>> = Mutex::new(None);
+
+struct EventsFile {
+    path: PathBuf,
+    max_file_size: u64,
+    keep_files: usize,
+    /// A failure was reported
+    failed: bool,
+}
+
+/// Start appending events to `events.jsonl`, if the settings say so (once
+/// it's open, it stays open for the process)
+pub fn open_events(settings: &LogSettings) -> Result<()> {
+    if !settings.events_file {
+        return Ok(());
+    }
+    let file = EventsFile {
+        path: dir()?.join("events.jsonl"),
+        max_file_size: settings.max_file_size,
+        keep_files: settings.keep_files,
+        failed: false,
+    };
+    *EVENTS.lock().unwrap_or_else(PoisonError::into_inner) = Some(file);
+    Ok(())
+}
+
+/// Append an event to `events.jsonl`, if it's open. Failures go to stderr,
+/// the first time only.
+pub fn record(event: serde_json::Value) {
+    let mut events = EVENTS.lock().unwrap_or_else(PoisonError::into_inner);
+    let Some(file) = events.as_mut() else {
+        return;
+    };
+    if let Err(e) = append(&file.path, &event.to_string(), file.max_file_size, file.keep_files) {
+        if !std::mem::replace(&mut file.failed, true) {
+            eprintln!("⚠ Failed to write the events file {}: {:#}", file.path.display(), e);
+        }
+    }
+}
+
+/// Writes lines to the watchers' log files, rolling them over
+#[derive(Debug)]
+pub struct WatcherFiles {
+    dir: PathBuf,
+    max_file_size: u64,
+    keep_files: usize,
+    /// Writes (and roll-overs) happen one at a time
+    lock: Mutex<()>,
+    /// A failure was reported (it's reported once, not for every line)
+    failed: AtomicBool,
+}
+
+impl WatcherFiles {
+    /// The files the settings ask for (None if watcher files are off)
+    pub fn from_settings(settings: &LogSettings) -> Result<Option<Self>> {
+        if !settings.watcher_files {
+            return Ok(None);
+        }
+        Ok(Some(Self {
+            dir: dir()?,
+            max_file_size: settings.max_file_size,
+            keep_files: settings.keep_files,
+            lock: Mutex::new(()),
+            failed: AtomicBool::new(false),
+        }))
+    }
+
+    /// Append a line to a watcher's file. Failures go to stderr, the first
+    /// time only.
+    pub fn write(&self, watcher_id: &str, line: &str) {
+        let path = self.dir.join(format!("watcher-{}.log", watcher_id));
+        let _lock = self.lock.lock().unwrap_or_else(PoisonError::into_inner);
+        if let Err(e) = append(&path, line, self.max_file_size, self.keep_files) {
+            if !self.failed.swap(true, Ordering::Relaxed) {
+                eprintln!("⚠ Failed to write the watcher log {}: {:#}", path.display(), e);
+            }
+        }
+    }
+}
+
+/// Append a line to a log file, rolling it over first if the line would
+/// take it past `max_size`
+pub fn append(path: &Path, line: &str, max_size: u64, keep: usize) -> Result<()> {
+    let size = std::fs::metadata(path).map(|metadata| metadata.len()).unwrap_or(0);
+    if size > 0 && size + line.len() as u64 + 1 > max_size {
+        roll_over(path, keep)?;
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))?;
+    writeln!(file, "{}", line).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Move `path` to `path.1` (and each older file one number up, dropping
+/// the one past `keep`)
+fn roll_over(path: &Path, keep: usize) -> Result<()> {
+    let numbered = |n: usize| PathBuf::from(format!("{}.{}", path.display(), n));
+    if keep == 0 {
+        return std::fs::remove_file(path).with_context(|| format!("Failed to remove {}", path.display()));
+    }
+    let _ = std::fs::remove_file(numbered(keep));
+    for n in (1..keep).rev() {
+        let from = numbered(n);
+        if from.exists() {
+            std::fs::rename(&from, numbered(n + 1)).with_context(|| format!("Failed to rename {}", from.display()))?;
+        }
+    }
+    std::fs::rename(path, numbered(1)).with_context(|| format!("Failed to rename {}", path.display()))
+}
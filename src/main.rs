@@ -1,50 +1,369 @@
-// Module declarations
-mod cache;
-mod config;
-mod diff;
-mod fetcher;
-mod matcher;
-mod monitor;
-mod notify;
-mod ui;
-mod watcher;
-
-use anyhow::Result;
+use anyhow::{Context, Result};
 use std::env;
+use std::io::IsTerminal;
+use std::process::ExitCode;
+
+use web_watcher_alert::{
+    cli::{self, Verbosity},
+    config, config_history,
+    events::{EventLog, Level, LogFormat},
+    links, monitor, notify, profile, remote, system, timezone, ui,
+};
+
+/// Environment variable selecting the daemon's log format (text or json)
+const LOG_FORMAT_ENV: &str = "WEB_WATCHER_ALERT_LOG_FORMAT";
+
+/// Errors (an invalid config, bad arguments) exit with 1; `check` also
+/// exits with 2 or 3 (see `cli::EXIT_FETCH_ERRORS` and `cli::EXIT_MATCHES`)
+fn main() -> Result<ExitCode> {
+    // Select the profile before anything looks up a path
+    let mut args: Vec<String> = env::args().collect();
+    select_user(&mut args)?;
+    select_profile(&mut args)?;
+    select_read_only(&mut args);
+    let verbosity = select_verbosity(&mut args)?;
+
+    // A webwatcher:// link runs the command it stands for
+    if let Some(link) = args.get(1).filter(|arg| links::is_link(arg)) {
+        let expanded = links::to_args(link)?;
+        args.splice(1..2, expanded);
+    }
 
-fn main() -> Result<()> {
     // Check if running in daemon mode
-    let args: Vec<String> = env::args().collect();
     let daemon_mode = args.iter().any(|arg| arg == "--daemon");
 
+    // Name the command in the config history of its saves
+    let command = match args.get(1) {
+        _ if daemon_mode => "daemon",
+        Some(arg) if arg.contains("://") => "add-url",
+        Some(arg) => arg.as_str(),
+        None => "tui",
+    };
+    config_history::set_command(command);
+
     if daemon_mode {
-        // Run in daemon mode (background service)
-        run_daemon()?;
-    } else {
-        // Run interactive TUI
-        let mut ui = ui::UI::new()?;
-        ui.run()?;
+        // Run in daemon mode (background service), for every user of a
+        // system installation with --system
+        if args.iter().any(|arg| arg == "--system") {
+            run_system_daemon(&args, verbosity)?;
+        } else {
+            run_daemon(&args, verbosity)?;
+        }
+        return Ok(ExitCode::SUCCESS);
     }
 
-    Ok(())
+    // Dispatch subcommands, falling back to the interactive TUI
+    match args.get(1).map(String::as_str) {
+        Some("check") => return cli::check(&args[2..], verbosity),
+        Some("add") => cli::add(&args[2..])?,
+        Some("add-url") => open_add_form(args.get(2))?,
+        Some("import-bookmarks") => cli::import_bookmarks(&args[2..])?,
+        Some("import-opml") => cli::import_opml(&args[2..])?,
+        Some("export-opml") => cli::export_opml(&args[2..])?,
+        Some("export-values") => cli::export_values(&args[2..])?,
+        Some("share") => cli::share(&args[2..])?,
+        Some("import-watcher") => cli::import_watcher(&args[2..])?,
+        Some("matches") => cli::list_matches(&args[2..])?,
+        Some("calendar") => cli::calendar(&args[2..])?,
+        Some("companion") => cli::companion(&args[2..])?,
+        Some("triggers") => cli::triggers(&args[2..])?,
+        Some("archive") => cli::archive(&args[2..], true)?,
+        Some("unarchive") => cli::archive(&args[2..], false)?,
+        Some("trash") => cli::trash(&args[2..])?,
+        Some("prune") => cli::prune(&args[2..])?,
+        Some("encryption") => cli::encryption(&args[2..])?,
+        Some("matrix") => cli::matrix(&args[2..])?,
+        Some("config") => cli::config(&args[2..])?,
+        Some("remote") => cli::remote(&args[2..])?,
+        Some("cluster") => cli::cluster(&args[2..])?,
+        Some("notify") => cli::notify(&args[2..])?,
+        Some("sync") => cli::sync(&args[2..])?,
+        Some("templates") => cli::list_templates()?,
+        Some("keyword-sets") => cli::list_keyword_sets()?,
+        Some("users") => cli::list_users()?,
+        Some("capture") => cli::capture(&args[2..])?,
+        Some("bench") => cli::bench(&args[2..])?,
+        Some("match-test") => cli::match_test(&args[2..])?,
+        Some("doctor") | Some("validate") => cli::doctor(&args[2..])?,
+        Some("backup") => cli::backup(&args[2..])?,
+        Some("restore") => cli::restore(&args[2..])?,
+        Some("help") | Some("--help") | Some("-h") => cli::print_usage(),
+        Some(url) if url.contains("://") => open_add_form(Some(&args[1]))?,
+        Some(other) => {
+            cli::print_usage();
+            anyhow::bail!("Unknown command: {}", other);
+        }
+        None if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() => {
+            // No terminal to draw the TUI on (container, cron, `ssh host cmd`):
+            // monitor instead of failing to enter raw mode
+            run_headless(verbosity)?;
+        }
+        None => {
+            // Run interactive TUI
+            let mut ui = ui::UI::new()?;
+            ui.run()?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
 }
 
-fn run_daemon() -> Result<()> {
-    // Load configuration
+/// Start the TUI on the add form, filled in with a URL (`add-url <url>`, or
+/// the URL alone, for "watch this page" actions of other tools)
+fn open_add_form(url: Option<&String>) -> Result<()> {
+    let url = url.context("Missing URL. Usage: add-url <url>")?;
+    if !std::io::stdout().is_terminal() || !std::io::stdin().is_terminal() {
+        anyhow::bail!(
+            "No terminal for the add form. Add the watcher with \
+             `web-watcher-alert add {} --keywords <a,b,c>` instead",
+            url
+        );
+    }
+
+    let mut ui = ui::UI::new()?;
+    ui.add_url(url)?;
+    ui.run()
+}
+
+/// Fallback for a plain invocation without a terminal: run the daemon if
+/// there is something to monitor, otherwise explain how to set it up
+fn run_headless(verbosity: Verbosity) -> Result<()> {
     let config = config::Config::load()?;
+    if !config.watchers.iter().any(|w| w.is_active()) {
+        anyhow::bail!(
+            "No terminal for the interactive TUI and no enabled watchers to monitor.\n\
+             Add one with `web-watcher-alert add <url> --keywords <a,b,c>` \
+             (see `web-watcher-alert help`)"
+        );
+    }
+
+    eprintln!("No terminal for the interactive TUI, running in daemon mode (pass --daemon to skip this check)");
+    run_daemon(&[], verbosity)
+}
 
-    // Print startup message
-    println!("Web Watcher Alert - Daemon Mode");
-    println!("Starting monitoring for {} watchers...", config.watchers.len());
+/// Run the monitor until every watcher has stopped or the process is told
+/// to stop. The daemon never detaches; `--foreground` makes that explicit
+/// for containers and switches the default log format to JSON lines.
+fn run_daemon(args: &[String], verbosity: Verbosity) -> Result<()> {
+    let foreground = args.iter().any(|arg| arg == "--foreground");
+    let format = log_format(args, foreground)?;
+
+    // Load configuration
+    let mut config = config::Config::load()?;
+    let level = log_level(args, verbosity, config.logging.level)?;
+    let log = EventLog::stdout(config.timezone, format).min_level(level);
+
+    // Print startup message (JSON logs stay parseable without it)
+    if format == LogFormat::Text && level == Level::Info {
+        println!("Web Watcher Alert - Daemon Mode");
+        println!("Starting monitoring for {} watchers...", config.watchers.len());
+        println!("Press Ctrl+C to stop.");
+    }
+
+    if let Some(name) = profile::active() {
+        log.info(format!("Profile: {}", name));
+    }
+
+    if config.is_read_only() {
+        log.info("Config is read-only: check results and disabled watchers won't be saved");
+    }
+
+    // Start with the settings the other machines pushed
+    if config.remote.enabled() && !config.is_read_only() {
+        match remote::pull(&mut config, false) {
+            Ok(pulled) => {
+                if pulled.applied {
+                    config.save()?;
+                    log.info(format!("Pulled the settings from the remote: {} watcher(s)", config.watchers.len()));
+                }
+                if pulled.records > 0 {
+                    log.info(format!("Merged {} check record(s) from other machines", pulled.records));
+                }
+            }
+            Err(e) => log.warning(format!("⚠ Failed to pull the settings from the remote: {:#}", e)),
+        }
+    }
+
+    let notifiers = notify::Notifiers::from_settings(&config.notifications);
+    if notifiers.is_empty() {
+        log.warning(format!(
+            "⚠ No desktop session and no webhook: alerts will only be logged (set notifications.webhook_url or {})",
+            config::WEBHOOK_ENV
+        ));
+    } else {
+        log.info(format!("Alerts go to: {}", notifiers.names().join(", ")));
+    }
 
     // Create monitor and start
-    let monitor = monitor::Monitor::new(config);
+    let monitor = monitor::Monitor::with_event_log(config, log.clone());
 
-    // Create Tokio runtime and run monitoring
+    // Create Tokio runtime and run monitoring. Dropping the runtime on the
+    // way out cancels the watcher tasks.
     let runtime = tokio::runtime::Runtime::new()?;
     runtime.block_on(async {
-        monitor.start().await
+        tokio::select! {
+            result = monitor.start() => result,
+            signal = shutdown_signal() => {
+                log.info(format!("Received {}, stopping", signal));
+                Ok(())
+            }
+        }
     })?;
 
     Ok(())
 }
+
+/// Run one monitor per user of the system installation (see `system`)
+/// until the process is told to stop
+fn run_system_daemon(args: &[String], verbosity: Verbosity) -> Result<()> {
+    let foreground = args.iter().any(|arg| arg == "--foreground");
+    let format = log_format(args, foreground)?;
+    let mut log = EventLog::stdout(timezone::Zone::Local, format);
+    if verbosity == Verbosity::Quiet {
+        log = log.min_level(Level::Match);
+    }
+
+    if format == LogFormat::Text && verbosity != Verbosity::Quiet {
+        println!("Web Watcher Alert - System Daemon");
+        println!("Press Ctrl+C to stop.");
+    }
+
+    // Dropping the runtime kills the users' monitor processes
+    let runtime = tokio::runtime::Runtime::new()?;
+    runtime.block_on(async {
+        tokio::select! {
+            result = system::serve(format, verbosity == Verbosity::Quiet, &log) => result,
+            signal = shutdown_signal() => {
+                log.info(format!("Received {}, stopping", signal));
+                Ok(())
+            }
+        }
+    })?;
+
+    Ok(())
+}
+
+/// Apply `--user <name>` (removed from the arguments): use that user's
+/// directories of the system installation
+fn select_user(args: &mut Vec<String>) -> Result<()> {
+    if let Some(i) = args.iter().position(|arg| arg == "--user") {
+        if i + 1 >= args.len() {
+            anyhow::bail!("--user needs a user name");
+        }
+        let name = args.remove(i + 1);
+        args.remove(i);
+        system::select_user(&name)?;
+    }
+    Ok(())
+}
+
+/// Apply `--profile <name>` (removed from the arguments so commands don't
+/// see it) or the profile environment variable
+fn select_profile(args: &mut Vec<String>) -> Result<()> {
+    if let Some(i) = args.iter().position(|arg| arg == "--profile") {
+        if i + 1 >= args.len() {
+            anyhow::bail!("--profile needs a profile name");
+        }
+        let name = args.remove(i + 1);
+        args.remove(i);
+        return profile::set_active(&name);
+    }
+
+    match env::var(profile::PROFILE_ENV) {
+        Ok(name) if !name.trim().is_empty() => profile::set_active(&name),
+        _ => Ok(()),
+    }
+}
+
+/// Apply `--read-only` (removed from the arguments) or the read-only
+/// environment variable
+fn select_read_only(args: &mut Vec<String>) {
+    let flag = args.iter().position(|arg| arg == "--read-only");
+    if let Some(i) = flag {
+        args.remove(i);
+    }
+
+    let from_env = env::var(config::READ_ONLY_ENV)
+        .is_ok_and(|value| !matches!(value.trim(), "" | "0" | "false"));
+    if flag.is_some() || from_env {
+        config::Config::set_read_only();
+    }
+}
+
+/// Apply `-q`/`--quiet` or `-v`/`--verbose` (removed from the arguments)
+fn select_verbosity(args: &mut Vec<String>) -> Result<Verbosity> {
+    let mut verbosity = Verbosity::Normal;
+    let mut i = 0;
+    while i < args.len() {
+        let flag = match args[i].as_str() {
+            "-q" | "--quiet" => Verbosity::Quiet,
+            "-v" | "--verbose" => Verbosity::Verbose,
+            _ => {
+                i += 1;
+                continue;
+            }
+        };
+        if verbosity != Verbosity::Normal && verbosity != flag {
+            anyhow::bail!("--quiet and --verbose can't be combined");
+        }
+        verbosity = flag;
+        args.remove(i);
+    }
+    Ok(verbosity)
+}
+
+/// Log format from `--log-format`, the environment, or the mode's default
+fn log_format(args: &[String], foreground: bool) -> Result<LogFormat> {
+    if let Some(i) = args.iter().position(|arg| arg == "--log-format") {
+        let value = args
+            .get(i + 1)
+            .ok_or_else(|| anyhow::anyhow!("--log-format needs a value (text or json)"))?;
+        return LogFormat::parse(value);
+    }
+    if let Ok(value) = env::var(LOG_FORMAT_ENV) {
+        return LogFormat::parse(&value);
+    }
+    Ok(if foreground { LogFormat::Json } else { LogFormat::Text })
+}
+
+/// Least important events the daemon prints: `--log-level`, `-q` (matches
+/// and up), or the config's `logging.level`
+fn log_level(args: &[String], verbosity: Verbosity, configured: Option<Level>) -> Result<Level> {
+    if let Some(i) = args.iter().position(|arg| arg == "--log-level") {
+        let value = args.get(i + 1).ok_or_else(|| {
+            anyhow::anyhow!("--log-level needs a value (info, changes, matches, warnings or errors)")
+        })?;
+        return Level::parse(value);
+    }
+    if verbosity == Verbosity::Quiet {
+        return Ok(Level::Match);
+    }
+    Ok(configured.unwrap_or(Level::Info))
+}
+
+/// Wait for Ctrl+C or SIGTERM (sent by `docker stop`, launchd and systemd).
+/// Running as a container's PID 1, the process would otherwise ignore
+/// SIGTERM and only stop when it's killed.
+async fn shutdown_signal() -> &'static str {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => tokio::select! {
+                _ = tokio::signal::ctrl_c() => "SIGINT",
+                _ = terminate.recv() => "SIGTERM",
+            },
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+                "SIGINT"
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+        "Ctrl+C"
+    }
+}
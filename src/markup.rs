@@ -0,0 +1,289 @@
+//! Formatting adapters for notifiers
+//!
+//! A keyword alert is composed once, as a list of blocks (a line of text,
+//! the matched keywords with their context, a list of changes), and each
+//! notifier gets it rendered in the format it displays: plain text for
+//! desktop notifications, Markdown for chat services (Slack, Discord,
+//! Telegram), HTML for email. Other alerts are plain text, which is
+//! escaped (and, for HTML, split into paragraphs) the same way.
+//!
+//! HTML has inline styles only, since email clients drop style sheets.
+
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::diff::{Segment, SideBySide};
+
+/// Background of removed lines and (darker) of the words removed from them
+const REMOVED_STYLE: (&str, &str) = ("#ffebe9", "#ffb3ad");
+
+/// Background of added lines and (darker) of the words added to them
+const ADDED_STYLE: (&str, &str) = ("#e6ffec", "#abf2bc");
+
+/// What a notifier displays
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    #[default]
+    Plain,
+    Markdown,
+    Html,
+}
+
+impl Format {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "plain" | "text" => Ok(Format::Plain),
+            "markdown" | "md" => Ok(Format::Markdown),
+            "html" => Ok(Format::Html),
+            other => anyhow::bail!("Unknown format: {} (use plain, markdown or html)", other),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Format::Plain => "plain",
+            Format::Markdown => "markdown",
+            Format::Html => "html",
+        }
+    }
+
+    /// Text with the characters the format would read as formatting escaped
+    pub fn escape(self, text: &str) -> String {
+        match self {
+            Format::Plain => text.to_string(),
+            Format::Markdown => escape_markdown(text),
+            Format::Html => escape_html(text),
+        }
+    }
+
+    /// A plain-text body (paragraphs separated by blank lines) in this format
+    pub fn render_plain(self, text: &str) -> String {
+        match self {
+            Format::Plain | Format::Markdown => self.escape(text),
+            Format::Html => text
+                .split("\n\n")
+                .map(|paragraph| format!("<p>{}</p>", escape_html(paragraph).replace('\n', "<br>\n")))
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+
+    /// The blocks of an alert body in this format
+    pub fn render(self, blocks: &[Block]) -> String {
+        let rendered: Vec<String> = blocks.iter().map(|block| self.render_block(block)).collect();
+        match self {
+            Format::Plain | Format::Markdown => rendered.join("\n\n"),
+            Format::Html => rendered.join("\n"),
+        }
+    }
+
+    fn render_block(self, block: &Block) -> String {
+        match block {
+            Block::Text(text) => match self {
+                Format::Html => format!("<p>{}</p>", escape_html(text)),
+                _ => self.escape(text),
+            },
+            Block::Items { items, more } => {
+                let mut lines = Vec::new();
+                if self == Format::Html {
+                    lines.push(String::from("<ul>"));
+                }
+                for item in items {
+                    lines.push(self.render_item(item));
+                }
+                if self == Format::Html {
+                    lines.push(String::from("</ul>"));
+                }
+                if let Some(more) = more {
+                    lines.push(match self {
+                        Format::Html => format!("<p>{}</p>", escape_html(more)),
+                        _ => self.escape(more),
+                    });
+                }
+                lines.join("\n")
+            }
+            Block::Code { heading, lines, more } => {
+                let mut code: Vec<&str> = lines.iter().map(String::as_str).collect();
+                if let Some(more) = more {
+                    code.push(more);
+                }
+                match self {
+                    Format::Plain => {
+                        let indented: Vec<String> = code.iter().map(|line| format!("  {}", line)).collect();
+                        format!("{}\n{}", heading, indented.join("\n"))
+                    }
+                    // Code blocks aren't escaped (nor can they end early:
+                    // fences inside are broken up)
+                    Format::Markdown => format!(
+                        "{}\n```\n{}\n```",
+                        escape_markdown(heading),
+                        code.join("\n").replace("```", "`\u{200b}``")
+                    ),
+                    Format::Html => format!(
+                        "<p>{}</p>\n<pre>{}</pre>",
+                        escape_html(heading),
+                        escape_html(&code.join("\n"))
+                    ),
+                }
+            }
+            Block::SideBySide { heading, diff } => match self {
+                Format::Html => render_side_by_side_html(heading, diff),
+                // Side by side doesn't fit text: a unified diff instead
+                _ => {
+                    let mut lines = Vec::new();
+                    for row in &diff.rows {
+                        if row.is_equal() {
+                            lines.push(format!("  {}", line_text(&row.new)));
+                            continue;
+                        }
+                        if row.old.is_some() {
+                            lines.push(format!("- {}", line_text(&row.old)));
+                        }
+                        if row.new.is_some() {
+                            lines.push(format!("+ {}", line_text(&row.new)));
+                        }
+                    }
+                    let more = (diff.more > 0).then(|| format!("... and {} more lines", diff.more));
+                    self.render_block(&Block::Code {
+                        heading: heading.clone(),
+                        lines,
+                        more,
+                    })
+                }
+            },
+        }
+    }
+
+    fn render_item(self, item: &Item) -> String {
+        match self {
+            Format::Plain => {
+                let mut text = format!("• {}: {}", item.label, item.text);
+                if let Some((label, note)) = &item.note {
+                    text.push_str(&format!("\n  {}: {}", label, note));
+                }
+                text
+            }
+            Format::Markdown => {
+                let mut text = format!("• **{}**: {}", escape_markdown(&item.label), escape_markdown(&item.text));
+                if let Some((label, note)) = &item.note {
+                    text.push_str(&format!("\n  _{}_: {}", escape_markdown(label), escape_markdown(note)));
+                }
+                text
+            }
+            Format::Html => {
+                let mut text = format!("<li><b>{}</b>: {}", escape_html(&item.label), escape_html(&item.text));
+                if let Some((label, note)) = &item.note {
+                    text.push_str(&format!("<br><i>{}</i>: {}", escape_html(label), escape_html(note)));
+                }
+                text.push_str("</li>");
+                text
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Format {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+/// A part of an alert body, rendered by each format its own way
+#[derive(Debug, Clone)]
+pub enum Block {
+    /// A line of text
+    Text(String),
+    /// Labelled entries (keywords and their context), then a note like
+    /// "... and 3 more keywords"
+    Items { items: Vec<Item>, more: Option<String> },
+    /// Lines shown verbatim (a diff, changed JSON paths) under a heading
+    Code {
+        heading: String,
+        lines: Vec<String>,
+        more: Option<String>,
+    },
+    /// The changed region of a page, old and new version side by side
+    SideBySide { heading: String, diff: SideBySide },
+}
+
+/// An entry of `Block::Items`, with an optional labelled note under it
+#[derive(Debug, Clone)]
+pub struct Item {
+    pub label: String,
+    pub text: String,
+    pub note: Option<(String, String)>,
+}
+
+/// A side-by-side diff as a two-column table, removed and added lines
+/// tinted and the changed words within them highlighted
+fn render_side_by_side_html(heading: &str, diff: &SideBySide) -> String {
+    let cell = "padding:2px 6px;vertical-align:top;white-space:pre-wrap;word-break:break-word;width:50%";
+    let mut html = vec![
+        format!("<p>{}</p>", escape_html(heading)),
+        String::from(
+            "<table style=\"border-collapse:collapse;font-family:monospace;font-size:13px;width:100%\">",
+        ),
+        format!(
+            "<tr><th style=\"{0};text-align:left\">Before</th><th style=\"{0};text-align:left\">After</th></tr>",
+            cell
+        ),
+    ];
+    for row in &diff.rows {
+        let equal = row.is_equal();
+        let side = |segments: &Option<Vec<Segment>>, (line, word): (&str, &str)| match segments {
+            None => format!("<td style=\"{};background:#f6f8fa\"></td>", cell),
+            Some(_) if equal => format!("<td style=\"{}\">{}</td>", cell, escape_html(&line_text(segments))),
+            Some(segments) => {
+                let text: String = segments
+                    .iter()
+                    .map(|segment| {
+                        if segment.changed {
+                            format!("<span style=\"background:{}\">{}</span>", word, escape_html(&segment.text))
+                        } else {
+                            escape_html(&segment.text)
+                        }
+                    })
+                    .collect();
+                format!("<td style=\"{};background:{}\">{}</td>", cell, line, text)
+            }
+        };
+        html.push(format!("<tr>{}{}</tr>", side(&row.old, REMOVED_STYLE), side(&row.new, ADDED_STYLE)));
+    }
+    html.push(String::from("</table>"));
+    if diff.more > 0 {
+        html.push(format!("<p>... and {} more lines</p>", diff.more));
+    }
+    html.join("\n")
+}
+
+/// The text of one side of a diff row
+fn line_text(segments: &Option<Vec<Segment>>) -> String {
+    segments.iter().flatten().map(|segment| segment.text.as_str()).collect()
+}
+
+/// Escape the characters Markdown would treat as formatting
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '*' | '_' | '`' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,247 @@
+//! Trying keywords on a page
+//!
+//! `match-test` loads a page once (fetched like a check would, read from a
+//! file, or pasted) and then takes keywords line by line, printing what
+//! each matches with its context right away, so a complicated regex or a
+//! selector can be worked out before it goes into a watcher. Lines starting
+//! with `:` change what's searched:
+//!
+//! ```text
+//! > sale, sold out
+//! > /price: \$(?P<amount>\d+)/
+//! > :selector #product
+//! > :content text
+//! ```
+
+use anyhow::{Context, Result};
+
+use crate::domains::{self, SiteSettings};
+use crate::extractor::ContentMode;
+use crate::matcher::{Keyword, KeywordMatcher};
+
+/// Contexts printed of each keyword (the rest are counted)
+const SHOWN_MATCHES: usize = 5;
+
+/// Lines of the watched content `:show` prints by default
+const SHOWN_LINES: usize = 40;
+
+pub const HELP: &str = "\
+Type keywords (comma-separated; /.../ is a regex) to see what they match, or:
+  :regex <pattern>         Try one regex (commas and all)
+  :whole-word on|off       Only match whole words
+  :selector [css]          Only search what the selector picks (none: the whole page)
+  :main on|off             Only search the page's main content
+  :content raw_html|text   Search the HTML or the visible text
+  :show [lines]            Print the content that's searched
+  :settings                Show the settings in use
+  :quit                    Leave (so does Ctrl-D)";
+
+/// What to do after a line
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Print(String),
+    Quit,
+}
+
+/// A page and the settings keywords are tried with
+pub struct Session {
+    page: String,
+    site: SiteSettings,
+    whole_word: bool,
+    /// The part of the page that's searched, for the current settings
+    content: String,
+}
+
+impl Session {
+    /// Start with the page as loaded and the settings its watcher (or its
+    /// domain) checks it with
+    pub fn new(page: String, site: SiteSettings) -> Result<Self> {
+        let content = domains::watched_part(&site, page.clone())?;
+        Ok(Self {
+            page,
+            site,
+            whole_word: false,
+            content,
+        })
+    }
+
+    /// The content that's searched
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Handle a line typed at the prompt
+    pub fn handle(&mut self, line: &str) -> Result<Step> {
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(Step::Print(String::new()));
+        }
+        let Some(command) = line.strip_prefix(':') else {
+            return self.report(&self.parse_keywords(line)).map(Step::Print);
+        };
+        let (name, value) = command.split_once(' ').unwrap_or((command, ""));
+        let value = value.trim();
+        match name {
+            "q" | "quit" | "exit" => Ok(Step::Quit),
+            "help" | "h" | "?" => Ok(Step::Print(HELP.to_string())),
+            "regex" => {
+                if value.is_empty() {
+                    anyhow::bail!("Usage: :regex <pattern>");
+                }
+                self.report(&[self.keyword(value, true)]).map(Step::Print)
+            }
+            "whole-word" => {
+                self.whole_word = parse_switch(value)?;
+                Ok(Step::Print(format!("Whole words only: {}", on_off(self.whole_word))))
+            }
+            "selector" => {
+                let selector = match value {
+                    "" => None,
+                    css => {
+                        domains::validate_selector(css)?;
+                        Some(css.to_string())
+                    }
+                };
+                self.change(|site| site.selector = selector)
+            }
+            "main" => {
+                let main_content = parse_switch(value)?;
+                self.change(|site| site.main_content = main_content)
+            }
+            "content" => {
+                let mode = ContentMode::parse(value)?;
+                self.change(|site| site.content = mode)
+            }
+            "show" => {
+                let lines = match value {
+                    "" => SHOWN_LINES,
+                    n => n.parse().context("Usage: :show [lines]")?,
+                };
+                Ok(Step::Print(self.show(lines)))
+            }
+            "settings" => Ok(Step::Print(self.settings())),
+            other => anyhow::bail!("Unknown command :{} (:help lists them)", other),
+        }
+    }
+
+    /// Keywords of a line: comma-separated, a `/.../` one is a regex (which
+    /// can have commas of its own, like `/\d{1,3}/`)
+    fn parse_keywords(&self, line: &str) -> Vec<Keyword> {
+        let mut texts: Vec<String> = Vec::new();
+        for part in line.split(',') {
+            match texts.last_mut() {
+                Some(last) if is_open_regex(last) => {
+                    last.push(',');
+                    last.push_str(part);
+                }
+                _ => texts.push(part.to_string()),
+            }
+        }
+        texts
+            .iter()
+            .map(|text| text.trim())
+            .filter(|text| !text.is_empty())
+            .map(|text| match regex_literal(text) {
+                Some(pattern) => self.keyword(pattern, true),
+                None => self.keyword(text, false),
+            })
+            .collect()
+    }
+
+    fn keyword(&self, text: &str, is_regex: bool) -> Keyword {
+        Keyword {
+            is_regex,
+            whole_word: self.whole_word,
+            ..Keyword::new(text.to_string())
+        }
+    }
+
+    /// The matches of each keyword, with their contexts
+    pub fn report(&self, keywords: &[Keyword]) -> Result<String> {
+        for keyword in keywords.iter().filter(|keyword| keyword.is_regex) {
+            keyword
+                .compile()
+                .map_err(|e| anyhow::anyhow!("Invalid regex {}: {}", keyword.text, e))?;
+        }
+        let matches = KeywordMatcher::new(keywords).find(&self.content);
+
+        let mut lines = Vec::new();
+        for keyword in keywords {
+            let found: Vec<_> = matches.iter().filter(|m| m.keyword == keyword.text).collect();
+            if found.is_empty() {
+                lines.push(format!("✗ {}: not found", keyword.describe()));
+                continue;
+            }
+            let plural = if found.len() == 1 { "" } else { "es" };
+            lines.push(format!("✓ {}: {} match{}", keyword.describe(), found.len(), plural));
+            for m in found.iter().take(SHOWN_MATCHES) {
+                lines.push(format!("    {}", m.context));
+                if !m.captures.is_empty() {
+                    let captures: Vec<String> = m.captures.iter().map(|(name, value)| format!("{}={}", name, value)).collect();
+                    lines.push(format!("      {}", captures.join(", ")));
+                }
+            }
+            if found.len() > SHOWN_MATCHES {
+                lines.push(format!("    (and {} more)", found.len() - SHOWN_MATCHES));
+            }
+        }
+        Ok(lines.join("\n"))
+    }
+
+    /// Change the settings and search what they pick (keeping the old ones
+    /// if the page can't be searched with the new)
+    fn change(&mut self, apply: impl FnOnce(&mut SiteSettings)) -> Result<Step> {
+        let mut site = self.site.clone();
+        apply(&mut site);
+        self.content = domains::watched_part(&site, self.page.clone())?;
+        self.site = site;
+        Ok(Step::Print(format!("Searching {} characters", self.content.chars().count())))
+    }
+
+    fn show(&self, limit: usize) -> String {
+        let lines: Vec<&str> = self.content.lines().collect();
+        let mut shown = lines.iter().take(limit).copied().collect::<Vec<_>>().join("\n");
+        if lines.len() > limit {
+            shown.push_str(&format!("\n(… {} more lines)", lines.len() - limit));
+        }
+        shown
+    }
+
+    fn settings(&self) -> String {
+        [
+            format!("Selector:      {}", self.site.selector.as_deref().unwrap_or("(none)")),
+            format!("Main content:  {}", on_off(self.site.main_content)),
+            format!("Content:       {}", self.site.content.name()),
+            format!("Whole words:   {}", on_off(self.whole_word)),
+            format!("Searching {} of the page's {} characters", self.content.chars().count(), self.page.chars().count()),
+        ]
+        .join("\n")
+    }
+}
+
+/// Whether a keyword starts a `/.../` regex it doesn't end yet
+fn is_open_regex(text: &str) -> bool {
+    let text = text.trim();
+    text.starts_with('/') && (text.len() == 1 || !text.ends_with('/'))
+}
+
+/// The pattern of a `/.../` keyword
+fn regex_literal(text: &str) -> Option<&str> {
+    text.strip_prefix('/')?.strip_suffix('/').filter(|pattern| !pattern.is_empty())
+}
+
+fn parse_switch(value: &str) -> Result<bool> {
+    match value {
+        "on" => Ok(true),
+        "off" => Ok(false),
+        _ => anyhow::bail!("Use on or off"),
+    }
+}
+
+fn on_off(value: bool) -> &'static str {
+    if value {
+        "on"
+    } else {
+        "off"
+    }
+}
@@ -1,62 +1,694 @@
-/// Keyword matching module
-///
-/// Searches for keywords in content and returns matches with context
+//! Keyword matching module
+//!
+//! Searches for keywords in content and returns matches with context.
+//! All plain keywords are found in a single pass with an Aho-Corasick
+//! automaton, built once per watcher and reused for every check; regex
+//! keywords are compiled alongside it. The named groups of a regex keyword,
+//! like `(?P<price>\$\d+)`, are kept with each match for the alert (see
+//! `notify`).
+//!
+//! Keywords are stored as plain strings unless they have settings of their
+//! own (regex, whole word, priority, channels), so older configs and simple
+//! keyword lists keep their shape.
+
+use aho_corasick::{AhoCorasick, AhoCorasickBuilder};
+use regex::{Regex, RegexBuilder};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::notify::Channel;
+
+/// Characters of context kept on each side of a match
+const CONTEXT_CHARS: usize = 100;
+
+/// Maximum length of a cleaned-up context snippet (in characters)
+const MAX_CONTEXT_CHARS: usize = 200;
+
+/// How urgent alerts for a keyword are
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Priority {
+    /// Delivered without a sound
+    Low,
+    #[default]
+    Normal,
+    /// Listed first and marked in the notification title
+    High,
+}
+
+impl Priority {
+    pub fn name(self) -> &'static str {
+        match self {
+            Priority::Low => "low",
+            Priority::Normal => "normal",
+            Priority::High => "high",
+        }
+    }
+
+    pub fn parse(input: &str) -> anyhow::Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "low" => Ok(Priority::Low),
+            "normal" => Ok(Priority::Normal),
+            "high" => Ok(Priority::High),
+            other => anyhow::bail!("Unknown priority: {} (use low, normal or high)", other),
+        }
+    }
+}
+
+/// A keyword to search for, with its own matching and alert settings
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(from = "KeywordEntry", into = "KeywordEntry")]
+pub struct Keyword {
+    pub text: String,
+    /// `text` is a regular expression (matched case-insensitively)
+    pub is_regex: bool,
+    /// Only match whole words, so "sale" doesn't match "wholesale"
+    pub whole_word: bool,
+    pub priority: Priority,
+    /// Channels this keyword alerts through; None means all enabled channels
+    pub channels: Option<Vec<Channel>>,
+}
+
+/// How a keyword is stored: a plain string, or an object when it has
+/// settings of its own
+enum KeywordEntry {
+    Plain(String),
+    Detailed(KeywordFields),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct KeywordFields {
+    text: String,
+    #[serde(default, skip_serializing_if = "is_false")]
+    is_regex: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    whole_word: bool,
+    #[serde(default, skip_serializing_if = "is_normal")]
+    priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    channels: Option<Vec<Channel>>,
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+fn is_normal(priority: &Priority) -> bool {
+    *priority == Priority::Normal
+}
+
+impl Serialize for KeywordEntry {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            KeywordEntry::Plain(text) => text.serialize(serializer),
+            KeywordEntry::Detailed(fields) => fields.serialize(serializer),
+        }
+    }
+}
+
+// Written out instead of `#[serde(untagged)]` so mistakes in an object
+// (like a misspelled setting) get a precise error
+impl<'de> Deserialize<'de> for KeywordEntry {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct EntryVisitor;
+
+        impl<'de> Visitor<'de> for EntryVisitor {
+            type Value = KeywordEntry;
+
+            fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str("a keyword string or an object with a \"text\" field")
+            }
+
+            fn visit_str<E: de::Error>(self, text: &str) -> Result<KeywordEntry, E> {
+                Ok(KeywordEntry::Plain(text.to_string()))
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<KeywordEntry, A::Error> {
+                KeywordFields::deserialize(de::value::MapAccessDeserializer::new(map)).map(KeywordEntry::Detailed)
+            }
+        }
+
+        deserializer.deserialize_any(EntryVisitor)
+    }
+}
+
+impl From<KeywordEntry> for Keyword {
+    fn from(entry: KeywordEntry) -> Self {
+        match entry {
+            KeywordEntry::Plain(text) => Keyword::new(text),
+            KeywordEntry::Detailed(fields) => Keyword {
+                text: fields.text,
+                is_regex: fields.is_regex,
+                whole_word: fields.whole_word,
+                priority: fields.priority,
+                channels: fields.channels,
+            },
+        }
+    }
+}
+
+impl From<Keyword> for KeywordEntry {
+    fn from(keyword: Keyword) -> Self {
+        if keyword.is_plain() {
+            return KeywordEntry::Plain(keyword.text);
+        }
+        KeywordEntry::Detailed(KeywordFields {
+            text: keyword.text,
+            is_regex: keyword.is_regex,
+            whole_word: keyword.whole_word,
+            priority: keyword.priority,
+            channels: keyword.channels,
+        })
+    }
+}
+
+impl From<String> for Keyword {
+    fn from(text: String) -> Self {
+        Keyword::new(text)
+    }
+}
+
+impl From<&str> for Keyword {
+    fn from(text: &str) -> Self {
+        Keyword::new(text.to_string())
+    }
+}
+
+impl fmt::Display for Keyword {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.text)
+    }
+}
+
+impl Keyword {
+    /// A plain keyword with default settings
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            is_regex: false,
+            whole_word: false,
+            priority: Priority::Normal,
+            channels: None,
+        }
+    }
 
-#[derive(Debug, Clone)]
+    /// Whether the keyword has no settings besides its text
+    pub fn is_plain(&self) -> bool {
+        !self.is_regex && !self.whole_word && self.priority == Priority::Normal && self.channels.is_none()
+    }
+
+    /// The keyword with its settings, like "sold out (whole word, high, webhook)"
+    pub fn describe(&self) -> String {
+        let mut settings = Vec::new();
+        if self.is_regex {
+            settings.push(String::from("regex"));
+        }
+        if self.whole_word {
+            settings.push(String::from("whole word"));
+        }
+        if self.priority != Priority::Normal {
+            settings.push(self.priority.name().to_string());
+        }
+        if let Some(channels) = &self.channels {
+            let names: Vec<&str> = channels.iter().map(|c| c.name()).collect();
+            settings.push(if names.is_empty() { String::from("no alerts") } else { names.join("+") });
+        }
+
+        if settings.is_empty() {
+            self.text.clone()
+        } else {
+            format!("{} ({})", self.text, settings.join(", "))
+        }
+    }
+
+    /// Compile a regex keyword (case-insensitive, word-bounded if
+    /// `whole_word` is set)
+    pub fn compile(&self) -> Result<Regex, regex::Error> {
+        let pattern = if self.whole_word {
+            format!(r"\b(?:{})\b", self.text)
+        } else {
+            self.text.clone()
+        };
+        RegexBuilder::new(&pattern).case_insensitive(true).build()
+    }
+}
+
+/// The keywords' texts joined for display, like "sold out, restock"
+pub fn keyword_list(keywords: &[Keyword]) -> String {
+    keywords
+        .iter()
+        .map(|k| k.text.as_str())
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Keywords for edited keyword texts: a keyword whose text is unchanged
+/// keeps its settings, new ones start out plain
+pub fn keywords_from_texts(texts: Vec<String>, existing: &[Keyword]) -> Vec<Keyword> {
+    texts
+        .into_iter()
+        .map(|text| {
+            existing
+                .iter()
+                .find(|k| k.text == text)
+                .cloned()
+                .unwrap_or_else(|| Keyword::new(text))
+        })
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct KeywordMatch {
     pub keyword: String,
     pub context: String,
+    #[serde(default)]
+    pub priority: Priority,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub channels: Option<Vec<Channel>>,
+    /// The context translated, for matches on foreign-language pages
+    /// (see `translate`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub translation: Option<String>,
+    /// Values of the regex keyword's named groups, by name
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub captures: BTreeMap<String, String>,
+}
+
+impl KeywordMatch {
+    pub fn new(keyword: &Keyword, context: String) -> Self {
+        Self {
+            keyword: keyword.text.clone(),
+            context,
+            priority: keyword.priority,
+            channels: keyword.channels.clone(),
+            translation: None,
+            captures: BTreeMap::new(),
+        }
+    }
+
+    /// Whether this match should be alerted through `channel`
+    pub fn alerts_through(&self, channel: Channel) -> bool {
+        self.channels.as_ref().is_none_or(|channels| channels.contains(&channel))
+    }
+}
+
+/// A compiled set of keywords
+pub struct KeywordMatcher {
+    keywords: Vec<Keyword>,
+    /// Search automaton for the plain keywords; None when there are none
+    automaton: Option<AhoCorasick>,
+    /// For each automaton pattern, the index of the keyword it belongs to
+    pattern_keyword: Vec<usize>,
+    /// Compiled regex keywords with their index
+    regexes: Vec<(usize, Regex)>,
+    /// Non-ASCII plain keywords with their index, as Unicode
+    /// case-insensitive regexes of their text
+    folded: Vec<(usize, Regex)>,
+}
+
+impl KeywordMatcher {
+    /// Build the automaton for a watcher's keywords
+    ///
+    /// Matching is ASCII case-insensitive; non-ASCII keywords are searched
+    /// Unicode case-insensitively instead (the automaton only folds ASCII),
+    /// so "Ärger" also matches "ärger", "ÄRGER" and "äRGER". Regex keywords
+    /// that don't compile match nothing (`doctor` reports them).
+    pub fn new(keywords: &[Keyword]) -> Self {
+        let mut patterns: Vec<String> = Vec::new();
+        let mut pattern_keyword = Vec::new();
+        let mut regexes = Vec::new();
+        let mut folded = Vec::new();
+
+        for (index, keyword) in keywords.iter().enumerate() {
+            if keyword.text.is_empty() {
+                continue;
+            }
+
+            if keyword.is_regex {
+                if let Ok(regex) = keyword.compile() {
+                    regexes.push((index, regex));
+                }
+                continue;
+            }
+
+            if !keyword.text.is_ascii() {
+                if let Ok(regex) = RegexBuilder::new(&regex::escape(&keyword.text)).case_insensitive(true).build() {
+                    folded.push((index, regex));
+                }
+                continue;
+            }
+
+            patterns.push(keyword.text.clone());
+            pattern_keyword.push(index);
+        }
+
+        // Building only fails for absurdly large pattern sets; fall back to
+        // matching nothing rather than taking the watcher down
+        let automaton = if patterns.is_empty() {
+            None
+        } else {
+            AhoCorasickBuilder::new()
+                .ascii_case_insensitive(true)
+                .build(&patterns)
+                .ok()
+        };
+
+        Self {
+            keywords: keywords.to_vec(),
+            automaton,
+            pattern_keyword,
+            regexes,
+            folded,
+        }
+    }
+
+    /// Search for all keywords in content (case-insensitive)
+    /// Returns matches with surrounding context (up to 100 chars before/after),
+    /// grouped by keyword in configuration order
+    pub fn find(&self, content: &str) -> Vec<KeywordMatch> {
+        // (keyword index, start, end) of every occurrence
+        let mut hits: Vec<(usize, usize, usize)> = Vec::new();
+        if let Some(automaton) = &self.automaton {
+            hits.extend(
+                automaton
+                    .find_overlapping_iter(content)
+                    .map(|m| (self.pattern_keyword[m.pattern().as_usize()], m.start(), m.end()))
+                    .filter(|&(index, start, end)| {
+                        !self.keywords[index].whole_word || is_whole_word(content, start, end)
+                    }),
+            );
+        }
+        for (index, regex) in &self.folded {
+            hits.extend(
+                regex
+                    .find_iter(content)
+                    .map(|m| (*index, m.start(), m.end()))
+                    .filter(|&(index, start, end)| {
+                        !self.keywords[index].whole_word || is_whole_word(content, start, end)
+                    }),
+            );
+        }
+        for (index, regex) in &self.regexes {
+            hits.extend(
+                regex
+                    .find_iter(content)
+                    .filter(|m| !m.is_empty())
+                    .map(|m| (*index, m.start(), m.end())),
+            );
+        }
+        hits.sort_unstable();
+
+        let mut matches = Vec::new();
+        let mut last: Option<(usize, usize)> = None; // (keyword index, end of last match)
+        for (keyword_index, start, end) in hits {
+            // Occurrences of the same keyword don't overlap (and variants of
+            // a keyword matching at the same spot count once)
+            if let Some((last_keyword, last_end)) = last {
+                if last_keyword == keyword_index && start < last_end {
+                    continue;
+                }
+            }
+            last = Some((keyword_index, end));
+
+            let mut found = KeywordMatch::new(&self.keywords[keyword_index], extract_context(content, start, end));
+            if let Some((_, regex)) = self.regexes.iter().find(|(index, _)| *index == keyword_index) {
+                found.captures = named_captures(regex, content, start);
+            }
+            matches.push(found);
+        }
+
+        matches
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Whether the match at `start..end` isn't part of a longer word. Only the
+/// match's word characters need a boundary, so "$5" still matches "a $5 fee".
+fn is_whole_word(content: &str, start: usize, end: usize) -> bool {
+    let matched = &content[start..end];
+    let starts_word = matched.chars().next().is_some_and(is_word_char);
+    let ends_word = matched.chars().next_back().is_some_and(is_word_char);
+
+    let joined_before = starts_word && content[..start].chars().next_back().is_some_and(is_word_char);
+    let joined_after = ends_word && content[end..].chars().next().is_some_and(is_word_char);
+    !joined_before && !joined_after
+}
+
+/// Values of the named groups of the regex match at `start`, with their
+/// whitespace collapsed (groups that didn't take part are left out)
+fn named_captures(regex: &Regex, content: &str, start: usize) -> BTreeMap<String, String> {
+    let Some(captures) = regex.captures_at(content, start) else {
+        return BTreeMap::new();
+    };
+    regex
+        .capture_names()
+        .flatten()
+        .filter_map(|name| {
+            let value = captures.name(name)?.as_str().split_whitespace().collect::<Vec<_>>().join(" ");
+            Some((name.to_string(), value))
+        })
+        .collect()
 }
 
 /// Search for keywords in content (case-insensitive)
-/// Returns matches with surrounding context (up to 100 chars before/after)
-pub fn find_keywords(content: &str, keywords: &[String]) -> Vec<KeywordMatch> {
-    let mut matches = Vec::new();
-    let content_lower = content.to_lowercase();
-
-    for keyword in keywords {
-        let keyword_lower = keyword.to_lowercase();
-
-        // Find all occurrences of this keyword
-        let mut start = 0;
-        while let Some(pos) = content_lower[start..].find(&keyword_lower) {
-            let absolute_pos = start + pos;
-
-            // Extract context around the match
-            let context_start = absolute_pos.saturating_sub(100);
-            let context_end = (absolute_pos + keyword.len() + 100).min(content.len());
-            let context = &content[context_start..context_end];
-
-            // Clean up the context (remove extra whitespace, newlines)
-            let context_cleaned = context
-                .lines()
-                .map(|line| line.trim())
-                .filter(|line| !line.is_empty())
-                .collect::<Vec<_>>()
-                .join(" ");
-
-            // Truncate if too long and add ellipsis
-            let context_display = if context_cleaned.len() > 200 {
-                format!("...{}...", &context_cleaned[..197])
-            } else if context_start > 0 && context_end < content.len() {
-                format!("...{}...", context_cleaned)
-            } else if context_start > 0 {
-                format!("{}...", context_cleaned)
-            } else if context_end < content.len() {
-                format!("...{}", context_cleaned)
-            } else {
-                context_cleaned
+/// Convenience wrapper for one-off searches; watchers keep a `KeywordMatcher`.
+pub fn find_keywords(content: &str, keywords: &[Keyword]) -> Vec<KeywordMatch> {
+    KeywordMatcher::new(keywords).find(content)
+}
+
+/// Context around the match at `start..end`, cleaned up for display
+fn extract_context(content: &str, start: usize, end: usize) -> String {
+    // Byte range covering up to CONTEXT_CHARS characters on each side
+    let context_start = content[..start]
+        .char_indices()
+        .rev()
+        .nth(CONTEXT_CHARS - 1)
+        .map_or(0, |(i, _)| i);
+    let context_end = content[end..]
+        .char_indices()
+        .nth(CONTEXT_CHARS)
+        .map_or(content.len(), |(i, _)| end + i);
+    let context = &content[context_start..context_end];
+
+    // Clean up the context (remove extra whitespace, newlines)
+    let context_cleaned = context
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+
+    // Truncate if too long and add ellipsis
+    if context_cleaned.chars().count() > MAX_CONTEXT_CHARS {
+        let cut: String = context_cleaned.chars().take(MAX_CONTEXT_CHARS - 3).collect();
+        format!("...{}...", cut)
+    } else if context_start > 0 && context_end < content.len() {
+        format!("...{}...", context_cleaned)
+    } else if context_start > 0 {
+        format!("{}...", context_cleaned)
+    } else if context_end < content.len() {
+        format!("...{}", context_cleaned)
+    } else {
+        context_cleaned
+    }
+}
+
+/// Longest match (in bytes) a regex keyword can have in a streamed body
+const STREAM_REGEX_SPAN: usize = 256;
+
+/// Case-insensitive keyword search over a body that arrives in chunks
+///
+/// Used for pages too large to keep in memory: it only records which
+/// keywords appear (no context). Plain keywords fold ASCII case only;
+/// regex keywords find matches of up to `STREAM_REGEX_SPAN` bytes.
+pub struct StreamSearch {
+    keywords: Vec<StreamKeyword>,
+    /// End of the previous chunk, so matches spanning two chunks are found
+    tail: Vec<u8>,
+    /// Length of the tail to keep
+    overlap: usize,
+    /// Whether the tail still starts at the beginning of the body
+    at_start: bool,
+}
+
+struct StreamKeyword {
+    text: String,
+    needle: Needle,
+    whole_word: bool,
+    found: bool,
+}
+
+enum Needle {
+    /// Lowercased bytes of a plain keyword
+    Bytes(Vec<u8>),
+    Regex(Regex),
+}
+
+impl StreamSearch {
+    pub fn new(keywords: &[Keyword]) -> Self {
+        let keywords: Vec<StreamKeyword> = keywords
+            .iter()
+            .filter(|k| !k.text.is_empty())
+            .filter_map(|k| {
+                let needle = if k.is_regex {
+                    Needle::Regex(k.compile().ok()?)
+                } else if !k.text.is_ascii() {
+                    // The window is only lowercased for ASCII
+                    let escaped = Keyword {
+                        text: regex::escape(&k.text),
+                        ..k.clone()
+                    };
+                    Needle::Regex(escaped.compile().ok()?)
+                } else {
+                    Needle::Bytes(k.text.to_ascii_lowercase().into_bytes())
+                };
+                Some(StreamKeyword {
+                    text: k.text.clone(),
+                    needle,
+                    whole_word: k.whole_word,
+                    found: false,
+                })
+            })
+            .collect();
+
+        // One byte more than the longest match, so whole-word checks can
+        // see the byte before a match that was cut off
+        let longest = keywords
+            .iter()
+            .map(|k| match &k.needle {
+                Needle::Bytes(bytes) => bytes.len(),
+                Needle::Regex(_) => STREAM_REGEX_SPAN,
+            })
+            .max()
+            .unwrap_or(0);
+
+        Self {
+            keywords,
+            tail: Vec::new(),
+            overlap: longest + 1,
+            at_start: true,
+        }
+    }
+
+    /// Scan the next chunk of the body
+    pub fn feed(&mut self, chunk: &[u8]) {
+        if self.keywords.is_empty() || chunk.is_empty() {
+            return;
+        }
+
+        let mut window = std::mem::take(&mut self.tail);
+        window.extend(chunk.iter().map(|b| b.to_ascii_lowercase()));
+        self.scan(&window, false);
+
+        // Keep just enough of the end to catch a keyword split across chunks
+        let keep = self.overlap.min(window.len());
+        if keep < window.len() {
+            self.at_start = false;
+        }
+        self.tail = window.split_off(window.len() - keep);
+    }
+
+    /// Look for the keywords not found yet. A whole-word match that ends the
+    /// window is only certain at the end of the body; until then it's left
+    /// for the next window, which still has it in the tail.
+    fn scan(&mut self, window: &[u8], at_end: bool) {
+        let mut text = None;
+        for keyword in self.keywords.iter_mut().filter(|k| !k.found) {
+            keyword.found = match &keyword.needle {
+                Needle::Bytes(needle) => window
+                    .windows(needle.len())
+                    .enumerate()
+                    .filter(|(_, w)| *w == needle.as_slice())
+                    .any(|(start, _)| {
+                        !keyword.whole_word
+                            || is_whole_word_bytes(window, start, start + needle.len(), self.at_start, at_end)
+                    }),
+                Needle::Regex(regex) => {
+                    let text = text.get_or_insert_with(|| String::from_utf8_lossy(window));
+                    regex.find_iter(text).any(|m| !m.is_empty())
+                }
             };
+        }
+    }
+
+    /// Keywords seen in the whole body, in configuration order
+    pub fn found_keywords(mut self) -> Vec<String> {
+        let tail = std::mem::take(&mut self.tail);
+        self.scan(&tail, true);
+
+        self.keywords
+            .into_iter()
+            .filter(|k| k.found)
+            .map(|k| k.text)
+            .collect()
+    }
+}
+
+fn is_word_byte(byte: u8) -> bool {
+    // Bytes of multi-byte characters count as letters
+    byte.is_ascii_alphanumeric() || byte == b'_' || byte >= 0x80
+}
+
+/// `is_whole_word` for a window of a streamed body. Byte 0 is only a
+/// boundary at the start of the body (a match there was checked with the
+/// byte before it in an earlier window), and the window's end only at the
+/// end of the body.
+fn is_whole_word_bytes(window: &[u8], start: usize, end: usize, at_start: bool, at_end: bool) -> bool {
+    let before_ok = if !is_word_byte(window[start]) {
+        true
+    } else if start == 0 {
+        at_start
+    } else {
+        !is_word_byte(window[start - 1])
+    };
+    let after_ok = if !is_word_byte(window[end - 1]) {
+        true
+    } else if end == window.len() {
+        at_end
+    } else {
+        !is_word_byte(window[end])
+    };
+    before_ok && after_ok
+}
 
-            matches.push(KeywordMatch {
-                keyword: keyword.clone(),
-                context: context_display,
-            });
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-            // Move past this match to find next occurrence
-            start = absolute_pos + keyword.len();
+    fn keyword(text: &str, whole_word: bool) -> Keyword {
+        Keyword {
+            whole_word,
+            ..Keyword::new(text.to_string())
         }
     }
 
-    matches
+    fn found(keywords: &[Keyword], content: &str) -> Vec<String> {
+        KeywordMatcher::new(keywords).find(content).into_iter().map(|m| m.keyword).collect()
+    }
+
+    #[test]
+    fn non_ascii_keywords_match_in_any_case() {
+        let keywords = [keyword("Ärger", false), keyword("été", false)];
+        assert_eq!(found(&keywords, "Viel äRGER im Sommer"), ["Ärger"]);
+        assert_eq!(found(&keywords, "Un ÉtÉ chaud, l'ÉTÉ"), ["été", "été"]);
+        assert!(found(&keywords, "Arger, ete").is_empty());
+
+        let mut stream = StreamSearch::new(&keywords);
+        stream.feed("Viel ä".as_bytes());
+        stream.feed("RGER im Sommer".as_bytes());
+        assert_eq!(stream.found_keywords(), ["Ärger"]);
+    }
+
+    #[test]
+    fn whole_word_applies_to_non_ascii_keywords() {
+        let keywords = [keyword("Öl", true)];
+        assert_eq!(found(&keywords, "Das ÖL ist da"), ["Öl"]);
+        assert!(found(&keywords, "Speiseöl").is_empty());
+    }
 }
@@ -0,0 +1,169 @@
+//! Matrix watchers
+//!
+//! A matrix watches the same keywords on every combination of a list of URL
+//! templates and a list of values, like a product ID on several shops:
+//!
+//! ```json
+//! "matrices": [{
+//!   "name": "gpus",
+//!   "urls": ["https://shop-a.example/p/{value}", "https://shop-b.example/item/{value}"],
+//!   "values": ["rtx-5090", "rtx-5080"],
+//!   "keywords": [{"text": "in stock"}],
+//!   "check_interval": 1800
+//! }]
+//! ```
+//!
+//! `expand` turns it into child watchers (one per URL), marked with the
+//! matrix's name: they're checked like any other watcher, but the TUI folds
+//! them into one row and they share the matrix's keywords and interval.
+//! Changing the matrix and expanding it again adds, updates and removes
+//! children to match.
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::matcher::Keyword;
+use crate::watcher::{normalize_url, Watcher};
+
+/// Where a URL template takes the value
+pub const PLACEHOLDER: &str = "{value}";
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Matrix {
+    /// Identifies the matrix on the command line and in its children
+    pub name: String,
+    /// URL templates with `{value}` in them
+    pub urls: Vec<String>,
+    /// Values put into every URL template
+    pub values: Vec<String>,
+    /// Keywords of every child
+    #[serde(default)]
+    pub keywords: Vec<Keyword>,
+    /// Keyword sets of every child
+    #[serde(default)]
+    pub keyword_sets: Vec<String>,
+    /// Interval of every child (in seconds)
+    #[serde(with = "crate::watcher::duration_serde")]
+    pub check_interval: Duration,
+}
+
+impl Matrix {
+    /// The URLs of the children: each template with each value, in order
+    pub fn child_urls(&self) -> Result<Vec<String>> {
+        let mut urls = Vec::new();
+        for template in &self.urls {
+            if !template.contains(PLACEHOLDER) {
+                anyhow::bail!("The URL {} has no {} for the values", template, PLACEHOLDER);
+            }
+            for value in &self.values {
+                let url = normalize_url(&template.replace(PLACEHOLDER, value.trim()))
+                    .with_context(|| format!("Invalid URL for the value {}", value))?;
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+        }
+        Ok(urls)
+    }
+}
+
+/// What expanding a matrix changed
+#[derive(Debug, Default)]
+pub struct Expanded {
+    pub added: usize,
+    pub updated: usize,
+    /// Children whose URL the matrix no longer makes (taken out of the
+    /// config; their state and history are the caller's to discard)
+    pub removed: Vec<Watcher>,
+}
+
+/// Make the matrix's children match it (not saved). Fails without changing
+/// anything if the matrix is invalid or one of its URLs already has a
+/// watcher of its own.
+pub fn expand(config: &mut Config, name: &str) -> Result<Expanded> {
+    let matrix = config
+        .matrices
+        .iter()
+        .find(|m| m.name == name)
+        .cloned()
+        .with_context(|| format!("No matrix named {}", name))?;
+    if matrix.values.iter().all(|value| value.trim().is_empty()) {
+        anyhow::bail!("The matrix {} has no values", name);
+    }
+    if matrix.keywords.is_empty() && matrix.keyword_sets.is_empty() {
+        anyhow::bail!("The matrix {} has no keywords", name);
+    }
+    config.check_keyword_sets(&matrix.keyword_sets)?;
+    let urls = matrix.child_urls()?;
+
+    for url in &urls {
+        if let Some(index) = config.find_watcher_by_url(url) {
+            if config.watchers[index].matrix.as_deref() != Some(name) {
+                anyhow::bail!("{} already has a watcher outside the matrix: remove it first", url);
+            }
+        }
+        if let Some(warning) = config.guardrails.check_interval(url, matrix.check_interval) {
+            anyhow::bail!(warning);
+        }
+    }
+
+    let mut expanded = Expanded::default();
+    for url in &urls {
+        match config.find_watcher_by_url(url) {
+            Some(index) => {
+                let child = &mut config.watchers[index];
+                if child.keywords != matrix.keywords
+                    || child.keyword_sets != matrix.keyword_sets
+                    || child.check_interval != matrix.check_interval
+                {
+                    child.keywords = matrix.keywords.clone();
+                    child.keyword_sets = matrix.keyword_sets.clone();
+                    child.check_interval = matrix.check_interval;
+                    expanded.updated += 1;
+                }
+            }
+            None => {
+                let mut child = Watcher::new(url.clone(), matrix.keywords.clone(), matrix.check_interval);
+                child.keyword_sets = matrix.keyword_sets.clone();
+                child.matrix = Some(name.to_string());
+                // Next to the other children, so the matrix stays together
+                let at = config
+                    .watchers
+                    .iter()
+                    .rposition(|w| w.matrix.as_deref() == Some(name))
+                    .map_or(config.watchers.len(), |last| last + 1);
+                config.watchers.insert(at, child);
+                expanded.added += 1;
+            }
+        }
+    }
+
+    let mut i = 0;
+    while i < config.watchers.len() {
+        let watcher = &config.watchers[i];
+        if watcher.matrix.as_deref() == Some(name) && !urls.contains(&watcher.url) {
+            expanded.removed.push(config.watchers.remove(i));
+        } else {
+            i += 1;
+        }
+    }
+    Ok(expanded)
+}
+
+/// Remove a matrix and its children (not saved). Returns the children.
+pub fn remove(config: &mut Config, name: &str) -> Result<Vec<Watcher>> {
+    let index = config
+        .matrices
+        .iter()
+        .position(|m| m.name == name)
+        .with_context(|| format!("No matrix named {}", name))?;
+    config.matrices.remove(index);
+
+    let (children, others) = std::mem::take(&mut config.watchers)
+        .into_iter()
+        .partition(|w| w.matrix.as_deref() == Some(name));
+    config.watchers = others;
+    Ok(children)
+}
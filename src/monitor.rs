@@ -1,164 +1,2271 @@
-/// Background monitoring engine
-///
-/// Manages async tasks that periodically check each enabled watcher
+//! Background monitoring engine
+//!
+//! Manages async tasks that periodically check each enabled watcher.
+//! Tasks that panic are restarted with backoff, and tasks that stop making
+//! progress are restarted by the watchdog (see `watchdog`). Checks pause
+//! while the network is offline or metered (see `network`), and
+//! non-critical watchers slow down on a low battery (see `power`). When
+//! enabled, new releases of the app are looked for (see `update`).
+//!
+//! The TUI runs the monitor in its own process and edits the config it
+//! runs with through a `LiveConfig`: watchers that are added, enabled,
+//! disabled, removed or changed are started, stopped or restarted as the
+//! edits come in.
+//!
+//! The outcome of every check is also published on an `EventBus` (see
+//! `events`), which the TUI's dashboard subscribes to.
+//!
+//! Streaming watchers (see `stream`) aren't checked: their task stays
+//! connected and matches each message as it arrives.
 
 use anyhow::{Context, Result};
-use chrono::Utc;
+use chrono::{DateTime, Utc};
+use futures_util::FutureExt;
+use std::any::Any;
+use std::collections::HashSet;
+use std::panic::AssertUnwindSafe;
+use std::path::Path;
 use std::sync::Arc;
-use tokio::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc::UnboundedReceiver, watch, Notify, RwLock};
+use tokio::task::JoinHandle;
 use tokio::time::sleep;
 
-use crate::{cache, config::Config, diff, fetcher, matcher, notify, watcher::Watcher};
+use crate::{
+    cache,
+    calendar::{self, CalendarAction},
+    cluster, companion,
+    config::{Config, EncryptionSettings, HistoryRetention, NotificationSettings},
+    diff,
+    domains::{self, SiteSettings},
+    events::{EventBus, EventLog, Level, LogFormat, MonitorEvent},
+    fetcher::{self, BlockedError, Fetched, HttpStatusError, Page, PageDigest, ResponseMeta},
+    history::{self, CheckOutcome, CheckRecord},
+    hook::{self, HookRun},
+    latency,
+    lease::{self, Claim},
+    logfiles::{self, WatcherFiles},
+    matcher::{KeywordMatch, KeywordMatcher},
+    network::{self, Connectivity},
+    notify::{self, Changes},
+    outbox,
+    power,
+    review,
+    state::WatcherState,
+    stream::{self, Stream},
+    timezone::Zone,
+    translate,
+    trash,
+    trigger::{self, Triggers},
+    update,
+    values::{self, Trend},
+    watchdog::{self, Heartbeat},
+    watcher::{format_expiry, format_interval, Watcher, MAX_CONSENSUS_FETCHES},
+};
+
+/// Pause between the fetches of a consensus check
+const CONSENSUS_DELAY: Duration = Duration::from_secs(2);
+
+/// Wait before restarting a crashed watcher (doubled for each crash in a row)
+const RESTART_BACKOFF: Duration = Duration::from_secs(10);
+
+/// Longest wait before restarting a crashed watcher. A watcher that ran
+/// this long before crashing starts over at the shortest wait.
+const MAX_RESTART_BACKOFF: Duration = Duration::from_secs(60 * 60);
+
+/// How often the network is checked for going offline/metered and back
+const NETWORK_POLL_INTERVAL: Duration = Duration::from_secs(15);
+
+/// How often the battery is checked
+const POWER_POLL_INTERVAL: Duration = Duration::from_secs(60);
+
+/// How often the outbox is looked at for alerts due for another try
+const OUTBOX_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Wait before reconnecting a stream (doubled for each failure in a row,
+/// unless the server asks for another wait)
+const STREAM_RETRY: Duration = Duration::from_secs(5);
+
+/// Longest wait before reconnecting a stream
+const MAX_STREAM_RETRY: Duration = Duration::from_secs(5 * 60);
+
+/// How often the update check runs (it only fetches the release feed once
+/// per `update_check.interval`)
+const UPDATE_POLL_INTERVAL: Duration = Duration::from_secs(60 * 60);
 
 pub struct Monitor {
-    config: Arc<Mutex<Config>>,
+    config: Arc<RwLock<Config>>,
+    /// Signalled when the config is edited through a `LiveConfig`
+    config_changed: Arc<Notify>,
+    /// Keep running without watchers, waiting for some to be enabled
+    live: bool,
+    /// Where progress is reported (stdout for the daemon, or the TUI)
+    log: EventLog,
+    /// Where check outcomes are published
+    bus: EventBus,
+    /// Current connectivity, shared with the watcher tasks
+    connectivity: watch::Sender<Connectivity>,
+    /// Whether battery saving is on, shared with the watcher tasks
+    battery_saving: watch::Sender<bool>,
+    /// Why saving the config last failed (None while saves succeed)
+    save_failure: watch::Sender<Option<String>>,
+    /// Wakes the watcher tasks for checks triggered from outside
+    triggers: Triggers,
+}
+
+/// Conditions shared by all watcher tasks that pause or slow down checks
+#[derive(Clone)]
+struct Conditions {
+    connectivity: watch::Receiver<Connectivity>,
+    battery_saving: watch::Receiver<bool>,
+    /// Signalled to check the watcher now (see `trigger`)
+    wake: Arc<Notify>,
+}
+
+/// The outcome of a check run by `Monitor::check_once`
+#[derive(Debug)]
+pub struct CheckReport {
+    pub url: String,
+    /// The page differs from the cached copy
+    pub changed: bool,
+    /// Keywords that were found and notified (empty if none)
+    pub matched_keywords: Vec<String>,
+    /// Why the check failed
+    pub error: Option<String>,
+    /// The check disabled the watcher (a one-shot match, or the page is gone)
+    pub disabled: bool,
+    /// The page wasn't fetched: this is the result of the check at this
+    /// time, within the watcher's `min_recheck_gap`
+    pub cached_at: Option<DateTime<Utc>>,
+}
+
+/// A running monitor's config, shared with the TUI so edits apply live
+#[derive(Clone)]
+pub struct LiveConfig {
+    config: Arc<RwLock<Config>>,
+    changed: Arc<Notify>,
+}
+
+/// A running watcher task
+struct WatcherTask {
+    watcher_id: String,
+    url: String,
+    /// The settings the task runs with (see `task_definition`)
+    definition: String,
+    handle: JoinHandle<()>,
+    heartbeat: Heartbeat,
 }
 
 impl Monitor {
+    /// A monitor that prints its progress to stdout/stderr
     pub fn new(config: Config) -> Self {
+        let log = EventLog::stdout(config.timezone, LogFormat::Text);
+        Self::with_event_log(config, log)
+    }
+
+    /// A monitor that reports its progress to the given event log (and,
+    /// if the config says so, to the watchers' log files and the events
+    /// file)
+    pub fn with_event_log(config: Config, log: EventLog) -> Self {
+        let log = match WatcherFiles::from_settings(&config.logging) {
+            Ok(Some(files)) => log.watcher_files(files),
+            Ok(None) => log,
+            Err(e) => {
+                log.warning(format!("⚠ Watcher log files are off: {:#}", e));
+                log
+            }
+        };
+        if let Err(e) = logfiles::open_events(&config.logging) {
+            log.warning(format!("⚠ The events file is off: {:#}", e));
+        }
         Self {
-            config: Arc::new(Mutex::new(config)),
+            config: Arc::new(RwLock::new(config)),
+            config_changed: Arc::new(Notify::new()),
+            live: false,
+            log,
+            bus: EventBus::default(),
+            connectivity: watch::channel(Connectivity::Online).0,
+            battery_saving: watch::channel(false).0,
+            save_failure: watch::channel(None).0,
+            triggers: Triggers::default(),
         }
     }
 
+    /// A monitor for the TUI: it keeps running when no watcher is enabled,
+    /// and follows the edits made through `live_config`
+    pub fn live(config: Config, log: EventLog) -> Self {
+        Self {
+            live: true,
+            ..Self::with_event_log(config, log)
+        }
+    }
+
+    /// Shared access to the config the monitor runs with
+    pub fn live_config(&self) -> LiveConfig {
+        LiveConfig {
+            config: Arc::clone(&self.config),
+            changed: Arc::clone(&self.config_changed),
+        }
+    }
+
+    /// Receive the outcome of every check from now on
+    pub fn subscribe(&self) -> UnboundedReceiver<MonitorEvent> {
+        self.bus.subscribe()
+    }
+
+    /// Verify (and repair) the caches of the enabled watchers, logging what
+    /// was wrong and a summary
+    fn verify_caches(&self, watchers: &[Watcher], encryption: &EncryptionSettings) {
+        let mut verified = 0;
+        let mut repaired = 0;
+        for watcher in watchers.iter().filter(|w| w.is_active() && !w.is_stream()) {
+            // A watcher whose last check succeeded has a cache
+            let checked = watcher.last_checked.is_some() && watcher.consecutive_errors == 0;
+            let problems = watcher
+                .full_cache_path()
+                .and_then(|path| cache::verify(&path, checked, encryption));
+            verified += 1;
+            match problems {
+                Ok(problems) if problems.is_empty() => {}
+                Ok(problems) => {
+                    repaired += 1;
+                    for problem in problems {
+                        self.log
+                            .for_watcher(&watcher.url)
+                            .with_file(&watcher.id)
+                            .warning(format!("⚠ {}: {}", watcher.url, problem.describe()));
+                    }
+                }
+                Err(e) => self.log.error(format!("✗ {}: failed to repair the cache: {:#}", watcher.url, e)),
+            }
+        }
+        if repaired > 0 {
+            self.log.warning(format!("⚠ Caches verified: {} of {} had problems", repaired, verified));
+        } else if verified > 0 {
+            self.log.info(format!("Caches of {} watcher(s) verified", verified));
+        }
+    }
+
+    /// Replace the cached copies of pages by their fingerprints (data
+    /// minimization was turned on since they were cached)
+    fn minimize_caches(&self, watchers: &[Watcher], encryption: &EncryptionSettings) {
+        let mut minimized = 0;
+        for watcher in watchers {
+            let result = watcher.full_cache_path().and_then(|path| match cache::read_cache(&path)? {
+                Some(content) => cache::write_digest(&path, &diff::fingerprint(&content), encryption).map(|()| true),
+                None => Ok(false),
+            });
+            match result {
+                Ok(true) => minimized += 1,
+                Ok(false) => {}
+                Err(e) => self.log.error(format!("✗ {}: failed to minimize the cache: {:#}", watcher.url, e)),
+            }
+        }
+        if minimized > 0 {
+            self.log.info(format!("Replaced {} cached page(s) by their fingerprints", minimized));
+        }
+        // Queued changes are parts of pages too
+        match review::clear() {
+            Ok(0) => {}
+            Ok(cleared) => self.log.info(format!("Dropped {} change(s) waiting for review", cleared)),
+            Err(e) => self.log.error(format!("✗ Failed to empty the review queue: {:#}", e)),
+        }
+    }
+
+    /// Follow whether the config can be saved (for showing it in the TUI)
+    pub fn save_failure(&self) -> watch::Receiver<Option<String>> {
+        self.save_failure.subscribe()
+    }
+
     /// Start monitoring all enabled watchers
     /// This will spawn a task for each watcher and run until interrupted
     pub async fn start(&self) -> Result<()> {
-        let watchers = {
-            let config = self.config.lock().await;
-            config.watchers.clone()
+        let (watchers, watch_network, watch_battery, startup_summary, companion, triggers, trash_days, storage, notifications) = {
+            let config = self.config.read().await;
+            (
+                config.watchers.clone(),
+                config.network.enabled(),
+                config.battery.enabled,
+                config.notifications.startup_summary,
+                config.companion.enabled,
+                config.triggers.enabled,
+                config.trash.days,
+                Storage::of(&config),
+                config.notifications.clone(),
+            )
         };
-
-        if watchers.is_empty() {
-            println!("No watchers configured. Add some watchers first!");
-            return Ok(());
+        let coordinator = notifications.cluster.role == Some(cluster::Role::Coordinator);
+        // Forget the watchers deleted long enough ago
+        if let Err(e) = trash::purge(trash_days) {
+            self.log.warning(format!("⚠ Failed to empty the trash: {:#}", e));
+        }
+        // Thin out the check histories (they're also pruned as they grow,
+        // but days pass without checks too)
+        let mut pruned = 0;
+        for watcher in &watchers {
+            match history::prune(&watcher.id, &storage.retention, false) {
+                Ok(result) => pruned += result.removed,
+                Err(e) => self.log.warning(format!("⚠ Failed to prune the history of {}: {:#}", watcher.url, e)),
+            }
         }
+        if pruned > 0 {
+            self.log.info(format!("Pruned {} old check record(s) from the history", pruned));
+        }
+        self.verify_caches(&watchers, &storage.encryption);
+        if storage.minimize {
+            self.minimize_caches(&watchers, &storage.encryption);
+        }
+        // Watchers added through the companion endpoint are started as they
+        // come, so the monitor waits for them like a live one. A coordinator
+        // keeps serving its agents.
+        let keep_running = self.live || companion || coordinator;
 
         let enabled_watchers: Vec<_> = watchers
-            .into_iter()
-            .filter(|w| w.enabled)
+            .iter()
+            .filter(|w| w.is_active())
+            .cloned()
             .collect();
 
+        if watchers.is_empty() {
+            self.log.info("No watchers configured. Add some watchers first!");
+        } else if enabled_watchers.is_empty() {
+            self.log.info("No enabled watchers. Enable at least one watcher to start monitoring.");
+        }
         if enabled_watchers.is_empty() {
-            println!("No enabled watchers. Enable at least one watcher to start monitoring.");
-            return Ok(());
+            if !keep_running {
+                return Ok(());
+            }
+        } else {
+            self.log.info(format!("Starting monitoring for {} watchers...", enabled_watchers.len()));
         }
 
-        println!("[{}] Starting monitoring for {} watchers...", Utc::now().format("%Y-%m-%d %H:%M:%S"), enabled_watchers.len());
-        println!("Press Ctrl+C to stop.\n");
-
         // Log each watcher being started
         for watcher in &enabled_watchers {
-            println!("[{}] Watcher: {} | Keywords: {} | Interval: {}min",
-                Utc::now().format("%Y-%m-%d %H:%M:%S"),
-                watcher.url,
-                watcher.keywords.join(", "),
-                watcher.check_interval.as_secs() / 60);
+            self.log_start(watcher);
         }
-        println!();
+
+        if startup_summary && !enabled_watchers.is_empty() {
+            self.send_startup_summary(&enabled_watchers).await;
+        }
+
+        // Pause checks while offline/metered
+        let network_task = watch_network.then(|| {
+            tokio::spawn(watch_connectivity(
+                Arc::clone(&self.config),
+                self.connectivity.clone(),
+                self.log.clone(),
+            ))
+        });
+
+        // Slow down non-critical watchers on a low battery
+        let power_task = watch_battery.then(|| {
+            tokio::spawn(watch_power(
+                Arc::clone(&self.config),
+                self.battery_saving.clone(),
+                self.log.clone(),
+            ))
+        });
+
+        // Look for new releases (the setting is read each time, so it can
+        // be switched on while running)
+        let update_task = tokio::spawn(watch_updates(Arc::clone(&self.config), self.log.clone()));
+
+        // Quiet and paused periods from calendars: read before the watchers
+        // start (unless that hangs), then kept up to date
+        let calendar_task = if self.config.read().await.calendars.is_empty() {
+            None
+        } else {
+            let (read, first_read) = tokio::sync::oneshot::channel();
+            let task = tokio::spawn(calendar::watch(Arc::clone(&self.config), self.log.clone(), read));
+            if tokio::time::timeout(calendar::FIRST_READ, first_read).await.is_err() {
+                self.log.warning("⚠ The calendars are still being read: watchers start without them");
+            }
+            Some(task)
+        };
+
+        // Retry alerts that couldn't be delivered
+        let outbox_task = tokio::spawn(watch_outbox(Arc::clone(&self.config), self.log.clone()));
+
+        // Add watchers sent by a browser extension or bookmarklet
+        let companion_task = companion.then(|| {
+            tokio::spawn(companion::serve(
+                Arc::clone(&self.config),
+                Arc::clone(&self.config_changed),
+                self.log.clone(),
+            ))
+        });
+
+        // Check watchers when other systems say so
+        let trigger_task = triggers.then(|| {
+            tokio::spawn(trigger::serve(Arc::clone(&self.config), self.triggers.clone(), self.log.clone()))
+        });
+
+        // Count the checks the log leaves out
+        let summary_interval = self.config.read().await.logging.summary_interval;
+        let summary_task = (self.log.level() > Level::Info && !summary_interval.is_zero())
+            .then(|| tokio::spawn(log_summaries(self.bus.subscribe(), summary_interval, self.log.clone())));
+
+        // Deliver the alerts of other machines
+        let cluster_task = coordinator.then(|| tokio::spawn(cluster::serve(notifications, self.log.clone())));
 
         // Spawn a task for each watcher
-        let mut handles = Vec::new();
+        let mut tasks = Vec::new();
         for watcher in enabled_watchers {
-            let config = Arc::clone(&self.config);
-            let handle = tokio::spawn(async move {
-                monitor_watcher(watcher, config).await
-            });
-            handles.push(handle);
+            tasks.push(self.spawn(watcher).await);
         }
 
-        // Wait for all tasks (they run indefinitely)
-        for handle in handles {
-            let _ = handle.await;
+        // Watch over the tasks until they have all finished (they only stop
+        // when their watcher expires or disables itself). A live monitor
+        // runs until it's shut down.
+        let mut last_ping: Option<Instant> = None;
+        while keep_running || !tasks.is_empty() {
+            tokio::select! {
+                _ = sleep(watchdog::POLL_INTERVAL) => {}
+                _ = self.config_changed.notified() => {
+                    self.reconcile(&mut tasks).await;
+                    continue;
+                }
+            }
+            tasks.retain(|task| !task.handle.is_finished());
+
+            let mut healthy = true;
+            for task in &mut tasks {
+                if let Some(stuck_for) = task.heartbeat.overdue() {
+                    healthy = false;
+                    self.restart_stuck(task, stuck_for).await;
+                }
+            }
+
+            // Tell the uptime service we're alive (only when nothing is stuck)
+            if healthy {
+                self.ping_uptime_service(&mut last_ping).await;
+            }
+        }
+
+        for task in [network_task, power_task, calendar_task, companion_task, trigger_task, summary_task, cluster_task].into_iter().flatten() {
+            task.abort();
         }
+        update_task.abort();
+        outbox_task.abort();
 
         Ok(())
     }
+
+    /// Check the given watchers once, right away (ignoring their intervals
+    /// and active hours), the way the monitor checks them: alerts are sent
+    /// and results saved. Expired watchers are disabled instead of checked.
+    pub async fn check_once(&self, watcher_ids: &[String]) -> Vec<CheckReport> {
+        let watchers: Vec<Watcher> = {
+            let cfg = self.config.read().await;
+            watcher_ids
+                .iter()
+                .filter_map(|id| cfg.watchers.iter().find(|w| &w.id == id))
+                .cloned()
+                .collect()
+        };
+
+        let checks = watchers.into_iter().map(|watcher| self.check_now(watcher));
+        futures_util::future::join_all(checks)
+            .await
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+
+    /// One check of `check_once` (None for an expired watcher, or a
+    /// streaming one, which has nothing to check)
+    async fn check_now(&self, mut watcher: Watcher) -> Option<CheckReport> {
+        let log = self.log.for_watcher(&watcher.url).with_file(&watcher.id);
+        if watcher.is_stream() {
+            return None;
+        }
+        if watcher.is_expired() {
+            let mut state = load_state(&watcher, &log);
+            expire_watcher(&watcher, &mut state, &self.config, &self.save_failure, &log).await;
+            return None;
+        }
+        if let Some(record) = recent_check(&watcher) {
+            let zone = self.config.read().await.timezone;
+            log.info(format!("{} was checked at {}, reporting that check", watcher.url,
+                zone.format(record.checked_at, "%H:%M:%S")));
+            return Some(cached_report(&watcher, record));
+        }
+
+        let (zone, max_page_size, site) = {
+            let cfg = self.config.read().await;
+            watcher.keywords = cfg.keywords_for(&watcher);
+            (cfg.timezone, cfg.limits.max_page_size_for(&watcher), cfg.site_settings(&watcher))
+        };
+        let keyword_matcher = KeywordMatcher::new(&watcher.keywords);
+        let mut state = load_state(&watcher, &log);
+
+        let context = CheckContext {
+            config: &self.config,
+            save_failure: &self.save_failure,
+            log: &log,
+            bus: &self.bus,
+        };
+        let report = run_check(
+            &mut watcher,
+            &keyword_matcher,
+            &mut state,
+            &site,
+            max_page_size,
+            zone,
+            &context,
+        )
+        .await;
+        Some(report)
+    }
+
+    /// Notify that monitoring started, with the next check of each watcher
+    async fn send_startup_summary(&self, watchers: &[Watcher]) {
+        let (zone, mut next_checks) = {
+            let cfg = self.config.read().await;
+            let next_checks: Vec<_> = watchers
+                .iter()
+                .map(|watcher| (first_check_at(watcher, &cfg), watcher.url.as_str()))
+                .collect();
+            (cfg.timezone, next_checks)
+        };
+        next_checks.sort();
+
+        let lines: Vec<String> = next_checks
+            .iter()
+            .map(|(at, url)| format!("{} {}", zone.format(*at, "%a %H:%M"), url))
+            .collect();
+
+        if let Err(e) = notify::send_startup_notification(watchers.len(), &lines) {
+            self.log.error(format!("✗ Startup notification failed: {}", e));
+        }
+    }
+
+    /// Ping the configured uptime URL if the last ping is an interval ago.
+    /// The request runs in the background so it can't delay the watchdog.
+    async fn ping_uptime_service(&self, last_ping: &mut Option<Instant>) {
+        let settings = self.config.read().await.uptime_ping.clone();
+        let Some(url) = settings.url else {
+            return;
+        };
+        if last_ping.is_some_and(|at| at.elapsed() < settings.interval) {
+            return;
+        }
+
+        *last_ping = Some(Instant::now());
+        let log = self.log.clone();
+        tokio::spawn(async move {
+            if let Err(e) = fetcher::ping(&url).await {
+                log.error(format!("✗ Uptime ping failed: {:#}", e));
+            }
+        });
+    }
+
+    /// Log the settings of a watcher that starts being monitored
+    fn log_start(&self, watcher: &Watcher) {
+        let log = self.log.for_watcher(&watcher.url).with_file(&watcher.id);
+        let interval = if watcher.is_stream() {
+            String::from("stream")
+        } else {
+            format_interval(watcher.check_interval)
+        };
+        log.info(format!("Watcher: {} | Keywords: {} | Interval: {}",
+            watcher.url,
+            watcher.keyword_summary(),
+            interval));
+
+        if watcher.tls.insecure {
+            log.warning("  ⚠ TLS certificate verification is DISABLED for this watcher");
+        }
+    }
+
+    /// Bring the tasks in line with an edited config: start the watchers
+    /// that were added or enabled, stop those removed or disabled, and
+    /// restart those whose settings changed
+    async fn reconcile(&self, tasks: &mut Vec<WatcherTask>) {
+        let mut stopped = Vec::new();
+        let started: Vec<Watcher> = {
+            let cfg = self.config.read().await;
+            tasks.retain(|task| {
+                let current = cfg.watchers.iter().find(|w| w.id == task.watcher_id && w.is_active());
+                if current.is_some_and(|w| task_definition(w, &cfg) == task.definition) {
+                    return true;
+                }
+                task.handle.abort();
+                stopped.push((task.watcher_id.clone(), task.url.clone()));
+                false
+            });
+            cfg.watchers
+                .iter()
+                .filter(|w| w.is_active() && !tasks.iter().any(|task| task.watcher_id == w.id))
+                .cloned()
+                .collect()
+        };
+
+        for (id, url) in &stopped {
+            if !started.iter().any(|w| &w.id == id) {
+                self.triggers.unregister(id);
+                self.log.for_watcher(url).with_file(id).info(format!("Stopped monitoring {}", url));
+            }
+        }
+        for watcher in started {
+            if stopped.iter().any(|(id, _)| *id == watcher.id) {
+                self.log
+                    .for_watcher(&watcher.url)
+                    .with_file(&watcher.id)
+                    .info(format!("Restarting {} with its new settings", watcher.url));
+            }
+            self.log_start(&watcher);
+            tasks.push(self.spawn(watcher).await);
+        }
+    }
+
+    /// Start the monitoring task of one watcher
+    async fn spawn(&self, watcher: Watcher) -> WatcherTask {
+        let definition = task_definition(&watcher, &*self.config.read().await);
+        let config = Arc::clone(&self.config);
+        let heartbeat = Heartbeat::default();
+        let conditions = Conditions {
+            connectivity: self.connectivity.subscribe(),
+            battery_saving: self.battery_saving.subscribe(),
+            wake: self.triggers.register(&watcher.id),
+        };
+        let save_failure = self.save_failure.clone();
+        let log = self.log.for_watcher(&watcher.url).with_file(&watcher.id);
+        let bus = self.bus.clone();
+
+        WatcherTask {
+            watcher_id: watcher.id.clone(),
+            url: watcher.url.clone(),
+            definition,
+            heartbeat: heartbeat.clone(),
+            handle: tokio::spawn(async move {
+                supervise(watcher, config, heartbeat, conditions, save_failure, log, bus).await
+            }),
+        }
+    }
+
+    /// Replace a task that missed its watchdog deadline with a fresh one
+    async fn restart_stuck(&self, task: &mut WatcherTask, stuck_for: Duration) {
+        // Abort first: a hung task may be holding the config lock
+        task.handle.abort();
+
+        let (watcher, settings, notify_restart) = {
+            let cfg = self.config.read().await;
+            (
+                cfg.watchers.iter().find(|w| w.id == task.watcher_id).cloned(),
+                cfg.notifications.clone(),
+                cfg.watchdog.notify,
+            )
+        };
+
+        let log = self.log.for_watcher(&task.url).with_file(&task.watcher_id);
+        let stuck_for = format_interval(stuck_for);
+        log.warning(format!("⚠ Watchdog: {} hasn't completed a check in {}, restarting it",
+            task.url, stuck_for));
+
+        if notify_restart {
+            if let Err(e) = notify::send_watchdog_notification(&settings, &task.url, &stuck_for).await {
+                log.error(format!("  ✗ Error: {}", e));
+            }
+        }
+
+        // A watcher removed or disabled in the meantime stays stopped (the
+        // aborted task is dropped on the next poll)
+        if let Some(watcher) = watcher.filter(Watcher::is_active) {
+            *task = self.spawn(watcher).await;
+        }
+    }
 }
 
-/// Monitor a single watcher indefinitely
-async fn monitor_watcher(mut watcher: Watcher, config: Arc<Mutex<Config>>) {
+impl LiveConfig {
+    /// A copy of the current config, with the monitor's latest check
+    /// results. Blocks, so it's only for use outside the monitor's runtime.
+    pub fn snapshot(&self) -> Config {
+        self.config.blocking_read().clone()
+    }
+
+    /// Edit the config and have the monitor pick up the changes. The
+    /// monitor's own updates wait until the edit is done, so neither side
+    /// overwrites the other. Blocks like `snapshot`.
+    pub fn edit<T>(&self, edit: impl FnOnce(&mut Config) -> T) -> T {
+        let result = edit(&mut self.config.blocking_write());
+        self.changed.notify_one();
+        result
+    }
+}
+
+/// What a watcher's task runs with: its settings without the check results
+/// the monitor keeps updating, and the keywords of its keyword sets. When
+/// this changes, the task is restarted.
+fn task_definition(watcher: &Watcher, config: &Config) -> String {
+    let mut settings = watcher.clone();
+    settings.keywords = config.keywords_for(watcher);
+    settings.last_checked = None;
+    settings.consecutive_errors = 0;
+    settings.last_error = None;
+    settings.blocked = None;
+    settings.slow = None;
+    settings.permanent_failures = 0;
+    settings.permanent_failure_since = None;
+    settings.moved_to = None;
+    settings.ignored_move = None;
+    serde_json::to_string(&settings).unwrap_or_default()
+}
+
+/// Run a watcher's monitoring loop, restarting it with backoff if it panics
+/// (a panic would otherwise stop the watcher silently until the daemon
+/// restarts)
+async fn supervise(
+    mut watcher: Watcher,
+    config: Arc<RwLock<Config>>,
+    heartbeat: Heartbeat,
+    conditions: Conditions,
+    save_failure: watch::Sender<Option<String>>,
+    log: EventLog,
+    bus: EventBus,
+) {
+    let mut crashes: u32 = 0;
+
     loop {
-        // Wait for the check interval
-        sleep(watcher.check_interval).await;
-
-        let timestamp = Utc::now().format("%Y-%m-%d %H:%M:%S");
-        println!("[{}] Checking {}...", timestamp, watcher.url);
-
-        // Perform the check
-        match check_watcher(&mut watcher).await {
-            Ok((found_matches, matched_keywords)) => {
-                if found_matches {
-                    println!("[{}]   ✓ Keywords found: {} | Notification sent",
-                        timestamp, matched_keywords.join(", "));
-                } else {
-                    println!("[{}]   - No changes or keywords found", timestamp);
+        let started = Instant::now();
+        let run = monitor_watcher(
+            watcher.clone(),
+            Arc::clone(&config),
+            heartbeat.clone(),
+            conditions.clone(),
+            save_failure.clone(),
+            log.clone(),
+            bus.clone(),
+        );
+        let Err(panic) = AssertUnwindSafe(run).catch_unwind().await else {
+            // Finished normally (expired or disabled itself)
+            return;
+        };
+
+        if started.elapsed() >= MAX_RESTART_BACKOFF {
+            crashes = 0;
+        }
+        crashes += 1;
+        let backoff = RESTART_BACKOFF
+            .saturating_mul(2u32.saturating_pow(crashes - 1))
+            .min(MAX_RESTART_BACKOFF);
+
+        let (settings, notify_restart) = {
+            let cfg = config.read().await;
+            (cfg.notifications.clone(), cfg.watchdog.notify)
+        };
+        let error = panic_message(panic.as_ref());
+        let restart_in = format_interval(backoff);
+        log.error(format!("✗ Watcher {} crashed: {} (restarting in {})",
+            watcher.url, error, restart_in));
+
+        if notify_restart {
+            if let Err(e) = notify::send_crash_notification(&settings, &watcher.url, &error, &restart_in).await {
+                log.error(format!("  ✗ Error: {}", e));
+            }
+        }
+
+        // Waiting out the backoff isn't being stuck
+        heartbeat.expect_within(None);
+        sleep(backoff).await;
+
+        // Pick up changes made since the crash; a watcher removed or
+        // disabled in the meantime stays stopped
+        let latest = config.read().await.watchers.iter().find(|w| w.id == watcher.id).cloned();
+        match latest {
+            Some(latest) if latest.is_active() => watcher = latest,
+            _ => return,
+        }
+    }
+}
+
+/// The watcher's last check if it's within its `min_recheck_gap`. Checks of
+/// every process are in the history, so this sees those of others too.
+fn recent_check(watcher: &Watcher) -> Option<CheckRecord> {
+    let gap = chrono::Duration::seconds(i64::try_from(watcher.min_recheck_gap?).ok()?);
+    let record = history::load(&watcher.id, 1).ok()?.pop()?;
+    (Utc::now() - record.checked_at < gap).then_some(record)
+}
+
+/// The report of a check that wasn't run, from the result of the last one
+fn cached_report(watcher: &Watcher, record: CheckRecord) -> CheckReport {
+    let mut report = CheckReport {
+        url: watcher.url.clone(),
+        changed: false,
+        matched_keywords: Vec::new(),
+        error: None,
+        disabled: false,
+        cached_at: Some(record.checked_at),
+    };
+    match record.outcome {
+        CheckOutcome::Unchanged => {}
+        CheckOutcome::Changed => report.changed = true,
+        CheckOutcome::Matched { keywords } => {
+            report.changed = true;
+            report.matched_keywords = keywords;
+        }
+        CheckOutcome::Error { message } => report.error = Some(message),
+        CheckOutcome::Blocked { reason } => report.error = Some(format!("blocked: {}", reason)),
+    }
+    report
+}
+
+/// When a watcher started now will run its first check: one interval from
+/// now, or when its active hours next begin
+fn first_check_at(watcher: &Watcher, config: &Config) -> DateTime<Utc> {
+    let interval = config.guardrails.effective_interval(watcher);
+    let at = Utc::now() + chrono::Duration::from_std(interval).unwrap_or_default();
+    within_active_window(watcher, config, at)
+}
+
+/// When a running monitor is expected to check a watcher next: one interval
+/// after its last check (now if that's overdue or it was never checked), or
+/// when its active hours next begin
+pub fn next_check_at(watcher: &Watcher, config: &Config) -> DateTime<Utc> {
+    let interval = config.guardrails.effective_interval(watcher);
+    let at = watcher
+        .last_checked
+        .map(|checked| checked + chrono::Duration::from_std(interval).unwrap_or_default())
+        .unwrap_or_default()
+        .max(Utc::now());
+    within_active_window(watcher, config, at)
+}
+
+/// `at`, or the next start of the watcher's active hours if they're closed then
+fn within_active_window(watcher: &Watcher, config: &Config, at: DateTime<Utc>) -> DateTime<Utc> {
+    let Some(window) = &watcher.active_window else {
+        return at;
+    };
+    let zone = config.timezone;
+    let wall_clock = zone.wall_clock(at);
+    if window.is_active(wall_clock) {
+        return at;
+    }
+    window
+        .next_start(wall_clock)
+        .and_then(|next| zone.utc_from_wall_clock(next))
+        .unwrap_or(at)
+}
+
+/// Poll the network and pause/resume the watchers when it changes, logging
+/// each change once (instead of a fetch error per watcher per check)
+async fn watch_connectivity(
+    config: Arc<RwLock<Config>>,
+    connectivity: watch::Sender<Connectivity>,
+    log: EventLog,
+) {
+    loop {
+        let settings = config.read().await.network.clone();
+
+        let metered_interfaces = settings.metered_interfaces.clone();
+        let mut status = tokio::task::spawn_blocking(move || network::detect(&metered_interfaces))
+            .await
+            .unwrap_or(Connectivity::Online);
+        if status == Connectivity::Offline && !settings.pause_when_offline {
+            status = Connectivity::Online;
+        }
+
+        let previous = connectivity.send_replace(status.clone());
+        if status != previous {
+            if status.allows_checks() {
+                log.info("Network is back, monitoring resumed");
+            } else {
+                log.warning(format!("Network is {}, pausing checks", status));
+            }
+        }
+
+        sleep(NETWORK_POLL_INTERVAL).await;
+    }
+}
+
+/// Poll the battery and switch battery saving on/off when it changes
+async fn watch_power(config: Arc<RwLock<Config>>, battery_saving: watch::Sender<bool>, log: EventLog) {
+    loop {
+        let settings = config.read().await.battery.clone();
+
+        let status = tokio::task::spawn_blocking(power::detect).await.ok().flatten();
+        let saving = settings.is_saving(status.as_ref());
+
+        let previous = battery_saving.send_replace(saving);
+        if saving && !previous {
+            let charge = status
+                .and_then(|s| s.charge_percent)
+                .map(|c| format!("{}%", c))
+                .unwrap_or_else(|| String::from("low"));
+            let action = if settings.pause_non_critical { "pausing" } else { "slowing down" };
+            log.info(format!("Battery at {}, {} non-critical watchers", charge, action));
+        } else if !saving && previous {
+            log.info("Battery saving off, back to normal intervals");
+        }
+
+        sleep(POWER_POLL_INTERVAL).await;
+    }
+}
+
+/// Check for new releases while enabled, logging each newer version once
+async fn watch_updates(config: Arc<RwLock<Config>>, log: EventLog) {
+    let mut reported: Option<String> = None;
+    loop {
+        let settings = config.read().await.update_check.clone();
+        if settings.enabled {
+            match update::check(&settings).await {
+                Ok(Some(version)) if reported.as_ref() != Some(&version) => {
+                    log.info(format!("New version available: {} (running {})",
+                        version, update::CURRENT_VERSION));
+                    reported = Some(version);
+                }
+                Ok(_) => {}
+                Err(e) => log.warning(format!("⚠ Update check failed: {:#}", e)),
+            }
+        }
+
+        sleep(UPDATE_POLL_INTERVAL).await;
+    }
+}
+
+/// Deliver the alerts waiting in the outbox as they come due
+async fn watch_outbox(config: Arc<RwLock<Config>>, log: EventLog) {
+    let mut waiting = 0;
+    loop {
+        let settings = config.read().await.notifications.clone();
+        match outbox::retry(&settings).await {
+            Ok(retried) => {
+                if retried.delivered > 0 {
+                    log.info(format!("✓ Delivered {} queued alert(s), {} still waiting",
+                        retried.delivered, retried.pending));
+                }
+                for (title, error) in retried.expired {
+                    log.error(format!("✗ Gave up on the alert \"{}\": {}", title, error));
+                }
+                if retried.pending > waiting {
+                    log.warning(format!("⚠ {} alert(s) couldn't be delivered, retrying later", retried.pending));
+                }
+                waiting = retried.pending;
+            }
+            Err(e) => log.error(format!("✗ {:#}", e)),
+        }
+
+        sleep(OUTBOX_POLL_INTERVAL).await;
+    }
+}
+
+/// While the log leaves out the checks, log every `interval` how many there
+/// were and how they went
+async fn log_summaries(mut events: UnboundedReceiver<MonitorEvent>, interval: Duration, log: EventLog) {
+    let mut ticks = tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+    let mut watchers = HashSet::new();
+    let (mut checks, mut changed, mut matched, mut failed) = (0, 0, 0, 0);
+    loop {
+        tokio::select! {
+            _ = ticks.tick() => {
+                log.summary(format!("Last {}: {} check(s) of {} watcher(s), {} changed, {} matched, {} failed",
+                    format_interval(interval), checks, watchers.len(), changed, matched, failed));
+                watchers.clear();
+                (checks, changed, matched, failed) = (0, 0, 0, 0);
+            }
+            event = events.recv() => {
+                let Some(event) = event else {
+                    return;
+                };
+                watchers.insert(event.watcher_id().to_string());
+                match event {
+                    MonitorEvent::CheckCompleted { changed: true, .. } => {
+                        checks += 1;
+                        changed += 1;
+                    }
+                    MonitorEvent::CheckCompleted { .. } => checks += 1,
+                    MonitorEvent::MatchFound { .. } => matched += 1,
+                    MonitorEvent::Error { .. } | MonitorEvent::Blocked { .. } => {
+                        checks += 1;
+                        failed += 1;
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// The message of a panic payload (panics carry a &str or a String)
+fn panic_message(panic: &(dyn Any + Send)) -> String {
+    if let Some(message) = panic.downcast_ref::<&str>() {
+        message.to_string()
+    } else if let Some(message) = panic.downcast_ref::<String>() {
+        message.clone()
+    } else {
+        String::from("unknown panic")
+    }
+}
+
+/// Monitor a single watcher until it expires (or forever)
+async fn monitor_watcher(
+    mut watcher: Watcher,
+    config: Arc<RwLock<Config>>,
+    heartbeat: Heartbeat,
+    mut conditions: Conditions,
+    save_failure: watch::Sender<Option<String>>,
+    log: EventLog,
+    bus: EventBus,
+) {
+    // Add the keywords of shared keyword sets (this copy of the watcher is
+    // never saved as a whole), then compile them once for every check
+    watcher.keywords = config.read().await.keywords_for(&watcher);
+    let keyword_matcher = KeywordMatcher::new(&watcher.keywords);
+
+    // Matches notified before a restart shouldn't be notified again
+    let mut state = load_state(&watcher, &log);
+
+    if watcher.is_stream() {
+        let context = CheckContext {
+            config: &config,
+            save_failure: &save_failure,
+            log: &log,
+            bus: &bus,
+        };
+        watch_stream(&mut watcher, &keyword_matcher, &mut state, &heartbeat, &mut conditions, &context).await;
+        return;
+    }
+
+    // The machine holding the lease on the watcher, if not this one
+    let mut held_by: Option<String> = None;
+
+    loop {
+        if watcher.is_expired() {
+            expire_watcher(&watcher, &mut state, &config, &save_failure, &log).await;
+            return;
+        }
+
+        // Wait for the check interval (never shorter than the configured
+        // minimum, and longer for non-critical watchers in battery saving)
+        let battery_saving = !watcher.critical && *conditions.battery_saving.borrow();
+        let (interval, zone, max_page_size, allowance, pause_on_battery, site, leases, cluster) = {
+            let cfg = config.read().await;
+            let mut interval = cfg.guardrails.effective_interval(&watcher);
+            if battery_saving && !cfg.battery.pause_non_critical {
+                interval = interval.saturating_mul(cfg.battery.interval_multiplier.max(1));
+            }
+            (
+                interval,
+                cfg.timezone,
+                cfg.limits.max_page_size_for(&watcher),
+                cfg.watchdog.allowance(interval),
+                !watcher.critical && cfg.battery.pause_non_critical,
+                cfg.site_settings(&watcher),
+                cfg.lease.clone(),
+                cfg.notifications.cluster.clone(),
+            )
+        };
+        heartbeat.expect_within(allowance);
+        // A check triggered from outside (see `trigger`) comes right away,
+        // whatever the active hours, battery or recent checks
+        let mut triggered = tokio::select! {
+            _ = sleep(interval) => false,
+            _ = conditions.wake.notified() => true,
+        };
+
+        // Outside the watcher's active hours: wait until the window opens
+        if let Some(window) = watcher.active_window.as_ref().filter(|_| !triggered) {
+            let now = zone.now();
+            if !window.is_active(now) {
+                if let Some(next) = window.next_start(now) {
+                    log.info(format!("{} is outside its active hours ({}), next check at {}",
+                        watcher.url,
+                        window,
+                        next.format("%a %H:%M")));
+                    // Sleep in UTC terms so DST changes don't shift the wake-up
+                    let wake = zone.utc_from_wall_clock(next).unwrap_or_else(Utc::now);
+                    let wait = (wake - Utc::now()).to_std().unwrap_or_default();
+                    heartbeat.expect_within(allowance.map(|allowance| allowance.saturating_add(wait)));
+                    triggered = tokio::select! {
+                        _ = sleep(wait) => false,
+                        _ = conditions.wake.notified() => true,
+                    };
+                }
+            }
+        }
+
+        // A paused period in a calendar: wait until it's over (looking
+        // again now and then, in case the event moves)
+        if !triggered && !watcher.critical {
+            if let Some(event) = calendar::current(CalendarAction::Pause) {
+                log.info(format!("{} is paused for \"{}\" until {}", watcher.url, event.summary,
+                    zone.format(event.end, "%a %H:%M")));
+                heartbeat.expect_within(None);
+                while let Some(event) = calendar::current(CalendarAction::Pause) {
+                    let wait = (event.end - Utc::now()).to_std().unwrap_or_default().min(calendar::REFRESH);
+                    tokio::select! {
+                        _ = sleep(wait) => {}
+                        _ = conditions.wake.notified() => {
+                            triggered = true;
+                            break;
+                        }
+                    }
+                }
+            }
+        }
+
+        // Offline or on a metered connection: wait until it's back (the
+        // network task logs the pause and resume)
+        if !conditions.connectivity.borrow().allows_checks() {
+            heartbeat.expect_within(None);
+            let _ = conditions.connectivity.wait_for(Connectivity::allows_checks).await;
+        }
+
+        // Battery saving can pause non-critical watchers until plugged in
+        if pause_on_battery && !triggered && *conditions.battery_saving.borrow() {
+            heartbeat.expect_within(None);
+            let _ = conditions.battery_saving.wait_for(|saving| !saving).await;
+        }
+
+        // The expiration may have passed while we were sleeping
+        if watcher.is_expired() {
+            expire_watcher(&watcher, &mut state, &config, &save_failure, &log).await;
+            return;
+        }
+
+        // Another machine running the same settings checks it (logged when
+        // that changes)
+        if leases.backend.is_some() {
+            match lease::acquire(&leases, &cluster, &watcher.id, lease::duration(interval)).await {
+                Ok(Claim::Granted) => {
+                    if let Some(holder) = held_by.take() {
+                        log.info(format!("{} is checked here again ({} stopped checking it)", watcher.url, holder));
+                    }
                 }
+                Ok(Claim::Held { holder }) => {
+                    if held_by.as_ref() != Some(&holder) {
+                        log.info(format!("{} is checked by {}, skipping it here", watcher.url, holder));
+                    }
+                    held_by = Some(holder);
+                    continue;
+                }
+                Err(e) => log.warning(format!("⚠ No lease on {}, checking it anyway: {:#}", watcher.url, e)),
+            }
+        }
+
+        // Checked meanwhile (`check`, or another monitor of the same data):
+        // don't fetch the page again so soon
+        if let Some(record) = recent_check(&watcher).filter(|_| !triggered) {
+            log.info(format!("{} was checked at {}, skipping this check", watcher.url,
+                zone.format(record.checked_at, "%H:%M:%S")));
+            continue;
+        }
+
+        let context = CheckContext {
+            config: &config,
+            save_failure: &save_failure,
+            log: &log,
+            bus: &bus,
+        };
+        let report = run_check(
+            &mut watcher,
+            &keyword_matcher,
+            &mut state,
+            &site,
+            max_page_size,
+            zone,
+            &context,
+        )
+        .await;
+        if report.disabled {
+            return;
+        }
+    }
+}
+
+/// Keep a streaming watcher connected, matching every message it receives,
+/// until it expires or disables itself. Dropped connections are reopened
+/// with backoff; a stream silent for longer than the check interval counts
+/// as dropped.
+async fn watch_stream(
+    watcher: &mut Watcher,
+    keyword_matcher: &KeywordMatcher,
+    state: &mut WatcherState,
+    heartbeat: &Heartbeat,
+    conditions: &mut Conditions,
+    context: &CheckContext<'_>,
+) {
+    let CheckContext { config, save_failure, log, bus } = *context;
+    let mut last_event_id: Option<String> = None;
+    let mut retry = STREAM_RETRY;
+
+    loop {
+        if watcher.is_expired() {
+            expire_watcher(watcher, state, config, save_failure, log).await;
+            return;
+        }
+        if finish_one_shot(watcher, state, context).await {
+            return;
+        }
+
+        // A stream is quiet for as long as its server likes, so the
+        // watchdog can't tell a stuck task from one waiting for messages
+        heartbeat.expect_within(None);
+        if !conditions.connectivity.borrow().allows_checks() {
+            let _ = conditions.connectivity.wait_for(Connectivity::allows_checks).await;
+        }
+
+        let (idle, zone, site) = {
+            let cfg = config.read().await;
+            (cfg.guardrails.effective_interval(watcher), cfg.timezone, cfg.site_settings(watcher))
+        };
 
-                // Update last_checked timestamp
+        log.info(format!("Connecting to {}...", watcher.url));
+        let result = match stream::connect(&watcher.url, &site.request, last_event_id.as_deref()).await {
+            Ok(mut stream) => {
+                log.info("  Connected, waiting for messages");
+                retry = STREAM_RETRY;
                 watcher.last_checked = Some(Utc::now());
+                watcher.consecutive_errors = 0;
+                watcher.last_error = None;
+                save_check_status(watcher, config, save_failure, log).await;
 
-                // Save updated config
-                let mut cfg = config.lock().await;
-                if let Some(w) = cfg.watchers.iter_mut().find(|w| w.id == watcher.id) {
-                    w.last_checked = watcher.last_checked;
+                let result = read_stream(&mut stream, watcher, keyword_matcher, state, idle, zone, context).await;
+                last_event_id = stream.last_event_id().map(str::to_string);
+                if let Some(wait) = stream.retry() {
+                    retry = wait;
                 }
-                let _ = cfg.save();
+                result
             }
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(true) => return,
+            // Expires at the top of the loop
+            Ok(false) if watcher.is_expired() => continue,
+            Ok(false) => log.info(format!("  Stream closed, reconnecting in {}", format_interval(retry))),
             Err(e) => {
-                eprintln!("[{}]   ✗ Error: {}", Utc::now().format("%Y-%m-%d %H:%M:%S"), e);
+                log.error(format!("  ✗ Error: {:#} (reconnecting in {})", e, format_interval(retry)));
+                watcher.consecutive_errors += 1;
+                watcher.last_error = Some(format!("{:#}", e));
+                save_check_status(watcher, config, save_failure, log).await;
+                let storage = Storage::of(&*config.read().await);
+                append_history(watcher, CheckOutcome::Error { message: format!("{:#}", e) }, None, &storage, log);
+                bus.publish(MonitorEvent::Error {
+                    watcher_id: watcher.id.clone(),
+                    url: watcher.url.clone(),
+                    at: Utc::now(),
+                    message: format!("{:#}", e),
+                });
             }
         }
+
+        sleep(retry).await;
+        retry = retry.saturating_mul(2).min(MAX_STREAM_RETRY);
+    }
+}
+
+/// Match the messages of an open stream until it closes (false) or the
+/// watcher disabled itself after a match (true)
+async fn read_stream(
+    stream: &mut Stream,
+    watcher: &mut Watcher,
+    keyword_matcher: &KeywordMatcher,
+    state: &mut WatcherState,
+    idle: Duration,
+    zone: Zone,
+    context: &CheckContext<'_>,
+) -> Result<bool> {
+    let CheckContext { config, save_failure, log, bus } = *context;
+
+    while let Some(message) = stream.next_message(idle).await? {
+        if finish_one_shot(watcher, state, context).await {
+            return Ok(true);
+        }
+        if !state.held.is_empty() {
+            let settings = config.read().await.notifications.clone();
+            send_held(watcher, state, &settings, log).await;
+        }
+
+        // Every message is new content; matches already alerted (the same
+        // message sent again) are skipped
+        let matches = state.new_matches(keyword_matcher.find(&message));
+        if matches.is_empty() {
+            continue;
+        }
+
+        let (settings, storage) = {
+            let config = config.read().await;
+            (config.notifications.clone(), Storage::of(&config))
+        };
+        let matched_keywords = match notify_matches(watcher, state, &settings, &matches, &Changes::default(), Some(message), None).await {
+            Ok(keywords) => keywords,
+            Err(e) => {
+                log.error(format!("  ✗ Error: {:#}", e));
+                continue;
+            }
+        };
+        log.matched(format!("  ✓ Keywords found: {} | {}", matched_keywords.join(", "), alert_note(watcher, zone, true)));
+
+        if let Err(e) = state.save(&watcher.id) {
+            log.error(format!("  ✗ {:#}", e));
+        }
+        let hook = run_hook(watcher, &matched_keywords, log).await;
+        append_history(watcher, CheckOutcome::Matched { keywords: matched_keywords.clone() }, hook, &storage, log);
+        watcher.last_checked = Some(Utc::now());
+        save_check_status(watcher, config, save_failure, log).await;
+
+        let at = Utc::now();
+        bus.publish(MonitorEvent::MatchFound {
+            watcher_id: watcher.id.clone(),
+            url: watcher.url.clone(),
+            at,
+            keywords: matched_keywords.clone(),
+        });
+        bus.publish(MonitorEvent::CheckCompleted {
+            watcher_id: watcher.id.clone(),
+            url: watcher.url.clone(),
+            at,
+            changed: true,
+        });
+
+        if watcher.disable_after_match && !state.held.is_empty() {
+            log.info("  One-shot watcher done once the alert held for the quiet period is sent");
+        } else if watcher.disable_after_match {
+            let reason = format!(
+                "Matched {} on {}",
+                matched_keywords.join(", "),
+                zone.format(Utc::now(), "%Y-%m-%d %H:%M")
+            );
+            log.info("  One-shot watcher done, disabling it");
+            disable_watcher(watcher, config, reason, save_failure, log).await;
+            return Ok(true);
+        }
+
+        if watcher.is_expired() {
+            return Ok(false);
+        }
+    }
+    Ok(false)
+}
+
+/// The state of a watcher's earlier checks, or an empty one if it can't be read
+fn load_state(watcher: &Watcher, log: &EventLog) -> WatcherState {
+    match WatcherState::load(&watcher.id) {
+        Ok(state) => state,
+        Err(e) => {
+            log.error(format!("✗ {:#} (starting with empty state)", e));
+            WatcherState::default()
+        }
+    }
+}
+
+/// Where a check reports to: the shared config (check status, disabled
+/// watchers), the log and the event bus
+struct CheckContext<'a> {
+    config: &'a Arc<RwLock<Config>>,
+    save_failure: &'a watch::Sender<Option<String>>,
+    log: &'a EventLog,
+    bus: &'a EventBus,
+}
+
+/// Check a watcher once and record the outcome: alerts, state, history,
+/// the check status in the config, and an event on the bus. One-shot
+/// watchers that matched, pages that are gone for good and blocked watchers
+/// set to pause get disabled.
+async fn run_check(
+    watcher: &mut Watcher,
+    keyword_matcher: &KeywordMatcher,
+    state: &mut WatcherState,
+    site: &SiteSettings,
+    max_page_size: Option<u64>,
+    zone: Zone,
+    context: &CheckContext<'_>,
+) -> CheckReport {
+    let CheckContext { config, save_failure, log, bus } = *context;
+    let mut report = CheckReport {
+        url: watcher.url.clone(),
+        changed: false,
+        matched_keywords: Vec::new(),
+        error: None,
+        disabled: false,
+        cached_at: None,
+    };
+
+    // A one-shot watcher holding its alert for a quiet period has matched
+    // already: it's done once the alert is sent
+    if watcher.disable_after_match && !state.held.is_empty() {
+        report.disabled = finish_one_shot(watcher, state, context).await;
+        return report;
+    }
+
+    log.info(format!("Checking {}...", watcher.url));
+
+    // Use the latest notification and storage settings for this check
+    let (settings, storage) = {
+        let config = config.read().await;
+        (config.notifications.clone(), Storage::of(&config))
+    };
+
+    // The average to compare the response time with, from before the check
+    let average = match watcher.latency_increase_percent {
+        Some(_) => latency::rolling_average(&watcher.id).unwrap_or_default(),
+        None => None,
+    };
+
+    // Perform the check
+    let result = check_watcher(watcher, site, keyword_matcher, state, &settings, max_page_size, &storage).await;
+    let hook = match &result {
+        Ok(check) if !check.matched_keywords.is_empty() => run_hook(watcher, &check.matched_keywords, log).await,
+        _ => None,
+    };
+    record_check(watcher, &result, hook, &storage, log);
+    send_held(watcher, state, &settings, log).await;
+
+    match result {
+        Ok(CheckResult { changed, matched_keywords, cleared_keywords, meta, .. }) => {
+            report.changed = changed;
+            report.matched_keywords = matched_keywords.clone();
+            let found_matches = !matched_keywords.is_empty();
+            if found_matches {
+                log.matched(format!("  ✓ Keywords found: {} | {}",
+                    matched_keywords.join(", "),
+                    alert_note(watcher, zone, true)));
+            } else if cleared_keywords.is_empty() && changed {
+                log.changed("  - Changed, no keywords found");
+            } else if cleared_keywords.is_empty() {
+                log.info("  - No changes or keywords found");
+            }
+
+            if !cleared_keywords.is_empty() {
+                let message = format!("  ✓ No longer on the page: {}", cleared_keywords.join(", "));
+                if watcher.notify_cleared {
+                    log.matched(format!("{} | {}", message, alert_note(watcher, zone, false)));
+                } else {
+                    log.info(message);
+                }
+            }
+
+            if state.is_dirty() {
+                if let Err(e) = state.save(&watcher.id) {
+                    log.error(format!("  ✗ {:#}", e));
+                }
+            }
+
+            note_latency(watcher, meta.response_time_ms, average, &settings, log).await;
+            note_move(watcher, meta.moved_to, config, &settings, log).await;
+
+            // Update last_checked timestamp and clear the error streak
+            watcher.last_checked = Some(Utc::now());
+            watcher.consecutive_errors = 0;
+            watcher.last_error = None;
+            watcher.permanent_failures = 0;
+            watcher.permanent_failure_since = None;
+            if watcher.blocked.take().is_some() {
+                log.info("  ✓ No longer blocked");
+            }
+            save_check_status(watcher, config, save_failure, log).await;
+
+            let at = Utc::now();
+            if found_matches {
+                bus.publish(MonitorEvent::MatchFound {
+                    watcher_id: watcher.id.clone(),
+                    url: watcher.url.clone(),
+                    at,
+                    keywords: matched_keywords.clone(),
+                });
+            }
+            bus.publish(MonitorEvent::CheckCompleted {
+                watcher_id: watcher.id.clone(),
+                url: watcher.url.clone(),
+                at,
+                changed,
+            });
+
+            // One-shot watchers are done after their first alert (a held
+            // one once it's sent)
+            if found_matches && watcher.disable_after_match && !state.held.is_empty() {
+                log.info("  One-shot watcher done once the alert held for the quiet period is sent");
+            } else if found_matches && watcher.disable_after_match {
+                let reason = format!(
+                    "Matched {} on {}",
+                    matched_keywords.join(", "),
+                    zone.format(Utc::now(), "%Y-%m-%d %H:%M")
+                );
+                log.info("  One-shot watcher done, disabling it");
+                disable_watcher(watcher, config, reason, save_failure, log).await;
+                report.disabled = true;
+            }
+        }
+        Err(e) => {
+            report.error = Some(format!("{:#}", e));
+            if let Some(blocked) = e.downcast_ref::<BlockedError>() {
+                report.disabled = note_blocked(watcher, &blocked.reason, &settings, zone, context).await;
+                return report;
+            }
+            log.error(format!("  ✗ Error: {}", e));
+
+            watcher.consecutive_errors += 1;
+            watcher.last_error = Some(format!("{:#}", e));
+            let permanent = fetcher::permanent_failure(&e);
+            if permanent.is_some() {
+                watcher.permanent_failures += 1;
+                watcher.permanent_failure_since.get_or_insert_with(Utc::now);
+            } else {
+                watcher.permanent_failures = 0;
+                watcher.permanent_failure_since = None;
+            }
+            save_check_status(watcher, config, save_failure, log).await;
+            bus.publish(MonitorEvent::Error {
+                watcher_id: watcher.id.clone(),
+                url: watcher.url.clone(),
+                at: Utc::now(),
+                message: format!("{:#}", e),
+            });
+
+            // Stop checking URLs that are gone for good
+            let auto_disable = config.read().await.auto_disable.clone();
+            if let (Some(cause), Some(since)) = (permanent, watcher.permanent_failure_since) {
+                if auto_disable.applies(watcher.permanent_failures, since) {
+                    let reason = format!(
+                        "Gone: {} on {} checks in a row since {}",
+                        cause,
+                        watcher.permanent_failures,
+                        zone.format(since, "%Y-%m-%d")
+                    );
+                    auto_disable_watcher(watcher, config, reason, save_failure, log).await;
+                    report.disabled = true;
+                }
+            }
+        }
+    }
+    report
+}
+
+/// Record that a check got a block page: notify when the blocking starts
+/// (not on every check) and pause the watcher if it asks for that. Returns
+/// whether the watcher was disabled.
+async fn note_blocked(
+    watcher: &mut Watcher,
+    reason: &str,
+    settings: &NotificationSettings,
+    zone: Zone,
+    context: &CheckContext<'_>,
+) -> bool {
+    let CheckContext { config, save_failure, log, bus } = *context;
+    log.warning(format!("  ⚠ Blocked: {}", reason));
+
+    watcher.consecutive_errors += 1;
+    watcher.last_error = Some(format!("Blocked: {}", reason));
+    watcher.permanent_failures = 0;
+    watcher.permanent_failure_since = None;
+    let newly_blocked = watcher.blocked.replace(reason.to_string()).is_none();
+    save_check_status(watcher, config, save_failure, log).await;
+    bus.publish(MonitorEvent::Blocked {
+        watcher_id: watcher.id.clone(),
+        url: watcher.url.clone(),
+        at: Utc::now(),
+        reason: reason.to_string(),
+    });
+
+    if newly_blocked {
+        if let Err(e) = notify::send_blocked_notification(settings, &watcher.url, reason, watcher.pause_when_blocked).await {
+            log.error(format!("  ✗ Error: {}", e));
+        }
+    }
+    if !watcher.pause_when_blocked {
+        return false;
+    }
+    let reason = format!(
+        "Blocked: {} on {} (re-enable to resume)",
+        reason,
+        zone.format(Utc::now(), "%Y-%m-%d %H:%M")
+    );
+    log.info("  Pausing the blocked watcher");
+    disable_watcher(watcher, config, reason, save_failure, log).await;
+    true
+}
+
+/// Notify when the site's responses become slow (not on every slow check),
+/// and note when they're fast again. The check status is saved afterwards.
+async fn note_latency(
+    watcher: &mut Watcher,
+    response_ms: u64,
+    average: Option<u64>,
+    settings: &NotificationSettings,
+    log: &EventLog,
+) {
+    let Some(reason) = latency::slow_reason(watcher, response_ms, average) else {
+        if watcher.slow.take().is_some() {
+            log.info(format!("  ✓ Response time back to normal ({}ms)", response_ms));
+        }
+        return;
+    };
+
+    log.warning(format!("  ⚠ Slow: {}", reason));
+    if watcher.slow.replace(reason.clone()).is_none() {
+        if let Err(e) = notify::send_slow_notification(settings, &watcher.url, &reason).await {
+            log.error(format!("  ✗ Error: {}", e));
+        }
+    }
+}
+
+/// Remember (and notify once) that the page permanently moved, or forget
+/// the move once the redirect is gone. Works on the shared config, where
+/// the TUI applies or dismisses moves (saved with the check status).
+async fn note_move(
+    watcher: &Watcher,
+    moved_to: Option<String>,
+    config: &Arc<RwLock<Config>>,
+    settings: &NotificationSettings,
+    log: &EventLog,
+) {
+    let new_move = {
+        let mut cfg = config.write().await;
+        // Skip if the user switched the watcher to another URL meanwhile
+        let Some(w) = cfg.watchers.iter_mut().find(|w| w.id == watcher.id && w.url == watcher.url) else {
+            return;
+        };
+        match moved_to {
+            Some(target) if w.moved_to.as_ref() != Some(&target) && w.ignored_move.as_ref() != Some(&target) => {
+                w.moved_to = Some(target.clone());
+                Some(target)
+            }
+            Some(_) => None,
+            None => {
+                w.moved_to = None;
+                None
+            }
+        }
+    };
+
+    if let Some(target) = new_move {
+        log.warning(format!("  ⚠ Page moved permanently to {}", target));
+        if let Err(e) = notify::send_moved_notification(settings, &watcher.url, &target).await {
+            log.error(format!("  ✗ Error: {}", e));
+        }
+    }
+}
+
+/// Copy the outcome bookkeeping of a check (last check time, error streak)
+/// into the shared config and save it
+async fn save_check_status(
+    watcher: &Watcher,
+    config: &Arc<RwLock<Config>>,
+    save_failure: &watch::Sender<Option<String>>,
+    log: &EventLog,
+) {
+    {
+        let mut cfg = config.write().await;
+        if let Some(w) = cfg.watchers.iter_mut().find(|w| w.id == watcher.id) {
+            w.last_checked = watcher.last_checked;
+            w.consecutive_errors = watcher.consecutive_errors;
+            w.last_error = watcher.last_error.clone();
+            w.blocked = watcher.blocked.clone();
+            w.slow = watcher.slow.clone();
+            w.permanent_failures = watcher.permanent_failures;
+            w.permanent_failure_since = watcher.permanent_failure_since;
+        }
+    }
+    save_config(config, save_failure, log).await;
+}
+
+/// Append the outcome of a check to the watcher's history
+fn record_check(
+    watcher: &Watcher,
+    result: &Result<CheckResult>,
+    hook: Option<HookRun>,
+    storage: &Storage,
+    log: &EventLog,
+) {
+    let (outcome, http, value) = match result {
+        Ok(check) => {
+            let outcome = if !check.matched_keywords.is_empty() {
+                CheckOutcome::Matched { keywords: check.matched_keywords.clone() }
+            } else if check.changed {
+                CheckOutcome::Changed
+            } else {
+                CheckOutcome::Unchanged
+            };
+            (outcome, Some(check.meta.clone()), check.value)
+        }
+        Err(e) => match e.downcast_ref::<BlockedError>() {
+            Some(blocked) => (
+                CheckOutcome::Blocked { reason: blocked.reason.clone() },
+                Some(blocked.meta.clone()),
+                None,
+            ),
+            None => (
+                CheckOutcome::Error { message: format!("{:#}", e) },
+                // Keep the status and headers of error responses
+                e.downcast_ref::<HttpStatusError>().map(|err| err.meta.clone()),
+                None,
+            ),
+        },
+    };
+
+    let record = CheckRecord {
+        checked_at: Utc::now(),
+        outcome,
+        http,
+        value,
+        hook,
+    };
+    if let Err(e) = history::append(&watcher.id, &record, &storage.retention, &storage.encryption) {
+        log.error(format!("  ✗ {:#}", e));
+    }
+}
+
+/// Run the watcher's hook (if it has one) for an alert, logging how it went
+async fn run_hook(watcher: &Watcher, keywords: &[String], log: &EventLog) -> Option<HookRun> {
+    let settings = watcher.hook.as_ref()?;
+    let run = hook::run(settings, watcher, keywords).await;
+    match run.succeeded() {
+        true => log.info(format!("  Hook: {}", run.summary())),
+        false if run.error.is_some() => log.error(format!("  ✗ Hook: {}", run.summary())),
+        false => {
+            let stderr = run.stderr.lines().next().map(|line| format!(": {}", line)).unwrap_or_default();
+            log.warning(format!("  ⚠ Hook: {}{}", run.summary(), stderr));
+        }
+    }
+    Some(run)
+}
+
+/// Append something that happened to a streaming watcher to its history
+fn append_history(
+    watcher: &Watcher,
+    outcome: CheckOutcome,
+    hook: Option<HookRun>,
+    storage: &Storage,
+    log: &EventLog,
+) {
+    let record = CheckRecord {
+        checked_at: Utc::now(),
+        outcome,
+        http: None,
+        value: None,
+        hook,
+    };
+    if let Err(e) = history::append(&watcher.id, &record, &storage.retention, &storage.encryption) {
+        log.error(format!("  ✗ {:#}", e));
+    }
+}
+
+/// Disable an expired watcher and send a final notification, with the
+/// alert it held for a quiet period, if any: it won't get another chance
+async fn expire_watcher(
+    watcher: &Watcher,
+    state: &mut WatcherState,
+    config: &Arc<RwLock<Config>>,
+    save_failure: &watch::Sender<Option<String>>,
+    log: &EventLog,
+) {
+    let (settings, zone) = {
+        let cfg = config.read().await;
+        (cfg.notifications.clone(), cfg.timezone)
+    };
+    log.info(format!("{} has expired, disabling it", watcher.url));
+
+    if let Some(expires_at) = watcher.expires_at {
+        let reason = format!("Expired on {}", format_expiry(expires_at, &zone));
+        disable_watcher(watcher, config, reason, save_failure, log).await;
+    }
+
+    release_held(watcher, state, &settings, log).await;
+    if let Err(e) = notify::send_expired_notification(&settings, &watcher.url).await {
+        log.error(format!("  ✗ Error: {}", e));
+    }
+}
+
+/// Disable a watcher whose page is gone and tell the user why
+async fn auto_disable_watcher(
+    watcher: &Watcher,
+    config: &Arc<RwLock<Config>>,
+    reason: String,
+    save_failure: &watch::Sender<Option<String>>,
+    log: &EventLog,
+) {
+    let settings = config.read().await.notifications.clone();
+    log.warning(format!("⚠ {} seems to be gone, disabling it: {}", watcher.url, reason));
+
+    if let Err(e) = notify::send_auto_disabled_notification(&settings, &watcher.url, &reason).await {
+        log.error(format!("  ✗ Error: {}", e));
+    }
+    disable_watcher(watcher, config, reason, save_failure, log).await;
+}
+
+/// Disable a watcher in the saved config, recording why
+async fn disable_watcher(
+    watcher: &Watcher,
+    config: &Arc<RwLock<Config>>,
+    reason: String,
+    save_failure: &watch::Sender<Option<String>>,
+    log: &EventLog,
+) {
+    {
+        let mut cfg = config.write().await;
+        if let Some(w) = cfg.watchers.iter_mut().find(|w| w.id == watcher.id) {
+            w.enabled = false;
+            w.disabled_reason = Some(reason);
+        }
+    }
+    save_config(config, save_failure, log).await;
+}
+
+/// Save the config. Every failure is logged, but only the first of a run
+/// is notified, so a read-only filesystem or a full disk alerts the user
+/// once instead of on every check.
+async fn save_config(
+    config: &Arc<RwLock<Config>>,
+    save_failure: &watch::Sender<Option<String>>,
+    log: &EventLog,
+) {
+    let (result, settings) = {
+        let cfg = config.read().await;
+        // A managed config isn't written back: check results, error counts
+        // and disabled watchers only last for this run
+        if cfg.is_read_only() {
+            return;
+        }
+        // What has to be pushed is pushed below, without holding the config
+        let saved = cfg.save_unpushed().map(|push| push.then(|| cfg.clone()));
+        (saved, cfg.notifications.clone())
+    };
+    let result = match result {
+        Ok(Some(saved)) => tokio::task::spawn_blocking(move || saved.push_saved())
+            .await
+            .unwrap_or_else(|e| Err(e.into())),
+        Ok(None) => Ok(()),
+        Err(e) => Err(e),
+    };
+
+    match result {
+        Ok(()) => {
+            if save_failure.send_replace(None).is_some() {
+                log.info("Config saved again");
+            }
+        }
+        Err(e) => {
+            let error = format!("{:#}", e);
+            log.error(format!("  ✗ Failed to save config: {}", error));
+
+            if save_failure.send_replace(Some(error.clone())).is_none() {
+                if let Err(e) = notify::send_save_failed_notification(&settings, &error).await {
+                    log.error(format!("  ✗ Error: {}", e));
+                }
+            }
+        }
+    }
+}
+
+/// Result of a successful check
+struct CheckResult {
+    /// Content differs from the cached version
+    changed: bool,
+    /// Keywords that were found and notified (empty if none)
+    matched_keywords: Vec<String>,
+    /// Alerted keywords that are no longer on the page
+    cleared_keywords: Vec<String>,
+    /// HTTP metadata of the fetch
+    meta: ResponseMeta,
+    /// The value read from the page (watchers tracking a value)
+    value: Option<f64>,
+}
+
+/// How checks keep what they saw: the config's settings at the time
+struct Storage {
+    retention: HistoryRetention,
+    /// Only a fingerprint of each page instead of a copy
+    minimize: bool,
+    encryption: EncryptionSettings,
+}
+
+impl Storage {
+    fn of(config: &Config) -> Self {
+        Storage {
+            retention: config.history.clone(),
+            minimize: config.minimize_data,
+            encryption: config.encryption.clone(),
+        }
     }
 }
 
 /// Check a single watcher once
-/// Returns Ok((found_matches, matched_keywords)) where:
-/// - found_matches: true if keywords were found, false otherwise
-/// - matched_keywords: list of keywords that were found
-async fn check_watcher(watcher: &Watcher) -> Result<(bool, Vec<String>)> {
-    // 1. Fetch the URL
-    let new_content = fetcher::fetch_url(&watcher.url)
+async fn check_watcher(
+    watcher: &Watcher,
+    site: &SiteSettings,
+    keyword_matcher: &KeywordMatcher,
+    state: &mut WatcherState,
+    settings: &NotificationSettings,
+    max_page_size: Option<u64>,
+    storage: &Storage,
+) -> Result<CheckResult> {
+    // Lightweight mode: a HEAD request decides whether the full GET is needed
+    let validators = if watcher.lightweight {
+        match fetcher::head(&watcher.url, &site.request).await {
+            Ok((meta, validators)) => {
+                if !validators.is_empty() && state.validators.as_ref() == Some(&validators) {
+                    // Same page, same value
+                    return Ok(CheckResult {
+                        changed: false,
+                        matched_keywords: Vec::new(),
+                        cleared_keywords: Vec::new(),
+                        meta,
+                        value: watcher.track_value.as_ref().and(state.last_value),
+                    });
+                }
+                Some(validators)
+            }
+            // Some servers don't support HEAD: fall back to a full check
+            Err(_) => None,
+        }
+    } else {
+        None
+    };
+
+    let result = full_check(watcher, site, keyword_matcher, state, settings, max_page_size, storage).await?;
+
+    // Only remember the validators once the full check has succeeded, so a
+    // failed check is retried instead of being skipped next time
+    if let Some(validators) = validators {
+        state.set_validators(validators);
+    }
+
+    Ok(result)
+}
+
+/// Fetch, diff and notify (the full check). With data minimized, only a
+/// fingerprint of the page is kept instead of a copy, and alerts have no
+/// diff.
+async fn full_check(
+    watcher: &Watcher,
+    site: &SiteSettings,
+    keyword_matcher: &KeywordMatcher,
+    state: &mut WatcherState,
+    settings: &NotificationSettings,
+    max_page_size: Option<u64>,
+    storage: &Storage,
+) -> Result<CheckResult> {
+    // 1. Fetch the URL (pages over the size cap are only hashed)
+    let Fetched { page, meta } = fetcher::fetch_page(&watcher.url, &site.request, max_page_size, &watcher.keywords)
         .await
         .context("Failed to fetch URL")?;
 
-    // 2. Get cached content
     let cache_path = watcher.full_cache_path()?;
+    let mut new_content = match page {
+        // Only the part of the page the selector picks is watched (pages
+        // over the size cap are never parsed, so they're watched whole)
+        Page::Text(content) => select(site, content)?,
+        Page::Oversized(digest) => {
+            let (changed, matched_keywords, cleared_keywords) =
+                check_oversized(watcher, state, settings, &cache_path, digest, &storage.encryption).await?;
+            return Ok(CheckResult { changed, matched_keywords, cleared_keywords, meta, value: None });
+        }
+    };
+
+    // Consensus mode: re-fetch and keep only what every fetch agrees on
+    if watcher.consensus_fetches > 1 {
+        new_content = consensus_fetch(watcher, site, new_content).await?;
+    }
+
+    // The tracked value is read on every check, changed or not
+    let trend = match &watcher.track_value {
+        Some(pattern) => {
+            let regex = values::compile(pattern).context("Invalid track_value pattern")?;
+            values::extract(&regex, &new_content).map(|value| state.track_value(value))
+        }
+        None => None,
+    };
+    let value = trend.map(|trend| trend.value);
+
+    // 2. Get cached content (a copy kept before minimizing was turned on
+    // is still compared with once)
     let old_content = cache::read_cache(&cache_path)?;
+    let new_hash = if storage.minimize {
+        diff::fingerprint(&new_content)
+    } else {
+        cache::content_hash(new_content.as_bytes())
+    };
 
     // 3. Check if content has changed
     let has_changed = match &old_content {
         Some(old) => diff::has_changed(old, &new_content),
-        None => true, // No cache means this is the first check
+        // No cache means this is the first check, unless the page used to be
+        // over the size cap (or data is minimized) and only its hash was kept
+        None => cache::read_digest(&cache_path)? != Some(new_hash.clone()),
     };
 
     if !has_changed {
-        return Ok((false, Vec::new()));
+        return Ok(CheckResult {
+            changed: false,
+            matched_keywords: Vec::new(),
+            cleared_keywords: Vec::new(),
+            meta,
+            value,
+        });
     }
 
-    // 4. Content has changed, search for keywords
-    let matches = matcher::find_keywords(&new_content, &watcher.keywords);
+    // 4. Content has changed, search for keywords (skipping ones already
+    // notified) and notice alerted keywords that are gone
+    let mut found = keyword_matcher.find(&new_content);
+    let foreign = site
+        .translation
+        .as_ref()
+        .and_then(|translation| Some((translation, translate::foreign_language(translation, &new_content)?)));
+    if let Some((translation, language)) = foreign {
+        let translated = translate::find_translated(translation, language, &watcher.keywords, state, &new_content)
+            .await
+            .context("Failed to translate the keywords")?;
+        found.extend(translated);
+    }
+    let found_keywords: Vec<String> = found.iter().map(|m| m.keyword.clone()).collect();
+    let cleared_keywords = note_cleared(watcher, state, settings, &found_keywords).await?;
+    let mut matches = state.new_matches(found);
+    if let (Some((translation, language)), false) = (foreign, matches.is_empty()) {
+        // Alert untranslated rather than not at all
+        let _ = translate::translate_contexts(translation, language, &mut matches).await;
+    }
 
     // 5. Send notification if keywords found
     if !matches.is_empty() {
-        // Get unique keywords that were matched
-        let matched_keywords: Vec<String> = matches
-            .iter()
-            .map(|m| m.keyword.clone())
-            .collect::<std::collections::HashSet<_>>()
-            .into_iter()
-            .collect();
+        // Path-based change list for JSON endpoints, the changed region
+        // side by side for other pages
+        // (not with minimized data: the alert only has the match contexts)
+        let old = old_content.as_deref().filter(|_| !storage.minimize);
+        let mut changes = Changes {
+            json: old
+                .and_then(|old| diff::json_changes(old, &new_content))
+                .unwrap_or_default(),
+            lines: None,
+        };
 
-        notify::send_notification(&watcher.url, &matches)?;
+        // What changed, kept with the alert for copying from the TUI
+        let summary = match old {
+            Some(_) if !changes.json.is_empty() => Some(changes.json.join("\n")),
+            Some(old) => {
+                changes.lines = diff::side_by_side(old, &new_content, watcher.diff);
+                Some(diff::get_diff(old, &new_content, watcher.diff))
+            }
+            None => None,
+        };
+
+        let matched_keywords =
+            notify_matches(watcher, state, settings, &matches, &changes, summary, trend.as_ref()).await?;
 
         // Update cache since we found matches
-        cache::write_cache(&cache_path, &new_content)?;
+        update_cache(&cache_path, &new_content, storage, &new_hash)?;
+
+        return Ok(CheckResult { changed: true, matched_keywords, cleared_keywords, meta, value });
+    }
+
+    // 6. No keywords found: queue the change for review (a change that
+    // can't be queued isn't worth failing the check), and update the cache
+    if let (Some(old), false) = (old_content.as_deref(), storage.minimize) {
+        let _ = review::add(watcher, old, &new_content, &storage.encryption);
+    }
+    update_cache(&cache_path, &new_content, storage, &new_hash)?;
+
+    Ok(CheckResult {
+        changed: true,
+        matched_keywords: Vec::new(),
+        cleared_keywords,
+        meta,
+        value,
+    })
+}
+
+/// Cache the checked page, or only its fingerprint when data is minimized
+fn update_cache(cache_path: &Path, content: &str, storage: &Storage, fingerprint: &str) -> Result<()> {
+    match storage.minimize {
+        true => cache::write_digest(cache_path, fingerprint, &storage.encryption),
+        false => cache::write_cache(cache_path, content, &storage.encryption),
+    }
+}
+
+/// The part of a fetched page the watcher looks at
+fn select(site: &SiteSettings, content: String) -> Result<String> {
+    domains::watched_part(site, content)
+}
+
+/// Find the alerted keywords that are no longer among those `found` on the
+/// page, and send a "cleared" notification if the watcher wants one.
+/// Returns the cleared keywords.
+async fn note_cleared(
+    watcher: &Watcher,
+    state: &mut WatcherState,
+    settings: &NotificationSettings,
+    found: &[String],
+) -> Result<Vec<String>> {
+    let cleared = state.absent_keywords(found);
+    if !cleared.is_empty() && watcher.notify_cleared && quiet_period(watcher).is_none() {
+        notify::send_cleared_notification(settings, &watcher.url, &cleared).await?;
+    }
+
+    // Only forget them once notified, so a failed notification is retried
+    state.forget_absent(found, watcher.notify_cleared);
+    Ok(cleared)
+}
+
+/// Fetch the page again (up to `consensus_fetches` in total) and reduce the
+/// fetches to the content they all share
+async fn consensus_fetch(watcher: &Watcher, site: &SiteSettings, first: String) -> Result<String> {
+    let mut fetches = vec![first];
+    let total = watcher.consensus_fetches.min(MAX_CONSENSUS_FETCHES);
+
+    while fetches.len() < total as usize {
+        // Small pause so the fetches don't hit the same edge cache request
+        sleep(CONSENSUS_DELAY).await;
+        let content = fetcher::fetch_url(&watcher.url, &site.request)
+            .await
+            .context("Failed to fetch URL (consensus)")?;
+        fetches.push(select(site, content)?);
+    }
+
+    Ok(diff::consensus(&fetches))
+}
 
-        return Ok((true, matched_keywords));
+/// Hash-only check for a page over the size cap
+///
+/// Any change in the raw body counts, and keyword matches carry no context.
+/// Returns (changed, notified keywords, cleared keywords).
+async fn check_oversized(
+    watcher: &Watcher,
+    state: &mut WatcherState,
+    settings: &NotificationSettings,
+    cache_path: &Path,
+    digest: PageDigest,
+    encryption: &EncryptionSettings,
+) -> Result<(bool, Vec<String>, Vec<String>)> {
+    let old_hash = cache::read_digest(cache_path)?;
+    if old_hash.as_deref() == Some(digest.hash.as_str()) {
+        return Ok((false, Vec::new(), Vec::new()));
+    }
+
+    let cleared_keywords = note_cleared(watcher, state, settings, &digest.keywords_found).await?;
+
+    let matches: Vec<KeywordMatch> = digest
+        .keywords_found
+        .iter()
+        .filter_map(|text| watcher.keywords.iter().find(|k| k.text == *text))
+        .map(|keyword| {
+            KeywordMatch::new(
+                keyword,
+                String::from("(page is over the size limit: no context available)"),
+            )
+        })
+        .collect();
+    let matches = state.new_matches(matches);
+
+    let matched_keywords = if matches.is_empty() {
+        Vec::new()
+    } else {
+        notify_matches(watcher, state, settings, &matches, &Changes::default(), None, None).await?
+    };
+
+    cache::write_digest(cache_path, &digest.hash, encryption)?;
+
+    Ok((true, matched_keywords, cleared_keywords))
+}
+
+/// Notify about new matches and remember them in the watcher's state
+/// (the monitor loop saves the state)
+/// Returns the distinct keywords that were matched
+async fn notify_matches(
+    watcher: &Watcher,
+    state: &mut WatcherState,
+    settings: &NotificationSettings,
+    matches: &[KeywordMatch],
+    changes: &Changes,
+    diff: Option<String>,
+    trend: Option<&Trend>,
+) -> Result<Vec<String>> {
+    let title = watcher.alert_title.as_deref();
+    match quiet_period(watcher) {
+        Some(_) => state.hold(matches),
+        None => notify::send_notification(settings, &watcher.url, matches, changes, title, trend).await?,
     }
 
-    // 6. No keywords found, but still update cache
-    cache::write_cache(&cache_path, &new_content)?;
+    state.record(matches);
+    state.set_last_alert(matches, diff);
+
+    // Get unique keywords that were matched
+    let mut matched_keywords: Vec<String> = matches.iter().map(|m| m.keyword.clone()).collect();
+    matched_keywords.dedup();
+    Ok(matched_keywords)
+}
+
+/// Alert on the matches held during a quiet period, once it's over (a
+/// channel that fails keeps the alert in the outbox, like any other).
+/// Returns the keywords alerted on, if any.
+async fn send_held(
+    watcher: &Watcher,
+    state: &mut WatcherState,
+    settings: &NotificationSettings,
+    log: &EventLog,
+) -> Option<Vec<String>> {
+    if state.held.is_empty() || quiet_period(watcher).is_some() {
+        return None;
+    }
+    release_held(watcher, state, settings, log).await
+}
 
-    Ok((false, Vec::new()))
+/// Alert on the held matches now, quiet period or not
+async fn release_held(
+    watcher: &Watcher,
+    state: &mut WatcherState,
+    settings: &NotificationSettings,
+    log: &EventLog,
+) -> Option<Vec<String>> {
+    if state.held.is_empty() {
+        return None;
+    }
+
+    let held = state.take_held();
+    let mut keywords: Vec<String> = held.iter().map(|m| m.keyword.clone()).collect();
+    keywords.dedup();
+    let title = watcher.alert_title.as_deref();
+    match notify::send_notification(settings, &watcher.url, &held, &Changes::default(), title, None).await {
+        Ok(()) => log.matched(format!("  ✓ Sent the alert held for the quiet period: {}", keywords.join(", "))),
+        Err(e) => log.error(format!("  ✗ Failed to send the alert held during the quiet period: {:#}", e)),
+    }
+    if let Err(e) = state.save(&watcher.id) {
+        log.error(format!("  ✗ {:#}", e));
+    }
+    Some(keywords)
+}
+
+/// Disable a one-shot watcher that matched during a quiet period once its
+/// held alert is out. Returns whether it was disabled.
+async fn finish_one_shot(
+    watcher: &Watcher,
+    state: &mut WatcherState,
+    context: &CheckContext<'_>,
+) -> bool {
+    let CheckContext { config, save_failure, log, .. } = *context;
+    if !watcher.disable_after_match || state.held.is_empty() {
+        return false;
+    }
+
+    let (settings, zone) = {
+        let cfg = config.read().await;
+        (cfg.notifications.clone(), cfg.timezone)
+    };
+    let Some(keywords) = send_held(watcher, state, &settings, log).await else {
+        return false;
+    };
+    let reason = format!("Matched {} on {}", keywords.join(", "), zone.format(Utc::now(), "%Y-%m-%d %H:%M"));
+    log.info("  One-shot watcher done, disabling it");
+    disable_watcher(watcher, config, reason, save_failure, log).await;
+    true
+}
+
+/// The quiet period (an event of a calendar) keeping the watcher's alerts
+/// from being sent, if one is on
+fn quiet_period(watcher: &Watcher) -> Option<calendar::Event> {
+    if watcher.critical {
+        return None;
+    }
+    calendar::current(CalendarAction::Quiet)
+}
+
+/// Whether an alert was sent, for the log (`held`: a keyword alert, sent
+/// when the quiet period is over)
+fn alert_note(watcher: &Watcher, zone: Zone, held: bool) -> String {
+    match quiet_period(watcher) {
+        Some(event) => format!("Quiet for \"{}\" until {}: {}",
+            event.summary,
+            zone.format(event.end, "%a %H:%M"),
+            if held { "notification held until then" } else { "no notification" }),
+        None => String::from("Notification sent"),
+    }
 }
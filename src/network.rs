@@ -0,0 +1,104 @@
+//! Network connectivity detection
+//!
+//! Lets the monitor pause checks while the machine is offline (instead of
+//! logging a fetch error for every watcher on every interval) or while the
+//! default route goes through an interface the user marked as metered,
+//! such as a phone hotspot.
+
+use std::fmt;
+use std::net::UdpSocket;
+
+/// Current state of the network, as far as checks are concerned
+#[derive(Debug, Clone, PartialEq)]
+pub enum Connectivity {
+    Online,
+    Offline,
+    /// Online through a metered interface (its name)
+    Metered(String),
+}
+
+impl Connectivity {
+    /// Whether watchers should run their checks
+    pub fn allows_checks(&self) -> bool {
+        *self == Connectivity::Online
+    }
+}
+
+impl fmt::Display for Connectivity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Connectivity::Online => write!(f, "online"),
+            Connectivity::Offline => write!(f, "offline"),
+            Connectivity::Metered(interface) => write!(f, "on metered connection {}", interface),
+        }
+    }
+}
+
+/// Detect the current connectivity. Blocking (may run `route` on macOS).
+pub fn detect(metered_interfaces: &[String]) -> Connectivity {
+    if !has_route() {
+        return Connectivity::Offline;
+    }
+
+    if !metered_interfaces.is_empty() {
+        if let Some(interface) = default_interface() {
+            if metered_interfaces.iter().any(|m| m.eq_ignore_ascii_case(&interface)) {
+                return Connectivity::Metered(interface);
+            }
+        }
+    }
+
+    Connectivity::Online
+}
+
+/// Whether there is a route to the internet. Connecting a UDP socket sends
+/// no packets, but fails right away when no route exists.
+fn has_route() -> bool {
+    let reachable = |bind: &str, target: &str| {
+        UdpSocket::bind(bind)
+            .and_then(|socket| socket.connect(target))
+            .is_ok()
+    };
+
+    reachable("0.0.0.0:0", "1.1.1.1:53") || reachable("[::]:0", "[2606:4700:4700::1111]:53")
+}
+
+/// Name of the interface the default route goes through
+#[cfg(target_os = "linux")]
+fn default_interface() -> Option<String> {
+    let routes = std::fs::read_to_string("/proc/net/route").ok()?;
+
+    // Columns: Iface Destination Gateway Flags RefCnt Use Metric ...
+    routes
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let flags = u32::from_str_radix(fields.get(3)?, 16).ok()?;
+            let metric: u32 = fields.get(6)?.parse().ok()?;
+            // Default route (destination 0.0.0.0) that is up (RTF_UP)
+            (fields[1] == "00000000" && flags & 1 == 1).then(|| (metric, fields[0].to_string()))
+        })
+        .min()
+        .map(|(_, interface)| interface)
+}
+
+/// Name of the interface the default route goes through
+#[cfg(target_os = "macos")]
+fn default_interface() -> Option<String> {
+    let output = std::process::Command::new("route")
+        .args(["-n", "get", "default"])
+        .output()
+        .ok()?;
+
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("interface:"))
+        .map(|interface| interface.trim().to_string())
+}
+
+/// Name of the interface the default route goes through (unsupported here)
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+fn default_interface() -> Option<String> {
+    None
+}
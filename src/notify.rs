@@ -1,49 +1,794 @@
-/// Notification system module
-///
-/// Sends macOS notifications when keywords are found
+//! Notification system module
+//!
+//! Sends alerts when keywords are found (and when a watcher expires),
+//! through each enabled channel:
+//! native macOS notifications and/or a JSON webhook.
+//! The optional startup summary is always a desktop notification.
+//!
+//! Without a desktop session (a server or a container) desktop
+//! notifications are skipped, leaving the webhook as the only channel.
+//!
+//! Every channel is a `Notifier`. The ones an alert goes to are collected
+//! in `Notifiers`, from the notification settings plus any notifier code
+//! using the library `register`ed (the integration tests record alerts
+//! this way), so a new channel is one more implementation of the trait.
+//!
+//! A notifier that fails to deliver an alert hands it to the `outbox`,
+//! which retries it later.
+//!
+//! A watcher can give its keyword alerts a title template of its own, like
+//! "{{price}} at Example Shop": `{{name}}` is filled in with the value of
+//! the named group `name` of a regex keyword that matched (see
+//! `fill_title`). The values are also sent to the webhook as `fields`.
+//! Watchers tracking a value add `{{value}}` and `{{change}}`, and their
+//! alerts say how the value moved since the previous check.
+//!
+//! What changed on the page goes with a keyword alert: the changed paths of
+//! a JSON endpoint, or for other pages (to HTML notifiers, like an email
+//! gateway) the changed region side by side, so the alert alone is enough
+//! to judge the change.
 
 use anyhow::{Context, Result};
-use crate::matcher::KeywordMatch;
+use chrono::{SecondsFormat, Utc};
+use crate::cluster::ClusterNotifier;
+use crate::config::{DesktopStyle, DesktopStyles, NotificationSettings};
+use crate::diff::SideBySide;
+use crate::markup::{Block, Format, Item};
+use crate::matcher::{Keyword, KeywordMatch, Priority};
+use crate::logfiles;
+use crate::outbox;
+use crate::tasks::TaskNotifier;
+use crate::values::{self, Trend};
+use futures_util::future::BoxFuture;
 use notify_rust::Notification;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::sync::{Arc, PoisonError, RwLock};
+use std::time::Duration;
 
-/// Send a macOS notification about keyword matches
-pub fn send_notification(url: &str, matches: &[KeywordMatch]) -> Result<()> {
-    if matches.is_empty() {
+/// Maximum number of distinct keywords listed in one notification body
+const MAX_KEYWORDS_IN_BODY: usize = 5;
+
+/// Maximum length (in characters) of each keyword's context snippet
+const MAX_CONTEXT_CHARS: usize = 120;
+
+/// Maximum number of JSON change lines listed in one notification body
+const MAX_CHANGES_IN_BODY: usize = 5;
+
+/// Page the alerts of `notify test` are about
+const TEST_URL: &str = "https://example.com/web-watcher-alert-test";
+
+/// Maximum number of upcoming checks listed in the startup summary
+const MAX_NEXT_CHECKS_IN_BODY: usize = 5;
+
+/// A way of delivering alerts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Channel {
+    Desktop,
+    Webhook,
+    Tasks,
+}
+
+impl Channel {
+    pub fn name(self) -> &'static str {
+        match self {
+            Channel::Desktop => "desktop",
+            Channel::Webhook => "webhook",
+            Channel::Tasks => "tasks",
+        }
+    }
+
+    /// The built-in channel with this name
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.trim().to_lowercase().as_str() {
+            "desktop" => Some(Channel::Desktop),
+            "webhook" => Some(Channel::Webhook),
+            "tasks" => Some(Channel::Tasks),
+            _ => None,
+        }
+    }
+
+    pub fn parse(input: &str) -> Result<Self> {
+        Self::from_name(input).ok_or_else(|| {
+            anyhow::anyhow!("Unknown channel: {} (use desktop, webhook or tasks)", input.trim().to_lowercase())
+        })
+    }
+}
+
+/// What changed on a page, for its keyword alert
+#[derive(Debug, Clone, Default)]
+pub struct Changes {
+    /// Changed paths of a JSON endpoint
+    pub json: Vec<String>,
+    /// The changed region of other pages, shown by HTML notifiers
+    pub lines: Option<SideBySide>,
+}
+
+/// An alert, as composed for one notifier
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Message {
+    pub title: String,
+    /// In the notifier's format
+    pub body: String,
+    /// Page the alert is about (empty for alerts about the app itself)
+    pub url: String,
+    /// Keywords the alert is about
+    pub keywords: Vec<String>,
+    /// Highest priority of the keywords (keyword alerts only)
+    pub priority: Option<Priority>,
+    /// Values of the matched regex keywords' named groups (keyword alerts
+    /// only)
+    pub fields: BTreeMap<String, String>,
+}
+
+/// A way of delivering alerts
+pub trait Notifier: Send + Sync {
+    /// Lowercase name, like "webhook". Keywords limited to some channels
+    /// only go to the built-in notifiers of those channels.
+    fn name(&self) -> &str;
+
+    /// What the notifier displays: alerts are rendered in this format
+    /// (keywords in bold, changes as code)
+    fn format(&self) -> Format {
+        Format::Plain
+    }
+
+    /// Deliver an alert
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<()>>;
+
+    /// Whether the notifier passes alerts on to other channels (the
+    /// coordinator): it gets the keywords limited to channels as well
+    fn relays(&self) -> bool {
+        false
+    }
+}
+
+/// Native desktop notifications, styled by the priority of the alert
+pub struct DesktopNotifier {
+    pub styles: DesktopStyles,
+}
+
+impl Notifier for DesktopNotifier {
+    fn name(&self) -> &str {
+        Channel::Desktop.name()
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<()>> {
+        let style = self.styles.for_priority(message.priority.unwrap_or_default());
+        let group = Some(message.url.as_str()).filter(|url| style.group && !url.is_empty());
+        let result = send_desktop(&message.title, &message.body, style, group);
+        Box::pin(async move { result })
+    }
+}
+
+/// A JSON POST to a webhook URL
+pub struct WebhookNotifier {
+    pub url: String,
+    /// What the receiving service displays (Markdown for chat services)
+    pub format: Format,
+}
+
+impl Notifier for WebhookNotifier {
+    fn name(&self) -> &str {
+        Channel::Webhook.name()
+    }
+
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<()>> {
+        Box::pin(send_webhook(&self.url, message))
+    }
+}
+
+/// Notifiers registered in code, which get every alert
+static REGISTERED: RwLock<Vec<Arc<dyn Notifier>>> = RwLock::new(Vec::new());
+
+/// Send all alerts of this process to `notifier` as well, whatever the
+/// notification settings. Keyword alerts only include the keywords that
+/// aren't limited to some channels.
+pub fn register(notifier: Arc<dyn Notifier>) {
+    REGISTERED.write().unwrap_or_else(PoisonError::into_inner).push(notifier);
+}
+
+/// The notifiers an alert goes to
+pub struct Notifiers {
+    notifiers: Vec<Arc<dyn Notifier>>,
+}
+
+impl Notifiers {
+    /// Where alerts go: the coordinator if this daemon has a part in a
+    /// cluster, otherwise this machine's channels
+    pub fn from_settings(settings: &NotificationSettings) -> Self {
+        if settings.cluster.role.is_some() {
+            return Self {
+                notifiers: vec![Arc::new(ClusterNotifier {
+                    settings: settings.cluster.clone(),
+                    local: settings.clone(),
+                })],
+            };
+        }
+        Self::local(settings)
+    }
+
+    /// The channels enabled in the settings that work in this environment,
+    /// followed by the registered notifiers
+    pub fn local(settings: &NotificationSettings) -> Self {
+        let mut notifiers: Vec<Arc<dyn Notifier>> = Vec::new();
+        if settings.desktop && desktop_available() {
+            notifiers.push(Arc::new(DesktopNotifier {
+                styles: settings.desktop_styles.clone(),
+            }));
+        }
+        if let Some(url) = settings.webhook() {
+            notifiers.push(Arc::new(WebhookNotifier {
+                url,
+                format: settings.webhook_format,
+            }));
+        }
+        if settings.tasks.target.is_some() {
+            notifiers.push(Arc::new(TaskNotifier {
+                settings: settings.tasks.clone(),
+            }));
+        }
+        notifiers.extend(REGISTERED.read().unwrap_or_else(PoisonError::into_inner).iter().cloned());
+        Self { notifiers }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.notifiers.is_empty()
+    }
+
+    /// Names of the notifiers, in order
+    pub fn names(&self) -> Vec<&str> {
+        self.notifiers.iter().map(|notifier| notifier.name()).collect()
+    }
+
+    /// The notifier with this name
+    pub fn get(&self, name: &str) -> Option<&Arc<dyn Notifier>> {
+        self.notifiers.iter().find(|notifier| notifier.name() == name)
+    }
+
+    /// Send each notifier the keyword alert composed for it (skipping those
+    /// none of the matches go to), titled from the watcher's template if it
+    /// has one
+    pub async fn send_matches(
+        &self,
+        url: &str,
+        matches: &[KeywordMatch],
+        changes: &Changes,
+        title: Option<&str>,
+        trend: Option<&Trend>,
+    ) -> Result<()> {
+        let mut first_error = None;
+        for notifier in &self.notifiers {
+            if let Some(alert) = compose_alert(url, matches, changes, title, trend, notifier.as_ref()) {
+                if let Err(e) = send_or_queue(notifier.as_ref(), &alert).await {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Send a message with a plain-text body to every notifier, in its
+    /// format
+    ///
+    /// Every notifier is attempted even if an earlier one fails. Failed
+    /// deliveries are queued for a retry; the first failure to queue one is
+    /// returned.
+    pub async fn send(&self, message: &Message) -> Result<()> {
+        let mut first_error = None;
+        for notifier in &self.notifiers {
+            let message = Message {
+                body: notifier.format().render_plain(&message.body),
+                ..message.clone()
+            };
+            if let Err(e) = send_or_queue(notifier.as_ref(), &message).await {
+                first_error.get_or_insert(e);
+            }
+        }
+
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+
+    /// Send each notifier (or only the one named `only`) a sample keyword
+    /// alert, to try its settings without waiting for a match. Failures are
+    /// returned per notifier, not queued.
+    pub async fn send_test(&self, only: Option<&str>) -> Vec<(String, Result<()>)> {
+        let keyword = Keyword::new(String::from("test"));
+        let matches = [KeywordMatch::new(
+            &keyword,
+            String::from("A sample alert from `notify test`: this channel works"),
+        )];
+        let mut results = Vec::new();
+        for notifier in self.notifiers.iter().filter(|n| only.is_none_or(|name| n.name() == name)) {
+            let result = match compose_alert(
+                TEST_URL,
+                &matches,
+                &Changes::default(),
+                Some("Web Watcher Alert: test alert"),
+                None,
+                notifier.as_ref(),
+            ) {
+                Some(alert) => notifier.send(&alert).await,
+                None => Ok(()),
+            };
+            results.push((notifier.name().to_string(), result));
+        }
+        results
+    }
+}
+
+/// Deliver a message, or keep it in the outbox if that fails. Fails only
+/// if the message can't be queued either.
+async fn send_or_queue(notifier: &dyn Notifier, message: &Message) -> Result<()> {
+    let result = notifier.send(message).await;
+    record_delivery(notifier.name(), message, &result, false);
+    let Err(e) = result else {
         return Ok(());
+    };
+    outbox::queue(notifier.name(), message, &e)
+        .with_context(|| format!("{:#} (and it couldn't be queued for a retry)", e))
+}
+
+/// Add a delivery (or a failed one) of an alert to the events file
+/// (`retry`: from the outbox)
+pub fn record_delivery(notifier: &str, message: &Message, result: &Result<()>, retry: bool) {
+    let mut event = serde_json::json!({
+        "time": Utc::now().to_rfc3339_opts(SecondsFormat::Millis, true),
+        "event": "notification",
+        "channel": notifier,
+        "url": message.url,
+        "title": message.title,
+        "keywords": message.keywords,
+        "delivered": result.is_ok(),
+        "retry": retry,
+    });
+    if let Err(e) = result {
+        event["error"] = format!("{:#}", e).into();
     }
+    logfiles::record(event);
+}
+
+/// Send a notification about keyword matches through all enabled channels
+///
+/// All matches are aggregated into a single notification that lists each
+/// distinct keyword once, together with the context of its first occurrence.
+/// Keywords limited to some channels are left out of the others, and
+/// high-priority keywords are listed first.
+/// `changes` is what changed on the page (if known), `trend` the value the
+/// watcher tracks.
+pub async fn send_notification(
+    settings: &NotificationSettings,
+    url: &str,
+    matches: &[KeywordMatch],
+    changes: &Changes,
+    title: Option<&str>,
+    trend: Option<&Trend>,
+) -> Result<()> {
+    Notifiers::from_settings(settings).send_matches(url, matches, changes, title, trend).await
+}
 
-    // Get unique keywords that were found
-    let keywords: Vec<String> = matches
+/// The alert for the matches that go to `notifier` (None if none do).
+/// Notifiers other than the built-in channels get the matches not limited
+/// to channels, unless they relay alerts.
+fn compose_alert(
+    url: &str,
+    matches: &[KeywordMatch],
+    changes: &Changes,
+    title: Option<&str>,
+    trend: Option<&Trend>,
+    notifier: &dyn Notifier,
+) -> Option<Message> {
+    let channel = Channel::from_name(notifier.name());
+    let matches: Vec<&KeywordMatch> = matches
         .iter()
-        .map(|m| m.keyword.clone())
-        .collect::<std::collections::HashSet<_>>()
-        .into_iter()
+        .filter(|m| notifier.relays() || channel.map_or(m.channels.is_none(), |channel| m.alerts_through(channel)))
         .collect();
+    if matches.is_empty() {
+        return None;
+    }
 
-    let keyword_list = keywords.join(", ");
+    // Keep the first match for each distinct keyword, in order of appearance
+    let mut first_matches: Vec<&KeywordMatch> = Vec::new();
+    for m in &matches {
+        if !first_matches.iter().any(|f| f.keyword == m.keyword) {
+            first_matches.push(m);
+        }
+    }
+    // Most urgent first (the sort is stable, so order of appearance remains
+    // within a priority)
+    first_matches.sort_by_key(|m| std::cmp::Reverse(m.priority));
+    let priority = first_matches[0].priority;
+
+    let keyword_list = first_matches
+        .iter()
+        .map(|m| m.keyword.as_str())
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    // Named groups of the first match that has each
+    let mut fields = BTreeMap::new();
+    for m in &first_matches {
+        for (name, value) in &m.captures {
+            fields.entry(name.clone()).or_insert_with(|| value.clone());
+        }
+    }
+    // The tracked value, unless a group has the same name
+    if let Some(trend) = trend {
+        fields.entry(String::from("value")).or_insert_with(|| values::format_value(trend.value));
+        if let Some(change) = trend.signed_change() {
+            fields.entry(String::from("change")).or_insert(change);
+        }
+    }
 
     // Create notification title
-    let title = format!("Web Watcher Alert: {} found!", keyword_list);
+    let title = if let Some(template) = title {
+        fill_title(template, url, &keyword_list, &fields)
+    } else if priority == Priority::High {
+        format!("Web Watcher Alert (high priority): {} found!", keyword_list)
+    } else {
+        format!("Web Watcher Alert: {} found!", keyword_list)
+    };
 
-    // Create notification body with context from first match
-    let body = if matches.len() == 1 {
-        format!("Found on {}\n\n{}", url, matches[0].context)
+    // Create notification body with one context snippet per keyword
+    let header = if matches.len() == 1 {
+        format!("Found on {}", url)
     } else {
-        format!(
-            "Found {} matches on {}\n\n{}",
-            matches.len(),
-            url,
-            matches[0].context
-        )
+        format!("Found {} matches on {}", matches.len(), url)
     };
 
-    // Send the notification
-    Notification::new()
-        .summary(&title)
-        .body(&body)
-        .sound_name("default")
-        .show()
-        .context("Failed to send notification")?;
+    let items = first_matches
+        .iter()
+        .take(MAX_KEYWORDS_IN_BODY)
+        .map(|m| {
+            let context = truncate(&m.context, MAX_CONTEXT_CHARS);
+            // Translated matches show the translation, then the original
+            match &m.translation {
+                Some(translation) => Item {
+                    label: m.keyword.clone(),
+                    text: truncate(translation, MAX_CONTEXT_CHARS),
+                    note: Some((String::from("Original"), context)),
+                },
+                None => Item {
+                    label: m.keyword.clone(),
+                    text: context,
+                    note: None,
+                },
+            }
+        })
+        .collect();
+    let more = (first_matches.len() > MAX_KEYWORDS_IN_BODY)
+        .then(|| format!("... and {} more keywords", first_matches.len() - MAX_KEYWORDS_IN_BODY));
+
+    let mut blocks = vec![Block::Text(header), Block::Items { items, more }];
+    if let Some(trend) = trend {
+        blocks.push(Block::Text(format!("Value: {}", trend.describe())));
+    }
+    if !changes.json.is_empty() {
+        blocks.push(Block::Code {
+            heading: String::from("Changes:"),
+            lines: changes.json.iter().take(MAX_CHANGES_IN_BODY).cloned().collect(),
+            more: (changes.json.len() > MAX_CHANGES_IN_BODY)
+                .then(|| format!("... and {} more changes", changes.json.len() - MAX_CHANGES_IN_BODY)),
+        });
+    }
+    // Too long for a notification or a chat message
+    if let (Some(lines), Format::Html) = (&changes.lines, notifier.format()) {
+        blocks.push(Block::SideBySide {
+            heading: String::from("What changed:"),
+            diff: lines.clone(),
+        });
+    }
+    let body = notifier.format().render(&blocks);
+
+    let keywords = first_matches.iter().map(|m| m.keyword.clone()).collect();
+    Some(Message {
+        title,
+        body,
+        url: url.to_string(),
+        keywords,
+        priority: Some(priority),
+        fields,
+    })
+}
+
+/// Fill in a title template: `{{name}}` becomes the named group's value,
+/// `{{keywords}}` the matched keywords and `{{url}}` the page. Names
+/// without a value are left as they are, so a typo shows in the alert.
+pub fn fill_title(template: &str, url: &str, keywords: &str, fields: &BTreeMap<String, String>) -> String {
+    let mut title = String::new();
+    let mut copied = 0;
+    for (range, name) in placeholders(template) {
+        title.push_str(&template[copied..range.start]);
+        match name {
+            "keywords" => title.push_str(keywords),
+            "url" => title.push_str(url),
+            _ => title.push_str(fields.get(name).map_or(&template[range.clone()], String::as_str)),
+        }
+        copied = range.end;
+    }
+    title.push_str(&template[copied..]);
+    title
+}
+
+/// The names used in a title template, besides `keywords` and `url`
+pub fn template_fields(template: &str) -> Vec<&str> {
+    let mut names = Vec::new();
+    for (_, name) in placeholders(template) {
+        if !matches!(name, "keywords" | "url") && !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    names
+}
+
+/// The `{{name}}` placeholders of a template, with where they are
+fn placeholders(template: &str) -> Vec<(std::ops::Range<usize>, &str)> {
+    let mut found = Vec::new();
+    let mut offset = 0;
+    while let Some(start) = template[offset..].find("{{").map(|i| offset + i) {
+        let Some(end) = template[start..].find("}}").map(|i| start + i + 2) else {
+            break;
+        };
+        found.push((start..end, template[start + 2..end - 2].trim()));
+        offset = end;
+    }
+    found
+}
+
+/// Tell the user that keywords they were alerted about are gone from the
+/// page (e.g. a "maintenance" banner was taken down)
+pub async fn send_cleared_notification(
+    settings: &NotificationSettings,
+    url: &str,
+    keywords: &[String],
+) -> Result<()> {
+    let keyword_list = keywords.join(", ");
+    let title = format!("Web Watcher Alert: {} cleared", keyword_list);
+    let body = format!("{} is no longer on {}", keyword_list, url);
+
+    let keywords: Vec<&str> = keywords.iter().map(String::as_str).collect();
+    deliver(settings, &title, &body, url, &keywords).await
+}
+
+/// Tell the user a watcher reached its expiration date and was disabled
+pub async fn send_expired_notification(settings: &NotificationSettings, url: &str) -> Result<()> {
+    let title = "Web Watcher Alert: watcher expired";
+    let body = format!("Stopped watching {}\n\nIts expiration date has passed, so it was disabled.", url);
+
+    deliver(settings, title, &body, url, &[]).await
+}
+
+/// Tell the user a watched page permanently moved
+pub async fn send_moved_notification(settings: &NotificationSettings, url: &str, moved_to: &str) -> Result<()> {
+    let title = "Web Watcher Alert: page moved";
+    let body = format!(
+        "{} now permanently redirects to {}\n\nOpen the watcher in the app to switch it to the new URL.",
+        url, moved_to
+    );
+
+    deliver(settings, title, &body, url, &[]).await
+}
+
+/// Tell the user a watcher was disabled because its page seems to be gone
+pub async fn send_auto_disabled_notification(
+    settings: &NotificationSettings,
+    url: &str,
+    reason: &str,
+) -> Result<()> {
+    let title = "Web Watcher Alert: watcher disabled";
+    let body = format!(
+        "Stopped watching {}\n\n{}. Fix the URL and re-enable the watcher if the page still exists.",
+        url, reason
+    );
+
+    deliver(settings, title, &body, url, &[]).await
+}
+
+/// Tell the user a watcher's checks get a block page instead of the page
+/// (`paused`: the watcher was disabled until re-enabled)
+pub async fn send_blocked_notification(
+    settings: &NotificationSettings,
+    url: &str,
+    reason: &str,
+    paused: bool,
+) -> Result<()> {
+    let title = "Web Watcher Alert: watcher blocked";
+    let next = if paused {
+        "The watcher is paused: re-enable it once the site lets it through again."
+    } else {
+        "Checks continue, but changes can't be seen until the site lets them through again. A request profile or the impersonate render mode may help."
+    };
+    let body = format!("{} answered with a block page ({}).\n\n{}", url, reason, next);
+
+    deliver(settings, title, &body, url, &[]).await
+}
+
+/// Tell the user a watched site became slow to respond
+pub async fn send_slow_notification(settings: &NotificationSettings, url: &str, reason: &str) -> Result<()> {
+    let title = "Web Watcher Alert: site slow";
+    let body = format!(
+        "{} is slow to respond: {}.\n\nYou won't be told again until its response time is back to normal and slows down again.",
+        url, reason
+    );
+
+    deliver(settings, title, &body, url, &[]).await
+}
+
+/// Tell the user the watchdog restarted a watcher whose checks hung
+pub async fn send_watchdog_notification(
+    settings: &NotificationSettings,
+    url: &str,
+    stuck_for: &str,
+) -> Result<()> {
+    let title = "Web Watcher Alert: watcher restarted";
+    let body = format!(
+        "{} hadn't completed a check in {}, so it was restarted.\n\nIf this keeps happening, the site may be hanging requests.",
+        url, stuck_for
+    );
+
+    deliver(settings, title, &body, url, &[]).await
+}
+
+/// Tell the user a watcher crashed and will be restarted
+pub async fn send_crash_notification(
+    settings: &NotificationSettings,
+    url: &str,
+    error: &str,
+    restart_in: &str,
+) -> Result<()> {
+    let title = "Web Watcher Alert: watcher crashed";
+    let body = format!("{} crashed: {}\n\nIt will be restarted in {}.", url, error, restart_in);
+
+    deliver(settings, title, &body, url, &[]).await
+}
+
+/// Tell the user the config can't be saved, so check times and disabled
+/// watchers won't survive a restart
+pub async fn send_save_failed_notification(settings: &NotificationSettings, error: &str) -> Result<()> {
+    let title = "Web Watcher Alert: can't save config";
+    let body = format!(
+        "{}\n\nMonitoring continues, but changes (like last check times) are lost on restart until this is fixed.",
+        error
+    );
+
+    deliver(settings, title, &body, "", &[]).await
+}
+
+/// Confirm that the daemon came up (e.g. after a reboot), listing the
+/// upcoming checks (soonest first)
+pub fn send_startup_notification(active_watchers: usize, next_checks: &[String]) -> Result<()> {
+    let title = format!(
+        "Web Watcher Alert: monitoring {} watcher{}",
+        active_watchers,
+        if active_watchers == 1 { "" } else { "s" }
+    );
+
+    let mut lines = vec![String::from("Next checks:")];
+    lines.extend(next_checks.iter().take(MAX_NEXT_CHECKS_IN_BODY).map(|c| format!("• {}", c)));
+    if next_checks.len() > MAX_NEXT_CHECKS_IN_BODY {
+        lines.push(format!("... and {} more", next_checks.len() - MAX_NEXT_CHECKS_IN_BODY));
+    }
+
+    send_desktop(&title, &lines.join("\n"), &DesktopStyle::default(), None)
+}
+
+/// Send a title/body through every enabled channel
+async fn deliver(
+    settings: &NotificationSettings,
+    title: &str,
+    body: &str,
+    url: &str,
+    keywords: &[&str],
+) -> Result<()> {
+    let message = Message {
+        title: title.to_string(),
+        body: body.to_string(),
+        url: url.to_string(),
+        keywords: keywords.iter().map(|k| k.to_string()).collect(),
+        priority: None,
+        fields: BTreeMap::new(),
+    };
+    Notifiers::from_settings(settings).send(&message).await
+}
+
+/// Whether any channel (or a registered notifier) can deliver alerts in
+/// this environment
+pub fn has_channel(settings: &NotificationSettings) -> bool {
+    !Notifiers::from_settings(settings).is_empty()
+}
+
+/// Whether there is a desktop session to show notifications in. macOS and
+/// Windows always have one; elsewhere it takes a D-Bus session or a display,
+/// which containers and SSH sessions don't have.
+pub fn desktop_available() -> bool {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return true;
+    }
+    ["DBUS_SESSION_BUS_ADDRESS", "DISPLAY", "WAYLAND_DISPLAY"]
+        .iter()
+        .any(|name| std::env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+/// Show a native desktop notification. Notifications of the same `group`
+/// (the page of the alert) replace each other where the desktop can.
+fn send_desktop(title: &str, body: &str, style: &DesktopStyle, group: Option<&str>) -> Result<()> {
+    let mut notification = Notification::new();
+    notification.summary(title).body(body);
+    if let Some(sound) = &style.sound {
+        notification.sound_name(sound);
+    }
+    // Critical on Linux and Windows, time-sensitive on macOS
+    if style.urgent {
+        notification.urgency(notify_rust::Urgency::Critical);
+    }
+    if let Some(group) = group {
+        // Notification Center replaces a notification of the same
+        // identifier; on Linux, servers like dunst stack the ones of the
+        // same tag. Windows has no equivalent.
+        #[cfg(target_os = "macos")]
+        notification.id(format!("web-watcher-alert:{}", group));
+        #[cfg(all(unix, not(target_os = "macos")))]
+        notification.hint(notify_rust::Hint::Custom(
+            "x-dunst-stack-tag".to_string(),
+            group.to_string(),
+        ));
+        #[cfg(target_os = "windows")]
+        let _ = group;
+    }
+    notification.show().context("Failed to send notification")?;
+
+    Ok(())
+}
+
+/// POST the alert as JSON to a webhook URL (keyword alerts include the
+/// highest priority of their keywords)
+async fn send_webhook(webhook_url: &str, message: &Message) -> Result<()> {
+    let mut payload = serde_json::json!({
+        "title": message.title,
+        "body": message.body,
+        "url": message.url,
+        "keywords": message.keywords,
+    });
+    if let Some(priority) = message.priority {
+        payload["priority"] = serde_json::Value::from(priority.name());
+    }
+    if !message.fields.is_empty() {
+        payload["fields"] = serde_json::json!(message.fields);
+    }
+
+    let response = reqwest::Client::new()
+        .post(webhook_url)
+        .timeout(Duration::from_secs(15))
+        .json(&payload)
+        .send()
+        .await
+        .with_context(|| format!("Failed to call webhook: {}", webhook_url))?;
+
+    if !response.status().is_success() {
+        anyhow::bail!("Webhook returned HTTP {}: {}", response.status(), webhook_url);
+    }
 
     Ok(())
 }
+
+/// Shorten text to at most `max_chars` characters, adding an ellipsis if cut
+fn truncate(text: &str, max_chars: usize) -> String {
+    if text.chars().count() <= max_chars {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(max_chars.saturating_sub(3)).collect();
+    format!("{}...", cut)
+}
@@ -0,0 +1,124 @@
+//! OPML import and export
+//!
+//! Feed readers exchange subscription lists as OPML: an XML file with one
+//! `<outline xmlUrl="...">` per feed, optionally nested in category
+//! outlines. Feeds are watched like any other page; watchers created from
+//! (or meant for) OPML are marked as feeds so they can be exported again.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use scraper::{Html, Selector};
+use std::fs;
+use std::path::Path;
+
+use crate::watcher::Watcher;
+
+/// A feed subscription
+#[derive(Debug, Clone)]
+pub struct Feed {
+    pub title: String,
+    pub url: String,
+}
+
+/// Read the feeds of an OPML file (categories are flattened)
+pub fn load(path: &Path) -> Result<Vec<Feed>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read OPML file: {}", path.display()))?;
+    if !content.to_ascii_lowercase().contains("<opml") {
+        anyhow::bail!("Not an OPML file: {}", path.display());
+    }
+
+    // Parsed like the bookmarks files: as HTML, which lowercases the
+    // attribute names. Category outlines have no feed URL.
+    let document = Html::parse_document(&content);
+    let outlines = Selector::parse("outline[xmlurl]").expect("valid selector");
+    let feeds = document
+        .select(&outlines)
+        .filter_map(|outline| {
+            let attribute = |name| {
+                outline
+                    .value()
+                    .attr(name)
+                    .map(str::trim)
+                    .filter(|value| !value.is_empty())
+                    .map(str::to_string)
+            };
+            let url = attribute("xmlurl")?;
+            let title = attribute("title")
+                .or_else(|| attribute("text"))
+                .unwrap_or_else(|| url.clone());
+            Some(Feed { title, url })
+        })
+        .collect();
+    Ok(feeds)
+}
+
+/// Render feed watchers as an OPML 2.0 document
+pub fn export(watchers: &[&Watcher]) -> String {
+    let mut opml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n");
+    opml.push_str("<opml version=\"2.0\">\n");
+    opml.push_str("  <head>\n");
+    opml.push_str("    <title>Web Watcher Alert feeds</title>\n");
+    opml.push_str(&format!("    <dateCreated>{}</dateCreated>\n", Utc::now().to_rfc2822()));
+    opml.push_str("  </head>\n");
+    opml.push_str("  <body>\n");
+    for watcher in watchers {
+        let title = escape(watcher.name.as_deref().unwrap_or(&watcher.url));
+        opml.push_str(&format!(
+            "    <outline type=\"rss\" text=\"{}\" title=\"{}\" xmlUrl=\"{}\"/>\n",
+            title,
+            title,
+            escape(&watcher.url)
+        ));
+    }
+    opml.push_str("  </body>\n");
+    opml.push_str("</opml>\n");
+    opml
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn feeds_survive_a_round_trip() {
+        let mut named = Watcher::new("https://blog.example.com/feed?a=1&b=2".into(), Vec::new(), Duration::from_secs(3600));
+        named.name = Some(String::from("Tom & Jerry's \"blog\""));
+        let unnamed = Watcher::new("https://news.example.com/rss".into(), Vec::new(), Duration::from_secs(3600));
+
+        let path = std::env::temp_dir().join(format!("web-watcher-alert-feeds-{}.opml", std::process::id()));
+        fs::write(&path, export(&[&named, &unnamed])).unwrap();
+        let feeds = load(&path).unwrap();
+
+        assert_eq!(feeds.len(), 2);
+        assert_eq!(feeds[0].url, named.url);
+        assert_eq!(feeds[0].title, "Tom & Jerry's \"blog\"");
+        assert_eq!(feeds[1].url, unnamed.url);
+        assert_eq!(feeds[1].title, unnamed.url);
+    }
+
+    #[test]
+    fn categories_are_flattened() {
+        let path = std::env::temp_dir().join(format!("web-watcher-alert-categories-{}.opml", std::process::id()));
+        fs::write(
+            &path,
+            r#"<opml version="1.0"><body>
+                <outline text="Tech"><outline text='Rust' xmlUrl='https://rust.example.com/feed'/></outline>
+                <outline text="Plain" url="https://not-a-feed.example.com/"/>
+            </body></opml>"#,
+        )
+        .unwrap();
+        let feeds = load(&path).unwrap();
+        assert_eq!(feeds.len(), 1);
+        assert_eq!(feeds[0].title, "Rust");
+        assert_eq!(feeds[0].url, "https://rust.example.com/feed");
+    }
+}
@@ -0,0 +1,175 @@
+//! Notification outbox
+//!
+//! An alert a notifier fails to deliver (the network is down, the webhook
+//! answers 500, the notification daemon isn't running) isn't lost: it goes
+//! into a persistent outbox (outbox.json in the data directory), and the
+//! monitor retries it through the same notifier with a growing wait until
+//! it's delivered or a day has passed.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use crate::config::{Config, NotificationSettings};
+use crate::notify::{self, Message, Notifiers};
+
+/// Wait before the first retry (doubled after each failed one)
+const FIRST_RETRY_SECS: i64 = 60;
+
+/// Longest wait between retries
+const MAX_RETRY_SECS: i64 = 60 * 60;
+
+/// How long an alert is retried before it's given up on
+const MAX_AGE_HOURS: i64 = 24;
+
+/// Serializes changes to the outbox file between the monitor's tasks
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// An alert waiting to be delivered
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub id: String,
+    /// Name of the notifier that failed to deliver it
+    pub notifier: String,
+    pub message: Message,
+    pub queued_at: DateTime<Utc>,
+    /// Failed deliveries so far
+    pub attempts: u32,
+    pub next_attempt: DateTime<Utc>,
+    pub last_error: String,
+}
+
+impl Entry {
+    fn expired(&self, now: DateTime<Utc>) -> bool {
+        now - self.queued_at > Duration::hours(MAX_AGE_HOURS)
+    }
+
+    /// Count a failed delivery and schedule the next one
+    fn failed(&mut self, error: String, now: DateTime<Utc>) {
+        self.attempts += 1;
+        let wait = FIRST_RETRY_SECS.saturating_mul(1 << self.attempts.saturating_sub(1).min(16));
+        self.next_attempt = now + Duration::seconds(wait.min(MAX_RETRY_SECS));
+        self.last_error = error;
+    }
+}
+
+/// What a round of retries did
+#[derive(Debug, Default)]
+pub struct Retried {
+    pub delivered: usize,
+    /// Alerts given up on, with their title and last error
+    pub expired: Vec<(String, String)>,
+    /// Alerts still waiting
+    pub pending: usize,
+}
+
+/// Keep an alert `notifier` failed to deliver, to retry it later
+pub fn queue(notifier: &str, message: &Message, error: &anyhow::Error) -> Result<()> {
+    let now = Utc::now();
+    let mut entry = Entry {
+        id: uuid::Uuid::new_v4().to_string(),
+        notifier: notifier.to_string(),
+        message: message.clone(),
+        queued_at: now,
+        attempts: 0,
+        next_attempt: now,
+        last_error: String::new(),
+    };
+    entry.failed(format!("{:#}", error), now);
+
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut entries = load()?;
+    entries.push(entry);
+    save(&entries)
+}
+
+/// The alerts waiting to be delivered, oldest first
+pub fn load() -> Result<Vec<Entry>> {
+    let path = outbox_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read outbox: {}", path.display()))?;
+    serde_json::from_str(&content).with_context(|| format!("Failed to parse outbox: {}", path.display()))
+}
+
+fn save(entries: &[Entry]) -> Result<()> {
+    let path = outbox_path()?;
+    if entries.is_empty() {
+        if path.exists() {
+            fs::remove_file(&path).with_context(|| format!("Failed to remove outbox: {}", path.display()))?;
+        }
+        return Ok(());
+    }
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create data directory: {}", parent.display()))?;
+    }
+    let content = serde_json::to_string_pretty(entries).context("Failed to serialize outbox")?;
+    fs::write(&path, content).with_context(|| format!("Failed to write outbox: {}", path.display()))
+}
+
+/// Try to deliver the alerts that are due. Alerts for notifiers that are no
+/// longer enabled are dropped like expired ones.
+pub async fn retry(settings: &NotificationSettings) -> Result<Retried> {
+    let now = Utc::now();
+    let due: Vec<Entry> = {
+        let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+        load()?.into_iter().filter(|entry| entry.next_attempt <= now).collect()
+    };
+    if due.is_empty() {
+        return Ok(Retried {
+            pending: load()?.len(),
+            ..Retried::default()
+        });
+    }
+
+    // Deliver without holding the lock, so new alerts can be queued meanwhile
+    let notifiers = Notifiers::from_settings(settings);
+    let mut outcomes = Vec::new();
+    for entry in due {
+        let (result, gone) = match notifiers.get(&entry.notifier) {
+            Some(notifier) => {
+                let result = notifier.send(&entry.message).await;
+                notify::record_delivery(&entry.notifier, &entry.message, &result, true);
+                (result.map_err(|e| format!("{:#}", e)), false)
+            }
+            None => (Err(format!("The {} channel is no longer enabled", entry.notifier)), true),
+        };
+        outcomes.push((entry.id, result, gone));
+    }
+
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut entries = load()?;
+    let mut retried = Retried::default();
+    for (id, result, gone) in outcomes {
+        let Some(index) = entries.iter().position(|entry| entry.id == id) else {
+            continue;
+        };
+        match result {
+            Ok(()) => {
+                entries.remove(index);
+                retried.delivered += 1;
+            }
+            Err(error) => {
+                let entry = &mut entries[index];
+                entry.failed(error, now);
+                if gone || entry.expired(now) {
+                    let entry = entries.remove(index);
+                    retried.expired.push((entry.message.title, entry.last_error));
+                }
+            }
+        }
+    }
+    retried.pending = entries.len();
+    save(&entries)?;
+    Ok(retried)
+}
+
+fn outbox_path() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join("outbox.json"))
+}
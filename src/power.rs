@@ -0,0 +1,74 @@
+//! Battery / power source detection
+//!
+//! Lets the monitor slow down or pause non-critical watchers while a laptop
+//! runs on a low battery.
+
+/// Power source of the machine
+#[derive(Debug, Clone, PartialEq)]
+pub struct PowerStatus {
+    /// Running on battery (not plugged in)
+    pub on_battery: bool,
+    /// Battery charge in percent, if known
+    pub charge_percent: Option<u8>,
+}
+
+/// Detect the power source. Returns None on machines without a battery (or
+/// where it can't be read). Blocking (may run `pmset` on macOS).
+#[cfg(target_os = "linux")]
+pub fn detect() -> Option<PowerStatus> {
+    let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+    let read = |path: std::path::PathBuf| {
+        std::fs::read_to_string(path).map(|s| s.trim().to_string()).ok()
+    };
+
+    let mut status = None;
+    for supply in supplies.flatten() {
+        let path = supply.path();
+        if read(path.join("type")).as_deref() != Some("Battery") {
+            continue;
+        }
+
+        // Peripheral batteries (mice, headsets) don't power the machine
+        if read(path.join("scope")).as_deref() == Some("Device") {
+            continue;
+        }
+
+        status = Some(PowerStatus {
+            on_battery: read(path.join("status")).as_deref() == Some("Discharging"),
+            charge_percent: read(path.join("capacity")).and_then(|c| c.parse().ok()),
+        });
+        break;
+    }
+    status
+}
+
+/// Detect the power source. Returns None on machines without a battery (or
+/// where it can't be read). Blocking (may run `pmset` on macOS).
+#[cfg(target_os = "macos")]
+pub fn detect() -> Option<PowerStatus> {
+    // Output looks like:
+    //   Now drawing from 'Battery Power'
+    //    -InternalBattery-0 (id=1234)	85%; discharging; 4:12 remaining present: true
+    let output = std::process::Command::new("pmset")
+        .args(["-g", "batt"])
+        .output()
+        .ok()?;
+    let output = String::from_utf8_lossy(&output.stdout);
+
+    let battery_line = output.lines().find(|line| line.contains("InternalBattery"))?;
+    let charge_percent = battery_line
+        .split_whitespace()
+        .find_map(|word| word.strip_suffix("%;"))
+        .and_then(|percent| percent.parse().ok());
+
+    Some(PowerStatus {
+        on_battery: output.contains("'Battery Power'"),
+        charge_percent,
+    })
+}
+
+/// Detect the power source (unsupported here)
+#[cfg(not(any(target_os = "linux", target_os = "macos")))]
+pub fn detect() -> Option<PowerStatus> {
+    None
+}
@@ -0,0 +1,106 @@
+//! Named profiles
+//!
+//! A profile is a separate set of watchers, notification settings, cache and
+//! history, e.g. "work" and "personal", so two daemons can run side by side
+//! without sharing state. The default profile uses the usual locations; a
+//! named profile lives in a `profiles/<name>` subdirectory of each of them.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+
+use crate::config::Config;
+
+/// Environment variable selecting the profile when `--profile` isn't given
+pub const PROFILE_ENV: &str = "WEB_WATCHER_ALERT_PROFILE";
+
+/// Name of the profile that uses the unscoped locations
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// Subdirectory holding the named profiles
+const PROFILES_DIR: &str = "profiles";
+
+/// The active named profile (None for the default one). Process-wide, since
+/// every path lookup depends on it.
+static ACTIVE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Check that a profile name can be used as a directory name
+pub fn validate(name: &str) -> Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("Profile name can't be empty");
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!(
+            "Invalid profile name '{}' (use letters, digits, '-' and '_')",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// Switch to a profile ("default" selects the default one). Paths looked up
+/// afterwards point into the profile's directories.
+pub fn set_active(name: &str) -> Result<()> {
+    let name = name.trim();
+    validate(name)?;
+
+    let mut active = ACTIVE.write().unwrap_or_else(|e| e.into_inner());
+    *active = (name != DEFAULT_PROFILE).then(|| name.to_string());
+    Ok(())
+}
+
+/// The active named profile (None for the default one)
+pub fn active() -> Option<String> {
+    ACTIVE.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Name of the active profile, "default" included
+pub fn active_name() -> String {
+    active().unwrap_or_else(|| DEFAULT_PROFILE.to_string())
+}
+
+/// `dir` for the default profile, `dir/profiles/<name>` for a named one
+pub fn scoped(dir: PathBuf) -> PathBuf {
+    match active() {
+        Some(name) => dir.join(PROFILES_DIR).join(name),
+        None => dir,
+    }
+}
+
+/// All profiles with a config directory, the default one first
+pub fn list() -> Result<Vec<String>> {
+    let mut names = vec![DEFAULT_PROFILE.to_string()];
+
+    let dir = profiles_dir()?;
+    if dir.is_dir() {
+        let entries = fs::read_dir(&dir)
+            .with_context(|| format!("Failed to read profiles directory: {}", dir.display()))?;
+        let mut named: Vec<String> = entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| validate(name).is_ok() && name != DEFAULT_PROFILE)
+            .collect();
+        named.sort();
+        names.extend(named);
+    }
+
+    // A profile that was switched to but hasn't saved anything yet
+    if let Some(name) = active() {
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+    Ok(names)
+}
+
+/// Directory holding the named profiles' configs
+fn profiles_dir() -> Result<PathBuf> {
+    let config_path = Config::default_config_path()?;
+    let dir = config_path
+        .parent()
+        .map(Path::to_path_buf)
+        .context("Config path has no parent directory")?;
+    Ok(dir.join(PROFILES_DIR))
+}
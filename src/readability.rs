@@ -0,0 +1,202 @@
+//! Main-content extraction
+//!
+//! Navigation bars, footers, sidebars and cookie banners change on their
+//! own (a new menu entry, a rotating promotion) and aren't what a watcher
+//! is about. With `main_content` on (for a watcher or a domain), only the
+//! main content of each page takes part in change detection and keyword
+//! matching: the element whose paragraphs hold the most text, found the
+//! way arc90's Readability does it, without the boilerplate inside it.
+//!
+//! The scoring, in short: every paragraph of some length scores for its
+//! parent (and half for its grandparent), more for longer text and for
+//! commas; classes and IDs like `content` or `article` add to an element's
+//! score, and ones like `sidebar` or `comment` take from it; the more of an
+//! element's text is links, the less it's worth. Pages without paragraphs
+//! fall back to their `<main>`, `<article>` or body.
+
+use anyhow::Result;
+use scraper::{ElementRef, Html, Node};
+use std::collections::HashMap;
+
+/// Shortest paragraph that scores (in characters)
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// Elements that are never content
+const BOILERPLATE_TAGS: &[&str] = &[
+    "nav", "footer", "aside", "script", "style", "noscript", "iframe", "form", "button", "svg", "template",
+    "dialog",
+];
+
+/// Roles of page furniture
+const BOILERPLATE_ROLES: &[&str] = &["navigation", "banner", "contentinfo", "complementary", "dialog", "alertdialog"];
+
+/// Class and ID parts of elements that are likely boilerplate...
+const UNLIKELY: &[&str] = &[
+    "agegate", "banner", "breadcrumb", "combx", "comment", "community", "consent", "cookie", "disqus", "footer",
+    "gdpr", "header", "masthead", "menu", "modal", "nav", "newsletter", "pager", "pagination", "popup", "related",
+    "remark", "replies", "share", "shoutbox", "sidebar", "skyscraper", "social", "sponsor", "subscribe",
+];
+
+/// ...unless they also have one of these
+const MAYBE: &[&str] = &["and", "article", "body", "column", "content", "main", "shadow"];
+
+/// Class and ID parts that make an element likelier to be the content
+const POSITIVE: &[&str] = &["article", "body", "content", "entry", "main", "page", "post", "story", "text", "blog"];
+
+/// Class and ID parts that make it less likely
+const NEGATIVE: &[&str] = &[
+    "banner", "comment", "contact", "cookie", "foot", "hidden", "masthead", "media", "meta", "promo", "related",
+    "scroll", "share", "shopping", "sidebar", "sponsor", "tags", "tool", "widget",
+];
+
+/// The main content of an HTML page, as HTML
+pub fn extract(html: &str) -> Result<String> {
+    let document = Html::parse_document(html);
+    let root = document.root_element();
+
+    let mut scores = HashMap::new();
+    for paragraph in root.descendent_elements() {
+        if !matches!(paragraph.value().name(), "p" | "pre" | "td" | "blockquote") || in_boilerplate(paragraph) {
+            continue;
+        }
+        let text: String = paragraph.text().collect();
+        let length = text.trim().chars().count();
+        if length < MIN_PARAGRAPH_CHARS {
+            continue;
+        }
+        let score = 1.0 + text.matches(',').count() as f64 + (length as f64 / 100.0).min(3.0);
+
+        let parent = paragraph.parent().and_then(ElementRef::wrap);
+        let grandparent = parent.and_then(|parent| parent.parent()).and_then(ElementRef::wrap);
+        for (candidate, share) in [(parent, 1.0), (grandparent, 0.5)] {
+            if let Some(candidate) = candidate {
+                *scores.entry(candidate.id()).or_insert_with(|| initial_score(candidate)) += score * share;
+            }
+        }
+    }
+
+    let best = scores
+        .into_iter()
+        .filter_map(|(id, score)| {
+            let candidate = ElementRef::wrap(document.tree.get(id)?)?;
+            Some((candidate, score * (1.0 - link_density(candidate))))
+        })
+        .max_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(candidate, _)| candidate);
+    let main = best.or_else(|| {
+        root.descendent_elements().find(|element| {
+            matches!(element.value().name(), "main" | "article") || element.value().attr("role") == Some("main")
+        })
+    });
+    let main = main
+        .or_else(|| root.child_elements().find(|element| element.value().name() == "body"))
+        .unwrap_or(root);
+
+    let mut content = String::new();
+    write_html(main, &mut content);
+    Ok(content)
+}
+
+/// Whether the element is page furniture rather than content
+fn is_boilerplate(element: ElementRef) -> bool {
+    let element = element.value();
+    if BOILERPLATE_TAGS.contains(&element.name()) {
+        return true;
+    }
+    if element.attr("role").is_some_and(|role| BOILERPLATE_ROLES.contains(&role))
+        || element.attr("aria-modal") == Some("true")
+    {
+        return true;
+    }
+    if matches!(element.name(), "html" | "body" | "main" | "article") {
+        return false;
+    }
+    let names = class_and_id(element);
+    UNLIKELY.iter().any(|part| names.contains(part)) && !MAYBE.iter().any(|part| names.contains(part))
+}
+
+fn in_boilerplate(element: ElementRef) -> bool {
+    std::iter::once(element)
+        .chain(element.ancestors().filter_map(ElementRef::wrap))
+        .any(is_boilerplate)
+}
+
+/// A candidate's score before its paragraphs are counted
+fn initial_score(element: ElementRef) -> f64 {
+    let tag = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "address" | "ol" | "ul" | "dl" | "dd" | "dt" | "li" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "h5" | "h6" | "th" => -5.0,
+        _ => 0.0,
+    };
+    let names = class_and_id(element.value());
+    let mut weight = 0.0;
+    if POSITIVE.iter().any(|part| names.contains(part)) {
+        weight += 25.0;
+    }
+    if NEGATIVE.iter().any(|part| names.contains(part)) {
+        weight -= 25.0;
+    }
+    tag + weight
+}
+
+/// The element's classes and ID, lowercased
+fn class_and_id(element: &scraper::node::Element) -> String {
+    let class = element.attr("class").unwrap_or_default();
+    let id = element.attr("id").unwrap_or_default();
+    format!("{} {}", class, id).to_lowercase()
+}
+
+/// How much of the element's text is in links (0 to 1)
+fn link_density(element: ElementRef) -> f64 {
+    let total: usize = element.text().map(|text| text.trim().chars().count()).sum();
+    if total == 0 {
+        return 0.0;
+    }
+    let linked: usize = element
+        .descendent_elements()
+        .filter(|descendant| descendant.value().name() == "a")
+        .flat_map(|link| link.text())
+        .map(|text| text.trim().chars().count())
+        .sum();
+    linked as f64 / total as f64
+}
+
+/// The element's HTML, without the boilerplate inside it
+fn write_html(element: ElementRef, out: &mut String) {
+    let value = element.value();
+    out.push('<');
+    out.push_str(value.name());
+    for (name, attr) in value.attrs() {
+        out.push_str(&format!(" {}=\"{}\"", name, attr.replace('&', "&amp;").replace('"', "&quot;")));
+    }
+    out.push('>');
+    for child in element.children() {
+        match child.value() {
+            Node::Text(text) => out.push_str(&escape(text)),
+            Node::Element(_) => {
+                if let Some(child) = ElementRef::wrap(child).filter(|child| !is_boilerplate(*child)) {
+                    write_html(child, out);
+                }
+            }
+            _ => {}
+        }
+    }
+    if !is_void(value.name()) {
+        out.push_str(&format!("</{}>", value.name()));
+    }
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;").replace('<', "&lt;").replace('>', "&gt;")
+}
+
+/// Elements without a closing tag
+fn is_void(name: &str) -> bool {
+    matches!(
+        name,
+        "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input" | "link" | "meta" | "source" | "track" | "wbr"
+    )
+}
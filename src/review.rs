@@ -0,0 +1,161 @@
+//! Changes to review
+//!
+//! A page that changed without any keyword matching lands in the review
+//! queue (review.json in the data directory), with the lines removed and
+//! added. In the TUI (`r` in the main menu) each change can be looked at
+//! and dismissed, or a line of it turned into a keyword of the watcher:
+//! lines that keep coming back across changes are marked, as they're the
+//! likeliest to be worth watching for.
+//!
+//! The queue keeps the latest changes only, and nothing with data
+//! minimization on. It's encrypted like the cache when encryption is on.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::{Mutex, PoisonError};
+
+use crate::config::{Config, EncryptionSettings};
+use crate::crypto::{self, Role};
+use crate::diff;
+use crate::watcher::Watcher;
+
+/// Most changes kept (older ones are dropped)
+const MAX_ITEMS: usize = 200;
+
+/// Most lines kept of each side of a change
+const MAX_LINES: usize = 40;
+
+/// Longest line kept (in characters)
+const MAX_LINE_CHARS: usize = 300;
+
+/// Serializes changes to the queue file between the monitor's tasks
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// A change waiting for review
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Item {
+    pub id: String,
+    pub watcher_id: String,
+    pub url: String,
+    pub detected_at: DateTime<Utc>,
+    pub removed: Vec<String>,
+    pub added: Vec<String>,
+}
+
+/// Queue a change of the watcher's page (none if only whitespace changed)
+pub fn add(watcher: &Watcher, old_content: &str, new_content: &str, encryption: &EncryptionSettings) -> Result<()> {
+    let (removed, added) = diff::changed_text(old_content, new_content, watcher.diff);
+    if removed.is_empty() && added.is_empty() {
+        return Ok(());
+    }
+    let item = Item {
+        id: uuid::Uuid::new_v4().to_string(),
+        watcher_id: watcher.id.clone(),
+        url: watcher.url.clone(),
+        detected_at: Utc::now(),
+        removed: shorten(removed),
+        added: shorten(added),
+    };
+
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut items = load()?;
+    items.push(item);
+    let excess = items.len().saturating_sub(MAX_ITEMS);
+    items.drain(..excess);
+    save(&items, encryption)
+}
+
+fn shorten(lines: Vec<String>) -> Vec<String> {
+    lines
+        .into_iter()
+        .take(MAX_LINES)
+        .map(|line| match line.char_indices().nth(MAX_LINE_CHARS) {
+            Some((end, _)) => format!("{}…", &line[..end]),
+            None => line,
+        })
+        .collect()
+}
+
+/// The changes waiting for review, oldest first
+pub fn load() -> Result<Vec<Item>> {
+    let path = queue_path()?;
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let data = fs::read(&path).with_context(|| format!("Failed to read review queue: {}", path.display()))?;
+    let content = crypto::open(&data, Role::ReviewQueue).with_context(|| format!("Failed to read review queue: {}", path.display()))?;
+    serde_json::from_slice(&content).with_context(|| format!("Failed to parse review queue: {}", path.display()))
+}
+
+fn save(items: &[Item], encryption: &EncryptionSettings) -> Result<()> {
+    if items.is_empty() {
+        return remove();
+    }
+    let path = queue_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create data directory: {}", parent.display()))?;
+    }
+    let content = serde_json::to_vec_pretty(items).context("Failed to serialize review queue")?;
+    fs::write(&path, crypto::seal(&content, Role::ReviewQueue, encryption)?)
+        .with_context(|| format!("Failed to write review queue: {}", path.display()))
+}
+
+fn remove() -> Result<()> {
+    let path = queue_path()?;
+    if path.exists() {
+        fs::remove_file(&path).with_context(|| format!("Failed to remove review queue: {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Remove changes from the queue. Returns how many were removed.
+pub fn dismiss(ids: &[String], encryption: &EncryptionSettings) -> Result<usize> {
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut items = load()?;
+    let before = items.len();
+    items.retain(|item| !ids.contains(&item.id));
+    save(&items, encryption)?;
+    Ok(before - items.len())
+}
+
+/// Drop every queued change. Returns how many there were.
+pub fn clear() -> Result<usize> {
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let count = load()?.len();
+    remove()?;
+    Ok(count)
+}
+
+/// Rewrite the queue encrypted or not, as encryption now is. Returns
+/// whether there was a queue.
+pub fn reseal(encryption: &EncryptionSettings) -> Result<bool> {
+    let _guard = LOCK.lock().unwrap_or_else(PoisonError::into_inner);
+    let items = load()?;
+    save(&items, encryption)?;
+    Ok(!items.is_empty())
+}
+
+/// How many of the watcher's queued changes added each line, for the
+/// lines added more than once
+pub fn recurring(items: &[Item], watcher_id: &str) -> HashMap<String, usize> {
+    let mut counts: HashMap<String, usize> = HashMap::new();
+    for item in items.iter().filter(|item| item.watcher_id == watcher_id) {
+        let mut lines: Vec<&String> = item.added.iter().collect();
+        lines.sort();
+        lines.dedup();
+        for line in lines {
+            *counts.entry(line.clone()).or_default() += 1;
+        }
+    }
+    counts.retain(|_, count| *count > 1);
+    counts
+}
+
+fn queue_path() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join("review.json"))
+}
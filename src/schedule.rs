@@ -0,0 +1,168 @@
+//! Scheduling helpers
+//!
+//! Per-watcher active windows: a watcher can be limited to certain hours of
+//! the day and/or days of the week (e.g. "08:00-20:00 mon-fri"). Outside its
+//! window the monitor doesn't check it at all. Windows are wall-clock times
+//! in the configured time zone (see `timezone`).
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, Duration as ChronoDuration, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ActiveWindow {
+    /// Start of the window, wall-clock time ("HH:MM")
+    #[serde(with = "time_of_day")]
+    pub start: NaiveTime,
+
+    /// End of the window, wall-clock time ("HH:MM"). May be before `start`
+    /// for windows that cross midnight (e.g. 22:00-06:00).
+    #[serde(with = "time_of_day")]
+    pub end: NaiveTime,
+
+    /// Days on which the window applies (empty means every day)
+    #[serde(default)]
+    pub days: Vec<Weekday>,
+}
+
+impl ActiveWindow {
+    /// Parse a window like "08:00-20:00", "08:00-20:00 mon-fri",
+    /// "09:00-17:00 weekdays" or "10:00-14:00 sat,sun".
+    /// An empty string means "no window" (always active).
+    pub fn parse(input: &str) -> Result<Option<Self>> {
+        let input = input.trim();
+        if input.is_empty() {
+            return Ok(None);
+        }
+
+        let mut parts = input.split_whitespace();
+        let times = parts.next().unwrap_or_default();
+        let days = parts.collect::<Vec<_>>().join("");
+
+        let (start, end) = times
+            .split_once('-')
+            .context("Active hours must look like 08:00-20:00")?;
+        let start = parse_time(start)?;
+        let end = parse_time(end)?;
+        if start == end {
+            anyhow::bail!("Active hours start and end can't be the same");
+        }
+
+        Ok(Some(Self {
+            start,
+            end,
+            days: parse_days(&days)?,
+        }))
+    }
+
+    /// Whether checks are allowed at the given wall-clock time
+    pub fn is_active(&self, now: NaiveDateTime) -> bool {
+        let time = now.time();
+
+        let (in_hours, day) = if self.start < self.end {
+            (time >= self.start && time < self.end, now.weekday())
+        } else if time >= self.start {
+            // Overnight window, evening part: belongs to today
+            (true, now.weekday())
+        } else {
+            // Overnight window, morning part: belongs to the previous day
+            (time < self.end, now.weekday().pred())
+        };
+
+        in_hours && (self.days.is_empty() || self.days.contains(&day))
+    }
+
+    /// The next time the window opens after `now`
+    pub fn next_start(&self, now: NaiveDateTime) -> Option<NaiveDateTime> {
+        (0..=7).find_map(|offset| {
+            let candidate = (now.date() + ChronoDuration::days(offset)).and_time(self.start);
+            (candidate > now && self.is_active(candidate)).then_some(candidate)
+        })
+    }
+}
+
+impl fmt::Display for ActiveWindow {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.start.format("%H:%M"), self.end.format("%H:%M"))?;
+        if !self.days.is_empty() {
+            let days: Vec<String> = self
+                .days
+                .iter()
+                .map(|d| d.to_string().to_lowercase())
+                .collect();
+            write!(f, " {}", days.join(","))?;
+        }
+        Ok(())
+    }
+}
+
+fn parse_time(input: &str) -> Result<NaiveTime> {
+    NaiveTime::parse_from_str(input.trim(), "%H:%M")
+        .with_context(|| format!("Invalid time '{}' (use HH:MM, e.g. 08:30)", input.trim()))
+}
+
+/// Parse "mon-fri", "sat,sun", "weekdays", "weekends" (or "" for every day)
+fn parse_days(input: &str) -> Result<Vec<Weekday>> {
+    let input = input.trim().to_lowercase();
+    match input.as_str() {
+        "" => return Ok(Vec::new()),
+        "weekdays" => return parse_days("mon-fri"),
+        "weekends" => return parse_days("sat,sun"),
+        _ => {}
+    }
+
+    let mut days: Vec<Weekday> = Vec::new();
+    let mut add = |day: Weekday| {
+        if !days.contains(&day) {
+            days.push(day);
+        }
+    };
+
+    for part in input.split(',').filter(|p| !p.is_empty()) {
+        if let Some((from, to)) = part.split_once('-') {
+            let mut day = parse_weekday(from)?;
+            let to = parse_weekday(to)?;
+            // Walk forward, wrapping around the week (e.g. fri-mon)
+            loop {
+                add(day);
+                if day == to {
+                    break;
+                }
+                day = day.succ();
+            }
+        } else {
+            add(parse_weekday(part)?);
+        }
+    }
+
+    Ok(days)
+}
+
+fn parse_weekday(input: &str) -> Result<Weekday> {
+    input
+        .trim()
+        .parse::<Weekday>()
+        .map_err(|_| anyhow::anyhow!("Invalid day '{}' (use mon, tue, ... sun)", input.trim()))
+}
+
+// Helper module for serializing a time of day as "HH:MM"
+mod time_of_day {
+    use chrono::NaiveTime;
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S>(time: &NaiveTime, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&time.format("%H:%M").to_string())
+    }
+
+    pub fn deserialize<'de, D>(deserializer: D) -> Result<NaiveTime, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let s = String::deserialize(deserializer)?;
+        NaiveTime::parse_from_str(&s, "%H:%M").map_err(serde::de::Error::custom)
+    }
+}
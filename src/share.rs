@@ -0,0 +1,140 @@
+//! Sharing single watchers
+//!
+//! `share <url>` turns a watcher into a snippet others can import with
+//! `import-watcher`: its settings as compact JSON (only those that differ
+//! from a new watcher's), or packed into one `webwatcher://import?watcher=…`
+//! link for pasting in a forum post or chat.
+//!
+//! A snippet only carries what makes the watcher work elsewhere: the
+//! keywords (those of keyword sets written out, as the importer won't have
+//! the sets), interval, selector and the like. Check results, local paths,
+//! TLS overrides and headers that look like credentials are left out.
+
+use anyhow::{Context, Result};
+use base64::Engine;
+use flate2::{read::DeflateDecoder, write::DeflateEncoder, Compression};
+use serde_json::{Map, Value};
+use std::io::{Read, Write};
+
+use crate::config::Config;
+use crate::links;
+use crate::watcher::{normalize_url, Watcher};
+
+/// Watcher fields never shared: identity, check results, local settings
+const PRIVATE_FIELDS: &[&str] = &[
+    "id",
+    "cache_path",
+    "enabled",
+    "matrix",
+    "archived",
+    "keyword_sets",
+    "allow_short_interval",
+    "expires_at",
+    "disabled_reason",
+    "tls",
+    "last_checked",
+    "consecutive_errors",
+    "last_error",
+    "blocked",
+    "slow",
+    "permanent_failures",
+    "permanent_failure_since",
+    "moved_to",
+    "ignored_move",
+];
+
+/// Parts of header names that mark credentials
+const SECRET_HEADERS: &[&str] = &["auth", "cookie", "token", "key", "secret", "session"];
+
+/// The watcher's settings as a snippet (a JSON object)
+pub fn snippet(config: &Config, watcher: &Watcher) -> Result<Value> {
+    let mut shared = watcher.clone();
+    shared.keywords = config.keywords_for(watcher);
+    shared
+        .headers
+        .retain(|name, _| !SECRET_HEADERS.iter().any(|secret| name.to_lowercase().contains(secret)));
+
+    let defaults = serde_json::to_value(Watcher::new(watcher.url.clone(), Vec::new(), watcher.check_interval))
+        .context("Failed to serialize watcher")?;
+    let Value::Object(fields) = serde_json::to_value(shared).context("Failed to serialize watcher")? else {
+        anyhow::bail!("A watcher isn't a JSON object");
+    };
+
+    // The URL, keywords and interval always; the rest where they differ
+    let snippet: Map<String, Value> = fields
+        .into_iter()
+        .filter(|(name, value)| {
+            !PRIVATE_FIELDS.contains(&name.as_str())
+                && (matches!(name.as_str(), "url" | "keywords" | "check_interval") || defaults.get(name) != Some(value))
+        })
+        .collect();
+    Ok(Value::Object(snippet))
+}
+
+/// A snippet packed into a `webwatcher://import` link
+pub fn link(snippet: &Value) -> Result<String> {
+    let mut encoder = DeflateEncoder::new(Vec::new(), Compression::best());
+    encoder.write_all(snippet.to_string().as_bytes())?;
+    let packed = encoder.finish().context("Failed to compress the snippet")?;
+    Ok(format!(
+        "{}://import?watcher={}",
+        links::SCHEME,
+        base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(packed)
+    ))
+}
+
+/// Read a snippet: JSON, a `webwatcher://import` link, or the packed
+/// `watcher` value of one
+pub fn parse(input: &str) -> Result<Value> {
+    let input = input.trim();
+    if input.starts_with('{') {
+        return serde_json::from_str(input).context("Invalid watcher snippet");
+    }
+
+    let packed = if links::is_link(input) {
+        let link = url::Url::parse(input).with_context(|| format!("Invalid link: {}", input))?;
+        link.query_pairs()
+            .find(|(name, _)| name == "watcher")
+            .map(|(_, value)| value.into_owned())
+            .context("The link has no watcher in it")?
+    } else {
+        input.to_string()
+    };
+    let data = base64::engine::general_purpose::URL_SAFE_NO_PAD
+        .decode(packed.trim_end_matches('='))
+        .context("Not a watcher snippet (expected JSON or a webwatcher://import link)")?;
+    let mut json = String::new();
+    DeflateDecoder::new(&data[..])
+        .read_to_string(&mut json)
+        .context("The watcher snippet is damaged")?;
+    serde_json::from_str(&json).context("Invalid watcher snippet")
+}
+
+/// A new watcher with a snippet's settings (not added to any config)
+pub fn to_watcher(snippet: &Value) -> Result<Watcher> {
+    let fields = snippet.as_object().context("A watcher snippet is a JSON object")?;
+    let url = fields
+        .get("url")
+        .and_then(Value::as_str)
+        .context("The watcher snippet has no url")?;
+    let url = normalize_url(url)?;
+
+    let Value::Object(mut watcher) =
+        serde_json::to_value(Watcher::new(url.clone(), Vec::new(), std::time::Duration::from_secs(30 * 60)))
+            .context("Failed to serialize watcher")?
+    else {
+        anyhow::bail!("A watcher isn't a JSON object");
+    };
+    for (name, value) in fields {
+        if !PRIVATE_FIELDS.contains(&name.as_str()) {
+            watcher.insert(name.clone(), value.clone());
+        }
+    }
+    watcher.insert(String::from("url"), Value::String(url));
+
+    let watcher: Watcher = serde_json::from_value(Value::Object(watcher)).context("Invalid watcher snippet")?;
+    if watcher.keywords.is_empty() {
+        anyhow::bail!("The watcher snippet has no keywords");
+    }
+    Ok(watcher)
+}
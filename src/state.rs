@@ -0,0 +1,253 @@
+//! Persistent per-watcher state
+//!
+//! Remembers which matches have already been notified, so restarting the
+//! daemon (or a page changing elsewhere) doesn't re-alert about a match the
+//! user has already seen, which alerted keywords are still on the page (for
+//! "cleared" notifications), the HTTP validators used by lightweight
+//! (HEAD-only) checks, the details of the latest alert (for copying from
+//! the TUI), the translated keywords of translated watchers and the matches
+//! found during a calendar's quiet period, alerted when it's over. Stored as
+//! one JSON file per watcher under ~/.local/share/web-watcher-alert/state/.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+
+use crate::{cache, config::Config, fetcher::Validators, matcher::KeywordMatch, values::Trend};
+
+/// How many notified matches to remember per watcher (oldest are dropped)
+const MAX_NOTIFIED: usize = 500;
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WatcherState {
+    /// Matches already notified, oldest first
+    #[serde(default)]
+    pub notified: Vec<NotifiedMatch>,
+
+    /// Keywords that were alerted on and were still on the page at the
+    /// last change
+    #[serde(default)]
+    pub present_keywords: Vec<String>,
+
+    /// Validators from the last full check (lightweight mode)
+    #[serde(default)]
+    pub validators: Option<Validators>,
+
+    /// The most recent alert
+    #[serde(default)]
+    pub last_alert: Option<LastAlert>,
+
+    /// The keywords translated into the page's language (translated
+    /// watchers), so they're only sent to the provider once
+    #[serde(default)]
+    pub keyword_translations: Option<KeywordTranslations>,
+
+    /// The value read by the last check (watchers tracking a value)
+    #[serde(default)]
+    pub last_value: Option<f64>,
+
+    /// Matches found during a quiet period, waiting for it to end
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub held: Vec<KeywordMatch>,
+
+    /// Changed since it was loaded or last saved
+    #[serde(skip)]
+    dirty: bool,
+}
+
+/// A (keyword, context) pair that has been notified
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NotifiedMatch {
+    pub keyword: String,
+    /// Hash of the match context, so the state file doesn't store page text
+    pub context_hash: String,
+}
+
+/// What the most recent alert was about
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LastAlert {
+    pub at: DateTime<Utc>,
+    /// The matches that were notified, with their context
+    pub matches: Vec<KeywordMatch>,
+    /// Summary of what changed on the page (None when unknown, e.g. on the
+    /// first check or for pages over the size cap)
+    #[serde(default)]
+    pub diff: Option<String>,
+}
+
+/// Keywords and their translations into one language
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct KeywordTranslations {
+    /// ISO 639-1 code, like "de"
+    pub language: String,
+    /// (keyword, translation) pairs
+    pub keywords: Vec<(String, String)>,
+}
+
+impl NotifiedMatch {
+    fn from_match(m: &KeywordMatch) -> Self {
+        Self {
+            keyword: m.keyword.clone(),
+            context_hash: cache::content_hash(m.context.as_bytes())[..16].to_string(),
+        }
+    }
+}
+
+impl WatcherState {
+    /// Load a watcher's state (empty if it has none yet)
+    pub fn load(watcher_id: &str) -> Result<Self> {
+        let path = Self::path(watcher_id)?;
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read state file: {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse state file: {}", path.display()))
+    }
+
+    /// Whether there are unsaved changes
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Save a watcher's state
+    pub fn save(&mut self, watcher_id: &str) -> Result<()> {
+        let path = Self::path(watcher_id)?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .with_context(|| format!("Failed to create state directory: {}", parent.display()))?;
+        }
+
+        let content = serde_json::to_string_pretty(self).context("Failed to serialize state")?;
+        fs::write(&path, content)
+            .with_context(|| format!("Failed to write state file: {}", path.display()))?;
+
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Delete a watcher's state (when the watcher is removed)
+    pub fn remove(watcher_id: &str) -> Result<()> {
+        let path = Self::path(watcher_id)?;
+        if path.exists() {
+            fs::remove_file(&path)
+                .with_context(|| format!("Failed to remove state file: {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Keep only matches that haven't been notified before
+    pub fn new_matches(&self, matches: Vec<KeywordMatch>) -> Vec<KeywordMatch> {
+        matches
+            .into_iter()
+            .filter(|m| !self.notified.contains(&NotifiedMatch::from_match(m)))
+            .collect()
+    }
+
+    /// Keywords that were alerted on but aren't among those `found` on the
+    /// page now
+    pub fn absent_keywords(&self, found: &[String]) -> Vec<String> {
+        self.present_keywords
+            .iter()
+            .filter(|keyword| !found.contains(keyword))
+            .cloned()
+            .collect()
+    }
+
+    /// Stop tracking alerted keywords that are no longer on the page. With
+    /// `realert`, their notified matches are forgotten too, so the keyword
+    /// alerts again when it comes back (even with the same context).
+    pub fn forget_absent(&mut self, found: &[String], realert: bool) {
+        let absent = self.absent_keywords(found);
+        if absent.is_empty() {
+            return;
+        }
+
+        self.present_keywords.retain(|keyword| found.contains(keyword));
+        if realert {
+            self.notified.retain(|n| !absent.contains(&n.keyword));
+        }
+        self.dirty = true;
+    }
+
+    /// Remember that these matches were notified
+    pub fn record(&mut self, matches: &[KeywordMatch]) {
+        for m in matches {
+            if !self.present_keywords.contains(&m.keyword) {
+                self.present_keywords.push(m.keyword.clone());
+            }
+            let notified = NotifiedMatch::from_match(m);
+            if !self.notified.contains(&notified) {
+                self.notified.push(notified);
+                self.dirty = true;
+            }
+        }
+
+        if self.notified.len() > MAX_NOTIFIED {
+            let excess = self.notified.len() - MAX_NOTIFIED;
+            self.notified.drain(..excess);
+        }
+    }
+
+    /// Keep matches to alert on once the quiet period is over
+    pub fn hold(&mut self, matches: &[KeywordMatch]) {
+        self.held.extend_from_slice(matches);
+        self.dirty = true;
+    }
+
+    /// The matches held for the end of a quiet period, forgetting them
+    pub fn take_held(&mut self) -> Vec<KeywordMatch> {
+        if !self.held.is_empty() {
+            self.dirty = true;
+        }
+        std::mem::take(&mut self.held)
+    }
+
+    /// Remember the details of an alert that was just sent
+    pub fn set_last_alert(&mut self, matches: &[KeywordMatch], diff: Option<String>) {
+        self.last_alert = Some(LastAlert {
+            at: Utc::now(),
+            matches: matches.to_vec(),
+            diff,
+        });
+        self.dirty = true;
+    }
+
+    /// Remember the validators of a completed full check
+    pub fn set_validators(&mut self, validators: Validators) {
+        if self.validators.as_ref() != Some(&validators) {
+            self.validators = Some(validators);
+            self.dirty = true;
+        }
+    }
+
+    /// Remember the translations of the keywords into a page's language
+    pub fn set_keyword_translations(&mut self, translations: KeywordTranslations) {
+        if self.keyword_translations.as_ref() != Some(&translations) {
+            self.keyword_translations = Some(translations);
+            self.dirty = true;
+        }
+    }
+
+    /// Remember the value a check read, returning how it moved
+    pub fn track_value(&mut self, value: f64) -> Trend {
+        let trend = Trend {
+            value,
+            previous: self.last_value,
+        };
+        if self.last_value != Some(value) {
+            self.last_value = Some(value);
+            self.dirty = true;
+        }
+        trend
+    }
+
+    /// Where a watcher's state is kept
+    pub fn path(watcher_id: &str) -> Result<PathBuf> {
+        Ok(Config::data_dir()?.join("state").join(format!("{}.json", watcher_id)))
+    }
+}
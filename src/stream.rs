@@ -0,0 +1,443 @@
+//! Streaming watchers
+//!
+//! Instead of polling a page, a streaming watcher keeps a connection open
+//! and matches its keywords against each message as it arrives, so alerts
+//! go out right away. `ws://` and `wss://` URLs are WebSockets; an http(s)
+//! URL of a watcher with `stream` set is read as server-sent events (SSE),
+//! the `text/event-stream` format many status pages push updates in.
+//!
+//! Both connect through reqwest, so proxies and TLS settings apply as they
+//! do for pages. A WebSocket's frames are then read with tokio-tungstenite.
+
+use anyhow::{Context, Result};
+use futures_util::StreamExt;
+use std::time::Duration;
+use tokio::time::timeout;
+use tokio_tungstenite::tungstenite::{
+    handshake::{client::generate_key, derive_accept_key},
+    protocol::{Role, WebSocketConfig},
+    Message,
+};
+use tokio_tungstenite::WebSocketStream;
+
+use crate::fetcher::RequestOptions;
+
+/// Time allowed for connecting and receiving the response headers
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Largest message accepted (a bigger one fails the connection)
+const MAX_MESSAGE_BYTES: usize = 4 * 1024 * 1024;
+
+/// Whether a URL is a WebSocket endpoint
+pub fn is_websocket_url(url: &str) -> bool {
+    url.starts_with("ws://") || url.starts_with("wss://")
+}
+
+/// An open stream of messages
+pub struct Stream {
+    source: Source,
+}
+
+enum Source {
+    Events(EventStream),
+    WebSocket(WebSocket),
+}
+
+/// Connect to a watcher's stream. `last_event_id` resumes an event stream
+/// after the last event received before a reconnect.
+pub async fn connect(url: &str, options: &RequestOptions, last_event_id: Option<&str>) -> Result<Stream> {
+    let source = if is_websocket_url(url) {
+        Source::WebSocket(WebSocket::connect(url, options).await?)
+    } else {
+        Source::Events(EventStream::connect(url, options, last_event_id).await?)
+    };
+    Ok(Stream { source })
+}
+
+impl Stream {
+    /// The next message, or None once the server closed the stream. Fails
+    /// when nothing at all (not even a keep-alive) arrives within `idle`.
+    pub async fn next_message(&mut self, idle: Duration) -> Result<Option<String>> {
+        match &mut self.source {
+            Source::Events(events) => events.next_message(idle).await,
+            Source::WebSocket(socket) => socket.next_message(idle).await,
+        }
+    }
+
+    /// ID of the last event received (event streams only)
+    pub fn last_event_id(&self) -> Option<&str> {
+        match &self.source {
+            Source::Events(events) => events.last_id.as_deref(),
+            Source::WebSocket(_) => None,
+        }
+    }
+
+    /// How long the server asked clients to wait before reconnecting
+    /// (event streams only)
+    pub fn retry(&self) -> Option<Duration> {
+        match &self.source {
+            Source::Events(events) => events.retry,
+            Source::WebSocket(_) => None,
+        }
+    }
+}
+
+/// A server-sent events stream
+struct EventStream {
+    response: reqwest::Response,
+    /// Received bytes not yet split into lines
+    buffer: Vec<u8>,
+    /// `data` lines of the event being received
+    data: Vec<String>,
+    last_id: Option<String>,
+    retry: Option<Duration>,
+}
+
+/// A client for a stream: no overall timeout (the response never ends),
+/// the watcher's TLS settings and the system's proxies
+fn client(options: &RequestOptions) -> Result<reqwest::ClientBuilder> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(CONNECT_TIMEOUT)
+        .user_agent(options.user_agent());
+    if let Some(cert) = options.tls.load_ca_cert()? {
+        builder = builder.add_root_certificate(cert);
+    }
+    if options.tls.insecure {
+        builder = builder.danger_accept_invalid_certs(true);
+    }
+    Ok(builder)
+}
+
+/// A request with the watcher's own headers
+fn request(client: &reqwest::Client, url: &str, options: &RequestOptions) -> reqwest::RequestBuilder {
+    let mut request = client.get(url);
+    for (name, value) in &options.headers {
+        if !name.eq_ignore_ascii_case("user-agent") {
+            request = request.header(name.as_str(), value.as_str());
+        }
+    }
+    request
+}
+
+impl EventStream {
+    async fn connect(url: &str, options: &RequestOptions, last_event_id: Option<&str>) -> Result<Self> {
+        let client = client(options)?.build().context("Failed to create HTTP client")?;
+        let mut request = request(&client, url, options)
+            .header("Accept", "text/event-stream")
+            .header("Cache-Control", "no-cache");
+        if let Some(id) = last_event_id {
+            request = request.header("Last-Event-ID", id);
+        }
+
+        let response = timeout(CONNECT_TIMEOUT, request.send())
+            .await
+            .with_context(|| format!("Timed out connecting to {}", url))?
+            .with_context(|| format!("Failed to connect to {}", url))?;
+        if !response.status().is_success() {
+            anyhow::bail!("HTTP {} from {}", response.status(), url);
+        }
+
+        let content_type = response
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+        if !content_type.starts_with("text/event-stream") {
+            anyhow::bail!(
+                "{} isn't an event stream (Content-Type: {})",
+                url,
+                if content_type.is_empty() { "none" } else { content_type }
+            );
+        }
+
+        Ok(Self {
+            response,
+            buffer: Vec::new(),
+            data: Vec::new(),
+            last_id: last_event_id.map(str::to_string),
+            retry: None,
+        })
+    }
+
+    async fn next_message(&mut self, idle: Duration) -> Result<Option<String>> {
+        loop {
+            while let Some(end) = self.buffer.iter().position(|&b| b == b'\n') {
+                let line: Vec<u8> = self.buffer.drain(..=end).collect();
+                let line = String::from_utf8_lossy(&line);
+                if let Some(message) = self.process_line(line.trim_end_matches(['\n', '\r'])) {
+                    return Ok(Some(message));
+                }
+            }
+
+            // An event that never ends is dropped at the end of the stream
+            let chunk = timeout(idle, self.response.chunk())
+                .await
+                .map_err(|_| anyhow::anyhow!("Nothing received for {}", humantime::format_duration(idle)))?
+                .context("Failed to read the event stream")?;
+            let Some(chunk) = chunk else {
+                return Ok(None);
+            };
+            self.buffer.extend_from_slice(&chunk);
+            if self.buffer.len() + self.data.iter().map(String::len).sum::<usize>() > MAX_MESSAGE_BYTES {
+                anyhow::bail!("Event larger than {} bytes", MAX_MESSAGE_BYTES);
+            }
+        }
+    }
+
+    /// Handle one line of the stream, returning the event it completes
+    fn process_line(&mut self, line: &str) -> Option<String> {
+        if line.is_empty() {
+            if self.data.is_empty() {
+                return None;
+            }
+            let message = self.data.join("\n");
+            self.data.clear();
+            return Some(message);
+        }
+
+        // Lines starting with ':' are comments (often keep-alives)
+        if line.starts_with(':') {
+            return None;
+        }
+
+        let (field, value) = match line.split_once(':') {
+            Some((field, value)) => (field, value.strip_prefix(' ').unwrap_or(value)),
+            None => (line, ""),
+        };
+        match field {
+            "data" => self.data.push(value.to_string()),
+            "id" if !value.contains('\0') => self.last_id = Some(value.to_string()),
+            "retry" => {
+                if let Ok(ms) = value.parse() {
+                    self.retry = Some(Duration::from_millis(ms));
+                }
+            }
+            // The event type doesn't matter for keyword matching
+            _ => {}
+        }
+        None
+    }
+}
+
+/// A client WebSocket connection
+struct WebSocket {
+    socket: WebSocketStream<reqwest::Upgraded>,
+}
+
+impl WebSocket {
+    async fn connect(url: &str, options: &RequestOptions) -> Result<Self> {
+        timeout(CONNECT_TIMEOUT, Self::open(url, options))
+            .await
+            .with_context(|| format!("Timed out connecting to {}", url))?
+    }
+
+    /// Connect and perform the opening handshake (RFC 6455, section 4)
+    async fn open(url: &str, options: &RequestOptions) -> Result<Self> {
+        let http_url = match (url.strip_prefix("wss://"), url.strip_prefix("ws://")) {
+            (Some(rest), _) => format!("https://{}", rest),
+            (None, Some(rest)) => format!("http://{}", rest),
+            (None, None) => anyhow::bail!("Not a WebSocket URL: {}", url),
+        };
+        // Upgrading needs HTTP/1.1
+        let client = client(options)?.http1_only().build().context("Failed to create HTTP client")?;
+
+        let key = generate_key();
+        let response = request(&client, &http_url, options)
+            .header(reqwest::header::CONNECTION, "Upgrade")
+            .header(reqwest::header::UPGRADE, "websocket")
+            .header(reqwest::header::SEC_WEBSOCKET_VERSION, "13")
+            .header(reqwest::header::SEC_WEBSOCKET_KEY, &key)
+            .send()
+            .await
+            .with_context(|| format!("Failed to connect to {}", url))?;
+        if response.status() != reqwest::StatusCode::SWITCHING_PROTOCOLS {
+            anyhow::bail!("{} didn't accept the WebSocket connection (HTTP {})", url, response.status());
+        }
+
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value: &reqwest::header::HeaderValue| value.to_str().ok())
+                .unwrap_or_default()
+                .trim()
+                .to_string()
+        };
+        if !header(reqwest::header::UPGRADE).eq_ignore_ascii_case("websocket") {
+            anyhow::bail!("{} didn't upgrade the connection to a WebSocket", url);
+        }
+        if header(reqwest::header::SEC_WEBSOCKET_ACCEPT) != derive_accept_key(key.as_bytes()) {
+            anyhow::bail!("{} answered the WebSocket handshake with the wrong Sec-WebSocket-Accept", url);
+        }
+
+        let connection = response
+            .upgrade()
+            .await
+            .with_context(|| format!("Failed to upgrade the connection to {}", url))?;
+        let config = WebSocketConfig {
+            max_message_size: Some(MAX_MESSAGE_BYTES),
+            max_frame_size: Some(MAX_MESSAGE_BYTES),
+            ..WebSocketConfig::default()
+        };
+        let socket = WebSocketStream::from_raw_socket(connection, Role::Client, Some(config)).await;
+        Ok(Self { socket })
+    }
+
+    /// The next text or binary message; pings are answered and close frames
+    /// acknowledged by tungstenite while reading
+    async fn next_message(&mut self, idle: Duration) -> Result<Option<String>> {
+        loop {
+            let message = timeout(idle, self.socket.next())
+                .await
+                .map_err(|_| anyhow::anyhow!("Nothing received for {}", humantime::format_duration(idle)))?;
+            match message {
+                None | Some(Ok(Message::Close(_))) => return Ok(None),
+                Some(Ok(Message::Text(text))) => return Ok(Some(text)),
+                Some(Ok(Message::Binary(data))) => return Ok(Some(String::from_utf8_lossy(&data).into_owned())),
+                Some(Ok(_)) => continue,
+                Some(Err(e)) => return Err(e).context("WebSocket connection lost"),
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::future::Future;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::task::JoinHandle;
+
+    /// A frame as a server sends it (unmasked unless `mask` is set), with a
+    /// payload under 126 bytes
+    fn frame(fin: bool, opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+        let mut frame = vec![if fin { 0x80 | opcode } else { opcode }];
+        match mask {
+            Some(mask) => {
+                frame.push(0x80 | payload.len() as u8);
+                frame.extend_from_slice(&mask);
+                frame.extend(payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]));
+            }
+            None => {
+                frame.push(payload.len() as u8);
+                frame.extend_from_slice(payload);
+            }
+        }
+        frame
+    }
+
+    /// Serve one WebSocket connection on `address`: answer the handshake
+    /// (with `accept`, or the right hash), then hand the connection over
+    async fn serve<F, Fut>(address: &str, accept: Option<&'static str>, then: F) -> Option<(String, JoinHandle<()>)>
+    where
+        F: FnOnce(BufReader<TcpStream>) -> Fut + Send + 'static,
+        Fut: Future<Output = ()> + Send,
+    {
+        let listener = TcpListener::bind(address).await.ok()?;
+        let url = format!("ws://{}/updates", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (connection, _) = listener.accept().await.unwrap();
+            let mut connection = BufReader::new(connection);
+            let mut key = String::new();
+            loop {
+                let mut line = String::new();
+                connection.read_line(&mut line).await.unwrap();
+                if line.trim().is_empty() {
+                    break;
+                }
+                if let Some((name, value)) = line.split_once(':') {
+                    if name.eq_ignore_ascii_case("sec-websocket-key") {
+                        key = value.trim().to_string();
+                    }
+                }
+            }
+            let accept = accept.map(str::to_string).unwrap_or_else(|| derive_accept_key(key.as_bytes()));
+            let response = format!(
+                "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+                 Sec-WebSocket-Accept: {}\r\n\r\n",
+                accept
+            );
+            connection.get_mut().write_all(response.as_bytes()).await.unwrap();
+            then(connection).await;
+        });
+        Some((url, server))
+    }
+
+    const IDLE: Duration = Duration::from_secs(5);
+
+    #[tokio::test]
+    async fn websocket_messages_are_reassembled_and_pings_answered() {
+        let (url, server) = serve("127.0.0.1:0", None, |mut connection| async move {
+            let mut frames = frame(true, 0x1, b"in stock", None);
+            // A fragmented message with a ping between its fragments
+            frames.extend(frame(false, 0x1, b"back ", None));
+            frames.extend(frame(true, 0x9, b"still there?", None));
+            frames.extend(frame(false, 0x0, b"in ", None));
+            frames.extend(frame(true, 0x0, b"stock", None));
+            frames.extend(frame(true, 0x2, b"binary update", None));
+            frames.extend(frame(true, 0x8, &1000u16.to_be_bytes(), None));
+            connection.get_mut().write_all(&frames).await.unwrap();
+
+            // The pong is masked, like everything a client sends
+            let mut head = [0u8; 2];
+            connection.read_exact(&mut head).await.unwrap();
+            assert_eq!(head[0], 0x8A);
+            assert_eq!(head[1], 0x80 | 12);
+            let mut mask = [0u8; 4];
+            connection.read_exact(&mut mask).await.unwrap();
+            let mut payload = [0u8; 12];
+            connection.read_exact(&mut payload).await.unwrap();
+            let payload: Vec<u8> = payload.iter().enumerate().map(|(i, byte)| byte ^ mask[i % 4]).collect();
+            assert_eq!(payload, b"still there?");
+        })
+        .await
+        .unwrap();
+
+        let mut stream = connect(&url, &RequestOptions::default(), None).await.unwrap();
+        assert_eq!(stream.next_message(IDLE).await.unwrap().as_deref(), Some("in stock"));
+        assert_eq!(stream.next_message(IDLE).await.unwrap().as_deref(), Some("back in stock"));
+        assert_eq!(stream.next_message(IDLE).await.unwrap().as_deref(), Some("binary update"));
+        assert_eq!(stream.next_message(IDLE).await.unwrap(), None);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn masked_server_frames_fail_the_connection() {
+        let (url, server) = serve("127.0.0.1:0", None, |mut connection| async move {
+            let frames = frame(true, 0x1, b"in stock", Some([1, 2, 3, 4]));
+            connection.get_mut().write_all(&frames).await.unwrap();
+        })
+        .await
+        .unwrap();
+
+        let mut stream = connect(&url, &RequestOptions::default(), None).await.unwrap();
+        assert!(stream.next_message(IDLE).await.is_err());
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn handshakes_with_the_wrong_accept_hash_are_refused() {
+        let (url, _server) = serve("127.0.0.1:0", Some("bm90IHRoZSByaWdodCBoYXNo"), |_| async {})
+            .await
+            .unwrap();
+        let error = connect(&url, &RequestOptions::default(), None).await.err().unwrap();
+        assert!(error.to_string().contains("Sec-WebSocket-Accept"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn websockets_connect_over_ipv6() {
+        // Skipped where IPv6 isn't available
+        let Some((url, server)) = serve("[::1]:0", None, |mut connection| async move {
+            connection.get_mut().write_all(&frame(true, 0x1, b"hello", None)).await.unwrap();
+        })
+        .await
+        else {
+            return;
+        };
+
+        let mut stream = connect(&url, &RequestOptions::default(), None).await.unwrap();
+        assert_eq!(stream.next_message(IDLE).await.unwrap().as_deref(), Some("hello"));
+        server.await.unwrap();
+    }
+}
@@ -0,0 +1,352 @@
+//! Declarative watcher sync ("config as code")
+//!
+//! A YAML file lists the watchers that should exist. `sync` compares it with
+//! the config and plans the additions, updates and removals that make the
+//! config match, so the watcher set can be kept in git and applied from CI.
+//! Watchers are matched by (normalized) URL; settings the file doesn't cover
+//! (TLS options, check results) are left alone.
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::{
+    config::Config,
+    matcher::Keyword,
+    schedule::ActiveWindow,
+    watcher::{
+        format_expiry, format_interval, normalize_url, parse_expiry, parse_interval, Watcher,
+        MAX_CONSENSUS_FETCHES,
+    },
+};
+
+/// One watcher as declared in the sync file
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Declared {
+    pub url: String,
+    /// Plain strings, or objects with settings (text, is_regex, whole_word,
+    /// priority, channels)
+    #[serde(default)]
+    pub keywords: Vec<Keyword>,
+    /// Names of shared keyword sets from the config
+    #[serde(default)]
+    pub keyword_sets: Vec<String>,
+    /// Check interval like "15m" (default 30m)
+    #[serde(default)]
+    pub interval: Option<String>,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Active hours like "08:00-20:00 mon-fri"
+    #[serde(default)]
+    pub active: Option<String>,
+    /// Expiration like "2025-12-31" or "2025-12-31 18:00"
+    #[serde(default)]
+    pub expires: Option<String>,
+    #[serde(default)]
+    pub once: bool,
+    #[serde(default)]
+    pub lightweight: bool,
+    #[serde(default)]
+    pub critical: bool,
+    #[serde(default)]
+    pub feed: bool,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub notify_cleared: bool,
+    #[serde(default)]
+    pub pause_when_blocked: bool,
+    #[serde(default)]
+    pub translate: bool,
+    /// Alert title template like "{{price}} at Example Shop"
+    #[serde(default)]
+    pub alert_title: Option<String>,
+    /// Pattern of a number read from the page on every check
+    #[serde(default)]
+    pub track_value: Option<String>,
+    /// Response time (ms) over which the site counts as slow
+    #[serde(default)]
+    pub latency_threshold_ms: Option<u64>,
+    /// Increase over the recent average (%) at which the site counts as slow
+    #[serde(default)]
+    pub latency_increase_percent: Option<u32>,
+    /// Time after a check in which the page isn't fetched again, like "5m"
+    #[serde(default)]
+    pub min_recheck_gap: Option<String>,
+    #[serde(default)]
+    pub consensus: u8,
+    /// Allow an interval below the minimum for public sites
+    #[serde(default)]
+    pub force_interval: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// A change that brings the config in line with the file
+pub enum Change {
+    Add(Watcher),
+    /// Replace the watcher at `index`; `fields` describes what changes
+    Update {
+        index: usize,
+        watcher: Watcher,
+        fields: Vec<String>,
+    },
+    Remove { index: usize },
+}
+
+/// The changes a sync would make, in file order (removals last)
+pub struct Plan {
+    pub changes: Vec<Change>,
+}
+
+/// Read the watcher list from a YAML file
+pub fn load(path: &Path) -> Result<Vec<Declared>> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read sync file: {}", path.display()))?;
+    serde_yaml::from_str(&content)
+        .with_context(|| format!("Failed to parse sync file (expected a list of watchers): {}", path.display()))
+}
+
+/// Work out the changes that make the config's watchers match the file.
+/// Fails on invalid entries without planning anything.
+pub fn plan(config: &Config, declared: &[Declared]) -> Result<Plan> {
+    let mut changes = Vec::new();
+    let mut kept = vec![false; config.watchers.len()];
+    let mut seen: Vec<String> = Vec::new();
+
+    for entry in declared {
+        let url = normalize_url(&entry.url)?;
+        if seen.contains(&url) {
+            anyhow::bail!("{} is listed more than once", url);
+        }
+        seen.push(url.clone());
+
+        match config.find_watcher_by_url(&url) {
+            Some(index) => {
+                kept[index] = true;
+                let current = &config.watchers[index];
+                let mut watcher = current.clone();
+                apply(entry, &mut watcher, config).with_context(|| format!("Invalid entry for {}", url))?;
+
+                let fields = differences(current, &watcher, config);
+                if !fields.is_empty() {
+                    changes.push(Change::Update { index, watcher, fields });
+                }
+            }
+            None => {
+                let mut watcher = Watcher::new(url.clone(), Vec::new(), Duration::ZERO);
+                apply(entry, &mut watcher, config).with_context(|| format!("Invalid entry for {}", url))?;
+                changes.push(Change::Add(watcher));
+            }
+        }
+    }
+
+    // Matrix children are managed by their matrix, not the file
+    for (index, kept) in kept.into_iter().enumerate() {
+        if !kept && config.watchers[index].matrix.is_none() {
+            changes.push(Change::Remove { index });
+        }
+    }
+
+    Ok(Plan { changes })
+}
+
+impl Plan {
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// One line per change, like `+ url (...)`, `~ url: ...` and `- url`
+    pub fn describe(&self, config: &Config) -> Vec<String> {
+        self.changes
+            .iter()
+            .map(|change| match change {
+                Change::Add(watcher) => format!(
+                    "+ {} (keywords: {} | interval: {}{})",
+                    watcher.url,
+                    watcher.keyword_summary(),
+                    format_interval(watcher.check_interval),
+                    if watcher.enabled { "" } else { " | disabled" }
+                ),
+                Change::Update { watcher, fields, .. } => {
+                    format!("~ {}: {}", watcher.url, fields.join(", "))
+                }
+                Change::Remove { index } => format!("- {}", config.watchers[*index].url),
+            })
+            .collect()
+    }
+
+    /// Counts of additions, updates and removals
+    pub fn counts(&self) -> (usize, usize, usize) {
+        let mut counts = (0, 0, 0);
+        for change in &self.changes {
+            match change {
+                Change::Add(_) => counts.0 += 1,
+                Change::Update { .. } => counts.1 += 1,
+                Change::Remove { .. } => counts.2 += 1,
+            }
+        }
+        counts
+    }
+
+    /// Make the changes in the config (not saved). Returns the removed
+    /// watchers, whose state and history the caller should delete.
+    pub fn apply(self, config: &mut Config) -> Vec<Watcher> {
+        let mut removals = Vec::new();
+        for change in self.changes {
+            match change {
+                Change::Add(watcher) => config.watchers.push(watcher),
+                Change::Update { index, watcher, .. } => config.watchers[index] = watcher,
+                Change::Remove { index } => removals.push(index),
+            }
+        }
+
+        // Highest index first, so the remaining indexes stay valid
+        removals.sort_unstable_by(|a, b| b.cmp(a));
+        removals
+            .into_iter()
+            .map(|index| config.watchers.remove(index))
+            .collect()
+    }
+}
+
+/// Set a watcher's declared settings, validated like the `add` command
+fn apply(entry: &Declared, watcher: &mut Watcher, config: &Config) -> Result<()> {
+    let keywords: Vec<Keyword> = entry
+        .keywords
+        .iter()
+        .map(|k| Keyword {
+            text: k.text.trim().to_string(),
+            ..k.clone()
+        })
+        .filter(|k| !k.text.is_empty())
+        .collect();
+    let keyword_sets: Vec<String> = entry
+        .keyword_sets
+        .iter()
+        .map(|name| name.trim().trim_start_matches('@').to_string())
+        .collect();
+    if keywords.is_empty() && keyword_sets.is_empty() {
+        anyhow::bail!("At least one keyword is required");
+    }
+    config.check_keyword_sets(&keyword_sets)?;
+    for keyword in keywords.iter().filter(|k| k.is_regex) {
+        keyword
+            .compile()
+            .with_context(|| format!("Invalid regex keyword: {}", keyword.text))?;
+    }
+
+    let interval = match &entry.interval {
+        Some(input) => parse_interval(input)?,
+        None => Duration::from_secs(30 * 60),
+    };
+    if let Some(warning) = config.guardrails.check_interval(&watcher.url, interval) {
+        if !entry.force_interval {
+            anyhow::bail!("{} Set force_interval: true to override.", warning);
+        }
+    }
+
+    if entry.consensus > MAX_CONSENSUS_FETCHES {
+        anyhow::bail!("consensus must be between 0 and {}", MAX_CONSENSUS_FETCHES);
+    }
+
+    // Re-enabling starts over, like toggling the watcher on in the TUI
+    if entry.enabled && !watcher.enabled {
+        watcher.disabled_reason = None;
+        watcher.blocked = None;
+        watcher.permanent_failures = 0;
+        watcher.permanent_failure_since = None;
+    }
+
+    watcher.keywords = keywords;
+    watcher.keyword_sets = keyword_sets;
+    watcher.check_interval = interval;
+    watcher.allow_short_interval = entry.force_interval;
+    watcher.enabled = entry.enabled;
+    watcher.active_window = match &entry.active {
+        Some(window) => ActiveWindow::parse(window)?,
+        None => None,
+    };
+    watcher.expires_at = match &entry.expires {
+        Some(date) => parse_expiry(date, &config.timezone)?,
+        None => None,
+    };
+    watcher.disable_after_match = entry.once;
+    watcher.lightweight = entry.lightweight;
+    watcher.critical = entry.critical;
+    watcher.feed = entry.feed;
+    watcher.stream = entry.stream;
+    watcher.notify_cleared = entry.notify_cleared;
+    watcher.pause_when_blocked = entry.pause_when_blocked;
+    watcher.translate = entry.translate;
+    watcher.alert_title = entry.alert_title.clone();
+    watcher.track_value = entry.track_value.clone();
+    watcher.latency_threshold_ms = entry.latency_threshold_ms;
+    watcher.latency_increase_percent = entry.latency_increase_percent;
+    watcher.consensus_fetches = entry.consensus;
+    watcher.min_recheck_gap = match &entry.min_recheck_gap {
+        Some(gap) => Some(parse_interval(gap)?.as_secs()),
+        None => None,
+    };
+    Ok(())
+}
+
+/// Describe the declared settings that differ between two versions of a watcher
+fn differences(old: &Watcher, new: &Watcher, config: &Config) -> Vec<String> {
+    let mut fields = Vec::new();
+    let mut compare = |name: &str, old: String, new: String| {
+        if old != new {
+            fields.push(format!("{} {} → {}", name, old, new));
+        }
+    };
+
+    let window = |w: &Watcher| w.active_window.as_ref().map_or(String::from("always"), |a| a.to_string());
+    let expiry = |w: &Watcher| {
+        w.expires_at
+            .map_or(String::from("never"), |at| format_expiry(at, &config.timezone))
+    };
+
+    let keywords = |w: &Watcher| w.keywords.iter().map(|k| k.describe()).collect::<Vec<_>>().join(", ");
+    compare("keywords", keywords(old), keywords(new));
+    let sets = |w: &Watcher| {
+        if w.keyword_sets.is_empty() { String::from("none") } else { w.keyword_sets.join(", ") }
+    };
+    compare("keyword_sets", sets(old), sets(new));
+    compare("interval", format_interval(old.check_interval), format_interval(new.check_interval));
+    compare("enabled", old.enabled.to_string(), new.enabled.to_string());
+    compare("active", window(old), window(new));
+    compare("expires", expiry(old), expiry(new));
+    compare("once", old.disable_after_match.to_string(), new.disable_after_match.to_string());
+    compare("lightweight", old.lightweight.to_string(), new.lightweight.to_string());
+    compare("critical", old.critical.to_string(), new.critical.to_string());
+    compare("feed", old.feed.to_string(), new.feed.to_string());
+    compare("stream", old.stream.to_string(), new.stream.to_string());
+    compare("notify_cleared", old.notify_cleared.to_string(), new.notify_cleared.to_string());
+    compare("pause_when_blocked", old.pause_when_blocked.to_string(), new.pause_when_blocked.to_string());
+    compare("translate", old.translate.to_string(), new.translate.to_string());
+    let title = |w: &Watcher| w.alert_title.clone().unwrap_or_else(|| String::from("default"));
+    compare("alert_title", title(old), title(new));
+    let tracked = |w: &Watcher| w.track_value.clone().unwrap_or_else(|| String::from("none"));
+    compare("track_value", tracked(old), tracked(new));
+    let threshold = |w: &Watcher| w.latency_threshold_ms.map_or(String::from("none"), |ms| format!("{}ms", ms));
+    compare("latency_threshold_ms", threshold(old), threshold(new));
+    let increase = |w: &Watcher| w.latency_increase_percent.map_or(String::from("none"), |pct| format!("{}%", pct));
+    compare("latency_increase_percent", increase(old), increase(new));
+    compare("consensus", old.consensus_fetches.to_string(), new.consensus_fetches.to_string());
+    let gap = |w: &Watcher| {
+        w.min_recheck_gap
+            .map_or(String::from("none"), |secs| format_interval(Duration::from_secs(secs)))
+    };
+    compare("min_recheck_gap", gap(old), gap(new));
+    compare(
+        "force_interval",
+        old.allow_short_interval.to_string(),
+        new.allow_short_interval.to_string(),
+    );
+    fields
+}
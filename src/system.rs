@@ -0,0 +1,322 @@
+//! System-wide installation serving several users
+//!
+//! For a family or a shared server, one daemon can monitor the watchers of
+//! several users. Each user has a directory under `users/` in the system
+//! directory (`/var/lib/web-watcher-alert` unless
+//! `WEB_WATCHER_ALERT_SYSTEM_DIR` says otherwise) holding their own config,
+//! data and cache:
+//!
+//! ```text
+//! /var/lib/web-watcher-alert/users/alice/config.json
+//! /var/lib/web-watcher-alert/users/alice/data/
+//! /var/lib/web-watcher-alert/users/alice/cache/
+//! ```
+//!
+//! `--daemon --system` runs one monitor process per user, with that user's
+//! directories, so every alert goes out through the notification channels
+//! of the user whose watcher matched. `--user <name>` points the other
+//! commands (and the TUI) at a user's directories.
+//!
+//! The monitors run as the service's account but with settings each user
+//! can edit, so they don't act on those settings with the account's rights:
+//! hooks, translation through the `command` provider, remote sync (`git`,
+//! `curl`, `aws`), custom CA files, calendar files, tasks (files and
+//! Reminders) and `http+unix://` sockets all fail for them.
+
+use anyhow::{Context, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+use std::time::{Duration, SystemTime};
+use tokio::io::{AsyncBufReadExt, AsyncRead, BufReader};
+use tokio::process::{Child, Command};
+
+use crate::{
+    config::{Config, CACHE_DIR_ENV, CONFIG_ENV, DATA_DIR_ENV, WEBHOOK_ENV},
+    events::{EventLog, LogFormat},
+    profile::PROFILE_ENV,
+};
+
+/// Environment variable overriding the system directory
+pub const SYSTEM_DIR_ENV: &str = "WEB_WATCHER_ALERT_SYSTEM_DIR";
+
+/// System directory when the environment variable isn't set
+pub const DEFAULT_SYSTEM_DIR: &str = "/var/lib/web-watcher-alert";
+
+/// Subdirectory of the system directory holding one directory per user
+const USERS_DIR: &str = "users";
+
+/// How often the users directory is scanned for added and removed users
+const USER_POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Environment variable naming the user a monitor was started for by the
+/// system daemon
+pub const MONITORED_USER_ENV: &str = "WEB_WATCHER_ALERT_SYSTEM_USER";
+
+/// The system directory
+pub fn system_dir() -> PathBuf {
+    std::env::var_os(SYSTEM_DIR_ENV)
+        .filter(|value| !value.is_empty())
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(DEFAULT_SYSTEM_DIR))
+}
+
+/// Check that a user name can be used as a directory name
+pub fn validate(name: &str) -> Result<()> {
+    if name.is_empty() {
+        anyhow::bail!("User name can't be empty");
+    }
+    if !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_') {
+        anyhow::bail!(
+            "Invalid user name '{}' (use letters, digits, '-' and '_')",
+            name
+        );
+    }
+    Ok(())
+}
+
+/// The user this process monitors for, if the system daemon started it.
+/// Such a monitor runs as the service's account with settings the user
+/// wrote, so it refuses to run commands, open files or connect to sockets
+/// named in them (see the module docs).
+pub fn monitored_user() -> Option<String> {
+    #[cfg(test)]
+    if let Some(user) = TEST_USER.with(|user| user.borrow().clone()) {
+        return Some(user);
+    }
+    std::env::var(MONITORED_USER_ENV).ok().filter(|name| !name.is_empty())
+}
+
+#[cfg(test)]
+thread_local! {
+    static TEST_USER: std::cell::RefCell<Option<String>> = const { std::cell::RefCell::new(None) };
+}
+
+/// Act as the monitor of `user` on this thread until the guard is dropped
+/// (the environment variable would leak into tests running alongside)
+#[cfg(test)]
+pub fn monitor_as(user: &str) -> impl Drop {
+    struct Reset;
+    impl Drop for Reset {
+        fn drop(&mut self) {
+            TEST_USER.with(|user| user.borrow_mut().take());
+        }
+    }
+    TEST_USER.with(|slot| *slot.borrow_mut() = Some(user.to_string()));
+    Reset
+}
+
+/// Directory of a user of the system installation
+pub fn user_dir(name: &str) -> Result<PathBuf> {
+    validate(name)?;
+    Ok(system_dir().join(USERS_DIR).join(name))
+}
+
+/// The config, data and cache locations of a user's directory, as the
+/// environment variables that select them
+fn user_env(dir: &Path) -> [(&'static str, PathBuf); 3] {
+    [
+        (CONFIG_ENV, dir.join("config.json")),
+        (DATA_DIR_ENV, dir.join("data")),
+        (CACHE_DIR_ENV, dir.join("cache")),
+    ]
+}
+
+/// Point every path lookup of this process at a user's directories
+/// (`--user <name>`). Call before any other thread is started.
+pub fn select_user(name: &str) -> Result<()> {
+    let dir = user_dir(name.trim())?;
+    for (variable, path) in user_env(&dir) {
+        std::env::set_var(variable, path);
+    }
+    Ok(())
+}
+
+/// Users with a config file, in name order
+pub fn users() -> Result<Vec<String>> {
+    let dir = system_dir().join(USERS_DIR);
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+
+    let entries = fs::read_dir(&dir)
+        .with_context(|| format!("Failed to read users directory: {}", dir.display()))?;
+    let mut names: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.path().join("config.json").is_file())
+        .filter_map(|entry| entry.file_name().into_string().ok())
+        .filter(|name| validate(name).is_ok())
+        .collect();
+    names.sort();
+    Ok(names)
+}
+
+/// A user's config
+pub fn load_user(name: &str) -> Result<Config> {
+    let path = user_dir(name)?.join("config.json");
+    let contents = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read config file: {}", path.display()))?;
+    serde_json::from_str(&contents)
+        .with_context(|| format!("Failed to parse config file: {}", path.display()))
+}
+
+/// When a user's config file was last written
+fn config_modified(name: &str) -> Option<SystemTime> {
+    let path = user_dir(name).ok()?.join("config.json");
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}
+
+/// The monitor process of one user
+struct UserDaemon {
+    child: Option<Child>,
+    /// How the last process ended, and when the config was last written at
+    /// that point. A process that stopped on its own (no enabled watchers
+    /// left) is started again once the config changes; one that failed is
+    /// retried on the next scan.
+    exited: Option<(ExitStatus, Option<SystemTime>)>,
+}
+
+/// Run one monitor process per user until the future is dropped (which
+/// kills them). The users directory is scanned again every 30 seconds for
+/// added and removed users.
+pub async fn serve(format: LogFormat, quiet: bool, log: &EventLog) -> Result<()> {
+    let mut daemons: BTreeMap<String, UserDaemon> = BTreeMap::new();
+    let mut warned = false;
+    log.info(format!("System directory: {}", system_dir().display()));
+
+    loop {
+        let users = users()?;
+        if users.is_empty() && !warned {
+            warned = true;
+            log.warning(format!(
+                "No users yet: add one with `web-watcher-alert --user <name> add <url>` (looking in {})",
+                system_dir().join(USERS_DIR).display()
+            ));
+        }
+
+        // Stop the processes of removed users
+        daemons.retain(|name, _| {
+            let keep = users.contains(name);
+            if !keep {
+                log.info(format!("User {} was removed, stopping their monitor", name));
+            }
+            keep
+        });
+
+        for name in &users {
+            let daemon = daemons.entry(name.clone()).or_insert(UserDaemon {
+                child: None,
+                exited: None,
+            });
+
+            // Notice processes that ended since the last scan
+            if let Some(child) = &mut daemon.child {
+                if let Ok(Some(status)) = child.try_wait() {
+                    if status.success() {
+                        log.info(format!(
+                            "Monitor of user {} stopped (no enabled watchers), waiting for their config to change",
+                            name
+                        ));
+                    } else {
+                        log.error(format!("Monitor of user {} failed ({}), restarting", name, status));
+                    }
+                    daemon.child = None;
+                    daemon.exited = Some((status, config_modified(name)));
+                }
+            }
+
+            let start = daemon.child.is_none()
+                && match daemon.exited {
+                    Some((status, modified)) if status.success() => config_modified(name) != modified,
+                    _ => true,
+                };
+            if start {
+                match spawn_user(name, format, quiet, log) {
+                    Ok(child) => {
+                        log.info(format!("Monitoring for user {}", name));
+                        daemon.child = Some(child);
+                        daemon.exited = None;
+                    }
+                    Err(e) => log.error(format!("Failed to start the monitor of user {}: {:#}", name, e)),
+                }
+            }
+        }
+
+        tokio::time::sleep(USER_POLL_INTERVAL).await;
+    }
+}
+
+/// Start the monitor of a user as a child process with the user's
+/// directories, forwarding its log tagged with the user name
+fn spawn_user(name: &str, format: LogFormat, quiet: bool, log: &EventLog) -> Result<Child> {
+    let dir = user_dir(name)?;
+    let program = std::env::current_exe().context("Failed to find the running executable")?;
+
+    // JSON lines can be re-logged in either format, with the user added
+    let mut command = Command::new(program);
+    command.args(["--daemon", "--foreground", "--log-format", "json"]);
+    if quiet {
+        command.arg("--quiet");
+    }
+
+    // Only the user's own settings: a webhook or profile from the system
+    // service's environment would send everyone's alerts to one place
+    command.envs(user_env(&dir));
+    command.env_remove(PROFILE_ENV).env_remove(WEBHOOK_ENV);
+    command.env(MONITORED_USER_ENV, name);
+
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start the monitor of user {}", name))?;
+
+    if let Some(stdout) = child.stdout.take() {
+        tokio::spawn(forward(stdout, name.to_string(), format, log.clone()));
+    }
+    if let Some(stderr) = child.stderr.take() {
+        tokio::spawn(forward(stderr, name.to_string(), format, log.clone()));
+    }
+    Ok(child)
+}
+
+/// Log the output of a user's monitor: JSON lines get a "user" field, text
+/// lines the user name in front of the message. Anything that isn't a log
+/// line (a startup error) is logged as an error of the user.
+async fn forward(output: impl AsyncRead + Unpin, user: String, format: LogFormat, log: EventLog) {
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let mut fields = match serde_json::from_str::<serde_json::Value>(&line) {
+            Ok(serde_json::Value::Object(fields)) => fields,
+            _ => {
+                log.error(format!("[{}] {}", user, line));
+                continue;
+            }
+        };
+
+        if format == LogFormat::Json {
+            fields.insert("user".to_string(), serde_json::Value::String(user.clone()));
+            println!("{}", serde_json::Value::Object(fields));
+            continue;
+        }
+
+        let message = format!(
+            "[{}] {}",
+            user,
+            fields.get("message").and_then(|m| m.as_str()).unwrap_or_default()
+        );
+        match fields.get("level").and_then(|l| l.as_str()) {
+            Some("match") => log.matched(message),
+            Some("warning") => log.warning(message),
+            Some("error") => log.error(message),
+            _ => log.info(message),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+//! Keyword alerts as tasks
+//!
+//! A notification is gone once dismissed; a task stays until it's done. With
+//! `"tasks"` in the notification settings, every keyword alert is also
+//! appended to a Markdown checklist or a todo.txt file, or created as a
+//! reminder in macOS Reminders (through `osascript`). Alerts about the app
+//! itself (errors, disabled watchers) don't become tasks.
+//!
+//! Tasks are a channel like the others: keywords can be limited to it
+//! (`"channels": ["tasks"]`), and failed writes are retried from the outbox.
+
+use anyhow::{Context, Result};
+use chrono::Local;
+use futures_util::future::BoxFuture;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::Command;
+
+use crate::matcher::Priority;
+use crate::notify::{Channel, Message, Notifier};
+
+/// Where tasks go
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TaskTarget {
+    /// `- [ ] ...` lines in a Markdown file
+    Markdown,
+    /// Lines in the todo.txt format
+    Todotxt,
+    /// macOS Reminders
+    Reminders,
+}
+
+impl TaskTarget {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "markdown" | "md" => Ok(TaskTarget::Markdown),
+            "todotxt" | "todo.txt" => Ok(TaskTarget::Todotxt),
+            "reminders" => Ok(TaskTarget::Reminders),
+            other => anyhow::bail!("Unknown task target: {} (use markdown, todotxt or reminders)", other),
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            TaskTarget::Markdown => "markdown",
+            TaskTarget::Todotxt => "todotxt",
+            TaskTarget::Reminders => "reminders",
+        }
+    }
+}
+
+/// Turning keyword alerts into tasks
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TaskSettings {
+    /// None: alerts don't become tasks
+    #[serde(default)]
+    pub target: Option<TaskTarget>,
+    /// The file tasks are appended to (markdown and todotxt; `~` is the
+    /// home directory)
+    #[serde(default)]
+    pub path: Option<String>,
+    /// The Reminders list (default: the default list)
+    #[serde(default)]
+    pub list: Option<String>,
+}
+
+impl TaskSettings {
+    /// The file tasks are appended to, with `~` expanded
+    pub fn file(&self) -> Result<PathBuf> {
+        let path = self
+            .path
+            .as_deref()
+            .filter(|path| !path.trim().is_empty())
+            .context("No task file: set notifications.tasks.path")?;
+        match path.strip_prefix("~/") {
+            Some(rest) => Ok(dirs::home_dir().context("No home directory")?.join(rest)),
+            None => Ok(PathBuf::from(path)),
+        }
+    }
+}
+
+/// Appends keyword alerts to a task file, or creates reminders
+pub struct TaskNotifier {
+    pub settings: TaskSettings,
+}
+
+impl Notifier for TaskNotifier {
+    fn name(&self) -> &str {
+        Channel::Tasks.name()
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, Result<()>> {
+        // Only keyword alerts have a priority
+        let result = match (message.priority, self.settings.target, crate::system::monitored_user()) {
+            // Writing the file (or running osascript) would happen as the
+            // service's account
+            (Some(_), Some(_), Some(user)) => Err(anyhow::anyhow!(
+                "Tasks are off for the users of the system daemon ({}): they would be written as the service's account",
+                user
+            )),
+            (Some(_), Some(TaskTarget::Reminders), None) => create_reminder(&self.settings, message),
+            (Some(_), Some(target), None) => append(&self.settings, task_line(target, message)),
+            _ => Ok(()),
+        };
+        Box::pin(async move { result })
+    }
+}
+
+/// The task for an alert, as a line of the file
+fn task_line(target: TaskTarget, message: &Message) -> String {
+    let title = message.title.replace(['\n', '\r'], " ");
+    let date = Local::now();
+    match target {
+        TaskTarget::Todotxt => {
+            // Creation date, and (A) for urgent ones so they sort first
+            let priority = if message.priority == Some(Priority::High) { "(A) " } else { "" };
+            format!("{}{} {} {} +webwatcher", priority, date.format("%Y-%m-%d"), title, message.url)
+        }
+        _ => format!("- [ ] [{}]({}) ({})", title, message.url, date.format("%Y-%m-%d %H:%M")),
+    }
+}
+
+fn append(settings: &TaskSettings, line: String) -> Result<()> {
+    let path = settings.file()?;
+    if let Some(parent) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .with_context(|| format!("Failed to open task file: {}", path.display()))?;
+    writeln!(file, "{}", line).with_context(|| format!("Failed to write task file: {}", path.display()))
+}
+
+fn create_reminder(settings: &TaskSettings, message: &Message) -> Result<()> {
+    if !cfg!(target_os = "macos") {
+        anyhow::bail!("Reminders are only on macOS: use the markdown or todotxt task target");
+    }
+    let list = match settings.list.as_deref().filter(|list| !list.trim().is_empty()) {
+        Some(list) => format!(" in list \"{}\"", applescript_string(list)),
+        None => String::new(),
+    };
+    let script = format!(
+        "tell application \"Reminders\" to make new reminder{} with properties {{name:\"{}\", body:\"{}\"}}",
+        list,
+        applescript_string(&message.title),
+        applescript_string(&format!("{}\n\n{}", message.url, message.body)),
+    );
+    let output = Command::new("osascript")
+        .args(["-e", &script])
+        .output()
+        .context("Failed to run osascript")?;
+    if !output.status.success() {
+        anyhow::bail!("Reminders refused the task: {}", String::from_utf8_lossy(&output.stderr).trim());
+    }
+    Ok(())
+}
+
+/// Text inside an AppleScript string literal
+fn applescript_string(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::BTreeMap;
+
+    #[tokio::test]
+    async fn monitored_users_get_no_tasks() {
+        let path = std::env::temp_dir().join(format!("web-watcher-alert-tasks-{}.md", std::process::id()));
+        let notifier = TaskNotifier {
+            settings: TaskSettings {
+                target: Some(TaskTarget::Markdown),
+                path: Some(path.display().to_string()),
+                list: None,
+            },
+        };
+        let message = Message {
+            title: String::from("Web Watcher Alert: restock found!"),
+            body: String::from("Found on the page"),
+            url: String::from("https://shop.example.com/gpu"),
+            keywords: vec![String::from("restock")],
+            priority: Some(Priority::Normal),
+            fields: BTreeMap::new(),
+        };
+
+        let _user = crate::system::monitor_as("alice");
+        let error = notifier.send(&message).await.unwrap_err();
+        assert!(error.to_string().contains("system daemon (alice)"), "{}", error);
+        assert!(!path.exists());
+    }
+}
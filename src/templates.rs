@@ -0,0 +1,203 @@
+//! Watcher templates module
+//!
+//! Provides presets for common watcher setups. Built-in templates ship with
+//! the app, and users can define their own in the config file. A template
+//! pre-fills the AddWatcher form (or the `add` command) with a URL prefix,
+//! keywords and a check interval, and sets the part of the page that's
+//! watched (a CSS selector, the main content, the visible text) and the
+//! parts that are ignored, so the site's boilerplate doesn't trigger alerts.
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+use crate::config::Config;
+use crate::extractor::ContentMode;
+use crate::watcher::Watcher;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Template {
+    /// Short identifier used on the command line (e.g. "github-release")
+    pub name: String,
+
+    /// Human-readable description shown in the UI
+    #[serde(default)]
+    pub description: String,
+
+    /// URL or URL prefix to pre-fill (may be empty)
+    #[serde(default)]
+    pub url: String,
+
+    /// Keywords to pre-fill
+    #[serde(default)]
+    pub keywords: Vec<String>,
+
+    /// Suggested check interval (in seconds)
+    #[serde(with = "crate::watcher::duration_serde")]
+    pub check_interval: Duration,
+
+    /// Only watch the part of the page this CSS selector picks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+
+    /// Leave out the parts of the page these CSS selectors pick
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+
+    /// Only watch the page's main content
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub main_content: Option<bool>,
+
+    /// Watch the HTML or the visible text
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<ContentMode>,
+}
+
+impl Template {
+    fn new(name: &str, description: &str, url: &str, keywords: &[&str], interval_mins: u64) -> Self {
+        Self {
+            name: name.to_string(),
+            description: description.to_string(),
+            url: url.to_string(),
+            keywords: keywords.iter().map(|k| k.to_string()).collect(),
+            check_interval: Duration::from_secs(interval_mins * 60),
+            selector: None,
+            ignore: Vec::new(),
+            main_content: None,
+            content: None,
+        }
+    }
+
+    fn selector(mut self, selector: &str) -> Self {
+        self.selector = Some(selector.to_string());
+        self
+    }
+
+    fn ignore(mut self, selectors: &[&str]) -> Self {
+        self.ignore = selectors.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    fn main_content(mut self) -> Self {
+        self.main_content = Some(true);
+        self
+    }
+
+    fn text(mut self) -> Self {
+        self.content = Some(ContentMode::Text);
+        self
+    }
+
+    /// Give a new watcher the template's settings for what's watched
+    pub fn apply(&self, watcher: &mut Watcher) {
+        watcher.selector = self.selector.clone();
+        watcher.ignore = self.ignore.clone();
+        watcher.main_content = self.main_content;
+        watcher.content = self.content;
+    }
+}
+
+/// Templates that ship with the app
+pub fn builtin_templates() -> Vec<Template> {
+    vec![
+        Template::new(
+            "amazon-availability",
+            "Amazon product availability",
+            "https://www.amazon.com/dp/",
+            &["in stock", "add to cart"],
+            30,
+        )
+        .selector("#availability, #buybox")
+        .ignore(&["#deliveryBlockMessage", "#buybox .a-price"])
+        .text(),
+        Template::new(
+            "github-release",
+            "GitHub release page",
+            "https://github.com/",
+            &["latest", "release"],
+            360,
+        )
+        .selector("main")
+        .ignore(&["relative-time", ".Counter"])
+        .text(),
+        Template::new(
+            "job-board",
+            "Job board listings",
+            "https://",
+            &["remote", "senior", "rust"],
+            60,
+        )
+        .main_content()
+        .ignore(&["time"])
+        .text(),
+        Template::new(
+            "ticket-sale",
+            "Event tickets going on sale",
+            "https://",
+            &["on sale", "buy tickets", "tickets available"],
+            15,
+        )
+        .ignore(&[".countdown", "time"])
+        .text(),
+    ]
+}
+
+/// All available templates: built-ins followed by user-defined templates.
+/// A user template with the same name as a built-in replaces it.
+pub fn all_templates(config: &Config) -> Vec<Template> {
+    let mut templates: Vec<Template> = builtin_templates()
+        .into_iter()
+        .filter(|b| !config.templates.iter().any(|t| t.name.eq_ignore_ascii_case(&b.name)))
+        .collect();
+
+    templates.extend(config.templates.iter().cloned());
+    templates
+}
+
+/// Look up a template by name (case-insensitive)
+pub fn find_template(config: &Config, name: &str) -> Option<Template> {
+    all_templates(config)
+        .into_iter()
+        .find(|t| t.name.eq_ignore_ascii_case(name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn user_templates_replace_builtins_of_the_same_name() {
+        let mine = Template::new("GitHub-Release", "My releases", "https://git.example.com/", &["tag"], 60);
+        let config = Config {
+            templates: vec![mine, Template::new("my-shop", "", "https://shop.example.com/", &["restock"], 15)],
+            ..Config::default()
+        };
+        let all = all_templates(&config);
+        assert_eq!(all.len(), builtin_templates().len() + 1);
+        assert_eq!(all.iter().filter(|t| t.name.eq_ignore_ascii_case("github-release")).count(), 1);
+
+        let found = find_template(&config, "github-release").unwrap();
+        assert_eq!(found.description, "My releases");
+        assert_eq!(find_template(&config, "my-shop").unwrap().check_interval, Duration::from_secs(15 * 60));
+        assert!(find_template(&config, "nope").is_none());
+    }
+
+    #[test]
+    fn templates_set_what_is_watched() {
+        let template = find_template(&Config::default(), "amazon-availability").unwrap();
+        let mut watcher = Watcher::new(template.url.clone(), Vec::new(), template.check_interval);
+        template.apply(&mut watcher);
+        assert_eq!(watcher.selector.as_deref(), Some("#availability, #buybox"));
+        assert_eq!(watcher.content, Some(ContentMode::Text));
+        assert_eq!(watcher.ignore, vec!["#deliveryBlockMessage", "#buybox .a-price"]);
+
+        let parsed: Template = serde_json::from_str(
+            r##"{ "name": "shop", "check_interval": 900, "selector": "#stock", "ignore": [".ad"], "main_content": true }"##,
+        )
+        .unwrap();
+        parsed.apply(&mut watcher);
+        assert_eq!(watcher.selector.as_deref(), Some("#stock"));
+        assert_eq!(watcher.ignore, vec![".ad"]);
+        assert_eq!(watcher.main_content, Some(true));
+        assert_eq!(watcher.content, None);
+    }
+}
@@ -0,0 +1,97 @@
+//! Time zone handling
+//!
+//! Timestamps are stored in UTC, but everything shown to the user (log lines,
+//! "last checked" times) and every wall-clock schedule (active hours) uses the
+//! configured zone, so "checked at 03:12" matches the user's clock. Defaults
+//! to the system's local time zone.
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Format used for log line timestamps
+pub const LOG_FORMAT: &str = "%Y-%m-%d %H:%M:%S";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Zone {
+    /// The system's local time zone
+    #[default]
+    Local,
+    /// An IANA time zone such as "Europe/Berlin" or "UTC"
+    Named(Tz),
+}
+
+impl Zone {
+    /// Parse "local" or an IANA time zone name
+    pub fn parse(input: &str) -> Result<Self> {
+        let input = input.trim();
+        if input.is_empty() || input.eq_ignore_ascii_case("local") {
+            return Ok(Zone::Local);
+        }
+
+        input.parse::<Tz>().map(Zone::Named).map_err(|_| {
+            anyhow::anyhow!(
+                "Unknown time zone '{}' (use \"local\" or a name like \"Europe/Berlin\")",
+                input
+            )
+        })
+    }
+
+    /// Convert a UTC timestamp to wall-clock time in this zone
+    pub fn wall_clock(&self, time: DateTime<Utc>) -> NaiveDateTime {
+        match self {
+            Zone::Local => time.with_timezone(&Local).naive_local(),
+            Zone::Named(tz) => time.with_timezone(tz).naive_local(),
+        }
+    }
+
+    /// Convert wall-clock time in this zone back to UTC.
+    /// Returns None for times skipped by a DST change.
+    pub fn utc_from_wall_clock(&self, time: NaiveDateTime) -> Option<DateTime<Utc>> {
+        match self {
+            Zone::Local => Local.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc)),
+            Zone::Named(tz) => tz.from_local_datetime(&time).earliest().map(|t| t.with_timezone(&Utc)),
+        }
+    }
+
+    /// Current wall-clock time in this zone
+    pub fn now(&self) -> NaiveDateTime {
+        self.wall_clock(Utc::now())
+    }
+
+    /// Format a UTC timestamp in this zone
+    pub fn format(&self, time: DateTime<Utc>, format: &str) -> String {
+        self.wall_clock(time).format(format).to_string()
+    }
+
+    /// Timestamp for the start of a log line
+    pub fn log_timestamp(&self) -> String {
+        self.format(Utc::now(), LOG_FORMAT)
+    }
+}
+
+impl fmt::Display for Zone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Zone::Local => write!(f, "local"),
+            Zone::Named(tz) => write!(f, "{}", tz.name()),
+        }
+    }
+}
+
+impl TryFrom<String> for Zone {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self> {
+        Zone::parse(&value)
+    }
+}
+
+impl From<Zone> for String {
+    fn from(zone: Zone) -> Self {
+        zone.to_string()
+    }
+}
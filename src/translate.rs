@@ -0,0 +1,264 @@
+//! Watching pages in other languages
+//!
+//! A translated watcher's keywords are written in the user's language
+//! (`translation.language`, English by default). When a changed page turns
+//! out to be in another language, the keywords are translated into it (once
+//! per language, remembered in the watcher's state) and looked for as well,
+//! and the context of each new match is translated back for the alert,
+//! which shows the original next to it.
+//!
+//! Translations come from a LibreTranslate server, DeepL, or a command of
+//! the user's that reads text on stdin and writes the translation to stdout.
+
+use anyhow::{Context, Result};
+use serde_json::{json, Value};
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::{TranslationProvider, TranslationSettings};
+use crate::matcher::{Keyword, KeywordMatch, KeywordMatcher};
+use crate::state::{KeywordTranslations, WatcherState};
+use crate::system;
+
+/// How long one translation request may take
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Characters of page text the language is detected from
+const SAMPLE_CHARS: usize = 5000;
+
+/// DeepL's API for free accounts (paid accounts set `url`)
+const DEEPL_FREE_URL: &str = "https://api-free.deepl.com";
+
+/// The language of a page as an ISO 639-1 code, if it can be told reliably
+pub fn detect_language(content: &str) -> Option<&'static str> {
+    let info = whatlang::detect(&visible_text(content))?;
+    if !info.is_reliable() {
+        return None;
+    }
+    isolang::Language::from_639_3(info.lang().code())?.to_639_1()
+}
+
+/// The page's language, unless it's the user's (or can't be told)
+pub fn foreign_language(settings: &TranslationSettings, content: &str) -> Option<&'static str> {
+    detect_language(content).filter(|language| !language.eq_ignore_ascii_case(&settings.language))
+}
+
+/// The start of a page's text, without markup, scripts and styles (plain
+/// text and JSON come out as they are)
+fn visible_text(content: &str) -> String {
+    let document = scraper::Html::parse_document(content);
+    let mut text = String::new();
+    for node in document.tree.nodes() {
+        let Some(fragment) = node.value().as_text() else {
+            continue;
+        };
+        let in_code = node
+            .parent()
+            .and_then(|parent| parent.value().as_element().map(|element| element.name()))
+            .is_some_and(|name| matches!(name, "script" | "style" | "noscript"));
+        if !in_code && !fragment.trim().is_empty() {
+            text.push_str(fragment.trim());
+            text.push(' ');
+        }
+        if text.len() >= SAMPLE_CHARS {
+            break;
+        }
+    }
+    text.chars().take(SAMPLE_CHARS).collect()
+}
+
+/// Matches of the keywords' translations into `language`. The translations
+/// are asked for once per language; regex keywords aren't translated.
+pub async fn find_translated(
+    settings: &TranslationSettings,
+    language: &str,
+    keywords: &[Keyword],
+    state: &mut WatcherState,
+    content: &str,
+) -> Result<Vec<KeywordMatch>> {
+    let plain: Vec<&Keyword> = keywords.iter().filter(|keyword| !keyword.is_regex).collect();
+    let texts: Vec<String> = plain.iter().map(|keyword| keyword.text.clone()).collect();
+
+    let known = state.keyword_translations.as_ref().filter(|known| {
+        known.language == language && known.keywords.iter().map(|(keyword, _)| keyword).eq(texts.iter())
+    });
+    let pairs = match known {
+        Some(known) => known.keywords.clone(),
+        None => {
+            let translated = translate(settings, &texts, &settings.language, language).await?;
+            let pairs: Vec<(String, String)> = texts.into_iter().zip(translated).collect();
+            state.set_keyword_translations(KeywordTranslations {
+                language: language.to_string(),
+                keywords: pairs.clone(),
+            });
+            pairs
+        }
+    };
+
+    // Translations that are the keyword itself were already looked for
+    let translated: Vec<Keyword> = plain
+        .iter()
+        .zip(&pairs)
+        .filter(|(keyword, (_, translation))| !translation.eq_ignore_ascii_case(&keyword.text))
+        .map(|(keyword, (_, translation))| Keyword {
+            text: translation.clone(),
+            ..(*keyword).clone()
+        })
+        .collect();
+
+    let mut matches = KeywordMatcher::new(&translated).find(content);
+    for m in &mut matches {
+        if let Some((keyword, _)) = pairs.iter().find(|(_, translation)| *translation == m.keyword) {
+            m.keyword = keyword.clone();
+        }
+    }
+    Ok(matches)
+}
+
+/// Translate the context of matches found on a page in `language` into the
+/// user's language
+pub async fn translate_contexts(
+    settings: &TranslationSettings,
+    language: &str,
+    matches: &mut [KeywordMatch],
+) -> Result<()> {
+    let contexts: Vec<String> = matches.iter().map(|m| m.context.clone()).collect();
+    let translated = translate(settings, &contexts, language, &settings.language).await?;
+    for (m, translation) in matches.iter_mut().zip(translated) {
+        m.translation = Some(translation);
+    }
+    Ok(())
+}
+
+/// Translate texts from one language into another (ISO 639-1 codes)
+pub async fn translate(settings: &TranslationSettings, texts: &[String], from: &str, to: &str) -> Result<Vec<String>> {
+    if texts.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    let translated = match settings.provider {
+        None => anyhow::bail!("No translation provider is configured"),
+        Some(TranslationProvider::LibreTranslate) => libretranslate(settings, texts, from, to).await?,
+        Some(TranslationProvider::DeepL) => deepl(settings, texts, from, to).await?,
+        Some(TranslationProvider::Command) => {
+            let mut translated = Vec::with_capacity(texts.len());
+            for text in texts {
+                translated.push(run_command(settings, text, from, to).await?);
+            }
+            translated
+        }
+    };
+
+    if translated.len() != texts.len() {
+        anyhow::bail!("The translation provider returned {} texts for {}", translated.len(), texts.len());
+    }
+    Ok(translated)
+}
+
+async fn libretranslate(settings: &TranslationSettings, texts: &[String], from: &str, to: &str) -> Result<Vec<String>> {
+    let Some(url) = &settings.url else {
+        anyhow::bail!("LibreTranslate needs the server's address in translation.url");
+    };
+    let mut payload = json!({
+        "q": texts,
+        "source": from,
+        "target": to,
+        "format": "text",
+    });
+    if let Some(key) = &settings.api_key {
+        payload["api_key"] = json!(key);
+    }
+
+    let endpoint = format!("{}/translate", url.trim_end_matches('/'));
+    let response = post(reqwest::Client::new().post(&endpoint).json(&payload), &endpoint).await?;
+    response["translatedText"]
+        .as_array()
+        .context("Unexpected response from LibreTranslate")?
+        .iter()
+        .map(|text| text.as_str().map(String::from).context("Unexpected response from LibreTranslate"))
+        .collect()
+}
+
+async fn deepl(settings: &TranslationSettings, texts: &[String], from: &str, to: &str) -> Result<Vec<String>> {
+    let Some(key) = &settings.api_key else {
+        anyhow::bail!("DeepL needs an API key in translation.api_key");
+    };
+    let url = settings.url.as_deref().unwrap_or(DEEPL_FREE_URL);
+    let payload = json!({
+        "text": texts,
+        "source_lang": from.to_uppercase(),
+        "target_lang": to.to_uppercase(),
+    });
+
+    let endpoint = format!("{}/v2/translate", url.trim_end_matches('/'));
+    let request = reqwest::Client::new()
+        .post(&endpoint)
+        .header(reqwest::header::AUTHORIZATION, format!("DeepL-Auth-Key {}", key))
+        .json(&payload);
+    let response = post(request, &endpoint).await?;
+    response["translations"]
+        .as_array()
+        .context("Unexpected response from DeepL")?
+        .iter()
+        .map(|translation| translation["text"].as_str().map(String::from).context("Unexpected response from DeepL"))
+        .collect()
+}
+
+/// Send a request to a translation API and read its JSON response
+async fn post(request: reqwest::RequestBuilder, endpoint: &str) -> Result<Value> {
+    let response = request
+        .timeout(TIMEOUT)
+        .send()
+        .await
+        .with_context(|| format!("Failed to reach the translation provider: {}", endpoint))?;
+    if !response.status().is_success() {
+        anyhow::bail!("The translation provider returned HTTP {}: {}", response.status(), endpoint);
+    }
+    response.json().await.context("Failed to read the translation")
+}
+
+/// Translate one text with the configured command
+async fn run_command(settings: &TranslationSettings, text: &str, from: &str, to: &str) -> Result<String> {
+    if let Some(user) = system::monitored_user() {
+        anyhow::bail!(
+            "The command provider is off for the users of the system daemon ({}): it would run as the service's account",
+            user
+        );
+    }
+    let Some(command_line) = &settings.command else {
+        anyhow::bail!("The command provider needs a command in translation.command");
+    };
+
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = tokio::process::Command::new("cmd");
+        command.arg("/C");
+        command
+    } else {
+        let mut command = tokio::process::Command::new("sh");
+        command.arg("-c");
+        command
+    };
+    let mut child = command
+        .arg(command_line)
+        .env("SOURCE_LANGUAGE", from)
+        .env("TARGET_LANGUAGE", to)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()
+        .with_context(|| format!("Failed to start the translation command: {}", command_line))?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        stdin.write_all(text.as_bytes()).await.context("Failed to pass the text to the translation command")?;
+    }
+    let output = tokio::time::timeout(TIMEOUT, child.wait_with_output())
+        .await
+        .with_context(|| format!("The translation command timed out: {}", command_line))?
+        .context("Failed to run the translation command")?;
+    if !output.status.success() {
+        anyhow::bail!("The translation command failed ({}): {}", output.status, command_line);
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
@@ -0,0 +1,118 @@
+//! Trash for deleted watchers
+//!
+//! Deleting a watcher (in the TUI, or `sync --apply` removing it) doesn't
+//! drop it: its config entry, check history and state are moved to
+//! trash/<id>/ in the data directory. From there it can be restored (Ctrl+Z
+//! in the TUI, or `trash restore`) until it's purged `trash.days` after the
+//! deletion.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::config::{Config, TrashSettings};
+use crate::history;
+use crate::state::WatcherState;
+use crate::watcher::Watcher;
+
+/// A deleted watcher
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Trashed {
+    pub watcher: Watcher,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Move a deleted watcher, with its history and state, into the trash
+pub fn put(watcher: &Watcher) -> Result<()> {
+    let dir = trash_dir()?.join(&watcher.id);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create trash directory: {}", dir.display()))?;
+
+    let entry = Trashed {
+        watcher: watcher.clone(),
+        deleted_at: Utc::now(),
+    };
+    let content = serde_json::to_string_pretty(&entry).context("Failed to serialize trashed watcher")?;
+    let path = dir.join("watcher.json");
+    fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+
+    move_file(&history::history_path(&watcher.id)?, &dir.join("history.jsonl"))?;
+    move_file(&WatcherState::path(&watcher.id)?, &dir.join("state.json"))
+}
+
+/// Get rid of a deleted watcher's history and state: into the trash, or
+/// for good when the trash is off
+pub fn discard(settings: &TrashSettings, watcher: &Watcher) -> Result<()> {
+    if settings.days > 0 {
+        return put(watcher);
+    }
+    WatcherState::remove(&watcher.id)?;
+    history::remove(&watcher.id)
+}
+
+/// The deleted watchers, most recently deleted first
+pub fn list() -> Result<Vec<Trashed>> {
+    let dir = trash_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut entries = Vec::new();
+    for item in fs::read_dir(&dir).with_context(|| format!("Failed to read trash: {}", dir.display()))? {
+        let path = item?.path().join("watcher.json");
+        // Skip what isn't a trashed watcher (or is half-written)
+        let Ok(content) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Ok(entry) = serde_json::from_str::<Trashed>(&content) {
+            entries.push(entry);
+        }
+    }
+    entries.sort_by_key(|entry| std::cmp::Reverse(entry.deleted_at));
+    Ok(entries)
+}
+
+/// Take a watcher out of the trash, putting its history and state back
+pub fn restore(watcher_id: &str) -> Result<Watcher> {
+    let dir = trash_dir()?.join(watcher_id);
+    let path = dir.join("watcher.json");
+    let content = fs::read_to_string(&path).with_context(|| format!("No watcher {} in the trash", watcher_id))?;
+    let entry: Trashed =
+        serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?;
+
+    move_file(&dir.join("history.jsonl"), &history::history_path(watcher_id)?)?;
+    move_file(&dir.join("state.json"), &WatcherState::path(watcher_id)?)?;
+    fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    Ok(entry.watcher)
+}
+
+/// Delete the watchers trashed more than `days` days ago (all of them with
+/// 0). Returns how many were deleted.
+pub fn purge(days: u32) -> Result<usize> {
+    let cutoff = Utc::now() - Duration::days(i64::from(days));
+    let mut purged = 0;
+    for entry in list()? {
+        if entry.deleted_at <= cutoff {
+            let dir = trash_dir()?.join(&entry.watcher.id);
+            fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+            purged += 1;
+        }
+    }
+    Ok(purged)
+}
+
+/// Move a file if it exists (a watcher may have no history or state yet)
+fn move_file(from: &Path, to: &Path) -> Result<()> {
+    if !from.exists() {
+        return Ok(());
+    }
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    fs::rename(from, to).with_context(|| format!("Failed to move {} to {}", from.display(), to.display()))
+}
+
+fn trash_dir() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join("trash"))
+}
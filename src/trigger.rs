@@ -0,0 +1,324 @@
+//! Inbound webhooks triggering checks
+//!
+//! With `triggers.enabled`, the monitor listens (on `triggers.listen`) for
+//! other systems telling it a page is worth checking now: a CI pipeline that
+//! just deployed, an IFTTT applet, a cron job elsewhere. A POST to
+//! `/trigger/<watcher id>` wakes the watcher, which checks right away
+//! (outside its active hours too) and then goes back to its interval.
+//!
+//! ```text
+//! POST /trigger/3f0c...
+//! Authorization: Bearer <token>
+//! ```
+//!
+//! A trigger never checks a page more often than the watcher's
+//! `min_recheck_gap` and the site's minimum interval (see `Guardrails`)
+//! allow: until then, requests get 429 with a `Retry-After`.
+
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{header, Body, Method, Request, Response, Server, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+use tokio::sync::{Notify, RwLock};
+
+use crate::companion;
+use crate::config::Config;
+use crate::events::EventLog;
+use crate::history;
+use crate::watcher::Watcher;
+
+/// Where the endpoint listens, and the secret requests carry
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TriggerSettings {
+    #[serde(default)]
+    pub enabled: bool,
+
+    /// Address to listen on (127.0.0.1 unless other machines send triggers)
+    #[serde(default = "default_listen")]
+    pub listen: String,
+
+    /// Secret that requests must send as `Authorization: Bearer <token>`
+    /// (the `triggers` command creates one)
+    #[serde(default)]
+    pub token: Option<String>,
+}
+
+impl Default for TriggerSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            listen: default_listen(),
+            token: None,
+        }
+    }
+}
+
+fn default_listen() -> String {
+    String::from("127.0.0.1:7393")
+}
+
+/// Wakes the watcher tasks of a monitor, by watcher ID
+#[derive(Clone, Default)]
+pub struct Triggers {
+    tasks: Arc<Mutex<HashMap<String, Arc<Notify>>>>,
+}
+
+impl Triggers {
+    /// What a watcher's task waits on besides its interval (replacing that
+    /// of a task it restarts)
+    pub fn register(&self, watcher_id: &str) -> Arc<Notify> {
+        let wake = Arc::new(Notify::new());
+        self.tasks
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert(watcher_id.to_string(), Arc::clone(&wake));
+        wake
+    }
+
+    /// Forget a watcher whose task stopped for good (removed or disabled)
+    pub fn unregister(&self, watcher_id: &str) {
+        self.tasks.lock().unwrap_or_else(PoisonError::into_inner).remove(watcher_id);
+    }
+
+    /// Wake a watcher's task. A task busy checking checks again as soon as
+    /// it's done. Returns false if the watcher has no task.
+    pub fn trigger(&self, watcher_id: &str) -> bool {
+        let tasks = self.tasks.lock().unwrap_or_else(PoisonError::into_inner);
+        match tasks.get(watcher_id) {
+            Some(wake) => {
+                wake.notify_one();
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// What the endpoint works with
+struct Shared {
+    config: Arc<RwLock<Config>>,
+    triggers: Triggers,
+    token: String,
+    log: EventLog,
+}
+
+/// Serve the endpoint until the task is aborted
+pub async fn serve(config: Arc<RwLock<Config>>, triggers: Triggers, log: EventLog) {
+    let settings = config.read().await.triggers.clone();
+    let Some(token) = settings.token.filter(|token| !token.is_empty()) else {
+        log.warning("⚠ The trigger endpoint needs a token: run `web-watcher-alert triggers` to create one");
+        return;
+    };
+    let addr: SocketAddr = match settings.listen.parse() {
+        Ok(addr) => addr,
+        Err(e) => {
+            log.error(format!("✗ Trigger endpoint: invalid listen address {}: {}", settings.listen, e));
+            return;
+        }
+    };
+    let server = match Server::try_bind(&addr) {
+        Ok(builder) => builder,
+        Err(e) => {
+            log.error(format!("✗ Trigger endpoint: failed to listen on {}: {}", addr, e));
+            return;
+        }
+    };
+
+    let shared = Arc::new(Shared {
+        config,
+        triggers,
+        token,
+        log: log.clone(),
+    });
+    let make_service = make_service_fn(move |_| {
+        let shared = Arc::clone(&shared);
+        async move {
+            Ok::<_, Infallible>(service_fn(move |request| {
+                let shared = Arc::clone(&shared);
+                async move { Ok::<_, Infallible>(respond(&shared, request).await) }
+            }))
+        }
+    });
+
+    log.info(format!("Trigger endpoint listening on http://{}", addr));
+    if let Err(e) = server.serve(make_service).await {
+        log.error(format!("✗ Trigger endpoint stopped: {}", e));
+    }
+}
+
+async fn respond(shared: &Shared, request: Request<Body>) -> Response<Body> {
+    let path = request.uri().path().to_string();
+    let mut retry_after = None;
+    let (status, body) = match (request.method(), path.strip_prefix("/trigger/")) {
+        _ if !authorized(shared, &request) => {
+            (StatusCode::UNAUTHORIZED, json!({ "ok": false, "error": "Missing or wrong token" }))
+        }
+        (&Method::POST, Some(watcher_id)) => match trigger(shared, watcher_id.trim_end_matches('/')).await {
+            Ok(message) => (StatusCode::ACCEPTED, json!({ "ok": true, "message": message })),
+            Err(refusal) => {
+                retry_after = refusal.retry_after;
+                (refusal.status, json!({ "ok": false, "error": refusal.error }))
+            }
+        },
+        (_, Some(_)) => (StatusCode::METHOD_NOT_ALLOWED, json!({ "ok": false, "error": "Use POST" })),
+        _ => (StatusCode::NOT_FOUND, json!({ "ok": false, "error": "Not found" })),
+    };
+
+    let mut response = Response::new(Body::from(body.to_string()));
+    *response.status_mut() = status;
+    let headers = response.headers_mut();
+    headers.insert(header::CONTENT_TYPE, header::HeaderValue::from_static("application/json"));
+    if let Some(wait) = retry_after {
+        headers.insert(header::RETRY_AFTER, header::HeaderValue::from(wait.as_secs().max(1)));
+    }
+    response
+}
+
+/// Whether the request carries the token
+fn authorized(shared: &Shared, request: &Request<Body>) -> bool {
+    request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .is_some_and(|sent| companion::token_matches(sent.trim(), &shared.token))
+}
+
+/// Why a trigger wasn't accepted
+struct Refusal {
+    status: StatusCode,
+    error: String,
+    /// How long until the watcher may be checked again (429 only)
+    retry_after: Option<Duration>,
+}
+
+impl Refusal {
+    fn new(status: StatusCode, error: impl Into<String>) -> Self {
+        Self {
+            status,
+            error: error.into(),
+            retry_after: None,
+        }
+    }
+}
+
+/// Wake the watcher a request names
+async fn trigger(shared: &Shared, watcher_id: &str) -> Result<String, Refusal> {
+    let url = {
+        let config = shared.config.read().await;
+        let watcher = config
+            .watchers
+            .iter()
+            .find(|w| w.id == watcher_id)
+            .ok_or_else(|| Refusal::new(StatusCode::NOT_FOUND, format!("No watcher with the ID {}", watcher_id)))?;
+        if !watcher.is_active() {
+            return Err(Refusal::new(StatusCode::CONFLICT, format!("The watcher of {} is disabled", watcher.url)));
+        }
+        if watcher.is_stream() {
+            return Err(Refusal::new(
+                StatusCode::CONFLICT,
+                format!("{} is a stream: it's watched all the time", watcher.url),
+            ));
+        }
+        if let Some(wait) = too_soon(&config, watcher) {
+            return Err(Refusal {
+                retry_after: Some(wait),
+                ..Refusal::new(
+                    StatusCode::TOO_MANY_REQUESTS,
+                    format!("{} was checked too recently, try again in {}s", watcher.url, wait.as_secs().max(1)),
+                )
+            });
+        }
+        watcher.url.clone()
+    };
+
+    if !shared.triggers.trigger(watcher_id) {
+        return Err(Refusal::new(StatusCode::SERVICE_UNAVAILABLE, format!("{} isn't being monitored yet", url)));
+    }
+    shared.log.for_watcher(&url).with_file(watcher_id).info(format!("Triggered: checking {} now", url));
+    Ok(format!("Checking {}", url))
+}
+
+/// How long until the watcher may be checked again: its last check (by any
+/// process, from the history) is within its `min_recheck_gap` or the
+/// minimum interval of its site
+fn too_soon(config: &Config, watcher: &Watcher) -> Option<Duration> {
+    let floor = config
+        .guardrails
+        .min_interval_for(&watcher.url)
+        .filter(|_| !watcher.allow_short_interval);
+    let gap = watcher.min_recheck_gap.map(Duration::from_secs).max(floor)?;
+    let last = history::load(&watcher.id, 1)
+        .ok()
+        .and_then(|mut records| records.pop())
+        .map(|record| record.checked_at)
+        .or(watcher.last_checked)?;
+    let since = (chrono::Utc::now() - last).to_std().unwrap_or_default();
+    gap.checked_sub(since).filter(|wait| !wait.is_zero())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn post(shared: &Shared, uri: &str, token: Option<&str>) -> Response<Body> {
+        let mut request = Request::post(uri);
+        if let Some(token) = token {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", token));
+        }
+        respond(shared, request.body(Body::empty()).unwrap()).await
+    }
+
+    #[tokio::test]
+    async fn triggers_need_the_token_and_respect_the_recheck_gap() {
+        let mut watcher = Watcher::new("http://localhost:8080/".into(), Vec::new(), Duration::from_secs(600));
+        watcher.min_recheck_gap = Some(300);
+        let id = watcher.id.clone();
+        let config = Config {
+            watchers: vec![watcher],
+            ..Config::default()
+        };
+        let (log, _) = EventLog::channel(config.timezone);
+        let shared = Shared {
+            config: Arc::new(RwLock::new(config)),
+            triggers: Triggers::default(),
+            token: String::from("secret"),
+            log,
+        };
+        shared.triggers.register(&id);
+        let uri = format!("/trigger/{}", id);
+
+        assert_eq!(post(&shared, &uri, Some("wrong")).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(post(&shared, &format!("{}?token=secret", uri), None).await.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(post(&shared, &uri, Some("secret")).await.status(), StatusCode::ACCEPTED);
+
+        shared.config.write().await.watchers[0].last_checked = Some(chrono::Utc::now());
+        let response = post(&shared, &uri, Some("secret")).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let retry_after: u64 = response.headers()[header::RETRY_AFTER].to_str().unwrap().parse().unwrap();
+        assert!((299..=300).contains(&retry_after), "{}", retry_after);
+
+        // Public sites aren't checked more often than their minimum interval
+        let mut public = Watcher::new("https://example.com/".into(), Vec::new(), Duration::from_secs(600));
+        public.last_checked = Some(chrono::Utc::now());
+        let uri = format!("/trigger/{}", public.id);
+        shared.triggers.register(&public.id);
+        shared.config.write().await.watchers.push(public);
+        assert_eq!(post(&shared, &uri, Some("secret")).await.status(), StatusCode::TOO_MANY_REQUESTS);
+    }
+
+    #[test]
+    fn stopped_watchers_are_forgotten() {
+        let triggers = Triggers::default();
+        triggers.register("a");
+        assert!(triggers.trigger("a"));
+        triggers.unregister("a");
+        assert!(!triggers.trigger("a"));
+        assert!(triggers.tasks.lock().unwrap().is_empty());
+    }
+}
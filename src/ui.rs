@@ -1,29 +1,83 @@
-/// Terminal User Interface module
-///
-/// This module handles all the interactive TUI screens:
-/// - Main menu
-/// - Add watcher form
-/// - List/edit watchers
-/// - Monitoring status view
+//! Terminal User Interface module
+//!
+//! This module handles all the interactive TUI screens:
+//! - First-run setup wizard
+//! - Main menu
+//! - Add watcher form
+//! - List/edit watchers
+//! - Live monitoring view (a dashboard of check outcomes and the log of the
+//!   monitor, which runs in the background while the other screens stay
+//!   usable and edits apply live)
+//! - Profile selector
+//! - Review queue of changes without keyword matches
+//! - Match heatmap: on which days and hours a watcher matched
 
 use anyhow::Result;
+use chrono::{DateTime, Datelike, Timelike, Utc};
 use crossterm::{
-    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
+    event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{
     backend::CrosstermBackend,
-    layout::{Alignment, Constraint, Direction, Layout},
+    layout::{Alignment, Constraint, Direction, Layout, Rect},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Wrap},
+    widgets::{Block, Borders, List, ListItem, ListState, Paragraph, Sparkline, Wrap},
     Frame, Terminal,
 };
+use std::collections::{BTreeMap, BTreeSet, VecDeque};
 use std::io;
-use std::time::Duration;
+use std::time::{Duration, Instant};
+use tokio::sync::{mpsc::UnboundedReceiver, watch};
+
+use crate::{
+    browser, clipboard,
+    config::Config,
+    events::{Event as LogEvent, EventLog, Level, MonitorEvent},
+    history::{self, CheckOutcome, CheckRecord},
+    matcher::{keywords_from_texts, Keyword},
+    monitor::{self, LiveConfig, Monitor},
+    profile,
+    review,
+    schedule::ActiveWindow,
+    state::WatcherState,
+    templates,
+    trash,
+    update,
+    values,
+    watcher::{
+        format_expiry, format_interval, parse_expiry, parse_interval, Watcher, INTERVAL_PRESETS,
+        MAX_CONSENSUS_FETCHES,
+    },
+};
+
+/// Number of recent checks shown on the watcher detail screen
+const DETAIL_HISTORY_LEN: usize = 50;
+
+/// Days of history the match heatmap covers
+const HEATMAP_DAYS: i64 = 30;
+
+/// Row labels of the heatmap, Monday first
+const WEEKDAYS: [&str; 7] = ["Mon", "Tue", "Wed", "Thu", "Fri", "Sat", "Sun"];
+const WEEKDAY_NAMES: [&str; 7] = ["Monday", "Tuesday", "Wednesday", "Thursday", "Friday", "Saturday", "Sunday"];
+
+/// Number of monitor events kept in the live monitoring log pane
+const MONITOR_LOG_LEN: usize = 2000;
+
+/// Most watchers listed on the live monitoring dashboard
+const DASHBOARD_ROWS: usize = 6;
+
+/// Lines moved by PgUp/PgDn in the log pane
+const LOG_PAGE: usize = 10;
+
+/// Changes Ctrl+Z can take back (older ones are forgotten)
+const UNDO_LEN: usize = 50;
 
-use crate::{config::Config, monitor::Monitor, watcher::Watcher};
+/// How often the main menu status panel is recomputed (it reads the config,
+/// every watcher's history and the service status)
+const MENU_STATUS_REFRESH: Duration = Duration::from_secs(5);
 
 #[derive(Debug, PartialEq)]
 enum Screen {
@@ -31,53 +85,300 @@ enum Screen {
     AddWatcher,
     ListWatchers,
     EditWatcher(usize), // Index of watcher being edited
+    WatcherDetail(usize), // Index of watcher shown with its check history
+    Heatmap(usize),       // Index of watcher whose matches are shown by day and hour
     ServiceControl,
+    Monitoring, // Live monitoring with the log pane
+    Profiles,
+    Review,
+    ReviewChange(usize), // Index of the change shown with its lines
+    Wizard(WizardStep),
 }
 
-#[derive(Debug, PartialEq)]
+/// Which events the live monitoring log pane shows
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum LogFilter {
+    All,
+    Matches,
+    Errors,
+}
+
+impl LogFilter {
+    /// The filter that 'f' switches to
+    fn next(self) -> Self {
+        match self {
+            LogFilter::All => LogFilter::Matches,
+            LogFilter::Matches => LogFilter::Errors,
+            LogFilter::Errors => LogFilter::All,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            LogFilter::All => "All",
+            LogFilter::Matches => "Matches only",
+            LogFilter::Errors => "Errors only",
+        }
+    }
+
+    fn shows(self, level: Level) -> bool {
+        match self {
+            LogFilter::All => true,
+            LogFilter::Matches => level == Level::Match,
+            LogFilter::Errors => level >= Level::Warning,
+        }
+    }
+}
+
+/// What the monitor reported about a watcher since monitoring started
+#[derive(Debug, Default)]
+struct WatcherActivity {
+    checks: u32,
+    matches: u32,
+    errors: u32,
+    /// Outcome of the latest check
+    last: Option<MonitorEvent>,
+}
+
+impl WatcherActivity {
+    fn record(&mut self, event: MonitorEvent) {
+        match &event {
+            MonitorEvent::CheckCompleted { at, .. } => {
+                self.checks += 1;
+                // Keep showing the match the check reported first
+                if matches!(&self.last, Some(MonitorEvent::MatchFound { at: found, .. }) if found == at) {
+                    return;
+                }
+            }
+            MonitorEvent::MatchFound { .. } => self.matches += 1,
+            MonitorEvent::Error { .. } | MonitorEvent::Blocked { .. } => {
+                self.checks += 1;
+                self.errors += 1;
+            }
+        }
+        self.last = Some(event);
+    }
+}
+
+/// Steps of the first-run setup wizard
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum WizardStep {
+    Welcome,
+    Watcher,
+    Notifications,
+    Service,
+    Done,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
 enum FormField {
     Url,
     Keywords,
     Interval,
+    Schedule,
+    Expires,
+}
+
+impl FormField {
+    /// The field that Tab moves to
+    fn next(self) -> Self {
+        match self {
+            FormField::Url => FormField::Keywords,
+            FormField::Keywords => FormField::Interval,
+            FormField::Interval => FormField::Schedule,
+            FormField::Schedule => FormField::Expires,
+            FormField::Expires => FormField::Url,
+        }
+    }
+}
+
+/// A change Ctrl+Z takes back
+enum Undo {
+    /// A watcher moved to the trash, and where it was in the list
+    Deleted { watcher_id: String, url: String, index: usize },
+    /// Watchers enabled or disabled, with their previous setting
+    Enabled(Vec<(String, bool)>),
+    /// A watcher archived or restored from the archive
+    Archived { watcher_id: String },
+    /// A watcher's check history, as it was before it was cleared
+    HistoryCleared { watcher_id: String, content: String },
+}
+
+/// Figures shown in the main menu status panel
+struct MenuStatus {
+    refreshed: Instant,
+    total: usize,
+    enabled: usize,
+    daemon_running: bool,
+    matches_last_day: usize,
+    /// Changes without keyword matches waiting for review
+    to_review: usize,
+    /// When the next check is due and for which URL (None without enabled watchers)
+    next_check: Option<(DateTime<Utc>, String)>,
+    /// A newer release, if the update check found one
+    update: Option<String>,
+}
+
+/// Validated values from the add/edit form
+struct FormValues {
+    url: String,
+    keywords: Vec<String>,
+    /// Keyword sets given as `@name`
+    keyword_sets: Vec<String>,
+    interval: Duration,
+    active_window: Option<ActiveWindow>,
+    expires_at: Option<DateTime<Utc>>,
 }
 
 pub struct UI {
     config: Config,
     screen: Screen,
     menu_state: ListState,
+    menu_status: Option<MenuStatus>, // Cached status panel figures
+    menu_message: String,            // Why the last menu action was refused
     watcher_list_state: ListState,
+    show_archived: bool, // The list shows the archived watchers instead of the others
+    expanded_matrices: BTreeSet<String>, // Matrices listed watcher by watcher (others are one row)
 
     // Form state for adding/editing watchers
     form_field: FormField,
     url_input: String,
     keywords_input: String,
     interval_input: String,
+    schedule_input: String,
+    expires_input: String,
+    template_index: Option<usize>, // Template currently applied to the add form
+    form_error: String,            // Validation error shown below the form
+    duplicate_of: Option<usize>,   // Existing watcher with the same URL (merge prompt)
+    pending_keywords: Vec<Keyword>, // Keywords to merge if the user accepts
+    pending_keyword_sets: Vec<String>, // ... and keyword sets
+    interval_override: bool,       // Short-interval warning shown; Enter again saves anyway
+
+    // Watcher detail state
+    detail_history: Vec<CheckRecord>, // Recent checks, newest first
+    detail_list_state: ListState,
+    detail_message: String, // Result of applying a page move or opening the page
+    heatmap_matches: Vec<DateTime<Utc>>, // Matched checks of the heatmap's watcher
+    list_message: String,   // Error of the last list action, shown in the title
+    undo: Vec<Undo>,        // Changes Ctrl+Z takes back, newest last
+
+    // Live monitoring state
+    monitor_runtime: Option<tokio::runtime::Runtime>, // Runs the monitor until stopped or the app exits
+    monitor_config: Option<LiveConfig>, // The running monitor's config (edited in place)
+    monitor_events: Option<UnboundedReceiver<LogEvent>>,
+    monitor_checks: Option<UnboundedReceiver<MonitorEvent>>, // Check outcomes for the dashboard
+    monitor_activity: BTreeMap<String, WatcherActivity>, // By watcher ID
+    monitor_save_failure: Option<watch::Receiver<Option<String>>>,
+    monitor_log: VecDeque<LogEvent>, // Oldest first
+    monitor_filter: LogFilter,
+    monitor_scroll: usize, // Lines scrolled up from the newest (0 follows new events)
+
+    // Profile selector state
+    profile_names: Vec<String>,
+    profile_state: ListState,
+    profile_input: Option<String>, // Name being typed for a new profile
+    profile_message: String,
+
+    // Review queue state
+    review_items: Vec<review::Item>, // Newest first
+    review_state: ListState,
+    review_line_state: ListState,   // Line selected in the change shown
+    review_keyword: Option<String>, // Keyword being made from a line
+    review_message: String,
 
     // Service control state
     service_status_message: String,
     service_is_running: bool,
+
+    // Setup wizard state
+    wizard_webhook_input: String,
+    wizard_webhook_focused: bool, // Webhook field focused (otherwise the desktop toggle)
+    wizard_message: String,
 }
 
 impl UI {
     pub fn new() -> Result<Self> {
+        // Launch the setup wizard if this is the first run (no config file yet)
+        let first_run = !Config::config_path()?.exists();
+
         let config = Config::load()?;
+        // Forget the watchers deleted long enough ago
+        let _ = trash::purge(config.trash.days);
         let mut menu_state = ListState::default();
         menu_state.select(Some(0));
 
+        // The wizard saves what it sets up, which a read-only config can't
+        let screen = if first_run && !config.is_read_only() {
+            Screen::Wizard(WizardStep::Welcome)
+        } else {
+            Screen::MainMenu
+        };
+
         Ok(Self {
             config,
-            screen: Screen::MainMenu,
+            screen,
             menu_state,
+            menu_status: None,
+            menu_message: String::new(),
             watcher_list_state: ListState::default(),
+            show_archived: false,
+            expanded_matrices: BTreeSet::new(),
             form_field: FormField::Url,
             url_input: String::new(),
             keywords_input: String::new(),
-            interval_input: String::from("30"),
+            interval_input: String::from("30m"),
+            schedule_input: String::new(),
+            expires_input: String::new(),
+            template_index: None,
+            form_error: String::new(),
+            duplicate_of: None,
+            pending_keywords: Vec::new(),
+            pending_keyword_sets: Vec::new(),
+            interval_override: false,
+            detail_history: Vec::new(),
+            detail_list_state: ListState::default(),
+            detail_message: String::new(),
+            heatmap_matches: Vec::new(),
+            list_message: String::new(),
+            undo: Vec::new(),
+            monitor_runtime: None,
+            monitor_config: None,
+            monitor_events: None,
+            monitor_checks: None,
+            monitor_activity: BTreeMap::new(),
+            monitor_save_failure: None,
+            monitor_log: VecDeque::new(),
+            monitor_filter: LogFilter::All,
+            monitor_scroll: 0,
+            profile_names: Vec::new(),
+            profile_state: ListState::default(),
+            profile_input: None,
+            profile_message: String::new(),
+            review_items: Vec::new(),
+            review_state: ListState::default(),
+            review_line_state: ListState::default(),
+            review_keyword: None,
+            review_message: String::new(),
             service_status_message: String::new(),
             service_is_running: false,
+            wizard_webhook_input: String::new(),
+            wizard_webhook_focused: false,
+            wizard_message: String::new(),
         })
     }
 
+    /// Start on the add form with the URL filled in (`add-url`, or a URL
+    /// passed by a browser extension or another tool at launch)
+    pub fn add_url(&mut self, url: &str) -> Result<()> {
+        self.config.ensure_writable()?;
+        let url = crate::watcher::normalize_url(url)?;
+        self.clear_form();
+        self.url_input = url;
+        self.form_field = FormField::Keywords;
+        self.screen = Screen::AddWatcher;
+        Ok(())
+    }
+
     pub fn run(&mut self) -> Result<()> {
         // Setup terminal
         enable_raw_mode()?;
@@ -86,8 +387,20 @@ impl UI {
         let backend = CrosstermBackend::new(stdout);
         let mut terminal = Terminal::new(backend)?;
 
+        // Look for a new release in the background; the status panel shows
+        // it once found
+        if self.config.update_check.enabled {
+            let settings = self.config.update_check.clone();
+            std::thread::spawn(move || {
+                if let Ok(runtime) = tokio::runtime::Runtime::new() {
+                    let _ = runtime.block_on(update::check(&settings));
+                }
+            });
+        }
+
         // Run the UI loop
         let result = self.run_loop(&mut terminal);
+        self.stop_monitoring();
 
         // Restore terminal
         disable_raw_mode()?;
@@ -103,12 +416,18 @@ impl UI {
 
     fn run_loop(&mut self, terminal: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
         loop {
+            // Show the monitor's latest check results when it reports something
+            if self.receive_monitor_events() {
+                if let Some(live) = &self.monitor_config {
+                    self.config = live.snapshot();
+                }
+            }
             terminal.draw(|f| self.draw(f))?;
 
             // Handle input with timeout
             if event::poll(Duration::from_millis(100))? {
                 if let Event::Key(key) = event::read()? {
-                    if self.handle_input(key.code)? {
+                    if self.handle_input(key)? {
                         break; // Exit requested
                     }
                 }
@@ -119,37 +438,111 @@ impl UI {
     }
 
     fn draw(&mut self, f: &mut Frame) {
+        // Recompute the status panel when coming back to the main menu
+        if self.screen != Screen::MainMenu {
+            self.menu_status = None;
+        }
+
         match &self.screen {
             Screen::MainMenu => self.draw_main_menu(f),
             Screen::AddWatcher => self.draw_add_watcher(f),
             Screen::ListWatchers => self.draw_list_watchers(f),
             Screen::EditWatcher(idx) => self.draw_edit_watcher(f, *idx),
+            Screen::WatcherDetail(idx) => self.draw_watcher_detail(f, *idx),
+            Screen::Heatmap(idx) => self.draw_heatmap(f, *idx),
             Screen::ServiceControl => self.draw_service_control(f),
+            Screen::Monitoring => self.draw_monitoring(f),
+            Screen::Profiles => self.draw_profiles(f),
+            Screen::Review => self.draw_review(f),
+            Screen::ReviewChange(idx) => self.draw_review_change(f, *idx),
+            Screen::Wizard(step) => self.draw_wizard(f, *step),
         }
     }
 
     fn draw_main_menu(&mut self, f: &mut Frame) {
+        let stale = self
+            .menu_status
+            .as_ref()
+            .is_none_or(|status| status.refreshed.elapsed() >= MENU_STATUS_REFRESH);
+        if stale {
+            self.refresh_menu_status();
+        }
+        // One more line in the status panel when an update is available
+        let status_height = match self.menu_status.as_ref().and_then(|s| s.update.as_ref()) {
+            Some(_) => 8,
+            None => 7,
+        };
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3),
+                Constraint::Length(status_height),
                 Constraint::Min(0),
                 Constraint::Length(3),
             ])
             .split(f.size());
 
-        // Title
-        let title = Paragraph::new("Web Watcher Alert")
+        // Title (names the profile unless it's the default one)
+        let mut title = match profile::active() {
+            Some(name) => format!("Web Watcher Alert - Profile: {}", name),
+            None => String::from("Web Watcher Alert"),
+        };
+        if self.config.is_read_only() {
+            title.push_str(" (read-only)");
+        }
+        let title = Paragraph::new(title)
             .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(title, chunks[0]);
 
+        // Status panel
+        if let Some(status) = &self.menu_status {
+            let daemon = if status.daemon_running {
+                Span::styled("● Running", Style::default().fg(Color::Green))
+            } else {
+                Span::styled("○ Stopped", Style::default().fg(Color::Gray))
+            };
+            let next_check = match &status.next_check {
+                Some((at, url)) if *at <= Utc::now() => format!("due now ({})", url),
+                Some((at, url)) => format!(
+                    "{} ({})",
+                    self.config.timezone.format(*at, "%b %d %H:%M"),
+                    url
+                ),
+                None => String::from("none (no enabled watchers)"),
+            };
+            let mut lines = vec![
+                Line::from(format!(
+                    "Watchers: {} ({} enabled)",
+                    status.total, status.enabled
+                )),
+                Line::from(vec![Span::raw("Service: "), daemon]),
+                Line::from(format!("Matches in the last 24h: {}", status.matches_last_day)),
+                Line::from(format!("Changes to review: {}", status.to_review)),
+                Line::from(format!("Next check: {}", next_check)),
+            ];
+            if let Some(version) = &status.update {
+                lines.push(Line::from(Span::styled(
+                    format!("Update: {} available (running {})", version, update::CURRENT_VERSION),
+                    Style::default().fg(Color::Yellow),
+                )));
+            }
+            let panel = Paragraph::new(lines)
+                .block(Block::default().title("Status").borders(Borders::ALL));
+            f.render_widget(panel, chunks[1]);
+        }
+
         // Menu items
         let menu_items = vec![
             ListItem::new("1. Add Watcher"),
             ListItem::new("2. List Watchers"),
-            ListItem::new("3. Start Monitoring"),
+            ListItem::new(if self.monitor_runtime.is_some() {
+                "3. Monitoring (running)"
+            } else {
+                "3. Start Monitoring"
+            }),
             ListItem::new("4. Service Control"),
             ListItem::new("5. Exit"),
         ];
@@ -163,75 +556,165 @@ impl UI {
             )
             .highlight_symbol(">> ");
 
-        f.render_stateful_widget(menu, chunks[1], &mut self.menu_state);
+        f.render_stateful_widget(menu, chunks[2], &mut self.menu_state);
 
         // Help text
-        let help = Paragraph::new("↑↓: Navigate | Enter: Select | q: Quit")
-            .style(Style::default().fg(Color::Gray))
+        let (help, help_color) = if self.menu_message.is_empty() {
+            ("↑↓: Navigate | Enter: Select | r: Review changes | p: Profiles | q: Quit", Color::Gray)
+        } else {
+            (self.menu_message.as_str(), Color::Red)
+        };
+        let help = Paragraph::new(help)
+            .style(Style::default().fg(help_color))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
-        f.render_widget(help, chunks[2]);
+        f.render_widget(help, chunks[3]);
+    }
+
+    /// Recompute the main menu status panel. Reads the config from disk
+    /// because the service updates last-checked times behind the TUI's back.
+    fn refresh_menu_status(&mut self) {
+        self.check_service_status();
+        let config = Config::load().unwrap_or_else(|_| self.config.clone());
+
+        let since = Utc::now() - chrono::Duration::hours(24);
+        let matches_last_day = config
+            .watchers
+            .iter()
+            .map(|w| history::matches_since(&w.id, since).unwrap_or(0))
+            .sum();
+        let next_check = config
+            .watchers
+            .iter()
+            .filter(|w| w.is_active() && !w.is_expired())
+            .map(|w| (monitor::next_check_at(w, &config), w.url.clone()))
+            .min_by_key(|(at, _)| *at);
+
+        self.menu_status = Some(MenuStatus {
+            refreshed: Instant::now(),
+            total: config.watchers.iter().filter(|w| !w.archived).count(),
+            enabled: config.watchers.iter().filter(|w| w.is_active()).count(),
+            daemon_running: self.service_is_running,
+            matches_last_day,
+            to_review: review::load().map_or(0, |items| items.len()),
+            next_check,
+            update: config.update_check.enabled.then(update::available).flatten(),
+        });
     }
 
     fn draw_add_watcher(&mut self, f: &mut Frame) {
+        // Title (shows the applied template, if any)
+        let title = match self.template_index {
+            Some(i) => {
+                let name = templates::all_templates(&self.config)
+                    .get(i)
+                    .map(|t| t.description.clone())
+                    .unwrap_or_default();
+                format!("Add New Watcher (Template: {})", name)
+            }
+            None => String::from("Add New Watcher"),
+        };
+
+        self.draw_watcher_form(
+            f,
+            &title,
+            "Tab: Next field | F2: Template | F3: Interval preset | F4: Paste URL | Enter: Save | Esc: Cancel",
+        );
+    }
+
+    /// Draw the add/edit watcher form: title, one input per field,
+    /// a validation/prompt line and the help bar
+    fn draw_watcher_form(&self, f: &mut Frame, title: &str, help: &str) {
+        let fields = [
+            (FormField::Url, "URL", self.url_input.as_str()),
+            (FormField::Keywords, "Keywords (comma-separated)", self.keywords_input.as_str()),
+            (FormField::Interval, "Check Interval (e.g. 90s, 15m, 2h, 1d)", self.interval_input.as_str()),
+            (
+                FormField::Schedule,
+                "Active Hours (optional, e.g. 08:00-20:00 mon-fri)",
+                self.schedule_input.as_str(),
+            ),
+            (
+                FormField::Expires,
+                "Expires (optional, YYYY-MM-DD [HH:MM])",
+                self.expires_input.as_str(),
+            ),
+        ];
+
+        let mut constraints = vec![Constraint::Length(3)];
+        constraints.extend(fields.iter().map(|_| Constraint::Length(3)));
+        constraints.push(Constraint::Min(0));
+        constraints.push(Constraint::Length(3));
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .margin(2)
-            .constraints([
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Min(0),
-                Constraint::Length(3),
-            ])
+            .constraints(constraints)
             .split(f.size());
 
         // Title
-        let title = Paragraph::new("Add New Watcher")
+        let title = Paragraph::new(title)
             .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(title, chunks[0]);
 
-        // URL field
-        let url_style = if self.form_field == FormField::Url {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let url = Paragraph::new(self.url_input.as_str())
-            .style(url_style)
-            .block(Block::default().title("URL").borders(Borders::ALL));
-        f.render_widget(url, chunks[1]);
-
-        // Keywords field
-        let keywords_style = if self.form_field == FormField::Keywords {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let keywords = Paragraph::new(self.keywords_input.as_str())
-            .style(keywords_style)
-            .block(Block::default().title("Keywords (comma-separated)").borders(Borders::ALL));
-        f.render_widget(keywords, chunks[2]);
+        // Input fields (the focused one is highlighted)
+        for (i, (field, label, value)) in fields.iter().enumerate() {
+            let style = if self.form_field == *field {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            let input = Paragraph::new(*value)
+                .style(style)
+                .block(Block::default().title(*label).borders(Borders::ALL));
+            f.render_widget(input, chunks[i + 1]);
+        }
 
-        // Interval field
-        let interval_style = if self.form_field == FormField::Interval {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
-        };
-        let interval = Paragraph::new(self.interval_input.as_str())
-            .style(interval_style)
-            .block(Block::default().title("Check Interval (minutes)").borders(Borders::ALL));
-        f.render_widget(interval, chunks[3]);
+        // Validation error
+        if !self.form_error.is_empty() {
+            // Merge and short-interval prompts are warnings, not errors
+            let color = if self.duplicate_of.is_some() || self.interval_override {
+                Color::Yellow
+            } else {
+                Color::Red
+            };
+            let error = Paragraph::new(self.form_error.as_str())
+                .style(Style::default().fg(color))
+                .wrap(Wrap { trim: true });
+            f.render_widget(error, chunks[fields.len() + 1]);
+        }
 
         // Help
-        let help = Paragraph::new("Tab: Next field | Enter: Save | Esc: Cancel")
+        let help = Paragraph::new(help)
             .style(Style::default().fg(Color::Gray))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
-        f.render_widget(help, chunks[5]);
+        f.render_widget(help, chunks[fields.len() + 2]);
+    }
+
+    /// The row of a folded matrix: how many watchers it has and how they do
+    fn matrix_row(&self, name: &str) -> ListItem<'static> {
+        let children = self.matrix_watchers(name);
+        let enabled = children.iter().filter(|&&i| self.config.watchers[i].enabled).count();
+        let failing = children
+            .iter()
+            .filter(|&&i| self.config.watchers[i].consecutive_errors > 0)
+            .count();
+        let first = &self.config.watchers[children[0]];
+        let mut text = format!(
+            "▸ Matrix {}: {} watchers ({} enabled) | Keywords: {} | Every {}",
+            name,
+            children.len(),
+            enabled,
+            first.keyword_summary(),
+            format_interval(first.check_interval)
+        );
+        if failing > 0 {
+            text.push_str(&format!(" | ⚠ {} failing", failing));
+            return ListItem::new(text).style(Style::default().fg(Color::Yellow));
+        }
+        ListItem::new(text).style(Style::default().fg(Color::Cyan))
     }
 
     fn draw_list_watchers(&mut self, f: &mut Frame) {
@@ -245,33 +728,129 @@ impl UI {
             .split(f.size());
 
         // Title
-        let title = Paragraph::new(format!("Watchers ({})", self.config.watchers.len()))
+        let listed = self.listed_watchers();
+        let heading = if self.show_archived { "Archived watchers" } else { "Watchers" };
+        let title = match self.list_message.as_str() {
+            "" => format!("{} ({})", heading, listed.len()),
+            message => format!("{} ({}) | {}", heading, listed.len(), message),
+        };
+        let title = Paragraph::new(title)
             .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(title, chunks[0]);
 
         // Watcher list
-        if self.config.watchers.is_empty() {
-            let empty = Paragraph::new("No watchers configured.\nPress 'a' to add one.")
+        if listed.is_empty() {
+            let message = if self.show_archived {
+                "No archived watchers.\nPress 'A' on a watcher to archive it."
+            } else {
+                "No watchers configured.\nPress 'a' to add one."
+            };
+            let empty = Paragraph::new(message)
                 .style(Style::default().fg(Color::Gray))
                 .alignment(Alignment::Center)
                 .block(Block::default().borders(Borders::ALL));
             f.render_widget(empty, chunks[1]);
         } else {
-            let items: Vec<ListItem> = self
-                .config
-                .watchers
+            let items: Vec<ListItem> = listed
                 .iter()
-                .enumerate()
+                .map(|&i| (i, &self.config.watchers[i]))
                 .map(|(i, w)| {
-                    let status = if w.enabled { "✓" } else { "✗" };
-                    let keywords = w.keywords.join(", ");
-                    let interval_mins = w.check_interval.as_secs() / 60;
-                    let text = format!(
-                        "{} [{}] {} | Keywords: {} | Every {} min",
-                        status, i + 1, w.url, keywords, interval_mins
+                    if let Some(name) = self.folded_matrix(i) {
+                        return self.matrix_row(name);
+                    }
+                    let status = if w.archived {
+                        "▪"
+                    } else if w.is_expired() {
+                        "⌛"
+                    } else if w.enabled {
+                        "✓"
+                    } else {
+                        "✗"
+                    };
+                    let keywords = w.keyword_summary();
+                    let mut text = format!(
+                        "{} [{}] {} | Keywords: {} | Every {}",
+                        status, i + 1, w.url, keywords, format_interval(w.check_interval)
                     );
+                    if let Some(name) = &w.name {
+                        text.push_str(&format!(" | {}", name));
+                    }
+                    if let Some(name) = &w.matrix {
+                        text = format!("  ↳ {} | Matrix {}", text, name);
+                    }
+                    if let Some(window) = &w.active_window {
+                        text.push_str(&format!(" | Active {}", window));
+                    }
+                    if let Some(expires_at) = w.expires_at {
+                        let label = if w.is_expired() { "Expired" } else { "Expires" };
+                        text.push_str(&format!(
+                            " | {} {}",
+                            label,
+                            format_expiry(expires_at, &self.config.timezone)
+                        ));
+                    }
+                    if w.disable_after_match {
+                        text.push_str(" | One-shot");
+                    }
+                    if w.consensus_fetches > 1 {
+                        text.push_str(&format!(" | Consensus x{}", w.consensus_fetches));
+                    }
+                    if w.lightweight {
+                        text.push_str(" | HEAD");
+                    }
+                    if w.critical {
+                        text.push_str(" | Critical");
+                    }
+                    if w.feed {
+                        text.push_str(" | Feed");
+                    }
+                    if w.is_stream() {
+                        text.push_str(" | Stream");
+                    }
+                    if let Some(profile) = w.request_profile {
+                        text.push_str(&format!(" | {}", profile));
+                    }
+                    if w.notify_cleared {
+                        text.push_str(" | Cleared alerts");
+                    }
+                    if w.pause_when_blocked {
+                        text.push_str(" | Pause when blocked");
+                    }
+                    if w.translate {
+                        text.push_str(" | Translated");
+                    }
+                    if w.track_value.is_some() {
+                        text.push_str(" | Tracks a value");
+                    }
+                    if let (true, Some(reason)) = (w.enabled, &w.blocked) {
+                        text.push_str(&format!(" | ⚠ Blocked: {}", reason));
+                    }
+                    if let (true, Some(reason)) = (w.enabled, &w.slow) {
+                        text.push_str(&format!(" | ⚠ Slow: {}", reason));
+                    }
+                    if w.tls.insecure {
+                        text.push_str(" | ⚠ TLS unverified");
+                    } else if w.tls.ca_cert.is_some() {
+                        text.push_str(" | Custom CA");
+                    }
+                    if let (false, Some(reason)) = (w.enabled, &w.disabled_reason) {
+                        text.push_str(&format!(" | Disabled: {}", reason));
+                    }
+                    if let Some(checked) = w.last_checked {
+                        text.push_str(&format!(
+                            " | Checked {}",
+                            self.config.timezone.format(checked, "%b %d %H:%M")
+                        ));
+                    }
+                    if w.moved_to.is_some() {
+                        text.push_str(" | ⚠ Moved");
+                    }
+                    if w.consecutive_errors > 0 {
+                        text.push_str(&format!(" | ⚠ {}", error_streak(w.consecutive_errors)));
+                        return ListItem::new(text).style(Style::default().fg(Color::Yellow));
+                    }
                     ListItem::new(text)
                 })
                 .collect();
@@ -289,75 +868,447 @@ impl UI {
         }
 
         // Help
-        let help = Paragraph::new("↑↓: Navigate | t: Toggle | e: Edit | d: Delete | a: Add | Esc: Back")
+        let help = Paragraph::new("↑↓: Navigate | Enter: Details | t: Toggle | T: Toggle all | o: One-shot | c: Consensus | h: HEAD mode | p: Critical | r: Cleared alerts | e: Edit | d: Delete | A: Archive/restore | Tab: Archived/active | m: Fold/unfold matrix | Ctrl+Z: Undo | a: Add | v: Add from clipboard | O: Open in browser | Esc: Back")
             .style(Style::default().fg(Color::Gray))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(help, chunks[2]);
     }
 
-    fn draw_edit_watcher(&mut self, f: &mut Frame, idx: usize) {
+    fn draw_watcher_detail(&mut self, f: &mut Frame, idx: usize) {
+        let Some(watcher) = self.config.watchers.get(idx) else {
+            return;
+        };
+        let zone = self.config.timezone;
+
+        // Room for the error and page-move lines when present
+        let extra_lines = u16::from(watcher.consecutive_errors > 0)
+            + u16::from(watcher.slow.is_some())
+            + u16::from(watcher.moved_to.is_some() || !self.detail_message.is_empty());
+
+        // Tracked values of the recent checks, oldest first
+        let tracked: Vec<f64> = self.detail_history.iter().rev().filter_map(|record| record.value).collect();
+
         let chunks = Layout::default()
             .direction(Direction::Vertical)
-            .margin(2)
             .constraints([
                 Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Length(3),
-                Constraint::Min(0),
+                Constraint::Length(4 + extra_lines),
+                Constraint::Length(if tracked.is_empty() { 0 } else { 5 }),
+                Constraint::Min(5),
+                Constraint::Length(9),
                 Constraint::Length(3),
             ])
             .split(f.size());
 
         // Title
-        let title = Paragraph::new(format!("Edit Watcher #{}", idx + 1))
+        let title = Paragraph::new(format!("Watcher #{}: {}", idx + 1, watcher.url))
             .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(title, chunks[0]);
 
-        // URL field
-        let url_style = if self.form_field == FormField::Url {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
+        // Summary
+        let status = match (watcher.enabled, &watcher.disabled_reason) {
+            _ if watcher.archived => String::from("Archived (not checked)"),
+            (true, _) => String::from("Enabled"),
+            (false, Some(reason)) => format!("Disabled ({})", reason),
+            (false, None) => String::from("Disabled"),
         };
-        let url = Paragraph::new(self.url_input.as_str())
-            .style(url_style)
-            .block(Block::default().title("URL").borders(Borders::ALL));
-        f.render_widget(url, chunks[1]);
+        let last_checked = watcher
+            .last_checked
+            .map(|t| zone.format(t, "%Y-%m-%d %H:%M:%S"))
+            .unwrap_or_else(|| String::from("never"));
+        let mut summary = vec![
+            Line::from(format!(
+                "Keywords: {}",
+                watcher
+                    .keywords
+                    .iter()
+                    .map(|k| k.describe())
+                    .chain(watcher.keyword_sets.iter().map(|name| {
+                        match self.config.keyword_sets.get(name) {
+                            Some(set) => format!("@{} ({} keywords)", name, set.len()),
+                            None => format!("@{} (undefined)", name),
+                        }
+                    }))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )),
+            Line::from(format!(
+                "Every {} | {} | Last checked: {}",
+                format_interval(watcher.check_interval),
+                status,
+                last_checked
+            )),
+        ];
+        if watcher.consecutive_errors > 0 {
+            summary.push(Line::styled(
+                format!(
+                    "⚠ {}: {}",
+                    error_streak(watcher.consecutive_errors),
+                    watcher.last_error.as_deref().unwrap_or("unknown error")
+                ),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        if let Some(reason) = &watcher.slow {
+            summary.push(Line::styled(format!("⚠ Slow: {}", reason), Style::default().fg(Color::Yellow)));
+        }
+        if !self.detail_message.is_empty() {
+            summary.push(Line::from(self.detail_message.clone()));
+        } else if let Some(moved_to) = &watcher.moved_to {
+            summary.push(Line::styled(
+                format!("⚠ Moved permanently to {} (u: Switch to it | x: Dismiss)", moved_to),
+                Style::default().fg(Color::Yellow),
+            ));
+        }
+        let summary = Paragraph::new(summary)
+            .wrap(Wrap { trim: true })
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(summary, chunks[1]);
+
+        // Chart of the tracked value
+        if let Some(&last) = tracked.last() {
+            let low = tracked.iter().copied().fold(f64::INFINITY, f64::min);
+            let high = tracked.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+            let title = format!(
+                "Value: {} (low {}, high {})",
+                values::format_value(last),
+                values::format_value(low),
+                values::format_value(high)
+            );
+            // The latest values, as many as fit
+            let width = usize::from(chunks[2].width.saturating_sub(2));
+            let points = values::sparkline(&tracked[tracked.len().saturating_sub(width)..]);
+            let sparkline = Sparkline::default()
+                .block(Block::default().title(title).borders(Borders::ALL))
+                .data(&points)
+                .max(100)
+                .style(Style::default().fg(Color::Cyan));
+            f.render_widget(sparkline, chunks[2]);
+        }
 
-        // Keywords field
-        let keywords_style = if self.form_field == FormField::Keywords {
-            Style::default().fg(Color::Yellow)
+        // Check history, newest first
+        if self.detail_history.is_empty() {
+            let empty = Paragraph::new("No checks recorded yet.")
+                .style(Style::default().fg(Color::Gray))
+                .alignment(Alignment::Center)
+                .block(Block::default().title("Recent Checks").borders(Borders::ALL));
+            f.render_widget(empty, chunks[3]);
         } else {
-            Style::default()
-        };
-        let keywords = Paragraph::new(self.keywords_input.as_str())
-            .style(keywords_style)
-            .block(Block::default().title("Keywords (comma-separated)").borders(Borders::ALL));
-        f.render_widget(keywords, chunks[2]);
+            let items: Vec<ListItem> = self
+                .detail_history
+                .iter()
+                .map(|record| {
+                    let http = match &record.http {
+                        Some(meta) => format!(
+                            "{} {:>6}ms {:>9}",
+                            meta.status,
+                            meta.response_time_ms,
+                            meta.content_length.map(format_bytes).unwrap_or_default()
+                        ),
+                        None => format!("{:<3} {:>8} {:>9}", "-", "", ""),
+                    };
+                    let color = match record.outcome {
+                        CheckOutcome::Matched { .. } => Color::Green,
+                        CheckOutcome::Error { .. } => Color::Red,
+                        CheckOutcome::Blocked { .. } => Color::Magenta,
+                        CheckOutcome::Changed => Color::Yellow,
+                        CheckOutcome::Unchanged => Color::Gray,
+                    };
+                    let value = record
+                        .value
+                        .map(|value| format!("  = {}", values::format_value(value)))
+                        .unwrap_or_default();
+                    ListItem::new(format!(
+                        "{}  {}  {}{}",
+                        zone.format(record.checked_at, "%m-%d %H:%M:%S"),
+                        http,
+                        record.outcome.label(),
+                        value
+                    ))
+                    .style(Style::default().fg(color))
+                })
+                .collect();
 
-        // Interval field
-        let interval_style = if self.form_field == FormField::Interval {
-            Style::default().fg(Color::Yellow)
-        } else {
-            Style::default()
+            let list = List::new(items)
+                .block(Block::default().title("Recent Checks").borders(Borders::ALL))
+                .highlight_style(Style::default().bg(Color::DarkGray))
+                .highlight_symbol(">> ");
+            f.render_stateful_widget(list, chunks[3], &mut self.detail_list_state);
+        }
+
+        // HTTP metadata of the selected check
+        let selected = self
+            .detail_list_state
+            .selected()
+            .and_then(|i| self.detail_history.get(i));
+        let mut lines: Vec<Line> = match selected.and_then(|r| r.http.as_ref()) {
+            Some(meta) => {
+                let mut lines = vec![Line::from(format!("Final URL: {}", meta.final_url))];
+                lines.extend(
+                    meta.headers
+                        .iter()
+                        .map(|(name, value)| Line::from(format!("{}: {}", name, value))),
+                );
+                lines
+            }
+            None if selected.is_some() => vec![Line::from("No HTTP response (request failed)")],
+            None => Vec::new(),
         };
-        let interval = Paragraph::new(self.interval_input.as_str())
-            .style(interval_style)
-            .block(Block::default().title("Check Interval (minutes)").borders(Borders::ALL));
-        f.render_widget(interval, chunks[3]);
+        // What the watcher's hook printed, first
+        if let Some(run) = selected.and_then(|r| r.hook.as_ref()) {
+            let color = if run.succeeded() { Color::Green } else { Color::Red };
+            let mut hook = vec![Line::from(Span::styled(format!("Hook: {}", run.summary()), Style::default().fg(color)))];
+            hook.extend(run.stdout.lines().map(|line| Line::from(line.to_string())));
+            hook.extend(
+                run.stderr
+                    .lines()
+                    .map(|line| Line::from(Span::styled(line.to_string(), Style::default().fg(Color::Red)))),
+            );
+            if run.truncated {
+                hook.push(Line::from("(output cut off)"));
+            }
+            hook.push(Line::from(""));
+            lines.splice(0..0, hook);
+        }
+        let response = Paragraph::new(lines)
+            .wrap(Wrap { trim: false })
+            .block(Block::default().title("Response").borders(Borders::ALL));
+        f.render_widget(response, chunks[4]);
 
         // Help
-        let help = Paragraph::new("Tab: Next field | Enter: Save | Esc: Cancel")
+        let help = Paragraph::new(
+            "↑↓: Select check | r: Refresh | e: Edit | o: Open in browser | c: Copy match | d: Copy diff | m: Match heatmap | H: Clear history | Ctrl+Z: Undo | Esc: Back",
+        )
             .style(Style::default().fg(Color::Gray))
             .alignment(Alignment::Center)
             .block(Block::default().borders(Borders::ALL));
         f.render_widget(help, chunks[5]);
     }
 
-    fn handle_input(&mut self, key: KeyCode) -> Result<bool> {
+    /// Matches of the last days as a calendar, and by weekday and hour, to
+    /// show patterns like a shop restocking on Tuesday mornings
+    fn draw_heatmap(&mut self, f: &mut Frame, idx: usize) {
+        let Some(watcher) = self.config.watchers.get(idx) else {
+            return;
+        };
+        let zone = self.config.timezone;
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(9),
+                Constraint::Length(10),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ])
+            .split(f.size());
+
+        let title = Paragraph::new(format!("Matches of {} in the last {} days", watcher.url, HEATMAP_DAYS))
+            .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        // Matches per day and per weekday and hour, in the display zone
+        let times: Vec<chrono::NaiveDateTime> = self.heatmap_matches.iter().map(|t| zone.wall_clock(*t)).collect();
+        let mut by_day: BTreeMap<chrono::NaiveDate, usize> = BTreeMap::new();
+        let mut by_hour = [[0usize; 24]; 7];
+        for time in &times {
+            *by_day.entry(time.date()).or_default() += 1;
+            by_hour[time.weekday().num_days_from_monday() as usize][time.hour() as usize] += 1;
+        }
+
+        // Calendar: a row per week, from the Monday before the first day
+        let today = zone.now().date();
+        let first = today - chrono::Duration::days(HEATMAP_DAYS - 1);
+        let mut day = first - chrono::Duration::days(i64::from(first.weekday().num_days_from_monday()));
+        let day_max = by_day.values().copied().max().unwrap_or(0);
+        let mut calendar = vec![Line::from(Span::styled(
+            "  Mon   Tue   Wed   Thu   Fri   Sat   Sun",
+            Style::default().fg(Color::Gray),
+        ))];
+        while day <= today {
+            let mut week = Vec::new();
+            for _ in 0..7 {
+                if day < first || day > today {
+                    week.push(Span::raw("      "));
+                } else {
+                    let count = by_day.get(&day).copied().unwrap_or(0);
+                    let (cell, color) = heat(count, day_max);
+                    week.push(Span::raw(format!("  {:>2}", day.day())));
+                    week.push(Span::styled(format!("{} ", cell), Style::default().fg(color)));
+                }
+                day += chrono::Duration::days(1);
+            }
+            calendar.push(Line::from(week));
+        }
+        let calendar = Paragraph::new(calendar).block(Block::default().title("By day").borders(Borders::ALL));
+        f.render_widget(calendar, chunks[1]);
+
+        // Weekday x hour grid
+        let hour_max = by_hour.iter().flatten().copied().max().unwrap_or(0);
+        let mut grid = vec![Line::from(Span::styled(
+            format!("     {}", (0..24).step_by(3).map(|h| format!("{:<6}", format!("{:02}", h))).collect::<String>()),
+            Style::default().fg(Color::Gray),
+        ))];
+        for (weekday, hours) in WEEKDAYS.iter().zip(by_hour.iter()) {
+            let mut row = vec![Span::styled(format!("{}  ", weekday), Style::default().fg(Color::Gray))];
+            for count in hours {
+                let (cell, color) = heat(*count, hour_max);
+                row.push(Span::styled(format!("{}{}", cell, cell), Style::default().fg(color)));
+            }
+            grid.push(Line::from(row));
+        }
+        let grid = Paragraph::new(grid).block(Block::default().title("By weekday and hour").borders(Borders::ALL));
+        f.render_widget(grid, chunks[2]);
+
+        // The busiest weekday and hour
+        let summary = if times.is_empty() {
+            vec![Line::from(format!("No matches in the last {} days", HEATMAP_DAYS))]
+        } else {
+            let weekday_totals: Vec<usize> = by_hour.iter().map(|hours| hours.iter().sum()).collect();
+            let hour_totals: Vec<usize> = (0..24).map(|h| by_hour.iter().map(|hours| hours[h]).sum()).collect();
+            let busiest_day = (0..7).max_by_key(|d| (weekday_totals[*d], std::cmp::Reverse(*d))).unwrap_or(0);
+            let busiest_hour = (0..24).max_by_key(|h| (hour_totals[*h], std::cmp::Reverse(*h))).unwrap_or(0);
+            vec![
+                Line::from(format!("{} matched checks", times.len())),
+                Line::from(format!(
+                    "Most on {}s ({}), and between {:02}:00 and {:02}:00 ({})",
+                    WEEKDAY_NAMES[busiest_day],
+                    weekday_totals[busiest_day],
+                    busiest_hour,
+                    (busiest_hour + 1) % 24,
+                    hour_totals[busiest_hour]
+                )),
+            ]
+        };
+        let summary = Paragraph::new(summary).block(Block::default().title("Summary").borders(Borders::ALL));
+        f.render_widget(summary, chunks[3]);
+
+        let help = Paragraph::new("Esc: Back")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(help, chunks[4]);
+    }
+
+    fn draw_edit_watcher(&mut self, f: &mut Frame, idx: usize) {
+        self.draw_watcher_form(
+            f,
+            &format!("Edit Watcher #{}", idx + 1),
+            "Tab: Next field | F3: Interval preset | F4: Paste URL | Enter: Save | Esc: Cancel",
+        );
+    }
+
+    fn handle_input(&mut self, key: KeyEvent) -> Result<bool> {
+        // While monitoring, work on the monitor's config: start from its
+        // latest state and hand the result back, so edits apply live
+        let Some(live) = self.monitor_config.clone() else {
+            return self.handle_key_event(key);
+        };
+        live.edit(|config| {
+            self.config = config.clone();
+            let result = self.handle_key_event(key);
+            *config = self.config.clone();
+            result
+        })
+    }
+
+    fn handle_key_event(&mut self, key: KeyEvent) -> Result<bool> {
+        // Ctrl+Z takes back the last deletion, toggle or cleared history
+        // (forms and the wizard have nothing to take back)
+        let in_form = matches!(self.screen, Screen::AddWatcher | Screen::EditWatcher(_) | Screen::Wizard(_));
+        if key.code == KeyCode::Char('z') && key.modifiers.contains(KeyModifiers::CONTROL) && !in_form {
+            let message = self.undo_last().unwrap_or_else(|e| format!("✗ Undo failed: {:#}", e));
+            match self.screen {
+                Screen::ListWatchers => self.list_message = message,
+                Screen::WatcherDetail(_) => self.detail_message = message,
+                _ => self.menu_message = message,
+            }
+            return Ok(false);
+        }
+        self.handle_key(key.code)
+    }
+
+    /// Take back the most recent change on the undo stack
+    fn undo_last(&mut self) -> Result<String> {
+        let Some(undo) = self.undo.pop() else {
+            return Ok(String::from("Nothing to undo"));
+        };
+        let message = match undo {
+            Undo::Deleted { watcher_id, url, index } => {
+                self.config.ensure_writable()?;
+                let watcher = trash::restore(&watcher_id)?;
+                let index = index.min(self.config.watchers.len());
+                self.config.watchers.insert(index, watcher);
+                self.config.save()?;
+                format!("Restored {}", url)
+            }
+            Undo::Enabled(previous) => {
+                self.config.ensure_writable()?;
+                for (id, enabled) in &previous {
+                    if let Some(watcher) = self.config.watchers.iter_mut().find(|w| &w.id == id) {
+                        watcher.enabled = *enabled;
+                    }
+                }
+                self.config.save()?;
+                format!("Switched {} watcher(s) back", previous.len())
+            }
+            Undo::Archived { watcher_id } => {
+                self.config.ensure_writable()?;
+                let watcher = self
+                    .config
+                    .watchers
+                    .iter_mut()
+                    .find(|w| w.id == watcher_id)
+                    .ok_or_else(|| anyhow::anyhow!("The watcher no longer exists"))?;
+                watcher.archived = !watcher.archived;
+                let message = if watcher.archived {
+                    format!("Archived {} again", watcher.url)
+                } else {
+                    format!("Restored {}", watcher.url)
+                };
+                self.config.save()?;
+                message
+            }
+            Undo::HistoryCleared { watcher_id, content } => {
+                history::restore(&watcher_id, &content, &self.config.history)?;
+                if let Screen::WatcherDetail(idx) = self.screen {
+                    self.load_detail_history(idx);
+                }
+                String::from("History restored")
+            }
+        };
+        self.clamp_list_selection();
+        Ok(message)
+    }
+
+    /// Remember a change for Ctrl+Z
+    fn push_undo(&mut self, undo: Undo) {
+        if self.undo.len() == UNDO_LEN {
+            self.undo.remove(0);
+        }
+        self.undo.push(undo);
+    }
+
+    fn handle_key(&mut self, key: KeyCode) -> Result<bool> {
+        // Refuse changes up front rather than failing halfway through
+        if self.config.is_read_only() && self.modifies_config(key) {
+            let message = match self.config.ensure_writable() {
+                Err(e) => format!("✗ {:#}", e),
+                Ok(()) => String::new(),
+            };
+            match self.screen {
+                Screen::MainMenu => self.menu_message = message,
+                Screen::ListWatchers => self.list_message = message,
+                Screen::WatcherDetail(_) => self.detail_message = message,
+                Screen::ReviewChange(_) => self.review_message = message,
+                _ => {}
+            }
+            return Ok(false);
+        }
+
         match &self.screen {
             Screen::MainMenu => self.handle_main_menu_input(key),
             Screen::AddWatcher => self.handle_add_watcher_input(key),
@@ -366,11 +1317,51 @@ impl UI {
                 let idx = *idx; // Copy the index
                 self.handle_edit_watcher_input(key, idx)
             }
+            Screen::WatcherDetail(idx) => {
+                let idx = *idx; // Copy the index
+                self.handle_watcher_detail_input(key, idx)
+            }
+            Screen::Heatmap(idx) => {
+                if key == KeyCode::Esc {
+                    self.screen = Screen::WatcherDetail(*idx);
+                }
+                Ok(false)
+            }
             Screen::ServiceControl => self.handle_service_control_input(key),
+            Screen::Monitoring => self.handle_monitoring_input(key),
+            Screen::Profiles => self.handle_profiles_input(key),
+            Screen::Review => self.handle_review_input(key),
+            Screen::ReviewChange(idx) => {
+                let idx = *idx; // Copy the index
+                self.handle_review_change_input(key, idx)
+            }
+            Screen::Wizard(step) => {
+                let step = *step; // Copy the step
+                self.handle_wizard_input(key, step)
+            }
+        }
+    }
+
+    /// Whether a key on the current screen would change the config
+    fn modifies_config(&self, key: KeyCode) -> bool {
+        match self.screen {
+            Screen::MainMenu => {
+                key == KeyCode::Char('1')
+                    || (matches!(key, KeyCode::Enter | KeyCode::Char(' '))
+                        && self.menu_state.selected() == Some(0))
+            }
+            Screen::ListWatchers => matches!(
+                key,
+                KeyCode::Char('t' | 'T' | 'o' | 'p' | 'r' | 'h' | 'c' | 'd' | 'a' | 'v' | 'e' | 'A')
+            ),
+            Screen::WatcherDetail(_) => matches!(key, KeyCode::Char('e' | 'u' | 'x')),
+            Screen::ReviewChange(_) => key == KeyCode::Char('a') && self.review_keyword.is_none(),
+            _ => false,
         }
     }
 
     fn handle_main_menu_input(&mut self, key: KeyCode) -> Result<bool> {
+        self.menu_message.clear();
         match key {
             KeyCode::Char('q') | KeyCode::Esc => return Ok(true),
             KeyCode::Down | KeyCode::Char('j') => {
@@ -397,15 +1388,9 @@ impl UI {
                 match self.menu_state.selected() {
                     Some(0) => self.screen = Screen::AddWatcher,
                     Some(1) => {
-                        self.screen = Screen::ListWatchers;
-                        if !self.config.watchers.is_empty() {
-                            self.watcher_list_state.select(Some(0));
-                        }
-                    }
-                    Some(2) => {
-                        // Start monitoring - exit TUI and run monitor
-                        return self.start_monitoring();
+                        self.open_watcher_list();
                     }
+                    Some(2) => self.start_monitoring()?,
                     Some(3) => {
                         // Service Control
                         self.check_service_status();
@@ -417,268 +1402,1494 @@ impl UI {
             }
             KeyCode::Char('1') => self.screen = Screen::AddWatcher,
             KeyCode::Char('2') => {
-                self.screen = Screen::ListWatchers;
-                if !self.config.watchers.is_empty() {
-                    self.watcher_list_state.select(Some(0));
-                }
+                self.open_watcher_list();
             }
-            KeyCode::Char('3') => return self.start_monitoring(),
+            KeyCode::Char('3') => self.start_monitoring()?,
             KeyCode::Char('4') => {
                 self.check_service_status();
                 self.screen = Screen::ServiceControl;
             }
             KeyCode::Char('5') => return Ok(true),
+            KeyCode::Char('p') => self.open_profiles(),
+            KeyCode::Char('r') => self.open_review(),
             _ => {}
         }
         Ok(false)
     }
 
-    fn handle_add_watcher_input(&mut self, key: KeyCode) -> Result<bool> {
-        match key {
-            KeyCode::Esc => {
-                self.screen = Screen::MainMenu;
-                self.clear_form();
+    /// Show the profile selector with the active profile selected
+    fn open_profiles(&mut self) {
+        self.profile_names = match profile::list() {
+            Ok(names) => names,
+            Err(e) => {
+                self.profile_message = format!("✗ {:#}", e);
+                vec![profile::active_name()]
             }
-            KeyCode::Tab => {
-                self.form_field = match self.form_field {
-                    FormField::Url => FormField::Keywords,
-                    FormField::Keywords => FormField::Interval,
-                    FormField::Interval => FormField::Url,
-                };
+        };
+        let active = profile::active_name();
+        let selected = self.profile_names.iter().position(|name| *name == active);
+        self.profile_state.select(selected.or(Some(0)));
+        self.profile_input = None;
+        self.screen = Screen::Profiles;
+    }
+
+    fn draw_profiles(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Min(0),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ])
+            .split(f.size());
+
+        let active = profile::active_name();
+        let items: Vec<ListItem> = self
+            .profile_names
+            .iter()
+            .map(|name| {
+                if *name == active {
+                    ListItem::new(format!("{} (active)", name)).style(Style::default().fg(Color::Green))
+                } else {
+                    ListItem::new(name.as_str())
+                }
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().title("Profiles").borders(Borders::ALL))
+            .highlight_style(
+                Style::default()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[0], &mut self.profile_state);
+
+        // New profile name, or the result of the last switch
+        let (text, style) = match &self.profile_input {
+            Some(input) => (input.clone(), Style::default().fg(Color::Yellow)),
+            None if self.profile_message.starts_with('✗') => {
+                (self.profile_message.clone(), Style::default().fg(Color::Red))
+            }
+            None => (
+                String::from("Each profile has its own watchers, notification settings, cache and history"),
+                Style::default().fg(Color::Gray),
+            ),
+        };
+        let title = if self.profile_input.is_some() { "New Profile Name" } else { "" };
+        let input = Paragraph::new(text)
+            .style(style)
+            .block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(input, chunks[1]);
+
+        let help = if self.profile_input.is_some() {
+            "Enter: Create and switch | Esc: Cancel"
+        } else {
+            "↑↓: Navigate | Enter: Switch | n: New profile | Esc: Back"
+        };
+        let help = Paragraph::new(help)
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(help, chunks[2]);
+    }
+
+    fn handle_profiles_input(&mut self, key: KeyCode) -> Result<bool> {
+        // Typing a new profile name
+        if let Some(input) = &mut self.profile_input {
+            match key {
+                KeyCode::Esc => self.profile_input = None,
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let name = input.trim().to_string();
+                    self.profile_input = None;
+                    self.switch_profile(&name);
+                }
+                _ => {}
+            }
+            return Ok(false);
+        }
+
+        match key {
+            KeyCode::Esc => {
+                self.profile_message.clear();
+                self.screen = Screen::MainMenu;
+            }
+            KeyCode::Down | KeyCode::Char('j') => {
+                let len = self.profile_names.len().max(1);
+                let i = self.profile_state.selected().map_or(0, |i| (i + 1) % len);
+                self.profile_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') => {
+                let len = self.profile_names.len().max(1);
+                let i = self.profile_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.profile_state.select(Some(i));
+            }
+            KeyCode::Char('n') => {
+                self.profile_message.clear();
+                self.profile_input = Some(String::new());
+            }
+            KeyCode::Enter => {
+                if let Some(name) = self
+                    .profile_state
+                    .selected()
+                    .and_then(|i| self.profile_names.get(i))
+                    .cloned()
+                {
+                    self.switch_profile(&name);
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Switch to a profile and load its config, staying on the current one
+    /// if that fails
+    fn switch_profile(&mut self, name: &str) {
+        let previous = profile::active_name();
+        let config = profile::set_active(name).and_then(|_| Config::load());
+        match config {
+            Ok(config) => {
+                // The monitor runs the previous profile's watchers
+                self.stop_monitoring();
+                self.config = config;
+                self.watcher_list_state = ListState::default();
+                self.profile_message.clear();
+                self.screen = Screen::MainMenu;
+            }
+            Err(e) => {
+                let _ = profile::set_active(&previous);
+                self.profile_message = format!("✗ Can't switch to '{}': {:#}", name, e);
+            }
+        }
+    }
+
+    /// Show the review queue, newest change first
+    fn open_review(&mut self) {
+        self.load_review();
+        self.review_message.clear();
+        self.screen = Screen::Review;
+    }
+
+    /// Reload the review queue, keeping the selection in range
+    fn load_review(&mut self) {
+        self.review_items = match review::load() {
+            Ok(items) => items,
+            Err(e) => {
+                self.review_message = format!("✗ {:#}", e);
+                Vec::new()
+            }
+        };
+        self.review_items.reverse();
+        let selected = match self.review_items.len() {
+            0 => None,
+            len => Some(self.review_state.selected().unwrap_or(0).min(len - 1)),
+        };
+        self.review_state.select(selected);
+    }
+
+    /// Index of the watcher a queued change is about (None if it's gone)
+    fn review_watcher(&self, item: &review::Item) -> Option<usize> {
+        self.config.watchers.iter().position(|w| w.id == item.watcher_id)
+    }
+
+    fn draw_review(&mut self, f: &mut Frame) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(0), Constraint::Length(3)])
+            .split(f.size());
+
+        let zone = self.config.timezone;
+        let items: Vec<ListItem> = self
+            .review_items
+            .iter()
+            .map(|item| {
+                let mut line = format!(
+                    "{}  {}  +{} -{}",
+                    zone.format(item.detected_at, "%b %d %H:%M"),
+                    item.url,
+                    item.added.len(),
+                    item.removed.len()
+                );
+                if self.review_watcher(item).is_none() {
+                    line.push_str("  (watcher deleted)");
+                }
+                ListItem::new(line)
+            })
+            .collect();
+        let title = match self.review_items.len() {
+            0 => String::from("Changes to Review (none: changes without keyword matches land here)"),
+            len => format!("Changes to Review ({})", len),
+        };
+        let list = List::new(items)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .highlight_style(
+                Style::default()
+                    .bg(Color::DarkGray)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[0], &mut self.review_state);
+
+        let (help, color) = if self.review_message.is_empty() {
+            (
+                "↑↓: Navigate | Enter: View | d: Dismiss | D: Dismiss all of this page | Esc: Back",
+                Color::Gray,
+            )
+        } else if self.review_message.starts_with('✗') {
+            (self.review_message.as_str(), Color::Red)
+        } else {
+            (self.review_message.as_str(), Color::Green)
+        };
+        let help = Paragraph::new(help)
+            .style(Style::default().fg(color))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(help, chunks[1]);
+    }
+
+    fn handle_review_input(&mut self, key: KeyCode) -> Result<bool> {
+        self.review_message.clear();
+        let len = self.review_items.len();
+        match key {
+            KeyCode::Esc => self.screen = Screen::MainMenu,
+            KeyCode::Down | KeyCode::Char('j') if len > 0 => {
+                let i = self.review_state.selected().map_or(0, |i| (i + 1) % len);
+                self.review_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if len > 0 => {
+                let i = self.review_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.review_state.select(Some(i));
             }
             KeyCode::Enter => {
-                // Save watcher
-                if !self.url_input.is_empty() && !self.keywords_input.is_empty() {
-                    let interval_mins: u64 = self.interval_input.parse().unwrap_or(30);
-                    let interval = Duration::from_secs(interval_mins * 60);
-
-                    let keywords: Vec<String> = self.keywords_input
-                        .split(',')
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
+                if let Some(i) = self.review_state.selected().filter(|i| *i < len) {
+                    self.review_line_state.select(Some(0));
+                    self.review_keyword = None;
+                    self.screen = Screen::ReviewChange(i);
+                }
+            }
+            KeyCode::Char('d') => {
+                if let Some(item) = self.review_state.selected().and_then(|i| self.review_items.get(i)) {
+                    self.dismiss_review(vec![item.id.clone()]);
+                }
+            }
+            KeyCode::Char('D') => {
+                if let Some(item) = self.review_state.selected().and_then(|i| self.review_items.get(i)) {
+                    let ids = self
+                        .review_items
+                        .iter()
+                        .filter(|other| other.watcher_id == item.watcher_id)
+                        .map(|other| other.id.clone())
                         .collect();
+                    self.dismiss_review(ids);
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
 
-                    let watcher = Watcher::new(
-                        self.url_input.clone(),
-                        keywords,
-                        interval,
-                    );
+    /// Remove changes from the queue and show what's left
+    fn dismiss_review(&mut self, ids: Vec<String>) {
+        match review::dismiss(&ids, &self.config.encryption) {
+            Ok(count) => self.review_message = format!("✓ Dismissed {} change(s)", count),
+            Err(e) => self.review_message = format!("✗ {:#}", e),
+        }
+        let message = std::mem::take(&mut self.review_message);
+        self.load_review();
+        if self.review_message.is_empty() {
+            self.review_message = message;
+        }
+    }
 
-                    self.config.watchers.push(watcher);
-                    self.config.save()?;
+    /// The lines of a change: removed ones first, like a diff
+    fn review_lines(item: &review::Item) -> Vec<(bool, &str)> {
+        item.removed
+            .iter()
+            .map(|line| (true, line.as_str()))
+            .chain(item.added.iter().map(|line| (false, line.as_str())))
+            .collect()
+    }
 
-                    self.screen = Screen::MainMenu;
-                    self.clear_form();
+    fn draw_review_change(&mut self, f: &mut Frame, idx: usize) {
+        let Some(item) = self.review_items.get(idx) else {
+            return;
+        };
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+                Constraint::Length(3),
+            ])
+            .split(f.size());
+
+        let title = Paragraph::new(format!(
+            "{} | {}",
+            item.url,
+            self.config.timezone.format(item.detected_at, "%Y-%m-%d %H:%M")
+        ))
+        .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+        .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        // Added lines other changes of the page added too are the likeliest
+        // keywords
+        let recurring = review::recurring(&self.review_items, &item.watcher_id);
+        let lines: Vec<ListItem> = Self::review_lines(item)
+            .into_iter()
+            .map(|(removed, line)| {
+                let (sign, color) = if removed { ("-", Color::Red) } else { ("+", Color::Green) };
+                let mut spans = vec![Span::styled(format!("{} {}", sign, line), Style::default().fg(color))];
+                if let Some(count) = recurring.get(line).filter(|_| !removed) {
+                    spans.push(Span::styled(
+                        format!("  (in {} changes)", count),
+                        Style::default().fg(Color::Yellow),
+                    ));
                 }
+                ListItem::new(Line::from(spans))
+            })
+            .collect();
+        let list = List::new(lines)
+            .block(Block::default().title("Changes").borders(Borders::ALL))
+            .highlight_style(Style::default().bg(Color::DarkGray))
+            .highlight_symbol(">> ");
+        f.render_stateful_widget(list, chunks[1], &mut self.review_line_state);
+
+        // Keyword being made, or the result of the last action
+        let (text, style) = match &self.review_keyword {
+            Some(input) => (input.clone(), Style::default().fg(Color::Yellow)),
+            None if self.review_message.starts_with('✗') => {
+                (self.review_message.clone(), Style::default().fg(Color::Red))
             }
-            KeyCode::Backspace => {
-                match self.form_field {
-                    FormField::Url => {
-                        self.url_input.pop();
-                    }
-                    FormField::Keywords => {
-                        self.keywords_input.pop();
+            None => (
+                String::from("Turn a line worth an alert into a keyword of the watcher, or dismiss the change"),
+                Style::default().fg(Color::Gray),
+            ),
+        };
+        let input_title = if self.review_keyword.is_some() { "New Keyword" } else { "" };
+        let input = Paragraph::new(text)
+            .style(style)
+            .block(Block::default().title(input_title).borders(Borders::ALL));
+        f.render_widget(input, chunks[2]);
+
+        let help = if self.review_keyword.is_some() {
+            "Enter: Add the keyword and dismiss the change | Esc: Cancel"
+        } else {
+            "↑↓: Select line | a: Make it a keyword | d: Dismiss | Esc: Back"
+        };
+        let help = Paragraph::new(help)
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(help, chunks[3]);
+    }
+
+    fn handle_review_change_input(&mut self, key: KeyCode, idx: usize) -> Result<bool> {
+        let Some(item) = self.review_items.get(idx).cloned() else {
+            self.screen = Screen::Review;
+            return Ok(false);
+        };
+
+        // Typing the keyword
+        if let Some(input) = &mut self.review_keyword {
+            match key {
+                KeyCode::Esc => self.review_keyword = None,
+                KeyCode::Char(c) => input.push(c),
+                KeyCode::Backspace => {
+                    input.pop();
+                }
+                KeyCode::Enter => {
+                    let keyword = input.trim().to_string();
+                    if keyword.is_empty() {
+                        return Ok(false);
                     }
-                    FormField::Interval => {
-                        self.interval_input.pop();
+                    self.review_keyword = None;
+                    let Some(watcher_idx) = self.review_watcher(&item) else {
+                        self.review_message = String::from("✗ The watcher was deleted");
+                        return Ok(false);
+                    };
+                    let added = self.config.watchers[watcher_idx].merge_keywords(&[Keyword::new(keyword.clone())]);
+                    if added > 0 {
+                        self.config.save()?;
                     }
+                    self.dismiss_review(vec![item.id]);
+                    self.review_message = if added > 0 {
+                        format!("✓ Added keyword \"{}\" to {}", keyword, item.url)
+                    } else {
+                        format!("✓ {} already has the keyword \"{}\"", item.url, keyword)
+                    };
+                    self.screen = Screen::Review;
                 }
+                _ => {}
             }
-            KeyCode::Char(c) => {
-                match self.form_field {
-                    FormField::Url => self.url_input.push(c),
-                    FormField::Keywords => self.keywords_input.push(c),
-                    FormField::Interval => {
-                        if c.is_ascii_digit() {
-                            self.interval_input.push(c);
-                        }
-                    }
+            return Ok(false);
+        }
+
+        self.review_message.clear();
+        let len = Self::review_lines(&item).len();
+        match key {
+            KeyCode::Esc => self.screen = Screen::Review,
+            KeyCode::Down | KeyCode::Char('j') if len > 0 => {
+                let i = self.review_line_state.selected().map_or(0, |i| (i + 1) % len);
+                self.review_line_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if len > 0 => {
+                let i = self.review_line_state.selected().map_or(0, |i| (i + len - 1) % len);
+                self.review_line_state.select(Some(i));
+            }
+            KeyCode::Char('a') => {
+                if self.review_watcher(&item).is_none() {
+                    self.review_message = String::from("✗ The watcher was deleted");
+                } else if let Some((_, line)) = self
+                    .review_line_state
+                    .selected()
+                    .and_then(|i| Self::review_lines(&item).get(i).copied())
+                {
+                    self.review_keyword = Some(line.trim_end_matches('…').trim().to_string());
                 }
             }
+            KeyCode::Char('d') => {
+                self.dismiss_review(vec![item.id]);
+                self.screen = Screen::Review;
+            }
             _ => {}
         }
         Ok(false)
     }
 
-    fn handle_list_watchers_input(&mut self, key: KeyCode) -> Result<bool> {
+    fn handle_add_watcher_input(&mut self, key: KeyCode) -> Result<bool> {
+        // A duplicate URL was detected: 'm' merges, anything else cancels
+        if let Some(index) = self.duplicate_of.take() {
+            if key == KeyCode::Char('m') {
+                let keywords = std::mem::take(&mut self.pending_keywords);
+                let keyword_sets = std::mem::take(&mut self.pending_keyword_sets);
+                if let Some(existing) = self.config.watchers.get_mut(index) {
+                    existing.merge_keywords(&keywords);
+                    existing.merge_keyword_sets(&keyword_sets);
+                }
+                self.config.save()?;
+
+                self.screen = Screen::MainMenu;
+                self.clear_form();
+            } else {
+                self.pending_keywords.clear();
+        self.pending_keyword_sets.clear();
+                self.pending_keyword_sets.clear();
+                self.form_error.clear();
+            }
+            return Ok(false);
+        }
+
+        // Any edit cancels a pending short-interval confirmation
+        if key != KeyCode::Enter {
+            self.interval_override = false;
+        }
+
         match key {
             KeyCode::Esc => {
                 self.screen = Screen::MainMenu;
+                self.clear_form();
             }
-            KeyCode::Down | KeyCode::Char('j') => {
-                if !self.config.watchers.is_empty() {
-                    let i = match self.watcher_list_state.selected() {
-                        Some(i) => (i + 1) % self.config.watchers.len(),
-                        None => 0,
-                    };
-                    self.watcher_list_state.select(Some(i));
+            KeyCode::F(2) => {
+                // Cycle through templates and pre-fill the form
+                self.apply_next_template();
+            }
+            KeyCode::Enter if !self.url_input.is_empty() && !self.keywords_input.is_empty() => {
+                // Save watcher (validation errors are shown in the form)
+                let FormValues { url, keywords, keyword_sets, interval, active_window, expires_at } = match self.parse_form() {
+                    Ok(values) => values,
+                    Err(e) => {
+                        self.form_error = e.to_string();
+                        return Ok(false);
+                    }
+                };
+
+                // Offer to merge instead of creating a duplicate watcher
+                if let Some(index) = self.config.find_watcher_by_url(&url) {
+                    self.form_error = format!(
+                        "A watcher for {} already exists (#{}).\n\
+                        Press 'm' to merge these keywords into it, or any other key to cancel.",
+                        url,
+                        index + 1
+                    );
+                    self.duplicate_of = Some(index);
+                    self.pending_keywords = keywords.into_iter().map(Keyword::new).collect();
+                    self.pending_keyword_sets = keyword_sets;
+                    return Ok(false);
                 }
+
+                let allow_short_interval = match self.confirm_interval(&url, interval) {
+                    Some(allow) => allow,
+                    None => return Ok(false),
+                };
+
+                let keywords = keywords.into_iter().map(Keyword::new).collect();
+                let mut watcher = Watcher::new(url, keywords, interval);
+                watcher.keyword_sets = keyword_sets;
+                watcher.allow_short_interval = allow_short_interval;
+                watcher.active_window = active_window;
+                watcher.expires_at = expires_at;
+                if let Some(template) = self
+                    .template_index
+                    .and_then(|i| templates::all_templates(&self.config).into_iter().nth(i))
+                {
+                    template.apply(&mut watcher);
+                }
+
+                self.config.watchers.push(watcher);
+                self.config.save()?;
+
+                self.screen = Screen::MainMenu;
+                self.clear_form();
             }
-            KeyCode::Up | KeyCode::Char('k') => {
-                if !self.config.watchers.is_empty() {
-                    let i = match self.watcher_list_state.selected() {
-                        Some(i) => {
-                            if i == 0 {
-                                self.config.watchers.len() - 1
-                            } else {
-                                i - 1
-                            }
+            // Tab, F3, typing and backspace
+            _ => self.handle_form_typing(key),
+        }
+        Ok(false)
+    }
+
+    fn handle_list_watchers_input(&mut self, key: KeyCode) -> Result<bool> {
+        self.list_message.clear();
+        let folded = self
+            .selected_watcher()
+            .and_then(|i| self.folded_matrix(i))
+            .map(str::to_string);
+        if let Some(name) = folded {
+            if self.handle_folded_matrix_key(&name, key)? {
+                return Ok(false);
+            }
+        }
+        match key {
+            KeyCode::Esc => {
+                self.screen = Screen::MainMenu;
+            }
+            KeyCode::Down | KeyCode::Char('j') if !self.listed_watchers().is_empty() => {
+                let i = match self.watcher_list_state.selected() {
+                    Some(i) => (i + 1) % self.listed_watchers().len(),
+                    None => 0,
+                };
+                self.watcher_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.listed_watchers().is_empty() => {
+                let i = match self.watcher_list_state.selected() {
+                    Some(i) => {
+                        if i == 0 {
+                            self.listed_watchers().len() - 1
+                        } else {
+                            i - 1
                         }
-                        None => 0,
-                    };
-                    self.watcher_list_state.select(Some(i));
-                }
+                    }
+                    None => 0,
+                };
+                self.watcher_list_state.select(Some(i));
             }
             KeyCode::Char('t') => {
                 // Toggle enabled/disabled
-                if let Some(i) = self.watcher_list_state.selected() {
+                if let Some(i) = self.selected_watcher() {
+                    if i < self.config.watchers.len() {
+                        let watcher = &mut self.config.watchers[i];
+                        let undo = Undo::Enabled(vec![(watcher.id.clone(), watcher.enabled)]);
+                        watcher.enabled = !watcher.enabled;
+                        if watcher.enabled {
+                            // Start over, or it would be auto-disabled again
+                            // on the next failure
+                            watcher.disabled_reason = None;
+                            watcher.blocked = None;
+                            watcher.permanent_failures = 0;
+                            watcher.permanent_failure_since = None;
+                        }
+                        self.config.save()?;
+                        self.push_undo(undo);
+                    }
+                }
+            }
+            KeyCode::Char('T') => {
+                // Disable every listed watcher, those of folded matrices
+                // included (or enable them all when none is enabled)
+                let listed: Vec<usize> = (0..self.config.watchers.len())
+                    .filter(|&i| self.config.watchers[i].archived == self.show_archived)
+                    .collect();
+                let enable = !listed.iter().any(|&i| self.config.watchers[i].enabled);
+                let mut previous = Vec::new();
+                for i in listed {
+                    let watcher = &mut self.config.watchers[i];
+                    if watcher.enabled != enable {
+                        previous.push((watcher.id.clone(), watcher.enabled));
+                        watcher.enabled = enable;
+                    }
+                }
+                if !previous.is_empty() {
+                    self.config.save()?;
+                    let action = if enable { "Enabled" } else { "Disabled" };
+                    self.list_message = format!("{} {} watcher(s) (Ctrl+Z to undo)", action, previous.len());
+                    self.push_undo(Undo::Enabled(previous));
+                }
+            }
+            KeyCode::Char('o') => {
+                // Toggle one-shot (disable after first match)
+                if let Some(i) = self.selected_watcher() {
+                    if i < self.config.watchers.len() {
+                        let watcher = &mut self.config.watchers[i];
+                        watcher.disable_after_match = !watcher.disable_after_match;
+                        self.config.save()?;
+                    }
+                }
+            }
+            KeyCode::Char('p') => {
+                // Toggle critical (exempt from battery saving)
+                if let Some(i) = self.selected_watcher() {
+                    if i < self.config.watchers.len() {
+                        let watcher = &mut self.config.watchers[i];
+                        watcher.critical = !watcher.critical;
+                        self.config.save()?;
+                    }
+                }
+            }
+            KeyCode::Char('r') => {
+                // Toggle notifications when matched keywords disappear
+                if let Some(i) = self.selected_watcher() {
                     if i < self.config.watchers.len() {
-                        self.config.watchers[i].enabled = !self.config.watchers[i].enabled;
+                        let watcher = &mut self.config.watchers[i];
+                        watcher.notify_cleared = !watcher.notify_cleared;
+                        self.config.save()?;
+                    }
+                }
+            }
+            KeyCode::Char('h') => {
+                // Toggle lightweight (HEAD-first) checks
+                if let Some(i) = self.selected_watcher() {
+                    if i < self.config.watchers.len() {
+                        let watcher = &mut self.config.watchers[i];
+                        watcher.lightweight = !watcher.lightweight;
+                        self.config.save()?;
+                    }
+                }
+            }
+            KeyCode::Char('c') => {
+                // Cycle consensus fetches: off -> 2 -> 3 -> off
+                if let Some(i) = self.selected_watcher() {
+                    if i < self.config.watchers.len() {
+                        let watcher = &mut self.config.watchers[i];
+                        watcher.consensus_fetches = match watcher.consensus_fetches {
+                            n if n < 2 => 2,
+                            n if n < MAX_CONSENSUS_FETCHES => n + 1,
+                            _ => 0,
+                        };
                         self.config.save()?;
                     }
                 }
             }
             KeyCode::Char('d') => {
                 // Delete watcher
-                if let Some(i) = self.watcher_list_state.selected() {
+                if let Some(i) = self.selected_watcher() {
                     if i < self.config.watchers.len() {
-                        self.config.watchers.remove(i);
+                        let removed = self.config.watchers.remove(i);
                         self.config.save()?;
-
-                        // Adjust selection
-                        if self.config.watchers.is_empty() {
-                            self.watcher_list_state.select(None);
-                        } else if i >= self.config.watchers.len() {
-                            self.watcher_list_state.select(Some(self.config.watchers.len() - 1));
+                        trash::discard(&self.config.trash, &removed)?;
+                        if self.config.trash.days > 0 {
+                            self.list_message = format!("Deleted {} (Ctrl+Z to undo)", removed.url);
+                            self.push_undo(Undo::Deleted {
+                                watcher_id: removed.id,
+                                url: removed.url,
+                                index: i,
+                            });
                         }
+
+                        self.clamp_list_selection();
                     }
                 }
             }
+            KeyCode::Char('A') => {
+                // Archive the watcher, or restore it from the archive
+                if let Some(i) = self.selected_watcher() {
+                    let watcher = &mut self.config.watchers[i];
+                    watcher.archived = !watcher.archived;
+                    let undo = Undo::Archived {
+                        watcher_id: watcher.id.clone(),
+                    };
+                    self.list_message = if watcher.archived {
+                        format!("Archived {} (Tab shows the archive)", watcher.url)
+                    } else {
+                        format!("Restored {}", watcher.url)
+                    };
+                    self.config.save()?;
+                    self.push_undo(undo);
+                    self.clamp_list_selection();
+                }
+            }
+            KeyCode::Char('m') => {
+                // Fold the selected watcher's matrix back into one row
+                let name = self.selected_watcher().and_then(|i| self.config.watchers[i].matrix.clone());
+                if let Some(name) = name {
+                    self.expanded_matrices.remove(&name);
+                    let first = self.matrix_watchers(&name).first().copied();
+                    let row = self.listed_watchers().iter().position(|&i| Some(i) == first);
+                    self.watcher_list_state.select(row);
+                }
+            }
+            KeyCode::Tab => {
+                // Switch between the watchers and the archived ones
+                self.show_archived = !self.show_archived;
+                let first = (!self.listed_watchers().is_empty()).then_some(0);
+                self.watcher_list_state.select(first);
+            }
             KeyCode::Char('a') => {
                 self.clear_form();
                 self.screen = Screen::AddWatcher;
             }
-            KeyCode::Char('e') => {
-                // Edit watcher
-                if let Some(i) = self.watcher_list_state.selected() {
-                    if i < self.config.watchers.len() {
-                        self.populate_form_from_watcher(i);
-                        self.screen = Screen::EditWatcher(i);
+            KeyCode::Char('O') => {
+                // Open the page in the browser ('o' toggles one-shot)
+                if let Some(watcher) = self.selected_watcher().map(|i| &self.config.watchers[i]) {
+                    if let Err(e) = browser::open(&watcher.url) {
+                        self.list_message = format!("✗ {:#}", e);
+                    }
+                }
+            }
+            KeyCode::Char('v') => {
+                // Add a watcher for the URL in the clipboard
+                self.clear_form();
+                self.paste_url();
+                self.screen = Screen::AddWatcher;
+            }
+            KeyCode::Char('e') => {
+                // Edit watcher
+                if let Some(i) = self.selected_watcher() {
+                    if i < self.config.watchers.len() {
+                        self.populate_form_from_watcher(i);
+                        self.screen = Screen::EditWatcher(i);
+                    }
+                }
+            }
+            KeyCode::Enter => {
+                // Show details and check history
+                if let Some(i) = self.selected_watcher() {
+                    if i < self.config.watchers.len() {
+                        self.load_detail_history(i);
+                        self.detail_message.clear();
+                        self.screen = Screen::WatcherDetail(i);
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    fn handle_watcher_detail_input(&mut self, key: KeyCode, idx: usize) -> Result<bool> {
+        match key {
+            KeyCode::Esc => {
+                self.screen = Screen::ListWatchers;
+            }
+            KeyCode::Down | KeyCode::Char('j') if !self.detail_history.is_empty() => {
+                let i = self.detail_list_state.selected().map_or(0, |i| {
+                    (i + 1).min(self.detail_history.len() - 1)
+                });
+                self.detail_list_state.select(Some(i));
+            }
+            KeyCode::Up | KeyCode::Char('k') if !self.detail_history.is_empty() => {
+                let i = self.detail_list_state.selected().map_or(0, |i| i.saturating_sub(1));
+                self.detail_list_state.select(Some(i));
+            }
+            KeyCode::Char('r') => {
+                self.load_detail_history(idx);
+            }
+            KeyCode::Char('e') => {
+                self.populate_form_from_watcher(idx);
+                self.screen = Screen::EditWatcher(idx);
+            }
+            KeyCode::Char('u') => self.apply_move(idx)?,
+            KeyCode::Char('m') => {
+                let since = Utc::now() - chrono::Duration::days(HEATMAP_DAYS);
+                self.heatmap_matches = self
+                    .config
+                    .watchers
+                    .get(idx)
+                    .and_then(|w| history::match_times(&w.id, since).ok())
+                    .unwrap_or_default();
+                self.screen = Screen::Heatmap(idx);
+            }
+            KeyCode::Char('o') => {
+                if let Some(watcher) = self.config.watchers.get(idx) {
+                    self.detail_message = match browser::open(&watcher.url) {
+                        Ok(()) => String::new(),
+                        Err(e) => format!("✗ {:#}", e),
+                    };
+                }
+            }
+            KeyCode::Char('c') => self.copy_last_alert(idx, false),
+            KeyCode::Char('d') => self.copy_last_alert(idx, true),
+            KeyCode::Char('H') => {
+                // Clear the check history (Ctrl+Z brings it back)
+                if let Some(watcher) = self.config.watchers.get(idx) {
+                    let watcher_id = watcher.id.clone();
+                    self.detail_message = match history::clear(&watcher_id) {
+                        Ok(content) => {
+                            self.push_undo(Undo::HistoryCleared { watcher_id, content });
+                            String::from("History cleared (Ctrl+Z to undo)")
+                        }
+                        Err(e) => format!("✗ {:#}", e),
+                    };
+                    self.load_detail_history(idx);
+                }
+            }
+            KeyCode::Char('x') => {
+                // Dismiss the move so it isn't suggested again
+                if let Some(watcher) = self.config.watchers.get_mut(idx) {
+                    if let Some(moved_to) = watcher.moved_to.take() {
+                        watcher.ignored_move = Some(moved_to);
+                        self.config.save()?;
+                    }
+                }
+            }
+            _ => {}
+        }
+        Ok(false)
+    }
+
+    /// Copy the match context (or the diff) of a watcher's latest alert to
+    /// the clipboard
+    fn copy_last_alert(&mut self, idx: usize, diff: bool) {
+        let Some(watcher) = self.config.watchers.get(idx) else {
+            return;
+        };
+        let alert = WatcherState::load(&watcher.id).ok().and_then(|state| state.last_alert);
+        let Some(alert) = alert else {
+            self.detail_message = String::from("No alerts yet, nothing to copy");
+            return;
+        };
+
+        let header = format!(
+            "{} ({})",
+            watcher.url,
+            self.config.timezone.format(alert.at, "%Y-%m-%d %H:%M")
+        );
+        let text = if diff {
+            match &alert.diff {
+                Some(diff) => format!("{}\n\n{}", header, diff),
+                None => {
+                    self.detail_message = String::from("The latest alert has no diff (first check or oversized page)");
+                    return;
+                }
+            }
+        } else {
+            let contexts: Vec<String> = alert
+                .matches
+                .iter()
+                .map(|m| format!("{}: {}", m.keyword, m.context))
+                .collect();
+            format!("{}\n\n{}", header, contexts.join("\n"))
+        };
+
+        let what = if diff { "diff" } else { "match context" };
+        self.detail_message = match clipboard::copy(&text) {
+            Ok(()) => format!("✓ Copied the latest {} to the clipboard", what),
+            Err(e) => format!("✗ {:#}", e),
+        };
+    }
+
+    /// Switch a watcher to the URL its page moved to. The watcher keeps its
+    /// id, so its cache, history and notified matches carry over.
+    fn apply_move(&mut self, idx: usize) -> Result<()> {
+        let Some(moved_to) = self.config.watchers.get(idx).and_then(|w| w.moved_to.clone()) else {
+            return Ok(());
+        };
+
+        let url = match crate::watcher::normalize_url(&moved_to) {
+            Ok(url) => url,
+            Err(e) => {
+                self.detail_message = format!("✗ Can't switch: {}", e);
+                return Ok(());
+            }
+        };
+        if let Some(other) = self.config.find_watcher_by_url(&url).filter(|other| *other != idx) {
+            self.detail_message = format!("✗ Watcher #{} already watches {}", other + 1, url);
+            return Ok(());
+        }
+
+        let watcher = &mut self.config.watchers[idx];
+        watcher.url = url;
+        watcher.moved_to = None;
+        watcher.ignored_move = None;
+        self.config.save()?;
+
+        self.detail_message = String::from("✓ Switched to the new URL");
+        Ok(())
+    }
+
+    /// Load the recent check history of a watcher for the detail screen
+    fn load_detail_history(&mut self, idx: usize) {
+        self.detail_history = self
+            .config
+            .watchers
+            .get(idx)
+            .and_then(|w| history::load(&w.id, DETAIL_HISTORY_LEN).ok())
+            .unwrap_or_default();
+        self.detail_history.reverse();
+
+        let selected = if self.detail_history.is_empty() { None } else { Some(0) };
+        self.detail_list_state.select(selected);
+    }
+
+    /// Show the watcher list (not the archive), with the first one selected
+    fn open_watcher_list(&mut self) {
+        self.screen = Screen::ListWatchers;
+        self.show_archived = false;
+        let first = (!self.listed_watchers().is_empty()).then_some(0);
+        self.watcher_list_state.select(first);
+    }
+
+    /// Indices of the watchers the list shows (archived or not). A folded
+    /// matrix is listed as its first watcher.
+    fn listed_watchers(&self) -> Vec<usize> {
+        let mut folded_seen = BTreeSet::new();
+        (0..self.config.watchers.len())
+            .filter(|&i| self.config.watchers[i].archived == self.show_archived)
+            .filter(|&i| match self.folded_matrix(i) {
+                Some(name) => folded_seen.insert(name),
+                None => true,
+            })
+            .collect()
+    }
+
+    /// The matrix a watcher belongs to, if it's folded into one row
+    fn folded_matrix(&self, i: usize) -> Option<&str> {
+        let name = self.config.watchers.get(i)?.matrix.as_deref()?;
+        (!self.expanded_matrices.contains(name)).then_some(name)
+    }
+
+    /// The watchers of a matrix shown in the list (archived or not)
+    fn matrix_watchers(&self, name: &str) -> Vec<usize> {
+        (0..self.config.watchers.len())
+            .filter(|&i| {
+                let watcher = &self.config.watchers[i];
+                watcher.archived == self.show_archived && watcher.matrix.as_deref() == Some(name)
+            })
+            .collect()
+    }
+
+    /// Keys on the row of a folded matrix: `m`/Enter unfold it, `t`
+    /// disables (or enables) all its watchers. Returns whether the key was
+    /// handled here; keys about single watchers are refused.
+    fn handle_folded_matrix_key(&mut self, name: &str, key: KeyCode) -> Result<bool> {
+        match key {
+            KeyCode::Char('m') | KeyCode::Enter => {
+                self.expanded_matrices.insert(name.to_string());
+            }
+            KeyCode::Char('t') => {
+                let children = self.matrix_watchers(name);
+                let enable = !children.iter().any(|&i| self.config.watchers[i].enabled);
+                let mut previous = Vec::new();
+                for i in children {
+                    let watcher = &mut self.config.watchers[i];
+                    if watcher.enabled != enable {
+                        previous.push((watcher.id.clone(), watcher.enabled));
+                        watcher.enabled = enable;
                     }
                 }
+                if !previous.is_empty() {
+                    self.config.save()?;
+                    let action = if enable { "Enabled" } else { "Disabled" };
+                    self.list_message = format!("{} the {} watcher(s) of {}", action, previous.len(), name);
+                    self.push_undo(Undo::Enabled(previous));
+                }
             }
+            KeyCode::Char('o' | 'p' | 'r' | 'h' | 'c' | 'd' | 'e' | 'A' | 'O') => {
+                self.list_message = format!("Press m to unfold the matrix {} first", name);
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Index in the config of the watcher selected in the list
+    fn selected_watcher(&self) -> Option<usize> {
+        let selected = self.watcher_list_state.selected()?;
+        self.listed_watchers().get(selected).copied()
+    }
+
+    /// Keep the list selection on a listed watcher after one left the list
+    fn clamp_list_selection(&mut self) {
+        let count = self.listed_watchers().len();
+        match self.watcher_list_state.selected() {
+            _ if count == 0 => self.watcher_list_state.select(None),
+            Some(i) if i >= count => self.watcher_list_state.select(Some(count - 1)),
             _ => {}
         }
-        Ok(false)
     }
 
     fn clear_form(&mut self) {
         self.url_input.clear();
         self.keywords_input.clear();
-        self.interval_input = String::from("30");
+        self.interval_input = String::from("30m");
+        self.schedule_input.clear();
+        self.expires_input.clear();
+        self.form_field = FormField::Url;
+        self.template_index = None;
+        self.form_error.clear();
+        self.duplicate_of = None;
+        self.pending_keywords.clear();
+        self.pending_keyword_sets.clear();
+        self.interval_override = false;
+    }
+
+    /// Check the interval against the guardrails before saving.
+    /// The first time an interval is too short a warning is shown and None is
+    /// returned; pressing Enter again confirms. Returns whether the watcher
+    /// should be allowed to run below the minimum.
+    fn confirm_interval(&mut self, url: &str, interval: Duration) -> Option<bool> {
+        match self.config.guardrails.check_interval(url, interval) {
+            Some(warning) if !self.interval_override => {
+                self.form_error = format!("{}\nPress Enter again to save anyway.", warning);
+                self.interval_override = true;
+                None
+            }
+            Some(_) => Some(true),
+            None => Some(false),
+        }
+    }
+
+    /// Validate the add/edit form
+    fn parse_form(&self) -> Result<FormValues> {
+        let url = crate::watcher::normalize_url(&self.url_input)?;
+
+        let (keywords, keyword_sets) =
+            crate::cli::split_keyword_sets(crate::cli::split_keywords(&self.keywords_input));
+        if keywords.is_empty() && keyword_sets.is_empty() {
+            anyhow::bail!("At least one keyword is required");
+        }
+        self.config.check_keyword_sets(&keyword_sets)?;
+
+        let interval = parse_interval(&self.interval_input)?;
+        let active_window = ActiveWindow::parse(&self.schedule_input)?;
+        let expires_at = parse_expiry(&self.expires_input, &self.config.timezone)?;
+
+        Ok(FormValues {
+            url,
+            keywords,
+            keyword_sets,
+            interval,
+            active_window,
+            expires_at,
+        })
+    }
+
+    /// Editing keys shared by the add/edit forms
+    fn handle_form_typing(&mut self, key: KeyCode) {
+        let input = match self.form_field {
+            FormField::Url => &mut self.url_input,
+            FormField::Keywords => &mut self.keywords_input,
+            FormField::Interval => &mut self.interval_input,
+            FormField::Schedule => &mut self.schedule_input,
+            FormField::Expires => &mut self.expires_input,
+        };
+
+        match key {
+            KeyCode::Tab => self.form_field = self.form_field.next(),
+            KeyCode::F(3) => self.next_interval_preset(),
+            KeyCode::F(4) => self.paste_url(),
+            KeyCode::Backspace => {
+                input.pop();
+            }
+            KeyCode::Char(c) => input.push(c),
+            _ => {}
+        }
+    }
+
+    /// Fill the URL field from the clipboard (errors are shown in the form)
+    fn paste_url(&mut self) {
+        match clipboard::paste_url() {
+            Ok(url) => {
+                self.url_input = url;
+                self.form_field = FormField::Keywords;
+                self.form_error.clear();
+            }
+            Err(e) => self.form_error = format!("{:#}", e),
+        }
+    }
+
+    /// Replace the interval field with the next quick preset
+    fn next_interval_preset(&mut self) {
+        let current = INTERVAL_PRESETS
+            .iter()
+            .position(|p| *p == self.interval_input.trim());
+        let next = match current {
+            Some(i) => (i + 1) % INTERVAL_PRESETS.len(),
+            None => 0,
+        };
+
+        self.interval_input = INTERVAL_PRESETS[next].to_string();
+        self.form_field = FormField::Interval;
+    }
+
+    /// Apply the next template in the list to the add form
+    fn apply_next_template(&mut self) {
+        let all = templates::all_templates(&self.config);
+        if all.is_empty() {
+            return;
+        }
+
+        let next = match self.template_index {
+            Some(i) => (i + 1) % all.len(),
+            None => 0,
+        };
+        let template = &all[next];
+
+        self.url_input = template.url.clone();
+        self.keywords_input = template.keywords.join(", ");
+        self.interval_input = format_interval(template.check_interval);
         self.form_field = FormField::Url;
+        self.template_index = Some(next);
     }
 
     fn populate_form_from_watcher(&mut self, index: usize) {
         if let Some(watcher) = self.config.watchers.get(index) {
             self.url_input = watcher.url.clone();
-            self.keywords_input = watcher.keywords.join(", ");
-            self.interval_input = (watcher.check_interval.as_secs() / 60).to_string();
+            self.keywords_input = watcher.keyword_summary();
+            self.interval_input = format_interval(watcher.check_interval);
+            self.schedule_input = watcher
+                .active_window
+                .as_ref()
+                .map(|w| w.to_string())
+                .unwrap_or_default();
+            self.expires_input = watcher
+                .expires_at
+                .map(|at| format_expiry(at, &self.config.timezone))
+                .unwrap_or_default();
             self.form_field = FormField::Url;
+            self.form_error.clear();
+            // Keep a previously confirmed short interval without asking again
+            self.interval_override = watcher.allow_short_interval;
         }
     }
 
     fn handle_edit_watcher_input(&mut self, key: KeyCode, index: usize) -> Result<bool> {
+        // Any edit cancels a pending short-interval confirmation
+        if key != KeyCode::Enter {
+            self.interval_override = false;
+        }
+
         match key {
             KeyCode::Esc => {
                 self.screen = Screen::ListWatchers;
                 self.clear_form();
             }
-            KeyCode::Tab => {
-                self.form_field = match self.form_field {
-                    FormField::Url => FormField::Keywords,
-                    FormField::Keywords => FormField::Interval,
-                    FormField::Interval => FormField::Url,
-                };
-            }
-            KeyCode::Enter => {
-                // Save edited watcher
-                if !self.url_input.is_empty() && !self.keywords_input.is_empty() {
-                    let interval_mins: u64 = self.interval_input.parse().unwrap_or(30);
-                    let interval = Duration::from_secs(interval_mins * 60);
-
-                    let keywords: Vec<String> = self.keywords_input
-                        .split(',')
-                        .map(|s| s.trim().to_string())
-                        .filter(|s| !s.is_empty())
-                        .collect();
-
-                    // Update the existing watcher
-                    if let Some(watcher) = self.config.watchers.get_mut(index) {
-                        watcher.url = self.url_input.clone();
-                        watcher.keywords = keywords;
-                        watcher.check_interval = interval;
+            KeyCode::Enter if !self.url_input.is_empty() && !self.keywords_input.is_empty() => {
+                // Save edited watcher (validation errors are shown in the form)
+                let FormValues { url, keywords, keyword_sets, interval, active_window, expires_at } = match self.parse_form() {
+                    Ok(values) => values,
+                    Err(e) => {
+                        self.form_error = e.to_string();
+                        return Ok(false);
                     }
+                };
 
-                    self.config.save()?;
+                let allow_short_interval = match self.confirm_interval(&url, interval) {
+                    Some(allow) => allow,
+                    None => return Ok(false),
+                };
 
-                    self.screen = Screen::ListWatchers;
-                    self.clear_form();
-                }
-            }
-            KeyCode::Backspace => {
-                match self.form_field {
-                    FormField::Url => {
-                        self.url_input.pop();
-                    }
-                    FormField::Keywords => {
-                        self.keywords_input.pop();
-                    }
-                    FormField::Interval => {
-                        self.interval_input.pop();
-                    }
-                }
-            }
-            KeyCode::Char(c) => {
-                match self.form_field {
-                    FormField::Url => self.url_input.push(c),
-                    FormField::Keywords => self.keywords_input.push(c),
-                    FormField::Interval => {
-                        if c.is_ascii_digit() {
-                            self.interval_input.push(c);
-                        }
+                // Update the existing watcher
+                if let Some(watcher) = self.config.watchers.get_mut(index) {
+                    if watcher.url != url {
+                        // A suggested move no longer applies to the new URL
+                        watcher.moved_to = None;
+                        watcher.ignored_move = None;
                     }
+                    watcher.url = url;
+                    // Keywords whose text is unchanged keep their settings
+                    watcher.keywords = keywords_from_texts(keywords, &watcher.keywords);
+                    watcher.keyword_sets = keyword_sets;
+                    watcher.check_interval = interval;
+                    watcher.allow_short_interval = allow_short_interval;
+                    watcher.active_window = active_window;
+                    watcher.expires_at = expires_at;
                 }
+
+                self.config.save()?;
+
+                self.screen = Screen::ListWatchers;
+                self.clear_form();
             }
-            _ => {}
+            // Tab, F3, typing and backspace
+            _ => self.handle_form_typing(key),
         }
         Ok(false)
     }
 
-    fn start_monitoring(&mut self) -> Result<bool> {
+    /// Run the monitor in the background (unless it's running already) and
+    /// open the live monitoring view
+    fn start_monitoring(&mut self) -> Result<()> {
+        if self.monitor_runtime.is_some() {
+            self.screen = Screen::Monitoring;
+            return Ok(());
+        }
+
         // Save any pending changes
         self.config.save()?;
 
-        // Exit TUI and start monitoring in blocking mode
-        // This returns true to exit the TUI loop
-        disable_raw_mode()?;
-        execute!(
-            io::stdout(),
-            LeaveAlternateScreen,
-            DisableMouseCapture
-        )?;
-
-        // Create monitor and start
-        let monitor = Monitor::new(self.config.clone());
+        let (log, events) = EventLog::channel(self.config.timezone);
+        let monitor = Monitor::live(self.config.clone(), log.clone());
+        self.monitor_save_failure = Some(monitor.save_failure());
+        self.monitor_config = Some(monitor.live_config());
+        self.monitor_checks = Some(monitor.subscribe());
+        self.monitor_activity.clear();
 
-        // Run monitoring in the async runtime
         let runtime = tokio::runtime::Runtime::new()?;
-        runtime.block_on(async {
-            monitor.start().await
-        })?;
+        runtime.spawn(async move {
+            match monitor.start().await {
+                Ok(()) => log.info("Monitoring stopped"),
+                Err(e) => log.error(format!("✗ Monitoring stopped: {:#}", e)),
+            }
+        });
 
-        // After monitoring ends, exit the application
-        Ok(true)
+        self.monitor_runtime = Some(runtime);
+        self.monitor_events = Some(events);
+        self.monitor_log.clear();
+        self.monitor_scroll = 0;
+        self.screen = Screen::Monitoring;
+        Ok(())
+    }
+
+    /// Stop the background monitor (if running)
+    fn stop_monitoring(&mut self) {
+        if let Some(runtime) = self.monitor_runtime.take() {
+            runtime.shutdown_background();
+        }
+        self.monitor_config = None;
+        self.monitor_events = None;
+        self.monitor_checks = None;
+        self.monitor_save_failure = None;
+    }
+
+    /// Move new monitor events into the log pane and the dashboard.
+    /// Returns whether there were any.
+    fn receive_monitor_events(&mut self) -> bool {
+        let mut received = false;
+        if let Some(checks) = &mut self.monitor_checks {
+            while let Ok(event) = checks.try_recv() {
+                received = true;
+                self.monitor_activity
+                    .entry(event.watcher_id().to_string())
+                    .or_default()
+                    .record(event);
+            }
+        }
+
+        let Some(events) = &mut self.monitor_events else {
+            return received;
+        };
+
+        while let Ok(event) = events.try_recv() {
+            received = true;
+            // Keep the view still while scrolled up
+            if self.monitor_scroll > 0 && self.monitor_filter.shows(event.level) {
+                self.monitor_scroll += 1;
+            }
+            self.monitor_log.push_back(event);
+            if self.monitor_log.len() > MONITOR_LOG_LEN {
+                self.monitor_log.pop_front();
+            }
+        }
+        received
+    }
+
+    fn draw_monitoring(&mut self, f: &mut Frame) {
+        let zone = self.config.timezone;
+        let save_failure = self
+            .monitor_save_failure
+            .as_ref()
+            .and_then(|failure| failure.borrow().clone());
+
+        let watchers = self.config.watchers.iter().filter(|w| w.is_active()).count();
+        let dashboard_height = watchers.clamp(1, DASHBOARD_ROWS) as u16 + 2;
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(dashboard_height),
+                Constraint::Min(3),
+                Constraint::Length(3),
+            ])
+            .split(f.size());
+
+        // Title, replaced by a warning while the config can't be saved
+        let title = match &save_failure {
+            Some(error) => Paragraph::new(format!("⚠ Can't save config: {}", error))
+                .style(Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+            None => Paragraph::new("Live Monitoring")
+                .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD)),
+        };
+        let title = title
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        self.draw_dashboard(f, chunks[1]);
+
+        // Log pane: the newest events that fit, or an older page when scrolled up
+        let shown: Vec<&LogEvent> = self
+            .monitor_log
+            .iter()
+            .filter(|event| self.monitor_filter.shows(event.level))
+            .collect();
+        let height = chunks[2].height.saturating_sub(2) as usize;
+        self.monitor_scroll = self.monitor_scroll.min(shown.len().saturating_sub(height));
+        let end = shown.len() - self.monitor_scroll;
+        let start = end.saturating_sub(height);
+
+        let lines: Vec<Line> = shown[start..end]
+            .iter()
+            .map(|event| {
+                let color = match event.level {
+                    Level::Info => Color::White,
+                    Level::Change => Color::Cyan,
+                    Level::Match => Color::Green,
+                    Level::Warning => Color::Yellow,
+                    Level::Error => Color::Red,
+                };
+                // Name the watcher when the message itself doesn't
+                let message = match &event.watcher {
+                    Some(url) if !event.message.contains(url.as_str()) => {
+                        format!("{}: {}", url, event.message.trim_start())
+                    }
+                    _ => event.message.clone(),
+                };
+                Line::from(vec![
+                    Span::styled(zone.format(event.at, "%H:%M:%S "), Style::default().fg(Color::Gray)),
+                    Span::styled(message, Style::default().fg(color)),
+                ])
+            })
+            .collect();
+
+        let position = if self.monitor_scroll == 0 {
+            String::from("following")
+        } else {
+            format!("{} lines up", self.monitor_scroll)
+        };
+        let log = Paragraph::new(lines).block(
+            Block::default()
+                .title(format!(
+                    "Log ({}, {} events, {})",
+                    self.monitor_filter.label(),
+                    shown.len(),
+                    position
+                ))
+                .borders(Borders::ALL),
+        );
+        f.render_widget(log, chunks[2]);
+
+        // Help
+        let help = Paragraph::new("↑↓/PgUp/PgDn: Scroll | End: Follow | f: Filter | s: Stop monitoring | Esc: Back")
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(help, chunks[3]);
+    }
+
+    /// One line per enabled watcher with the outcome of its latest check,
+    /// most recently checked first
+    fn draw_dashboard(&self, f: &mut Frame, area: Rect) {
+        let zone = self.config.timezone;
+        let mut watchers: Vec<(&Watcher, Option<&WatcherActivity>)> = self
+            .config
+            .watchers
+            .iter()
+            .filter(|w| w.is_active())
+            .map(|w| (w, self.monitor_activity.get(&w.id)))
+            .collect();
+        watchers.sort_by_key(|(_, activity)| {
+            std::cmp::Reverse(activity.and_then(|a| a.last.as_ref()).map(MonitorEvent::at))
+        });
+
+        let lines: Vec<Line> = watchers
+            .iter()
+            .take(DASHBOARD_ROWS)
+            .map(|(watcher, activity)| {
+                let Some(activity) = activity else {
+                    return Line::from(vec![
+                        Span::styled("--:--:-- ", Style::default().fg(Color::Gray)),
+                        Span::raw(format!("{} ", watcher.url)),
+                        Span::styled("waiting for the first check", Style::default().fg(Color::Gray)),
+                    ]);
+                };
+                let (outcome, color) = match &activity.last {
+                    Some(MonitorEvent::MatchFound { keywords, .. }) => {
+                        (format!("✓ matched {}", keywords.join(", ")), Color::Green)
+                    }
+                    Some(MonitorEvent::CheckCompleted { changed: true, .. }) => {
+                        (String::from("~ changed"), Color::White)
+                    }
+                    Some(MonitorEvent::CheckCompleted { changed: false, .. }) | None => {
+                        (String::from("- unchanged"), Color::Gray)
+                    }
+                    Some(MonitorEvent::Error { message, .. }) => (format!("✗ {}", message), Color::Red),
+                    Some(MonitorEvent::Blocked { reason, .. }) => {
+                        (format!("⚠ blocked: {}", reason), Color::Magenta)
+                    }
+                };
+                let at = activity
+                    .last
+                    .as_ref()
+                    .map_or(String::from("--:--:--"), |event| zone.format(event.at(), "%H:%M:%S"));
+                Line::from(vec![
+                    Span::styled(format!("{} ", at), Style::default().fg(Color::Gray)),
+                    Span::raw(format!("{} ", watcher.url)),
+                    Span::styled(
+                        format!("[{} checks, {} matches, {} errors] ", activity.checks, activity.matches, activity.errors),
+                        Style::default().fg(Color::Gray),
+                    ),
+                    Span::styled(outcome, Style::default().fg(color)),
+                ])
+            })
+            .collect();
+
+        let lines = if lines.is_empty() {
+            vec![Line::from(Span::styled(
+                "No enabled watchers: enable one in the watcher list to start monitoring it",
+                Style::default().fg(Color::Gray),
+            ))]
+        } else {
+            lines
+        };
+        let title = if watchers.len() > DASHBOARD_ROWS {
+            format!("Watchers ({} enabled, latest {} shown)", watchers.len(), DASHBOARD_ROWS)
+        } else {
+            format!("Watchers ({} enabled)", watchers.len())
+        };
+        let dashboard = Paragraph::new(lines).block(Block::default().title(title).borders(Borders::ALL));
+        f.render_widget(dashboard, area);
+    }
+
+    fn handle_monitoring_input(&mut self, key: KeyCode) -> Result<bool> {
+        match key {
+            // The monitor keeps running in the background
+            KeyCode::Esc | KeyCode::Char('q') => self.screen = Screen::MainMenu,
+            KeyCode::Char('s') => {
+                self.stop_monitoring();
+                self.screen = Screen::MainMenu;
+            }
+            KeyCode::Up | KeyCode::Char('k') => self.monitor_scroll += 1,
+            KeyCode::Down | KeyCode::Char('j') => {
+                self.monitor_scroll = self.monitor_scroll.saturating_sub(1);
+            }
+            KeyCode::PageUp => self.monitor_scroll += LOG_PAGE,
+            KeyCode::PageDown => self.monitor_scroll = self.monitor_scroll.saturating_sub(LOG_PAGE),
+            // Clamped to the oldest event when drawing
+            KeyCode::Home => self.monitor_scroll = usize::MAX,
+            KeyCode::End => self.monitor_scroll = 0,
+            KeyCode::Char('f') => {
+                self.monitor_filter = self.monitor_filter.next();
+                self.monitor_scroll = 0;
+            }
+            _ => {}
+        }
+        Ok(false)
     }
 
     fn draw_service_control(&mut self, f: &mut Frame) {
@@ -726,7 +2937,7 @@ impl UI {
         let message_text = if !self.service_status_message.is_empty() {
             self.service_status_message.clone()
         } else {
-            format!(
+            String::from(
                 "Controls:\n\n\
                 s - Start service\n\
                 x - Stop service\n\
@@ -736,6 +2947,14 @@ impl UI {
                 Close this app and it will keep monitoring!"
             )
         };
+        // The installed service runs plain `--daemon`
+        let message_text = match profile::active() {
+            Some(name) => format!(
+                "{}\n\nThe service monitors the default profile. Run `web-watcher-alert --profile {} --daemon` for this one.",
+                message_text, name
+            ),
+            None => message_text,
+        };
 
         let message = Paragraph::new(message_text)
             .wrap(Wrap { trim: true })
@@ -775,7 +2994,7 @@ impl UI {
         use std::process::Command;
 
         let output = Command::new("launchctl")
-            .args(&["list", "com.webwatcheralert"])
+            .args(["list", "com.webwatcheralert"])
             .output();
 
         match output {
@@ -841,7 +3060,7 @@ impl UI {
         }
 
         let output = Command::new("launchctl")
-            .args(&["start", "com.webwatcheralert"])
+            .args(["start", "com.webwatcheralert"])
             .output();
 
         match output {
@@ -934,7 +3153,7 @@ impl UI {
 
         let target = format!("gui/{}/com.webwatcheralert", uid);
         let output = Command::new("launchctl")
-            .args(&["kill", "SIGTERM", &target])
+            .args(["kill", "SIGTERM", &target])
             .output();
 
         match output {
@@ -971,4 +3190,315 @@ impl UI {
             }
         }
     }
+
+    fn draw_wizard(&mut self, f: &mut Frame, step: WizardStep) {
+        // The watcher step reuses the add/edit form
+        if step == WizardStep::Watcher {
+            self.draw_watcher_form(
+                f,
+                "Setup Wizard (2/5): Your First Watcher",
+                "Tab: Next field | F3: Interval preset | F4: Paste URL | Enter: Continue | Esc: Skip this step",
+            );
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(0),
+                Constraint::Length(3),
+            ])
+            .split(f.size());
+
+        let (step_number, step_name) = match step {
+            WizardStep::Welcome => (1, "Welcome"),
+            WizardStep::Watcher => (2, "Your First Watcher"),
+            WizardStep::Notifications => (3, "Notifications"),
+            WizardStep::Service => (4, "Background Service"),
+            WizardStep::Done => (5, "All Set"),
+        };
+
+        // Title
+        let title = Paragraph::new(format!("Setup Wizard ({}/5): {}", step_number, step_name))
+            .style(Style::default().fg(Color::Cyan).add_modifier(Modifier::BOLD))
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(title, chunks[0]);
+
+        let help_text = match step {
+            WizardStep::Welcome => "Enter: Begin | Esc: Skip wizard",
+            WizardStep::Watcher => "",
+            WizardStep::Notifications => "↑↓: Select | Space: Toggle | Enter: Continue",
+            WizardStep::Service => "y: Install service | n/Enter: Skip",
+            WizardStep::Done => "Enter: Go to main menu",
+        };
+
+        match step {
+            WizardStep::Notifications => {
+                let form = Layout::default()
+                    .direction(Direction::Vertical)
+                    .constraints([
+                        Constraint::Length(3),
+                        Constraint::Length(3),
+                        Constraint::Min(0),
+                    ])
+                    .split(chunks[1]);
+
+                let desktop_style = if !self.wizard_webhook_focused {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                let checkbox = if self.config.notifications.desktop { "[x]" } else { "[ ]" };
+                let desktop = Paragraph::new(format!("{} Desktop notifications", checkbox))
+                    .style(desktop_style)
+                    .block(Block::default().title("Desktop").borders(Borders::ALL));
+                f.render_widget(desktop, form[0]);
+
+                let webhook_style = if self.wizard_webhook_focused {
+                    Style::default().fg(Color::Yellow)
+                } else {
+                    Style::default()
+                };
+                let webhook = Paragraph::new(self.wizard_webhook_input.as_str())
+                    .style(webhook_style)
+                    .block(Block::default().title("Webhook URL (optional)").borders(Borders::ALL));
+                f.render_widget(webhook, form[1]);
+
+                let info = Paragraph::new(
+                    "Alerts can be shown as desktop notifications and/or POSTed as JSON \
+                    to a webhook (e.g. a Slack/Discord bridge or ntfy).",
+                )
+                .style(Style::default().fg(Color::Gray))
+                .wrap(Wrap { trim: true });
+                f.render_widget(info, form[2]);
+            }
+            _ => {
+                let text = match step {
+                    WizardStep::Welcome => String::from(
+                        "Welcome to Web Watcher Alert!\n\n\
+                        This wizard will help you:\n\
+                        1. Create your first watcher\n\
+                        2. Choose how you want to be notified\n\
+                        3. Optionally install the background service\n\n\
+                        You can change everything later from the main menu.",
+                    ),
+                    WizardStep::Service => {
+                        if self.wizard_message.is_empty() {
+                            String::from(
+                                "The background service keeps monitoring after you close this app.\n\n\
+                                Install it now? (requires a release build: cargo build --release)",
+                            )
+                        } else {
+                            self.wizard_message.clone()
+                        }
+                    }
+                    _ => format!(
+                        "Setup complete!\n\n\
+                        Watchers configured: {}\n\
+                        Desktop notifications: {}\n\
+                        Webhook: {}\n\n\
+                        Config saved to: {}",
+                        self.config.watchers.len(),
+                        if self.config.notifications.desktop { "on" } else { "off" },
+                        self.config.notifications.webhook_url.as_deref().unwrap_or("none"),
+                        Config::config_path()
+                            .map(|p| p.display().to_string())
+                            .unwrap_or_default(),
+                    ),
+                };
+
+                let body = Paragraph::new(text)
+                    .wrap(Wrap { trim: false })
+                    .block(Block::default().borders(Borders::ALL));
+                f.render_widget(body, chunks[1]);
+            }
+        }
+
+        // Help
+        let help = Paragraph::new(help_text)
+            .style(Style::default().fg(Color::Gray))
+            .alignment(Alignment::Center)
+            .block(Block::default().borders(Borders::ALL));
+        f.render_widget(help, chunks[2]);
+    }
+
+    fn handle_wizard_input(&mut self, key: KeyCode, step: WizardStep) -> Result<bool> {
+        match step {
+            WizardStep::Welcome => match key {
+                KeyCode::Enter => {
+                    self.clear_form();
+                    self.screen = Screen::Wizard(WizardStep::Watcher);
+                }
+                KeyCode::Esc => {
+                    // Save an empty config so the wizard doesn't run again
+                    self.config.save()?;
+                    self.screen = Screen::MainMenu;
+                }
+                _ => {}
+            },
+            WizardStep::Watcher => match key {
+                KeyCode::Esc => {
+                    self.clear_form();
+                    self.screen = Screen::Wizard(WizardStep::Notifications);
+                }
+                KeyCode::Enter if !self.url_input.is_empty() && !self.keywords_input.is_empty() => {
+                    let FormValues { url, keywords, keyword_sets, interval, active_window, expires_at } = match self.parse_form() {
+                        Ok(values) => values,
+                        Err(e) => {
+                            self.form_error = e.to_string();
+                            return Ok(false);
+                        }
+                    };
+
+                    let allow_short_interval = match self.confirm_interval(&url, interval) {
+                        Some(allow) => allow,
+                        None => return Ok(false),
+                    };
+
+                    let keywords = keywords.into_iter().map(Keyword::new).collect();
+                    let mut watcher = Watcher::new(url, keywords, interval);
+                    watcher.keyword_sets = keyword_sets;
+                    watcher.allow_short_interval = allow_short_interval;
+                    watcher.active_window = active_window;
+                    watcher.expires_at = expires_at;
+                    self.config.watchers.push(watcher);
+
+                    self.clear_form();
+                    self.screen = Screen::Wizard(WizardStep::Notifications);
+                }
+                // Tab, typing and backspace behave like the add form
+                _ => {
+                    self.handle_add_watcher_input(key)?;
+                }
+            },
+            WizardStep::Notifications => match key {
+                KeyCode::Up | KeyCode::Down | KeyCode::Tab => {
+                    self.wizard_webhook_focused = !self.wizard_webhook_focused;
+                }
+                KeyCode::Char(' ') if !self.wizard_webhook_focused => {
+                    self.config.notifications.desktop = !self.config.notifications.desktop;
+                }
+                KeyCode::Char(c) if self.wizard_webhook_focused => {
+                    self.wizard_webhook_input.push(c);
+                }
+                KeyCode::Backspace if self.wizard_webhook_focused => {
+                    self.wizard_webhook_input.pop();
+                }
+                KeyCode::Enter => {
+                    let webhook = self.wizard_webhook_input.trim();
+                    self.config.notifications.webhook_url = if webhook.is_empty() {
+                        None
+                    } else {
+                        Some(webhook.to_string())
+                    };
+                    self.config.save()?;
+                    self.wizard_message.clear();
+                    self.screen = Screen::Wizard(WizardStep::Service);
+                }
+                _ => {}
+            },
+            WizardStep::Service => match key {
+                KeyCode::Char('y') if self.wizard_message.is_empty() => {
+                    self.wizard_message = install_service();
+                }
+                KeyCode::Char('n') | KeyCode::Enter | KeyCode::Esc => {
+                    self.screen = Screen::Wizard(WizardStep::Done);
+                }
+                _ => {}
+            },
+            WizardStep::Done => {
+                if matches!(key, KeyCode::Enter | KeyCode::Esc) {
+                    self.config.save()?;
+                    self.screen = Screen::MainMenu;
+                }
+            }
+        }
+        Ok(false)
+    }
+}
+
+/// Run the service install script and describe the outcome
+fn install_service() -> String {
+    use std::process::Command;
+
+    // The script lives in the project directory, two levels above target/release/
+    let script = std::env::current_exe()
+        .ok()
+        .and_then(|exe| exe.parent()?.parent()?.parent().map(|p| p.to_path_buf()))
+        .map(|project| project.join("scripts/install-service.sh"))
+        .filter(|path| path.exists())
+        .unwrap_or_else(|| std::path::PathBuf::from("./scripts/install-service.sh"));
+
+    match Command::new("bash").arg(&script).output() {
+        Ok(result) if result.status.success() => String::from(
+            "✓ Service installed!\n\n\
+            Start it later from Service Control in the main menu.\n\n\
+            Press Enter to continue.",
+        ),
+        Ok(result) => format!(
+            "Service installation failed.\n\n{}\n\n\
+            You can retry later with ./scripts/install-service.sh\n\n\
+            Press Enter to continue.",
+            strip_ansi(&String::from_utf8_lossy(&result.stdout)).trim()
+        ),
+        Err(e) => format!(
+            "Failed to run {}: {}\n\nPress Enter to continue.",
+            script.display(),
+            e
+        ),
+    }
+}
+
+/// Remove terminal color escape sequences (e.g. from script output)
+fn strip_ansi(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            // Skip until the end of the escape sequence (a letter)
+            for next in chars.by_ref() {
+                if next.is_ascii_alphabetic() {
+                    break;
+                }
+            }
+        } else {
+            result.push(c);
+        }
+    }
+    result
+}
+
+/// Human-readable byte size, e.g. "512 B", "45.1 KB", "3.2 MB"
+pub fn format_bytes(bytes: u64) -> String {
+    const KB: f64 = 1024.0;
+    const MB: f64 = KB * 1024.0;
+
+    let b = bytes as f64;
+    if b >= MB {
+        format!("{:.1} MB", b / MB)
+    } else if b >= KB {
+        format!("{:.1} KB", b / KB)
+    } else {
+        format!("{} B", bytes)
+    }
+}
+
+/// "1 error in a row", "5 errors in a row"
+/// A heatmap cell for `count` out of the busiest cell's `max`
+fn heat(count: usize, max: usize) -> (&'static str, Color) {
+    if count == 0 || max == 0 {
+        return ("·", Color::DarkGray);
+    }
+    match (count * 4).div_ceil(max) {
+        1 => ("░", Color::Green),
+        2 => ("▒", Color::Green),
+        3 => ("▓", Color::LightGreen),
+        _ => ("█", Color::LightGreen),
+    }
+}
+
+fn error_streak(count: u32) -> String {
+    format!("{} error{} in a row", count, if count == 1 { "" } else { "s" })
 }
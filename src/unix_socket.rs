@@ -0,0 +1,117 @@
+//! HTTP over unix sockets
+//!
+//! Lets watchers target local daemons that expose a status page on a unix
+//! socket instead of a TCP port. The socket path is the percent-encoded host
+//! of an `http+unix://` URL, e.g.
+//! `http+unix://%2Fvar%2Frun%2Fdocker.sock/info` requests `/info` from
+//! `/var/run/docker.sock`.
+
+use anyhow::{Context, Result};
+use hyper::{Body, Method, Request, Response};
+use percent_encoding::percent_decode_str;
+use std::path::PathBuf;
+use std::time::Duration;
+use tokio::net::UnixStream;
+
+/// URL scheme of unix socket targets
+pub const SCHEME: &str = "http+unix";
+
+/// Whether a URL targets a unix socket
+pub fn is_unix_url(url: &str) -> bool {
+    url.starts_with("http+unix://")
+}
+
+/// Split an `http+unix://` URL into the socket path and the request path
+/// (including the query string)
+pub fn parse(url: &str) -> Result<(PathBuf, String)> {
+    let parsed = url::Url::parse(url).with_context(|| format!("Invalid URL: {}", url))?;
+    if parsed.scheme() != SCHEME {
+        anyhow::bail!("Not a unix socket URL: {}", url);
+    }
+
+    let host = parsed
+        .host_str()
+        .filter(|h| !h.is_empty())
+        .with_context(|| format!("URL '{}' has no socket path", url))?;
+    let socket = percent_decode_str(host)
+        .decode_utf8()
+        .with_context(|| format!("Invalid socket path in URL: {}", url))?;
+    if !socket.starts_with('/') {
+        anyhow::bail!(
+            "Socket path must be absolute and percent-encoded, e.g. http+unix://%2Frun%2Fapp.sock/status"
+        );
+    }
+
+    let mut path = parsed.path().to_string();
+    if path.is_empty() {
+        path.push('/');
+    }
+    if let Some(query) = parsed.query() {
+        path.push('?');
+        path.push_str(query);
+    }
+
+    Ok((PathBuf::from(socket.into_owned()), path))
+}
+
+/// Send a request over the socket named in the URL (redirects aren't
+/// followed). `timeout` covers connecting and receiving the headers.
+pub async fn request(
+    url: &str,
+    method: Method,
+    user_agent: &str,
+    timeout: Duration,
+) -> Result<Response<Body>> {
+    let (socket, path) = parse(url)?;
+    if let Some(user) = crate::system::monitored_user() {
+        anyhow::bail!(
+            "Unix sockets are off for the users of the system daemon ({}): they would be opened as the service's account",
+            user
+        );
+    }
+
+    let send = async {
+        let stream = UnixStream::connect(&socket)
+            .await
+            .with_context(|| format!("Failed to connect to socket: {}", socket.display()))?;
+
+        let (mut sender, connection) = hyper::client::conn::handshake(stream)
+            .await
+            .context("HTTP handshake over unix socket failed")?;
+        // Drive the connection until the response body has been read
+        tokio::spawn(async move {
+            let _ = connection.await;
+        });
+
+        let request = Request::builder()
+            .method(method)
+            .uri(path)
+            .header(hyper::header::HOST, "localhost")
+            .header(hyper::header::USER_AGENT, user_agent)
+            .body(Body::empty())
+            .context("Failed to build request")?;
+
+        sender
+            .send_request(request)
+            .await
+            .with_context(|| format!("Failed to fetch URL: {}", url))
+    };
+
+    tokio::time::timeout(timeout, send)
+        .await
+        .with_context(|| format!("Timed out fetching URL: {}", url))?
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn monitored_users_cant_open_sockets() {
+        let _user = crate::system::monitor_as("alice");
+        let error = request("http+unix://%2Fvar%2Frun%2Fdocker.sock/info", Method::GET, "test", Duration::from_secs(5))
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("system daemon (alice)"), "{}", error);
+    }
+}
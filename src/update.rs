@@ -0,0 +1,127 @@
+//! Optional check for new releases
+//!
+//! Off unless `update_check.enabled` is set. The project's release feed (an
+//! Atom feed of release tags) is fetched at most once per interval, and a
+//! release newer than the running version is reported in the monitor's log
+//! and the TUI's status panel. When the feed was last fetched and the newest
+//! release it listed are kept in the data directory, so restarts don't
+//! query the feed again.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::{
+    config::{Config, UpdateCheck},
+    fetcher::{self, RequestOptions},
+};
+
+/// The version of this build
+pub const CURRENT_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Shortest time between two fetches of the release feed
+pub const MIN_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// What the last fetch of the release feed found
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct UpdateState {
+    checked_at: Option<DateTime<Utc>>,
+    /// Newest release tag in the feed, like "v0.2.0"
+    latest: Option<String>,
+}
+
+/// The newest release if it's newer than this build, as of the last fetch
+/// of the feed (doesn't go online)
+pub fn available() -> Option<String> {
+    let latest = load_state().ok()?.latest?;
+    is_newer(&latest, CURRENT_VERSION).then_some(latest)
+}
+
+/// Fetch the release feed unless that was done less than an interval ago.
+/// Returns the newest release if it's newer than this build.
+pub async fn check(settings: &UpdateCheck) -> Result<Option<String>> {
+    let mut state = load_state()?;
+    let interval = chrono::Duration::from_std(settings.interval.max(MIN_INTERVAL)).unwrap_or_default();
+    let due = state.checked_at.is_none_or(|at| Utc::now() - at >= interval);
+
+    if due {
+        // Remember the attempt even if it fails, so a broken feed isn't
+        // queried on every call
+        state.checked_at = Some(Utc::now());
+        let result = fetcher::fetch_url(&settings.feed_url, &RequestOptions::default()).await;
+        if let Ok(feed) = &result {
+            state.latest = latest_release(feed);
+        }
+        save_state(&state)?;
+        result.with_context(|| format!("Failed to fetch the release feed {}", settings.feed_url))?;
+    }
+
+    Ok(state.latest.filter(|latest| is_newer(latest, CURRENT_VERSION)))
+}
+
+/// The first release tag in an Atom feed of releases (feeds list the newest
+/// first). Pre-releases like "v1.0.0-rc.1" are skipped.
+fn latest_release(feed: &str) -> Option<String> {
+    let tag = Regex::new(r#"/releases/tag/([^"/<>\s]+)"#).ok()?;
+    for captures in tag.captures_iter(feed) {
+        let name = &captures[1];
+        if parse_version(name).is_some() {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
+/// Whether release `tag` is a higher version than `current`
+fn is_newer(tag: &str, current: &str) -> bool {
+    match (parse_version(tag), parse_version(current)) {
+        (Some(tag), Some(current)) => tag > current,
+        _ => false,
+    }
+}
+
+/// The numbers of a version like "v1.2" or "1.2.3" (missing ones are 0)
+fn parse_version(version: &str) -> Option<[u64; 3]> {
+    let version = version.trim().trim_start_matches(['v', 'V']);
+    let mut numbers = [0; 3];
+    let mut parts = version.split('.');
+    for number in &mut numbers {
+        match parts.next() {
+            Some(part) => *number = part.parse().ok()?,
+            None => break,
+        }
+    }
+    parts.next().is_none().then_some(numbers)
+}
+
+fn state_path() -> Result<PathBuf> {
+    Ok(Config::data_dir()?.join("update-check.json"))
+}
+
+fn load_state() -> Result<UpdateState> {
+    let path = state_path()?;
+    if !path.exists() {
+        return Ok(UpdateState::default());
+    }
+
+    let content = fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read update check file: {}", path.display()))?;
+    serde_json::from_str(&content)
+        .with_context(|| format!("Failed to parse update check file: {}", path.display()))
+}
+
+fn save_state(state: &UpdateState) -> Result<()> {
+    let path = state_path()?;
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create data directory: {}", parent.display()))?;
+    }
+
+    let content = serde_json::to_string_pretty(state).context("Failed to serialize update check")?;
+    fs::write(&path, content)
+        .with_context(|| format!("Failed to write update check file: {}", path.display()))
+}
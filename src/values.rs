@@ -0,0 +1,219 @@
+//! Numeric values tracked over time
+//!
+//! A watcher with `track_value` reads a number from its page on every check
+//! (a price, a stock count, a queue length): the first group of the
+//! pattern, or its whole match. The values are kept in the check history,
+//! drawn as a sparkline on the detail screen, and alerts tell how the value
+//! moved since the previous check. `export-values` writes the series out as
+//! CSV or JSON for spreadsheets.
+
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use regex::{Regex, RegexBuilder};
+use serde_json::{json, Value};
+
+use crate::history;
+use crate::watcher::Watcher;
+
+/// Compile a `track_value` pattern (case-insensitive, like regex keywords)
+pub fn compile(pattern: &str) -> Result<Regex, regex::Error> {
+    RegexBuilder::new(pattern).case_insensitive(true).build()
+}
+
+/// The number the pattern finds first in `content`
+pub fn extract(regex: &Regex, content: &str) -> Option<f64> {
+    let captures = regex.captures(content)?;
+    let text = captures.get(1).or_else(|| captures.get(0))?.as_str();
+    parse_number(text)
+}
+
+/// A number as pages write it: "$1,299.00", "1.299,00 €", "-3", "42 left".
+/// The last of a mix of separators is the decimal one; a lone separator
+/// followed by exactly three digits groups thousands.
+pub fn parse_number(text: &str) -> Option<f64> {
+    let start = text.find(|c: char| c.is_ascii_digit())?;
+    let negative = text[..start].ends_with(['-', '−']);
+    let number: String = text[start..]
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || matches!(c, '.' | ',' | '\'' | '\u{a0}' | '\u{202f}'))
+        .filter(|c| c.is_ascii_digit() || matches!(c, '.' | ','))
+        .collect();
+    let number = number.trim_end_matches(['.', ',']);
+
+    let decimal = match (number.rfind('.'), number.rfind(',')) {
+        (Some(dot), Some(comma)) => Some(dot.max(comma)),
+        (Some(last), None) | (None, Some(last)) => {
+            let separator = number.as_bytes()[last];
+            let repeated = number.bytes().filter(|&b| b == separator).count() > 1;
+            let grouping = repeated || number.len() - last - 1 == 3;
+            (!grouping).then_some(last)
+        }
+        (None, None) => None,
+    };
+
+    let digits: String = number
+        .char_indices()
+        .filter_map(|(i, c)| match c {
+            '.' | ',' if Some(i) == decimal => Some('.'),
+            '.' | ',' => None,
+            c => Some(c),
+        })
+        .collect();
+    let value: f64 = digits.parse().ok()?;
+    Some(if negative { -value } else { value })
+}
+
+/// A value for display, without float noise or trailing zeros
+pub fn format_value(value: f64) -> String {
+    let text = format!("{:.4}", value);
+    let text = text.trim_end_matches('0').trim_end_matches('.');
+    if text == "-0" {
+        String::from("0")
+    } else {
+        text.to_string()
+    }
+}
+
+/// A value read by a check, and the one before it
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Trend {
+    pub value: f64,
+    pub previous: Option<f64>,
+}
+
+impl Trend {
+    /// Difference from the previous value
+    pub fn change(&self) -> Option<f64> {
+        self.previous.map(|previous| self.value - previous)
+    }
+
+    /// The difference with its sign, like "+2" or "-0.5"
+    pub fn signed_change(&self) -> Option<String> {
+        let change = self.change()?;
+        Some(if change > 0.0 {
+            format!("+{}", format_value(change))
+        } else {
+            format_value(change)
+        })
+    }
+
+    /// Like "19.99 ▲ +2 (was 17.99)"
+    pub fn describe(&self) -> String {
+        let value = format_value(self.value);
+        let (Some(previous), Some(change)) = (self.previous, self.signed_change()) else {
+            return value;
+        };
+        if self.value > previous {
+            format!("{} ▲ {} (was {})", value, change, format_value(previous))
+        } else if self.value < previous {
+            format!("{} ▼ {} (was {})", value, change, format_value(previous))
+        } else {
+            format!("{} (unchanged)", value)
+        }
+    }
+}
+
+/// Values scaled to 0..=100 for a sparkline, lowest to highest (a flat
+/// series sits in the middle)
+pub fn sparkline(values: &[f64]) -> Vec<u64> {
+    let low = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let high = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    values
+        .iter()
+        .map(|value| {
+            if high > low {
+                // Keep the lowest value visible as a bar
+                (5.0 + (value - low) / (high - low) * 95.0).round() as u64
+            } else {
+                50
+            }
+        })
+        .collect()
+}
+
+/// The values a watcher's checks read, oldest first
+pub struct Series {
+    pub url: String,
+    pub pattern: Option<String>,
+    pub points: Vec<(DateTime<Utc>, f64)>,
+}
+
+/// The series in a watcher's check history
+pub fn series(watcher: &Watcher) -> Result<Series> {
+    let points = history::load(&watcher.id, usize::MAX)?
+        .into_iter()
+        .filter_map(|record| Some((record.checked_at, record.value?)))
+        .collect();
+    Ok(Series {
+        url: watcher.url.clone(),
+        pattern: watcher.track_value.clone(),
+        points,
+    })
+}
+
+/// File format of an export
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExportFormat {
+    Csv,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(input: &str) -> Result<Self> {
+        match input.trim().to_lowercase().as_str() {
+            "csv" => Ok(ExportFormat::Csv),
+            "json" => Ok(ExportFormat::Json),
+            other => anyhow::bail!("Unknown export format: {} (use csv or json)", other),
+        }
+    }
+
+    /// The format a file name's extension stands for
+    pub fn from_file_name(name: &str) -> Option<Self> {
+        let (_, extension) = name.rsplit_once('.')?;
+        Self::parse(extension).ok()
+    }
+
+    /// The series in this format: one `url,checked_at,value` row per value
+    /// (CSV), or one object per watcher (JSON)
+    pub fn write(self, series: &[Series]) -> Result<String> {
+        match self {
+            ExportFormat::Csv => {
+                let mut csv = String::from("url,checked_at,value\n");
+                for one in series {
+                    for (at, value) in &one.points {
+                        csv.push_str(&format!("{},{},{}\n", csv_field(&one.url), timestamp(at), value));
+                    }
+                }
+                Ok(csv)
+            }
+            ExportFormat::Json => {
+                let watchers: Vec<Value> = series
+                    .iter()
+                    .map(|one| {
+                        let values: Vec<Value> = one
+                            .points
+                            .iter()
+                            .map(|(at, value)| json!({ "checked_at": timestamp(at), "value": value }))
+                            .collect();
+                        json!({ "url": one.url, "pattern": one.pattern, "values": values })
+                    })
+                    .collect();
+                Ok(serde_json::to_string_pretty(&watchers)? + "\n")
+            }
+        }
+    }
+}
+
+/// A check's time in UTC, to the second (spreadsheets read this format)
+fn timestamp(at: &DateTime<Utc>) -> String {
+    at.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// A CSV field, quoted if it has to be
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
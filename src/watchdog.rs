@@ -0,0 +1,59 @@
+//! Watchdog for watcher tasks
+//!
+//! Each watcher task carries a `Heartbeat` and promises to report again
+//! before a deadline (a few check intervals, plus any wait for its active
+//! hours). The monitor polls the heartbeats and restarts tasks that miss
+//! their deadline, so one hung request or deadlock doesn't silently stop a
+//! watcher forever.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// How often the monitor looks at the heartbeats
+pub const POLL_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Progress reports of one watcher task, shared with the monitor
+#[derive(Debug, Clone)]
+pub struct Heartbeat {
+    inner: Arc<Mutex<Beat>>,
+}
+
+#[derive(Debug)]
+struct Beat {
+    /// Last report
+    at: Instant,
+    /// When the next report is due (None: no deadline)
+    deadline: Option<Instant>,
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self {
+            inner: Arc::new(Mutex::new(Beat {
+                at: Instant::now(),
+                deadline: None,
+            })),
+        }
+    }
+}
+
+impl Heartbeat {
+    /// Report progress and promise to report again within `allowance`
+    /// (None means no deadline, e.g. when the watchdog is disabled)
+    pub fn expect_within(&self, allowance: Option<Duration>) {
+        let now = Instant::now();
+        let mut beat = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        beat.at = now;
+        // An allowance too long to add is no deadline either
+        beat.deadline = allowance.and_then(|allowance| now.checked_add(allowance));
+    }
+
+    /// Time since the last report, if the deadline has passed
+    pub fn overdue(&self) -> Option<Duration> {
+        let beat = self.inner.lock().unwrap_or_else(|e| e.into_inner());
+        let now = Instant::now();
+        beat.deadline
+            .is_some_and(|deadline| now > deadline)
+            .then(|| now - beat.at)
+    }
+}
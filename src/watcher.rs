@@ -1,12 +1,23 @@
-/// Watcher data structure
-///
-/// Represents a single website being monitored
+//! Watcher data structure
+//!
+//! Represents a single website being monitored
 
-use chrono::{DateTime, Utc};
+use anyhow::Context;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, Utc};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use std::path::PathBuf;
 use std::time::Duration;
 
+use crate::diff::DiffOptions;
+use crate::extractor::ContentMode;
+use crate::fetcher::{RenderMode, TlsOptions};
+use crate::hook::HookSettings;
+use crate::impersonate::RequestProfile;
+use crate::matcher::Keyword;
+use crate::schedule::ActiveWindow;
+use crate::timezone::Zone;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Watcher {
     /// Unique identifier
@@ -15,8 +26,17 @@ pub struct Watcher {
     /// URL to monitor
     pub url: String,
 
-    /// Keywords to search for in new content
-    pub keywords: Vec<String>,
+    /// Name shown with the URL, e.g. the title of the feed it was imported from
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+
+    /// Keywords to search for in new content, each with its own settings
+    pub keywords: Vec<Keyword>,
+
+    /// Names of shared keyword sets (`Config::keyword_sets`) searched for
+    /// in addition to `keywords`
+    #[serde(default)]
+    pub keyword_sets: Vec<String>,
 
     /// How often to check (in seconds)
     #[serde(with = "duration_serde")]
@@ -25,16 +45,193 @@ pub struct Watcher {
     /// Whether this watcher is active
     pub enabled: bool,
 
+    /// Name of the matrix this watcher was expanded from (see `matrix`)
+    #[serde(default)]
+    pub matrix: Option<String>,
+
+    /// Put away: kept with its history, but hidden from the watcher list and
+    /// never checked until it's restored
+    #[serde(default)]
+    pub archived: bool,
+
     /// Last time this watcher was checked
     pub last_checked: Option<DateTime<Utc>>,
 
     /// Path to cached content
     pub cache_path: PathBuf,
+
+    /// Allow an interval below the configured minimum for public sites
+    #[serde(default)]
+    pub allow_short_interval: bool,
+
+    /// Only check during these hours/days (None means always)
+    #[serde(default)]
+    pub active_window: Option<ActiveWindow>,
+
+    /// Disable this watcher automatically after this time
+    #[serde(default)]
+    pub expires_at: Option<DateTime<Utc>>,
+
+    /// One-shot watcher: disable after the first match has been notified
+    #[serde(default)]
+    pub disable_after_match: bool,
+
+    /// Why the monitor disabled this watcher (cleared when re-enabled)
+    #[serde(default)]
+    pub disabled_reason: Option<String>,
+
+    /// Per-watcher page size cap in bytes, overriding `limits.max_page_size`
+    #[serde(default)]
+    pub max_page_size: Option<u64>,
+
+    /// Fetch the page this many times per check and only keep content
+    /// present in every fetch (filters A/B tests and per-request tokens).
+    /// 0 or 1 means a single fetch.
+    #[serde(default)]
+    pub consensus_fetches: u8,
+
+    /// Lightweight mode: check with a HEAD request first and only download
+    /// the page when its ETag/Last-Modified/Content-Length changed
+    #[serde(default)]
+    pub lightweight: bool,
+
+    /// Custom CA certificate / disabled verification for this watcher
+    #[serde(default)]
+    pub tls: TlsOptions,
+
+    /// Critical watchers keep their interval in battery saving mode
+    #[serde(default)]
+    pub critical: bool,
+
+    /// Seconds after a check during which the page isn't fetched again:
+    /// a manual check (or another monitor) in that window gets the result
+    /// of the last check instead
+    #[serde(default)]
+    pub min_recheck_gap: Option<u64>,
+
+    /// Checks that have failed in a row (reset by a successful check)
+    #[serde(default)]
+    pub consecutive_errors: u32,
+
+    /// Error of the most recent failed check (cleared by a successful check)
+    #[serde(default)]
+    pub last_error: Option<String>,
+
+    /// What blocked the checks (see `blocked`) while they keep getting
+    /// block pages; the user is notified when this gets set
+    #[serde(default)]
+    pub blocked: Option<String>,
+
+    /// Disable the watcher when a check gets a block page, until it's
+    /// re-enabled by hand
+    #[serde(default)]
+    pub pause_when_blocked: bool,
+
+    /// Alarm when a response takes longer than this (see `latency`)
+    #[serde(default)]
+    pub latency_threshold_ms: Option<u64>,
+
+    /// Alarm when a response takes this much longer than the average of
+    /// the recent checks
+    #[serde(default)]
+    pub latency_increase_percent: Option<u32>,
+
+    /// Why the site counts as slow, while its checks stay slow; the user is
+    /// notified when this gets set
+    #[serde(default)]
+    pub slow: Option<String>,
+
+    /// Permanent failures (unknown domain, 404/410) in a row, and when the
+    /// first of them happened; used for auto-disabling dead URLs
+    #[serde(default)]
+    pub permanent_failures: u32,
+    #[serde(default)]
+    pub permanent_failure_since: Option<DateTime<Utc>>,
+
+    /// Where the page permanently redirects to, until the user switches the
+    /// watcher to it or dismisses the suggestion
+    #[serde(default)]
+    pub moved_to: Option<String>,
+
+    /// A move the user dismissed (not suggested again)
+    #[serde(default)]
+    pub ignored_move: Option<String>,
+
+    /// The URL is an RSS/Atom feed (included in OPML exports)
+    #[serde(default)]
+    pub feed: bool,
+
+    /// Also notify when alerted keywords disappear from the page again
+    #[serde(default)]
+    pub notify_cleared: bool,
+
+    /// Also look for the keywords' translations on pages in another
+    /// language, and translate the alerts (see `translate`)
+    #[serde(default)]
+    pub translate: bool,
+
+    /// Title of the keyword alerts, with `{{name}}` filled in from the
+    /// regex keywords' named groups (see `notify::fill_title`)
+    #[serde(default)]
+    pub alert_title: Option<String>,
+
+    /// Pattern of a number to read from the page on every check: its first
+    /// group, or its whole match (see `values`)
+    #[serde(default)]
+    pub track_value: Option<String>,
+
+    /// Extra request headers, added to those of the watcher's domain
+    /// (see `domains`)
+    #[serde(default)]
+    pub headers: BTreeMap<String, String>,
+
+    /// Only watch the part of the page matching this CSS selector
+    /// (overrides the domain's)
+    #[serde(default)]
+    pub selector: Option<String>,
+
+    /// Parts of the page (CSS selectors) left out of what's watched, e.g.
+    /// relative timestamps or rotating ads
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+
+    /// Only watch the main content of the page, without navigation,
+    /// footers and banners (overrides the domain's; see `readability`)
+    #[serde(default)]
+    pub main_content: Option<bool>,
+
+    /// Watch the page's HTML ("raw_html") or its visible text ("text";
+    /// overrides the domain's; see `extractor`)
+    #[serde(default)]
+    pub content: Option<ContentMode>,
+
+    /// Command run when keywords match (see `hook`)
+    #[serde(default)]
+    pub hook: Option<HookSettings>,
+
+    /// How the page is fetched (overrides the domain's)
+    #[serde(default)]
+    pub render: Option<RenderMode>,
+
+    /// Browser whose headers the requests carry (overrides the domain's)
+    #[serde(default)]
+    pub request_profile: Option<RequestProfile>,
+
+    /// What a change is made of (lines, words or characters) and the diff
+    /// algorithm, for the diffs in alerts and the TUI
+    #[serde(default)]
+    pub diff: DiffOptions,
+
+    /// Read the URL as a stream of server-sent events, matching each event
+    /// as it arrives instead of checking the page (ws:// and wss:// URLs
+    /// are always streams)
+    #[serde(default)]
+    pub stream: bool,
 }
 
 impl Watcher {
     /// Create a new watcher
-    pub fn new(url: String, keywords: Vec<String>, check_interval: Duration) -> Self {
+    pub fn new(url: String, keywords: Vec<Keyword>, check_interval: Duration) -> Self {
         let id = uuid::Uuid::new_v4().to_string();
         // Store just the filename, cache module will resolve full path
         let cache_path = PathBuf::from(format!("{}.html", id));
@@ -42,12 +239,104 @@ impl Watcher {
         Self {
             id,
             url,
+            name: None,
             keywords,
+            keyword_sets: Vec::new(),
             check_interval,
             enabled: true,
+            matrix: None,
+            archived: false,
             last_checked: None,
             cache_path,
+            allow_short_interval: false,
+            active_window: None,
+            expires_at: None,
+            disable_after_match: false,
+            disabled_reason: None,
+            max_page_size: None,
+            consensus_fetches: 0,
+            lightweight: false,
+            tls: TlsOptions::default(),
+            critical: false,
+            min_recheck_gap: None,
+            consecutive_errors: 0,
+            last_error: None,
+            blocked: None,
+            pause_when_blocked: false,
+            latency_threshold_ms: None,
+            latency_increase_percent: None,
+            slow: None,
+            permanent_failures: 0,
+            permanent_failure_since: None,
+            moved_to: None,
+            ignored_move: None,
+            feed: false,
+            notify_cleared: false,
+            translate: false,
+            alert_title: None,
+            track_value: None,
+            headers: BTreeMap::new(),
+            selector: None,
+            ignore: Vec::new(),
+            main_content: None,
+            content: None,
+            hook: None,
+            render: None,
+            request_profile: None,
+            diff: DiffOptions::default(),
+            stream: false,
+        }
+    }
+
+    /// Whether this watcher matches messages of a stream (see `stream`)
+    pub fn is_stream(&self) -> bool {
+        self.stream || crate::stream::is_websocket_url(&self.url)
+    }
+
+    /// The keywords as typed in the TUI and on the command line, with
+    /// keyword sets as `@name`, like "sale, @gpu-models"
+    pub fn keyword_summary(&self) -> String {
+        self.keywords
+            .iter()
+            .map(|k| k.text.clone())
+            .chain(self.keyword_sets.iter().map(|name| format!("@{}", name)))
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    /// Use keyword sets that aren't already in use
+    pub fn merge_keyword_sets(&mut self, names: &[String]) -> usize {
+        let mut added = 0;
+        for name in names {
+            if !self.keyword_sets.contains(name) {
+                self.keyword_sets.push(name.clone());
+                added += 1;
+            }
         }
+        added
+    }
+
+    /// Add keywords that aren't already present (case-insensitive)
+    /// Returns the number of keywords added
+    pub fn merge_keywords(&mut self, keywords: &[Keyword]) -> usize {
+        let mut added = 0;
+        for keyword in keywords {
+            if !self.keywords.iter().any(|k| k.text.eq_ignore_ascii_case(&keyword.text)) {
+                self.keywords.push(keyword.clone());
+                added += 1;
+            }
+        }
+        added
+    }
+
+    /// Whether the monitor runs this watcher (enabled and not archived)
+    pub fn is_active(&self) -> bool {
+        self.enabled && !self.archived
+    }
+
+    /// Whether the watcher's expiration time has passed
+    pub fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|at| at <= Utc::now())
     }
 
     /// Get the full cache file path
@@ -57,8 +346,127 @@ impl Watcher {
     }
 }
 
+/// Validate and normalize a URL entered by the user
+///
+/// - Adds `https://` when no scheme is given
+/// - Rejects schemes other than http/https/http+unix/ws/wss
+/// - Drops the fragment (`#...`) and any trailing slash on the path,
+///   so equivalent URLs compare equal
+pub fn normalize_url(input: &str) -> anyhow::Result<String> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        anyhow::bail!("URL is empty");
+    }
+
+    // Assume https when the scheme is missing (e.g. "example.com/page")
+    let with_scheme = if trimmed.contains("://") {
+        trimmed.to_string()
+    } else {
+        format!("https://{}", trimmed)
+    };
+
+    let mut url = url::Url::parse(&with_scheme)
+        .map_err(|e| anyhow::anyhow!("Invalid URL '{}': {}", trimmed, e))?;
+
+    if url.scheme() == crate::unix_socket::SCHEME {
+        // Socket targets: the host is the percent-encoded socket path
+        crate::unix_socket::parse(url.as_str())?;
+    } else if !matches!(url.scheme(), "http" | "https" | "ws" | "wss") {
+        anyhow::bail!(
+            "Unsupported URL scheme '{}': only http://, https://, http+unix://, ws:// and wss:// are supported",
+            url.scheme()
+        );
+    }
+
+    if url.host_str().is_none_or(|h| h.is_empty()) {
+        anyhow::bail!("URL '{}' has no host", trimmed);
+    }
+
+    url.set_fragment(None);
+
+    // Remove trailing slash from non-root paths ("/page/" -> "/page")
+    let path = url.path().to_string();
+    if path.len() > 1 && path.ends_with('/') {
+        url.set_path(path.trim_end_matches('/'));
+    }
+
+    Ok(url.to_string())
+}
+
+/// Parse an expiration date such as "2025-12-31" or "2025-12-31 18:00",
+/// as wall-clock time in the given zone. A date without a time expires at
+/// the end of that day. An empty string means "never expires".
+pub fn parse_expiry(input: &str, zone: &Zone) -> anyhow::Result<Option<DateTime<Utc>>> {
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+
+    let wall_clock = NaiveDateTime::parse_from_str(trimmed, "%Y-%m-%d %H:%M")
+        .or_else(|_| {
+            NaiveDate::parse_from_str(trimmed, "%Y-%m-%d")
+                .map(|date| date.and_time(NaiveTime::from_hms_opt(23, 59, 59).unwrap_or_default()))
+        })
+        .map_err(|_| {
+            anyhow::anyhow!("Invalid expiration '{}' (use YYYY-MM-DD or YYYY-MM-DD HH:MM)", trimmed)
+        })?;
+
+    let expires_at = zone
+        .utc_from_wall_clock(wall_clock)
+        .with_context(|| format!("'{}' doesn't exist in time zone {}", trimmed, zone))?;
+
+    Ok(Some(expires_at))
+}
+
+/// Format an expiration time for display/editing (inverse of `parse_expiry`)
+pub fn format_expiry(expires_at: DateTime<Utc>, zone: &Zone) -> String {
+    zone.format(expires_at, "%Y-%m-%d %H:%M")
+}
+
+/// Largest allowed number of consensus fetches per check
+pub const MAX_CONSENSUS_FETCHES: u8 = 3;
+
+/// Quick interval presets offered in the TUI forms
+pub const INTERVAL_PRESETS: &[&str] = &["5m", "15m", "30m", "1h", "6h", "1d"];
+
+/// Longest interval accepted (longer ones are cut to it when read from a
+/// config): deadlines derived from intervals stay far from overflowing
+pub const MAX_INTERVAL: Duration = Duration::from_secs(366 * 24 * 60 * 60);
+
+/// Parse a check interval such as "90s", "15m", "2h" or "1d"
+///
+/// A bare number is treated as minutes, for compatibility with older input.
+pub fn parse_interval(input: &str) -> anyhow::Result<Duration> {
+    let trimmed = input.trim();
+
+    let interval = if let Ok(mins) = trimmed.parse::<u64>() {
+        let secs = mins
+            .checked_mul(60)
+            .ok_or_else(|| anyhow::anyhow!("Invalid interval '{}': too long", trimmed))?;
+        Duration::from_secs(secs)
+    } else {
+        humantime::parse_duration(trimmed).map_err(|e| {
+            anyhow::anyhow!("Invalid interval '{}': {} (try 90s, 15m, 2h or 1d)", trimmed, e)
+        })?
+    };
+
+    if interval.as_secs() == 0 {
+        anyhow::bail!("Interval must be at least 1 second");
+    }
+    if interval > MAX_INTERVAL {
+        anyhow::bail!("Invalid interval '{}': longer than a year", trimmed);
+    }
+
+    Ok(interval)
+}
+
+/// Format an interval the same way it can be typed (e.g. "15m", "2h 30m")
+pub fn format_interval(interval: Duration) -> String {
+    humantime::format_duration(Duration::from_secs(interval.as_secs())).to_string()
+}
+
 // Helper module for serializing Duration
-mod duration_serde {
+pub mod duration_serde {
     use serde::{Deserialize, Deserializer, Serializer};
     use std::time::Duration;
 
@@ -74,6 +482,45 @@ mod duration_serde {
         D: Deserializer<'de>,
     {
         let secs = u64::deserialize(deserializer)?;
-        Ok(Duration::from_secs(secs))
+        Ok(Duration::from_secs(secs).min(super::MAX_INTERVAL))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn urls_are_normalized() {
+        assert_eq!(normalize_url("example.com/page/").unwrap(), "https://example.com/page");
+        assert_eq!(normalize_url(" https://Example.COM/a#reviews ").unwrap(), "https://example.com/a");
+        assert_eq!(normalize_url("https://example.com/").unwrap(), "https://example.com/");
+        assert_eq!(normalize_url("wss://stream.example.com/updates").unwrap(), "wss://stream.example.com/updates");
+        assert!(normalize_url("ftp://example.com/file").is_err());
+        assert!(normalize_url("https://").is_err());
+        assert!(normalize_url("  ").is_err());
+    }
+
+    #[test]
+    fn intervals_are_minutes_or_durations() {
+        assert_eq!(parse_interval("15").unwrap(), Duration::from_secs(15 * 60));
+        assert_eq!(parse_interval("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_interval(" 2h 30m ").unwrap(), Duration::from_secs(9000));
+        assert!(parse_interval("0").is_err());
+        assert!(parse_interval("0s").is_err());
+        assert!(parse_interval("soon").is_err());
+        assert!(parse_interval("999999999999999999").is_err());
+        assert!(parse_interval("999999999999999999m").is_err());
+        assert!(parse_interval("999999999999999999h").is_err());
+        assert!(parse_interval("999999999999999999d").is_err());
+        // Parses, but would overflow the deadlines derived from it
+        assert!(parse_interval("300000000000y").is_err());
+        assert!(parse_interval("2y").is_err());
+        assert_eq!(parse_interval("1y").unwrap(), humantime::parse_duration("1y").unwrap());
+        let mut saved = serde_json::to_value(Watcher::new("https://example.com/".into(), Vec::new(), MAX_INTERVAL)).unwrap();
+        saved["check_interval"] = serde_json::json!(u64::MAX);
+        let watcher: Watcher = serde_json::from_value(saved).unwrap();
+        assert_eq!(watcher.check_interval, MAX_INTERVAL);
+        assert_eq!(format_interval(Duration::from_secs(9000)), "2h 30m");
     }
 }
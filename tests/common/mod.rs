@@ -0,0 +1,283 @@
+//! Shared pieces of the integration tests: a local HTTP server whose pages
+//! change on demand, and a notifier that records the alerts it receives
+
+use futures_util::future::BoxFuture;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server, StatusCode};
+use std::collections::HashMap;
+use std::convert::Infallible;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex, Once, OnceLock};
+use std::time::Duration;
+
+use web_watcher_alert::{
+    config::{self, Config, NotificationSettings},
+    events::EventLog,
+    markup::Format,
+    matcher::Keyword,
+    monitor::{CheckReport, Monitor},
+    notify::{self, Message, Notifier},
+    watcher::Watcher,
+};
+
+/// A page served by the test server
+struct Page {
+    status: StatusCode,
+    content_type: &'static str,
+    body: String,
+    requests: usize,
+}
+
+type Pages = Arc<Mutex<HashMap<String, Page>>>;
+
+/// HTTP server on a free local port, running until the test's runtime ends.
+/// Unknown paths get a 404.
+pub struct TestServer {
+    addr: SocketAddr,
+    pages: Pages,
+}
+
+impl TestServer {
+    pub async fn start() -> Self {
+        let pages: Pages = Arc::default();
+        let shared = Arc::clone(&pages);
+        let make_service = make_service_fn(move |_| {
+            let pages = Arc::clone(&shared);
+            async move {
+                Ok::<_, Infallible>(service_fn(move |request: Request<Body>| {
+                    let response = respond(&pages, request.uri().path());
+                    async move { Ok::<_, Infallible>(response) }
+                }))
+            }
+        });
+
+        let server = Server::bind(&SocketAddr::from(([127, 0, 0, 1], 0))).serve(make_service);
+        let addr = server.local_addr();
+        tokio::spawn(server);
+        Self { addr, pages }
+    }
+
+    /// URL of a path on this server
+    pub fn url(&self, path: &str) -> String {
+        format!("http://{}{}", self.addr, path)
+    }
+
+    /// Serve `body` at `path` from now on
+    pub fn serve(&self, path: &str, body: &str) {
+        self.set(path, StatusCode::OK, "text/html", body);
+    }
+
+    /// Serve `events` at `path` as a server-sent event stream, which ends
+    /// after them
+    pub fn serve_events(&self, path: &str, events: &str) {
+        self.set(path, StatusCode::OK, "text/event-stream", events);
+    }
+
+    /// Answer requests for `path` with an error status from now on
+    pub fn fail(&self, path: &str, status: StatusCode) {
+        self.set(path, status, "text/plain", "error");
+    }
+
+    /// How many times `path` was requested
+    pub fn requests(&self, path: &str) -> usize {
+        self.pages.lock().unwrap().get(path).map_or(0, |page| page.requests)
+    }
+
+    fn set(&self, path: &str, status: StatusCode, content_type: &'static str, body: &str) {
+        let mut pages = self.pages.lock().unwrap();
+        let page = pages.entry(path.to_string()).or_insert(Page {
+            status,
+            content_type,
+            body: String::new(),
+            requests: 0,
+        });
+        page.status = status;
+        page.content_type = content_type;
+        page.body = body.to_string();
+    }
+}
+
+fn respond(pages: &Pages, path: &str) -> Response<Body> {
+    let mut pages = pages.lock().unwrap();
+    let Some(page) = pages.get_mut(path) else {
+        let mut response = Response::new(Body::from("not found"));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    };
+
+    page.requests += 1;
+    let mut response = Response::new(Body::from(page.body.clone()));
+    *response.status_mut() = page.status;
+    response
+        .headers_mut()
+        .insert("Content-Type", hyper::header::HeaderValue::from_static(page.content_type));
+    response
+}
+
+/// Records every alert (alerts of all tests in the binary end up here, so
+/// look them up by URL)
+#[derive(Default)]
+pub struct Recorder {
+    format: Format,
+    messages: Mutex<Vec<Message>>,
+}
+
+impl Notifier for Recorder {
+    fn name(&self) -> &str {
+        match self.format {
+            Format::Plain => "recorder",
+            Format::Markdown => "markdown-recorder",
+            Format::Html => "html-recorder",
+        }
+    }
+
+    fn format(&self) -> Format {
+        self.format
+    }
+
+    fn send<'a>(&'a self, message: &'a Message) -> BoxFuture<'a, anyhow::Result<()>> {
+        self.messages.lock().unwrap().push(message.clone());
+        Box::pin(async { Ok(()) })
+    }
+}
+
+static RECORDER: OnceLock<Arc<Recorder>> = OnceLock::new();
+static MARKUP_RECORDER: OnceLock<Arc<Recorder>> = OnceLock::new();
+static HTML_RECORDER: OnceLock<Arc<Recorder>> = OnceLock::new();
+
+/// Alerts sent about `url` so far
+pub fn alerts_for(url: &str) -> Vec<Message> {
+    recorded(&RECORDER, url)
+}
+
+/// Alerts sent about `url` so far, as composed for a notifier that
+/// supports markup
+pub fn markup_alerts_for(url: &str) -> Vec<Message> {
+    recorded(&MARKUP_RECORDER, url)
+}
+
+/// Alerts sent about `url` so far, as composed for an HTML notifier (an
+/// email gateway)
+pub fn html_alerts_for(url: &str) -> Vec<Message> {
+    recorded(&HTML_RECORDER, url)
+}
+
+fn recorded(recorder: &OnceLock<Arc<Recorder>>, url: &str) -> Vec<Message> {
+    let recorder = recorder.get().expect("setup() wasn't called");
+    let messages = recorder.messages.lock().unwrap();
+    messages.iter().filter(|m| m.url == url).cloned().collect()
+}
+
+/// Point config, data and cache at a directory of this test run, and
+/// register the recorders. Call at the start of every test.
+pub fn setup() {
+    static SETUP: Once = Once::new();
+    SETUP.call_once(|| {
+        let dir = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("run-{}", std::process::id()));
+        std::env::set_var(config::CONFIG_ENV, dir.join("config.json"));
+        std::env::set_var(config::DATA_DIR_ENV, dir.join("data"));
+        std::env::set_var(config::CACHE_DIR_ENV, dir.join("cache"));
+        std::env::remove_var(config::WEBHOOK_ENV);
+
+        for (format, slot) in [
+            (Format::Plain, &RECORDER),
+            (Format::Markdown, &MARKUP_RECORDER),
+            (Format::Html, &HTML_RECORDER),
+        ] {
+            let recorder = Arc::new(Recorder {
+                format,
+                ..Recorder::default()
+            });
+            notify::register(recorder.clone());
+            let _ = slot.set(recorder);
+        }
+    });
+}
+
+/// A watcher of `url` for the given keywords
+pub fn watcher(url: &str, keywords: &[&str]) -> Watcher {
+    let keywords = keywords.iter().map(|k| Keyword::new(k.to_string())).collect();
+    Watcher::new(url.to_string(), keywords, Duration::from_secs(60))
+}
+
+/// A monitor of `watchers` that alerts only through registered notifiers
+/// and never writes the config file
+pub fn monitor(watchers: Vec<Watcher>) -> Monitor {
+    monitor_with(
+        watchers,
+        NotificationSettings {
+            desktop: false,
+            ..NotificationSettings::default()
+        },
+    )
+}
+
+/// A monitor of `watchers` with these notification settings, which never
+/// writes the config file
+pub fn monitor_with(watchers: Vec<Watcher>, notifications: NotificationSettings) -> Monitor {
+    let config = Config {
+        watchers,
+        notifications,
+        read_only: true,
+        ..Config::default()
+    };
+
+    let (log, _) = EventLog::channel(config.timezone);
+    Monitor::with_event_log(config, log)
+}
+
+/// Check one watcher of `monitor` now
+pub async fn check(monitor: &Monitor, watcher_id: &str) -> CheckReport {
+    let mut reports = monitor.check_once(&[watcher_id.to_string()]).await;
+    reports.remove(0)
+}
+
+/// A page on a test server of its own, watched by a monitor of only that
+/// watcher
+pub struct Site {
+    pub server: TestServer,
+    pub url: String,
+    pub id: String,
+    pub monitor: Monitor,
+    path: String,
+}
+
+impl Site {
+    /// Serve `body` at `path` and watch it for `keywords`
+    pub async fn watch(path: &str, body: &str, keywords: &[&str]) -> Self {
+        Self::watch_with(path, body, keywords, |_| {}).await
+    }
+
+    /// Like `watch`, with the watcher's settings changed by `configure`
+    pub async fn watch_with(path: &str, body: &str, keywords: &[&str], configure: impl FnOnce(&mut Watcher)) -> Self {
+        setup();
+        let server = TestServer::start().await;
+        server.serve(path, body);
+        let url = server.url(path);
+        let mut watcher = watcher(&url, keywords);
+        configure(&mut watcher);
+        let id = watcher.id.clone();
+        Self {
+            server,
+            url,
+            id,
+            monitor: monitor(vec![watcher]),
+            path: path.to_string(),
+        }
+    }
+
+    /// Serve `body` from now on
+    pub fn serve(&self, body: &str) {
+        self.server.serve(&self.path, body);
+    }
+
+    /// Check the watcher now
+    pub async fn check(&self) -> CheckReport {
+        check(&self.monitor, &self.id).await
+    }
+
+    /// Alerts sent about the page so far
+    pub fn alerts(&self) -> Vec<Message> {
+        alerts_for(&self.url)
+    }
+}
@@ -0,0 +1,731 @@
+//! End-to-end checks: fetch from the test server, compare with the cache,
+//! match keywords and notify
+
+mod common;
+
+use hyper::StatusCode;
+use std::time::{Duration, Instant};
+use web_watcher_alert::{
+    calendar,
+    config::{CompanionSettings, Config, EncryptionSettings, NotificationSettings},
+    domains::SiteSettings,
+    events::{EventLog, Level, MonitorEvent},
+    extractor::ContentMode,
+    history,
+    hook::HookSettings,
+    logfiles::{self, LogSettings},
+    match_test::{Session, Step},
+    matcher::Keyword,
+    monitor::Monitor,
+    notify::{Channel, Notifiers},
+    outbox, review,
+    tasks::{TaskSettings, TaskTarget},
+    timezone::Zone,
+};
+
+use common::{alerts_for, check, html_alerts_for, markup_alerts_for, monitor, monitor_with, setup, watcher, Site, TestServer};
+
+#[tokio::test]
+async fn alerts_when_a_keyword_appears() {
+    let site = Site::watch("/shop", "<p>Sold out</p>", &["restock"]).await;
+
+    let report = site.check().await;
+    assert!(report.error.is_none(), "{:?}", report.error);
+    assert!(report.matched_keywords.is_empty());
+    assert!(site.alerts().is_empty());
+
+    site.serve("<p>Restock: 3 left</p>");
+    let report = site.check().await;
+    assert!(report.changed);
+    assert_eq!(report.matched_keywords, ["restock"]);
+
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].keywords, ["restock"]);
+    assert!(alerts[0].body.contains("Restock: 3 left"), "{}", alerts[0].body);
+    assert_eq!(site.server.requests("/shop"), 2);
+}
+
+#[tokio::test]
+async fn matches_of_several_keywords_make_one_alert() {
+    let site = Site::watch("/deals", "<p>Nothing yet</p>", &["sale", "restock", "coupon"]).await;
+    site.check().await;
+
+    site.serve("<p>Restock of the blue ones</p><p>Spring sale</p><p>Another sale on shoes</p>");
+    site.check().await;
+
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].title, "Web Watcher Alert: sale, restock found!");
+    let body = &alerts[0].body;
+    assert!(body.contains("Found 3 matches on"), "{}", body);
+    // Each keyword once, with the context of its first match
+    assert_eq!(body.matches("• sale: ").count(), 1, "{}", body);
+    assert_eq!(body.matches("• restock: ").count(), 1, "{}", body);
+    assert!(!body.contains("coupon"), "{}", body);
+}
+
+#[tokio::test]
+async fn unchanged_page_is_not_reported() {
+    let site = Site::watch("/status", "All systems operational", &["outage"]).await;
+
+    assert!(site.check().await.changed);
+    let report = site.check().await;
+    assert!(!report.changed);
+    assert!(report.error.is_none());
+}
+
+#[tokio::test]
+async fn change_without_keywords_does_not_alert() {
+    let site = Site::watch("/news", "<p>Monday</p>", &["recall"]).await;
+
+    site.check().await;
+    site.serve("<p>Tuesday</p>");
+    let report = site.check().await;
+    assert!(report.changed);
+    assert!(report.matched_keywords.is_empty());
+    assert!(site.alerts().is_empty());
+}
+
+#[tokio::test]
+async fn same_match_is_alerted_once() {
+    let page = format!("<p>Big sale</p>{}", "<p>-</p>".repeat(30));
+    let site = Site::watch("/deals", &page, &["sale"]).await;
+
+    assert_eq!(site.check().await.matched_keywords, ["sale"]);
+
+    // The page changes away from the match, which was already alerted
+    site.serve(&format!("{}<p>Ends Friday</p>", page));
+    let report = site.check().await;
+    assert!(report.changed);
+    assert!(report.matched_keywords.is_empty());
+    assert_eq!(site.alerts().len(), 1);
+}
+
+#[tokio::test]
+async fn cleared_keywords_are_notified() {
+    let site = Site::watch_with("/banner", "<p>Scheduled maintenance tonight</p>", &["maintenance"], |watcher| {
+        watcher.notify_cleared = true;
+    })
+    .await;
+
+    site.check().await;
+    site.serve("<p>All good</p>");
+    site.check().await;
+
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 2);
+    assert!(alerts[1].title.contains("maintenance cleared"), "{}", alerts[1].title);
+}
+
+#[tokio::test]
+async fn markup_notifiers_get_markdown() {
+    let site = Site::watch("/promo", "<p>Flash sale on *everything*</p>", &["flash sale"]).await;
+
+    site.check().await;
+
+    let plain = &site.alerts()[0];
+    assert!(plain.body.contains("• flash sale: "), "{}", plain.body);
+    let markup = &markup_alerts_for(&site.url)[0];
+    assert!(markup.body.contains("• **flash sale**: "), "{}", markup.body);
+    assert!(markup.body.contains("\\*everything\\*"), "{}", markup.body);
+}
+
+#[tokio::test]
+async fn html_alerts_show_the_change_side_by_side() {
+    let site = Site::watch(
+        "/tickets",
+        "<h1>Tour</h1>\n<p>Berlin: sold out</p>\n<p>Paris: sold out</p>",
+        &["available"],
+    )
+    .await;
+
+    site.check().await;
+    site.serve("<h1>Tour</h1>\n<p>Berlin: available</p>\n<p>Paris: sold out</p>");
+    site.check().await;
+
+    let html = &html_alerts_for(&site.url)[0];
+    assert!(html.body.contains("<table"), "{}", html.body);
+    assert!(html.body.contains("&lt;p&gt;Berlin: <span style=\"background:#ffb3ad\">sold out"), "{}", html.body);
+    assert!(html.body.contains("<span style=\"background:#abf2bc\">available"), "{}", html.body);
+    // Too long for the other notifiers
+    let plain = &site.alerts()[0];
+    assert!(!plain.body.contains("Berlin: sold out"), "{}", plain.body);
+}
+
+#[tokio::test]
+async fn minimized_data_keeps_no_copy_of_the_page() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/private", "<p>Quarterly   report</p>");
+    let url = server.url("/private");
+    let watcher = watcher(&url, &["leak"]);
+    let (id, cache_path) = (watcher.id.clone(), watcher.full_cache_path().unwrap());
+    let config = Config {
+        watchers: vec![watcher],
+        notifications: NotificationSettings {
+            desktop: false,
+            ..NotificationSettings::default()
+        },
+        minimize_data: true,
+        read_only: true,
+        ..Config::default()
+    };
+    let (log, _) = EventLog::channel(config.timezone);
+    let monitor = Monitor::with_event_log(config, log);
+
+    assert!(check(&monitor, &id).await.changed);
+    assert!(!cache_path.exists());
+
+    // Compared like a cached copy: whitespace doesn't count
+    server.serve("/private", "  <p>Quarterly   report</p>\n");
+    assert!(!check(&monitor, &id).await.changed);
+
+    server.serve("/private", "<p>Quarterly report: leak found</p>");
+    let report = check(&monitor, &id).await;
+    assert_eq!(report.matched_keywords, ["leak"]);
+    assert!(!cache_path.exists());
+    let alerts = alerts_for(&url);
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].body.contains("leak found"), "{}", alerts[0].body);
+}
+
+#[tokio::test]
+async fn changes_without_matches_wait_for_review() {
+    let site = Site::watch("/news", "<p>Nothing new</p>", &["recall"]).await;
+    let queued = || -> Vec<review::Item> {
+        review::load().unwrap().into_iter().filter(|item| item.watcher_id == site.id).collect()
+    };
+
+    site.check().await;
+    assert!(queued().is_empty());
+
+    site.serve("<p>Nothing new</p><p>Price cut on model X</p>");
+    assert!(site.check().await.changed);
+    let items = queued();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].added, ["<p>Nothing new</p><p>Price cut on model X</p>"]);
+
+    // Changes with matches are alerted instead
+    site.serve("<p>Product recall</p>");
+    assert_eq!(site.check().await.matched_keywords, ["recall"]);
+    assert_eq!(queued().len(), 1);
+
+    review::dismiss(&[items[0].id.clone()], &EncryptionSettings::default()).unwrap();
+    assert!(queued().is_empty());
+}
+
+#[tokio::test]
+async fn keyword_alerts_become_tasks() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/drop", "<p>Coming soon</p>");
+    let url = server.url("/drop");
+    let mut watcher = watcher(&url, &["available", "preorder"]);
+    watcher.keywords[1].channels = Some(vec![Channel::Tasks]);
+    let id = watcher.id.clone();
+    let todo = std::path::Path::new(env!("CARGO_TARGET_TMPDIR")).join(format!("todo-{}.txt", std::process::id()));
+    let monitor = monitor_with(
+        vec![watcher],
+        NotificationSettings {
+            desktop: false,
+            tasks: TaskSettings {
+                target: Some(TaskTarget::Todotxt),
+                path: Some(todo.display().to_string()),
+                list: None,
+            },
+            ..NotificationSettings::default()
+        },
+    );
+
+    check(&monitor, &id).await;
+    server.serve("/drop", "<p>Preorder now</p>");
+    assert_eq!(check(&monitor, &id).await.matched_keywords, ["preorder"]);
+
+    // Only the task: the keyword is limited to it
+    assert!(alerts_for(&url).is_empty());
+    let tasks = std::fs::read_to_string(&todo).unwrap();
+    let lines: Vec<&str> = tasks.lines().collect();
+    assert_eq!(lines.len(), 1, "{}", tasks);
+    assert!(lines[0].contains("preorder found!"), "{}", tasks);
+    assert!(lines[0].ends_with(&format!("{} +webwatcher", url)), "{}", tasks);
+}
+
+#[tokio::test]
+async fn channel_limited_keywords_skip_other_notifiers() {
+    let site = Site::watch_with("/outage", "<p>Partial outage</p>", &["outage"], |watcher| {
+        watcher.keywords[0].channels = Some(vec![Channel::Webhook]);
+    })
+    .await;
+
+    site.check().await;
+    assert!(site.alerts().is_empty());
+    assert!(markup_alerts_for(&site.url).is_empty());
+}
+
+#[tokio::test]
+async fn failed_fetch_is_reported_without_alert() {
+    let site = Site::watch("/down", "", &["error"]).await;
+    site.server.fail("/down", StatusCode::INTERNAL_SERVER_ERROR);
+
+    let report = site.check().await;
+    assert!(report.error.is_some());
+    assert!(!report.changed);
+    assert!(site.alerts().is_empty());
+}
+
+#[tokio::test]
+async fn check_outcomes_are_published() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/feed", "<p>New release: v2</p>");
+    let watcher = watcher(&server.url("/feed"), &["release"]);
+    let id = watcher.id.clone();
+    let monitor = monitor(vec![watcher]);
+    let mut events = monitor.subscribe();
+
+    check(&monitor, &id).await;
+
+    match events.try_recv() {
+        Ok(MonitorEvent::MatchFound { watcher_id, keywords, .. }) => {
+            assert_eq!(watcher_id, id);
+            assert_eq!(keywords, ["release"]);
+        }
+        other => panic!("expected a match, got {:?}", other),
+    }
+    match events.try_recv() {
+        Ok(MonitorEvent::CheckCompleted { watcher_id, changed, .. }) => {
+            assert_eq!(watcher_id, id);
+            assert!(changed);
+        }
+        other => panic!("expected a completed check, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn stream_messages_alert_as_they_arrive() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve_events("/events", ": hello\n\nid: 1\ndata: all fine\n\nid: 2\ndata: service\ndata: degraded\n\n");
+    let url = server.url("/events");
+    let mut watcher = watcher(&url, &["degraded"]);
+    watcher.stream = true;
+    let monitor = monitor(vec![watcher]);
+    tokio::spawn(async move { monitor.start().await });
+
+    let deadline = Instant::now() + Duration::from_secs(10);
+    while alerts_for(&url).is_empty() && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+
+    let alerts = alerts_for(&url);
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].body.contains("service degraded"), "{}", alerts[0].body);
+}
+
+#[tokio::test]
+async fn block_pages_are_notified_once_instead_of_alerting() {
+    let challenge = "<title>Just a moment...</title><script src=\"/cdn-cgi/challenge-platform/h/g/orchestrate/jsch/v1\"></script>";
+    let site = Site::watch("/tickets", challenge, &["moment"]).await;
+
+    for _ in 0..2 {
+        let report = site.check().await;
+        assert!(report.error.as_deref().is_some_and(|e| e.contains("Cloudflare challenge")), "{:?}", report.error);
+        assert!(!report.changed);
+    }
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].title.contains("blocked"), "{}", alerts[0].title);
+
+    site.serve("<p>The moment you've been waiting for</p>");
+    let report = site.check().await;
+    assert!(report.error.is_none(), "{:?}", report.error);
+    assert_eq!(report.matched_keywords, ["moment"]);
+}
+
+#[tokio::test]
+async fn named_groups_fill_the_alert_title() {
+    let site = Site::watch_with("/gpu", "<p>RTX 5080</p><p>Price: $999</p>", &[], |watcher| {
+        watcher.keywords = vec![Keyword {
+            is_regex: true,
+            ..Keyword::new(String::from(r"price: (?P<price>\$\d+)"))
+        }];
+        watcher.alert_title = Some(String::from("GPU now {{price}} ({{currency}})"));
+    })
+    .await;
+
+    site.check().await;
+
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].title, "GPU now $999 ({{currency}})");
+    assert_eq!(alerts[0].fields.get("price").map(String::as_str), Some("$999"));
+}
+
+#[tokio::test]
+async fn tracked_values_show_their_trend_in_alerts() {
+    let site = Site::watch_with("/gpu", "<p>Price: $1,049.00</p><p>Sold out</p>", &["in stock"], |watcher| {
+        watcher.track_value = Some(String::from(r"price: \$([\d,.]+)"));
+        watcher.alert_title = Some(String::from("GPU {{value}} ({{change}})"));
+    })
+    .await;
+
+    site.check().await;
+    assert!(site.alerts().is_empty());
+
+    site.serve("<p>Price: $999.50</p><p>In stock</p>");
+    site.check().await;
+
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 1);
+    assert_eq!(alerts[0].title, "GPU 999.5 (-49.5)");
+    assert!(alerts[0].body.contains("Value: 999.5 ▼ -49.5 (was 1049)"), "{}", alerts[0].body);
+}
+
+#[tokio::test]
+async fn undelivered_alerts_wait_in_the_outbox() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/tickets", "<p>Tickets on sale</p>");
+    server.fail("/hook", StatusCode::INTERNAL_SERVER_ERROR);
+    let url = server.url("/tickets");
+    let watcher = watcher(&url, &["on sale"]);
+    let id = watcher.id.clone();
+    let settings = NotificationSettings {
+        desktop: false,
+        webhook_url: Some(server.url("/hook")),
+        ..NotificationSettings::default()
+    };
+    let monitor = monitor_with(vec![watcher], settings);
+
+    // The webhook failing doesn't fail the check: the alert is kept
+    let report = check(&monitor, &id).await;
+    assert!(report.error.is_none(), "{:?}", report.error);
+    assert_eq!(report.matched_keywords, ["on sale"]);
+    assert_eq!(server.requests("/hook"), 1);
+
+    let queued: Vec<_> = outbox::load().unwrap().into_iter().filter(|entry| entry.message.url == url).collect();
+    assert_eq!(queued.len(), 1);
+    assert_eq!(queued[0].notifier, "webhook");
+    assert!(queued[0].last_error.contains("500"), "{}", queued[0].last_error);
+    assert!(queued[0].next_attempt > queued[0].queued_at);
+}
+
+#[tokio::test]
+async fn companion_endpoint_adds_a_watcher_for_the_selection() {
+    setup();
+    let port = std::net::TcpListener::bind("127.0.0.1:0").unwrap().local_addr().unwrap().port();
+    let config = Config {
+        companion: CompanionSettings {
+            enabled: true,
+            port,
+            token: Some(String::from("secret")),
+        },
+        ..Config::default()
+    };
+    let (log, _) = EventLog::channel(config.timezone);
+    let monitor = Monitor::with_event_log(config, log);
+    let task = tokio::spawn(async move { monitor.start().await });
+
+    let client = reqwest::Client::new();
+    let endpoint = format!("http://127.0.0.1:{}", port);
+    let started = Instant::now();
+    while client.get(format!("{}/status", endpoint)).send().await.is_err() {
+        assert!(started.elapsed() < Duration::from_secs(5), "the endpoint didn't start");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+
+    let body = r#"{"url": "https://example.com/item", "selection": "  Back in\nstock  "}"#;
+    let refused = client.post(format!("{}/watch", endpoint)).body(body).send().await.unwrap();
+    assert_eq!(refused.status(), StatusCode::UNAUTHORIZED);
+
+    let added = client
+        .post(format!("{}/watch", endpoint))
+        .bearer_auth("secret")
+        .body(body)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(added.status(), StatusCode::CREATED);
+    task.abort();
+
+    let saved = Config::load().unwrap();
+    let watcher = &saved.watchers[saved.find_watcher_by_url("https://example.com/item").unwrap()];
+    let keywords: Vec<&str> = watcher.keywords.iter().map(|k| k.text.as_str()).collect();
+    assert_eq!(keywords, ["Back in", "stock"]);
+}
+
+#[test]
+fn calendar_events_become_quiet_periods() {
+    let ics = "BEGIN:VCALENDAR\r\n\
+        BEGIN:VEVENT\r\n\
+        UID:standup\r\n\
+        SUMMARY:Standup\r\n\
+        DTSTART;TZID=Europe/Berlin:20261005T090000\r\n\
+        DTEND;TZID=Europe/Berlin:20261005T093000\r\n\
+        RRULE:FREQ=WEEKLY;BYDAY=MO,WE\r\n\
+        EXDATE;TZID=Europe/Berlin:20261014T090000\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Vaca\r\n tion\r\n\
+        DTSTART;VALUE=DATE:20261016\r\n\
+        DTEND;VALUE=DATE:20261018\r\n\
+        BEGIN:VALARM\r\n\
+        SUMMARY:Reminder\r\n\
+        END:VALARM\r\n\
+        END:VEVENT\r\n\
+        BEGIN:VEVENT\r\n\
+        SUMMARY:Called off\r\n\
+        DTSTART:20261013T120000Z\r\n\
+        DURATION:PT1H\r\n\
+        STATUS:CANCELLED\r\n\
+        END:VEVENT\r\n\
+        END:VCALENDAR\r\n";
+    let time = |text: &str| text.parse::<chrono::DateTime<chrono::Utc>>().unwrap();
+    let zone = Zone::parse("UTC").unwrap();
+    let events = calendar::parse(ics, zone, time("2026-10-12T00:00:00Z"), time("2026-10-19T00:00:00Z"));
+
+    let found: Vec<_> = events.iter().map(|e| (e.summary.as_str(), e.start, e.end)).collect();
+    assert_eq!(
+        found,
+        [
+            ("Standup", time("2026-10-12T07:00:00Z"), time("2026-10-12T07:30:00Z")),
+            ("Vacation", time("2026-10-16T00:00:00Z"), time("2026-10-18T00:00:00Z")),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn main_content_ignores_boilerplate_changes() {
+    let page = |menu: &str, text: &str| {
+        format!(
+            "<nav><a href=\"/\">{}</a></nav><div id=\"cookie-consent\">We use cookies, like every site, accept them</div>\
+             <article><p>{}, shipping within two days of your order.</p></article>",
+            menu, text
+        )
+    };
+    let site = Site::watch_with("/item", &page("Home", "Sold out"), &["in stock", "cookies"], |watcher| {
+        watcher.main_content = Some(true);
+    })
+    .await;
+
+    assert!(site.check().await.changed);
+    site.serve(&page("Home | Sale", "Sold out"));
+    assert!(!site.check().await.changed);
+
+    site.serve(&page("Home | Sale", "Back in stock"));
+    let report = site.check().await;
+    assert!(report.changed);
+    assert_eq!(report.matched_keywords, ["in stock"]);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn hooks_are_killed_after_their_timeout() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/drop", "<p>Restock today</p>");
+    let mut watcher = watcher(&server.url("/drop"), &["restock"]);
+    watcher.hook = Some(HookSettings {
+        timeout: 1,
+        ..HookSettings::new(String::from("echo \"$MATCHED_KEYWORDS\"; sleep 30"))
+    });
+    let id = watcher.id.clone();
+    let monitor = monitor(vec![watcher]);
+
+    let started = Instant::now();
+    let report = check(&monitor, &id).await;
+    assert_eq!(report.matched_keywords, ["restock"]);
+    assert!(started.elapsed() < Duration::from_secs(10));
+
+    let records = history::load(&id, 1).unwrap();
+    let run = records[0].hook.as_ref().expect("the hook's run is in the history");
+    assert!(run.timed_out);
+    assert_eq!(run.stdout, "restock\n");
+}
+
+#[tokio::test]
+async fn notify_test_reports_each_channel_without_queueing() {
+    setup();
+    let server = TestServer::start().await;
+    server.fail("/test-hook", StatusCode::BAD_GATEWAY);
+    let settings = NotificationSettings {
+        desktop: false,
+        webhook_url: Some(server.url("/test-hook")),
+        ..NotificationSettings::default()
+    };
+
+    let results = Notifiers::local(&settings).send_test(None).await;
+    let webhook = results.iter().find(|(name, _)| name == "webhook").unwrap();
+    assert!(webhook.1.as_ref().unwrap_err().to_string().contains("502"));
+    assert!(results.iter().filter(|(name, _)| name != "webhook").all(|(_, result)| result.is_ok()));
+    assert_eq!(server.requests("/test-hook"), 1);
+
+    let url = "https://example.com/web-watcher-alert-test";
+    assert_eq!(alerts_for(url).len(), 1);
+    assert_eq!(alerts_for(url)[0].title, "Web Watcher Alert: test alert");
+    // A failed test alert isn't retried later
+    assert!(outbox::load().unwrap().iter().all(|entry| entry.message.url != url));
+
+    let only = Notifiers::local(&settings).send_test(Some("recorder")).await;
+    assert_eq!(only.len(), 1);
+    assert_eq!(server.requests("/test-hook"), 1);
+}
+
+#[tokio::test]
+async fn watcher_logs_roll_over_in_their_own_files() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/noisy", "<p>Nothing yet</p>");
+    let watcher = watcher(&server.url("/noisy"), &["news"]);
+    let id = watcher.id.clone();
+    let config = Config {
+        watchers: vec![watcher],
+        logging: LogSettings {
+            watcher_files: true,
+            max_file_size: 200,
+            keep_files: 1,
+            ..LogSettings::default()
+        },
+        read_only: true,
+        ..Config::default()
+    };
+    let (log, _) = EventLog::channel(config.timezone);
+    let monitor = Monitor::with_event_log(config, log);
+
+    for _ in 0..5 {
+        check(&monitor, &id).await;
+    }
+    let file = logfiles::dir().unwrap().join(format!("watcher-{}.log", id));
+    let rolled = format!("{}.1", file.display());
+    let current = std::fs::read_to_string(&file).unwrap();
+    assert!(current.len() <= 200);
+    let previous = std::fs::read_to_string(&rolled).unwrap();
+    assert!(previous.contains(&format!("Checking {}", server.url("/noisy"))), "{}", previous);
+    assert!(!std::path::Path::new(&format!("{}.2", file.display())).exists());
+}
+
+#[tokio::test]
+async fn checks_and_notifications_go_to_the_events_file() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/events", "<p>Tickets on sale</p>");
+    let url = server.url("/events");
+    let watcher = watcher(&url, &["on sale"]);
+    let id = watcher.id.clone();
+    let config = Config {
+        watchers: vec![watcher],
+        notifications: NotificationSettings {
+            desktop: false,
+            ..NotificationSettings::default()
+        },
+        logging: LogSettings {
+            events_file: true,
+            ..LogSettings::default()
+        },
+        read_only: true,
+        ..Config::default()
+    };
+    let (log, _) = EventLog::channel(config.timezone);
+    let monitor = Monitor::with_event_log(config, log);
+    check(&monitor, &id).await;
+
+    let file = std::fs::read_to_string(logfiles::dir().unwrap().join("events.jsonl")).unwrap();
+    let events: Vec<serde_json::Value> = file
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .filter(|event: &serde_json::Value| event["url"] == url.as_str())
+        .collect();
+    let kinds: Vec<&str> = events.iter().filter_map(|event| event["event"].as_str()).collect();
+    assert!(kinds.contains(&"check") && kinds.contains(&"match"), "{:?}", kinds);
+    let delivery = events
+        .iter()
+        .find(|event| event["event"] == "notification" && event["channel"] == "recorder")
+        .unwrap();
+    assert_eq!(delivery["delivered"], true);
+    assert_eq!(delivery["keywords"], serde_json::json!(["on sale"]));
+}
+
+#[tokio::test]
+async fn changes_level_keeps_changed_pages_and_drops_checks() {
+    setup();
+    let server = TestServer::start().await;
+    server.serve("/busy", "<p>Version 1</p>");
+    let watcher = watcher(&server.url("/busy"), &["sale"]);
+    let id = watcher.id.clone();
+    let config = Config {
+        watchers: vec![watcher],
+        read_only: true,
+        ..Config::default()
+    };
+    let (log, mut events) = EventLog::channel(config.timezone);
+    let monitor = Monitor::with_event_log(config, log.min_level(Level::parse("changes").unwrap()));
+
+    check(&monitor, &id).await;
+    check(&monitor, &id).await;
+    server.serve("/busy", "<p>Version 2</p>");
+    check(&monitor, &id).await;
+
+    let mut logged = Vec::new();
+    while let Ok(event) = events.try_recv() {
+        logged.push(event);
+    }
+    assert!(logged.iter().all(|event| event.level >= Level::Change));
+    // The first check (nothing cached yet) and the new version
+    let changes = logged.iter().filter(|event| event.message.contains("Changed, no keywords found")).count();
+    assert_eq!(changes, 2);
+}
+
+#[tokio::test]
+async fn text_content_ignores_scripts_and_tokens() {
+    let page = |token: &str, text: &str| {
+        format!(
+            "<html><head><script src=\"/app.{}.js\"></script></head><body class=\"sale-banner\">\
+             <form><input type=\"hidden\" name=\"csrf\" value=\"{}\"></form><p>{}</p>\
+             <script>var promo = \"sale\";</script></body></html>",
+            token, token, text
+        )
+    };
+    let site = Site::watch_with("/shop", &page("a1", "Regular prices"), &["sale"], |watcher| {
+        watcher.content = Some(ContentMode::Text);
+    })
+    .await;
+
+    let report = site.check().await;
+    assert!(report.changed);
+    assert!(report.matched_keywords.is_empty());
+    site.serve(&page("b2", "Regular prices"));
+    assert!(!site.check().await.changed);
+
+    site.serve(&page("c3", "Summer sale: 20% off"));
+    let report = site.check().await;
+    assert_eq!(report.matched_keywords, ["sale"]);
+    assert!(site.alerts()[0].body.contains("Summer sale: 20% off"));
+}
+
+#[test]
+fn match_test_tries_keywords_regexes_and_selectors() {
+    let page = "<html><head><script>var sale = 1;</script></head><body>\
+                <div id=\"product\"><p>Price: $499</p><p>Summer sale</p></div>\
+                <footer>Wholesale terms</footer></body></html>";
+    let mut session = Session::new(page.to_string(), SiteSettings::default()).unwrap();
+    let text = |step: Step| match step {
+        Step::Print(text) => text,
+        Step::Quit => panic!("the session ended"),
+    };
+
+    let report = text(session.handle("sale, restock").unwrap());
+    assert!(report.contains("✓ sale: 3 matches"), "{}", report);
+    assert!(report.contains("✗ restock: not found"));
+
+    text(session.handle(":selector #product").unwrap());
+    text(session.handle(":content text").unwrap());
+    let report = text(session.handle("/price: \\$(?P<amount>\\d{1,3})/, sale").unwrap());
+    assert!(report.contains("1 match\n    Price: $499 Summer sale\n      amount=499"), "{}", report);
+    assert!(report.contains("✓ sale: 1 match"));
+
+    assert!(session.handle("/(/").is_err());
+    assert!(session.handle(":selector #missing").is_err());
+    assert_eq!(session.content(), "Price: $499\nSummer sale");
+    assert_eq!(session.handle(":quit").unwrap(), Step::Quit);
+}
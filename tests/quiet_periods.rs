@@ -0,0 +1,101 @@
+//! Quiet periods from calendars. They hold the alerts of every watcher in
+//! the process, so these tests run apart from the pipeline ones.
+
+#[allow(dead_code)]
+mod common;
+
+use chrono::{Duration, Utc};
+use web_watcher_alert::{
+    calendar::{self, CalendarAction, CalendarSettings},
+    events::EventLog,
+    timezone::Zone,
+};
+
+use common::Site;
+
+/// The quiet period in force, taken by one test at a time
+static QUIET: tokio::sync::Mutex<()> = tokio::sync::Mutex::const_new(());
+
+/// Start a quiet period from an hour ago to an hour from now, from a
+/// calendar file named after `name`
+async fn start_quiet_period(name: &str) -> (std::path::PathBuf, Zone, EventLog) {
+    let now = Utc::now();
+    let ics = format!(
+        "BEGIN:VCALENDAR\r\n\
+         BEGIN:VEVENT\r\n\
+         SUMMARY:Vacation\r\n\
+         DTSTART:{}\r\n\
+         DTEND:{}\r\n\
+         END:VEVENT\r\n\
+         END:VCALENDAR\r\n",
+        (now - Duration::hours(1)).format("%Y%m%dT%H%M%SZ"),
+        (now + Duration::hours(1)).format("%Y%m%dT%H%M%SZ"),
+    );
+    let path = std::env::temp_dir().join(format!("web-watcher-alert-{}-{}.ics", name, std::process::id()));
+    std::fs::write(&path, ics).unwrap();
+    let calendars = [CalendarSettings {
+        url: path.display().to_string(),
+        action: CalendarAction::Quiet,
+        summary: None,
+    }];
+    let zone = Zone::parse("UTC").unwrap();
+    let (log, _) = EventLog::channel(zone);
+    calendar::refresh(&calendars, zone, &log).await;
+    assert!(calendar::current(CalendarAction::Quiet).is_some());
+    (path, zone, log)
+}
+
+#[tokio::test]
+async fn matches_in_a_quiet_period_are_alerted_when_it_ends() {
+    let _quiet = QUIET.lock().await;
+    let site = Site::watch("/shop", "<p>Sold out</p>", &["restock"]).await;
+    site.check().await;
+
+    let (path, zone, log) = start_quiet_period("quiet").await;
+
+    site.serve("<p>Restock on Friday</p>");
+    let report = site.check().await;
+    assert_eq!(report.matched_keywords, ["restock"]);
+    assert!(site.alerts().is_empty());
+
+    // Nothing changes on the page, but the held alert goes out once the
+    // period is over
+    calendar::refresh(&[], zone, &log).await;
+    site.check().await;
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].body.contains("Restock on Friday"), "{}", alerts[0].body);
+
+    site.check().await;
+    assert_eq!(site.alerts().len(), 1);
+    let _ = std::fs::remove_file(path);
+}
+
+#[tokio::test]
+async fn one_shot_watchers_stay_on_until_their_held_alert_is_sent() {
+    let _quiet = QUIET.lock().await;
+    let site = Site::watch_with("/drop", "<p>Sold out</p>", &["restock"], |watcher| {
+        watcher.disable_after_match = true;
+    })
+    .await;
+    site.check().await;
+
+    let (path, zone, log) = start_quiet_period("one-shot").await;
+
+    site.serve("<p>Restock on Friday</p>");
+    let report = site.check().await;
+    assert_eq!(report.matched_keywords, ["restock"]);
+    assert!(!report.disabled);
+    assert!(site.alerts().is_empty());
+
+    calendar::refresh(&[], zone, &log).await;
+    let report = site.check().await;
+    assert!(report.disabled);
+    let alerts = site.alerts();
+    assert_eq!(alerts.len(), 1);
+    assert!(alerts[0].body.contains("Restock on Friday"), "{}", alerts[0].body);
+
+    site.check().await;
+    assert_eq!(site.alerts().len(), 1);
+    let _ = std::fs::remove_file(path);
+}